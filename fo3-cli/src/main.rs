@@ -0,0 +1,356 @@
+//! FO3 CLI
+//!
+//! Command-line tool on top of fo3-wallet for support engineers and CI smoke
+//! tests: generate and check mnemonics, derive addresses, inspect balances,
+//! and craft, sign, broadcast and decode transactions.
+//!
+//! Mnemonics are read from the `FO3_MNEMONIC` environment variable or from
+//! stdin, never from arguments, so they do not end up in shell history.
+
+use std::io::BufRead;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use ethers::prelude::{Address, Eip1559TransactionRequest, TransactionRequest as EthersTransactionRequest, U256};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::rlp::Rlp;
+use ethers_providers::{Http, Middleware, Provider};
+use ethers_signers::{LocalWallet, Signer};
+
+use fo3_wallet::{
+    crypto::{keys::{self, KeyType}, mnemonic::{self, MnemonicStrength}},
+    transaction::provider::{ProviderConfig, ProviderType},
+};
+
+/// Environment variable holding the mnemonic phrase
+const MNEMONIC_ENV: &str = "FO3_MNEMONIC";
+
+#[derive(Debug, Parser)]
+#[command(name = "fo3-cli", version, about = "FO3 wallet operations from the command line")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generate or validate mnemonic phrases
+    #[command(subcommand)]
+    Mnemonic(MnemonicCommand),
+    /// Derive an address from the mnemonic
+    Derive(DeriveArgs),
+    /// Show the balances of an address
+    Balances(BalancesArgs),
+    /// Sign, broadcast and decode transactions
+    #[command(subcommand)]
+    Tx(TxCommand),
+}
+
+#[derive(Debug, Subcommand)]
+enum MnemonicCommand {
+    /// Generate a new mnemonic phrase
+    Generate {
+        /// Number of words (12 or 24)
+        #[arg(long, default_value_t = 12)]
+        words: u8,
+    },
+    /// Check whether the mnemonic phrase is valid
+    Validate,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Chain {
+    Ethereum,
+    Solana,
+    Bitcoin,
+}
+
+impl From<Chain> for KeyType {
+    fn from(chain: Chain) -> Self {
+        match chain {
+            Chain::Ethereum => KeyType::Ethereum,
+            Chain::Solana => KeyType::Solana,
+            Chain::Bitcoin => KeyType::Bitcoin,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct DeriveArgs {
+    /// Chain to derive for
+    #[arg(long, value_enum)]
+    chain: Chain,
+    /// Derivation path (defaults to the chain's first account)
+    #[arg(long)]
+    path: Option<String>,
+    /// BIP-39 passphrase
+    #[arg(long, env = "FO3_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+    /// Use Bitcoin testnet addresses
+    #[arg(long)]
+    testnet: bool,
+}
+
+#[derive(Debug, Args)]
+struct BalancesArgs {
+    /// Chain of the address
+    #[arg(long, value_enum)]
+    chain: Chain,
+    /// Address to inspect
+    address: String,
+    /// RPC endpoint
+    #[arg(long, env = "FO3_RPC_URL")]
+    rpc_url: String,
+}
+
+#[derive(Debug, Subcommand)]
+enum TxCommand {
+    /// Build and sign an EVM transaction offline, printing the raw transaction
+    SignEvm(SignEvmArgs),
+    /// Broadcast a raw signed EVM transaction
+    Broadcast {
+        /// 0x-prefixed raw signed transaction
+        raw: String,
+        /// RPC endpoint
+        #[arg(long, env = "FO3_RPC_URL")]
+        rpc_url: String,
+    },
+    /// Decode a raw signed EVM transaction
+    Decode {
+        /// 0x-prefixed raw signed transaction
+        raw: String,
+    },
+}
+
+#[derive(Debug, Args)]
+struct SignEvmArgs {
+    /// Chain ID
+    #[arg(long)]
+    chain_id: u64,
+    /// Nonce
+    #[arg(long)]
+    nonce: u64,
+    /// Recipient address
+    #[arg(long)]
+    to: String,
+    /// Value in wei
+    #[arg(long, default_value = "0")]
+    value: String,
+    /// Gas limit
+    #[arg(long, default_value_t = 21_000)]
+    gas_limit: u64,
+    /// Gas price in wei (max fee per gas with --priority-fee)
+    #[arg(long)]
+    gas_price: String,
+    /// Max priority fee per gas in wei; builds an EIP-1559 transaction
+    #[arg(long)]
+    priority_fee: Option<String>,
+    /// Hex-encoded call data
+    #[arg(long)]
+    data: Option<String>,
+    /// Derivation path
+    #[arg(long, default_value = "m/44'/60'/0'/0/0")]
+    path: String,
+    /// BIP-39 passphrase
+    #[arg(long, env = "FO3_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+}
+
+/// Read the mnemonic from the environment, or from the first line of stdin
+fn read_mnemonic() -> anyhow::Result<String> {
+    if let Ok(phrase) = std::env::var(MNEMONIC_ENV) {
+        return Ok(phrase.trim().to_string());
+    }
+
+    eprintln!("Reading mnemonic from stdin (set {} to skip)", MNEMONIC_ENV);
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line).context("failed to read mnemonic")?;
+    Ok(line.trim().to_string())
+}
+
+fn default_path(chain: Chain) -> &'static str {
+    match chain {
+        Chain::Ethereum => "m/44'/60'/0'/0/0",
+        Chain::Solana => "m/44'/501'/0'/0'",
+        Chain::Bitcoin => "m/44'/0'/0'/0/0",
+    }
+}
+
+fn parse_u256(field: &str, value: &str) -> anyhow::Result<U256> {
+    U256::from_dec_str(value).map_err(|e| anyhow!("invalid {}: {}", field, e))
+}
+
+fn parse_hex(value: &str) -> anyhow::Result<Vec<u8>> {
+    hex::decode(value.trim().trim_start_matches("0x")).context("invalid hex")
+}
+
+fn provider_config(rpc_url: &str) -> ProviderConfig {
+    ProviderConfig {
+        provider_type: ProviderType::Http,
+        url: rpc_url.to_string(),
+        api_key: None,
+        timeout: Some(30),
+    }
+}
+
+fn mnemonic_command(command: MnemonicCommand) -> anyhow::Result<()> {
+    match command {
+        MnemonicCommand::Generate { words } => {
+            let strength = match words {
+                12 => MnemonicStrength::Words12,
+                24 => MnemonicStrength::Words24,
+                _ => bail!("--words must be 12 or 24"),
+            };
+            println!("{}", mnemonic::generate_mnemonic(strength)?);
+        }
+        MnemonicCommand::Validate => {
+            if !mnemonic::validate_mnemonic(&read_mnemonic()?)? {
+                bail!("invalid mnemonic");
+            }
+            println!("valid");
+        }
+    }
+
+    Ok(())
+}
+
+fn derive(args: DeriveArgs) -> anyhow::Result<()> {
+    let phrase = read_mnemonic()?;
+    let seed = mnemonic::mnemonic_to_seed(&phrase, args.passphrase.as_deref())?;
+    let path = args.path.as_deref().unwrap_or(default_path(args.chain));
+    let key_pair = keys::derive_key_pair(&seed, args.chain.into(), path)?;
+
+    let address = match args.chain {
+        Chain::Ethereum => keys::ethereum::public_key_to_address(key_pair.public_key())?,
+        Chain::Solana => keys::solana::public_key_to_address(key_pair.public_key())?,
+        Chain::Bitcoin => {
+            let network = if args.testnet { keys::bitcoin::Network::Testnet } else { keys::bitcoin::Network::Bitcoin };
+            keys::bitcoin::public_key_to_address(key_pair.public_key(), network)?
+        }
+    };
+
+    println!("{}", serde_json::to_string_pretty(&serde_json::json!({
+        "chain": KeyType::from(args.chain),
+        "path": path,
+        "public_key": hex::encode(key_pair.public_key().as_bytes()),
+        "address": address,
+    }))?);
+
+    Ok(())
+}
+
+async fn balances(args: BalancesArgs) -> anyhow::Result<()> {
+    let mut output = serde_json::json!({ "address": args.address });
+
+    if let Chain::Ethereum = args.chain {
+        let provider = Provider::<Http>::try_from(args.rpc_url.as_str()).context("invalid RPC URL")?;
+        let address = Address::from_str(&args.address).context("invalid address")?;
+        let balance = provider.get_balance(address, None).await.context("failed to fetch native balance")?;
+        output["native"] = serde_json::json!(balance.to_string());
+    }
+
+    match fo3_wallet::defi::get_token_balances(args.chain.into(), &args.address, &provider_config(&args.rpc_url)) {
+        Ok(tokens) => output["tokens"] = serde_json::to_value(tokens)?,
+        Err(e) => eprintln!("token balances unavailable: {}", e),
+    }
+
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+fn sign_evm(args: SignEvmArgs) -> anyhow::Result<()> {
+    let phrase = read_mnemonic()?;
+    let seed = mnemonic::mnemonic_to_seed(&phrase, args.passphrase.as_deref())?;
+    let key_pair = keys::derive_key_pair(&seed, KeyType::Ethereum, &args.path)?;
+    let wallet = LocalWallet::from_bytes(key_pair.private_key().as_bytes())?.with_chain_id(args.chain_id);
+
+    let to = Address::from_str(&args.to).context("invalid recipient address")?;
+    let value = parse_u256("value", &args.value)?;
+    let gas_price = parse_u256("gas price", &args.gas_price)?;
+    let data = args.data.as_deref().map(parse_hex).transpose()?.unwrap_or_default();
+
+    let tx: TypedTransaction = match &args.priority_fee {
+        Some(priority_fee) => Eip1559TransactionRequest::new()
+            .to(to)
+            .value(value)
+            .gas(args.gas_limit)
+            .max_fee_per_gas(gas_price)
+            .max_priority_fee_per_gas(parse_u256("priority fee", priority_fee)?)
+            .nonce(args.nonce)
+            .data(data)
+            .chain_id(args.chain_id)
+            .into(),
+        None => EthersTransactionRequest::new()
+            .to(to)
+            .value(value)
+            .gas(args.gas_limit)
+            .gas_price(gas_price)
+            .nonce(args.nonce)
+            .data(data)
+            .chain_id(args.chain_id)
+            .into(),
+    };
+
+    let signature = wallet.sign_transaction_sync(&tx)?;
+    println!("0x{}", hex::encode(tx.rlp_signed(&signature)));
+    Ok(())
+}
+
+async fn broadcast(raw: &str, rpc_url: &str) -> anyhow::Result<()> {
+    let provider = Provider::<Http>::try_from(rpc_url).context("invalid RPC URL")?;
+    let pending = provider.send_raw_transaction(parse_hex(raw)?.into()).await.context("broadcast failed")?;
+    println!("{:?}", pending.tx_hash());
+    Ok(())
+}
+
+fn decode(raw: &str) -> anyhow::Result<()> {
+    let bytes = parse_hex(raw)?;
+    let (tx, signature) = TypedTransaction::decode_signed(&Rlp::new(&bytes))
+        .map_err(|e| anyhow!("invalid signed transaction: {}", e))?;
+    let from = signature.recover(tx.sighash()).context("failed to recover sender")?;
+
+    println!("{}", serde_json::to_string_pretty(&serde_json::json!({
+        "hash": format!("{:?}", tx.hash(&signature)),
+        "from": format!("{:?}", from),
+        "transaction": tx,
+        "signature": signature.to_string(),
+    }))?);
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    match Cli::parse().command {
+        Command::Mnemonic(command) => mnemonic_command(command),
+        Command::Derive(args) => derive(args),
+        Command::Balances(args) => balances(args).await,
+        Command::Tx(TxCommand::SignEvm(args)) => sign_evm(args),
+        Command::Tx(TxCommand::Broadcast { raw, rpc_url }) => broadcast(&raw, &rpc_url).await,
+        Command::Tx(TxCommand::Decode { raw }) => decode(&raw),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_decode_golden_transfer() {
+        let raw = include_str!("../../fo3-wallet/tests/golden/ethereum_signed_transfer.hex");
+        let bytes = parse_hex(raw).unwrap();
+        let (tx, signature) = TypedTransaction::decode_signed(&Rlp::new(&bytes)).unwrap();
+
+        // Signed by the first account of the standard test mnemonic
+        let from = signature.recover(tx.sighash()).unwrap();
+        assert_eq!(from, Address::from_str("0x9858EfFD232B4033E47d90003D41EC34EcaEda94").unwrap());
+        assert_eq!(tx.value(), Some(&U256::exp10(18)));
+    }
+}
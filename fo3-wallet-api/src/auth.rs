@@ -0,0 +1,108 @@
+//! Caller authentication
+//!
+//! Operators authenticate with an API key sent as `Authorization: Bearer
+//! <key>`. Keys are configured in the `api_keys` secret as a JSON object
+//! mapping the hex SHA-256 hash of each key to the principal it stands for,
+//! so the keys themselves are never stored:
+//!
+//! ```json
+//! { "9f86d081…": { "actor": "ops@example.com", "roles": ["compliance", "auditor"] } }
+//! ```
+//!
+//! Without the secret no key authenticates, and endpoints that need a role
+//! refuse every request.
+
+use std::collections::HashMap;
+
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+
+use fo3_wallet::error::{Error, Result};
+
+/// What an authenticated caller may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Everything below
+    Admin,
+    /// Work the compliance review queue
+    Compliance,
+    /// Export and verify the audit log
+    Auditor,
+    /// Report incoming payments seen on chain
+    Watcher,
+}
+
+/// An authenticated caller
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Principal {
+    /// Name recorded as the actor of audited actions
+    pub actor: String,
+    /// Roles granted to the key
+    #[serde(default)]
+    pub roles: Vec<Role>,
+}
+
+impl Principal {
+    /// Whether the caller has a role (admins have every role)
+    pub fn has_role(&self, role: Role) -> bool {
+        self.roles.iter().any(|granted| *granted == role || *granted == Role::Admin)
+    }
+}
+
+/// Configured API keys, by hash
+#[derive(Debug, Default)]
+pub struct ApiKeys {
+    principals: HashMap<String, Principal>,
+}
+
+impl ApiKeys {
+    /// Load keys from a JSON object of key hashes and principals
+    pub fn from_json(json: &str) -> Result<Self> {
+        let principals: HashMap<String, Principal> = serde_json::from_str(json)
+            .map_err(|e| Error::InvalidInput(format!("Invalid API keys: {}", e)))?;
+
+        let principals = principals.into_iter()
+            .map(|(hash, principal)| {
+                let hash = hash.to_ascii_lowercase();
+                if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(Error::InvalidInput(format!("API key of {} must be given as a hex SHA-256 hash", principal.actor)));
+                }
+                Ok((hash, principal))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { principals })
+    }
+
+    /// Get the principal an API key stands for
+    ///
+    /// Keys are looked up by hash, so lookup time says nothing about how
+    /// close a guess came to a real key.
+    pub fn authenticate(&self, key: &str) -> Option<&Principal> {
+        self.principals.get(&hex::encode(Sha256::digest(key.as_bytes())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authenticate() {
+        let hash = hex::encode(Sha256::digest(b"secret-key"));
+        let keys = ApiKeys::from_json(&format!(r#"{{ "{}": {{ "actor": "ops", "roles": ["compliance"] }} }}"#, hash.to_uppercase())).unwrap();
+
+        let principal = keys.authenticate("secret-key").unwrap();
+        assert_eq!(principal.actor, "ops");
+        assert!(principal.has_role(Role::Compliance));
+        assert!(!principal.has_role(Role::Auditor));
+        assert!(keys.authenticate("other-key").is_none());
+
+        let admin = Principal { actor: "root".to_string(), roles: vec![Role::Admin] };
+        assert!(admin.has_role(Role::Auditor));
+
+        assert!(ApiKeys::from_json(r#"{ "secret-key": { "actor": "ops" } }"#).is_err());
+    }
+}
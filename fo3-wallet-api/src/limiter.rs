@@ -0,0 +1,353 @@
+//! Adaptive concurrency limits and load shedding
+//!
+//! Each service (the first segment of a request path, e.g. `transactions`)
+//! has its own [`AdaptiveLimiter`], so a flood of balance lookups cannot use
+//! up the capacity of the signing path. A limiter caps the requests in flight
+//! and adjusts the cap from observed latency, gradient style: while latency
+//! stays near its long-run average the limit grows by roughly its square
+//! root; when latency climbs, usually because a chain node or exchange is
+//! struggling, the limit shrinks in proportion. Requests over the limit are
+//! shed straight away instead of queueing behind slow ones.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use serde::{Serialize, Deserialize};
+
+use fo3_wallet::error::{Error, FieldViolation, Result};
+
+/// Limits of one service
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimiterConfig {
+    /// Concurrency limit before any latency has been observed
+    pub initial_limit: usize,
+    /// The limit never drops below this
+    pub min_limit: usize,
+    /// The limit never grows above this
+    pub max_limit: usize,
+    /// How far latency may rise above its long-run average before the limit shrinks
+    pub tolerance: f64,
+    /// Weight of each new estimate in the limit (0.0 - 1.0)
+    pub smoothing: f64,
+    /// Number of samples the long-run latency average spans
+    pub window: u32,
+}
+
+impl Default for LimiterConfig {
+    fn default() -> Self {
+        Self { initial_limit: 20, min_limit: 4, max_limit: 200, tolerance: 1.5, smoothing: 0.2, window: 600 }
+    }
+}
+
+impl LimiterConfig {
+    /// Check that the limits are consistent
+    pub fn validate(&self, field: &str) -> Result<()> {
+        let mut violations = Vec::new();
+        if self.min_limit == 0 || self.min_limit > self.max_limit {
+            violations.push(FieldViolation::new(&format!("{}.min_limit", field), "must be between 1 and max_limit"));
+        }
+        if !(self.min_limit..=self.max_limit).contains(&self.initial_limit) {
+            violations.push(FieldViolation::new(&format!("{}.initial_limit", field), "must be between min_limit and max_limit"));
+        }
+        if self.tolerance.is_nan() || self.tolerance < 1.0 {
+            violations.push(FieldViolation::new(&format!("{}.tolerance", field), "must be at least 1.0"));
+        }
+        if self.smoothing.is_nan() || self.smoothing <= 0.0 || self.smoothing > 1.0 {
+            violations.push(FieldViolation::new(&format!("{}.smoothing", field), "must be above 0.0 and at most 1.0"));
+        }
+        if self.window == 0 {
+            violations.push(FieldViolation::new(&format!("{}.window", field), "must be at least 1"));
+        }
+        if violations.is_empty() { Ok(()) } else { Err(Error::Validation(violations)) }
+    }
+}
+
+/// Counters of one service's limiter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LimiterStats {
+    /// Service name
+    pub service: String,
+    /// Current concurrency limit
+    pub limit: usize,
+    /// Requests being handled
+    pub in_flight: usize,
+    /// Requests admitted since startup
+    pub admitted: u64,
+    /// Requests shed since startup
+    pub shed: u64,
+    /// Long-run average latency in milliseconds, once requests have completed
+    pub average_latency_ms: Option<f64>,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    limit: f64,
+    in_flight: usize,
+    admitted: u64,
+    shed: u64,
+    long_rtt: Option<f64>,
+}
+
+/// Concurrency limiter that adapts its limit to latency
+#[derive(Debug)]
+pub struct AdaptiveLimiter {
+    config: LimiterConfig,
+    state: Mutex<LimiterState>,
+}
+
+impl AdaptiveLimiter {
+    /// Create a limiter starting at the configured initial limit
+    pub fn new(config: LimiterConfig) -> Self {
+        let state = LimiterState { limit: config.initial_limit as f64, in_flight: 0, admitted: 0, shed: 0, long_rtt: None };
+        Self { config, state: Mutex::new(state) }
+    }
+
+    /// Admit a request, or `None` if the service is at its limit
+    ///
+    /// Dropping the permit records the request's latency.
+    pub fn try_acquire(self: &Arc<Self>) -> Option<Permit> {
+        let mut state = self.state.lock().unwrap();
+        if state.in_flight >= state.limit as usize {
+            state.shed += 1;
+            return None;
+        }
+        state.in_flight += 1;
+        state.admitted += 1;
+        Some(Permit { limiter: self.clone(), started: Instant::now() })
+    }
+
+    /// Get the current limit
+    pub fn limit(&self) -> usize {
+        self.state.lock().unwrap().limit as usize
+    }
+
+    fn release(&self, latency: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let in_flight = state.in_flight;
+        state.in_flight -= 1;
+
+        // Sub-millisecond differences are noise, not a sign of overload
+        let rtt = latency.as_secs_f64().max(0.001);
+        let long_rtt = match state.long_rtt {
+            None => rtt,
+            Some(average) => {
+                let average = average + (rtt - average) / self.config.window as f64;
+                // Let the average recover quickly after an incident so the
+                // limit does not stay high against a latency baseline that
+                // is no longer true
+                if average > 2.0 * rtt { average * 0.95 } else { average }
+            }
+        };
+        state.long_rtt = Some(long_rtt);
+
+        // A service using under half its limit tells nothing about whether
+        // a higher one would hold, so only latency increases move it
+        let gradient = (self.config.tolerance * long_rtt / rtt).clamp(0.5, 1.0);
+        if gradient >= 1.0 && (in_flight as f64) < state.limit / 2.0 {
+            return;
+        }
+        let estimate = state.limit * gradient + state.limit.sqrt();
+        let limit = state.limit * (1.0 - self.config.smoothing) + estimate * self.config.smoothing;
+        state.limit = limit.clamp(self.config.min_limit as f64, self.config.max_limit as f64);
+    }
+
+    fn stats(&self, service: &str) -> LimiterStats {
+        let state = self.state.lock().unwrap();
+        LimiterStats {
+            service: service.to_string(),
+            limit: state.limit as usize,
+            in_flight: state.in_flight,
+            admitted: state.admitted,
+            shed: state.shed,
+            average_latency_ms: state.long_rtt.map(|rtt| rtt * 1000.0),
+        }
+    }
+}
+
+/// An admitted request; dropping it frees the slot and records the latency
+#[derive(Debug)]
+pub struct Permit {
+    limiter: Arc<AdaptiveLimiter>,
+    started: Instant,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.limiter.release(self.started.elapsed());
+    }
+}
+
+/// Per-service limiters, created on a service's first request
+#[derive(Debug, Default)]
+pub struct LoadShedder {
+    /// Limits by service, where `default` applies to the rest
+    limits: RwLock<HashMap<String, LimiterConfig>>,
+    limiters: RwLock<HashMap<String, Arc<AdaptiveLimiter>>>,
+}
+
+impl LoadShedder {
+    /// Create a load shedder with per-service limits, where `default` applies to the rest
+    pub fn new(limits: HashMap<String, LimiterConfig>) -> Result<Self> {
+        validate(&limits)?;
+        Ok(Self { limits: RwLock::new(limits), limiters: RwLock::new(HashMap::new()) })
+    }
+
+    /// Replace the limits
+    ///
+    /// Services whose limits changed get a fresh limiter, and their counters
+    /// start over; requests in flight finish against the old one.
+    pub fn reconfigure(&self, limits: HashMap<String, LimiterConfig>) -> Result<()> {
+        validate(&limits)?;
+        let mut current = self.limits.write().unwrap();
+        *current = limits;
+        self.limiters.write().unwrap().retain(|service, limiter| limiter.config == config_for(&current, service));
+        Ok(())
+    }
+
+    /// Admit a request to `service`, or `None` if it should be shed
+    pub fn try_acquire(&self, service: &str) -> Option<Permit> {
+        if let Some(limiter) = self.limiters.read().unwrap().get(service) {
+            return limiter.try_acquire();
+        }
+        let config = config_for(&self.limits.read().unwrap(), service);
+        let limiter = self.limiters.write().unwrap()
+            .entry(service.to_string())
+            .or_insert_with(|| Arc::new(AdaptiveLimiter::new(config)))
+            .clone();
+        limiter.try_acquire()
+    }
+
+    /// Get the counters of every service that has had a request, by name
+    pub fn stats(&self) -> Vec<LimiterStats> {
+        let mut stats: Vec<_> = self.limiters.read().unwrap().iter()
+            .map(|(service, limiter)| limiter.stats(service))
+            .collect();
+        stats.sort_by(|a, b| a.service.cmp(&b.service));
+        stats
+    }
+}
+
+fn validate(limits: &HashMap<String, LimiterConfig>) -> Result<()> {
+    for (service, config) in limits {
+        config.validate(service)?;
+    }
+    Ok(())
+}
+
+fn config_for(limits: &HashMap<String, LimiterConfig>, service: &str) -> LimiterConfig {
+    limits.get(service).or_else(|| limits.get("default")).cloned().unwrap_or_default()
+}
+
+/// Get the service a request path belongs to: its first segment
+pub fn service_for(path: &str) -> &str {
+    path.trim_start_matches('/').split('/').next().unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(json: &str) -> HashMap<String, LimiterConfig> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn limiter(initial_limit: usize) -> Arc<AdaptiveLimiter> {
+        Arc::new(AdaptiveLimiter::new(LimiterConfig { initial_limit, min_limit: 2, max_limit: 50, ..Default::default() }))
+    }
+
+    #[test]
+    fn test_sheds_over_limit() {
+        let limiter = limiter(2);
+        let first = limiter.try_acquire().unwrap();
+        let _second = limiter.try_acquire().unwrap();
+        assert!(limiter.try_acquire().is_none());
+
+        drop(first);
+        assert!(limiter.try_acquire().is_some());
+
+        let stats = limiter.stats("transactions");
+        assert_eq!((stats.admitted, stats.shed), (3, 1));
+    }
+
+    #[test]
+    fn test_limit_follows_latency() {
+        let limiter = limiter(10);
+
+        // Steady latency at full use grows the limit
+        for _ in 0..20 {
+            let permits: Vec<_> = (0..limiter.limit()).map_while(|_| limiter.try_acquire()).collect();
+            for permit in permits {
+                std::mem::forget(permit);
+                limiter.release(Duration::from_millis(10));
+            }
+        }
+        let grown = limiter.limit();
+        assert!(grown > 10);
+
+        // Latency spiking well past the average shrinks it
+        for _ in 0..20 {
+            let permit = limiter.try_acquire().unwrap();
+            std::mem::forget(permit);
+            limiter.release(Duration::from_millis(200));
+        }
+        assert!(limiter.limit() < grown);
+        assert!(limiter.limit() >= 2);
+    }
+
+    #[test]
+    fn test_idle_service_keeps_limit() {
+        let limiter = limiter(10);
+        for _ in 0..20 {
+            std::mem::forget(limiter.try_acquire().unwrap());
+            limiter.release(Duration::from_millis(10));
+        }
+        assert_eq!(limiter.limit(), 10);
+    }
+
+    #[test]
+    fn test_per_service_limits() {
+        let shedder = LoadShedder::new(limits(r#"{
+            "default": { "initial_limit": 4, "min_limit": 1, "max_limit": 10 },
+            "transactions": { "initial_limit": 1, "min_limit": 1, "max_limit": 5 }
+        }"#)).unwrap();
+
+        let _permit = shedder.try_acquire("transactions").unwrap();
+        assert!(shedder.try_acquire("transactions").is_none());
+        assert!(shedder.try_acquire("wallets").is_some());
+
+        let stats = shedder.stats();
+        assert_eq!(stats.iter().map(|s| s.service.as_str()).collect::<Vec<_>>(), ["transactions", "wallets"]);
+        assert_eq!(stats[0].shed, 1);
+
+        assert!(LoadShedder::new(limits(r#"{ "default": { "min_limit": 0 } }"#)).is_err());
+        assert!(LoadShedder::new(limits(r#"{ "wallets": { "initial_limit": 500 } }"#)).is_err());
+    }
+
+    #[test]
+    fn test_reconfigure() {
+        let shedder = LoadShedder::new(limits(r#"{ "transactions": { "initial_limit": 1, "min_limit": 1 } }"#)).unwrap();
+        let permit = shedder.try_acquire("transactions").unwrap();
+        let _wallets = shedder.try_acquire("wallets").unwrap();
+        assert!(shedder.try_acquire("transactions").is_none());
+
+        shedder.reconfigure(limits(r#"{ "transactions": { "initial_limit": 2, "min_limit": 1 } }"#)).unwrap();
+        assert!(shedder.try_acquire("transactions").is_some());
+        drop(permit);
+
+        // Unchanged services keep their limiter and counters
+        let stats = shedder.stats();
+        assert_eq!((stats[0].service.as_str(), stats[0].admitted), ("transactions", 1));
+        assert_eq!((stats[1].service.as_str(), stats[1].admitted), ("wallets", 1));
+
+        assert!(shedder.reconfigure(limits(r#"{ "default": { "window": 0 } }"#)).is_err());
+    }
+
+    #[test]
+    fn test_service_for() {
+        assert_eq!(service_for("/transactions/0xabc"), "transactions");
+        assert_eq!(service_for("/health"), "health");
+        assert_eq!(service_for("/"), "");
+    }
+}
@@ -2,47 +2,289 @@
 //!
 //! This is the REST API server for the FO3 multi-chain wallet and DeFi SDK.
 
+// The OpenAPI schema table is one large `json!` literal
+#![recursion_limit = "512"]
+
+mod auth;
+mod config;
+mod limiter;
+mod openapi;
+
+use std::cmp::Reverse;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
-    extract::{Extension, Json, Path},
-    http::StatusCode,
+    async_trait,
+    extract::{Extension, FromRequestParts, Json, Path, Query},
+    http::{HeaderMap, StatusCode, header, request::Parts},
+    response::sse::{Event, KeepAlive, Sse},
 };
 use serde::{Serialize, Deserialize};
+use tracing::Instrument;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+use auth::{ApiKeys, Principal, Role};
+use config::{ApiConfig, ConfigHandle};
+use limiter::{LimiterStats, LoadShedder};
+
 use fo3_wallet::{
-    account::Wallet,
-    crypto::keys::KeyType,
-    transaction::{TransactionRequest, TransactionStatus, provider::{ProviderConfig, ProviderType, ProviderFactory}},
-    defi::{Token, SwapRequest, LendingRequest, StakingRequest},
+    address::validate_address,
+    caip::AssetId,
+    audit::{AuditEvent, AuditLog, AuditStore, AuditVerification, FileAuditStore, InMemoryAuditStore},
+    account::{Wallet, backup::{self, BackupBundle, BackupMetadata}, deposit::{DepositAddress, DepositAddresses, DepositRequest, DepositStore, InMemoryDepositStore}, metadata::{self as wallet_metadata, FileMetadataRepository, InMemoryMetadataRepository, MetadataRepository, WalletMetadata}, export::{self as activity_export, ActivityEvent, ActivityJournal, ConvertedPrices, ExportFormat, ExportJob, ExportJobStatus, ExportRequest, ExportService, PriceSource}},
+    crypto::{keys::KeyType, sharding::{EscrowReceipt, FileShareStore, InMemoryShareStore, PartialSignature, Recovery, ServerShares, ShareEscrow, ShareStore, SigningNonce}},
+    transaction::{
+        Transaction, TransactionRequest, TransactionStatus, SolanaProvider, BitcoinProvider,
+        dust::{self, ConsolidationPolicy, TokenAccountCleanup, UtxoConsolidation},
+        intents::{self, Batch, BatchRequest},
+        fee_payer::{FeePayer, FeePayerPolicy, FeePayerUsage, SponsoredTransaction},
+        lightning::{self, Invoice, LightningBackend, LightningPayment, PaymentDirection},
+        XrpBalance, XrpProvider, CosmosProvider,
+        validators::{ValidatorAlert, ValidatorMonitor, ValidatorNotifier},
+        Instruction,
+        fee_budget::{FeeBudget, FeeBudgets},
+        schedule::{FileScheduleStore, InMemoryScheduleStore, OrderExecutor, OrderKind, RunRecord, Schedule, ScheduleNotification, ScheduleNotifier, SchedulePolicy, ScheduleStore, ScheduledOrder, Scheduler},
+        mev::{MevProtection, MevProtections, RpcRelay, SubmissionMode, SubmissionRoute},
+        compliance::{ComplianceScreener, CompliancePolicy, CompositeScreener, ChainalysisScreener, InMemoryScreeningAudit, LocalListScreener, ScreeningAction, ScreeningProvider, ScreeningRecord},
+        travel_rule::{self, Beneficiary, Originator, Person, TransferState, TravelRuleAudit, TravelRuleEvent, TravelRuleGate, TravelRulePolicy, TravelRuleTransfer, TrpMessenger, Vasp, Withdrawal},
+        provider::{ProviderConfig, ProviderType, ProviderFactory},
+    },
+    defi::{ApprovalOptions, ApprovalStrategy, Token, TokenAmount, SwapQuote, SwapQuoteBook, SwapRequest, LendingRequest, StakingRequest, PlatformFeeConfig, FeeLedger, InMemoryFeeLedger, FileFeeLedger, AdjustedBalance, PendingBalances, PortfolioFilter, SpamClassifier, SpamOverride, TokenOverride, Protocol, ProtocolRiskRegistry, ProtocolRiskProfile, ExploitReport, RiskAssessment, RiskEvent, RiskPolicy, DefiLlamaRiskFeed, LendingAction, StakingAction, EarnPosition, EmergencyExits, ExitReport, PipelineExitExecutor, TrackPositionRequest, AutoCompounder, CompoundExecutor, CompoundPolicy, CompoundPosition, CompoundRequest, CompoundRun, EarnAnalytics, PipelineCompoundExecutor, StakingResult, compounding_key_type},
+    validation::Validate,
+    pagination::{Page, PageRequest, paginate, paginate_source},
+    invoice::{CreatePaymentRequest, PaymentRequest, PaymentRequestStatus, PaymentRequests, ReceivedPayment},
+    pricing::{BackfillReport, BinanceCandleSource, Candle, CandleService, DisplayCurrencies, EcbRateSource, FiatAmount, FiatRateService, FiatRates, InMemoryCandleStore, Interval, OpenExchangeRatesSource, PriceHistoryRequest},
+    secrets::{CachedSecrets, EnvSecrets, FileSecrets, Secret, SecretChain, SecretProvider, VaultSecrets},
+    exchange::{AggregatedBalance, ConnectionError, ExchangeConnection, ExchangeConnections, ExchangeCredentials, ExchangeKind, ExchangeTrade, InMemoryConnectionStore},
     error::{Error as WalletError},
+    time::unix_timestamp,
 };
 
 // Application state
 struct AppState {
     // In a real application, this would be a database
     wallets: std::sync::RwLock<std::collections::HashMap<String, Wallet>>,
-    // Provider configuration
-    provider_config: ProviderConfig,
+    // User-defined wallet colors, icons, archived flags and account notes
+    wallet_metadata: Box<dyn MetadataRepository>,
+    // Deposit addresses assigned to invoices and customers
+    deposits: DepositAddresses<InMemoryDepositStore>,
+    // Payment requests paid into deposit addresses
+    payment_requests: PaymentRequests,
+    // Payment request changes, for status streams
+    payment_updates: tokio::sync::broadcast::Sender<PaymentRequest>,
+    // Escrowed server key shares, keyed by share ID
+    key_shares: ShareEscrow,
+    // Activity export jobs
+    exports: ExportService,
+    // Swaps and staking rewards executed here, merged into exports
+    activity: ActivityJournal,
+    // Collected platform fees
+    fee_ledger: Box<dyn FeeLedger>,
+    // Swap quotes issued to clients, executed by ID
+    swap_quotes: SwapQuoteBook,
+    // Counterparty screening, if configured
+    screener: Option<ComplianceScreener>,
+    // Screening decisions
+    screening_audit: Arc<InMemoryScreeningAudit>,
+    // Per-user limits on transaction fees
+    fee_budgets: FeeBudgets,
+    // Users' recurring transfers and DCA swaps
+    scheduler: Scheduler,
+    // Per-wallet private relay submission of EVM transactions
+    mev_protections: MevProtections,
+    // Balance changes of broadcast transactions awaiting confirmation
+    pending_balances: PendingBalances,
+    // Hash-chained audit log of state-changing requests
+    audit_log: Arc<AuditLog>,
+    // Spam token classification and per-user overrides
+    spam_classifier: SpamClassifier,
+    // Service account sponsoring Solana fees, if configured
+    fee_payer: Option<FeePayer>,
+    // Lightning node, if configured
+    lightning: Option<LightningNode>,
+    // Last seen state of the validators users delegate to
+    validator_monitor: ValidatorMonitor,
+    // Historical OHLCV candles
+    candles: Arc<CandleService<InMemoryCandleStore>>,
+    // Daily exchange rates from USD to display currencies
+    fiat_rates: FiatRateService,
+    // Per-user display currency
+    display_currencies: DisplayCurrencies,
+    // Users' linked exchange accounts, with encrypted API keys
+    exchange_connections: ExchangeConnections<InMemoryConnectionStore>,
+    // Risk metadata and deposit pauses of DeFi protocols
+    protocol_risk: ProtocolRiskRegistry,
+    // Tracked DeFi positions and their emergency exits
+    emergency_exits: EmergencyExits,
+    // Staking positions whose rewards are restaked on a schedule
+    compounder: AutoCompounder,
+    // Reloadable endpoints and fees
+    config: ConfigHandle,
+    // Secrets, for API keys looked up per use
+    secrets: CachedSecrets<SecretChain>,
+    // Per-service adaptive concurrency limits
+    load_shedder: LoadShedder,
+    // Operator API keys
+    api_keys: ApiKeys,
+    // Travel rule exchange for withdrawals, if the deployment is a VASP
+    travel_rule: Option<TravelRuleGate>,
+    // RPC endpoint, without its API key (see `provider_config`)
+    rpc_config: ProviderConfig,
 }
 
 impl AppState {
-    fn new() -> Self {
-        let provider_config = ProviderConfig {
+    fn new() -> fo3_wallet::error::Result<Self> {
+        let secrets = secrets_from_env();
+        let config = ConfigHandle::from_env()?;
+        let current = config.current();
+
+        let rpc_config = ProviderConfig {
             provider_type: ProviderType::Http,
             url: "https://mainnet.infura.io/v3/your-api-key".to_string(),
             api_key: None,
             timeout: Some(30),
         };
 
-        Self {
+        let screening_audit = Arc::new(InMemoryScreeningAudit::new());
+        let api_keys = api_keys_from_secrets(&secrets);
+        let audit_log = Arc::new(audit_log_from_env(&secrets));
+        let candles = Arc::new(candles_from_env());
+
+        Ok(Self {
             wallets: std::sync::RwLock::new(std::collections::HashMap::new()),
-            provider_config,
+            wallet_metadata: wallet_metadata_from_env(),
+            deposits: DepositAddresses::new(InMemoryDepositStore::new()),
+            payment_requests: PaymentRequests::new(),
+            payment_updates: tokio::sync::broadcast::channel(256).0,
+            key_shares: key_shares_from_env(&secrets),
+            exports: ExportService::new(),
+            activity: ActivityJournal::new(),
+            fee_ledger: fee_ledger_from_env(),
+            swap_quotes: SwapQuoteBook::new(),
+            screener: screener_from_env(&secrets, &screening_audit),
+            screening_audit,
+            fee_budgets: FeeBudgets::new(current.fee_budget()),
+            scheduler: scheduler_from_env()?,
+            mev_protections: MevProtections::new(mev_protection_from_env()),
+            pending_balances: PendingBalances::new(),
+            travel_rule: travel_rule_from_env(&candles, &audit_log)?,
+            audit_log,
+            spam_classifier: spam_classifier_from_env(),
+            fee_payer: fee_payer_from_secrets(&secrets),
+            lightning: lightning_from_env(&secrets),
+            validator_monitor: ValidatorMonitor::default(),
+            candles,
+            fiat_rates: fiat_rates_from_env(&secrets),
+            display_currencies: DisplayCurrencies::new(),
+            exchange_connections: exchange_connections_from_secrets(&secrets),
+            protocol_risk: protocol_risk_from_env(),
+            emergency_exits: EmergencyExits::new(),
+            compounder: AutoCompounder::new(CompoundPolicy::default()),
+            config,
+            secrets,
+            load_shedder: LoadShedder::new(current.concurrency_limits.clone())?,
+            api_keys,
+            rpc_config,
+        })
+    }
+
+    /// Authenticate the caller's API key and check it grants `role`
+    fn authorize(&self, headers: &HeaderMap, role: Role) -> Result<Principal> {
+        let principal = self.api_keys.authenticate(&bearer_token(headers)?)
+            .ok_or_else(|| ApiError::Wallet(WalletError::Unauthenticated("Unknown API key".to_string())))?;
+        if !principal.has_role(role) {
+            return Err(ApiError::PermissionDenied(format!("{} lacks the {:?} role", principal.actor, role)));
+        }
+        Ok(principal.clone())
+    }
+
+    /// Get the platform fee charged on swaps, if configured
+    fn platform_fee(&self) -> Option<PlatformFeeConfig> {
+        self.config.current().platform_fee()
+    }
+
+    /// Get the RPC endpoint, authenticated with the `rpc_api_key` secret if set
+    ///
+    /// The key is looked up on every call (cached by `CachedSecrets`), so a
+    /// rotated key is used without a restart.
+    fn provider_config(&self) -> ProviderConfig {
+        match self.rpc_config.clone().with_api_key_from(&self.secrets, "rpc_api_key") {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::error!("Failed to load the RPC API key: {}", e);
+                self.rpc_config.clone()
+            }
+        }
+    }
+
+    /// Get the Bitcoin address index (Esplora or Electrum), if configured
+    fn bitcoin_config(&self) -> Option<ProviderConfig> {
+        self.config.current().bitcoin_config()
+    }
+
+    /// Get the toncenter API, authenticated with the `toncenter_api_key` secret if set
+    fn ton_config(&self) -> Option<ProviderConfig> {
+        let config = self.config.current().ton_config()?;
+        match config.with_api_key_from(&self.secrets, "toncenter_api_key") {
+            Ok(config) => Some(config),
+            Err(e) => {
+                tracing::error!("Failed to load the toncenter API key: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Get the Cosmos SDK REST (LCD) API, if configured
+    ///
+    /// The chain is picked from the URL (`.../cosmoshub`, `.../osmosis`), defaulting to the Cosmos Hub.
+    fn cosmos_config(&self) -> Option<ProviderConfig> {
+        self.config.current().cosmos_config()
+    }
+
+    /// Get the rippled JSON-RPC server for the XRP Ledger, if configured
+    ///
+    /// The network is picked from the URL (`altnet`/`testnet`, `devnet`), defaulting to mainnet.
+    fn xrp_config(&self) -> Option<ProviderConfig> {
+        self.config.current().xrp_config()
+    }
+
+    /// Re-read the configuration, applying it if it changed and is valid
+    fn reload_config(&self) -> fo3_wallet::error::Result<Option<Arc<ApiConfig>>> {
+        let reloaded = self.config.reload()?;
+        if let Some(config) = &reloaded {
+            self.fee_budgets.set_default(config.fee_budget())?;
+            self.load_shedder.reconfigure(config.concurrency_limits.clone())?;
+            tracing::info!("Reloaded configuration");
         }
+        Ok(reloaded)
+    }
+
+    /// Record a state-changing request in the audit log
+    ///
+    /// The actor is the principal of the caller's API key, or `anonymous`.
+    /// An `X-Actor` header is kept only as the unverified claimed actor. The
+    /// entry is written in the background, off the async workers; store
+    /// failures are logged rather than failing the request, since the action
+    /// has already taken effect.
+    fn audit(&self, headers: &HeaderMap, action: &str, resource: &str, before: Option<serde_json::Value>, after: Option<serde_json::Value>) {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+        let claimed = actor(headers);
+        let (actor, claimed_actor) = match bearer_token(headers).ok().and_then(|token| self.api_keys.authenticate(&token)) {
+            Some(principal) => (principal.actor.clone(), claimed.filter(|claimed| *claimed != principal.actor)),
+            None => ("anonymous".to_string(), claimed),
+        };
+
+        let event = AuditEvent::new(&actor, action, resource)
+            .map(|event| event.with_change(before, after).with_claimed_actor(claimed_actor).with_request_id(header("x-request-id")));
+        let (log, action, resource) = (self.audit_log.clone(), action.to_string(), resource.to_string());
+        spawn_job("audit", move || {
+            if let Err(e) = event.and_then(|event| log.record(event)) {
+                tracing::error!("Failed to record audit event {} on {}: {}", action, resource, e);
+            }
+        });
     }
 
     fn add_wallet(&self, wallet: Wallet) -> std::result::Result<(), String> {
@@ -62,6 +304,425 @@ impl AppState {
     fn get_all_wallets(&self) -> Vec<Wallet> {
         self.wallets.read().unwrap().values().cloned().collect()
     }
+
+    fn rename_wallet(&self, id: &str, name: String) -> Option<Wallet> {
+        let mut wallets = self.wallets.write().unwrap();
+        let wallet = wallets.get_mut(id)?;
+        wallet.set_name(name);
+        Some(wallet.clone())
+    }
+}
+
+/// Create the default MEV protection from `FO3_MEV_PROTECTION` (`public` or `private`) and `FO3_MEV_RELAY_URL`
+fn mev_protection_from_env() -> MevProtection {
+    let protection = MevProtection {
+        mode: match std::env::var("FO3_MEV_PROTECTION").as_deref() {
+            Ok("private") => SubmissionMode::Private,
+            _ => SubmissionMode::Public,
+        },
+        relay_url: std::env::var("FO3_MEV_RELAY_URL").ok(),
+        ..MevProtection::default()
+    };
+
+    match protection.validate() {
+        Ok(()) => protection,
+        Err(e) => {
+            tracing::error!("Ignoring invalid MEV protection configuration: {}", e);
+            MevProtection::default()
+        }
+    }
+}
+
+/// Open the audit log in `FO3_AUDIT_LOG` (a JSON lines file), or in memory,
+/// keyed with the `audit_hmac_key` secret if set
+fn audit_log_from_env(secrets: &dyn SecretProvider) -> AuditLog {
+    let store: Box<dyn AuditStore> = match std::env::var("FO3_AUDIT_LOG") {
+        Ok(path) => Box::new(FileAuditStore::new(path)),
+        Err(_) => Box::new(InMemoryAuditStore::new()),
+    };
+
+    let log = AuditLog::open(store).unwrap_or_else(|e| {
+        tracing::error!("Failed to open the audit log, falling back to memory: {}", e);
+        AuditLog::open(Box::new(InMemoryAuditStore::new())).expect("in-memory audit store cannot fail")
+    });
+
+    match secrets.get("audit_hmac_key") {
+        Ok(Some(key)) => log.with_key(key.expose().as_bytes().to_vec()),
+        Ok(None) => {
+            tracing::warn!("No audit_hmac_key secret, the audit log is an unkeyed hash chain");
+            log
+        }
+        Err(e) => {
+            tracing::error!("Failed to load the audit HMAC key, the audit log is an unkeyed hash chain: {}", e);
+            log
+        }
+    }
+}
+
+/// Record platform fees in `FO3_FEE_LEDGER` (JSON lines), or in memory
+fn fee_ledger_from_env() -> Box<dyn FeeLedger> {
+    match std::env::var("FO3_FEE_LEDGER") {
+        Ok(path) => Box::new(FileFeeLedger::new(path)),
+        Err(_) => {
+            tracing::warn!("FO3_FEE_LEDGER is not set, collected platform fees are lost on restart");
+            Box::new(InMemoryFeeLedger::new())
+        }
+    }
+}
+
+/// Keep scheduled orders and their runs in `FO3_SCHEDULES` (a JSON file), or in memory
+fn scheduler_from_env() -> fo3_wallet::error::Result<Scheduler> {
+    let store: Box<dyn ScheduleStore> = match std::env::var("FO3_SCHEDULES") {
+        Ok(path) => Box::new(FileScheduleStore::new(path)),
+        Err(_) => {
+            tracing::warn!("FO3_SCHEDULES is not set, scheduled orders are lost on restart");
+            Box::new(InMemoryScheduleStore::new())
+        }
+    };
+    Scheduler::with_store(SchedulePolicy::default(), store)
+}
+
+/// Keep wallet metadata in `FO3_WALLET_METADATA` (a JSON file), or in memory
+fn wallet_metadata_from_env() -> Box<dyn MetadataRepository> {
+    match std::env::var("FO3_WALLET_METADATA") {
+        Ok(path) => Box::new(FileMetadataRepository::new(path)),
+        Err(_) => Box::new(InMemoryMetadataRepository::new()),
+    }
+}
+
+/// The caller named in the `X-Actor` header
+fn actor(headers: &HeaderMap) -> Option<String> {
+    headers.get("x-actor").and_then(|value| value.to_str().ok()).map(str::to_string)
+}
+
+/// The user a request acts for: the actor of the caller's API key
+///
+/// Any API key will do, whatever its roles. Handlers whose user is optional
+/// take `Option<User>`, which is `None` without a valid key.
+struct User(String);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for User {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self> {
+        let Extension(app) = Extension::<Arc<AppState>>::from_request_parts(parts, state).await
+            .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+        let principal = app.api_keys.authenticate(&bearer_token(&parts.headers)?)
+            .ok_or_else(|| ApiError::Wallet(WalletError::Unauthenticated("Unknown API key".to_string())))?;
+        Ok(User(principal.actor.clone()))
+    }
+}
+
+/// The token in an `Authorization: Bearer` header
+fn bearer_token(headers: &HeaderMap) -> Result<String> {
+    headers.get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+        .ok_or_else(|| ApiError::Wallet(WalletError::Unauthenticated("A bearer token is required".to_string())))
+}
+
+/// Reject a malformed address with the reason it failed validation
+fn check_address(key_type: KeyType, address: &str) -> Result<()> {
+    validate_address(key_type, address).map_err(WalletError::from)?;
+    Ok(())
+}
+
+/// Create the spam classifier, loading known scam tokens from `FO3_SCAM_TOKEN_LIST` if set
+fn spam_classifier_from_env() -> SpamClassifier {
+    let classifier = SpamClassifier::new();
+    if let Ok(path) = std::env::var("FO3_SCAM_TOKEN_LIST") {
+        let loaded = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| classifier.load_known_scams(&json).map_err(|e| e.to_string()));
+        match loaded {
+            Ok(count) => tracing::info!("Loaded {} known scam tokens", count),
+            Err(e) => tracing::error!("Failed to load the scam token list {}: {}", path, e),
+        }
+    }
+    classifier
+}
+
+/// Load protocol risk profiles from the JSON list in `FO3_PROTOCOL_RISK_PROFILES`
+fn protocol_risk_from_env() -> ProtocolRiskRegistry {
+    let registry = ProtocolRiskRegistry::new(RiskPolicy::default());
+    if let Ok(path) = std::env::var("FO3_PROTOCOL_RISK_PROFILES") {
+        let loaded = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str::<Vec<ProtocolRiskProfile>>(&json).map_err(|e| e.to_string()))
+            .and_then(|profiles| Ok((profiles, unix_timestamp().map_err(|e| e.to_string())?)));
+        match loaded {
+            Ok((profiles, now)) => {
+                tracing::info!("Loaded {} protocol risk profiles", profiles.len());
+                for profile in profiles {
+                    let exploits = profile.exploits.clone();
+                    let tvl = profile.tvl.clone();
+                    let protocol = profile.protocol.clone();
+                    registry.set_profile(profile, now);
+                    registry.record_tvl(&protocol, &tvl, now);
+                    for exploit in exploits {
+                        registry.record_exploit(exploit, now);
+                    }
+                }
+            }
+            Err(e) => tracing::error!("Failed to load the protocol risk profiles {}: {}", path, e),
+        }
+    }
+    registry
+}
+
+/// Build the secrets provider
+///
+/// Secrets are looked up in Vault (if `VAULT_ADDR` and `VAULT_TOKEN` are set),
+/// then in `FO3_SECRETS_DIR`, then in `FO3_`-prefixed environment variables,
+/// and cached for five minutes so rotated values are picked up.
+fn secrets_from_env() -> CachedSecrets<SecretChain> {
+    let mut providers: Vec<Box<dyn SecretProvider>> = Vec::new();
+
+    if let (Ok(addr), Ok(token)) = (std::env::var("VAULT_ADDR"), std::env::var("VAULT_TOKEN")) {
+        let path = std::env::var("FO3_VAULT_PATH").unwrap_or_else(|_| "fo3-wallet".to_string());
+        providers.push(Box::new(VaultSecrets::new(&addr, &token, "secret", &path)));
+    }
+    if let Ok(dir) = std::env::var("FO3_SECRETS_DIR") {
+        providers.push(Box::new(FileSecrets::new(dir)));
+    }
+    providers.push(Box::new(EnvSecrets::new("FO3_")));
+
+    CachedSecrets::new(SecretChain::new(providers), 300)
+}
+
+/// Store candles at the intervals in `FO3_CANDLE_INTERVALS` (default `1h,1d`),
+/// backfilling from the Binance klines API at `FO3_CANDLE_SOURCE_URL` if set
+fn candles_from_env() -> CandleService<InMemoryCandleStore> {
+    let intervals = std::env::var("FO3_CANDLE_INTERVALS")
+        .unwrap_or_else(|_| "1h,1d".to_string())
+        .split(',')
+        .map(|interval| interval.trim().parse())
+        .collect::<std::result::Result<Vec<Interval>, _>>()
+        .unwrap_or_else(|e| {
+            tracing::error!("Ignoring invalid candle intervals: {}", e);
+            vec![Interval::Hour, Interval::Day]
+        });
+    let service = CandleService::new(InMemoryCandleStore::new(), intervals)
+        .expect("at least one candle interval");
+
+    match std::env::var("FO3_CANDLE_SOURCE_URL") {
+        Ok(url) => service.with_source(Box::new(BinanceCandleSource::with_url(&url, "USDT"))),
+        Err(_) => service,
+    }
+}
+
+/// Fetch exchange rates from the source in `FO3_FIAT_RATES_SOURCE`
+///
+/// `ecb` uses the European Central Bank's daily reference rates and
+/// `openexchangerates` uses Open Exchange Rates with the `openexchangerates_app_id`
+/// secret. Without a source, values are shown in USD only.
+fn fiat_rates_from_env(secrets: &dyn SecretProvider) -> FiatRateService {
+    match std::env::var("FO3_FIAT_RATES_SOURCE").as_deref() {
+        Ok("ecb") => FiatRateService::new(Box::new(EcbRateSource::new())),
+        Ok("openexchangerates") => match secrets.get("openexchangerates_app_id") {
+            Ok(Some(app_id)) => FiatRateService::new(Box::new(OpenExchangeRatesSource::new(app_id.expose()))),
+            Ok(None) => {
+                tracing::error!("FO3_FIAT_RATES_SOURCE is openexchangerates but no openexchangerates_app_id secret is set");
+                FiatRateService::default()
+            }
+            Err(e) => {
+                tracing::error!("Failed to load the Open Exchange Rates app ID: {}", e);
+                FiatRateService::default()
+            }
+        },
+        Ok(source) => {
+            tracing::error!("Ignoring unknown exchange rate source {}", source);
+            FiatRateService::default()
+        }
+        Err(_) => FiatRateService::default(),
+    }
+}
+
+/// Encrypt exchange API keys with the `exchange_credentials_key` secret (64 hex characters)
+///
+/// Without the secret a random key is used, so linked accounts only last
+/// until the server restarts.
+fn exchange_connections_from_secrets(secrets: &dyn SecretProvider) -> ExchangeConnections<InMemoryConnectionStore> {
+    let key = match secrets.get("exchange_credentials_key") {
+        Ok(Some(key)) => hex::decode(key.expose()).ok().and_then(|key| <[u8; 32]>::try_from(key).ok()).or_else(|| {
+            tracing::error!("Ignoring exchange_credentials_key: it must be 32 bytes of hex");
+            None
+        }),
+        Ok(None) => None,
+        Err(e) => {
+            tracing::error!("Failed to load the exchange credentials key: {}", e);
+            None
+        }
+    };
+    let key = key.unwrap_or_else(|| {
+        tracing::warn!("No exchange_credentials_key secret; exchange accounts will not survive a restart");
+        rand::random()
+    });
+    ExchangeConnections::new(InMemoryConnectionStore::new(), key)
+}
+
+/// Escrow key shares in `FO3_KEY_SHARES` (a JSON file), encrypted with the
+/// `key_share_encryption_key` secret (64 hex characters)
+///
+/// Without the secret the shares are kept in memory under a random key, so
+/// they only last until the server restarts.
+fn key_shares_from_env(secrets: &dyn SecretProvider) -> ShareEscrow {
+    let key = match secrets.get("key_share_encryption_key") {
+        Ok(Some(key)) => hex::decode(key.expose()).ok().and_then(|key| <[u8; 32]>::try_from(key).ok()).or_else(|| {
+            tracing::error!("Ignoring key_share_encryption_key: it must be 32 bytes of hex");
+            None
+        }),
+        Ok(None) => None,
+        Err(e) => {
+            tracing::error!("Failed to load the key share encryption key: {}", e);
+            None
+        }
+    };
+    let (store, key): (Box<dyn ShareStore>, _) = match (std::env::var("FO3_KEY_SHARES"), key) {
+        (Ok(path), Some(key)) => (Box::new(FileShareStore::new(path)), key),
+        (path, key) => {
+            if path.is_ok() {
+                tracing::error!("FO3_KEY_SHARES is set but there is no key_share_encryption_key; keeping key shares in memory");
+            } else if key.is_none() {
+                tracing::warn!("No key_share_encryption_key secret; escrowed key shares will not survive a restart");
+            }
+            (Box::new(InMemoryShareStore::new()), key.unwrap_or_else(rand::random))
+        }
+    };
+    ShareEscrow::new(store, key)
+}
+
+/// A Lightning node and the network its invoices are for
+struct LightningNode {
+    backend: Box<dyn LightningBackend>,
+    network: String,
+}
+
+/// Connect to the LND node at `FO3_LND_URL`, authenticated with the `lnd_macaroon` secret (hex)
+///
+/// `FO3_LIGHTNING_NETWORK` names the node's network (default `bitcoin`).
+fn lightning_from_env(secrets: &dyn SecretProvider) -> Option<LightningNode> {
+    let url = std::env::var("FO3_LND_URL").ok()?;
+    let config = ProviderConfig { provider_type: ProviderType::Lnd, url, api_key: None, timeout: Some(60) };
+    let backend = config.with_api_key_from(secrets, "lnd_macaroon")
+        .and_then(|config| lightning::lightning_backend(&config));
+    match backend {
+        Ok(backend) => Some(LightningNode {
+            backend,
+            network: std::env::var("FO3_LIGHTNING_NETWORK").unwrap_or_else(|_| "bitcoin".to_string()),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to connect to the Lightning node: {}", e);
+            None
+        }
+    }
+}
+
+/// Load the Solana fee payer from the `solana_fee_payer_key` secret (a base58 keypair)
+fn fee_payer_from_secrets(secrets: &dyn SecretProvider) -> Option<FeePayer> {
+    match secrets.get("solana_fee_payer_key") {
+        Ok(Some(keypair)) => match FeePayer::from_base58(keypair.expose(), FeePayerPolicy::default()) {
+            Ok(fee_payer) => Some(fee_payer),
+            Err(e) => {
+                tracing::error!("Failed to load the Solana fee payer: {}", e);
+                None
+            }
+        },
+        Ok(None) => None,
+        Err(e) => {
+            tracing::error!("Failed to load the Solana fee payer: {}", e);
+            None
+        }
+    }
+}
+
+/// Load operator API keys from the `api_keys` secret
+fn api_keys_from_secrets(secrets: &dyn SecretProvider) -> ApiKeys {
+    match secrets.get("api_keys").and_then(|keys| keys.map(|keys| ApiKeys::from_json(keys.expose())).transpose()) {
+        Ok(keys) => keys.unwrap_or_default(),
+        Err(e) => {
+            tracing::error!("Failed to load API keys: {}", e);
+            ApiKeys::default()
+        }
+    }
+}
+
+/// Build the counterparty screener from `FO3_SCREENING_LIST` (a CSV file) and the `chainalysis_api_key` secret
+fn screener_from_env(secrets: &dyn SecretProvider, audit: &Arc<InMemoryScreeningAudit>) -> Option<ComplianceScreener> {
+    let mut providers: Vec<Box<dyn ScreeningProvider>> = Vec::new();
+
+    if let Ok(path) = std::env::var("FO3_SCREENING_LIST") {
+        match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|list| LocalListScreener::from_csv(&list).map_err(|e| e.to_string())) {
+            Ok(list) => providers.push(Box::new(list)),
+            Err(e) => tracing::error!("Failed to load screening list {}: {}", path, e),
+        }
+    }
+    match secrets.get("chainalysis_api_key") {
+        Ok(Some(api_key)) => providers.push(Box::new(ChainalysisScreener::new(api_key.expose()))),
+        Ok(None) => {}
+        Err(e) => tracing::error!("Failed to load the Chainalysis API key: {}", e),
+    }
+
+    if providers.is_empty() {
+        return None;
+    }
+
+    Some(ComplianceScreener::new(
+        Box::new(CompositeScreener::new(providers)),
+        CompliancePolicy::default(),
+        Box::new(audit.clone()),
+    ))
+}
+
+/// Writes travel rule decisions to the hash-chained audit log
+struct TravelRuleAuditLog(Arc<AuditLog>);
+
+impl TravelRuleAudit for TravelRuleAuditLog {
+    fn record(&self, event: TravelRuleEvent) {
+        let recorded = AuditEvent::new("travel_rule", "travel_rule.transfer", &event.transfer_id)
+            .and_then(|audit| self.0.record(audit.with_change(None, serde_json::to_value(&event).ok())));
+        if let Err(e) = recorded {
+            tracing::error!("Failed to record travel rule event of {}: {}", event.transfer_id, e);
+        }
+    }
+}
+
+/// Build the travel rule gate from `FO3_TRAVEL_RULE_POLICY` (JSON), if set
+///
+/// Originator KYC data is read from `FO3_TRAVEL_RULE_ORIGINATORS`, a JSON
+/// file mapping sending addresses to IVMS101 persons, and counterparties
+/// post their responses to `FO3_TRAVEL_RULE_CALLBACK_URL`. A deployment
+/// asking for the travel rule does not start without a valid policy.
+fn travel_rule_from_env(candles: &Arc<CandleService<InMemoryCandleStore>>, audit_log: &Arc<AuditLog>) -> fo3_wallet::error::Result<Option<TravelRuleGate>> {
+    let Ok(policy) = std::env::var("FO3_TRAVEL_RULE_POLICY") else { return Ok(None) };
+    let policy: TravelRulePolicy = serde_json::from_str(&policy)
+        .map_err(|e| WalletError::InvalidInput(format!("Invalid travel rule policy: {}", e)))?;
+
+    let originators: std::collections::HashMap<String, Vec<Person>> = match std::env::var("FO3_TRAVEL_RULE_ORIGINATORS") {
+        Ok(path) => {
+            let json = std::fs::read_to_string(&path)
+                .map_err(|e| WalletError::InvalidInput(format!("Failed to read travel rule originators {}: {}", path, e)))?;
+            serde_json::from_str(&json)
+                .map_err(|e| WalletError::InvalidInput(format!("Invalid travel rule originators {}: {}", path, e)))?
+        }
+        Err(_) => std::collections::HashMap::new(),
+    };
+    let callback_url = std::env::var("FO3_TRAVEL_RULE_CALLBACK_URL")
+        .unwrap_or_else(|_| "http://localhost:3000/travel-rule/callbacks".to_string());
+
+    let candles = candles.clone();
+    Ok(Some(TravelRuleGate::new(
+        policy,
+        move |request| match originators.get(&request.from) {
+            Some(persons) => Ok(Originator { originator_persons: persons.clone(), account_number: vec![request.from.clone()] }),
+            None => Err(WalletError::Compliance(format!("No originator data on record for {}", request.from))),
+        },
+        move |asset| candles.price_at(asset, unix_timestamp()?),
+        Box::new(TrpMessenger::new(&callback_url)),
+        Box::new(TravelRuleAuditLog(audit_log.clone())),
+    )))
 }
 
 // API error type
@@ -71,32 +732,79 @@ enum ApiError {
     NotFound(String),
 
     #[error("Bad request: {0}")]
-    #[allow(dead_code)]
     BadRequest(String),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
     #[error("Internal server error: {0}")]
     InternalServerError(String),
 
+    #[error("Overloaded: {0}")]
+    Overloaded(String),
+
     #[error("Wallet error: {0}")]
     Wallet(#[from] WalletError),
 }
 
 impl axum::response::IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
-        let (status, error_message) = match &self {
-            Self::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
-            Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
-            Self::InternalServerError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-            Self::Wallet(err) => (StatusCode::BAD_REQUEST, &err.to_string()),
+        // Error bodies follow google.rpc.Status: a canonical status, a
+        // user-facing message, and ErrorInfo / BadRequest details
+        let (status, canonical, reason, message, retryable) = match &self {
+            Self::NotFound(msg) => (StatusCode::NOT_FOUND, "NOT_FOUND", "NOT_FOUND", msg.clone(), false),
+            Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, "INVALID_ARGUMENT", "INVALID_ARGUMENT", msg.clone(), false),
+            Self::Forbidden(msg) => (StatusCode::FORBIDDEN, "PERMISSION_DENIED", "COMPLIANCE_REJECTED", msg.clone(), false),
+            Self::PermissionDenied(msg) => (StatusCode::FORBIDDEN, "PERMISSION_DENIED", "PERMISSION_DENIED", msg.clone(), false),
+            Self::Overloaded(msg) => (StatusCode::SERVICE_UNAVAILABLE, "RESOURCE_EXHAUSTED", "OVERLOADED", msg.clone(), true),
+            Self::InternalServerError(msg) => {
+                tracing::error!("Internal error: {}", msg);
+                (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL", "INTERNAL", "Internal error".to_string(), false)
+            }
+            Self::Wallet(err) => {
+                let status = StatusCode::from_u16(err.status().http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                if status.is_server_error() {
+                    tracing::error!("Request failed: {}", err);
+                }
+                (status, err.status().as_str(), err.code().as_str(), err.public_message(), err.is_retryable())
+            }
         };
 
+        let mut details = vec![serde_json::json!({
+            "@type": "type.googleapis.com/google.rpc.ErrorInfo",
+            "reason": reason,
+            "domain": "fo3-wallet",
+            "metadata": { "retryable": retryable.to_string() },
+        })];
+        if let Self::Wallet(err) = &self {
+            if let Some(exceeded) = err.fee_budget_exceeded() {
+                details[0]["metadata"]["fee"] = serde_json::json!(exceeded.fee);
+                details[0]["metadata"]["limit"] = serde_json::json!(exceeded.limit);
+                details[0]["metadata"]["rule"] = serde_json::to_value(exceeded.rule).unwrap();
+            }
+            if !err.violations().is_empty() {
+                details.push(serde_json::json!({
+                    "@type": "type.googleapis.com/google.rpc.BadRequest",
+                    "fieldViolations": err.violations(),
+                }));
+            }
+        }
+
         let body = Json(serde_json::json!({
             "error": {
-                "message": error_message,
-                "code": status.as_u16()
+                "message": message,
+                "code": status.as_u16(),
+                "status": canonical,
+                "details": details,
             }
         }));
 
+        if matches!(self, Self::Overloaded(_)) {
+            return (status, [(header::RETRY_AFTER, "1")], body).into_response();
+        }
         (status, body).into_response()
     }
 }
@@ -113,6 +821,67 @@ struct CreateWalletRequest {
 struct WalletResponse {
     wallet: Wallet,
     mnemonic: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<WalletMetadata>,
+}
+
+/// A wallet with its user-defined labels, as listed
+#[derive(Debug, Serialize)]
+struct WalletSummary {
+    #[serde(flatten)]
+    wallet: Wallet,
+    #[serde(flatten)]
+    metadata: WalletMetadata,
+}
+
+#[derive(Debug, Deserialize)]
+struct WalletListQuery {
+    /// List archived wallets too
+    #[serde(default)]
+    include_archived: bool,
+}
+
+/// Changes to a wallet's name and labels; omitted fields are left as they are
+#[derive(Debug, Deserialize)]
+struct UpdateWalletRequest {
+    name: Option<String>,
+    /// `#RRGGBB`, or an empty string to clear it
+    color: Option<String>,
+    /// Icon name, or an empty string to clear it
+    icon: Option<String>,
+    archived: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DepositAttributionQuery {
+    /// Destination tag of the payment (XRP)
+    destination_tag: Option<u32>,
+    /// Memo of the payment (TON, Cosmos)
+    memo: Option<String>,
+}
+
+/// An incoming payment reported by the chain watcher
+#[derive(Debug, Deserialize)]
+struct IncomingPaymentRequest {
+    key_type: KeyType,
+    /// Sender, screened when compliance screening is configured
+    from: Option<String>,
+    /// Address paid
+    to: String,
+    destination_tag: Option<u32>,
+    memo: Option<String>,
+    /// Token contract or mint, or none for the native asset
+    asset: Option<String>,
+    hash: String,
+    /// Amount in the smallest unit
+    amount: String,
+    timestamp: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountNoteRequest {
+    /// Note text; a blank note removes it
+    note: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -121,6 +890,19 @@ struct ImportWalletRequest {
     mnemonic: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct ExportBackupRequest {
+    password: String,
+    #[serde(default)]
+    metadata: BackupMetadata,
+}
+
+#[derive(Debug, Deserialize)]
+struct RestoreBackupRequest {
+    bundle: BackupBundle,
+    password: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct DeriveAddressRequest {
     wallet_id: String,
@@ -135,167 +917,2173 @@ struct AddressResponse {
     path: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct EscrowKeyShareRequest {
+    /// Server shares of a key split on the client
+    server_shares: ServerShares,
+}
+
+#[derive(Debug, Deserialize)]
+struct CoSignRequest {
+    /// Hex-encoded message (a 32-byte hash for secp256k1 keys)
+    message: String,
+    /// The device's half of the signature, over the nonce last started
+    partial: PartialSignature,
+}
+
+#[derive(Debug, Serialize)]
+struct CoSignResponse {
+    signature: String,
+}
+
 #[derive(Debug, Serialize)]
 struct TransactionResponse {
     hash: String,
     status: TransactionStatus,
+    /// Route taken when the wallet's MEV protection applied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    route: Option<SubmissionRoute>,
 }
 
-// API handlers
-async fn create_wallet(
-    Extension(state): Extension<Arc<AppState>>,
-    Json(request): Json<CreateWalletRequest>,
-) -> Result<(StatusCode, Json<WalletResponse>)> {
-    let (wallet, mnemonic) = Wallet::new(request.name)
-        .map_err(ApiError::Wallet)?;
+#[derive(Debug, Deserialize)]
+struct SendTransactionRequest {
+    #[serde(flatten)]
+    request: TransactionRequest,
+    /// Beneficiary data for the travel rule, needed above its threshold
+    #[serde(default)]
+    travel_rule: Option<TravelRuleDetails>,
+}
 
-    state.add_wallet(wallet.clone())
-        .map_err(|e| ApiError::InternalServerError(e))?;
+#[derive(Debug, Default, Deserialize)]
+struct TravelRuleDetails {
+    beneficiary_persons: Vec<Person>,
+    /// Beneficiary VASP, or `None` for unhosted wallets
+    beneficiary_vasp: Option<Vasp>,
+    /// Travel rule endpoint of the beneficiary VASP
+    counterparty_endpoint: Option<String>,
+}
 
-    Ok((
-        StatusCode::CREATED,
-        Json(WalletResponse {
-            wallet,
-            mnemonic: Some(mnemonic),
-        }),
-    ))
+#[derive(Debug, Deserialize)]
+struct ExecuteSwapRequest {
+    #[serde(flatten)]
+    request: SwapRequest,
+    /// ID of a quote from `POST /defi/swap/quote`, required unless dry-running
+    quote_id: Option<String>,
+    /// Account the swap is made from, to include it in the account's exports
+    owner: Option<String>,
 }
 
-async fn import_wallet(
-    Extension(state): Extension<Arc<AppState>>,
-    Json(request): Json<ImportWalletRequest>,
-) -> Result<(StatusCode, Json<WalletResponse>)> {
-    let wallet = Wallet::from_mnemonic(request.name, &request.mnemonic)
-        .map_err(ApiError::Wallet)?;
+#[derive(Debug, Deserialize)]
+struct ExecuteStakingRequest {
+    #[serde(flatten)]
+    request: StakingRequest,
+    /// Account claiming rewards, to include them in the account's exports
+    owner: Option<String>,
+    /// Chain of `owner`
+    key_type: Option<KeyType>,
+}
 
-    state.add_wallet(wallet.clone())
-        .map_err(|e| ApiError::InternalServerError(e))?;
+#[derive(Debug, Deserialize)]
+struct QuoteQuery {
+    /// Check this address's allowance and plan the approvals the swap needs
+    owner: Option<String>,
+    /// How the router is allowed to pull the input token
+    #[serde(default)]
+    approval: ApprovalStrategy,
+    /// Smart account batching the approvals with the swap
+    smart_account: Option<String>,
+}
 
-    Ok((
-        StatusCode::CREATED,
-        Json(WalletResponse {
-            wallet,
-            mnemonic: None,
-        }),
-    ))
+#[derive(Debug, Deserialize)]
+struct SendQuery {
+    /// Wallet whose MEV protection settings apply
+    wallet_id: Option<String>,
 }
 
-async fn get_wallet(
-    Extension(state): Extension<Arc<AppState>>,
-    Path(id): Path<String>,
-) -> Result<Json<WalletResponse>> {
-    let wallet = state.get_wallet(&id)
-        .ok_or_else(|| ApiError::NotFound(format!("Wallet not found: {}", id)))?;
+#[derive(Debug, Deserialize)]
+struct BalanceQuery {
+    /// Apply balance changes of transactions sent through this API that are still pending
+    #[serde(default = "default_include_pending")]
+    include_pending: bool,
+    /// Show tokens classified as spam
+    #[serde(default)]
+    include_spam: bool,
+    /// Currency to value balances in, overriding the caller's display currency
+    currency: Option<String>,
+}
 
-    Ok(Json(WalletResponse {
-        wallet,
-        mnemonic: None,
-    }))
+#[derive(Debug, Deserialize)]
+struct SpamOverrideRequest {
+    action: SpamOverride,
 }
 
-async fn get_all_wallets(
-    Extension(state): Extension<Arc<AppState>>,
-) -> Result<Json<Vec<Wallet>>> {
-    let wallets = state.get_all_wallets();
-    Ok(Json(wallets))
+/// A wallet cleanup plan
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum CleanupPlan {
+    /// Empty Solana token accounts to close
+    TokenAccounts(TokenAccountCleanup),
+    /// Small Bitcoin UTXOs to consolidate, `null` when none are worth it
+    Utxos(Option<UtxoConsolidation>),
 }
 
-async fn derive_address(
-    Extension(state): Extension<Arc<AppState>>,
-    Json(request): Json<DeriveAddressRequest>,
-) -> Result<Json<AddressResponse>> {
-    let wallet = state.get_wallet(&request.wallet_id)
-        .ok_or_else(|| ApiError::NotFound(format!("Wallet not found: {}", request.wallet_id)))?;
+#[derive(Debug, Deserialize)]
+struct SponsorRequest {
+    /// Instructions to pay the fees of
+    instructions: Vec<Instruction>,
+    /// Blockhash the transaction expires with
+    recent_blockhash: String,
+}
 
-    let address = match request.key_type {
-        KeyType::Ethereum => wallet.get_ethereum_address(&request.path, None),
-        KeyType::Solana => wallet.get_solana_address(&request.path, None),
-        KeyType::Bitcoin => wallet.get_bitcoin_address(&request.path, fo3_wallet::crypto::keys::bitcoin::Network::Bitcoin, None),
-    }.map_err(ApiError::Wallet)?;
+#[derive(Debug, Serialize)]
+struct SubmitSponsoredResponse {
+    /// Transaction signature, base58
+    signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateScheduleRequest {
+    /// What the order does
+    kind: OrderKind,
+    /// When the order runs
+    schedule: Schedule,
+}
+
+#[derive(Debug, Deserialize)]
+struct FeePayerQuery {
+    /// Signing key to report the usage of
+    signer: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct FeePayerResponse {
+    address: String,
+    usage: Option<FeePayerUsage>,
+    total_usage: FeePayerUsage,
+}
+
+#[derive(Debug, Deserialize)]
+struct DecodeInvoiceRequest {
+    payment_request: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateInvoiceRequest {
+    /// Amount in millisatoshis; omit to let the payer choose
+    amount_msat: Option<u64>,
+    description: String,
+    /// Seconds until the invoice expires
+    #[serde(default = "default_invoice_expiry")]
+    expiry: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct PayInvoiceRequest {
+    payment_request: String,
+    /// Amount in millisatoshis, for invoices without one
+    amount_msat: Option<u64>,
+    /// Most to pay in routing fees, in millisatoshis
+    max_fee_msat: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct LightningPaymentQuery {
+    #[serde(default = "default_payment_direction")]
+    direction: PaymentDirection,
+}
+
+fn default_invoice_expiry() -> u64 {
+    3600
+}
+
+fn default_payment_direction() -> PaymentDirection {
+    PaymentDirection::Outgoing
+}
+
+fn default_include_pending() -> bool {
+    true
+}
+
+/// Seconds after which an unconfirmed transaction no longer adjusts balances
+const PENDING_BALANCE_MAX_AGE: u64 = 3600;
+
+/// Seconds between checks of pending transactions for confirmation
+const PENDING_BALANCE_RECONCILE_INTERVAL: u64 = 15;
+
+/// Seconds between runs of due scheduled orders
+const SCHEDULE_POLL_INTERVAL: u64 = 30;
+
+/// Seconds before a scheduled run that its owner is told it is upcoming
+const SCHEDULE_NOTICE: u64 = 3600;
+
+/// Maximum number of transactions fetched for an export
+const MAX_EXPORT_TRANSACTIONS: usize = 10_000;
+
+#[derive(Debug, Deserialize)]
+struct CreateExportRequest {
+    key_type: KeyType,
+    address: String,
+    from: u64,
+    to: u64,
+    format: ExportFormat,
+    /// Currency to value events in, defaulting to the caller's display currency
+    fiat_currency: Option<String>,
+}
+
+
+#[derive(Debug, Serialize)]
+struct ExportJobResponse {
+    id: String,
+    request: ExportRequest,
+    status: ExportJobStatus,
+    created_at: u64,
+    completed_at: Option<u64>,
+    download_url: Option<String>,
+}
+
+impl ExportJobResponse {
+    /// Describe a job, with its download URL only for the caller that submitted it
+    fn new(job: ExportJob, download_token: Option<&str>) -> Self {
+        let download_url = download_token
+            .map(|token| format!("/exports/{}/download?token={}", job.id, token));
+
+        Self {
+            id: job.id,
+            request: job.request,
+            status: job.status,
+            created_at: job.created_at,
+            completed_at: job.completed_at,
+            download_url,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PriceHistoryQuery {
+    interval: Interval,
+    from: u64,
+    to: u64,
+    max_points: Option<usize>,
+    /// Currency to quote prices in, overriding the caller's display currency
+    currency: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DisplayCurrencyRequest {
+    currency: String,
+}
+
+#[derive(Debug, Serialize)]
+struct DisplayCurrencyResponse {
+    currency: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LinkExchangeRequest {
+    exchange: ExchangeKind,
+    label: Option<String>,
+    api_key: String,
+    api_secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExchangeBalancesQuery {
+    /// Currency to value balances in, overriding the caller's display currency
+    currency: Option<String>,
+}
+
+/// An asset held on exchanges, valued in the display currency when priced
+#[derive(Debug, Serialize)]
+struct ExchangeBalanceResponse {
+    #[serde(flatten)]
+    balance: AggregatedBalance,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fiat_value: Option<FiatAmount>,
+}
+
+#[derive(Debug, Serialize)]
+struct ExchangeBalancesResponse {
+    balances: Vec<ExchangeBalanceResponse>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<ConnectionError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExchangeTradesQuery {
+    /// Unix timestamp of the earliest trade
+    #[serde(default)]
+    since: u64,
+    /// Comma-separated markets to read (e.g. `BTCUSDT,ETHUSDT`), for exchanges that list trades per market
+    markets: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BackfillRequest {
+    interval: Interval,
+    from: u64,
+    to: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResumeDepositsRequest {
+    protocol: Protocol,
+}
+
+#[derive(Debug, Deserialize)]
+struct DownloadQuery {
+    token: String,
+}
+
+// API handlers
+async fn create_wallet(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<CreateWalletRequest>,
+) -> Result<(StatusCode, Json<WalletResponse>)> {
+    let (wallet, mnemonic) = Wallet::new(request.name)
+        .map_err(ApiError::Wallet)?;
+
+    state.add_wallet(wallet.clone())
+        .map_err(|e| ApiError::InternalServerError(e))?;
+    state.audit(&headers, "wallet.create", wallet.id(), None, serde_json::to_value(&wallet).ok());
+
+    Ok((
+        StatusCode::CREATED,
+        Json(WalletResponse {
+            wallet,
+            mnemonic: Some(mnemonic),
+            metadata: None,
+        }),
+    ))
+}
+
+async fn import_wallet(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<ImportWalletRequest>,
+) -> Result<(StatusCode, Json<WalletResponse>)> {
+    let wallet = Wallet::from_mnemonic(request.name, &request.mnemonic)
+        .map_err(ApiError::Wallet)?;
+
+    state.add_wallet(wallet.clone())
+        .map_err(|e| ApiError::InternalServerError(e))?;
+    state.audit(&headers, "wallet.import", wallet.id(), None, serde_json::to_value(&wallet).ok());
+
+    Ok((
+        StatusCode::CREATED,
+        Json(WalletResponse {
+            wallet,
+            mnemonic: None,
+            metadata: None,
+        }),
+    ))
+}
+
+async fn get_wallet(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<WalletResponse>> {
+    let wallet = state.get_wallet(&id)
+        .ok_or_else(|| ApiError::NotFound(format!("Wallet not found: {}", id)))?;
+
+    let metadata = state.wallet_metadata.get_or_default(&id)?;
+
+    Ok(Json(WalletResponse {
+        wallet,
+        mnemonic: None,
+        metadata: Some(metadata),
+    }))
+}
+
+async fn get_all_wallets(
+    Extension(state): Extension<Arc<AppState>>,
+    Query(query): Query<WalletListQuery>,
+) -> Result<Json<Vec<WalletSummary>>> {
+    let mut wallets = Vec::new();
+    for wallet in state.get_all_wallets() {
+        let metadata = state.wallet_metadata.get_or_default(wallet.id())?;
+        if query.include_archived || !metadata.archived {
+            wallets.push(WalletSummary { wallet, metadata });
+        }
+    }
+    wallets.sort_by(|a, b| (a.wallet.created_at(), a.wallet.id()).cmp(&(b.wallet.created_at(), b.wallet.id())));
+    Ok(Json(wallets))
+}
+
+async fn update_wallet(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(request): Json<UpdateWalletRequest>,
+) -> Result<Json<WalletSummary>> {
+    let wallet = state.get_wallet(&id)
+        .ok_or_else(|| ApiError::NotFound(format!("Wallet not found: {}", id)))?;
+    let before = state.wallet_metadata.get_or_default(&id)?;
+
+    let mut metadata = before.clone();
+    if let Some(color) = request.color {
+        metadata.color = Some(color).filter(|color| !color.is_empty());
+    }
+    if let Some(icon) = request.icon {
+        metadata.icon = Some(icon).filter(|icon| !icon.is_empty());
+    }
+    if let Some(archived) = request.archived {
+        metadata.archived = archived;
+    }
+    metadata.validate()?;
+    let name = request.name.map(|name| name.trim().to_string());
+    if let Some(name) = &name {
+        wallet_metadata::validate_name(name)?;
+    }
+
+    state.wallet_metadata.put(&id, &metadata)?;
+    let wallet = match name {
+        Some(name) => state.rename_wallet(&id, name).unwrap_or(wallet),
+        None => wallet,
+    };
+    state.audit(&headers, "wallet.update", &id, serde_json::to_value(&before).ok(), serde_json::to_value(&metadata).ok());
+
+    Ok(Json(WalletSummary { wallet, metadata }))
+}
+
+async fn assign_deposit_address(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(request): Json<DepositRequest>,
+) -> Result<Json<DepositAddress>> {
+    let wallet = state.get_wallet(&id)
+        .ok_or_else(|| ApiError::NotFound(format!("Wallet not found: {}", id)))?;
+    let deposit = state.deposits.assign(&wallet, &request, unix_timestamp()?)?;
+    state.audit(&headers, "deposit_address.assign", &id, None, serde_json::to_value(&deposit).ok());
+    Ok(Json(deposit))
+}
+
+async fn get_deposit_addresses(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<DepositAddress>>> {
+    state.get_wallet(&id).ok_or_else(|| ApiError::NotFound(format!("Wallet not found: {}", id)))?;
+    Ok(Json(state.deposits.store().list(&id)?))
+}
+
+async fn attribute_deposit(
+    Extension(state): Extension<Arc<AppState>>,
+    Path((key_type, address)): Path<(KeyType, String)>,
+    Query(query): Query<DepositAttributionQuery>,
+) -> Result<Json<DepositAddress>> {
+    check_address(key_type, &address)?;
+    state.deposits.attribute(key_type, &address, query.destination_tag, query.memo.as_deref())?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("No deposit address matches {}", address)))
+}
+
+async fn create_payment_request(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(request): Json<CreatePaymentRequest>,
+) -> Result<(StatusCode, Json<PaymentRequest>)> {
+    let wallet = state.get_wallet(&id)
+        .ok_or_else(|| ApiError::NotFound(format!("Wallet not found: {}", id)))?;
+    let created = state.payment_requests.create(&wallet, &state.deposits, request, unix_timestamp()?)?;
+    state.audit(&headers, "invoice.create", &created.id, None, serde_json::to_value(&created).ok());
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+async fn get_payment_requests(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<PaymentRequest>>> {
+    state.get_wallet(&id).ok_or_else(|| ApiError::NotFound(format!("Wallet not found: {}", id)))?;
+    Ok(Json(state.payment_requests.list(&id, unix_timestamp()?)))
+}
+
+async fn get_payment_request(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<PaymentRequest>> {
+    state.payment_requests.get(&id, unix_timestamp()?)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Payment request not found: {}", id)))
+}
+
+/// Stream a payment request's status as server-sent events
+///
+/// The current state is sent first, then every change, and the stream ends
+/// once the request is paid or expired.
+async fn stream_payment_request(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Sse<tokio_stream::wrappers::ReceiverStream<std::result::Result<Event, serde_json::Error>>>> {
+    let mut updates = state.payment_updates.subscribe();
+    let mut current = state.payment_requests.get(&id, unix_timestamp()?)
+        .ok_or_else(|| ApiError::NotFound(format!("Payment request not found: {}", id)))?;
+
+    let (sender, receiver) = tokio::sync::mpsc::channel(16);
+    tokio::spawn(async move {
+        loop {
+            if sender.send(Event::default().event("status").json_data(&current)).await.is_err() {
+                return;
+            }
+            if matches!(current.status, PaymentRequestStatus::Paid | PaymentRequestStatus::Expired) {
+                return;
+            }
+
+            let Ok(now) = unix_timestamp() else { return };
+            let expiry = tokio::time::sleep(std::time::Duration::from_secs(current.expires_at.saturating_sub(now)));
+            tokio::pin!(expiry);
+            let next = loop {
+                tokio::select! {
+                    update = updates.recv() => match update {
+                        Ok(update) if update.id == current.id => break Some(update),
+                        Ok(_) => {}
+                        // Missed updates are caught up by re-reading the request
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => break unix_timestamp().ok().and_then(|now| state.payment_requests.get(&current.id, now)),
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+                    },
+                    _ = &mut expiry => break unix_timestamp().ok().and_then(|now| state.payment_requests.get(&current.id, now)),
+                }
+            };
+            match next {
+                Some(next) => current = next,
+                None => return,
+            }
+        }
+    }.in_current_span());
+
+    Ok(Sse::new(tokio_stream::wrappers::ReceiverStream::new(receiver)).keep_alive(KeepAlive::default()))
+}
+
+async fn record_incoming_payment(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<IncomingPaymentRequest>,
+) -> Result<Json<PaymentRequest>> {
+    // Only the chain watcher may mark requests paid
+    state.authorize(&headers, Role::Watcher)?;
+    let deposit = state.deposits.attribute(request.key_type, &request.to, request.destination_tag, request.memo.as_deref())?
+        .ok_or_else(|| ApiError::NotFound(format!("No deposit address matches {}", request.to)))?;
+
+    // Blocked deposits stay in the review queue instead of paying the request
+    if let (Some(_), Some(sender)) = (&state.screener, &request.from) {
+        let (task_state, key_type, sender, hash) = (state.clone(), request.key_type, sender.clone(), request.hash.clone());
+        let record = blocking(move || match &task_state.screener {
+            Some(screener) => Ok(Some(screener.screen_sender(key_type, &sender, &hash)?)),
+            None => Ok(None),
+        }).await?;
+        if record.is_some_and(|record| record.action == ScreeningAction::Block) {
+            return Err(ApiError::Forbidden(format!("Deposit {} held for compliance review", request.hash)));
+        }
+    }
+    let payment = ReceivedPayment { hash: request.hash, amount: request.amount, timestamp: request.timestamp };
+    let updated = state.payment_requests.record_payment(&deposit, request.asset.as_deref(), payment.clone(), unix_timestamp()?)?
+        .ok_or_else(|| ApiError::NotFound(format!("Deposit address {} is not a payment request's", deposit.address)))?;
+
+    state.audit(&headers, "invoice.payment", &updated.id, None, serde_json::to_value(&payment).ok());
+    // Nobody may be streaming this request
+    let _ = state.payment_updates.send(updated.clone());
+    Ok(Json(updated))
+}
+
+async fn set_account_note(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((id, address)): Path<(String, String)>,
+    Json(request): Json<AccountNoteRequest>,
+) -> Result<StatusCode> {
+    state.get_wallet(&id).ok_or_else(|| ApiError::NotFound(format!("Wallet not found: {}", id)))?;
+    let mut metadata = state.wallet_metadata.get_or_default(&id)?;
+    let before = metadata.note(&address).map(str::to_string);
+
+    metadata.set_note(&address, &request.note);
+    metadata.validate()?;
+    state.wallet_metadata.put(&id, &metadata)?;
+    state.audit(&headers, "wallet.note.set", &format!("{}/{}", id, address), before.map(serde_json::Value::from), metadata.note(&address).map(serde_json::Value::from));
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Export a wallet's mnemonic, encrypted under a password the caller picks
+///
+/// Anyone who can list wallet IDs could otherwise take the keys of every
+/// wallet, so this takes an admin API key.
+async fn export_backup(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(request): Json<ExportBackupRequest>,
+) -> Result<Json<BackupBundle>> {
+    state.authorize(&headers, Role::Admin)?;
+    let wallet = state.get_wallet(&id)
+        .ok_or_else(|| ApiError::NotFound(format!("Wallet not found: {}", id)))?;
+
+    let bundle = backup::export_wallet(&wallet, request.metadata, &request.password)
+        .map_err(ApiError::Wallet)?;
+    state.audit(&headers, "wallet.backup.export", &id, None, None);
+
+    Ok(Json(bundle))
+}
+
+async fn restore_backup(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<RestoreBackupRequest>,
+) -> Result<(StatusCode, Json<WalletResponse>)> {
+    let payload = backup::import_backup(&request.bundle, &request.password)
+        .map_err(ApiError::Wallet)?;
+    let wallet = payload.to_wallet()
+        .map_err(ApiError::Wallet)?;
+
+    state.add_wallet(wallet.clone())
+        .map_err(|e| ApiError::InternalServerError(e))?;
+    state.audit(&headers, "wallet.backup.restore", wallet.id(), None, serde_json::to_value(&wallet).ok());
+
+    Ok((
+        StatusCode::CREATED,
+        Json(WalletResponse {
+            wallet,
+            mnemonic: None,
+            metadata: None,
+        }),
+    ))
+}
+
+async fn escrow_key_share(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<EscrowKeyShareRequest>,
+) -> Result<(StatusCode, Json<EscrowReceipt>)> {
+    // Keys are split on the client, so the server never sees the full secret
+    let public_key = request.server_shares.signing.public_key.clone();
+    // Generating the Paillier key of a secp256k1 key takes a while
+    let task_state = state.clone();
+    let receipt = blocking(move || Ok(task_state.key_shares.deposit(request.server_shares)?)).await?;
+    state.audit(&headers, "key_share.escrow", &receipt.share_id, None, Some(serde_json::json!({ "public_key": public_key })));
+
+    Ok((StatusCode::CREATED, Json(receipt)))
+}
+
+async fn start_co_sign(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(share_id): Path<String>,
+) -> Result<Json<SigningNonce>> {
+    let token = bearer_token(&headers)?;
+    // Encrypting the server share under its Paillier key is CPU-bound
+    let (task_state, task_id) = (state.clone(), share_id.clone());
+    let nonce = blocking(move || Ok(task_state.key_shares.start_signing(&task_id, &token)?)).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Key share not found: {}", share_id)))?;
+
+    Ok(Json(nonce))
+}
+
+async fn co_sign(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(share_id): Path<String>,
+    Json(request): Json<CoSignRequest>,
+) -> Result<Json<CoSignResponse>> {
+    let token = bearer_token(&headers)?;
+    let message = hex::decode(request.message.trim_start_matches("0x"))
+        .map_err(|e| ApiError::BadRequest(format!("Invalid message: {}", e)))?;
+
+    let (task_state, task_id, task_message) = (state.clone(), share_id.clone(), message.clone());
+    let signature = blocking(move || Ok(task_state.key_shares.co_sign(&task_id, &token, &task_message, &request.partial)?)).await?
+        .ok_or_else(|| ApiError::NotFound(format!("Key share not found: {}", share_id)))?;
+    state.audit(&headers, "key_share.co_sign", &share_id, None, Some(serde_json::json!({ "message": hex::encode(&message) })));
+
+    Ok(Json(CoSignResponse {
+        signature: hex::encode(signature),
+    }))
+}
+
+async fn recover_key_shares(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(share_id): Path<String>,
+) -> Result<Json<Recovery>> {
+    // Recovery is authorized by the recovery token, not the access token
+    // kept on the lost device
+    let token = bearer_token(&headers)?;
+    let recovery = state.key_shares.recover(&share_id, &token)?
+        .ok_or_else(|| ApiError::NotFound(format!("Key share not found: {}", share_id)))?;
+    state.audit(&headers, "key_share.recover", &share_id, None, None);
+
+    Ok(Json(recovery))
+}
+
+async fn derive_address(
+    Extension(state): Extension<Arc<AppState>>,
+    Json(request): Json<DeriveAddressRequest>,
+) -> Result<Json<AddressResponse>> {
+    let wallet = state.get_wallet(&request.wallet_id)
+        .ok_or_else(|| ApiError::NotFound(format!("Wallet not found: {}", request.wallet_id)))?;
+
+    let address = match request.key_type {
+        KeyType::Ethereum => wallet.get_ethereum_address(&request.path, None),
+        KeyType::Solana => wallet.get_solana_address(&request.path, None),
+        KeyType::Bitcoin => wallet.get_bitcoin_address(&request.path, fo3_wallet::crypto::keys::bitcoin::Network::Bitcoin, None),
+        KeyType::Ton => wallet.get_ton_address(&request.path, false, None),
+        KeyType::Cosmos => wallet.get_cosmos_address(&request.path, "cosmos", None),
+        KeyType::Xrp => wallet.get_xrp_address(&request.path, None),
+    }.map_err(ApiError::Wallet)?;
+
+    Ok(Json(AddressResponse {
+        address,
+        key_type: request.key_type,
+        path: request.path,
+    }))
+}
+
+async fn send_transaction(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    user: Option<User>,
+    Query(query): Query<SendQuery>,
+    Json(SendTransactionRequest { request, travel_rule }): Json<SendTransactionRequest>,
+) -> Result<axum::response::Response> {
+    request.validate()?;
+    let user = user.map(|User(user)| user);
+    state.fee_budgets.budget_for(user.as_deref()).check(&request).map_err(ApiError::Wallet)?;
+
+    if state.screener.is_some() {
+        // Screening providers make network calls
+        let (task_state, task_request) = (state.clone(), request.clone());
+        let record = blocking(move || match &task_state.screener {
+            Some(screener) => Ok(Some(screener.screen_outgoing(&task_request)?)),
+            None => Ok(None),
+        }).await?;
+        if record.is_some_and(|record| record.action == ScreeningAction::Block) {
+            return Err(ApiError::Forbidden(format!("Transfer to {} blocked by compliance screening", request.to)));
+        }
+    }
+
+    if state.travel_rule.is_some() {
+        let details = travel_rule.unwrap_or_default();
+        let withdrawal = Withdrawal {
+            beneficiary: Beneficiary { beneficiary_persons: details.beneficiary_persons, account_number: vec![request.to.clone()] },
+            beneficiary_vasp: details.beneficiary_vasp,
+            counterparty_endpoint: details.counterparty_endpoint,
+            request: request.clone(),
+        };
+
+        // Sending the identity payload to the counterparty is a network call
+        let task_state = state.clone();
+        let transfer = blocking(move || {
+            let gate = task_state.travel_rule.as_ref().expect("checked above");
+            let transfer = gate.submit(withdrawal, unix_timestamp()?)?;
+            if transfer.state != TransferState::AwaitingCounterparty {
+                gate.release(&transfer.id, unix_timestamp()?)?;
+            }
+            Ok(transfer)
+        }).await?;
+        state.audit(&headers, "travel_rule.submit", &transfer.id, None, Some(serde_json::json!({ "state": transfer.state, "fiat_value": transfer.fiat_value })));
+
+        if transfer.state == TransferState::AwaitingCounterparty {
+            return Ok(axum::response::IntoResponse::into_response((StatusCode::ACCEPTED, Json(transfer))));
+        }
+    }
+
+    let response = broadcast_transaction(&state, &headers, request, query.wallet_id.as_deref()).await?;
+    Ok(axum::response::IntoResponse::into_response(Json(response)))
+}
+
+/// Sign and send a transaction that passed the pre-send checks
+async fn broadcast_transaction(state: &Arc<AppState>, headers: &HeaderMap, request: TransactionRequest, wallet_id: Option<&str>) -> Result<TransactionResponse> {
+    let protection = state.mev_protections.protection_for(wallet_id);
+    let (config, task_request) = (state.provider_config(), request.clone());
+    let (hash, route) = blocking(move || {
+        let request = task_request;
+        let provider = ProviderFactory::create_provider(request.key_type, config)?;
+        if protection.applies(request.key_type) {
+            let signed = provider.create_and_sign_transaction(&request)?;
+            let submission = protection.submit(&RpcRelay::new(protection.relay_url()), &*provider, &signed)?;
+            if let Some(error) = &submission.relay_error {
+                tracing::warn!("Private relay unavailable, sent {} to the public mempool: {}", submission.hash, error);
+            }
+            Ok((submission.hash, Some(submission.route)))
+        } else {
+            Ok((provider.send_transaction(&request)?, None))
+        }
+    }).await?;
+
+    state.audit(headers, "transaction.send", &hash, None, serde_json::to_value(&request).ok());
+    state.pending_balances.record_transaction(&request, &hash, unix_timestamp()?);
+
+    let (config, key_type, task_hash) = (state.provider_config(), request.key_type, hash.clone());
+    let status = blocking(move || Ok(ProviderFactory::create_provider(key_type, config)?.get_transaction_status(&task_hash)?)).await?;
+
+    Ok(TransactionResponse {
+        hash,
+        status,
+        route,
+    })
+}
+
+/// Get a travel rule transfer, polling the counterparty if it is still held
+async fn get_travel_rule_transfer(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<TravelRuleTransfer>> {
+    let task_state = state.clone();
+    Ok(Json(blocking(move || Ok(travel_rule_gate(&task_state)?.poll(&id, unix_timestamp()?)?)).await?))
+}
+
+/// Send the transaction of a travel rule transfer the counterparty accepted
+async fn release_travel_rule_transfer(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Query(query): Query<SendQuery>,
+) -> Result<Json<TransactionResponse>> {
+    state.authorize(&headers, Role::Compliance)?;
+    let request = travel_rule_gate(&state)?.release(&id, unix_timestamp()?)?;
+    state.audit(&headers, "travel_rule.release", &id, None, None);
+    Ok(Json(broadcast_transaction(&state, &headers, request, query.wallet_id.as_deref()).await?))
+}
+
+/// Receive the beneficiary VASP's response to a TRP inquiry
+///
+/// The callback URL carries the transfer's unguessable ID, which only the
+/// counterparty was sent.
+async fn travel_rule_callback(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(body): Json<serde_json::Value>,
+) -> Result<StatusCode> {
+    let response = travel_rule::parse_trp_callback(&body)?;
+    let task_state = state.clone();
+    blocking(move || Ok(travel_rule_gate(&task_state)?.record_response(&id, response, unix_timestamp()?)?)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn travel_rule_gate(state: &AppState) -> Result<&TravelRuleGate> {
+    state.travel_rule.as_ref().ok_or_else(|| ApiError::NotFound("The travel rule is not enabled".to_string()))
+}
+
+async fn build_batch(
+    Json(request): Json<BatchRequest>,
+) -> Result<Json<Batch>> {
+    check_address(request.key_type, &request.from)?;
+    Ok(Json(intents::build_batch(&request)?))
+}
+
+async fn get_transaction(
+    Extension(state): Extension<Arc<AppState>>,
+    Path((key_type, hash)): Path<(KeyType, String)>,
+) -> Result<Json<serde_json::Value>> {
+    let config = state.provider_config();
+    let transaction = blocking(move || Ok(ProviderFactory::create_provider(key_type, config)?.get_transaction(&hash)?)).await?;
+
+    Ok(Json(serde_json::to_value(transaction).unwrap()))
+}
+
+async fn get_balances(
+    Extension(state): Extension<Arc<AppState>>,
+    Path((key_type, address)): Path<(KeyType, String)>,
+    Query(query): Query<BalanceQuery>,
+    user: Option<User>,
+) -> Result<Json<Vec<AdjustedBalance>>> {
+    check_address(key_type, &address)?;
+    let user = user.map(|User(user)| user);
+
+    // Solana addresses report every token they hold, not just listed ones
+    let (task_state, task_address, task_user, include_spam) = (state.clone(), address.clone(), user.clone(), query.include_spam);
+    let balances = blocking(move || {
+        let (state, address, user) = (task_state, task_address, task_user);
+        Ok(match key_type {
+            KeyType::Solana => {
+                let filter = PortfolioFilter { include_spam, user, ..PortfolioFilter::default() };
+                fo3_wallet::defi::get_all_token_balances(&address, &state.provider_config(), &filter, &state.spam_classifier)
+            }
+            _ => fo3_wallet::defi::get_token_balances(key_type, &address, &state.provider_config()).and_then(|balances| {
+                if include_spam {
+                    return Ok(balances);
+                }
+                let token_list = fo3_wallet::defi::get_supported_tokens(key_type, &state.provider_config())?;
+                Ok(state.spam_classifier.filter_balances(user.as_deref(), balances, &token_list))
+            }),
+        }?)
+    }).await?;
+
+    let currency = query.currency.unwrap_or_else(|| state.display_currencies.currency_for(user.as_deref()));
+    let now = unix_timestamp()?;
+    let rate = state.fiat_rates.usd_rate(&currency, now)?;
+
+    // Settled transactions are dropped from the overlay in the background
+    // (see `reconcile_pending_balances`), not on every query
+    let pending = if query.include_pending {
+        state.pending_balances.apply(&address, balances)
+    } else {
+        PendingBalances::new().apply(&address, balances)
+    };
+    let balances = with_asset_ids(&state, key_type, pending);
+    Ok(Json(with_fiat_values(&state, balances, &currency, rate, now)))
+}
+
+/// Value balances in a display currency, given its units per US dollar
+///
+/// Tokens without a stored price are left unvalued.
+fn with_fiat_values(state: &AppState, mut balances: Vec<AdjustedBalance>, currency: &str, rate: f64, now: u64) -> Vec<AdjustedBalance> {
+    for balance in &mut balances {
+        let token = &balance.balance.token;
+        let price = match state.candles.price_at(&token.symbol.to_uppercase(), now) {
+            Ok(Some(price)) => price,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::warn!("Failed to look up the price of {}: {}", token.symbol, e);
+                continue;
+            }
+        };
+        let Ok(amount) = balance.balance.amount.parse::<f64>() else { continue };
+        let value = amount / 10f64.powi(token.decimals as i32) * price * rate;
+        balance.fiat_value = Some(FiatAmount { amount: (value * 100.0).round() / 100.0, currency: currency.to_string() });
+    }
+    balances
+}
+
+/// Attach CAIP-19 asset IDs of the configured chain to balances
+fn with_asset_ids(state: &AppState, key_type: KeyType, mut balances: Vec<AdjustedBalance>) -> Vec<AdjustedBalance> {
+    let chain_id = ProviderFactory::network_binding(key_type, &state.provider_config()).and_then(|binding| binding.chain_id());
+    if let Ok(chain_id) = chain_id {
+        for balance in &mut balances {
+            balance.asset_id = AssetId::from_token(chain_id.clone(), &balance.balance.token).ok();
+        }
+    }
+    balances
+}
+
+async fn get_cleanup_plan(
+    Extension(state): Extension<Arc<AppState>>,
+    Path((key_type, address)): Path<(KeyType, String)>,
+) -> Result<Json<CleanupPlan>> {
+    check_address(key_type, &address)?;
+
+    match key_type {
+        KeyType::Solana => {
+            let config = state.provider_config();
+            let plan = blocking(move || {
+                let provider = SolanaProvider::new(config)?;
+                let (_, accounts) = provider.get_token_accounts(&address)?;
+                Ok(dust::plan_token_account_cleanup(&accounts, &address)?)
+            }).await?;
+            Ok(Json(CleanupPlan::TokenAccounts(plan)))
+        }
+        KeyType::Bitcoin => {
+            // Dust consolidation needs the address's UTXOs from an address index
+            let config = state.bitcoin_config()
+                .ok_or_else(|| ApiError::BadRequest("No Bitcoin address index configured".to_string()))?;
+            let plan = blocking(move || {
+                let provider = BitcoinProvider::new(config)?;
+                let utxos = provider.get_utxos(&address)?;
+                let fee_rate = provider.get_fee_estimates()?.economy.ceil().max(1.0) as u64;
+                let policy = ConsolidationPolicy { fee_rate, ..ConsolidationPolicy::default() };
+                Ok(dust::plan_utxo_consolidation(&utxos, &policy))
+            }).await?;
+            Ok(Json(CleanupPlan::Utxos(plan)))
+        }
+        KeyType::Ethereum | KeyType::Ton | KeyType::Cosmos | KeyType::Xrp => Err(ApiError::BadRequest(format!("Wallet cleanup is not supported for {:?}", key_type))),
+    }
+}
+
+async fn get_transaction_history(
+    Extension(state): Extension<Arc<AppState>>,
+    Path((key_type, address)): Path<(KeyType, String)>,
+    Query(page): Query<PageRequest>,
+) -> Result<Json<Page<Transaction>>> {
+    check_address(key_type, &address)?;
+    let config = match key_type {
+        KeyType::Ton => state.ton_config()
+            .ok_or_else(|| ApiError::BadRequest("No toncenter API configured".to_string()))?,
+        KeyType::Cosmos => state.cosmos_config()
+            .ok_or_else(|| ApiError::BadRequest("No Cosmos REST API configured".to_string()))?,
+        KeyType::Xrp => state.xrp_config()
+            .ok_or_else(|| ApiError::BadRequest("No XRP Ledger server configured".to_string()))?,
+        _ => state.provider_config(),
+    };
+    // Newest first; pending transactions have no timestamp yet and sort first
+    let transactions = blocking(move || {
+        let provider = ProviderFactory::create_provider(key_type, config)?;
+        Ok(paginate_source(
+            &page,
+            |transaction: &Transaction| Reverse((transaction.timestamp.unwrap_or(u64::MAX), transaction.hash.clone())),
+            |limit, offset| provider.get_transactions(&address, limit, offset),
+        )?)
+    }).await?;
+
+    Ok(Json(transactions))
+}
+
+async fn create_export(
+    Extension(state): Extension<Arc<AppState>>,
+    user: Option<User>,
+    Json(request): Json<CreateExportRequest>,
+) -> Result<(StatusCode, Json<ExportJobResponse>)> {
+    check_address(request.key_type, &request.address)?;
+    let fiat_currency = request.fiat_currency.clone()
+        .unwrap_or_else(|| state.display_currencies.currency_for(user.map(|User(user)| user).as_deref()));
+    let (job, download_token) = state.exports.submit(ExportRequest {
+        from: request.from,
+        to: request.to,
+        format: request.format,
+        fiat_currency,
+    })?;
+
+    // Fetching history and building the report can take a while, so the job
+    // runs in the background and the client polls until it has completed.
+    // Events are valued from the server's USD candles, converted at today's
+    // rate for other currencies.
+    let job_id = job.id.clone();
+    let currency = job.request.fiat_currency.clone();
+    let task_state = state.clone();
+    spawn_job("export", move || {
+        let state = task_state;
+        let rate = unix_timestamp().and_then(|now| state.fiat_rates.usd_rate(&currency, now));
+        let outcome = rate.and_then(|rate| {
+            let events = collect_activity(&state, request.key_type, &request.address)?;
+            state.exports.run(&job_id, &events, &ConvertedPrices::new(state.candles.as_ref(), rate))
+        });
+
+        if let Err(e) = outcome {
+            tracing::warn!("Export job {} failed: {}", job_id, e);
+            let _ = state.exports.fail(&job_id, &e.to_string());
+        }
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(ExportJobResponse::new(job, Some(&download_token)))))
+}
+
+/// Run provider calls on the blocking thread pool and wait for them
+///
+/// Providers block on RPC round trips and sleep between retries, which would
+/// stall a runtime worker and every request queued on it.
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || span.in_scope(work))
+        .await
+        .map_err(|e| ApiError::InternalServerError(format!("Provider task failed: {}", e)))?
+}
+
+/// Run blocking work in the background, in a span under the request that started it
+fn spawn_job<F: FnOnce() + Send + 'static>(name: &'static str, job: F) {
+    let span = tracing::info_span!("job", name);
+    tokio::task::spawn_blocking(move || span.in_scope(job));
+}
+
+/// Fetch the full transaction history of an address as activity events,
+/// with the swaps and staking rewards it executed here
+fn collect_activity(state: &AppState, key_type: KeyType, address: &str) -> std::result::Result<Vec<ActivityEvent>, WalletError> {
+    let provider = ProviderFactory::create_provider(key_type, state.provider_config())?;
+
+    let mut events = Vec::new();
+    let mut offset = 0;
+    while offset < MAX_EXPORT_TRANSACTIONS {
+        let page = provider.get_transactions(address, 100, offset)?;
+        for transaction in &page {
+            events.extend(ActivityEvent::from_transaction(transaction, address)?);
+        }
+        if page.len() < 100 {
+            break;
+        }
+        offset += page.len();
+    }
+
+    Ok(activity_export::merge_journal(events, state.activity.events(key_type, address)))
+}
+
+async fn get_export(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<ExportJobResponse>> {
+    let job = state.exports.job(&id)
+        .map_err(|_| ApiError::NotFound(format!("Export job not found: {}", id)))?;
+
+    Ok(Json(ExportJobResponse::new(job, None)))
+}
+
+async fn download_export(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<DownloadQuery>,
+) -> Result<axum::response::Response> {
+    let file = state.exports.download(&id, &query.token)
+        .map_err(|e| ApiError::NotFound(e.to_string()))?;
+
+    let disposition = format!("attachment; filename=\"{}\"", file.file_name);
+    Ok(axum::response::IntoResponse::into_response((
+        [
+            (axum::http::header::CONTENT_TYPE, file.content_type.to_string()),
+            (axum::http::header::CONTENT_DISPOSITION, disposition),
+        ],
+        file.contents,
+    )))
+}
+
+async fn swap_tokens(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(ExecuteSwapRequest { request, quote_id, owner }): Json<ExecuteSwapRequest>,
+) -> Result<Json<serde_json::Value>> {
+    request.validate()?;
+
+    if request.dry_run {
+        let (task_state, task_request) = (state.clone(), request.clone());
+        let preview = blocking(move || {
+            Ok(fo3_wallet::defi::dry_run_swap(&task_request, &task_state.provider_config(), task_state.platform_fee().as_ref())?)
+        }).await?;
+        return Ok(Json(serde_json::to_value(preview).unwrap()));
+    }
+
+    // Swaps only execute against a quote this server issued, so the minimum
+    // output and expiry checked are the ones it computed
+    let quote_id = quote_id.ok_or_else(|| ApiError::BadRequest("quote_id is required; get one from POST /defi/swap/quote".to_string()))?;
+    if let Some(owner) = &owner {
+        check_address(request.from.token.key_type, owner)?;
+    }
+    let quote = state.swap_quotes.take(&quote_id)
+        .ok_or_else(|| ApiError::NotFound(format!("Swap quote not found or already used: {}", quote_id)))?;
+
+    // The fee ledger may write to disk
+    let (task_state, task_request) = (state.clone(), request.clone());
+    let result = blocking(move || {
+        fo3_wallet::defi::execute_quoted_swap(&task_request, &quote, &task_state.provider_config(), task_state.platform_fee().as_ref(), task_state.fee_ledger.as_ref())
+            .map_err(ApiError::Wallet)
+    }).await?;
+    state.audit(&headers, "defi.swap", &result.transaction_hash, serde_json::to_value(&request).ok(), serde_json::to_value(&result).ok());
+    if let Some(owner) = &owner {
+        state.activity.record(request.from.token.key_type, owner, ActivityEvent::from_swap(&result, unix_timestamp()?)?);
+    }
+
+    Ok(Json(serde_json::to_value(result).unwrap()))
+}
+
+async fn quote_swap(
+    Extension(state): Extension<Arc<AppState>>,
+    Query(query): Query<QuoteQuery>,
+    Json(request): Json<SwapRequest>,
+) -> Result<Json<SwapQuote>> {
+    request.validate()?;
+
+    if let Some(owner) = &query.owner {
+        check_address(request.from.token.key_type, owner)?;
+    }
+
+    let task_state = state.clone();
+    let quote = blocking(move || {
+        let state = task_state;
+        Ok(match (&query.owner, &state.platform_fee()) {
+            (Some(owner), platform_fee) => {
+                let options = ApprovalOptions { strategy: query.approval, smart_account: query.smart_account.clone() };
+                fo3_wallet::defi::quote_swap_with_approval(&request, owner, &options, &state.provider_config(), platform_fee.as_ref())
+            }
+            (None, Some(platform_fee)) => fo3_wallet::defi::quote_swap_with_platform_fee(&request, &state.provider_config(), platform_fee),
+            (None, None) => fo3_wallet::defi::quote_swap(&request, &state.provider_config()),
+        }?)
+    }).await?;
+
+    Ok(Json(state.swap_quotes.issue(quote, unix_timestamp()?)?))
+}
+
+async fn get_supported_tokens(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(key_type): Path<KeyType>,
+) -> Result<Json<Vec<Token>>> {
+    let config = state.provider_config();
+    let tokens = blocking(move || Ok(fo3_wallet::defi::get_supported_tokens(key_type, &config)?)).await?;
+
+    Ok(Json(tokens))
+}
+
+async fn execute_lending(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<LendingRequest>,
+) -> Result<Json<serde_json::Value>> {
+    let config = state.provider_config();
+    if request.dry_run {
+        let preview = blocking(move || Ok(fo3_wallet::defi::dry_run_lending(&request, &config)?)).await?;
+        return Ok(Json(serde_json::to_value(preview).unwrap()));
+    }
+
+    if let LendingAction::Supply(_) = request.action {
+        state.protocol_risk.check_deposit(&request.protocol)?;
+    }
+    let task_request = request.clone();
+    let result = blocking(move || Ok(fo3_wallet::defi::execute_lending(&task_request, &config)?)).await?;
+    state.audit(&headers, "defi.lending", &result.transaction_hash, serde_json::to_value(&request).ok(), serde_json::to_value(&result).ok());
+
+    Ok(Json(serde_json::to_value(result).unwrap()))
+}
+
+async fn execute_staking(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(ExecuteStakingRequest { request, owner, key_type }): Json<ExecuteStakingRequest>,
+) -> Result<Json<serde_json::Value>> {
+    let config = state.provider_config();
+    if request.dry_run {
+        let preview = blocking(move || Ok(fo3_wallet::defi::dry_run_staking(&request, &config)?)).await?;
+        return Ok(Json(serde_json::to_value(preview).unwrap()));
+    }
+
+    let owner = match (owner, key_type) {
+        (Some(owner), Some(key_type)) => {
+            check_address(key_type, &owner)?;
+            Some((key_type, owner))
+        }
+        (None, _) => None,
+        (Some(_), None) => return Err(ApiError::BadRequest("owner requires key_type".to_string())),
+    };
+
+    if let StakingAction::Stake(_) = request.action {
+        state.protocol_risk.check_deposit(&request.protocol)?;
+    }
+    let task_request = request.clone();
+    let result = blocking(move || Ok(fo3_wallet::defi::execute_staking(&task_request, &config)?)).await?;
+    state.audit(&headers, "defi.staking", &result.transaction_hash, serde_json::to_value(&request).ok(), serde_json::to_value(&result).ok());
+    if let Some((key_type, owner)) = &owner {
+        state.activity.record(*key_type, owner, ActivityEvent::from_staking_reward(&result, unix_timestamp()?)?);
+    }
+
+    Ok(Json(serde_json::to_value(result).unwrap()))
+}
+
+async fn get_protocol_risk(
+    Extension(state): Extension<Arc<AppState>>,
+) -> Json<Vec<RiskAssessment>> {
+    Json(state.protocol_risk.assessments())
+}
+
+async fn set_protocol_risk_profile(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(profile): Json<ProtocolRiskProfile>,
+) -> Result<Json<RiskAssessment>> {
+    state.authorize(&headers, Role::Admin)?;
+    let protocol = profile.protocol.clone();
+    let resource = format!("{:?}", protocol);
+    let before = state.protocol_risk.profile(&protocol).map(|profile| serde_json::json!(profile));
+    state.protocol_risk.set_profile(profile, unix_timestamp()?);
+    let after = state.protocol_risk.profile(&protocol).map(|profile| serde_json::json!(profile));
+    state.audit(&headers, "protocol_risk.profile", &resource, before, after);
+    Ok(Json(state.protocol_risk.assessment(&protocol).expect("profile was just set")))
+}
+
+async fn report_exploit(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(report): Json<ExploitReport>,
+) -> Result<Json<Vec<RiskEvent>>> {
+    state.authorize(&headers, Role::Admin)?;
+    state.audit(&headers, "protocol_risk.exploit", &format!("{:?}", report.protocol), None, Some(serde_json::json!(report)));
+    let now = unix_timestamp()?;
+    let events = state.protocol_risk.record_exploit(report, now);
+
+    // Emergency exits send transactions
+    let (task_state, task_events) = (state.clone(), events.clone());
+    blocking(move || {
+        handle_risk_events(&task_state, &task_events, now);
+        Ok(())
+    }).await?;
+    Ok(Json(events))
+}
+
+async fn resume_protocol_deposits(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<ResumeDepositsRequest>,
+) -> Result<StatusCode> {
+    state.authorize(&headers, Role::Admin)?;
+    let resource = format!("{:?}", request.protocol);
+    let before = state.protocol_risk.assessment(&request.protocol).map(|assessment| serde_json::json!({ "pause_reason": assessment.pause_reason }));
+    state.protocol_risk.resume_deposits(&request.protocol)?;
+    state.audit(&headers, "protocol_risk.resume", &resource, before, None);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn refresh_protocol_risk(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<StatusCode> {
+    state.authorize(&headers, Role::Admin)?;
+    state.audit(&headers, "protocol_risk.refresh", "defillama", None, None);
+
+    // One request per protocol plus the hacks list, so this runs in the background
+    let task_state = state.clone();
+    spawn_job("protocol_risk.refresh", move || {
+        let refreshed = unix_timestamp().and_then(|now| {
+            let since = now.saturating_sub(30 * 24 * 60 * 60);
+            Ok((task_state.protocol_risk.refresh(&DefiLlamaRiskFeed::new(), since, now)?, now))
+        });
+        match refreshed {
+            Ok((events, now)) => handle_risk_events(&task_state, &events, now),
+            Err(e) => tracing::warn!("Protocol risk refresh failed: {}", e),
+        }
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Log risk events and exit the positions in protocols they flag
+fn handle_risk_events(state: &AppState, events: &[RiskEvent], now: u64) {
+    let provider_config = state.provider_config();
+    let executor = PipelineExitExecutor::new(&provider_config);
+    for event in events {
+        tracing::warn!("Protocol risk event: {:?}", event);
+        for report in state.emergency_exits.on_risk_event(event, &executor, now) {
+            tracing::warn!("Emergency exit {} of {}: {:?}, stranded positions {:?}", report.id, report.owner, report.status, report.stranded);
+        }
+    }
+}
+
+async fn get_positions(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+) -> Result<Json<Vec<EarnPosition>>> {
+    Ok(Json(state.emergency_exits.positions(&user)))
+}
+
+async fn track_position(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Json(request): Json<TrackPositionRequest>,
+) -> Result<(StatusCode, Json<EarnPosition>)> {
+    let position = state.emergency_exits.track(&user, request, unix_timestamp()?)?;
+    state.audit(&headers, "position.track", &position.id, None, Some(serde_json::json!(position)));
+    Ok((StatusCode::CREATED, Json(position)))
+}
+
+async fn untrack_position(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    state.emergency_exits.untrack(&user, &id)?;
+    state.audit(&headers, "position.untrack", &id, None, None);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_exits(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+) -> Result<Json<Vec<ExitReport>>> {
+    Ok(Json(state.emergency_exits.reports(&user)))
+}
+
+async fn get_exit(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    Path(id): Path<String>,
+) -> Result<Json<ExitReport>> {
+    state.emergency_exits.report(&id)
+        .filter(|report| report.owner == user)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Exit not found: {}", id)))
+}
+
+/// Exit every tracked position of the caller
+async fn panic_exit(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+) -> Result<Json<ExitReport>> {
+    let task_state = state.clone();
+    let report = blocking(move || {
+        Ok(task_state.emergency_exits.panic(&user, &PipelineExitExecutor::new(&task_state.provider_config()), unix_timestamp()?)?)
+    }).await?;
+    state.audit(&headers, "exit.panic", &report.id, None, Some(serde_json::json!({ "status": report.status, "stranded": report.stranded })));
+    Ok(Json(report))
+}
+
+async fn retry_exit(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<ExitReport>> {
+    let (task_state, task_id) = (state.clone(), id.clone());
+    let report = blocking(move || {
+        Ok(task_state.emergency_exits.retry(&user, &task_id, &PipelineExitExecutor::new(&task_state.provider_config()), unix_timestamp()?)?)
+    }).await?;
+    state.audit(&headers, "exit.retry", &id, None, Some(serde_json::json!({ "status": report.status, "stranded": report.stranded })));
+    Ok(Json(report))
+}
+
+async fn get_compound_positions(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+) -> Result<Json<Vec<CompoundPosition>>> {
+    Ok(Json(state.compounder.positions(&user)))
+}
+
+async fn add_compound_position(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Json(request): Json<CompoundRequest>,
+) -> Result<(StatusCode, Json<CompoundPosition>)> {
+    check_address(compounding_key_type(&request.protocol)?, &request.address)?;
+    let position = state.compounder.add_position(&user, request, unix_timestamp()?)?;
+    state.audit(&headers, "compound.add", &position.id, None, Some(serde_json::json!(position)));
+    Ok((StatusCode::CREATED, Json(position)))
+}
+
+async fn get_compound_position(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    Path(id): Path<String>,
+) -> Result<Json<CompoundPosition>> {
+    Ok(Json(owned_compound_position(&state, &user, &id)?))
+}
+
+async fn remove_compound_position(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    let position = owned_compound_position(&state, &user, &id)?;
+    state.compounder.remove(&id)?;
+    state.audit(&headers, "compound.remove", &id, Some(serde_json::json!(position)), None);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn pause_compound_position(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<CompoundPosition>> {
+    set_compound_enabled(&state, &user, &headers, &id, false)
+}
+
+/// Resume compounding a position; runs missed while it was paused are skipped
+async fn resume_compound_position(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<CompoundPosition>> {
+    set_compound_enabled(&state, &user, &headers, &id, true)
+}
+
+fn set_compound_enabled(state: &AppState, user: &str, headers: &HeaderMap, id: &str, enabled: bool) -> Result<Json<CompoundPosition>> {
+    let before = owned_compound_position(state, user, id)?;
+    state.compounder.set_enabled(id, enabled, unix_timestamp()?)?;
+    let after = owned_compound_position(state, user, id)?;
+    let action = if enabled { "compound.resume" } else { "compound.pause" };
+    state.audit(headers, action, id, Some(serde_json::json!({ "enabled": before.enabled })), Some(serde_json::json!({ "enabled": after.enabled })));
+    Ok(Json(after))
+}
+
+async fn get_compound_runs(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<CompoundRun>>> {
+    owned_compound_position(&state, &user, &id)?;
+    Ok(Json(state.compounder.runs(&id)))
+}
 
-    Ok(Json(AddressResponse {
-        address,
-        key_type: request.key_type,
-        path: request.path,
+async fn get_earn_analytics(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+) -> Json<EarnAnalytics> {
+    Json(state.compounder.analytics(&user))
+}
+
+/// Get a compounding position of the caller; other users' positions are not found
+fn owned_compound_position(state: &AppState, user: &str, id: &str) -> Result<CompoundPosition> {
+    state.compounder.position(id)
+        .filter(|position| position.owner == user)
+        .ok_or_else(|| ApiError::NotFound(format!("Compounding position not found: {}", id)))
+}
+
+/// Compounds through the staking pipeline, skipping protocols whose deposits are paused
+///
+/// The check runs before rewards are claimed, so an exploited protocol does
+/// not leave claimed rewards waiting on a restake that cannot happen.
+struct GuardedCompoundExecutor<'a> {
+    protocol_risk: &'a ProtocolRiskRegistry,
+    pipeline: PipelineCompoundExecutor<'a>,
+}
+
+impl CompoundExecutor for GuardedCompoundExecutor<'_> {
+    fn check(&self, request: &CompoundRequest) -> fo3_wallet::error::Result<()> {
+        self.protocol_risk.check_deposit(&request.protocol)
+    }
+
+    fn pending_rewards(&self, request: &CompoundRequest) -> fo3_wallet::error::Result<TokenAmount> {
+        self.pipeline.pending_rewards(request)
+    }
+
+    fn network_fee(&self, request: &CompoundRequest) -> fo3_wallet::error::Result<u128> {
+        self.pipeline.network_fee(request)
+    }
+
+    fn execute(&self, request: &StakingRequest) -> fo3_wallet::error::Result<StakingResult> {
+        self.pipeline.execute(request)
+    }
+}
+
+/// Get the running configuration, with credentials in URLs masked
+async fn get_effective_config(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ApiConfig>> {
+    state.authorize(&headers, Role::Admin)?;
+    Ok(Json(state.config.current().redacted()))
+}
+
+async fn reload_config(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ApiConfig>> {
+    state.authorize(&headers, Role::Admin)?;
+    let before = serde_json::json!(state.config.current().redacted());
+    if state.reload_config()?.is_some() {
+        state.audit(&headers, "config.reload", "config", Some(before), Some(serde_json::json!(state.config.current().redacted())));
+    }
+    Ok(Json(state.config.current().redacted()))
+}
+
+async fn get_screening_reviews(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(page): Query<PageRequest>,
+) -> Result<Json<Page<ScreeningRecord>>> {
+    state.authorize(&headers, Role::Compliance)?;
+
+    // Oldest first; the queue is append-only, so positions break timestamp ties
+    let reviews = paginate(
+        state.screening_audit.review_queue().into_iter().enumerate(),
+        |(position, record)| (record.timestamp, *position),
+        &page,
+    )?;
+
+    Ok(Json(Page {
+        items: reviews.items.into_iter().map(|(_, record)| record).collect(),
+        next_cursor: reviews.next_cursor,
     }))
 }
 
-async fn send_transaction(
+async fn get_spam_overrides(
     Extension(state): Extension<Arc<AppState>>,
-    Json(request): Json<TransactionRequest>,
-) -> Result<Json<TransactionResponse>> {
-    let provider = ProviderFactory::create_provider(request.key_type, state.provider_config.clone())
-        .map_err(|e| ApiError::Wallet(e))?;
+    User(user): User,
+) -> Result<Json<Vec<TokenOverride>>> {
+    Ok(Json(state.spam_classifier.overrides(&user)))
+}
 
-    let hash = provider.send_transaction(&request)
-        .map_err(|e| ApiError::Wallet(e))?;
+async fn get_fee_budget(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+) -> Result<Json<FeeBudget>> {
+    Ok(Json(state.fee_budgets.budget_for(Some(&user))))
+}
 
-    let status = provider.get_transaction_status(&hash)
-        .map_err(|e| ApiError::Wallet(e))?;
+async fn set_fee_budget(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Json(budget): Json<FeeBudget>,
+) -> Result<StatusCode> {
+    state.fee_budgets.set(&user, budget.clone()).map_err(ApiError::Wallet)?;
+    state.audit(&headers, "fee_budget.set", &user, None, serde_json::to_value(&budget).ok());
+    Ok(StatusCode::NO_CONTENT)
+}
 
-    Ok(Json(TransactionResponse {
-        hash,
-        status,
+async fn delete_fee_budget(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+) -> Result<StatusCode> {
+    if !state.fee_budgets.remove(&user) {
+        return Err(ApiError::NotFound(format!("No fee budget for {}", user)));
+    }
+    state.audit(&headers, "fee_budget.delete", &user, None, None);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn create_schedule(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Json(CreateScheduleRequest { kind, schedule }): Json<CreateScheduleRequest>,
+) -> Result<(StatusCode, Json<ScheduledOrder>)> {
+    match &kind {
+        OrderKind::Transfer(request) => request.validate()?,
+        OrderKind::Dca(request) => {
+            request.validate()?;
+            if request.dry_run {
+                return Err(ApiError::BadRequest("Scheduled swaps cannot be dry runs".to_string()));
+            }
+        }
+    }
+
+    let order = state.scheduler.add_order(&user, kind, schedule, unix_timestamp()?)?;
+    state.audit(&headers, "schedule.create", &order.id, None, serde_json::to_value(&order).ok());
+    Ok((StatusCode::CREATED, Json(order)))
+}
+
+async fn get_schedules(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+) -> Result<Json<Vec<ScheduledOrder>>> {
+    Ok(Json(state.scheduler.orders(&user)))
+}
+
+async fn get_schedule(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    Path(id): Path<String>,
+) -> Result<Json<ScheduledOrder>> {
+    Ok(Json(owned_schedule(&state, &user, &id)?))
+}
+
+async fn delete_schedule(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    let order = owned_schedule(&state, &user, &id)?;
+    state.scheduler.cancel(&id)?;
+    state.audit(&headers, "schedule.cancel", &id, serde_json::to_value(&order).ok(), None);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn pause_schedule(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<ScheduledOrder>> {
+    set_schedule_enabled(&state, &user, &headers, &id, false)
+}
+
+/// Resume a paused order; runs missed while it was paused are skipped
+async fn resume_schedule(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<ScheduledOrder>> {
+    set_schedule_enabled(&state, &user, &headers, &id, true)
+}
+
+fn set_schedule_enabled(state: &AppState, user: &str, headers: &HeaderMap, id: &str, enabled: bool) -> Result<Json<ScheduledOrder>> {
+    let before = owned_schedule(state, user, id)?;
+    state.scheduler.set_enabled(id, enabled, unix_timestamp()?)?;
+    let after = owned_schedule(state, user, id)?;
+    let action = if enabled { "schedule.resume" } else { "schedule.pause" };
+    state.audit(headers, action, id, Some(serde_json::json!({ "enabled": before.enabled })), Some(serde_json::json!({ "enabled": after.enabled })));
+    Ok(Json(after))
+}
+
+async fn get_schedule_runs(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<RunRecord>>> {
+    owned_schedule(&state, &user, &id)?;
+    Ok(Json(state.scheduler.runs(&id)))
+}
+
+/// Get a scheduled order of the caller; other users' orders are not found
+fn owned_schedule(state: &AppState, user: &str, id: &str) -> Result<ScheduledOrder> {
+    state.scheduler.order(id)
+        .filter(|order| order.owner == user)
+        .ok_or_else(|| ApiError::NotFound(format!("Scheduled order not found: {}", id)))
+}
+
+/// Executes due scheduled orders with the checks `POST /transactions` applies
+///
+/// Transfers are held to the owner's fee budget, compliance screening and
+/// the travel rule. Scheduled transfers carry no beneficiary details, so
+/// above the travel rule threshold only unhosted wallets the policy allows
+/// clear; anything needing a counterparty exchange is blocked. DCA swaps are
+/// charged the platform fee like any other swap.
+struct ScheduledOrderExecutor<'a> {
+    state: &'a AppState,
+}
+
+impl OrderExecutor for ScheduledOrderExecutor<'_> {
+    fn check(&self, order: &ScheduledOrder) -> fo3_wallet::error::Result<()> {
+        let OrderKind::Transfer(request) = &order.kind else {
+            return Ok(());
+        };
+
+        self.state.fee_budgets.budget_for(Some(&order.owner)).check(request)?;
+
+        if let Some(screener) = &self.state.screener {
+            if screener.screen_outgoing(request)?.action == ScreeningAction::Block {
+                return Err(WalletError::Compliance(format!("Transfer to {} blocked by compliance screening", request.to)));
+            }
+        }
+
+        if let Some(gate) = &self.state.travel_rule {
+            let withdrawal = Withdrawal {
+                beneficiary: Beneficiary { beneficiary_persons: Vec::new(), account_number: vec![request.to.clone()] },
+                beneficiary_vasp: None,
+                counterparty_endpoint: None,
+                request: request.clone(),
+            };
+            let transfer = gate.submit(withdrawal, unix_timestamp()?)?;
+            gate.release(&transfer.id, unix_timestamp()?)?;
+        }
+
+        Ok(())
+    }
+
+    fn execute(&self, kind: &OrderKind) -> fo3_wallet::error::Result<String> {
+        let config = self.state.provider_config();
+        match kind {
+            OrderKind::Transfer(request) => {
+                let hash = ProviderFactory::create_provider(request.key_type, config)?.send_transaction(request)?;
+                self.state.pending_balances.record_transaction(request, &hash, unix_timestamp()?);
+                Ok(hash)
+            }
+            OrderKind::Dca(request) => {
+                let result = match self.state.platform_fee() {
+                    Some(fee) => fo3_wallet::defi::swap_tokens_with_platform_fee(request, &config, &fee, self.state.fee_ledger.as_ref())?,
+                    None => fo3_wallet::defi::swap_tokens(request, &config)?,
+                };
+                Ok(result.transaction_hash)
+            }
+        }
+    }
+}
+
+/// Logs schedule notifications until push notifications are wired up
+struct LogScheduleNotifier;
+
+impl ScheduleNotifier for LogScheduleNotifier {
+    fn notify(&self, notification: ScheduleNotification) {
+        match notification {
+            ScheduleNotification::Upcoming { order_id, owner, run_at } => tracing::info!("Scheduled order {} of {} runs at {}", order_id, owner, run_at),
+            ScheduleNotification::Executed { order_id, owner, transaction_hash } => tracing::info!("Scheduled order {} of {} sent {}", order_id, owner, transaction_hash),
+            ScheduleNotification::Failed { order_id, owner, reason } => tracing::warn!("Scheduled order {} of {} did not run: {}", order_id, owner, reason),
+            ScheduleNotification::Paused { order_id, owner } => tracing::warn!("Scheduled order {} of {} paused after repeated failures", order_id, owner),
+        }
+    }
+}
+
+async fn get_mev_protection(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<MevProtection>> {
+    state.get_wallet(&id).ok_or_else(|| ApiError::NotFound(format!("Wallet not found: {}", id)))?;
+    Ok(Json(state.mev_protections.protection_for(Some(&id))))
+}
+
+async fn set_mev_protection(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(protection): Json<MevProtection>,
+) -> Result<StatusCode> {
+    state.get_wallet(&id).ok_or_else(|| ApiError::NotFound(format!("Wallet not found: {}", id)))?;
+    let before = serde_json::to_value(state.mev_protections.protection_for(Some(&id))).ok();
+    state.mev_protections.set(&id, protection.clone()).map_err(ApiError::Wallet)?;
+    state.audit(&headers, "mev_protection.set", &id, before, serde_json::to_value(&protection).ok());
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn delete_mev_protection(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    if !state.mev_protections.remove(&id) {
+        return Err(ApiError::NotFound(format!("No MEV protection settings for {}", id)));
+    }
+    state.audit(&headers, "mev_protection.delete", &id, None, None);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn set_spam_override(
+    Extension(state): Extension<Arc<AppState>>,
+    Path((key_type, address)): Path<(KeyType, String)>,
+    User(user): User,
+    headers: HeaderMap,
+    Json(request): Json<SpamOverrideRequest>,
+) -> Result<StatusCode> {
+    check_address(key_type, &address)?;
+    state.spam_classifier.set_override(&user, key_type, &address, request.action);
+    state.audit(&headers, "spam_override.set", &address, None, serde_json::to_value(request.action).ok());
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn delete_spam_override(
+    Extension(state): Extension<Arc<AppState>>,
+    Path((key_type, address)): Path<(KeyType, String)>,
+    User(user): User,
+    headers: HeaderMap,
+) -> Result<StatusCode> {
+    check_address(key_type, &address)?;
+    if !state.spam_classifier.remove_override(&user, key_type, &address) {
+        return Err(ApiError::NotFound(format!("No override for {}", address)));
+    }
+    state.audit(&headers, "spam_override.delete", &address, None, None);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_fee_payer(
+    Extension(state): Extension<Arc<AppState>>,
+    Query(query): Query<FeePayerQuery>,
+) -> Result<Json<FeePayerResponse>> {
+    let fee_payer = state.fee_payer.as_ref().ok_or_else(|| ApiError::NotFound("No fee payer configured".to_string()))?;
+    let now = unix_timestamp()?;
+    Ok(Json(FeePayerResponse {
+        address: fee_payer.address().to_string(),
+        usage: query.signer.map(|signer| fee_payer.usage(&signer, now)),
+        total_usage: fee_payer.total_usage(now),
     }))
 }
 
-async fn get_transaction(
+async fn sponsor_transaction(
     Extension(state): Extension<Arc<AppState>>,
-    Path((key_type, hash)): Path<(KeyType, String)>,
-) -> Result<Json<serde_json::Value>> {
-    let provider = ProviderFactory::create_provider(key_type, state.provider_config.clone())
-        .map_err(|e| ApiError::Wallet(e))?;
+    _user: User,
+    Json(request): Json<SponsorRequest>,
+) -> Result<Json<SponsoredTransaction>> {
+    let fee_payer = state.fee_payer.as_ref().ok_or_else(|| ApiError::NotFound("No fee payer configured".to_string()))?;
+    Ok(Json(fee_payer.sponsor(&request.instructions, &request.recent_blockhash, unix_timestamp()?)?))
+}
+
+/// Countersign a sponsored transaction its signers have signed and broadcast it
+async fn submit_sponsored_transaction(
+    Extension(state): Extension<Arc<AppState>>,
+    _user: User,
+    headers: HeaderMap,
+    Json(transaction): Json<SponsoredTransaction>,
+) -> Result<Json<SubmitSponsoredResponse>> {
+    if state.fee_payer.is_none() {
+        return Err(ApiError::NotFound("No fee payer configured".to_string()));
+    }
 
-    let transaction = provider.get_transaction(&hash)
-        .map_err(|e| ApiError::Wallet(e))?;
+    let (task_state, task_transaction) = (state.clone(), transaction.clone());
+    let signature = blocking(move || {
+        let fee_payer = task_state.fee_payer.as_ref().expect("checked above");
+        let provider = ProviderFactory::create_provider(KeyType::Solana, task_state.provider_config())?;
+        Ok(fee_payer.submit(&task_transaction, unix_timestamp()?, &*provider)?)
+    }).await?;
+    state.audit(&headers, "fee_payer.submit", &signature, None, Some(serde_json::json!({ "message": transaction.message_base64, "fee": transaction.fee })));
+    Ok(Json(SubmitSponsoredResponse { signature }))
+}
 
-    Ok(Json(serde_json::to_value(transaction).unwrap()))
+async fn decode_invoice(
+    Json(request): Json<DecodeInvoiceRequest>,
+) -> Result<Json<Invoice>> {
+    Ok(Json(lightning::decode_invoice(&request.payment_request)?))
 }
 
-async fn swap_tokens(
+async fn create_invoice(
     Extension(state): Extension<Arc<AppState>>,
-    Json(request): Json<SwapRequest>,
-) -> Result<Json<serde_json::Value>> {
-    let result = fo3_wallet::defi::swap_tokens(&request, &state.provider_config)
-        .map_err(|e| ApiError::Wallet(e))?;
+    headers: HeaderMap,
+    Json(request): Json<CreateInvoiceRequest>,
+) -> Result<Json<Invoice>> {
+    let task_state = state.clone();
+    let invoice = blocking(move || {
+        let node = task_state.lightning.as_ref().ok_or_else(|| ApiError::NotFound("No Lightning node configured".to_string()))?;
+        Ok(node.backend.create_invoice(request.amount_msat, &request.description, request.expiry)?)
+    }).await?;
+    state.audit(&headers, "lightning.invoice", &invoice.payment_hash, None, serde_json::to_value(invoice.amount_msat).ok());
+    Ok(Json(invoice))
+}
 
-    Ok(Json(serde_json::to_value(result).unwrap()))
+async fn pay_invoice(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<PayInvoiceRequest>,
+) -> Result<Json<LightningPayment>> {
+    // Payments spend the node's own funds
+    state.authorize(&headers, Role::Admin)?;
+    let task_state = state.clone();
+    let payment = blocking(move || {
+        let node = task_state.lightning.as_ref().ok_or_else(|| ApiError::NotFound("No Lightning node configured".to_string()))?;
+        Ok(lightning::pay(
+            node.backend.as_ref(),
+            &request.payment_request,
+            &node.network,
+            request.amount_msat,
+            request.max_fee_msat,
+            unix_timestamp()?,
+        )?)
+    }).await?;
+    state.audit(&headers, "lightning.pay", &payment.payment_hash, None, serde_json::to_value(&payment).ok());
+    Ok(Json(payment))
 }
 
-async fn get_supported_tokens(
+async fn get_lightning_payment(
     Extension(state): Extension<Arc<AppState>>,
-    Path(key_type): Path<KeyType>,
-) -> Result<Json<Vec<Token>>> {
-    let tokens = fo3_wallet::defi::get_supported_tokens(key_type, &state.provider_config)
-        .map_err(|e| ApiError::Wallet(e))?;
+    Path(payment_hash): Path<String>,
+    Query(query): Query<LightningPaymentQuery>,
+) -> Result<Json<Transaction>> {
+    let payment = blocking(move || {
+        let node = state.lightning.as_ref().ok_or_else(|| ApiError::NotFound("No Lightning node configured".to_string()))?;
+        let payment = node.backend.get_payment(&payment_hash, query.direction)?;
+        Ok(payment.to_transaction(&node.backend.node_id()?))
+    }).await?;
+    Ok(Json(payment))
+}
 
-    Ok(Json(tokens))
+async fn get_xrp_balance(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(address): Path<String>,
+) -> Result<Json<XrpBalance>> {
+    check_address(KeyType::Xrp, &address)?;
+    let config = state.xrp_config().ok_or_else(|| ApiError::NotFound("No XRP Ledger server configured".to_string()))?;
+    Ok(Json(blocking(move || Ok(XrpProvider::new(config)?.get_balance(&address)?)).await?))
 }
 
-async fn execute_lending(
+/// Logs validator alerts until push notifications are wired up
+struct LogValidatorNotifier;
+
+impl ValidatorNotifier for LogValidatorNotifier {
+    fn notify(&self, alert: &ValidatorAlert) {
+        tracing::warn!("Validator {} of {} degraded: {:?}", alert.validator, alert.delegator, alert.issues);
+    }
+}
+
+async fn get_validator_alerts(
     Extension(state): Extension<Arc<AppState>>,
-    Json(request): Json<LendingRequest>,
-) -> Result<Json<serde_json::Value>> {
-    let result = fo3_wallet::defi::execute_lending(&request, &state.provider_config)
-        .map_err(|e| ApiError::Wallet(e))?;
+    Path(address): Path<String>,
+) -> Result<Json<Vec<ValidatorAlert>>> {
+    check_address(KeyType::Cosmos, &address)?;
+    let config = state.cosmos_config().ok_or_else(|| ApiError::NotFound("No Cosmos REST API configured".to_string()))?;
+    let task_state = state.clone();
+    let alerts = blocking(move || {
+        let provider = CosmosProvider::new(config)?;
+        Ok(task_state.validator_monitor.check(&address, &provider, &LogValidatorNotifier)?)
+    }).await?;
+    Ok(Json(alerts))
+}
 
-    Ok(Json(serde_json::to_value(result).unwrap()))
+async fn get_price_history(
+    Extension(state): Extension<Arc<AppState>>,
+    user: Option<User>,
+    Path(asset): Path<String>,
+    Query(query): Query<PriceHistoryQuery>,
+) -> Result<Json<Vec<Candle>>> {
+    let request = PriceHistoryRequest {
+        asset: asset.to_uppercase(),
+        interval: query.interval,
+        from: query.from,
+        to: query.to,
+        max_points: query.max_points,
+    };
+    let now = unix_timestamp()?;
+    let currency = query.currency.unwrap_or_else(|| state.display_currencies.currency_for(user.map(|User(user)| user).as_deref()));
+    let rate = state.fiat_rates.usd_rate(&currency, now)?;
+
+    let candles = state.candles.price_history(&request, now)?;
+    Ok(Json(candles.iter().map(|candle| candle.converted(rate)).collect()))
 }
 
-async fn execute_staking(
+async fn get_fiat_rates(
     Extension(state): Extension<Arc<AppState>>,
-    Json(request): Json<StakingRequest>,
-) -> Result<Json<serde_json::Value>> {
-    let result = fo3_wallet::defi::execute_staking(&request, &state.provider_config)
-        .map_err(|e| ApiError::Wallet(e))?;
+) -> Result<Json<FiatRates>> {
+    Ok(Json(state.fiat_rates.rates(unix_timestamp()?)?))
+}
 
-    Ok(Json(serde_json::to_value(result).unwrap()))
+async fn get_display_currency(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+) -> Result<Json<DisplayCurrencyResponse>> {
+    Ok(Json(DisplayCurrencyResponse { currency: state.display_currencies.currency_for(Some(&user)) }))
+}
+
+async fn set_display_currency(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Json(request): Json<DisplayCurrencyRequest>,
+) -> Result<StatusCode> {
+    let currency = request.currency.to_uppercase();
+    // Only accept currencies values can actually be converted to
+    state.fiat_rates.usd_rate(&currency, unix_timestamp()?)?;
+
+    let before = state.display_currencies.currency_for(Some(&user));
+    state.display_currencies.set(&user, &currency).map_err(ApiError::Wallet)?;
+    state.audit(&headers, "display_currency.set", &user, Some(serde_json::json!(before)), Some(serde_json::json!(currency)));
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_exchange_connections(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+) -> Result<Json<Vec<ExchangeConnection>>> {
+    Ok(Json(state.exchange_connections.list(&user)?))
+}
+
+async fn link_exchange(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Json(request): Json<LinkExchangeRequest>,
+) -> Result<(StatusCode, Json<ExchangeConnection>)> {
+    let credentials = ExchangeCredentials { api_key: request.api_key, api_secret: Secret::new(request.api_secret) };
+    let connection = state.exchange_connections.link(&user, request.exchange, request.label, credentials, unix_timestamp()?)?;
+    state.audit(&headers, "exchange_connection.link", &connection.id, None, serde_json::to_value(&connection).ok());
+    Ok((StatusCode::CREATED, Json(connection)))
+}
+
+async fn unlink_exchange(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    if !state.exchange_connections.unlink(&user, &id)? {
+        return Err(ApiError::NotFound(format!("Exchange connection not found: {}", id)));
+    }
+    state.audit(&headers, "exchange_connection.unlink", &id, None, None);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_exchange_balances(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    Query(query): Query<ExchangeBalancesQuery>,
+) -> Result<Json<ExchangeBalancesResponse>> {
+    let now = unix_timestamp()?;
+    let currency = query.currency.unwrap_or_else(|| state.display_currencies.currency_for(Some(&user)));
+    let rate = state.fiat_rates.usd_rate(&currency, now)?;
+
+    let aggregated = state.exchange_connections.balances(&user, now)?;
+    let balances = aggregated.balances.into_iter()
+        .map(|balance| {
+            // Assets without a stored price are left unvalued
+            let price = state.candles.price_at(&balance.asset, now).unwrap_or_else(|e| {
+                tracing::warn!("Failed to look up the price of {}: {}", balance.asset, e);
+                None
+            });
+            let fiat_value = price.map(|price| FiatAmount {
+                amount: (balance.total * price * rate * 100.0).round() / 100.0,
+                currency: currency.clone(),
+            });
+            ExchangeBalanceResponse { balance, fiat_value }
+        })
+        .collect();
+    Ok(Json(ExchangeBalancesResponse { balances, errors: aggregated.errors }))
+}
+
+async fn get_exchange_trades(
+    Extension(state): Extension<Arc<AppState>>,
+    User(user): User,
+    Path(id): Path<String>,
+    Query(query): Query<ExchangeTradesQuery>,
+) -> Result<Json<Vec<ExchangeTrade>>> {
+    let markets: Vec<String> = query.markets.as_deref().unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|market| !market.is_empty())
+        .map(str::to_string)
+        .collect();
+    if !state.exchange_connections.list(&user)?.iter().any(|connection| connection.id == id) {
+        return Err(ApiError::NotFound(format!("Exchange connection not found: {}", id)));
+    }
+    Ok(Json(state.exchange_connections.trades(&user, &id, &markets, query.since)?))
+}
+
+async fn backfill_prices(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(asset): Path<String>,
+    Json(request): Json<BackfillRequest>,
+) -> Result<StatusCode> {
+    if !state.candles.intervals().contains(&request.interval) {
+        return Err(ApiError::BadRequest(format!("Candles are not stored at {}", request.interval)));
+    }
+    let asset = asset.to_uppercase();
+    state.audit(&headers, "prices.backfill", &asset, None, Some(serde_json::json!({
+        "interval": request.interval,
+        "from": request.from,
+        "to": request.to,
+    })));
+
+    // Backfills can span many upstream requests, so they run in the background
+    let task_state = state.clone();
+    spawn_job("candles.backfill", move || {
+        match unix_timestamp().and_then(|now| task_state.candles.backfill(&asset, request.interval, request.from, request.to, now)) {
+            Ok(BackfillReport { fetched, requests, .. }) => {
+                tracing::info!("Backfilled {} {} candles of {} in {} requests", fetched, request.interval, asset, requests);
+            }
+            Err(e) => tracing::warn!("Backfill of {} {} candles failed: {}", asset, request.interval, e),
+        }
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn export_audit_log(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<([(header::HeaderName, &'static str); 1], String)> {
+    state.authorize(&headers, Role::Auditor)?;
+
+    let audit_log = state.audit_log.clone();
+    let log = blocking(move || Ok(audit_log.export()?)).await?;
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], log))
+}
+
+async fn verify_audit_log(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<AuditVerification>> {
+    state.authorize(&headers, Role::Auditor)?;
+
+    let audit_log = state.audit_log.clone();
+    Ok(Json(blocking(move || Ok(audit_log.verify()?)).await?))
+}
+
+/// Shed requests to a service that is at its concurrency limit
+///
+/// Health checks are always answered, so an overloaded instance is not taken
+/// for a dead one.
+async fn shed_load<B>(
+    Extension(state): Extension<Arc<AppState>>,
+    request: axum::http::Request<B>,
+    next: axum::middleware::Next<B>,
+) -> axum::response::Response {
+    let service = limiter::service_for(request.uri().path());
+    if service == "health" {
+        return next.run(request).await;
+    }
+    match state.load_shedder.try_acquire(service) {
+        Some(_permit) => next.run(request).await,
+        None => {
+            tracing::warn!("Shedding a request to {}: at its concurrency limit", service);
+            axum::response::IntoResponse::into_response(ApiError::Overloaded(format!("The {} service is overloaded; retry shortly", service)))
+        }
+    }
+}
+
+async fn get_concurrency_limits(
+    Extension(state): Extension<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<LimiterStats>>> {
+    state.authorize(&headers, Role::Admin)?;
+    Ok(Json(state.load_shedder.stats()))
+}
+
+/// Open the span a request is handled in, so provider calls and background
+/// jobs it starts can be traced back to its `X-Request-Id`
+fn request_span<B>(request: &axum::http::Request<B>) -> tracing::Span {
+    let request_id = request.headers().get("x-request-id").and_then(|value| value.to_str().ok()).unwrap_or("");
+    tracing::info_span!("request", method = %request.method(), path = request.uri().path(), request_id)
 }
 
 async fn health_check() -> &'static str {
     "OK"
 }
 
+async fn openapi_spec() -> Json<serde_json::Value> {
+    Json(openapi::spec())
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Initialize tracing
@@ -307,34 +3095,308 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     // Create application state
-    let state = Arc::new(AppState::new());
+    let state = Arc::new(AppState::new()?);
+    tokio::spawn(watch_config(state.clone()));
+    tokio::spawn(reconcile_pending_balances(state.clone()));
+    tokio::spawn(run_schedules(state.clone()));
+    tokio::spawn(run_compounding(state.clone()));
 
     // Build our application with routes
     let app = Router::new()
         .route("/health", get(health_check))
+        .route("/openapi.json", get(openapi_spec))
         // Wallet routes
         .route("/wallets", get(get_all_wallets))
         .route("/wallets", post(create_wallet))
-        .route("/wallets/:id", get(get_wallet))
+        .route("/wallets/:id", get(get_wallet).patch(update_wallet))
+        .route("/wallets/:id/notes/:address", put(set_account_note))
+        .route("/wallets/:id/deposit-addresses", get(get_deposit_addresses).post(assign_deposit_address))
+        .route("/deposit-addresses/:key_type/:address", get(attribute_deposit))
+        .route("/deposit-addresses/payments", post(record_incoming_payment))
+        .route("/wallets/:id/invoices", get(get_payment_requests).post(create_payment_request))
+        .route("/invoices/:id", get(get_payment_request))
+        .route("/invoices/:id/events", get(stream_payment_request))
         .route("/wallets/import", post(import_wallet))
         .route("/wallets/derive-address", post(derive_address))
+        .route("/wallets/restore", post(restore_backup))
+        .route("/wallets/:id/backup", post(export_backup))
+        .route("/wallets/:id/mev-protection", get(get_mev_protection).put(set_mev_protection).delete(delete_mev_protection))
+        // Key share routes
+        .route("/shares", post(escrow_key_share))
+        .route("/shares/:share_id/nonce", post(start_co_sign))
+        .route("/shares/:share_id/sign", post(co_sign))
+        .route("/shares/:share_id/recover", post(recover_key_shares))
+        // Address routes
+        .route("/addresses/:key_type/:address/balances", get(get_balances))
+        .route("/addresses/:key_type/:address/transactions", get(get_transaction_history))
+        .route("/addresses/:key_type/:address/cleanup", get(get_cleanup_plan))
         // Transaction routes
         .route("/transactions", post(send_transaction))
+        .route("/transactions/batch", post(build_batch))
         .route("/transactions/:key_type/:hash", get(get_transaction))
+        .route("/travel-rule/transfers/:id", get(get_travel_rule_transfer))
+        .route("/travel-rule/transfers/:id/release", post(release_travel_rule_transfer))
+        .route("/travel-rule/callbacks/:id", post(travel_rule_callback))
+        // Compliance routes
+        .route("/compliance/reviews", get(get_screening_reviews))
+        // Spam and fee budget routes
+        .route("/spam/overrides", get(get_spam_overrides))
+        .route("/spam/overrides/:key_type/:address", put(set_spam_override).delete(delete_spam_override))
+        .route("/fee-budget", get(get_fee_budget).put(set_fee_budget).delete(delete_fee_budget))
+        // Scheduled order routes
+        .route("/schedules", get(get_schedules).post(create_schedule))
+        .route("/schedules/:id", get(get_schedule).delete(delete_schedule))
+        .route("/schedules/:id/pause", post(pause_schedule))
+        .route("/schedules/:id/resume", post(resume_schedule))
+        .route("/schedules/:id/runs", get(get_schedule_runs))
+        // Fee payer routes
+        .route("/fee-payer", get(get_fee_payer))
+        .route("/fee-payer/sponsor", post(sponsor_transaction))
+        .route("/fee-payer/submit", post(submit_sponsored_transaction))
+        // Lightning routes
+        .route("/lightning/decode", post(decode_invoice))
+        .route("/lightning/invoices", post(create_invoice))
+        .route("/lightning/payments", post(pay_invoice))
+        .route("/lightning/payments/:payment_hash", get(get_lightning_payment))
+        // XRP Ledger routes
+        .route("/xrp/accounts/:address/balance", get(get_xrp_balance))
+        // Cosmos routes
+        .route("/cosmos/delegations/:address/alerts", get(get_validator_alerts))
+        // Price routes
+        .route("/prices/:asset/history", get(get_price_history))
+        .route("/prices/:asset/backfill", post(backfill_prices))
+        .route("/fiat/rates", get(get_fiat_rates))
+        .route("/display-currency", get(get_display_currency).put(set_display_currency))
+        // Exchange routes
+        .route("/exchange-connections", get(get_exchange_connections).post(link_exchange))
+        .route("/exchange-connections/balances", get(get_exchange_balances))
+        .route("/exchange-connections/:id", delete(unlink_exchange))
+        .route("/exchange-connections/:id/trades", get(get_exchange_trades))
+        // Admin routes
+        .route("/admin/config", get(get_effective_config))
+        .route("/admin/config/reload", post(reload_config))
+        .route("/admin/limits", get(get_concurrency_limits))
+        // Audit routes
+        .route("/audit/export", get(export_audit_log))
+        .route("/audit/verify", get(verify_audit_log))
+        // Export routes
+        .route("/exports", post(create_export))
+        .route("/exports/:id", get(get_export))
+        .route("/exports/:id/download", get(download_export))
         // DeFi routes
         .route("/defi/tokens/:key_type", get(get_supported_tokens))
         .route("/defi/swap", post(swap_tokens))
+        .route("/defi/swap/quote", post(quote_swap))
         .route("/defi/lending", post(execute_lending))
         .route("/defi/staking", post(execute_staking))
-        .layer(Extension(state));
+        .route("/defi/risk", get(get_protocol_risk))
+        .route("/defi/risk/profiles", put(set_protocol_risk_profile))
+        .route("/defi/risk/exploits", post(report_exploit))
+        .route("/defi/risk/resume", post(resume_protocol_deposits))
+        .route("/defi/risk/refresh", post(refresh_protocol_risk))
+        .route("/defi/positions", get(get_positions).post(track_position))
+        .route("/defi/positions/:id", delete(untrack_position))
+        .route("/defi/exits", get(get_exits).post(panic_exit))
+        .route("/defi/exits/:id", get(get_exit))
+        .route("/defi/exits/:id/retry", post(retry_exit))
+        .route("/defi/compound", get(get_compound_positions).post(add_compound_position))
+        .route("/defi/compound/:id", get(get_compound_position).delete(remove_compound_position))
+        .route("/defi/compound/:id/pause", post(pause_compound_position))
+        .route("/defi/compound/:id/resume", post(resume_compound_position))
+        .route("/defi/compound/:id/runs", get(get_compound_runs))
+        .route("/defi/analytics", get(get_earn_analytics))
+        .layer(axum::middleware::from_fn(shed_load))
+        .layer(Extension(state))
+        .layer(tower_http::trace::TraceLayer::new_for_http().make_span_with(request_span));
 
     // Run the server
     let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
     tracing::info!("Listening on {}", addr);
     // let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::Server::bind(&addr)
+    let draining = Arc::new(tokio::sync::Notify::new());
+    let server = axum::Server::bind(&addr)
         .serve(app.into_make_service())
-        .await?;
+        .with_graceful_shutdown({
+            let draining = draining.clone();
+            async move {
+                shutdown_signal().await;
+                draining.notify_one();
+            }
+        });
+    tokio::pin!(server);
+
+    // Once a shutdown signal arrives the server stops accepting connections;
+    // in-flight requests get until the drain deadline to complete
+    tokio::select! {
+        result = &mut server => result?,
+        _ = draining.notified() => {
+            let deadline = drain_timeout();
+            tracing::info!("Shutting down; draining in-flight requests for up to {:?}", deadline);
+            match tokio::time::timeout(deadline, &mut server).await {
+                Ok(result) => result?,
+                Err(_) => tracing::warn!("Drain deadline elapsed with requests still in flight"),
+            }
+        }
+    }
 
+    tracing::info!("Shutdown complete");
     Ok(())
 }
+
+/// Drop pending balance changes of settled transactions
+///
+/// Each chain with pending changes is checked once per interval, however
+/// often balances are queried.
+async fn reconcile_pending_balances(state: Arc<AppState>) {
+    let mut poll = tokio::time::interval(std::time::Duration::from_secs(PENDING_BALANCE_RECONCILE_INTERVAL));
+    loop {
+        poll.tick().await;
+
+        let key_types: std::collections::HashSet<KeyType> = state.pending_balances.deltas().iter()
+            .map(|delta| delta.key_type)
+            .collect();
+        for key_type in key_types {
+            let task_state = state.clone();
+            let reconciled = blocking(move || {
+                let provider = ProviderFactory::create_provider(key_type, task_state.provider_config())?;
+                task_state.pending_balances.reconcile(key_type, provider.as_ref(), unix_timestamp()?, PENDING_BALANCE_MAX_AGE)?;
+                Ok(())
+            }).await;
+            if let Err(e) = reconciled {
+                tracing::warn!("Failed to reconcile pending {:?} balances: {}", key_type, e);
+            }
+        }
+    }
+}
+
+/// Run due scheduled orders and announce upcoming ones
+///
+/// Runs are recorded in the audit log under the `scheduler` actor.
+async fn run_schedules(state: Arc<AppState>) {
+    let mut poll = tokio::time::interval(std::time::Duration::from_secs(SCHEDULE_POLL_INTERVAL));
+    loop {
+        poll.tick().await;
+
+        let task_state = state.clone();
+        let ran = blocking(move || {
+            let scheduler = &task_state.scheduler;
+            let now = unix_timestamp()?;
+            scheduler.notify_upcoming(now, SCHEDULE_NOTICE, &LogScheduleNotifier)?;
+            let records = scheduler.run_due(now, &ScheduledOrderExecutor { state: &task_state }, &LogScheduleNotifier)?;
+            for record in &records {
+                let event = AuditEvent::new("scheduler", "schedule.run", &record.order_id)
+                    .map(|event| event.with_change(None, serde_json::to_value(record).ok()));
+                if let Err(e) = event.and_then(|event| task_state.audit_log.record(event)) {
+                    tracing::error!("Failed to record the run of scheduled order {}: {}", record.order_id, e);
+                }
+            }
+            Ok(())
+        }).await;
+        if let Err(e) = ran {
+            tracing::error!("Failed to run scheduled orders: {}", e);
+        }
+    }
+}
+
+/// Compound the staking positions that are due
+///
+/// Runs are recorded in the audit log under the `scheduler` actor.
+async fn run_compounding(state: Arc<AppState>) {
+    let mut poll = tokio::time::interval(std::time::Duration::from_secs(SCHEDULE_POLL_INTERVAL));
+    loop {
+        poll.tick().await;
+
+        let task_state = state.clone();
+        let ran = blocking(move || {
+            let config = task_state.provider_config();
+            let executor = GuardedCompoundExecutor {
+                protocol_risk: &task_state.protocol_risk,
+                pipeline: PipelineCompoundExecutor::new(&config),
+            };
+            for run in task_state.compounder.run_due(unix_timestamp()?, &executor, &LogScheduleNotifier) {
+                let event = AuditEvent::new("scheduler", "compound.run", &run.position_id)
+                    .map(|event| event.with_change(None, serde_json::to_value(&run).ok()));
+                if let Err(e) = event.and_then(|event| task_state.audit_log.record(event)) {
+                    tracing::error!("Failed to record the compounding run of {}: {}", run.position_id, e);
+                }
+            }
+            Ok(())
+        }).await;
+        if let Err(e) = ran {
+            tracing::error!("Failed to compound staking positions: {}", e);
+        }
+    }
+}
+
+/// Reload the configuration when its file changes or, on Unix, on SIGHUP
+async fn watch_config(state: Arc<AppState>) {
+    #[cfg(unix)]
+    let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(signal) => Some(signal),
+        Err(e) => {
+            tracing::error!("Failed to listen for SIGHUP: {}", e);
+            None
+        }
+    };
+
+    let mut poll = tokio::time::interval(std::time::Duration::from_secs(5));
+    loop {
+        #[cfg(unix)]
+        let forced = async {
+            match hangup.as_mut() {
+                Some(signal) => { signal.recv().await; }
+                None => std::future::pending::<()>().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let forced = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = forced => {}
+            _ = poll.tick() => {
+                if !state.config.file_changed() {
+                    continue;
+                }
+            }
+        }
+        if let Err(e) = state.reload_config() {
+            tracing::error!("Keeping the current configuration; the new one is invalid: {}", e);
+        }
+    }
+}
+
+/// Wait for Ctrl-C or, on Unix, SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => { signal.recv().await; }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Read the drain deadline from `FO3_SHUTDOWN_TIMEOUT` (seconds, default 30)
+fn drain_timeout() -> std::time::Duration {
+    let seconds = std::env::var("FO3_SHUTDOWN_TIMEOUT").ok()
+        .and_then(|seconds| seconds.parse().ok())
+        .unwrap_or(30);
+    std::time::Duration::from_secs(seconds)
+}
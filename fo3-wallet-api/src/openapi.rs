@@ -0,0 +1,1387 @@
+//! OpenAPI description of the REST API
+//!
+//! The spec is generated from the operation table below, which lists the
+//! routes mounted in `main`. A test checks the two stay in sync.
+
+use serde_json::{json, Map, Value};
+
+/// A documented API operation
+struct Operation {
+    /// HTTP method
+    method: &'static str,
+    /// Route path in axum syntax (`:param`)
+    path: &'static str,
+    /// Operation tag
+    tag: &'static str,
+    /// One-line summary
+    summary: &'static str,
+    /// Request body schema, if any
+    request: Option<&'static str>,
+    /// Success status code
+    status: u16,
+    /// Response body schema
+    response: &'static str,
+    /// Query parameters
+    query: &'static [&'static str],
+}
+
+const OPERATIONS: &[Operation] = &[
+    Operation { method: "get", path: "/health", tag: "system", summary: "Health check", request: None, status: 200, response: "Text", query: &[] },
+    Operation { method: "get", path: "/openapi.json", tag: "system", summary: "Get this OpenAPI document", request: None, status: 200, response: "OpenApi", query: &[] },
+    Operation { method: "get", path: "/wallets", tag: "wallets", summary: "List wallets with their labels, hiding archived ones", request: None, status: 200, response: "WalletList", query: &["include_archived"] },
+    Operation { method: "post", path: "/wallets", tag: "wallets", summary: "Create a wallet", request: Some("CreateWalletRequest"), status: 201, response: "WalletResponse", query: &[] },
+    Operation { method: "get", path: "/wallets/:id", tag: "wallets", summary: "Get a wallet", request: None, status: 200, response: "WalletResponse", query: &[] },
+    Operation { method: "patch", path: "/wallets/:id", tag: "wallets", summary: "Rename a wallet or change its color, icon or archived flag", request: Some("UpdateWalletRequest"), status: 200, response: "WalletSummary", query: &[] },
+    Operation { method: "put", path: "/wallets/:id/notes/:address", tag: "wallets", summary: "Set the note on one of the wallet's accounts (a blank note removes it)", request: Some("AccountNoteRequest"), status: 204, response: "Empty", query: &[] },
+    Operation { method: "get", path: "/wallets/:id/deposit-addresses", tag: "deposits", summary: "List the deposit addresses assigned from a wallet", request: None, status: 200, response: "DepositAddressList", query: &[] },
+    Operation { method: "post", path: "/wallets/:id/deposit-addresses", tag: "deposits", summary: "Get the deposit address of an invoice or customer, assigning the next one on first use", request: Some("DepositRequest"), status: 200, response: "DepositAddress", query: &[] },
+    Operation { method: "get", path: "/deposit-addresses/:key_type/:address", tag: "deposits", summary: "Find the invoice or customer an incoming payment belongs to", request: None, status: 200, response: "DepositAddress", query: &["destination_tag", "memo"] },
+    Operation { method: "post", path: "/deposit-addresses/payments", tag: "deposits", summary: "Record an incoming payment seen by the chain watcher against its payment request (watcher role)", request: Some("IncomingPayment"), status: 200, response: "PaymentRequest", query: &[] },
+    Operation { method: "get", path: "/wallets/:id/invoices", tag: "invoices", summary: "List a wallet's payment requests, newest first", request: None, status: 200, response: "PaymentRequestList", query: &[] },
+    Operation { method: "post", path: "/wallets/:id/invoices", tag: "invoices", summary: "Request a payment into a fresh deposit address, with a wallet URI to show as a QR code", request: Some("CreatePaymentRequest"), status: 201, response: "PaymentRequest", query: &[] },
+    Operation { method: "get", path: "/invoices/:id", tag: "invoices", summary: "Get a payment request and the payments received", request: None, status: 200, response: "PaymentRequest", query: &[] },
+    Operation { method: "get", path: "/invoices/:id/events", tag: "invoices", summary: "Stream a payment request's status until it is paid or expired", request: None, status: 200, response: "PaymentRequestEvents", query: &[] },
+    Operation { method: "post", path: "/wallets/import", tag: "wallets", summary: "Import a wallet from a mnemonic", request: Some("ImportWalletRequest"), status: 201, response: "WalletResponse", query: &[] },
+    Operation { method: "get", path: "/wallets/:id/mev-protection", tag: "wallets", summary: "Get where the wallet's EVM transactions are submitted", request: None, status: 200, response: "MevProtection", query: &[] },
+    Operation { method: "put", path: "/wallets/:id/mev-protection", tag: "wallets", summary: "Submit the wallet's EVM transactions through a private relay, or the public mempool", request: Some("MevProtection"), status: 204, response: "Empty", query: &[] },
+    Operation { method: "delete", path: "/wallets/:id/mev-protection", tag: "wallets", summary: "Return to the deployment's default MEV protection", request: None, status: 204, response: "Empty", query: &[] },
+    Operation { method: "post", path: "/wallets/restore", tag: "wallets", summary: "Restore a wallet from an encrypted backup bundle", request: Some("RestoreBackupRequest"), status: 201, response: "WalletResponse", query: &[] },
+    Operation { method: "post", path: "/wallets/:id/backup", tag: "wallets", summary: "Export a wallet as an encrypted backup bundle (admin role)", request: Some("ExportBackupRequest"), status: 200, response: "BackupBundle", query: &[] },
+    Operation { method: "post", path: "/wallets/derive-address", tag: "wallets", summary: "Derive an address", request: Some("DeriveAddressRequest"), status: 200, response: "AddressResponse", query: &[] },
+    Operation { method: "post", path: "/shares", tag: "shares", summary: "Escrow the server shares of a key split on the client, returning its access and recovery tokens", request: Some("EscrowKeyShareRequest"), status: 201, response: "EscrowReceipt", query: &[] },
+    Operation { method: "post", path: "/shares/:share_id/nonce", tag: "shares", summary: "Start a two-party signature, returning the server's nonce (bearer access token)", request: None, status: 200, response: "SigningNonce", query: &[] },
+    Operation { method: "post", path: "/shares/:share_id/sign", tag: "shares", summary: "Complete a two-party signature from the device's partial signature (bearer access token)", request: Some("CoSignRequest"), status: 200, response: "CoSignResponse", query: &[] },
+    Operation { method: "post", path: "/shares/:share_id/recover", tag: "shares", summary: "Pair the server share with a new device share derived from the recovery share, replacing the access token (bearer recovery token)", request: None, status: 200, response: "Recovery", query: &[] },
+    Operation { method: "get", path: "/addresses/:key_type/:address/balances", tag: "addresses", summary: "Get token balances of an address (every token held on Solana)", request: None, status: 200, response: "AdjustedBalanceList", query: &["include_pending", "include_spam", "currency"] },
+    Operation { method: "get", path: "/addresses/:key_type/:address/transactions", tag: "addresses", summary: "Get the transaction history of an address", request: None, status: 200, response: "TransactionPage", query: &["limit", "cursor"] },
+    Operation { method: "get", path: "/addresses/:key_type/:address/cleanup", tag: "addresses", summary: "Plan closing empty Solana token accounts, or consolidating small Bitcoin UTXOs", request: None, status: 200, response: "CleanupPlan", query: &[] },
+    Operation { method: "post", path: "/transactions", tag: "transactions", summary: "Sign and send a transaction, through a private relay if the wallet's MEV protection asks for it; withdrawals held for the travel rule return 202 with the transfer", request: Some("SendTransactionRequest"), status: 200, response: "TransactionResponse", query: &["wallet_id"] },
+    Operation { method: "post", path: "/transactions/batch", tag: "transactions", summary: "Combine several intents into one unsigned transaction", request: Some("BatchRequest"), status: 200, response: "Batch", query: &[] },
+    Operation { method: "get", path: "/transactions/:key_type/:hash", tag: "transactions", summary: "Get a transaction", request: None, status: 200, response: "Transaction", query: &[] },
+    Operation { method: "get", path: "/travel-rule/transfers/:id", tag: "travel-rule", summary: "Get a travel rule transfer, polling the beneficiary VASP while it is held", request: None, status: 200, response: "TravelRuleTransfer", query: &[] },
+    Operation { method: "post", path: "/travel-rule/transfers/:id/release", tag: "travel-rule", summary: "Send the transaction of a transfer the beneficiary VASP accepted (compliance role)", request: None, status: 200, response: "TransactionResponse", query: &["wallet_id"] },
+    Operation { method: "post", path: "/travel-rule/callbacks/:id", tag: "travel-rule", summary: "TRP callback on which the beneficiary VASP approves or rejects a transfer", request: Some("TrpCallback"), status: 204, response: "Empty", query: &[] },
+    Operation { method: "post", path: "/exports", tag: "exports", summary: "Start an activity export", request: Some("CreateExportRequest"), status: 202, response: "ExportJob", query: &[] },
+    Operation { method: "get", path: "/exports/:id", tag: "exports", summary: "Get an export job", request: None, status: 200, response: "ExportJob", query: &[] },
+    Operation { method: "get", path: "/exports/:id/download", tag: "exports", summary: "Download a completed export", request: None, status: 200, response: "ExportFile", query: &["token"] },
+    Operation { method: "get", path: "/spam/overrides", tag: "spam", summary: "List the caller's spam overrides (API key)", request: None, status: 200, response: "TokenOverrideList", query: &[] },
+    Operation { method: "put", path: "/spam/overrides/:key_type/:address", tag: "spam", summary: "Always show or always hide a token for the caller (API key)", request: Some("SpamOverrideRequest"), status: 204, response: "Empty", query: &[] },
+    Operation { method: "delete", path: "/spam/overrides/:key_type/:address", tag: "spam", summary: "Remove the caller's override of a token (API key)", request: None, status: 204, response: "Empty", query: &[] },
+    Operation { method: "get", path: "/fee-budget", tag: "fee-budget", summary: "Get the fee limits applied to the caller's transactions (API key)", request: None, status: 200, response: "FeeBudget", query: &[] },
+    Operation { method: "put", path: "/fee-budget", tag: "fee-budget", summary: "Cap the caller's transaction fees absolutely per chain and as a share of the value sent (API key)", request: Some("FeeBudget"), status: 204, response: "Empty", query: &[] },
+    Operation { method: "delete", path: "/fee-budget", tag: "fee-budget", summary: "Return to the deployment's default fee limits (API key)", request: None, status: 204, response: "Empty", query: &[] },
+    Operation { method: "get", path: "/schedules", tag: "schedules", summary: "List the caller's scheduled transfers and DCA swaps, oldest first (API key)", request: None, status: 200, response: "ScheduledOrderList", query: &[] },
+    Operation { method: "post", path: "/schedules", tag: "schedules", summary: "Schedule a recurring transfer or DCA swap; each run passes the fee budget, screening and travel rule checks (API key)", request: Some("CreateScheduleRequest"), status: 201, response: "ScheduledOrder", query: &[] },
+    Operation { method: "get", path: "/schedules/:id", tag: "schedules", summary: "Get a scheduled order of the caller (API key)", request: None, status: 200, response: "ScheduledOrder", query: &[] },
+    Operation { method: "delete", path: "/schedules/:id", tag: "schedules", summary: "Cancel a scheduled order (API key)", request: None, status: 204, response: "Empty", query: &[] },
+    Operation { method: "post", path: "/schedules/:id/pause", tag: "schedules", summary: "Pause a scheduled order (API key)", request: None, status: 200, response: "ScheduledOrder", query: &[] },
+    Operation { method: "post", path: "/schedules/:id/resume", tag: "schedules", summary: "Resume a scheduled order, skipping runs missed while it was paused (API key)", request: None, status: 200, response: "ScheduledOrder", query: &[] },
+    Operation { method: "get", path: "/schedules/:id/runs", tag: "schedules", summary: "Get the runs of a scheduled order from the last 30 days, oldest first (API key)", request: None, status: 200, response: "RunRecordList", query: &[] },
+    Operation { method: "get", path: "/fee-payer", tag: "fee-payer", summary: "Get the Solana fee payer, its sponsored usage today and optionally a signing key's", request: None, status: 200, response: "FeePayer", query: &["signer"] },
+    Operation { method: "post", path: "/fee-payer/sponsor", tag: "fee-payer", summary: "Compile Solana instructions with the fee payer, returning the transaction for the caller to sign (API key)", request: Some("SponsorRequest"), status: 200, response: "SponsoredTransaction", query: &[] },
+    Operation { method: "post", path: "/fee-payer/submit", tag: "fee-payer", summary: "Add the fee payer's signature to a signed sponsored transaction and broadcast it, counting it against the quotas (API key)", request: Some("SponsoredTransaction"), status: 200, response: "SubmitSponsoredResponse", query: &[] },
+    Operation { method: "post", path: "/lightning/decode", tag: "lightning", summary: "Decode a BOLT-11 invoice", request: Some("DecodeInvoiceRequest"), status: 200, response: "Invoice", query: &[] },
+    Operation { method: "post", path: "/lightning/invoices", tag: "lightning", summary: "Create an invoice to receive a Lightning payment", request: Some("CreateInvoiceRequest"), status: 200, response: "Invoice", query: &[] },
+    Operation { method: "post", path: "/lightning/payments", tag: "lightning", summary: "Pay a BOLT-11 invoice (admin role)", request: Some("PayInvoiceRequest"), status: 200, response: "LightningPayment", query: &[] },
+    Operation { method: "get", path: "/lightning/payments/:payment_hash", tag: "lightning", summary: "Get the status of a Lightning payment as a transaction", request: None, status: 200, response: "Transaction", query: &["direction"] },
+    Operation { method: "get", path: "/cosmos/delegations/:address/alerts", tag: "cosmos", summary: "Check a delegator's validators, returning those that degraded since the last check with a redelegation message", request: None, status: 200, response: "ValidatorAlertList", query: &[] },
+    Operation { method: "get", path: "/xrp/accounts/:address/balance", tag: "xrp", summary: "Get an XRP account's balance net of its reserve", request: None, status: 200, response: "XrpBalance", query: &[] },
+    Operation { method: "get", path: "/prices/:asset/history", tag: "prices", summary: "Get OHLCV candles of an asset, downsampled to the interval and point count", request: None, status: 200, response: "CandleList", query: &["interval", "from", "to", "max_points", "currency"] },
+    Operation { method: "post", path: "/prices/:asset/backfill", tag: "prices", summary: "Fetch missing candles of an asset from the upstream source in the background", request: Some("BackfillRequest"), status: 202, response: "Empty", query: &[] },
+    Operation { method: "get", path: "/fiat/rates", tag: "prices", summary: "Get today's exchange rates of the configured source", request: None, status: 200, response: "FiatRates", query: &[] },
+    Operation { method: "get", path: "/display-currency", tag: "prices", summary: "Get the currency the caller's balances and prices are shown in (API key)", request: None, status: 200, response: "DisplayCurrency", query: &[] },
+    Operation { method: "put", path: "/display-currency", tag: "prices", summary: "Show the caller's balances, prices and exports in a fiat currency (API key)", request: Some("DisplayCurrency"), status: 204, response: "Empty", query: &[] },
+    Operation { method: "get", path: "/exchange-connections", tag: "exchanges", summary: "List the caller's linked exchange accounts (API key)", request: None, status: 200, response: "ExchangeConnectionList", query: &[] },
+    Operation { method: "post", path: "/exchange-connections", tag: "exchanges", summary: "Link an exchange account with a read-only exchange API key (API key)", request: Some("LinkExchangeRequest"), status: 201, response: "ExchangeConnection", query: &[] },
+    Operation { method: "delete", path: "/exchange-connections/:id", tag: "exchanges", summary: "Unlink an exchange account and delete its exchange API key (API key)", request: None, status: 204, response: "Empty", query: &[] },
+    Operation { method: "get", path: "/exchange-connections/balances", tag: "exchanges", summary: "Get the caller's exchange balances summed per asset (API key)", request: None, status: 200, response: "ExchangeBalances", query: &["currency"] },
+    Operation { method: "get", path: "/exchange-connections/:id/trades", tag: "exchanges", summary: "Get trades filled on an exchange account (API key)", request: None, status: 200, response: "ExchangeTradeList", query: &["since", "markets"] },
+    Operation { method: "post", path: "/defi/swap/quote", tag: "defi", summary: "Quote a swap with price impact, route and fees; the quote is kept on the server under its id", request: Some("SwapRequest"), status: 200, response: "SwapQuote", query: &["owner", "approval", "smart_account"] },
+    Operation { method: "post", path: "/defi/swap", tag: "defi", summary: "Execute a swap against a quote issued by this server, or preview it with dry_run", request: Some("ExecuteSwapRequest"), status: 200, response: "SwapResult", query: &[] },
+    Operation { method: "get", path: "/defi/tokens/:key_type", tag: "defi", summary: "List the tokens supported on a chain", request: None, status: 200, response: "TokenList", query: &[] },
+    Operation { method: "post", path: "/defi/lending", tag: "defi", summary: "Supply, withdraw, borrow or repay on a lending market, or preview it with dry_run; supplies are refused while the protocol's deposits are paused", request: Some("LendingRequest"), status: 200, response: "DefiResult", query: &[] },
+    Operation { method: "post", path: "/defi/staking", tag: "defi", summary: "Stake, unstake or claim rewards, or preview it with dry_run; stakes are refused while the protocol's deposits are paused", request: Some("ExecuteStakingRequest"), status: 200, response: "DefiResult", query: &[] },
+    Operation { method: "get", path: "/defi/risk", tag: "defi-risk", summary: "Get the risk assessment of every tracked protocol", request: None, status: 200, response: "RiskAssessmentList", query: &[] },
+    Operation { method: "put", path: "/defi/risk/profiles", tag: "defi-risk", summary: "Set a protocol's audit status, oracle dependencies, exploits and TVL history (admin role)", request: Some("ProtocolRiskProfile"), status: 200, response: "RiskAssessment", query: &[] },
+    Operation { method: "post", path: "/defi/risk/exploits", tag: "defi-risk", summary: "Report an exploit, pausing deposits into the protocol if it is unresolved (admin role)", request: Some("ExploitReport"), status: 200, response: "RiskEventList", query: &[] },
+    Operation { method: "post", path: "/defi/risk/resume", tag: "defi-risk", summary: "Resume deposits into a paused protocol (admin role)", request: Some("ResumeDepositsRequest"), status: 204, response: "Empty", query: &[] },
+    Operation { method: "post", path: "/defi/risk/refresh", tag: "defi-risk", summary: "Refresh exploits and TVL of every tracked protocol from DefiLlama in the background (admin role)", request: None, status: 202, response: "Empty", query: &[] },
+    Operation { method: "get", path: "/defi/positions", tag: "defi-exits", summary: "List the caller's tracked positions (API key)", request: None, status: 200, response: "EarnPositionList", query: &[] },
+    Operation { method: "post", path: "/defi/positions", tag: "defi-exits", summary: "Track a supply, borrow or stake position for emergency exits (API key)", request: Some("TrackPositionRequest"), status: 201, response: "EarnPosition", query: &[] },
+    Operation { method: "delete", path: "/defi/positions/:id", tag: "defi-exits", summary: "Stop tracking a position (API key)", request: None, status: 204, response: "Empty", query: &[] },
+    Operation { method: "get", path: "/defi/exits", tag: "defi-exits", summary: "List the caller's exit reports (API key)", request: None, status: 200, response: "ExitReportList", query: &[] },
+    Operation { method: "post", path: "/defi/exits", tag: "defi-exits", summary: "Panic: unwind every tracked position of the caller (API key)", request: None, status: 200, response: "ExitReport", query: &[] },
+    Operation { method: "get", path: "/defi/exits/:id", tag: "defi-exits", summary: "Get an exit report (API key)", request: None, status: 200, response: "ExitReport", query: &[] },
+    Operation { method: "post", path: "/defi/exits/:id/retry", tag: "defi-exits", summary: "Retry the failed and skipped steps of an exit (API key)", request: None, status: 200, response: "ExitReport", query: &[] },
+    Operation { method: "get", path: "/defi/compound", tag: "defi-compound", summary: "List the caller's auto-compounded staking positions (API key)", request: None, status: 200, response: "CompoundPositionList", query: &[] },
+    Operation { method: "post", path: "/defi/compound", tag: "defi-compound", summary: "Claim and restake a Lido or Marinade position's rewards on a schedule (API key)", request: Some("CompoundRequest"), status: 201, response: "CompoundPosition", query: &[] },
+    Operation { method: "get", path: "/defi/compound/:id", tag: "defi-compound", summary: "Get an auto-compounded position of the caller (API key)", request: None, status: 200, response: "CompoundPosition", query: &[] },
+    Operation { method: "delete", path: "/defi/compound/:id", tag: "defi-compound", summary: "Stop compounding a position; its compounded yield is kept (API key)", request: None, status: 204, response: "Empty", query: &[] },
+    Operation { method: "post", path: "/defi/compound/:id/pause", tag: "defi-compound", summary: "Pause compounding a position (API key)", request: None, status: 200, response: "CompoundPosition", query: &[] },
+    Operation { method: "post", path: "/defi/compound/:id/resume", tag: "defi-compound", summary: "Resume compounding a position, skipping runs missed while it was paused (API key)", request: None, status: 200, response: "CompoundPosition", query: &[] },
+    Operation { method: "get", path: "/defi/compound/:id/runs", tag: "defi-compound", summary: "Get the compounding runs of a position, oldest first (API key)", request: None, status: 200, response: "CompoundRunList", query: &[] },
+    Operation { method: "get", path: "/defi/analytics", tag: "defi-compound", summary: "Get the caller's Earn analytics: compounding positions and compounded yield per token (API key)", request: None, status: 200, response: "EarnAnalytics", query: &[] },
+    Operation { method: "get", path: "/admin/config", tag: "admin", summary: "Get the running configuration, with URL credentials masked (admin role)", request: None, status: 200, response: "ApiConfig", query: &[] },
+    Operation { method: "post", path: "/admin/config/reload", tag: "admin", summary: "Re-read the config file and environment, keeping the running configuration if the new one is invalid (admin role)", request: None, status: 200, response: "ApiConfig", query: &[] },
+    Operation { method: "get", path: "/admin/limits", tag: "admin", summary: "Get each service's adaptive concurrency limit, requests in flight and shed counts (admin role)", request: None, status: 200, response: "LimiterStatsList", query: &[] },
+    Operation { method: "get", path: "/compliance/reviews", tag: "compliance", summary: "List flagged and blocked screening decisions awaiting review, oldest first (compliance role)", request: None, status: 200, response: "ScreeningRecordPage", query: &["limit", "cursor"] },
+    Operation { method: "get", path: "/audit/export", tag: "audit", summary: "Export the audit log as JSON lines (auditor role)", request: None, status: 200, response: "AuditExport", query: &[] },
+    Operation { method: "get", path: "/audit/verify", tag: "audit", summary: "Verify the audit log hash chain (auditor role)", request: None, status: 200, response: "AuditVerification", query: &[] },
+];
+
+/// Generate the OpenAPI 3.0 document
+pub fn spec() -> Value {
+    let mut paths = Map::new();
+
+    for operation in OPERATIONS {
+        let mut parameters: Vec<Value> = operation.path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix(':'))
+            .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": path_schema(name) }))
+            .collect();
+        parameters.extend(operation.query.iter().map(|name| query_parameter(name)));
+
+        let mut responses = Map::new();
+        responses.insert(operation.status.to_string(), json!({
+            "description": "Success",
+            "content": response_content(operation.response),
+        }));
+        responses.insert("default".to_string(), json!({
+            "description": "Error",
+            "content": { "application/json": { "schema": schema_ref("Error") } },
+        }));
+
+        let mut item = json!({
+            "tags": [operation.tag],
+            "summary": operation.summary,
+            "parameters": parameters,
+            "responses": responses,
+        });
+        if let Some(request) = operation.request {
+            item["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": schema_ref(request) } },
+            });
+        }
+
+        let path = openapi_path(operation.path);
+        let entry = paths.entry(path).or_insert_with(|| json!({}));
+        entry[operation.method] = item;
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "FO3 Wallet API",
+            "description": "REST API for the FO3 multi-chain wallet and DeFi SDK",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": { "schemas": schemas() },
+    })
+}
+
+/// Convert an axum route path to OpenAPI syntax
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => format!("{{{}}}", name),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn path_schema(name: &str) -> Value {
+    match name {
+        "key_type" => schema_ref("KeyType"),
+        _ => json!({ "type": "string" }),
+    }
+}
+
+fn query_parameter(name: &str) -> Value {
+    match name {
+        "token" => json!({ "name": name, "in": "query", "required": true, "schema": { "type": "string" } }),
+        "include_pending" => json!({ "name": name, "in": "query", "required": false, "schema": { "type": "boolean", "default": true } }),
+        "include_archived" => json!({ "name": name, "in": "query", "required": false, "schema": { "type": "boolean", "default": false } }),
+        "include_spam" => json!({ "name": name, "in": "query", "required": false, "schema": { "type": "boolean", "default": false } }),
+        "direction" => json!({ "name": name, "in": "query", "required": false, "schema": { "type": "string", "enum": ["outgoing", "incoming"], "default": "outgoing" } }),
+        "interval" => json!({ "name": name, "in": "query", "required": true, "schema": schema_ref("Interval") }),
+        "from" | "to" => json!({ "name": name, "in": "query", "required": true, "schema": { "type": "integer", "minimum": 0 } }),
+        "currency" => json!({ "name": name, "in": "query", "required": false, "schema": { "type": "string", "description": "ISO 4217 code, defaulting to the caller's display currency" } }),
+        "markets" => json!({ "name": name, "in": "query", "required": false, "schema": { "type": "string", "description": "Comma-separated markets, required by Binance (e.g. BTCUSDT,ETHUSDT)" } }),
+        "approval" => json!({ "name": name, "in": "query", "required": false, "schema": { "type": "string", "enum": ["exact", "permit2"], "default": "exact" } }),
+        "cursor" | "wallet_id" | "memo" | "signer" | "owner" | "smart_account" => json!({ "name": name, "in": "query", "required": false, "schema": { "type": "string" } }),
+        _ => json!({ "name": name, "in": "query", "required": false, "schema": { "type": "integer", "minimum": 0 } }),
+    }
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn response_content(schema: &str) -> Value {
+    match schema {
+        "Empty" => json!({}),
+        "TokenOverrideList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("TokenOverride") } } }),
+        "Text" => json!({ "text/plain": { "schema": { "type": "string" } } }),
+        "DepositAddressList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("DepositAddress") } } }),
+        "PaymentRequestList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("PaymentRequest") } } }),
+        "PaymentRequestEvents" => json!({ "text/event-stream": { "schema": { "type": "string", "description": "`status` events carrying a PaymentRequest" } } }),
+        "ExchangeConnectionList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("ExchangeConnection") } } }),
+        "ExchangeTradeList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("ExchangeTrade") } } }),
+        "ValidatorAlertList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("ValidatorAlert") } } }),
+        "ScheduledOrderList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("ScheduledOrder") } } }),
+        "RunRecordList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("RunRecord") } } }),
+        "LimiterStatsList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("LimiterStats") } } }),
+        "CandleList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("Candle") } } }),
+        "WalletList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("WalletSummary") } } }),
+        "AdjustedBalanceList" => json!({ "application/json": { "schema": { "type": "array", "items": {
+            "allOf": [schema_ref("TokenAmount"), {
+                "type": "object",
+                "properties": {
+                    "confirmed_amount": { "type": "string" },
+                    "pending_adjusted": { "type": "boolean", "description": "Whether pending transactions sent through this API were applied" },
+                    "asset_id": { "type": "string", "description": "CAIP-19 asset ID, e.g. eip155:1/erc20:0xa0b8…" },
+                    "fiat_value": schema_ref("FiatAmount"),
+                },
+            }],
+        } } } }),
+        "TransactionPage" => json!({ "application/json": { "schema": {
+            "type": "object",
+            "properties": {
+                "items": { "type": "array", "items": schema_ref("Transaction") },
+                "next_cursor": { "type": "string", "nullable": true, "description": "Pass as `cursor` to fetch the next page" },
+            },
+        } } }),
+        "ScreeningRecordPage" => json!({ "application/json": { "schema": {
+            "type": "object",
+            "properties": {
+                "items": { "type": "array", "items": schema_ref("ScreeningRecord") },
+                "next_cursor": { "type": "string", "nullable": true, "description": "Pass as `cursor` to fetch the next page" },
+            },
+        } } }),
+        "ExportFile" => json!({
+            "text/csv": { "schema": { "type": "string" } },
+            "application/json": { "schema": { "type": "object" } },
+        }),
+        "RiskAssessmentList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("RiskAssessment") } } }),
+        "RiskEventList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("RiskEvent") } } }),
+        "OpenApi" => json!({ "application/json": { "schema": { "type": "object", "description": "OpenAPI 3.0 document" } } }),
+        "TokenList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("Token") } } }),
+        "DefiResult" => json!({ "application/json": { "schema": { "type": "object", "description": "The action's result with its transaction hash and fee, or a preview if dry_run was set" } } }),
+        "CompoundPositionList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("CompoundPosition") } } }),
+        "CompoundRunList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("CompoundRun") } } }),
+        "EarnPositionList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("EarnPosition") } } }),
+        "ExitReportList" => json!({ "application/json": { "schema": { "type": "array", "items": schema_ref("ExitReport") } } }),
+        "AuditExport" => json!({ "application/x-ndjson": { "schema": { "type": "string", "description": "One AuditEntry per line" } } }),
+        other => json!({ "application/json": { "schema": schema_ref(other) } }),
+    }
+}
+
+fn schemas() -> Value {
+    let string = json!({ "type": "string" });
+    let optional_string = json!({ "type": "string", "nullable": true });
+    let optional_integer = json!({ "type": "integer", "nullable": true });
+
+    json!({
+        "KeyType": { "type": "string", "enum": ["Ethereum", "Solana", "Bitcoin", "Ton", "Cosmos", "Xrp"] },
+        "TransactionStatus": { "type": "string", "enum": ["Pending", "Confirmed", "Failed"] },
+        "Error": {
+            "type": "object",
+            "properties": {
+                "error": {
+                    "type": "object",
+                    "properties": {
+                        "message": string,
+                        "code": { "type": "integer", "description": "HTTP status code" },
+                        "status": { "type": "string", "description": "Canonical status (google.rpc.Code name)" },
+                        "details": {
+                            "type": "array",
+                            "description": "google.rpc ErrorInfo (reason, domain, retryable) and BadRequest (fieldViolations) details",
+                            "items": { "type": "object" },
+                        },
+                    },
+                },
+            },
+        },
+        "Wallet": {
+            "type": "object",
+            "properties": { "id": string, "name": string, "is_backed_up": { "type": "boolean" }, "created_at": { "type": "integer" } },
+        },
+        "WalletMetadata": {
+            "type": "object",
+            "properties": {
+                "color": { "type": "string", "description": "#RRGGBB" },
+                "icon": string,
+                "archived": { "type": "boolean" },
+                "notes": { "type": "object", "additionalProperties": string, "description": "Notes keyed by account address" },
+            },
+        },
+        "WalletSummary": { "allOf": [schema_ref("Wallet"), schema_ref("WalletMetadata")] },
+        "WalletResponse": {
+            "type": "object",
+            "properties": { "wallet": schema_ref("Wallet"), "mnemonic": optional_string, "metadata": schema_ref("WalletMetadata") },
+        },
+        "DepositRequest": {
+            "type": "object",
+            "required": ["reference", "key_type"],
+            "properties": {
+                "reference": { "type": "string", "description": "Invoice or customer ID" },
+                "key_type": schema_ref("KeyType"),
+                "mint": { "type": "string", "description": "Token mint, for Solana token deposits" },
+            },
+        },
+        "DepositAddress": {
+            "type": "object",
+            "properties": {
+                "wallet_id": string,
+                "reference": string,
+                "key_type": schema_ref("KeyType"),
+                "scheme": { "type": "string", "enum": ["fresh_address", "associated_token_account", "destination_tag", "memo"] },
+                "index": { "type": "integer" },
+                "path": string,
+                "address": string,
+                "owner": { "type": "string", "description": "Owner of the Solana token account" },
+                "mint": string,
+                "destination_tag": { "type": "integer", "description": "Destination tag the payer must set (XRP)" },
+                "memo": { "type": "string", "description": "Memo the payer must attach (TON, Cosmos)" },
+                "created_at": { "type": "integer" },
+            },
+        },
+        "CreatePaymentRequest": {
+            "type": "object",
+            "required": ["key_type", "amount"],
+            "properties": {
+                "key_type": schema_ref("KeyType"),
+                "asset": { "type": "string", "description": "Token contract or mint; the native asset if omitted" },
+                "decimals": { "type": "integer", "description": "Token decimals, required with asset" },
+                "amount": { "type": "string", "description": "Amount in the smallest unit" },
+                "expires_in": { "type": "integer", "default": 3600, "description": "Seconds until the request expires" },
+                "memo": { "type": "string", "description": "Message shown to the payer" },
+                "label": { "type": "string", "description": "Merchant name shown to the payer" },
+                "chain_id": { "type": "integer", "default": 1, "description": "EVM chain ID" },
+            },
+        },
+        "PaymentRequest": {
+            "allOf": [schema_ref("CreatePaymentRequest"), {
+                "type": "object",
+                "properties": {
+                    "id": string,
+                    "wallet_id": string,
+                    "deposit": schema_ref("DepositAddress"),
+                    "uri": { "type": "string", "description": "EIP-681, Solana Pay or BIP-21 URI to encode as a QR code" },
+                    "status": { "type": "string", "enum": ["pending", "partially_paid", "paid", "expired"] },
+                    "payments": { "type": "array", "items": {
+                        "type": "object",
+                        "properties": { "hash": string, "amount": string, "timestamp": { "type": "integer" } },
+                    } },
+                    "created_at": { "type": "integer" },
+                    "expires_at": { "type": "integer" },
+                },
+            }],
+        },
+        "IncomingPayment": {
+            "type": "object",
+            "required": ["key_type", "to", "hash", "amount", "timestamp"],
+            "properties": {
+                "key_type": schema_ref("KeyType"),
+                "from": { "type": "string", "description": "Sender, screened when compliance screening is configured" },
+                "to": { "type": "string", "description": "Address paid" },
+                "destination_tag": { "type": "integer" },
+                "memo": string,
+                "asset": { "type": "string", "description": "Token contract or mint; the native asset if omitted" },
+                "hash": string,
+                "amount": { "type": "string", "description": "Amount in the smallest unit" },
+                "timestamp": { "type": "integer" },
+            },
+        },
+        "UpdateWalletRequest": {
+            "type": "object",
+            "description": "Omitted fields are left unchanged; an empty color or icon clears it",
+            "properties": { "name": string, "color": string, "icon": string, "archived": { "type": "boolean" } },
+        },
+        "AccountNoteRequest": {
+            "type": "object",
+            "required": ["note"],
+            "properties": { "note": string },
+        },
+        "CreateWalletRequest": {
+            "type": "object",
+            "required": ["name"],
+            "properties": { "name": string },
+        },
+        "ImportWalletRequest": {
+            "type": "object",
+            "required": ["name", "mnemonic"],
+            "properties": { "name": string, "mnemonic": string },
+        },
+        "DeriveAddressRequest": {
+            "type": "object",
+            "required": ["wallet_id", "key_type", "path"],
+            "properties": { "wallet_id": string, "key_type": schema_ref("KeyType"), "path": string },
+        },
+        "AddressResponse": {
+            "type": "object",
+            "properties": { "address": string, "key_type": schema_ref("KeyType"), "path": string },
+        },
+        "Token": {
+            "type": "object",
+            "properties": {
+                "name": string, "symbol": string, "decimals": { "type": "integer" },
+                "address": string, "key_type": schema_ref("KeyType"), "logo_url": optional_string,
+            },
+        },
+        "TokenAmount": {
+            "type": "object",
+            "properties": { "token": schema_ref("Token"), "amount": string },
+        },
+        "NetworkBinding": {
+            "type": "object",
+            "description": "Exactly one of Evm, Solana, Bitcoin, Ton, Cosmos or Xrp",
+            "properties": {
+                "Evm": { "type": "object", "properties": { "chain_id": { "type": "integer" } } },
+                "Solana": { "type": "object", "properties": { "genesis_hash": string } },
+                "Bitcoin": { "type": "object", "properties": { "network": string } },
+                "Ton": { "type": "object", "properties": { "global_id": { "type": "integer" } } },
+                "Cosmos": { "type": "object", "properties": { "chain_id": string } },
+                "Xrp": { "type": "object", "properties": { "network_id": { "type": "integer" } } },
+            },
+        },
+        "Memo": {
+            "type": "object",
+            "required": ["type", "value"],
+            "description": "Text on Solana, Cosmos and TON; text or bytes on the XRP Ledger",
+            "properties": {
+                "type": { "type": "string", "enum": ["text", "bytes"] },
+                "value": { "description": "A string for text, an array of byte values for bytes" },
+            },
+        },
+        "TransactionRequest": {
+            "type": "object",
+            "required": ["key_type", "network", "from", "to", "value"],
+            "properties": {
+                "key_type": schema_ref("KeyType"), "network": schema_ref("NetworkBinding"),
+                "from": string, "to": string, "value": string,
+                "gas_price": optional_string, "gas_limit": optional_string, "nonce": optional_integer,
+                "data": { "type": "array", "items": { "type": "integer" }, "nullable": true },
+                "destination_tag": { "type": "integer", "nullable": true, "description": "XRP Ledger only" },
+                "memo": schema_ref("Memo"),
+            },
+        },
+        "TransactionResponse": {
+            "type": "object",
+            "properties": {
+                "hash": string,
+                "status": schema_ref("TransactionStatus"),
+                "route": { "type": "string", "enum": ["private_relay", "public"], "description": "Set when the wallet's MEV protection applied" },
+            },
+        },
+        "BatchRequest": {
+            "type": "object",
+            "required": ["key_type", "network", "from", "intents"],
+            "properties": {
+                "key_type": schema_ref("KeyType"),
+                "network": schema_ref("NetworkBinding"),
+                "from": string,
+                "intents": { "type": "array", "items": { "type": "object" }, "description": "transfer, token_transfer, approve, call, spl_transfer or instruction intents, tagged by `type`" },
+                "evm_mode": { "type": "object", "nullable": true, "description": "`{\"type\": \"smart_account\", \"account\": ...}` or `{\"type\": \"multicall\"}`" },
+            },
+        },
+        "Batch": {
+            "type": "object",
+            "properties": {
+                "payload": { "type": "object", "description": "Unsigned EVM transaction request or Solana instructions, tagged by `type`" },
+                "items": { "type": "array", "items": { "type": "object" }, "description": "Each intent with the calls or instructions carrying it" },
+            },
+        },
+        "Transaction": {
+            "type": "object",
+            "properties": {
+                "hash": string, "transaction_type": string, "key_type": schema_ref("KeyType"),
+                "from": string, "to": string, "value": string,
+                "gas_price": optional_string, "gas_limit": optional_string, "nonce": optional_integer,
+                "status": schema_ref("TransactionStatus"), "block_number": optional_integer,
+                "timestamp": optional_integer, "fee": optional_string, "memo": schema_ref("Memo"),
+            },
+        },
+        "ExportFormat": { "type": "string", "enum": ["Csv", "Json"] },
+        "ExportRequest": {
+            "type": "object",
+            "properties": {
+                "from": { "type": "integer" }, "to": { "type": "integer" },
+                "format": schema_ref("ExportFormat"), "fiat_currency": string,
+            },
+        },
+        "CreateExportRequest": {
+            "type": "object",
+            "required": ["key_type", "address", "from", "to", "format"],
+            "properties": {
+                "key_type": schema_ref("KeyType"), "address": string,
+                "from": { "type": "integer", "description": "Range start (inclusive Unix timestamp)" },
+                "to": { "type": "integer", "description": "Range end (exclusive Unix timestamp)" },
+                "format": schema_ref("ExportFormat"),
+                "fiat_currency": { "type": "string", "description": "Defaults to the caller's display currency" },
+            },
+        },
+        "ExportJob": {
+            "type": "object",
+            "properties": {
+                "id": string, "request": schema_ref("ExportRequest"),
+                "status": { "description": "Pending, Running, Completed or {\"Failed\": reason}" },
+                "created_at": { "type": "integer" }, "completed_at": optional_integer,
+                "download_url": { "type": "string", "nullable": true, "description": "Only in the response creating the job; works once the job has completed" },
+            },
+        },
+        "TokenAccountCleanup": {
+            "type": "object",
+            "properties": {
+                "accounts": { "type": "array", "items": { "type": "object" }, "description": "Token accounts to close" },
+                "transactions": { "type": "array", "items": { "type": "array", "items": { "type": "object" } }, "description": "CloseAccount instructions grouped into transactions" },
+                "recovered_lamports": { "type": "integer" },
+                "fee_lamports": { "type": "integer" },
+            },
+        },
+        "FeePayer": {
+            "type": "object",
+            "properties": {
+                "address": string,
+                "usage": {
+                    "type": "object",
+                    "nullable": true,
+                    "description": "Usage of the signing key in the signer query parameter",
+                    "properties": { "day": { "type": "integer" }, "transactions": { "type": "integer" }, "fees": { "type": "integer" } },
+                },
+                "total_usage": {
+                    "type": "object",
+                    "description": "Usage across all users",
+                    "properties": { "day": { "type": "integer" }, "transactions": { "type": "integer" }, "fees": { "type": "integer" } },
+                },
+            },
+        },
+        "SponsorRequest": {
+            "type": "object",
+            "required": ["instructions", "recent_blockhash"],
+            "properties": {
+                "instructions": { "type": "array", "items": { "type": "object" }, "description": "Instructions with program_id, accounts and data; only token, associated token, memo and compute budget programs are sponsored" },
+                "recent_blockhash": string,
+            },
+        },
+        "SponsoredTransaction": {
+            "type": "object",
+            "properties": {
+                "message": { "type": "object" },
+                "message_base64": { "type": "string", "description": "Serialized message for the remaining signers to sign" },
+                "signatures": { "type": "array", "items": optional_string, "description": "Base58 signatures in signer order; the fee payer's, first, is added on submit" },
+                "fee": { "type": "integer" },
+            },
+        },
+        "SubmitSponsoredResponse": {
+            "type": "object",
+            "properties": { "signature": { "type": "string", "description": "Transaction signature, base58" } },
+        },
+        "UtxoConsolidation": {
+            "type": "object",
+            "nullable": true,
+            "properties": {
+                "inputs": { "type": "array", "items": { "type": "object" }, "description": "UTXOs to spend, smallest first" },
+                "uneconomical": { "type": "array", "items": { "type": "object" }, "description": "Dust worth less than the fee to spend it" },
+                "vsize": { "type": "integer" },
+                "fee": { "type": "integer" },
+                "output_value": { "type": "integer" },
+            },
+        },
+        "CleanupPlan": { "oneOf": [schema_ref("TokenAccountCleanup"), schema_ref("UtxoConsolidation")] },
+        "DecodeInvoiceRequest": {
+            "type": "object",
+            "required": ["payment_request"],
+            "properties": { "payment_request": string },
+        },
+        "CreateInvoiceRequest": {
+            "type": "object",
+            "required": ["description"],
+            "properties": {
+                "amount_msat": { "type": "integer", "description": "Omit to let the payer choose" },
+                "description": string,
+                "expiry": { "type": "integer", "default": 3600 },
+            },
+        },
+        "PayInvoiceRequest": {
+            "type": "object",
+            "required": ["payment_request", "max_fee_msat"],
+            "properties": {
+                "payment_request": string,
+                "amount_msat": { "type": "integer", "description": "Required for invoices without an amount" },
+                "max_fee_msat": { "type": "integer", "description": "Most to pay in routing fees" },
+            },
+        },
+        "Invoice": {
+            "type": "object",
+            "properties": {
+                "payment_request": string,
+                "network": { "type": "string", "enum": ["bitcoin", "testnet", "signet", "regtest"] },
+                "amount_msat": optional_integer,
+                "timestamp": { "type": "integer" },
+                "expiry": { "type": "integer" },
+                "payment_hash": string,
+                "payment_secret": optional_string,
+                "description": optional_string,
+                "description_hash": optional_string,
+                "payee": string,
+                "min_final_cltv_expiry": { "type": "integer" },
+            },
+        },
+        "LightningPayment": {
+            "type": "object",
+            "properties": {
+                "payment_hash": string,
+                "direction": { "type": "string", "enum": ["outgoing", "incoming"] },
+                "amount_msat": { "type": "integer" },
+                "fee_msat": optional_integer,
+                "status": schema_ref("TransactionStatus"),
+                "preimage": optional_string,
+                "failure": optional_string,
+                "timestamp": optional_integer,
+            },
+        },
+        "ValidatorAlert": {
+            "type": "object",
+            "properties": {
+                "delegator": string,
+                "validator": { "type": "string", "description": "Operator address" },
+                "moniker": string,
+                "issues": { "type": "array", "items": {
+                    "type": "object",
+                    "properties": {
+                        "kind": { "type": "string", "enum": ["jailed", "inactive", "commission_increase", "slashed"] },
+                        "from": { "type": "number", "description": "Previous commission rate (commission_increase)" },
+                        "to": { "type": "number", "description": "New commission rate (commission_increase)" },
+                        "fraction": { "type": "number", "description": "Share of the delegation lost (slashed)" },
+                    },
+                } },
+                "delegated": { "type": "object", "properties": { "denom": string, "amount": string } },
+                "redelegation": { "type": "object", "nullable": true, "description": "begin_redelegate message moving the delegation to the active validator with the lowest commission" },
+            },
+        },
+        "LimiterStats": {
+            "type": "object",
+            "properties": {
+                "service": { "type": "string", "description": "First segment of the request path, e.g. transactions" },
+                "limit": { "type": "integer", "description": "Current concurrency limit" },
+                "in_flight": { "type": "integer" },
+                "admitted": { "type": "integer" },
+                "shed": { "type": "integer", "description": "Requests refused with RESOURCE_EXHAUSTED since startup" },
+                "average_latency_ms": { "type": "number", "nullable": true },
+            },
+        },
+        "LimiterConfig": {
+            "type": "object",
+            "properties": {
+                "initial_limit": { "type": "integer", "default": 20 },
+                "min_limit": { "type": "integer", "default": 4 },
+                "max_limit": { "type": "integer", "default": 200 },
+                "tolerance": { "type": "number", "default": 1.5, "description": "How far latency may rise above its long-run average before the limit shrinks" },
+                "smoothing": { "type": "number", "default": 0.2 },
+                "window": { "type": "integer", "default": 600, "description": "Number of samples the long-run latency average spans" },
+            },
+        },
+        "ApiConfig": {
+            "type": "object",
+            "properties": {
+                "endpoints": {
+                    "type": "object",
+                    "properties": {
+                        "esplora_url": string,
+                        "electrum_url": string,
+                        "toncenter_url": string,
+                        "cosmos_rest_url": string,
+                        "xrpl_url": string,
+                        "timeout": { "type": "integer", "description": "Request timeout in seconds" },
+                    },
+                },
+                "fees": {
+                    "type": "object",
+                    "properties": {
+                        "platform_fee_bps": { "type": "integer", "nullable": true },
+                        "platform_fee_evm_recipient": string,
+                        "platform_fee_solana_recipient": string,
+                        "max_fee_bps": { "type": "integer", "nullable": true, "description": "Default cap on network fees in basis points of the value sent" },
+                    },
+                },
+                "concurrency_limits": { "type": "object", "additionalProperties": schema_ref("LimiterConfig"), "description": "Limits by service, where default applies to the rest" },
+            },
+        },
+        "XrpBalance": {
+            "type": "object",
+            "description": "Amounts in drops",
+            "properties": { "total": { "type": "integer" }, "reserved": { "type": "integer" }, "spendable": { "type": "integer" } },
+        },
+        "FeeBudget": {
+            "type": "object",
+            "properties": {
+                "max_fee": { "type": "object", "additionalProperties": string, "description": "Highest fee per chain (keyed by KeyType), in the smallest unit of its native token" },
+                "max_fee_bps": { "type": "integer", "nullable": true, "description": "Highest fee in basis points of the value sent" },
+            },
+        },
+        "OrderKind": {
+            "type": "object",
+            "description": "Exactly one of Transfer or Dca",
+            "properties": {
+                "Transfer": { "allOf": [schema_ref("TransactionRequest")], "description": "Recurring transfer, without a nonce" },
+                "Dca": { "allOf": [schema_ref("SwapRequest")], "description": "Dollar-cost averaging swap" },
+            },
+        },
+        "Schedule": {
+            "type": "object",
+            "description": "Exactly one of Once, Interval or Cron; times are Unix timestamps",
+            "properties": {
+                "Once": { "type": "object", "properties": { "at": { "type": "integer" } } },
+                "Interval": { "type": "object", "properties": { "start": { "type": "integer" }, "seconds": { "type": "integer", "minimum": 60 } } },
+                "Cron": { "type": "string", "description": "Five-field cron expression in UTC, e.g. `0 9 * * 1`" },
+            },
+        },
+        "CreateScheduleRequest": {
+            "type": "object",
+            "required": ["kind", "schedule"],
+            "properties": { "kind": schema_ref("OrderKind"), "schedule": schema_ref("Schedule") },
+        },
+        "ScheduledOrder": {
+            "type": "object",
+            "properties": {
+                "id": string,
+                "owner": string,
+                "kind": schema_ref("OrderKind"),
+                "schedule": schema_ref("Schedule"),
+                "next_run": { "type": "integer", "nullable": true, "description": "Null once the schedule is exhausted" },
+                "enabled": { "type": "boolean", "description": "False when paused, including after repeated failures" },
+                "consecutive_failures": { "type": "integer" },
+                "notified_run": { "type": "integer", "nullable": true },
+                "created_at": { "type": "integer" },
+            },
+        },
+        "RunRecord": {
+            "type": "object",
+            "properties": {
+                "order_id": string,
+                "owner": string,
+                "asset": { "type": "string", "description": "Asset spent, counted against the daily limit" },
+                "amount": string,
+                "scheduled_at": { "type": "integer" },
+                "executed_at": { "type": "integer" },
+                "outcome": {
+                    "type": "object",
+                    "description": "{\"Executed\": {\"transaction_hash\"}}, {\"Blocked\": {\"reason\"}} or {\"Failed\": {\"error\"}}",
+                },
+            },
+        },
+        "MevProtection": {
+            "type": "object",
+            "properties": {
+                "mode": { "type": "string", "enum": ["public", "private"], "default": "public" },
+                "relay_url": { "type": "string", "nullable": true, "description": "Private relay RPC URL, Flashbots Protect if not set" },
+                "fallback_to_public": { "type": "boolean", "default": true, "description": "Submit to the public mempool when the relay is unreachable" },
+            },
+        },
+        "Interval": { "type": "string", "enum": ["1m", "5m", "15m", "1h", "4h", "1d"] },
+        "Candle": {
+            "type": "object",
+            "properties": {
+                "open_time": { "type": "integer", "description": "Unix timestamp the interval starts at" },
+                "open": { "type": "number" },
+                "high": { "type": "number" },
+                "low": { "type": "number" },
+                "close": { "type": "number" },
+                "volume": { "type": "number" },
+            },
+        },
+        "FiatRates": {
+            "type": "object",
+            "properties": {
+                "base": string,
+                "date": { "type": "integer", "description": "Unix timestamp the rates were published" },
+                "rates": { "type": "object", "additionalProperties": { "type": "number" }, "description": "Units of each currency per unit of the base" },
+            },
+        },
+        "FiatAmount": {
+            "type": "object",
+            "properties": { "amount": { "type": "number" }, "currency": string },
+        },
+        "LinkExchangeRequest": {
+            "type": "object",
+            "required": ["exchange", "api_key", "api_secret"],
+            "properties": {
+                "exchange": { "type": "string", "enum": ["binance", "kraken"] },
+                "label": string,
+                "api_key": string,
+                "api_secret": { "type": "string", "description": "Stored encrypted; keys allowing trading or withdrawals are rejected" },
+            },
+        },
+        "ExchangeConnection": {
+            "type": "object",
+            "properties": {
+                "id": string,
+                "user": string,
+                "exchange": { "type": "string", "enum": ["binance", "kraken"] },
+                "label": string,
+                "api_key_hint": { "type": "string", "description": "Last four characters of the API key" },
+                "created_at": { "type": "integer" },
+                "synced_at": { "type": "integer", "description": "When balances were last read" },
+            },
+        },
+        "ExchangeBalances": {
+            "type": "object",
+            "properties": {
+                "balances": { "type": "array", "items": {
+                    "type": "object",
+                    "properties": {
+                        "asset": string,
+                        "total": { "type": "number" },
+                        "sources": { "type": "object", "additionalProperties": { "type": "number" }, "description": "Amount held per connection ID" },
+                        "fiat_value": schema_ref("FiatAmount"),
+                    },
+                } },
+                "errors": { "type": "array", "description": "Connections whose balances could not be read", "items": {
+                    "type": "object",
+                    "properties": { "connection_id": string, "exchange": string, "message": string },
+                } },
+            },
+        },
+        "ExchangeTrade": {
+            "type": "object",
+            "properties": {
+                "id": string,
+                "symbol": { "type": "string", "description": "Market as named by the exchange" },
+                "side": { "type": "string", "enum": ["buy", "sell"] },
+                "price": { "type": "number" },
+                "quantity": { "type": "number" },
+                "fee": { "type": "number" },
+                "fee_asset": optional_string,
+                "timestamp": { "type": "integer" },
+            },
+        },
+        "DisplayCurrency": {
+            "type": "object",
+            "required": ["currency"],
+            "properties": { "currency": { "type": "string", "description": "ISO 4217 code, e.g. EUR" } },
+        },
+        "BackfillRequest": {
+            "type": "object",
+            "required": ["interval", "from", "to"],
+            "properties": { "interval": schema_ref("Interval"), "from": { "type": "integer" }, "to": { "type": "integer" } },
+        },
+        "SpamOverride": { "type": "string", "enum": ["allow", "block"] },
+        "SpamOverrideRequest": {
+            "type": "object",
+            "required": ["action"],
+            "properties": { "action": schema_ref("SpamOverride") },
+        },
+        "TokenOverride": {
+            "type": "object",
+            "properties": { "key_type": schema_ref("KeyType"), "address": string, "action": schema_ref("SpamOverride") },
+        },
+        "Protocol": { "type": "string", "description": "DeFi protocol, e.g. Uniswap or Raydium" },
+        "SwapRequest": {
+            "type": "object",
+            "required": ["from", "to", "slippage", "protocol"],
+            "properties": {
+                "from": schema_ref("TokenAmount"),
+                "to": schema_ref("Token"),
+                "slippage": { "type": "number", "description": "Slippage tolerance in percent (0-50)" },
+                "protocol": schema_ref("Protocol"),
+                "deadline": optional_integer,
+                "dry_run": { "type": "boolean", "default": false },
+            },
+        },
+        "ExecuteSwapRequest": {
+            "allOf": [schema_ref("SwapRequest"), {
+                "type": "object",
+                "properties": {
+                    "quote_id": { "type": "string", "description": "ID of the quote to execute, required unless dry_run is set" },
+                    "owner": { "type": "string", "description": "Account swapping, to include the swap in its exports" },
+                },
+            }],
+        },
+        "SwapQuote": {
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "description": "Pass as quote_id to POST /defi/swap; each quote executes once" },
+                "from": schema_ref("TokenAmount"),
+                "to": schema_ref("TokenAmount"),
+                "min_amount_out": string,
+                "price_impact": { "type": "number", "description": "Percent" },
+                "route": { "type": "array", "items": {
+                    "type": "object",
+                    "properties": { "protocol": schema_ref("Protocol"), "from": schema_ref("Token"), "to": schema_ref("Token"), "portion_bps": { "type": "integer" } },
+                } },
+                "fees": {
+                    "type": "object",
+                    "properties": { "lp_fee": schema_ref("TokenAmount"), "protocol_fee": schema_ref("TokenAmount"), "platform_fee": schema_ref("TokenAmount") },
+                },
+                "quoted_at": { "type": "integer" },
+                "expires_at": { "type": "integer" },
+                "suggest_private_submission": { "type": "boolean" },
+                "approval": { "type": "object", "nullable": true, "description": "Approvals needed first, when owner was given" },
+            },
+        },
+        "SwapResult": {
+            "type": "object",
+            "properties": {
+                "from": schema_ref("TokenAmount"),
+                "to": schema_ref("TokenAmount"),
+                "transaction_hash": string,
+                "protocol": schema_ref("Protocol"),
+                "fee": string,
+                "platform_fee": { "type": "object", "nullable": true },
+            },
+        },
+        "AuditStatus": {
+            "type": "object",
+            "required": ["status"],
+            "properties": {
+                "status": { "type": "string", "enum": ["unaudited", "audited"] },
+                "auditors": { "type": "array", "items": string, "description": "Only when audited" },
+                "last_audit": { "type": "integer", "description": "Unix timestamp of the latest audit, only when audited" },
+            },
+        },
+        "ExploitReport": {
+            "type": "object",
+            "required": ["id", "protocol", "reported_at", "loss_usd", "description", "resolved"],
+            "properties": {
+                "id": string,
+                "protocol": schema_ref("Protocol"),
+                "reported_at": { "type": "integer" },
+                "loss_usd": { "type": "number" },
+                "description": string,
+                "resolved": { "type": "boolean", "description": "Whether the protocol has been fixed and reopened" },
+            },
+        },
+        "TvlPoint": {
+            "type": "object",
+            "required": ["timestamp", "tvl_usd"],
+            "properties": {
+                "timestamp": { "type": "integer" },
+                "tvl_usd": { "type": "number" },
+            },
+        },
+        "ProtocolRiskProfile": {
+            "type": "object",
+            "required": ["protocol", "audit"],
+            "properties": {
+                "protocol": schema_ref("Protocol"),
+                "audit": schema_ref("AuditStatus"),
+                "oracles": { "type": "array", "items": string },
+                "exploits": { "type": "array", "items": schema_ref("ExploitReport") },
+                "tvl": { "type": "array", "items": schema_ref("TvlPoint") },
+            },
+        },
+        "RiskLevel": { "type": "string", "enum": ["low", "medium", "high", "critical"] },
+        "RiskFactor": {
+            "type": "object",
+            "required": ["kind"],
+            "properties": {
+                "kind": { "type": "string", "enum": ["unaudited", "stale_audit", "exploit", "tvl_drop", "oracle_dependency"] },
+                "last_audit": { "type": "integer" },
+                "id": string,
+                "loss_usd": { "type": "number" },
+                "resolved": { "type": "boolean" },
+                "fraction": { "type": "number", "description": "Share of the TVL peak lost" },
+                "oracle": string,
+            },
+        },
+        "RiskAssessment": {
+            "type": "object",
+            "properties": {
+                "protocol": schema_ref("Protocol"),
+                "score": { "type": "integer", "minimum": 0, "maximum": 100 },
+                "level": schema_ref("RiskLevel"),
+                "factors": { "type": "array", "items": schema_ref("RiskFactor") },
+                "deposits_paused": { "type": "boolean" },
+                "pause_reason": optional_string,
+                "assessed_at": { "type": "integer" },
+            },
+        },
+        "RiskEvent": {
+            "type": "object",
+            "required": ["kind", "protocol"],
+            "properties": {
+                "kind": { "type": "string", "enum": ["exploited", "tvl_drop", "level_changed"] },
+                "protocol": schema_ref("Protocol"),
+                "report": schema_ref("ExploitReport"),
+                "fraction": { "type": "number" },
+                "from": schema_ref("RiskLevel"),
+                "to": schema_ref("RiskLevel"),
+            },
+        },
+        "ResumeDepositsRequest": {
+            "type": "object",
+            "required": ["protocol"],
+            "properties": { "protocol": schema_ref("Protocol") },
+        },
+        "BackupBundle": {
+            "type": "object",
+            "properties": {
+                "version": { "type": "integer" },
+                "kdf": {
+                    "type": "object",
+                    "properties": {
+                        "algorithm": string,
+                        "iterations": { "type": "integer" },
+                        "salt": { "type": "string", "description": "Hex" },
+                    },
+                },
+                "cipher": string,
+                "nonce": { "type": "string", "description": "Hex" },
+                "ciphertext": { "type": "string", "description": "Base64, including the authentication tag" },
+            },
+        },
+        "ExportBackupRequest": {
+            "type": "object",
+            "required": ["password"],
+            "properties": {
+                "password": string,
+                "metadata": { "type": "object", "description": "Derived accounts and their labels to include" },
+            },
+        },
+        "RestoreBackupRequest": {
+            "type": "object",
+            "required": ["bundle", "password"],
+            "properties": {
+                "bundle": schema_ref("BackupBundle"),
+                "password": string,
+            },
+        },
+        "LendingRequest": {
+            "type": "object",
+            "required": ["action", "protocol"],
+            "properties": {
+                "action": { "type": "object", "description": "One of Supply, Withdraw, Borrow or Repay, keyed to a TokenAmount, e.g. {\"Supply\": {…}}" },
+                "protocol": schema_ref("Protocol"),
+                "dry_run": { "type": "boolean", "default": false },
+            },
+        },
+        "StakingRequest": {
+            "type": "object",
+            "required": ["action", "protocol"],
+            "properties": {
+                "action": { "description": "{\"Stake\": TokenAmount}, {\"Unstake\": TokenAmount} or \"ClaimRewards\"" },
+                "protocol": schema_ref("Protocol"),
+                "dry_run": { "type": "boolean", "default": false },
+            },
+        },
+        "ExecuteStakingRequest": {
+            "allOf": [schema_ref("StakingRequest"), {
+                "type": "object",
+                "properties": {
+                    "owner": { "type": "string", "description": "Account claiming, to include the rewards in its exports" },
+                    "key_type": schema_ref("KeyType"),
+                },
+            }],
+        },
+        "PositionKind": { "type": "string", "enum": ["supply", "borrow", "stake"] },
+        "TrackPositionRequest": {
+            "type": "object",
+            "required": ["protocol", "kind", "amount"],
+            "properties": {
+                "protocol": schema_ref("Protocol"),
+                "kind": schema_ref("PositionKind"),
+                "amount": schema_ref("TokenAmount"),
+            },
+        },
+        "EarnPosition": {
+            "type": "object",
+            "properties": {
+                "id": string,
+                "owner": string,
+                "protocol": schema_ref("Protocol"),
+                "kind": schema_ref("PositionKind"),
+                "amount": schema_ref("TokenAmount"),
+                "created_at": { "type": "integer" },
+            },
+        },
+        "ExitStep": {
+            "type": "object",
+            "properties": {
+                "position_id": string,
+                "protocol": schema_ref("Protocol"),
+                "action": {
+                    "type": "object",
+                    "description": "{\"type\": \"lending\" | \"staking\", \"action\": …}",
+                },
+                "depends_on": { "type": "array", "items": { "type": "integer" }, "description": "Indexes of the steps that must succeed first" },
+                "outcome": {
+                    "type": "object",
+                    "required": ["status"],
+                    "properties": {
+                        "status": { "type": "string", "enum": ["pending", "succeeded", "failed", "skipped"] },
+                        "transaction_hash": string,
+                        "fee": string,
+                        "error": string,
+                        "reason": string,
+                    },
+                },
+                "attempts": { "type": "integer" },
+            },
+        },
+        "ExitReport": {
+            "type": "object",
+            "properties": {
+                "id": string,
+                "owner": string,
+                "trigger": {
+                    "type": "object",
+                    "required": ["kind"],
+                    "properties": {
+                        "kind": { "type": "string", "enum": ["risk", "panic"] },
+                        "event": schema_ref("RiskEvent"),
+                    },
+                },
+                "steps": { "type": "array", "items": schema_ref("ExitStep") },
+                "status": { "type": "string", "enum": ["completed", "partially_failed"] },
+                "recovered": { "type": "array", "items": schema_ref("TokenAmount") },
+                "stranded": { "type": "array", "items": string, "description": "IDs of positions not fully unwound" },
+                "started_at": { "type": "integer" },
+                "finished_at": { "type": "integer" },
+            },
+        },
+        "CompoundRequest": {
+            "type": "object",
+            "required": ["protocol", "address", "schedule", "min_rewards"],
+            "properties": {
+                "protocol": { "type": "string", "enum": ["Lido", "Marinade"] },
+                "address": { "type": "string", "description": "Address holding the position" },
+                "schedule": { "allOf": [schema_ref("Schedule")], "description": "Intervals must be at least an hour" },
+                "min_rewards": { "type": "string", "description": "Smallest rewards worth compounding, in the smallest unit" },
+            },
+        },
+        "CompoundPosition": {
+            "type": "object",
+            "properties": {
+                "id": string,
+                "owner": string,
+                "request": schema_ref("CompoundRequest"),
+                "next_run": { "type": "integer", "nullable": true },
+                "enabled": { "type": "boolean", "description": "False when paused, including after repeated failures" },
+                "consecutive_failures": { "type": "integer" },
+                "unstaked": { "allOf": [schema_ref("TokenAmount")], "description": "Rewards claimed by a run whose restake failed, restaked by the next run" },
+                "created_at": { "type": "integer" },
+            },
+        },
+        "CompoundRun": {
+            "type": "object",
+            "properties": {
+                "position_id": string,
+                "owner": string,
+                "scheduled_at": { "type": "integer" },
+                "executed_at": { "type": "integer" },
+                "outcome": {
+                    "type": "object",
+                    "description": "{\"Compounded\": {\"claim_hash\", \"stake_hash\", \"amount\", \"fees\"}}, {\"Skipped\": {\"reason\"}} or {\"Failed\": {\"error\"}}",
+                },
+            },
+        },
+        "EarnAnalytics": {
+            "type": "object",
+            "properties": {
+                "owner": string,
+                "active_positions": { "type": "integer" },
+                "paused_positions": { "type": "integer" },
+                "next_run": { "type": "integer", "nullable": true, "description": "Next compounding run of any active position" },
+                "compounded_yield": {
+                    "type": "array",
+                    "description": "Rewards restaked per token, including positions since removed",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "token": schema_ref("Token"),
+                            "compounded": { "type": "string", "description": "Total restaked, in the smallest unit" },
+                            "fees": { "type": "string", "description": "Network fees paid for compounding, in the smallest unit of the native token" },
+                            "runs": { "type": "integer" },
+                        },
+                    },
+                },
+            },
+        },
+        "SendTransactionRequest": {
+            "allOf": [schema_ref("TransactionRequest"), {
+                "type": "object",
+                "properties": {
+                    "travel_rule": {
+                        "type": "object",
+                        "description": "Beneficiary data, needed when the travel rule applies",
+                        "properties": {
+                            "beneficiary_persons": { "type": "array", "items": { "type": "object", "description": "IVMS101 Person" } },
+                            "beneficiary_vasp": { "type": "object", "nullable": true, "description": "IVMS101 VASP; absent for unhosted wallets" },
+                            "counterparty_endpoint": { "type": "string", "nullable": true, "description": "TRP endpoint of the beneficiary VASP" },
+                        },
+                    },
+                },
+            }],
+        },
+        "TravelRuleTransfer": {
+            "type": "object",
+            "properties": {
+                "id": string,
+                "withdrawal": { "type": "object", "description": "The transaction request and beneficiary data" },
+                "fiat_value": { "type": "integer", "nullable": true, "description": "Value in US cents as priced by the server; null if it could not be valued" },
+                "payload": { "type": "object", "nullable": true, "description": "IVMS101 identity payload" },
+                "message_id": optional_string,
+                "state": { "description": "NotRequired, Unhosted, AwaitingCounterparty, Accepted, {\"Rejected\": {\"reason\": …}}, Expired or Released" },
+                "created_at": { "type": "integer" },
+            },
+        },
+        "TrpCallback": {
+            "type": "object",
+            "description": "Either approved or rejected is set",
+            "properties": {
+                "approved": { "type": "object" },
+                "rejected": string,
+            },
+        },
+        "KeyShare": {
+            "type": "object",
+            "required": ["key_type", "holder", "share", "public_share", "peer_public_share", "public_key"],
+            "properties": {
+                "key_type": schema_ref("KeyType"),
+                "holder": { "type": "string", "enum": ["Device", "Server", "Recovery"] },
+                "share": { "type": "string", "description": "Hex-encoded share secret" },
+                "public_share": { "type": "string", "description": "Hex-encoded public share" },
+                "peer_public_share": { "type": "string", "description": "Hex-encoded public share of the share this one pairs with" },
+                "public_key": { "type": "string", "description": "Hex-encoded public key of the full key (compressed for secp256k1)" },
+            },
+        },
+        "EscrowKeyShareRequest": {
+            "type": "object",
+            "required": ["server_shares"],
+            "properties": {
+                "server_shares": {
+                    "type": "object",
+                    "required": ["signing", "recovery"],
+                    "properties": {
+                        "signing": schema_ref("KeyShare"),
+                        "recovery": schema_ref("KeyShare"),
+                    },
+                },
+            },
+        },
+        "EscrowReceipt": {
+            "type": "object",
+            "properties": {
+                "share_id": string,
+                "access_token": { "type": "string", "description": "Sent as a bearer token to sign; the server keeps only its hash" },
+                "recovery_token": { "type": "string", "description": "Sent as a bearer token to recover; keep it with the recovery share" },
+            },
+        },
+        "SigningNonce": {
+            "type": "object",
+            "properties": {
+                "nonce": { "type": "string", "description": "Hex-encoded public nonce of the server" },
+                "paillier_modulus": { "type": "string", "nullable": true, "description": "Hex-encoded Paillier modulus (secp256k1 only)" },
+                "encrypted_share": { "type": "string", "nullable": true, "description": "Hex-encoded Paillier encryption of the server share (secp256k1 only)" },
+            },
+        },
+        "CoSignRequest": {
+            "type": "object",
+            "required": ["message", "partial"],
+            "properties": {
+                "message": { "type": "string", "description": "Hex-encoded message (a 32-byte hash for secp256k1 keys)" },
+                "partial": {
+                    "type": "object",
+                    "required": ["nonce", "share"],
+                    "properties": {
+                        "nonce": { "type": "string", "description": "Hex-encoded public nonce of the device" },
+                        "share": { "type": "string", "description": "Hex-encoded signature share of the device" },
+                    },
+                },
+            },
+        },
+        "CoSignResponse": { "type": "object", "properties": { "signature": string } },
+        "Recovery": {
+            "type": "object",
+            "properties": {
+                "access_token": { "type": "string", "description": "Replaces the previous access token" },
+                "refresh": { "type": "string", "description": "Hex-encoded value the device derives its new share with" },
+            },
+        },
+        "ScreeningRecord": {
+            "type": "object",
+            "properties": {
+                "key_type": schema_ref("KeyType"),
+                "direction": { "type": "string", "enum": ["Outgoing", "Deposit"] },
+                "counterparty": string,
+                "transaction_hash": optional_string,
+                "result": {
+                    "type": "object",
+                    "nullable": true,
+                    "properties": {
+                        "address": string,
+                        "risk": { "type": "string", "enum": ["Low", "Medium", "High", "Severe"] },
+                        "categories": { "type": "array", "items": string },
+                        "source": string,
+                    },
+                },
+                "error": optional_string,
+                "action": { "type": "string", "enum": ["Allow", "Flag", "Block"] },
+                "timestamp": { "type": "integer" },
+            },
+        },
+        "AuditVerification": {
+            "type": "object",
+            "properties": {
+                "valid": { "type": "boolean" },
+                "entries": { "type": "integer" },
+                "first_invalid": optional_integer,
+                "head": { "type": "string", "description": "Hash of the last entry; HMAC-SHA256 tag if the log is keyed" },
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_paths() {
+        let spec = spec();
+
+        assert!(spec["paths"]["/wallets/{id}"]["get"].is_object());
+        assert!(spec["paths"]["/wallets"]["post"]["requestBody"].is_object());
+
+        let history = &spec["paths"]["/addresses/{key_type}/{address}/transactions"]["get"];
+        assert_eq!(history["parameters"].as_array().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_operations_match_router() {
+        let mut routes = Vec::new();
+        for line in include_str!("main.rs").lines() {
+            let Some(route) = line.trim().strip_prefix(".route(\"") else { continue };
+            let (path, handlers) = route.split_once('"').unwrap();
+            for method in ["get", "post", "put", "patch", "delete"] {
+                if handlers.contains(&format!(" {}(", method)) || handlers.contains(&format!(".{}(", method)) {
+                    routes.push((method.to_string(), path.to_string()));
+                }
+            }
+        }
+        routes.sort();
+
+        let mut documented: Vec<_> = OPERATIONS.iter()
+            .map(|operation| (operation.method.to_string(), operation.path.to_string()))
+            .collect();
+        documented.sort();
+
+        assert_eq!(routes, documented);
+    }
+
+    #[test]
+    fn test_schema_refs_resolve() {
+        let spec = spec();
+        let text = spec.to_string();
+
+        // Every referenced schema must be defined
+        for reference in text.split("#/components/schemas/").skip(1) {
+            let name: String = reference.chars().take_while(|c| c.is_alphanumeric()).collect();
+            assert!(spec["components"]["schemas"][&name].is_object(), "undefined schema {}", name);
+        }
+    }
+}
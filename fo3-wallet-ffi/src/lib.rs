@@ -0,0 +1,607 @@
+//! FO3 Wallet FFI
+//!
+//! UniFFI bindings exposing the fo3-wallet core to Swift and Kotlin, so the
+//! mobile apps sign on the client with the same code paths as the server.
+//! Secrets cross the boundary as mnemonic phrases; keys are derived on each
+//! call and never returned to the host language.
+//!
+//! Transactions are built and signed by the core's per-chain signers. The
+//! core has no Bitcoin transaction signer yet, so Bitcoin is limited to
+//! message signing.
+
+use std::str::FromStr;
+
+use ed25519_dalek::{Signer as _, SigningKey};
+use ethers::prelude::Address;
+use ethers_signers::LocalWallet;
+use secp256k1::{Message, Secp256k1, SecretKey};
+use sha2::{Digest, Sha256, Sha512};
+
+use fo3_wallet::crypto::keys::{self, KeyPair, KeyType};
+use fo3_wallet::crypto::mnemonic::{self, MnemonicStrength};
+use fo3_wallet::transaction::{
+    self as chain, Coin, CosmosAccount, CosmosFee, CosmosMsg, InternalMessage, Memo, NetworkBinding,
+    TonAddress, TonWallet, TransactionRequest, XrpPayment,
+};
+
+uniffi::setup_scaffolding!();
+
+/// Errors surfaced to the host language
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum FfiError {
+    #[error("Invalid input: {message}")]
+    InvalidInput { message: String },
+
+    #[error("Wallet error: {message}")]
+    Wallet { message: String },
+
+    #[error("Signing error: {message}")]
+    Signing { message: String },
+}
+
+impl From<fo3_wallet::Error> for FfiError {
+    fn from(err: fo3_wallet::Error) -> Self {
+        match err {
+            fo3_wallet::Error::InvalidInput(message) => Self::InvalidInput { message },
+            err @ fo3_wallet::Error::Validation(_) => Self::InvalidInput { message: err.to_string() },
+            fo3_wallet::Error::Signing(message) => Self::Signing { message },
+            other => Self::Wallet { message: other.to_string() },
+        }
+    }
+}
+
+/// Result type for FFI calls
+pub type Result<T> = std::result::Result<T, FfiError>;
+
+fn invalid_input(message: impl Into<String>) -> FfiError {
+    FfiError::InvalidInput { message: message.into() }
+}
+
+fn signing_error(message: impl Into<String>) -> FfiError {
+    FfiError::Signing { message: message.into() }
+}
+
+/// Supported chains
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum FfiKeyType {
+    /// Ethereum and EVM compatible chains
+    Ethereum,
+    /// Solana
+    Solana,
+    /// Bitcoin
+    Bitcoin,
+    /// TON
+    Ton,
+    /// Cosmos SDK chains
+    Cosmos,
+    /// XRP Ledger
+    Xrp,
+}
+
+impl From<FfiKeyType> for KeyType {
+    fn from(key_type: FfiKeyType) -> Self {
+        match key_type {
+            FfiKeyType::Ethereum => KeyType::Ethereum,
+            FfiKeyType::Solana => KeyType::Solana,
+            FfiKeyType::Bitcoin => KeyType::Bitcoin,
+            FfiKeyType::Ton => KeyType::Ton,
+            FfiKeyType::Cosmos => KeyType::Cosmos,
+            FfiKeyType::Xrp => KeyType::Xrp,
+        }
+    }
+}
+
+/// Bitcoin networks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum FfiBitcoinNetwork {
+    /// Bitcoin mainnet
+    Mainnet,
+    /// Bitcoin testnet
+    Testnet,
+    /// Bitcoin signet
+    Signet,
+    /// Bitcoin regtest
+    Regtest,
+}
+
+impl From<FfiBitcoinNetwork> for keys::bitcoin::Network {
+    fn from(network: FfiBitcoinNetwork) -> Self {
+        match network {
+            FfiBitcoinNetwork::Mainnet => keys::bitcoin::Network::Bitcoin,
+            FfiBitcoinNetwork::Testnet => keys::bitcoin::Network::Testnet,
+            FfiBitcoinNetwork::Signet => keys::bitcoin::Network::Signet,
+            FfiBitcoinNetwork::Regtest => keys::bitcoin::Network::Regtest,
+        }
+    }
+}
+
+/// A derived account
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct DerivedAccount {
+    /// Chain of the account
+    pub key_type: FfiKeyType,
+    /// Derivation path
+    pub path: String,
+    /// Hex-encoded public key
+    pub public_key: String,
+    /// Address
+    pub address: String,
+}
+
+/// An EVM transaction to build and sign
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct EvmTransaction {
+    /// Chain ID
+    pub chain_id: u64,
+    /// Nonce
+    pub nonce: u64,
+    /// Recipient address
+    pub to: String,
+    /// Value in wei (decimal string)
+    pub value: String,
+    /// Gas limit
+    pub gas_limit: u64,
+    /// Gas price in wei for legacy transactions, or max fee per gas for EIP-1559
+    pub gas_price: String,
+    /// Max priority fee per gas in wei; set to build an EIP-1559 transaction
+    pub max_priority_fee_per_gas: Option<String>,
+    /// Call data
+    pub data: Vec<u8>,
+}
+
+/// A SOL transfer to build and sign
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct SolanaTransfer {
+    /// Recipient address
+    pub to: String,
+    /// Amount in lamports
+    pub lamports: u64,
+    /// Recent blockhash the transaction is valid for
+    pub recent_blockhash: String,
+    /// Memo to attach
+    pub memo: Option<String>,
+}
+
+/// A Cosmos bank send to build and sign
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct CosmosTransfer {
+    /// Chain ID (`cosmoshub-4`)
+    pub chain_id: String,
+    /// Bech32 prefix of the chain's addresses (`cosmos`)
+    pub bech32_prefix: String,
+    /// Account number of the sender
+    pub account_number: u64,
+    /// Sequence of the sender
+    pub sequence: u64,
+    /// Recipient address
+    pub to: String,
+    /// Amount in the denom's base unit (decimal string)
+    pub amount: String,
+    /// Denom sent and paid as fee (`uatom`)
+    pub denom: String,
+    /// Fee in the denom's base unit (decimal string)
+    pub fee_amount: String,
+    /// Gas limit
+    pub gas_limit: u64,
+    /// Memo
+    pub memo: String,
+}
+
+/// An XRP payment to build and sign
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct XrpTransfer {
+    /// Recipient, as a classic address or X-address
+    pub destination: String,
+    /// Amount in drops
+    pub amount: u64,
+    /// Fee in drops
+    pub fee: u64,
+    /// Sequence of the sending account
+    pub sequence: u32,
+    /// Destination tag, unless carried by an X-address
+    pub destination_tag: Option<u32>,
+    /// Last ledger the payment may be included in
+    pub last_ledger_sequence: Option<u32>,
+    /// Memo to attach
+    pub memo: Option<String>,
+}
+
+/// A TON transfer from a v4R2 wallet to build and sign
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct TonTransfer {
+    /// Recipient address
+    pub to: String,
+    /// Value in nanotons (decimal string)
+    pub value: String,
+    /// Text comment
+    pub comment: Option<String>,
+    /// Sequence number of the wallet
+    pub seqno: u32,
+    /// Unix time after which the transfer is rejected
+    pub valid_until: u32,
+    /// Whether to deploy the wallet with the transfer, while it is uninitialized
+    pub deploy: bool,
+}
+
+/// Generate a new mnemonic phrase with 12 or 24 words
+#[uniffi::export]
+pub fn generate_mnemonic(word_count: u8) -> Result<String> {
+    let strength = match word_count {
+        12 => MnemonicStrength::Words12,
+        24 => MnemonicStrength::Words24,
+        _ => return Err(invalid_input("Word count must be 12 or 24")),
+    };
+
+    Ok(mnemonic::generate_mnemonic(strength)?)
+}
+
+/// Check whether a mnemonic phrase is valid
+#[uniffi::export]
+pub fn validate_mnemonic(phrase: String) -> bool {
+    mnemonic::validate_mnemonic(&phrase).unwrap_or(false)
+}
+
+/// Derive an account from a mnemonic phrase
+#[uniffi::export]
+pub fn derive_account(
+    phrase: String,
+    passphrase: Option<String>,
+    key_type: FfiKeyType,
+    path: String,
+    bitcoin_network: Option<FfiBitcoinNetwork>,
+) -> Result<DerivedAccount> {
+    let key_pair = derive(&phrase, passphrase.as_deref(), key_type, &path)?;
+
+    let address = match key_type {
+        FfiKeyType::Ethereum => keys::ethereum::public_key_to_address(key_pair.public_key())?,
+        FfiKeyType::Solana => keys::solana::public_key_to_address(key_pair.public_key())?,
+        FfiKeyType::Bitcoin => {
+            let network = bitcoin_network.unwrap_or(FfiBitcoinNetwork::Mainnet);
+            keys::bitcoin::public_key_to_address(key_pair.public_key(), network.into())?
+        }
+        FfiKeyType::Ton => keys::ton::public_key_to_address(key_pair.public_key(), false)?,
+        FfiKeyType::Cosmos => keys::cosmos::public_key_to_address(key_pair.public_key(), "cosmos")?,
+        FfiKeyType::Xrp => keys::xrp::public_key_to_address(key_pair.public_key())?,
+    };
+
+    Ok(DerivedAccount {
+        key_type,
+        path,
+        public_key: hex::encode(key_pair.public_key().as_bytes()),
+        address,
+    })
+}
+
+/// Normalize an address for display, rejecting malformed input
+///
+/// EVM addresses are returned in EIP-55 checksum form.
+#[uniffi::export]
+pub fn format_address(key_type: FfiKeyType, address: String) -> Result<String> {
+    match key_type {
+        FfiKeyType::Ethereum => {
+            let address = Address::from_str(address.trim())
+                .map_err(|e| invalid_input(format!("Invalid EVM address: {}", e)))?;
+            Ok(ethers::utils::to_checksum(&address, None))
+        }
+        FfiKeyType::Solana => {
+            let bytes = bs58::decode(address.trim()).into_vec()
+                .map_err(|e| invalid_input(format!("Invalid Solana address: {}", e)))?;
+            if bytes.len() != 32 {
+                return Err(invalid_input("Solana addresses must be 32 bytes"));
+            }
+            Ok(address.trim().to_string())
+        }
+        FfiKeyType::Bitcoin => {
+            let parsed = bitcoin::Address::from_str(address.trim())
+                .map_err(|e| invalid_input(format!("Invalid Bitcoin address: {}", e)))?;
+            Ok(parsed.assume_checked().to_string())
+        }
+        FfiKeyType::Ton => {
+            let (parsed, flags) = TonAddress::parse(address.trim())
+                .map_err(|e| invalid_input(e.to_string()))?;
+            Ok(match flags {
+                Some(flags) => parsed.to_friendly(flags.bounceable, flags.testnet),
+                None => parsed.to_raw(),
+            })
+        }
+        FfiKeyType::Cosmos | FfiKeyType::Xrp => {
+            let info = fo3_wallet::address::validate_address(key_type.into(), address.trim())
+                .map_err(|e| invalid_input(e.to_string()))?;
+            Ok(info.normalized)
+        }
+    }
+}
+
+/// Build and sign an EVM transaction, returning the raw signed transaction as 0x-prefixed hex
+#[uniffi::export]
+pub fn sign_evm_transaction(phrase: String, passphrase: Option<String>, path: String, transaction: EvmTransaction) -> Result<String> {
+    let key_pair = derive(&phrase, passphrase.as_deref(), FfiKeyType::Ethereum, &path)?;
+    let request = TransactionRequest {
+        key_type: KeyType::Ethereum,
+        network: NetworkBinding::Evm { chain_id: transaction.chain_id },
+        from: keys::ethereum::public_key_to_address(key_pair.public_key())?,
+        to: transaction.to,
+        value: transaction.value,
+        gas_price: Some(transaction.gas_price),
+        gas_limit: Some(transaction.gas_limit.to_string()),
+        nonce: Some(transaction.nonce),
+        data: Some(transaction.data),
+        destination_tag: None,
+        memo: None,
+    };
+
+    let raw = chain::sign_evm_transaction(&key_pair, &request, transaction.max_priority_fee_per_gas.as_deref())?;
+    Ok(format!("0x{}", hex::encode(raw)))
+}
+
+/// Build and sign a SOL transfer paid for by the sender, returning the wire transaction
+#[uniffi::export]
+pub fn sign_solana_transfer(phrase: String, passphrase: Option<String>, path: String, transfer: SolanaTransfer) -> Result<Vec<u8>> {
+    let key_pair = derive(&phrase, passphrase.as_deref(), FfiKeyType::Solana, &path)?;
+    let from = keys::solana::public_key_to_address(key_pair.public_key())?;
+
+    let mut instructions = vec![chain::system_transfer_instruction(&from, &transfer.to, transfer.lamports)];
+    if let Some(memo) = transfer.memo {
+        instructions.push(chain::memo_instruction(&Memo::Text(memo), &from)?);
+    }
+    let message = chain::Message::compile(&from, &instructions, &transfer.recent_blockhash)?;
+
+    Ok(chain::sign_solana_message(&key_pair, &message)?)
+}
+
+/// Build and sign a Cosmos bank send, returning the `TxRaw` bytes
+#[uniffi::export]
+pub fn sign_cosmos_transfer(phrase: String, passphrase: Option<String>, path: String, transfer: CosmosTransfer) -> Result<Vec<u8>> {
+    let key_pair = derive(&phrase, passphrase.as_deref(), FfiKeyType::Cosmos, &path)?;
+    let to = fo3_wallet::address::validate_address(KeyType::Cosmos, &transfer.to)
+        .map_err(|e| invalid_input(e.to_string()))?;
+    let coin = |field: &str, amount: &str| -> Result<Coin> {
+        let amount = amount.parse::<u128>()
+            .map_err(|e| invalid_input(format!("Invalid {}: {}", field, e)))?;
+        Ok(Coin::new(&transfer.denom, amount))
+    };
+
+    let message = CosmosMsg::Send {
+        from_address: keys::cosmos::public_key_to_address(key_pair.public_key(), &transfer.bech32_prefix)?,
+        to_address: to.normalized,
+        amount: vec![coin("amount", &transfer.amount)?],
+    };
+    let account = CosmosAccount { account_number: transfer.account_number, sequence: transfer.sequence };
+    let fee = CosmosFee { amount: vec![coin("fee", &transfer.fee_amount)?], gas_limit: transfer.gas_limit };
+
+    Ok(chain::sign_direct(&key_pair, &transfer.chain_id, account, &[message], &transfer.memo, &fee)?)
+}
+
+/// Build and sign an XRP payment, returning the transaction blob
+#[uniffi::export]
+pub fn sign_xrp_payment(phrase: String, passphrase: Option<String>, path: String, transfer: XrpTransfer) -> Result<Vec<u8>> {
+    let key_pair = derive(&phrase, passphrase.as_deref(), FfiKeyType::Xrp, &path)?;
+    let (destination, destination_tag, _) = chain::resolve_destination(&transfer.destination, transfer.destination_tag)?;
+    let memo = transfer.memo.map(Memo::Text);
+    if let Some(memo) = &memo {
+        memo.check(KeyType::Xrp)?;
+    }
+
+    let payment = XrpPayment {
+        account: keys::xrp::public_key_to_address(key_pair.public_key())?,
+        destination: destination.to_classic(),
+        amount: transfer.amount,
+        fee: transfer.fee,
+        sequence: transfer.sequence,
+        destination_tag,
+        last_ledger_sequence: transfer.last_ledger_sequence,
+        memo,
+    };
+
+    Ok(chain::sign_payment(&key_pair, &payment)?)
+}
+
+/// Build and sign a TON transfer from the key's v4R2 wallet, returning the external message as a BOC
+#[uniffi::export]
+pub fn sign_ton_transfer(phrase: String, passphrase: Option<String>, path: String, transfer: TonTransfer) -> Result<Vec<u8>> {
+    let key_pair = derive(&phrase, passphrase.as_deref(), FfiKeyType::Ton, &path)?;
+    let value = transfer.value.parse::<u128>()
+        .map_err(|e| invalid_input(format!("Invalid value: {}", e)))?;
+    let message = InternalMessage::transfer(&transfer.to, value, transfer.comment.as_deref())?;
+
+    let wallet = TonWallet::from_key_pair(&key_pair)?;
+    let external = wallet.transfer(&ed25519_signing_key(&key_pair)?, transfer.seqno, transfer.valid_until, &[message], transfer.deploy)?;
+    Ok(external.to_boc())
+}
+
+/// Sign a message using the chain's message signing convention
+///
+/// - Ethereum: EIP-191 `personal_sign`, 65 bytes `r || s || v`
+/// - Solana and TON: raw ed25519 signature, 64 bytes
+/// - Bitcoin: BIP-137 signed message for a compressed key, 65 bytes `header || r || s`
+/// - Cosmos: secp256k1 signature over the message's SHA-256, 64 bytes `r || s`
+/// - XRP: secp256k1 signature over the message's SHA-512Half, 64 bytes `r || s`
+#[uniffi::export]
+pub fn sign_message(phrase: String, passphrase: Option<String>, key_type: FfiKeyType, path: String, message: Vec<u8>) -> Result<Vec<u8>> {
+    let key_pair = derive(&phrase, passphrase.as_deref(), key_type, &path)?;
+
+    match key_type {
+        FfiKeyType::Ethereum => {
+            let wallet = LocalWallet::from_bytes(key_pair.private_key().as_bytes())
+                .map_err(|e| signing_error(format!("Invalid private key: {}", e)))?;
+            let signature = wallet.sign_hash(ethers::utils::hash_message(&message))
+                .map_err(|e| signing_error(e.to_string()))?;
+            Ok(signature.to_vec())
+        }
+        FfiKeyType::Solana | FfiKeyType::Ton => {
+            Ok(ed25519_signing_key(&key_pair)?.sign(&message).to_bytes().to_vec())
+        }
+        FfiKeyType::Bitcoin => Ok(keys::bitcoin::sign_message(&key_pair, &message)?),
+        FfiKeyType::Cosmos | FfiKeyType::Xrp => {
+            let secret_key = SecretKey::from_slice(key_pair.private_key().as_bytes())
+                .map_err(|e| signing_error(format!("Invalid private key: {}", e)))?;
+            let digest: [u8; 32] = match key_type {
+                FfiKeyType::Xrp => Sha512::digest(&message)[..32].try_into().expect("SHA-512 digests are 64 bytes"),
+                _ => Sha256::digest(&message).into(),
+            };
+            let signature = Secp256k1::signing_only().sign_ecdsa(&Message::from_digest(digest), &secret_key);
+            Ok(signature.serialize_compact().to_vec())
+        }
+    }
+}
+
+fn derive(phrase: &str, passphrase: Option<&str>, key_type: FfiKeyType, path: &str) -> Result<KeyPair> {
+    let seed = mnemonic::mnemonic_to_seed(phrase, passphrase)?;
+    Ok(keys::derive_key_pair(&seed, key_type.into(), path)?)
+}
+
+fn ed25519_signing_key(key_pair: &KeyPair) -> Result<SigningKey> {
+    let secret: [u8; 32] = key_pair.private_key().as_bytes().try_into()
+        .map_err(|_| signing_error("Invalid ed25519 private key length"))?;
+    Ok(SigningKey::from_bytes(&secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_generate_and_validate_mnemonic() {
+        let phrase = generate_mnemonic(24).unwrap();
+        assert_eq!(phrase.split_whitespace().count(), 24);
+        assert!(validate_mnemonic(phrase));
+
+        assert!(generate_mnemonic(13).is_err());
+        assert!(!validate_mnemonic("not a mnemonic".to_string()));
+    }
+
+    #[test]
+    fn test_derive_account() {
+        let account = derive_account(PHRASE.to_string(), None, FfiKeyType::Ethereum, "m/44'/60'/0'/0/0".to_string(), None).unwrap();
+        assert_eq!(account.address.to_lowercase(), "0x9858effd232b4033e47d90003d41ec34ecaeda94");
+
+        let account = derive_account(PHRASE.to_string(), None, FfiKeyType::Solana, "m/44'/501'/0'/0'".to_string(), None).unwrap();
+        assert_eq!(account.address, "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk");
+    }
+
+    #[test]
+    fn test_format_address() {
+        let formatted = format_address(FfiKeyType::Ethereum, "0x9858effd232b4033e47d90003d41ec34ecaeda94".to_string()).unwrap();
+        assert_eq!(formatted, "0x9858EfFD232B4033E47d90003D41EC34EcaEda94");
+
+        assert!(format_address(FfiKeyType::Solana, "not-base58!".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_sign_evm_transaction_matches_core_golden() {
+        let transaction = EvmTransaction {
+            chain_id: 1,
+            nonce: 0,
+            to: "0x742d35Cc6634C0532925a3b844Bc454e4438f44e".to_string(),
+            value: "1000000000000000000".to_string(),
+            gas_limit: 21_000,
+            gas_price: "20000000000".to_string(),
+            max_priority_fee_per_gas: None,
+            data: vec![],
+        };
+
+        let raw = sign_evm_transaction(PHRASE.to_string(), None, "m/44'/60'/0'/0/0".to_string(), transaction).unwrap();
+
+        // Same transaction as the core crate's golden file
+        let golden = include_str!("../../fo3-wallet/tests/golden/ethereum_signed_transfer.hex");
+        assert_eq!(raw, golden.trim());
+    }
+
+    #[test]
+    fn test_sign_solana_transfer() {
+        let transfer = SolanaTransfer {
+            to: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(),
+            lamports: 1_000,
+            recent_blockhash: "11111111111111111111111111111111".to_string(),
+            memo: Some("invoice 42".to_string()),
+        };
+
+        let raw = sign_solana_transfer(PHRASE.to_string(), None, "m/44'/501'/0'/0'".to_string(), transfer.clone()).unwrap();
+        // One signature, then the message
+        assert_eq!(raw[0], 1);
+        assert_eq!(raw[65], 1);
+
+        let transfer = SolanaTransfer { to: "not-base58!".to_string(), ..transfer };
+        assert!(sign_solana_transfer(PHRASE.to_string(), None, "m/44'/501'/0'/0'".to_string(), transfer).is_err());
+    }
+
+    #[test]
+    fn test_sign_cosmos_transfer_matches_core() {
+        let transfer = CosmosTransfer {
+            chain_id: "cosmoshub-4".to_string(),
+            bech32_prefix: "cosmos".to_string(),
+            account_number: 12345,
+            sequence: 7,
+            to: "cosmos1pkptre7fdkl6gfrzlesjjvhxhlc3r4gmmk8rs6".to_string(),
+            amount: "1000".to_string(),
+            denom: "uatom".to_string(),
+            fee_amount: "2500".to_string(),
+            gas_limit: 100_000,
+            memo: "fo3".to_string(),
+        };
+
+        let tx_bytes = sign_cosmos_transfer(PHRASE.to_string(), None, "m/44'/118'/0'/0/0".to_string(), transfer).unwrap();
+        // Same transaction as the core crate's `sign_direct` test
+        assert_eq!(fo3_wallet::transaction::cosmos_tx_hash(&tx_bytes), "8A390A50F0A6C77A9D71B863DB51B244F54F7DF25266482950E68B304C03EB80");
+    }
+
+    #[test]
+    fn test_sign_xrp_payment_matches_core() {
+        let transfer = XrpTransfer {
+            destination: "XVPcpSm47b1CZkf5AkKM9a84dQHe3mTAxgxfLw2qYoe7Boa".to_string(),
+            amount: 1_000_000,
+            fee: 12,
+            sequence: 42,
+            destination_tag: None,
+            last_ledger_sequence: Some(90_000_000),
+            memo: None,
+        };
+
+        let blob = sign_xrp_payment(PHRASE.to_string(), None, "m/44'/144'/0'/0/0".to_string(), transfer).unwrap();
+        // Same payment as the core crate's `sign_payment` test
+        assert_eq!(fo3_wallet::transaction::xrp_tx_hash(&blob), "E52E5D54ED72A74740DFE2ABCE0F09936C09A19D3749C207B22A3619CDC5F863");
+    }
+
+    #[test]
+    fn test_sign_ton_transfer() {
+        let transfer = TonTransfer {
+            to: "UQDAmzgkCP_QqkZg7shsqi2QM_FSBibVu47gM5vTFk7dnJtq".to_string(),
+            value: "1000000000".to_string(),
+            comment: Some("fo3".to_string()),
+            seqno: 0,
+            valid_until: 1_700_000_060,
+            deploy: true,
+        };
+
+        let boc = sign_ton_transfer(PHRASE.to_string(), None, "m/44'/607'/0'".to_string(), transfer.clone()).unwrap();
+        let external = fo3_wallet::transaction::Cell::from_boc(&boc).unwrap();
+        // The state init and the signed body
+        assert_eq!(external.refs().len(), 2);
+
+        let transfer = TonTransfer { value: "-1".to_string(), ..transfer };
+        assert!(sign_ton_transfer(PHRASE.to_string(), None, "m/44'/607'/0'".to_string(), transfer).is_err());
+    }
+
+    #[test]
+    fn test_sign_message() {
+        let signature = sign_message(PHRASE.to_string(), None, FfiKeyType::Ethereum, "m/44'/60'/0'/0/0".to_string(), b"hello".to_vec()).unwrap();
+        assert_eq!(signature.len(), 65);
+
+        let signature = sign_message(PHRASE.to_string(), None, FfiKeyType::Solana, "m/44'/501'/0'/0'".to_string(), b"hello".to_vec()).unwrap();
+        assert_eq!(signature.len(), 64);
+
+        let signature = sign_message(PHRASE.to_string(), None, FfiKeyType::Bitcoin, "m/44'/0'/0'/0/0".to_string(), b"hello".to_vec()).unwrap();
+        assert_eq!(signature.len(), 65);
+        assert!((31..=34).contains(&signature[0]));
+
+        // Same message as the core crate's golden file
+        let signature = sign_message(PHRASE.to_string(), None, FfiKeyType::Bitcoin, "m/44'/0'/0'/0/0".to_string(), b"fo3-wallet golden bitcoin".to_vec()).unwrap();
+        let golden = include_str!("../../fo3-wallet/tests/golden/bitcoin_signed_message.hex");
+        assert_eq!(hex::encode(signature), golden.trim());
+
+        let signature = sign_message(PHRASE.to_string(), None, FfiKeyType::Xrp, "m/44'/144'/0'/0/0".to_string(), b"hello".to_vec()).unwrap();
+        assert_eq!(signature.len(), 64);
+    }
+}
@@ -0,0 +1,494 @@
+//! Encrypted wallet backup bundles
+//!
+//! A backup bundle is a versioned JSON document holding the wallet secret
+//! (mnemonic or raw keys) and account metadata, encrypted with AES-256-GCM
+//! under a key derived from the user's password with PBKDF2-SHA256.
+//!
+//! Compatibility importers are provided for MetaMask vault JSON and Phantom
+//! private key exports.
+
+use aes_gcm::{Aes256Gcm, AesGcm, KeyInit};
+use aes_gcm::aead::Aead;
+use aes_gcm::aead::consts::U16;
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aes::Aes256;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use pbkdf2::pbkdf2_hmac;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Serialize, Deserialize};
+use sha2::Sha256;
+
+use crate::error::{Error, Result};
+use crate::crypto::keys::KeyType;
+use crate::crypto::mnemonic::validate_mnemonic;
+use super::wallet::Wallet;
+
+/// Current backup bundle format version
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Default PBKDF2 iteration count for new bundles
+pub const DEFAULT_KDF_ITERATIONS: u32 = 100_000;
+
+/// PBKDF2 iteration count used by MetaMask vaults without key metadata
+const METAMASK_LEGACY_ITERATIONS: u32 = 10_000;
+
+/// Fewest PBKDF2 iterations accepted, so a bundle cannot downgrade the KDF
+pub const MIN_KDF_ITERATIONS: u32 = 10_000;
+
+/// Most PBKDF2 iterations accepted, so a crafted bundle cannot stall the importer
+pub const MAX_KDF_ITERATIONS: u32 = 1_000_000;
+
+/// Most accounts restored from a MetaMask HD keyring
+const MAX_METAMASK_ACCOUNTS: u64 = 1_000;
+
+/// Key derivation parameters stored alongside the ciphertext
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// KDF algorithm name
+    pub algorithm: String,
+    /// Iteration count
+    pub iterations: u32,
+    /// Hex-encoded salt
+    pub salt: String,
+}
+
+/// An encrypted, versioned backup bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupBundle {
+    /// Bundle format version
+    pub version: u32,
+    /// Key derivation parameters
+    pub kdf: KdfParams,
+    /// Cipher name
+    pub cipher: String,
+    /// Hex-encoded nonce
+    pub nonce: String,
+    /// Base64-encoded ciphertext (including the authentication tag)
+    pub ciphertext: String,
+}
+
+impl BackupBundle {
+    /// Serialize the bundle to JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| Error::Serialization(e.to_string()))
+    }
+
+    /// Parse a bundle from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| Error::Serialization(e.to_string()))
+    }
+}
+
+/// A raw private key included in a backup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedKey {
+    /// The type of key
+    pub key_type: KeyType,
+    /// Derivation path the key was derived from, if known
+    pub path: Option<String>,
+    /// Hex-encoded private key bytes
+    pub private_key: String,
+}
+
+/// The secret material held by a backup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BackupSecret {
+    /// BIP-39 mnemonic phrase
+    Mnemonic(String),
+    /// Individually exported private keys
+    PrivateKeys(Vec<ExportedKey>),
+}
+
+/// A derived account recorded in a backup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupAccount {
+    /// The type of key
+    pub key_type: KeyType,
+    /// Derivation path
+    pub path: String,
+    /// User-defined label
+    pub label: Option<String>,
+}
+
+/// Wallet metadata carried in a backup
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupMetadata {
+    /// Accounts the user has derived, with their labels
+    pub accounts: Vec<BackupAccount>,
+}
+
+/// The decrypted contents of a backup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupPayload {
+    /// Original wallet ID, if the backup came from an FO3 wallet
+    pub wallet_id: Option<String>,
+    /// Wallet name
+    pub name: String,
+    /// Original wallet creation timestamp
+    pub created_at: u64,
+    /// Secret material
+    pub secret: BackupSecret,
+    /// Account metadata
+    pub metadata: BackupMetadata,
+}
+
+impl BackupPayload {
+    /// Rebuild a wallet from a mnemonic backup
+    pub fn to_wallet(&self) -> Result<Wallet> {
+        let mnemonic = match &self.secret {
+            BackupSecret::Mnemonic(mnemonic) => mnemonic,
+            BackupSecret::PrivateKeys(_) => {
+                return Err(Error::Backup("Backup does not contain a mnemonic".to_string()));
+            }
+        };
+
+        validate_mnemonic(mnemonic)?;
+
+        match &self.wallet_id {
+            Some(id) => Ok(Wallet::from_parts(id.clone(), self.name.clone(), mnemonic.clone(), true, self.created_at)),
+            None => Wallet::from_mnemonic(self.name.clone(), mnemonic),
+        }
+    }
+}
+
+/// Export a wallet as an encrypted backup bundle
+pub fn export_wallet(wallet: &Wallet, metadata: BackupMetadata, password: &str) -> Result<BackupBundle> {
+    let mnemonic = wallet.mnemonic()
+        .ok_or_else(|| Error::Backup("Mnemonic not available".to_string()))?;
+
+    let payload = BackupPayload {
+        wallet_id: Some(wallet.id().to_string()),
+        name: wallet.name().to_string(),
+        created_at: wallet.created_at(),
+        secret: BackupSecret::Mnemonic(mnemonic.to_string()),
+        metadata,
+    };
+
+    encrypt_payload(&payload, password, DEFAULT_KDF_ITERATIONS)
+}
+
+/// Encrypt a backup payload into a bundle
+pub fn encrypt_payload(payload: &BackupPayload, password: &str, iterations: u32) -> Result<BackupBundle> {
+    if password.is_empty() {
+        return Err(Error::Backup("Backup password must not be empty".to_string()));
+    }
+    check_iterations(iterations)?;
+
+    let plaintext = serde_json::to_vec(payload)
+        .map_err(|e| Error::Serialization(e.to_string()))?;
+
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let key = derive_key(password, &salt, iterations);
+    let cipher = Aes256Gcm::new(GenericArray::from_slice(&key));
+    let ciphertext = cipher.encrypt(GenericArray::from_slice(&nonce), plaintext.as_ref())
+        .map_err(|_| Error::Backup("Encryption failed".to_string()))?;
+
+    Ok(BackupBundle {
+        version: BACKUP_VERSION,
+        kdf: KdfParams {
+            algorithm: "pbkdf2-sha256".to_string(),
+            iterations,
+            salt: hex::encode(salt),
+        },
+        cipher: "aes-256-gcm".to_string(),
+        nonce: hex::encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+/// Decrypt a backup bundle
+pub fn import_backup(bundle: &BackupBundle, password: &str) -> Result<BackupPayload> {
+    if bundle.version != BACKUP_VERSION {
+        return Err(Error::Backup(format!("Unsupported backup version: {}", bundle.version)));
+    }
+    if bundle.kdf.algorithm != "pbkdf2-sha256" || bundle.cipher != "aes-256-gcm" {
+        return Err(Error::Backup(format!("Unsupported backup scheme: {}/{}", bundle.kdf.algorithm, bundle.cipher)));
+    }
+
+    let salt = hex::decode(&bundle.kdf.salt)
+        .map_err(|e| Error::Backup(format!("Invalid salt: {}", e)))?;
+    let nonce = hex::decode(&bundle.nonce)
+        .map_err(|e| Error::Backup(format!("Invalid nonce: {}", e)))?;
+    if nonce.len() != 12 {
+        return Err(Error::Backup("Invalid nonce length".to_string()));
+    }
+    let ciphertext = BASE64.decode(&bundle.ciphertext)
+        .map_err(|e| Error::Backup(format!("Invalid ciphertext: {}", e)))?;
+
+    check_iterations(bundle.kdf.iterations)?;
+    let key = derive_key(password, &salt, bundle.kdf.iterations);
+    let cipher = Aes256Gcm::new(GenericArray::from_slice(&key));
+    let plaintext = cipher.decrypt(GenericArray::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| Error::Backup("Incorrect password or corrupted backup".to_string()))?;
+
+    serde_json::from_slice(&plaintext).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+/// Import a MetaMask vault (the encrypted `vault` JSON from extension storage)
+pub fn import_metamask_vault(vault_json: &str, password: &str) -> Result<BackupPayload> {
+    let vault: serde_json::Value = serde_json::from_str(vault_json)
+        .map_err(|e| Error::Serialization(e.to_string()))?;
+
+    let field = |name: &str| -> Result<Vec<u8>> {
+        let value = vault.get(name).and_then(|v| v.as_str())
+            .ok_or_else(|| Error::Backup(format!("MetaMask vault is missing '{}'", name)))?;
+        BASE64.decode(value).map_err(|e| Error::Backup(format!("Invalid vault field '{}': {}", name, e)))
+    };
+
+    let data = field("data")?;
+    let iv = field("iv")?;
+    let salt = field("salt")?;
+    if iv.len() != 16 {
+        return Err(Error::Backup("Invalid MetaMask vault IV length".to_string()));
+    }
+
+    let iterations = match vault.pointer("/keyMetadata/params/iterations") {
+        Some(value) => value.as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| Error::Backup("Invalid MetaMask vault iteration count".to_string()))?,
+        None => METAMASK_LEGACY_ITERATIONS,
+    };
+    check_iterations(iterations)?;
+
+    // MetaMask uses WebCrypto AES-GCM with a 16-byte IV
+    let key = derive_key(password, &salt, iterations);
+    let cipher = AesGcm::<Aes256, U16>::new(GenericArray::from_slice(&key));
+    let plaintext = cipher.decrypt(GenericArray::from_slice(&iv), data.as_ref())
+        .map_err(|_| Error::Backup("Incorrect password or corrupted vault".to_string()))?;
+
+    let keyrings: Vec<serde_json::Value> = serde_json::from_slice(&plaintext)
+        .map_err(|e| Error::Serialization(e.to_string()))?;
+
+    let mut mnemonic = None;
+    let mut accounts = Vec::new();
+    let mut keys = Vec::new();
+
+    for keyring in &keyrings {
+        match keyring.get("type").and_then(|t| t.as_str()) {
+            Some("HD Key Tree") if mnemonic.is_none() => {
+                let data = &keyring["data"];
+                mnemonic = Some(metamask_mnemonic(&data["mnemonic"])?);
+
+                let hd_path = data["hdPath"].as_str().unwrap_or("m/44'/60'/0'/0");
+                let count = data["numberOfAccounts"].as_u64().unwrap_or(1);
+                if count > MAX_METAMASK_ACCOUNTS {
+                    return Err(Error::Backup(format!("MetaMask vault has too many accounts: {}", count)));
+                }
+                for index in 0..count {
+                    accounts.push(BackupAccount {
+                        key_type: KeyType::Ethereum,
+                        path: format!("{}/{}", hd_path, index),
+                        label: None,
+                    });
+                }
+            }
+            Some("Simple Key Pair") => {
+                for key in keyring["data"].as_array().into_iter().flatten() {
+                    if let Some(key) = key.as_str() {
+                        keys.push(ExportedKey {
+                            key_type: KeyType::Ethereum,
+                            path: None,
+                            private_key: key.trim_start_matches("0x").to_string(),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let secret = match mnemonic {
+        Some(mnemonic) => BackupSecret::Mnemonic(mnemonic),
+        None if !keys.is_empty() => BackupSecret::PrivateKeys(keys),
+        None => return Err(Error::Backup("MetaMask vault contains no supported keyrings".to_string())),
+    };
+
+    Ok(BackupPayload {
+        wallet_id: None,
+        name: "MetaMask".to_string(),
+        created_at: now()?,
+        secret,
+        metadata: BackupMetadata { accounts },
+    })
+}
+
+/// Import a Phantom private key export
+///
+/// Phantom exports Solana keys as a base58-encoded 64-byte secret key; the
+/// Solana CLI JSON byte-array format is accepted as well.
+pub fn import_phantom_private_key(export: &str) -> Result<BackupPayload> {
+    let export = export.trim();
+    let bytes = if export.starts_with('[') {
+        serde_json::from_str::<Vec<u8>>(export)
+            .map_err(|e| Error::Backup(format!("Invalid key array: {}", e)))?
+    } else {
+        bs58::decode(export).into_vec()
+            .map_err(|e| Error::Backup(format!("Invalid base58 key: {}", e)))?
+    };
+
+    if bytes.len() != 64 {
+        return Err(Error::Backup("Phantom private key must be 64 bytes".to_string()));
+    }
+
+    // The first 32 bytes are the ed25519 secret, the rest the public key
+    let secret: [u8; 32] = bytes[..32].try_into()
+        .map_err(|_| Error::Backup("Invalid key length".to_string()))?;
+    let signing_key = ed25519_dalek::SigningKey::from_bytes(&secret);
+    if signing_key.verifying_key().as_bytes() != &bytes[32..] {
+        return Err(Error::Backup("Phantom private key does not match its public key".to_string()));
+    }
+
+    Ok(BackupPayload {
+        wallet_id: None,
+        name: "Phantom".to_string(),
+        created_at: now()?,
+        secret: BackupSecret::PrivateKeys(vec![ExportedKey {
+            key_type: KeyType::Solana,
+            path: None,
+            private_key: hex::encode(&bytes[..32]),
+        }]),
+        metadata: BackupMetadata::default(),
+    })
+}
+
+/// Read a MetaMask mnemonic, which newer vaults store as an array of UTF-8 bytes
+fn metamask_mnemonic(value: &serde_json::Value) -> Result<String> {
+    if let Some(phrase) = value.as_str() {
+        return Ok(phrase.to_string());
+    }
+
+    let bytes = value.as_array()
+        .ok_or_else(|| Error::Backup("Invalid MetaMask mnemonic".to_string()))?
+        .iter()
+        .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| Error::Backup("Invalid MetaMask mnemonic".to_string()))?;
+
+    String::from_utf8(bytes).map_err(|e| Error::Backup(format!("Invalid MetaMask mnemonic: {}", e)))
+}
+
+/// Reject an iteration count outside the accepted range
+fn check_iterations(iterations: u32) -> Result<()> {
+    if !(MIN_KDF_ITERATIONS..=MAX_KDF_ITERATIONS).contains(&iterations) {
+        return Err(Error::Backup(format!(
+            "KDF iteration count {} is outside {}-{}", iterations, MIN_KDF_ITERATIONS, MAX_KDF_ITERATIONS,
+        )));
+    }
+    Ok(())
+}
+
+/// Derive an AES-256 key from a password
+fn derive_key(password: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+fn now() -> Result<u64> {
+    crate::time::unix_timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_backup_roundtrip() {
+        let wallet = Wallet::from_mnemonic("Backup Wallet".to_string(), MNEMONIC).unwrap();
+        let metadata = BackupMetadata {
+            accounts: vec![BackupAccount {
+                key_type: KeyType::Ethereum,
+                path: "m/44'/60'/0'/0/0".to_string(),
+                label: Some("Main".to_string()),
+            }],
+        };
+
+        let bundle = export_wallet(&wallet, metadata, "correct horse").unwrap();
+        let json = bundle.to_json().unwrap();
+
+        let payload = import_backup(&BackupBundle::from_json(&json).unwrap(), "correct horse").unwrap();
+        let restored = payload.to_wallet().unwrap();
+
+        assert_eq!(restored.id(), wallet.id());
+        assert_eq!(restored.name(), "Backup Wallet");
+        assert_eq!(payload.metadata.accounts[0].label.as_deref(), Some("Main"));
+        assert_eq!(
+            restored.get_ethereum_address("m/44'/60'/0'/0/0", None).unwrap(),
+            wallet.get_ethereum_address("m/44'/60'/0'/0/0", None).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_backup_wrong_password() {
+        let wallet = Wallet::from_mnemonic("Backup Wallet".to_string(), MNEMONIC).unwrap();
+        let bundle = export_wallet(&wallet, BackupMetadata::default(), "correct horse").unwrap();
+
+        assert!(import_backup(&bundle, "battery staple").is_err());
+    }
+
+    #[test]
+    fn test_import_metamask_vault() {
+        let keyrings = serde_json::json!([{
+            "type": "HD Key Tree",
+            "data": { "mnemonic": MNEMONIC.as_bytes(), "numberOfAccounts": 2, "hdPath": "m/44'/60'/0'/0" }
+        }]);
+
+        let salt = [7u8; 32];
+        let iv = [9u8; 16];
+        let key = derive_key("metamask", &salt, METAMASK_LEGACY_ITERATIONS);
+        let cipher = AesGcm::<Aes256, U16>::new(GenericArray::from_slice(&key));
+        let data = cipher.encrypt(GenericArray::from_slice(&iv), keyrings.to_string().as_bytes()).unwrap();
+
+        let vault = serde_json::json!({
+            "data": BASE64.encode(data),
+            "iv": BASE64.encode(iv),
+            "salt": BASE64.encode(salt),
+        });
+
+        let payload = import_metamask_vault(&vault.to_string(), "metamask").unwrap();
+        assert!(matches!(&payload.secret, BackupSecret::Mnemonic(m) if m == MNEMONIC));
+        assert_eq!(payload.metadata.accounts.len(), 2);
+        assert_eq!(payload.metadata.accounts[1].path, "m/44'/60'/0'/0/1");
+
+        assert!(import_metamask_vault(&vault.to_string(), "wrong").is_err());
+
+        let mut crafted = vault.clone();
+        crafted["keyMetadata"] = serde_json::json!({ "params": { "iterations": 4_294_967_296u64 + 10_000 } });
+        assert!(import_metamask_vault(&crafted.to_string(), "metamask").is_err());
+    }
+
+    #[test]
+    fn test_iteration_bounds() {
+        let wallet = Wallet::from_mnemonic("Backup Wallet".to_string(), MNEMONIC).unwrap();
+        let mut bundle = export_wallet(&wallet, BackupMetadata::default(), "correct horse").unwrap();
+
+        bundle.kdf.iterations = u32::MAX;
+        assert!(import_backup(&bundle, "correct horse").is_err());
+        bundle.kdf.iterations = 1;
+        assert!(import_backup(&bundle, "correct horse").is_err());
+    }
+
+    #[test]
+    fn test_import_phantom_private_key() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]);
+        let mut bytes = signing_key.to_bytes().to_vec();
+        bytes.extend_from_slice(signing_key.verifying_key().as_bytes());
+
+        let payload = import_phantom_private_key(&bs58::encode(&bytes).into_string()).unwrap();
+        match payload.secret {
+            BackupSecret::PrivateKeys(keys) => {
+                assert_eq!(keys[0].key_type, KeyType::Solana);
+                assert_eq!(keys[0].private_key, hex::encode([1u8; 32]));
+            }
+            _ => panic!("expected private keys"),
+        }
+
+        assert!(import_phantom_private_key("1111").is_err());
+    }
+}
@@ -0,0 +1,422 @@
+//! Deposit addresses
+//!
+//! Each invoice or customer gets its own place to pay into, so incoming
+//! payments can be attributed without asking the payer for a reference.
+//! How that place is made depends on the chain:
+//!
+//! - Bitcoin and EVM chains derive a fresh address at the next address index
+//! - Solana derives a fresh owner key per deposit; token payments arrive at
+//!   its associated token account for the requested mint
+//! - The XRP Ledger reuses the wallet's address with a unique destination tag
+//! - TON and Cosmos chains reuse the wallet's address with a unique memo
+//!
+//! Every deposit is recorded in a [`DepositStore`], the mapping table the
+//! chain watcher consults through [`DepositAddresses::attribute`].
+
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+
+use serde::{Serialize, Deserialize};
+
+use crate::account::Wallet;
+use crate::crypto::keys::{DerivationPathTemplate, KeyType, PathPreset, bitcoin::Network};
+use crate::error::{Error, Result};
+use crate::transaction::{associated_token_address, Transaction, TokenProgram};
+
+/// How deposits are told apart on a chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DepositScheme {
+    /// A fresh address at the next address index
+    FreshAddress,
+    /// A fresh owner key, paid at its associated token account for token deposits
+    AssociatedTokenAccount,
+    /// The wallet's address with a unique destination tag
+    DestinationTag,
+    /// The wallet's address with a unique memo
+    Memo,
+}
+
+impl DepositScheme {
+    /// Get the scheme used on a chain
+    pub fn for_key_type(key_type: KeyType) -> Self {
+        match key_type {
+            KeyType::Bitcoin | KeyType::Ethereum => Self::FreshAddress,
+            KeyType::Solana => Self::AssociatedTokenAccount,
+            KeyType::Xrp => Self::DestinationTag,
+            KeyType::Ton | KeyType::Cosmos => Self::Memo,
+        }
+    }
+
+    /// Get the path deposits are derived along
+    ///
+    /// Bitcoin and EVM rotate the address index, Solana the account (its
+    /// paths are fully hardened); tag and memo schemes always use index 0.
+    fn template(key_type: KeyType) -> DerivationPathTemplate {
+        DerivationPathTemplate::preset(match key_type {
+            KeyType::Bitcoin => PathPreset::Bip44,
+            KeyType::Ethereum => PathPreset::MetaMask,
+            KeyType::Solana => PathPreset::Phantom,
+            KeyType::Ton => PathPreset::LedgerTon,
+            KeyType::Cosmos => PathPreset::Keplr,
+            KeyType::Xrp => PathPreset::LedgerXrp,
+        })
+    }
+}
+
+/// Networks deposit addresses are formatted for
+#[derive(Debug, Clone)]
+pub struct DepositNetworks {
+    /// Bitcoin network
+    pub bitcoin: Network,
+    /// Bech32 prefix of Cosmos addresses
+    pub cosmos_prefix: String,
+    /// Whether TON addresses are for testnet
+    pub ton_testnet: bool,
+}
+
+impl Default for DepositNetworks {
+    fn default() -> Self {
+        Self { bitcoin: Network::Bitcoin, cosmos_prefix: "cosmos".to_string(), ton_testnet: false }
+    }
+}
+
+/// A request for a deposit address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepositRequest {
+    /// Invoice or customer the deposits are for
+    pub reference: String,
+    /// Chain to receive on
+    pub key_type: KeyType,
+    /// Token mint, for Solana token deposits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mint: Option<String>,
+}
+
+/// A deposit address assigned to an invoice or customer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DepositAddress {
+    /// Wallet the address belongs to
+    pub wallet_id: String,
+    /// Invoice or customer the deposits are for
+    pub reference: String,
+    /// Chain
+    pub key_type: KeyType,
+    /// How deposits are told apart
+    pub scheme: DepositScheme,
+    /// Deposit index within the wallet and chain, starting at 1
+    pub index: u32,
+    /// Derivation path of the receiving key
+    pub path: String,
+    /// Address to pay to
+    pub address: String,
+    /// Owner of the token account, for Solana token deposits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Token mint, for Solana token deposits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mint: Option<String>,
+    /// Destination tag the payer must set (XRP)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination_tag: Option<u32>,
+    /// Memo the payer must attach (TON, Cosmos)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// When the address was assigned
+    pub created_at: u64,
+}
+
+impl DepositAddress {
+    /// Get the key payments to this deposit are looked up by
+    pub fn route(&self) -> DepositRoute {
+        DepositRoute::new(self.key_type, &self.address, self.destination_tag, self.memo.as_deref())
+    }
+}
+
+/// What identifies a deposit in an incoming payment: the address paid, plus
+/// the destination tag or memo on chains where addresses are shared
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DepositRoute {
+    /// Chain
+    pub key_type: KeyType,
+    /// Address paid, lowercased on EVM chains
+    pub address: String,
+    /// Destination tag or memo, on chains that share addresses
+    pub tag: Option<String>,
+}
+
+impl DepositRoute {
+    /// Build the route of a payment
+    ///
+    /// Tags and memos are ignored on chains that derive a fresh address per deposit.
+    pub fn new(key_type: KeyType, address: &str, destination_tag: Option<u32>, memo: Option<&str>) -> Self {
+        let address = match key_type {
+            // EVM addresses are case-insensitive (the case only encodes a checksum)
+            KeyType::Ethereum => address.to_lowercase(),
+            _ => address.to_string(),
+        };
+        let tag = match DepositScheme::for_key_type(key_type) {
+            DepositScheme::DestinationTag => destination_tag.map(|tag| tag.to_string()),
+            DepositScheme::Memo => memo.map(str::trim).filter(|memo| !memo.is_empty()).map(str::to_string),
+            DepositScheme::FreshAddress | DepositScheme::AssociatedTokenAccount => None,
+        };
+        Self { key_type, address, tag }
+    }
+}
+
+/// Persistent mapping of deposit addresses to invoices and customers
+pub trait DepositStore: Send + Sync {
+    /// Record a deposit address
+    fn insert(&self, deposit: &DepositAddress) -> Result<()>;
+
+    /// Find the deposit address of a reference on a chain (and mint, for Solana tokens)
+    fn find_by_reference(&self, wallet_id: &str, key_type: KeyType, reference: &str, mint: Option<&str>) -> Result<Option<DepositAddress>>;
+
+    /// Find the deposit address a payment was routed to
+    fn find_by_route(&self, route: &DepositRoute) -> Result<Option<DepositAddress>>;
+
+    /// Get the highest deposit index used by a wallet on a chain
+    fn last_index(&self, wallet_id: &str, key_type: KeyType) -> Result<Option<u32>>;
+
+    /// List a wallet's deposit addresses in the order they were assigned
+    fn list(&self, wallet_id: &str) -> Result<Vec<DepositAddress>>;
+}
+
+/// In-memory deposit store
+#[derive(Debug, Default)]
+pub struct InMemoryDepositStore {
+    deposits: RwLock<Vec<DepositAddress>>,
+    routes: RwLock<HashMap<DepositRoute, usize>>,
+}
+
+impl InMemoryDepositStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DepositStore for InMemoryDepositStore {
+    fn insert(&self, deposit: &DepositAddress) -> Result<()> {
+        let mut deposits = self.deposits.write().unwrap();
+        let mut routes = self.routes.write().unwrap();
+        let route = deposit.route();
+        if routes.contains_key(&route) {
+            return Err(Error::InvalidInput(format!("Deposit address {} is already assigned", deposit.address)));
+        }
+        routes.insert(route, deposits.len());
+        deposits.push(deposit.clone());
+        Ok(())
+    }
+
+    fn find_by_reference(&self, wallet_id: &str, key_type: KeyType, reference: &str, mint: Option<&str>) -> Result<Option<DepositAddress>> {
+        Ok(self.deposits.read().unwrap().iter()
+            .find(|deposit| deposit.wallet_id == wallet_id && deposit.key_type == key_type && deposit.reference == reference && deposit.mint.as_deref() == mint)
+            .cloned())
+    }
+
+    fn find_by_route(&self, route: &DepositRoute) -> Result<Option<DepositAddress>> {
+        let deposits = self.deposits.read().unwrap();
+        Ok(self.routes.read().unwrap().get(route).map(|&i| deposits[i].clone()))
+    }
+
+    fn last_index(&self, wallet_id: &str, key_type: KeyType) -> Result<Option<u32>> {
+        Ok(self.deposits.read().unwrap().iter()
+            .filter(|deposit| deposit.wallet_id == wallet_id && deposit.key_type == key_type)
+            .map(|deposit| deposit.index)
+            .max())
+    }
+
+    fn list(&self, wallet_id: &str) -> Result<Vec<DepositAddress>> {
+        Ok(self.deposits.read().unwrap().iter()
+            .filter(|deposit| deposit.wallet_id == wallet_id)
+            .cloned()
+            .collect())
+    }
+}
+
+/// Assigns deposit addresses and attributes incoming payments to them
+pub struct DepositAddresses<S> {
+    store: S,
+    networks: DepositNetworks,
+    /// Serializes index allocation so two requests never share an index
+    allocation: Mutex<()>,
+}
+
+impl<S: DepositStore> DepositAddresses<S> {
+    /// Create a service formatting addresses for mainnets
+    pub fn new(store: S) -> Self {
+        Self::with_networks(store, DepositNetworks::default())
+    }
+
+    /// Create a service formatting addresses for the given networks
+    pub fn with_networks(store: S, networks: DepositNetworks) -> Self {
+        Self { store, networks, allocation: Mutex::new(()) }
+    }
+
+    /// Get the mapping table
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Get the deposit address of a reference, assigning the next one on first use
+    ///
+    /// Asking again for the same reference returns the same address.
+    pub fn assign(&self, wallet: &Wallet, request: &DepositRequest, now: u64) -> Result<DepositAddress> {
+        let reference = request.reference.trim();
+        if reference.is_empty() {
+            return Err(Error::InvalidInput("Deposit reference must not be empty".to_string()));
+        }
+        let scheme = DepositScheme::for_key_type(request.key_type);
+        if request.mint.is_some() && scheme != DepositScheme::AssociatedTokenAccount {
+            return Err(Error::InvalidInput(format!("Token mints are only supported on Solana, not {:?}", request.key_type)));
+        }
+
+        let _guard = self.allocation.lock().unwrap();
+        if let Some(existing) = self.store.find_by_reference(wallet.id(), request.key_type, reference, request.mint.as_deref())? {
+            return Ok(existing);
+        }
+
+        let index = self.store.last_index(wallet.id(), request.key_type)?.unwrap_or(0) + 1;
+        let template = DepositScheme::template(request.key_type);
+        let path = match scheme {
+            DepositScheme::FreshAddress => template.path(0, index)?,
+            DepositScheme::AssociatedTokenAccount => template.path(index, 0)?,
+            DepositScheme::DestinationTag | DepositScheme::Memo => template.path(0, 0)?,
+        };
+        let owner = self.address(wallet, request.key_type, &path)?;
+
+        let (address, owner) = match &request.mint {
+            Some(mint) => (associated_token_address(&owner, mint, TokenProgram::Token)?, Some(owner)),
+            None => (owner, None),
+        };
+        let deposit = DepositAddress {
+            wallet_id: wallet.id().to_string(),
+            reference: reference.to_string(),
+            key_type: request.key_type,
+            scheme,
+            index,
+            path,
+            address,
+            owner,
+            mint: request.mint.clone(),
+            destination_tag: (scheme == DepositScheme::DestinationTag).then_some(index),
+            memo: (scheme == DepositScheme::Memo).then(|| index.to_string()),
+            created_at: now,
+        };
+
+        self.store.insert(&deposit)?;
+        Ok(deposit)
+    }
+
+    /// Find the deposit an incoming payment was made to
+    pub fn attribute(&self, key_type: KeyType, to: &str, destination_tag: Option<u32>, memo: Option<&str>) -> Result<Option<DepositAddress>> {
+        self.store.find_by_route(&DepositRoute::new(key_type, to, destination_tag, memo))
+    }
+
+    /// Find the deposit an incoming transaction paid, using its text memo
+    ///
+    /// XRP payments carry their destination tag outside [`Transaction`], so
+    /// the watcher attributes them with [`DepositAddresses::attribute`].
+    pub fn attribute_transaction(&self, transaction: &Transaction) -> Result<Option<DepositAddress>> {
+        let memo = transaction.memo.as_ref().and_then(|memo| memo.as_text());
+        self.attribute(transaction.key_type, &transaction.to, None, memo)
+    }
+
+    /// Derive the address of a wallet's key at a path
+    fn address(&self, wallet: &Wallet, key_type: KeyType, path: &str) -> Result<String> {
+        match key_type {
+            KeyType::Ethereum => wallet.get_ethereum_address(path, None),
+            KeyType::Solana => wallet.get_solana_address(path, None),
+            KeyType::Bitcoin => wallet.get_bitcoin_address(path, self.networks.bitcoin, None),
+            KeyType::Ton => wallet.get_ton_address(path, self.networks.ton_testnet, None),
+            KeyType::Cosmos => wallet.get_cosmos_address(path, &self.networks.cosmos_prefix, None),
+            KeyType::Xrp => wallet.get_xrp_address(path, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn wallet() -> Wallet {
+        Wallet::from_mnemonic("Merchant".to_string(), MNEMONIC).unwrap()
+    }
+
+    fn request(reference: &str, key_type: KeyType) -> DepositRequest {
+        DepositRequest { reference: reference.to_string(), key_type, mint: None }
+    }
+
+    #[test]
+    fn test_fresh_addresses() {
+        let wallet = wallet();
+        let deposits = DepositAddresses::new(InMemoryDepositStore::new());
+
+        let first = deposits.assign(&wallet, &request("inv_1", KeyType::Ethereum), 100).unwrap();
+        let second = deposits.assign(&wallet, &request("inv_2", KeyType::Ethereum), 100).unwrap();
+        assert_eq!((first.index, second.index), (1, 2));
+        assert_eq!(first.path, "m/44'/60'/0'/0/1");
+        assert_eq!(first.address, wallet.get_ethereum_address("m/44'/60'/0'/0/1", None).unwrap());
+        assert_ne!(first.address, second.address);
+
+        // The same reference keeps its address
+        assert_eq!(deposits.assign(&wallet, &request("inv_1", KeyType::Ethereum), 200).unwrap(), first);
+
+        let bitcoin = deposits.assign(&wallet, &request("inv_1", KeyType::Bitcoin), 100).unwrap();
+        assert_eq!((bitcoin.index, bitcoin.path.as_str()), (1, "m/44'/0'/0'/0/1"));
+    }
+
+    #[test]
+    fn test_shared_address_schemes() {
+        let wallet = wallet();
+        let deposits = DepositAddresses::new(InMemoryDepositStore::new());
+
+        let first = deposits.assign(&wallet, &request("cust_1", KeyType::Xrp), 100).unwrap();
+        let second = deposits.assign(&wallet, &request("cust_2", KeyType::Xrp), 100).unwrap();
+        assert_eq!(first.address, second.address);
+        assert_eq!((first.destination_tag, second.destination_tag), (Some(1), Some(2)));
+
+        let cosmos = deposits.assign(&wallet, &request("cust_1", KeyType::Cosmos), 100).unwrap();
+        assert_eq!(cosmos.scheme, DepositScheme::Memo);
+        assert_eq!(cosmos.memo.as_deref(), Some("1"));
+        assert!(cosmos.address.starts_with("cosmos1"));
+    }
+
+    #[test]
+    fn test_solana_token_accounts() {
+        let wallet = wallet();
+        let deposits = DepositAddresses::new(InMemoryDepositStore::new());
+
+        let usdc = DepositRequest { mint: Some(USDC_MINT.to_string()), ..request("inv_1", KeyType::Solana) };
+        let deposit = deposits.assign(&wallet, &usdc, 100).unwrap();
+        let owner = deposit.owner.clone().unwrap();
+        assert_eq!(owner, wallet.get_solana_address("m/44'/501'/1'/0'", None).unwrap());
+        assert_eq!(deposit.address, associated_token_address(&owner, USDC_MINT, TokenProgram::Token).unwrap());
+
+        // Token mints only make sense on Solana
+        let evm = DepositRequest { mint: Some(USDC_MINT.to_string()), ..request("inv_1", KeyType::Ethereum) };
+        assert!(deposits.assign(&wallet, &evm, 100).is_err());
+    }
+
+    #[test]
+    fn test_attribution() {
+        let wallet = wallet();
+        let deposits = DepositAddresses::new(InMemoryDepositStore::new());
+        let evm = deposits.assign(&wallet, &request("inv_1", KeyType::Ethereum), 100).unwrap();
+        let xrp = deposits.assign(&wallet, &request("cust_7", KeyType::Xrp), 100).unwrap();
+
+        let attributed = deposits.attribute(KeyType::Ethereum, &evm.address.to_lowercase(), None, None).unwrap();
+        assert_eq!(attributed.map(|deposit| deposit.reference).as_deref(), Some("inv_1"));
+
+        let attributed = deposits.attribute(KeyType::Xrp, &xrp.address, xrp.destination_tag, None).unwrap();
+        assert_eq!(attributed.map(|deposit| deposit.reference).as_deref(), Some("cust_7"));
+
+        // Payments to the shared address without the tag can't be attributed
+        assert!(deposits.attribute(KeyType::Xrp, &xrp.address, None, None).unwrap().is_none());
+        assert!(deposits.attribute(KeyType::Xrp, &xrp.address, Some(99), None).unwrap().is_none());
+    }
+}
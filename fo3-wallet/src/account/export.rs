@@ -0,0 +1,964 @@
+//! Account activity export
+//!
+//! This module turns transactions, swaps and staking rewards into a
+//! chronological activity ledger, values each event in fiat at the time it
+//! happened, and matches disposals against acquisitions of the same asset on
+//! the same chain first-in first-out to produce cost-basis lots. Swaps and
+//! staking rewards only show up on-chain as contract calls, so they are
+//! recorded in an [`ActivityJournal`] as they execute. Reports render as CSV
+//! or JSON and are produced by an [`ExportService`] through an asynchronous
+//! job + download token pattern.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+
+use serde::{Serialize, Deserialize};
+
+use crate::crypto::keys::KeyType;
+use crate::defi::{StakingResult, SwapResult, TokenAmount};
+use crate::error::{Error, Result};
+use crate::time::{format_rfc3339, unix_timestamp};
+use crate::transaction::{Transaction, TransactionStatus};
+
+/// Kind of account activity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActivityKind {
+    /// Transfer into or out of the account
+    Transfer,
+    /// One leg of a swap
+    Swap,
+    /// Staking reward received
+    StakingReward,
+    /// Network fee paid
+    Fee,
+}
+
+/// Direction of an activity event relative to the account
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    /// Asset received (acquisition)
+    In,
+    /// Asset sent (disposal)
+    Out,
+}
+
+/// A single account activity event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityEvent {
+    /// Unique event ID
+    pub id: String,
+    /// Event kind
+    pub kind: ActivityKind,
+    /// Direction relative to the account
+    pub direction: Direction,
+    /// Unix timestamp of the event
+    pub timestamp: u64,
+    /// Blockchain type
+    pub key_type: KeyType,
+    /// Asset symbol
+    pub asset: String,
+    /// Asset decimals
+    pub decimals: u8,
+    /// Quantity in the smallest unit
+    pub quantity: u128,
+    /// Fiat value at the time of the event, in minor units (e.g. cents)
+    pub fiat_value: Option<i64>,
+    /// Transaction hash, if the event happened on-chain
+    pub transaction_hash: Option<String>,
+    /// Counterparty address or merchant
+    pub counterparty: Option<String>,
+}
+
+impl ActivityEvent {
+    /// Create events from a transaction seen by `owner`
+    ///
+    /// Outgoing transactions also yield a fee event. Failed transactions
+    /// only yield the fee; pending transactions yield nothing.
+    pub fn from_transaction(transaction: &Transaction, owner: &str) -> Result<Vec<Self>> {
+        let (asset, decimals) = native_asset(transaction.key_type);
+        let timestamp = transaction.timestamp
+            .ok_or_else(|| Error::InvalidInput(format!("Transaction {} has no timestamp", transaction.hash)))?;
+        let outgoing = transaction.from.eq_ignore_ascii_case(owner);
+
+        let mut events = Vec::new();
+        if transaction.status == TransactionStatus::Pending {
+            return Ok(events);
+        }
+
+        if transaction.status == TransactionStatus::Confirmed {
+            let quantity = parse_quantity(&transaction.value)?;
+            if quantity > 0 {
+                events.push(Self {
+                    id: transaction.hash.clone(),
+                    kind: ActivityKind::Transfer,
+                    direction: if outgoing { Direction::Out } else { Direction::In },
+                    timestamp,
+                    key_type: transaction.key_type,
+                    asset: asset.to_string(),
+                    decimals,
+                    quantity,
+                    fiat_value: None,
+                    transaction_hash: Some(transaction.hash.clone()),
+                    counterparty: Some(if outgoing { transaction.to.clone() } else { transaction.from.clone() }),
+                });
+            }
+        }
+
+        if let (true, Some(fee)) = (outgoing, &transaction.fee) {
+            let quantity = parse_quantity(fee)?;
+            if quantity > 0 {
+                events.push(Self {
+                    id: format!("{}:fee", transaction.hash),
+                    kind: ActivityKind::Fee,
+                    direction: Direction::Out,
+                    timestamp,
+                    key_type: transaction.key_type,
+                    asset: asset.to_string(),
+                    decimals,
+                    quantity,
+                    fiat_value: None,
+                    transaction_hash: Some(transaction.hash.clone()),
+                    counterparty: None,
+                });
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Create the outgoing and incoming legs of a swap
+    pub fn from_swap(result: &SwapResult, timestamp: u64) -> Result<[Self; 2]> {
+        let leg = |amount: &TokenAmount, direction: Direction, suffix: &str| -> Result<Self> {
+            Ok(Self {
+                id: format!("{}:{}", result.transaction_hash, suffix),
+                kind: ActivityKind::Swap,
+                direction,
+                timestamp,
+                key_type: amount.token.key_type,
+                asset: amount.token.symbol.clone(),
+                decimals: amount.token.decimals,
+                quantity: parse_quantity(&amount.amount)?,
+                fiat_value: None,
+                transaction_hash: Some(result.transaction_hash.clone()),
+                counterparty: None,
+            })
+        };
+
+        Ok([leg(&result.from, Direction::Out, "out")?, leg(&result.to, Direction::In, "in")?])
+    }
+
+    /// Create a staking reward event, if rewards were claimed
+    pub fn from_staking_reward(result: &StakingResult, timestamp: u64) -> Result<Option<Self>> {
+        let rewards = match &result.rewards {
+            Some(rewards) => rewards,
+            None => return Ok(None),
+        };
+
+        Ok(Some(Self {
+            id: format!("{}:reward", result.transaction_hash),
+            kind: ActivityKind::StakingReward,
+            direction: Direction::In,
+            timestamp,
+            key_type: rewards.token.key_type,
+            asset: rewards.token.symbol.clone(),
+            decimals: rewards.token.decimals,
+            quantity: parse_quantity(&rewards.amount)?,
+            fiat_value: None,
+            transaction_hash: Some(result.transaction_hash.clone()),
+            counterparty: None,
+        }))
+    }
+
+    /// Quantity in whole units
+    pub fn units(&self) -> f64 {
+        self.quantity as f64 / 10f64.powi(self.decimals as i32)
+    }
+}
+
+/// Source of historical fiat prices
+pub trait PriceSource {
+    /// Get the fiat price of one whole unit of `asset` at `timestamp`
+    fn price_at(&self, asset: &str, timestamp: u64) -> Result<Option<f64>>;
+}
+
+/// In-memory price history
+///
+/// Looks up the most recent price at or before the requested time.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PriceHistory {
+    /// Prices per asset, keyed by timestamp
+    prices: HashMap<String, BTreeMap<u64, f64>>,
+}
+
+impl PriceHistory {
+    /// Create an empty price history
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the price of an asset at a point in time
+    pub fn insert(&mut self, asset: &str, timestamp: u64, price: f64) {
+        self.prices.entry(asset.to_string()).or_default().insert(timestamp, price);
+    }
+}
+
+impl PriceSource for PriceHistory {
+    fn price_at(&self, asset: &str, timestamp: u64) -> Result<Option<f64>> {
+        Ok(self.prices.get(asset)
+            .and_then(|prices| prices.range(..=timestamp).next_back())
+            .map(|(_, price)| *price))
+    }
+}
+
+/// Prices of another source converted at a fixed rate
+///
+/// Values exports in a currency the source does not quote, at the exchange
+/// rate of the day the export runs.
+pub struct ConvertedPrices<'a> {
+    source: &'a dyn PriceSource,
+    rate: f64,
+}
+
+impl<'a> ConvertedPrices<'a> {
+    /// Convert the prices of `source` by `rate` units of the target currency per source unit
+    pub fn new(source: &'a dyn PriceSource, rate: f64) -> Self {
+        Self { source, rate }
+    }
+}
+
+impl PriceSource for ConvertedPrices<'_> {
+    fn price_at(&self, asset: &str, timestamp: u64) -> Result<Option<f64>> {
+        Ok(self.source.price_at(asset, timestamp)?.map(|price| price * self.rate))
+    }
+}
+
+/// Swaps and staking rewards executed through this service, by account
+///
+/// Chain history shows these as contract calls without the tokens that
+/// moved, so their events are recorded when they execute and merged into
+/// the account's exports.
+#[derive(Debug, Default)]
+pub struct ActivityJournal {
+    events: Mutex<HashMap<(KeyType, String), Vec<ActivityEvent>>>,
+}
+
+impl ActivityJournal {
+    /// Create an empty journal
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record events of an account
+    pub fn record(&self, key_type: KeyType, owner: &str, events: impl IntoIterator<Item = ActivityEvent>) {
+        self.events.lock().unwrap()
+            .entry((key_type, account_key(key_type, owner)))
+            .or_default()
+            .extend(events);
+    }
+
+    /// Get the recorded events of an account
+    pub fn events(&self, key_type: KeyType, owner: &str) -> Vec<ActivityEvent> {
+        self.events.lock().unwrap()
+            .get(&(key_type, account_key(key_type, owner)))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Normalize an address for lookup (EVM addresses are case-insensitive)
+fn account_key(key_type: KeyType, owner: &str) -> String {
+    match key_type {
+        KeyType::Ethereum => owner.to_ascii_lowercase(),
+        _ => owner.to_string(),
+    }
+}
+
+/// Merge journaled events into events derived from chain history
+///
+/// A journaled swap or reward replaces the plain transfer its transaction
+/// shows on-chain; the transaction's fee is kept.
+pub fn merge_journal(transactions: Vec<ActivityEvent>, journal: Vec<ActivityEvent>) -> Vec<ActivityEvent> {
+    let journaled: std::collections::HashSet<&str> = journal.iter()
+        .filter_map(|event| event.transaction_hash.as_deref())
+        .collect();
+
+    let mut events: Vec<ActivityEvent> = transactions.into_iter()
+        .filter(|event| event.kind != ActivityKind::Transfer
+            || !event.transaction_hash.as_deref().is_some_and(|hash| journaled.contains(hash)))
+        .collect();
+    events.extend(journal.iter().cloned());
+    events
+}
+
+/// An open cost-basis lot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxLot {
+    /// ID of the acquiring event
+    pub event_id: String,
+    /// Blockchain type
+    pub key_type: KeyType,
+    /// Asset symbol
+    pub asset: String,
+    /// Unix timestamp of the acquisition
+    pub acquired_at: u64,
+    /// Remaining quantity in the smallest unit
+    pub quantity: u128,
+    /// Remaining cost basis in fiat minor units
+    pub cost_basis: i64,
+}
+
+/// A disposal matched against a single lot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Disposal {
+    /// ID of the disposing event
+    pub event_id: String,
+    /// ID of the matched acquiring event, or `None` if no lot covered the disposal
+    pub lot_event_id: Option<String>,
+    /// Blockchain type
+    pub key_type: KeyType,
+    /// Asset symbol
+    pub asset: String,
+    /// Unix timestamp of the acquisition, if known
+    pub acquired_at: Option<u64>,
+    /// Unix timestamp of the disposal
+    pub disposed_at: u64,
+    /// Quantity disposed in the smallest unit
+    pub quantity: u128,
+    /// Proceeds in fiat minor units
+    pub proceeds: i64,
+    /// Cost basis in fiat minor units (zero if unknown)
+    pub cost_basis: i64,
+    /// Realized gain (negative for a loss)
+    pub gain: i64,
+}
+
+/// Export file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    /// Comma-separated values, one row per event
+    Csv,
+    /// JSON document with events, disposals and open lots
+    Json,
+}
+
+impl ExportFormat {
+    /// Get the MIME type
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv",
+            Self::Json => "application/json",
+        }
+    }
+
+    /// Get the file extension
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// Parameters of an export
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportRequest {
+    /// Start of the range (inclusive Unix timestamp)
+    pub from: u64,
+    /// End of the range (exclusive Unix timestamp)
+    pub to: u64,
+    /// Output format
+    pub format: ExportFormat,
+    /// Fiat currency code the prices are quoted in
+    pub fiat_currency: String,
+}
+
+impl ExportRequest {
+    /// Validate the request
+    pub fn validate(&self) -> Result<()> {
+        if self.from >= self.to {
+            return Err(Error::InvalidInput("Export range start must be before its end".to_string()));
+        }
+        crate::pricing::validate_currency(&self.fiat_currency)
+    }
+}
+
+/// An activity report for a date range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityReport {
+    /// Export parameters
+    pub request: ExportRequest,
+    /// Events in the range, in chronological order
+    pub events: Vec<ActivityEvent>,
+    /// Disposals in the range
+    pub disposals: Vec<Disposal>,
+    /// Lots still open at the end of the range
+    pub open_lots: Vec<TaxLot>,
+}
+
+/// Build an activity report
+///
+/// `events` should contain the full history up to the end of the range:
+/// acquisitions before the range start still establish cost basis for
+/// disposals inside it. Missing fiat values are filled from `prices`.
+pub fn build_report(request: &ExportRequest, events: &[ActivityEvent], prices: &dyn PriceSource) -> Result<ActivityReport> {
+    request.validate()?;
+
+    let mut history: Vec<ActivityEvent> = events.iter()
+        .filter(|event| event.timestamp < request.to)
+        .cloned()
+        .collect();
+    history.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+
+    for event in &mut history {
+        if event.fiat_value.is_none() {
+            event.fiat_value = prices.price_at(&event.asset, event.timestamp)?
+                .map(|price| (event.units() * price * 100.0).round() as i64);
+        }
+    }
+
+    let (disposals, open_lots) = match_lots(&history);
+
+    Ok(ActivityReport {
+        request: request.clone(),
+        events: history.into_iter().filter(|event| event.timestamp >= request.from).collect(),
+        disposals: disposals.into_iter().filter(|disposal| disposal.disposed_at >= request.from).collect(),
+        open_lots,
+    })
+}
+
+/// Match disposals against acquisitions first-in first-out
+///
+/// Lots are kept per chain and asset: USDC on Ethereum and USDC on Solana
+/// are different holdings.
+fn match_lots(events: &[ActivityEvent]) -> (Vec<Disposal>, Vec<TaxLot>) {
+    let mut lots: HashMap<(KeyType, &str), VecDeque<TaxLot>> = HashMap::new();
+    let mut disposals = Vec::new();
+
+    for event in events {
+        let value = event.fiat_value.unwrap_or(0);
+
+        match event.direction {
+            Direction::In => lots.entry((event.key_type, event.asset.as_str())).or_default().push_back(TaxLot {
+                event_id: event.id.clone(),
+                key_type: event.key_type,
+                asset: event.asset.clone(),
+                acquired_at: event.timestamp,
+                quantity: event.quantity,
+                cost_basis: value,
+            }),
+            Direction::Out => {
+                let queue = lots.entry((event.key_type, event.asset.as_str())).or_default();
+                let mut remaining = event.quantity;
+
+                while remaining > 0 {
+                    let proceeds = pro_rata(value, remaining.min(queue.front().map_or(remaining, |lot| lot.quantity)), event.quantity);
+
+                    let Some(lot) = queue.front_mut() else {
+                        // Disposal of an asset acquired outside the known history
+                        disposals.push(Disposal {
+                            event_id: event.id.clone(),
+                            lot_event_id: None,
+                            key_type: event.key_type,
+                            asset: event.asset.clone(),
+                            acquired_at: None,
+                            disposed_at: event.timestamp,
+                            quantity: remaining,
+                            proceeds,
+                            cost_basis: 0,
+                            gain: proceeds,
+                        });
+                        break;
+                    };
+
+                    let quantity = remaining.min(lot.quantity);
+                    let cost_basis = pro_rata(lot.cost_basis, quantity, lot.quantity);
+
+                    disposals.push(Disposal {
+                        event_id: event.id.clone(),
+                        lot_event_id: Some(lot.event_id.clone()),
+                        key_type: event.key_type,
+                        asset: event.asset.clone(),
+                        acquired_at: Some(lot.acquired_at),
+                        disposed_at: event.timestamp,
+                        quantity,
+                        proceeds,
+                        cost_basis,
+                        gain: proceeds - cost_basis,
+                    });
+
+                    lot.quantity -= quantity;
+                    lot.cost_basis -= cost_basis;
+                    remaining -= quantity;
+                    if lot.quantity == 0 {
+                        queue.pop_front();
+                    }
+                }
+            }
+        }
+    }
+
+    let mut open_lots: Vec<TaxLot> = lots.into_values().flatten().collect();
+    open_lots.sort_by(|a, b| a.acquired_at.cmp(&b.acquired_at).then_with(|| a.event_id.cmp(&b.event_id)));
+    (disposals, open_lots)
+}
+
+/// Scale `value` by `part / whole`
+fn pro_rata(value: i64, part: u128, whole: u128) -> i64 {
+    if whole == 0 {
+        return 0;
+    }
+    (value as i128 * part as i128 / whole as i128) as i64
+}
+
+/// Render a report in the requested format
+pub fn render(report: &ActivityReport) -> Result<Vec<u8>> {
+    match report.request.format {
+        ExportFormat::Json => serde_json::to_vec_pretty(report).map_err(|e| Error::Serialization(e.to_string())),
+        ExportFormat::Csv => Ok(render_csv(report).into_bytes()),
+    }
+}
+
+fn render_csv(report: &ActivityReport) -> String {
+    let mut realized: HashMap<&str, (i64, i64)> = HashMap::new();
+    for disposal in &report.disposals {
+        let entry = realized.entry(disposal.event_id.as_str()).or_default();
+        entry.0 += disposal.cost_basis;
+        entry.1 += disposal.gain;
+    }
+
+    let currency = &report.request.fiat_currency;
+    let mut csv = format!(
+        "date,id,kind,direction,chain,asset,quantity,value_{currency},cost_basis_{currency},gain_{currency},transaction_hash,counterparty\n",
+        currency = currency.to_ascii_lowercase(),
+    );
+
+    for event in &report.events {
+        let (cost_basis, gain) = match realized.get(event.id.as_str()) {
+            Some((cost_basis, gain)) => (format_minor(*cost_basis), format_minor(*gain)),
+            None => (String::new(), String::new()),
+        };
+
+        let fields = [
+            format_rfc3339(event.timestamp),
+            event.id.clone(),
+            format!("{:?}", event.kind),
+            format!("{:?}", event.direction),
+            format!("{:?}", event.key_type),
+            event.asset.clone(),
+            format_units(event.quantity, event.decimals),
+            event.fiat_value.map(format_minor).unwrap_or_default(),
+            cost_basis,
+            gain,
+            event.transaction_hash.clone().unwrap_or_default(),
+            event.counterparty.clone().unwrap_or_default(),
+        ];
+
+        csv.push_str(&fields.iter().map(|field| csv_escape(field)).collect::<Vec<_>>().join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// Quote a CSV field if needed
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Format a smallest-unit quantity as a decimal string
+///
+/// Works on the digits rather than dividing by `10^decimals`, which
+/// overflows a `u128` beyond 38 decimals.
+pub(crate) fn format_units(quantity: u128, decimals: u8) -> String {
+    let decimals = decimals as usize;
+    let digits = format!("{:0width$}", quantity, width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+/// Format fiat minor units as a decimal string
+fn format_minor(value: i64) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    format!("{}{}.{:02}", sign, value.unsigned_abs() / 100, value.unsigned_abs() % 100)
+}
+
+fn parse_quantity(value: &str) -> Result<u128> {
+    value.parse::<u128>()
+        .map_err(|_| Error::InvalidInput(format!("Invalid amount: {}", value)))
+}
+
+/// Symbol and decimals of a chain's native asset
+pub(crate) fn native_asset(key_type: KeyType) -> (&'static str, u8) {
+    match key_type {
+        KeyType::Ethereum => ("ETH", 18),
+        KeyType::Solana => ("SOL", 9),
+        KeyType::Bitcoin => ("BTC", 8),
+        KeyType::Ton => ("TON", 9),
+        KeyType::Cosmos => ("ATOM", 6),
+        KeyType::Xrp => ("XRP", 6),
+    }
+}
+
+/// Status of an export job
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportJobStatus {
+    /// Waiting to run
+    Pending,
+    /// Report is being built
+    Running,
+    /// Report is ready for download
+    Completed,
+    /// Report could not be built
+    Failed(String),
+}
+
+/// An export job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportJob {
+    /// Job ID
+    pub id: String,
+    /// Export parameters
+    pub request: ExportRequest,
+    /// Job status
+    pub status: ExportJobStatus,
+    /// Unix timestamp of submission
+    pub created_at: u64,
+    /// Unix timestamp of completion
+    pub completed_at: Option<u64>,
+}
+
+/// A rendered export file
+#[derive(Debug, Clone)]
+pub struct ExportFile {
+    /// File name
+    pub file_name: String,
+    /// MIME type
+    pub content_type: &'static str,
+    /// File contents
+    pub contents: Vec<u8>,
+}
+
+/// How long an export job and its file are kept, in seconds
+pub const EXPORT_RETENTION: u64 = 24 * 60 * 60;
+
+/// Most export jobs an [`ExportService`] holds at once
+pub const MAX_EXPORT_JOBS: usize = 10_000;
+
+/// A job with its download token and rendered file
+#[derive(Debug)]
+struct StoredExport {
+    job: ExportJob,
+    download_token: String,
+    contents: Option<Vec<u8>>,
+}
+
+/// Runs export jobs and holds their output until downloaded
+///
+/// Callers submit a job, build the report in the background with
+/// [`ExportService::run`], and poll the job until it has completed. The
+/// single-use download token is only returned by [`ExportService::submit`],
+/// so looking a job up by ID does not give access to its file. Jobs are
+/// dropped [`EXPORT_RETENTION`] after submission, downloaded or not.
+#[derive(Debug, Default)]
+pub struct ExportService {
+    jobs: Mutex<HashMap<String, StoredExport>>,
+}
+
+impl ExportService {
+    /// Create an export service
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Submit an export job, returning it with its download token
+    pub fn submit(&self, request: ExportRequest) -> Result<(ExportJob, String)> {
+        request.validate()?;
+        let now = unix_timestamp()?;
+
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|_, stored| now < stored.job.created_at.saturating_add(EXPORT_RETENTION));
+        if jobs.len() >= MAX_EXPORT_JOBS {
+            return Err(Error::InvalidInput("Too many export jobs; retry later".to_string()));
+        }
+
+        let job = ExportJob {
+            id: format!("export_{}", hex::encode(rand::random::<[u8; 8]>())),
+            request,
+            status: ExportJobStatus::Pending,
+            created_at: now,
+            completed_at: None,
+        };
+        let download_token = hex::encode(rand::random::<[u8; 16]>());
+
+        jobs.insert(job.id.clone(), StoredExport { job: job.clone(), download_token: download_token.clone(), contents: None });
+        Ok((job, download_token))
+    }
+
+    /// Get a job
+    pub fn job(&self, id: &str) -> Result<ExportJob> {
+        self.jobs.lock().unwrap().get(id)
+            .map(|stored| stored.job.clone())
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown export job: {}", id)))
+    }
+
+    /// Build and store the report for a pending job
+    pub fn run(&self, id: &str, events: &[ActivityEvent], prices: &dyn PriceSource) -> Result<ExportJob> {
+        let request = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let stored = jobs.get_mut(id)
+                .ok_or_else(|| Error::InvalidInput(format!("Unknown export job: {}", id)))?;
+            if stored.job.status != ExportJobStatus::Pending {
+                return Err(Error::InvalidInput(format!("Export job {} has already run", id)));
+            }
+            stored.job.status = ExportJobStatus::Running;
+            stored.job.request.clone()
+        };
+
+        let output = build_report(&request, events, prices).and_then(|report| render(&report));
+        let completed_at = unix_timestamp()?;
+
+        let mut jobs = self.jobs.lock().unwrap();
+        let stored = jobs.get_mut(id)
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown export job: {}", id)))?;
+        stored.job.completed_at = Some(completed_at);
+
+        match output {
+            Ok(output) => {
+                stored.job.status = ExportJobStatus::Completed;
+                stored.contents = Some(output);
+            }
+            Err(e) => stored.job.status = ExportJobStatus::Failed(e.to_string()),
+        }
+
+        Ok(stored.job.clone())
+    }
+
+    /// Mark a job as failed before it could run (e.g. the history could not be fetched)
+    pub fn fail(&self, id: &str, reason: &str) -> Result<()> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let stored = jobs.get_mut(id)
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown export job: {}", id)))?;
+        stored.job.status = ExportJobStatus::Failed(reason.to_string());
+        stored.job.completed_at = Some(unix_timestamp()?);
+        Ok(())
+    }
+
+    /// Take the file of a completed job
+    ///
+    /// The token is single-use: the file is released from memory once downloaded.
+    pub fn download(&self, id: &str, token: &str) -> Result<ExportFile> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let stored = jobs.get_mut(id)
+            .filter(|stored| stored.download_token == token)
+            .ok_or_else(|| Error::InvalidInput("Invalid or expired download token".to_string()))?;
+
+        if stored.job.status != ExportJobStatus::Completed {
+            return Err(Error::InvalidInput(format!("Export job {} has not completed", id)));
+        }
+        let contents = stored.contents.take()
+            .ok_or_else(|| Error::InvalidInput("Export has already been downloaded".to_string()))?;
+
+        Ok(ExportFile {
+            file_name: format!("{}.{}", stored.job.id, stored.job.request.format.extension()),
+            content_type: stored.job.request.format.content_type(),
+            contents,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86_400;
+
+    fn event(id: &str, direction: Direction, timestamp: u64, quantity: u128, fiat_value: Option<i64>) -> ActivityEvent {
+        ActivityEvent {
+            id: id.to_string(),
+            kind: ActivityKind::Transfer,
+            direction,
+            timestamp,
+            key_type: KeyType::Ethereum,
+            asset: "ETH".to_string(),
+            decimals: 18,
+            quantity,
+            fiat_value,
+            transaction_hash: None,
+            counterparty: None,
+        }
+    }
+
+    fn request(from: u64, to: u64, format: ExportFormat) -> ExportRequest {
+        ExportRequest { from, to, format, fiat_currency: "USD".to_string() }
+    }
+
+    const ETH: u128 = 1_000_000_000_000_000_000;
+
+    #[test]
+    fn test_fifo_lots() {
+        let events = vec![
+            event("buy1", Direction::In, DAY, ETH, Some(100_000)),
+            event("buy2", Direction::In, 2 * DAY, ETH, Some(200_000)),
+            event("sell", Direction::Out, 3 * DAY, 3 * ETH / 2, Some(450_000)),
+        ];
+
+        let report = build_report(&request(0, 10 * DAY, ExportFormat::Json), &events, &PriceHistory::new()).unwrap();
+
+        assert_eq!(report.disposals.len(), 2);
+        assert_eq!(report.disposals[0].lot_event_id.as_deref(), Some("buy1"));
+        assert_eq!(report.disposals[0].proceeds, 300_000);
+        assert_eq!(report.disposals[0].gain, 200_000);
+        assert_eq!(report.disposals[1].lot_event_id.as_deref(), Some("buy2"));
+        assert_eq!(report.disposals[1].cost_basis, 100_000);
+
+        assert_eq!(report.open_lots.len(), 1);
+        assert_eq!(report.open_lots[0].quantity, ETH / 2);
+        assert_eq!(report.open_lots[0].cost_basis, 100_000);
+    }
+
+    #[test]
+    fn test_range_keeps_prior_basis() {
+        let events = vec![
+            event("buy", Direction::In, DAY, ETH, Some(100_000)),
+            event("sell", Direction::Out, 5 * DAY, ETH, Some(150_000)),
+        ];
+
+        let report = build_report(&request(4 * DAY, 6 * DAY, ExportFormat::Json), &events, &PriceHistory::new()).unwrap();
+
+        assert_eq!(report.events.len(), 1);
+        assert_eq!(report.disposals[0].cost_basis, 100_000);
+        assert_eq!(report.disposals[0].gain, 50_000);
+    }
+
+    #[test]
+    fn test_unmatched_disposal_and_pricing() {
+        let mut prices = PriceHistory::new();
+        prices.insert("ETH", 0, 2_000.0);
+        prices.insert("ETH", 10 * DAY, 3_000.0);
+
+        let events = vec![event("sell", Direction::Out, 5 * DAY, ETH / 2, None)];
+        let report = build_report(&request(0, 20 * DAY, ExportFormat::Json), &events, &prices).unwrap();
+
+        assert_eq!(report.events[0].fiat_value, Some(100_000));
+        assert_eq!(report.disposals[0].lot_event_id, None);
+        assert_eq!(report.disposals[0].gain, 100_000);
+    }
+
+    #[test]
+    fn test_render_csv() {
+        let mut sell = event("sell", Direction::Out, DAY, 3 * ETH / 2, Some(450_000));
+        sell.counterparty = Some("Coffee, Inc.".to_string());
+        let events = vec![event("buy", Direction::In, 0, 2 * ETH, Some(400_000)), sell];
+
+        let report = build_report(&request(0, 2 * DAY, ExportFormat::Csv), &events, &PriceHistory::new()).unwrap();
+        let csv = String::from_utf8(render(&report).unwrap()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert!(lines[0].starts_with("date,id,kind"));
+        assert!(lines[0].contains("gain_usd"));
+        assert_eq!(lines[1], "1970-01-01T00:00:00Z,buy,Transfer,In,Ethereum,ETH,2,4000.00,,,,");
+        assert_eq!(lines[2], "1970-01-02T00:00:00Z,sell,Transfer,Out,Ethereum,ETH,1.5,4500.00,3000.00,1500.00,,\"Coffee, Inc.\"");
+    }
+
+    #[test]
+    fn test_lots_per_chain() {
+        let mut solana = event("sell", Direction::Out, 2 * DAY, ETH, Some(150_000));
+        solana.key_type = KeyType::Solana;
+        let events = vec![event("buy", Direction::In, DAY, ETH, Some(100_000)), solana];
+
+        let report = build_report(&request(0, 10 * DAY, ExportFormat::Json), &events, &PriceHistory::new()).unwrap();
+
+        // Selling on Solana does not consume the Ethereum lot
+        assert_eq!(report.disposals[0].lot_event_id, None);
+        assert_eq!(report.open_lots.len(), 1);
+        assert_eq!(report.open_lots[0].key_type, KeyType::Ethereum);
+    }
+
+    #[test]
+    fn test_format_units() {
+        assert_eq!(format_units(1_500_000, 6), "1.5");
+        assert_eq!(format_units(42, 0), "42");
+        assert_eq!(format_units(5, 3), "0.005");
+        assert_eq!(format_units(u128::MAX, 40), "0.0340282366920938463463374607431768211455");
+    }
+
+    #[test]
+    fn test_merge_journal() {
+        let mut transfer = event("0xswap", Direction::Out, DAY, ETH, None);
+        transfer.transaction_hash = Some("0xswap".to_string());
+        let mut fee = transfer.clone();
+        fee.id = "0xswap:fee".to_string();
+        fee.kind = ActivityKind::Fee;
+        let mut leg = transfer.clone();
+        leg.id = "0xswap:out".to_string();
+        leg.kind = ActivityKind::Swap;
+
+        let journal = ActivityJournal::new();
+        journal.record(KeyType::Ethereum, "0xAAAA", [leg]);
+        assert!(journal.events(KeyType::Solana, "0xaaaa").is_empty());
+
+        let events = merge_journal(vec![transfer, fee], journal.events(KeyType::Ethereum, "0xaaaa"));
+        let ids: Vec<&str> = events.iter().map(|event| event.id.as_str()).collect();
+        assert_eq!(ids, ["0xswap:fee", "0xswap:out"]);
+    }
+
+    #[test]
+    fn test_events_from_transaction() {
+        let transaction = Transaction {
+            hash: "0xabc".to_string(),
+            transaction_type: crate::transaction::TransactionType::Transfer,
+            key_type: KeyType::Ethereum,
+            from: "0xAAAA".to_string(),
+            to: "0xbbbb".to_string(),
+            value: ETH.to_string(),
+            gas_price: None,
+            gas_limit: None,
+            nonce: None,
+            data: None,
+            status: TransactionStatus::Confirmed,
+            block_number: Some(1),
+            timestamp: Some(DAY),
+            fee: Some("21000".to_string()),
+            memo: None,
+        };
+
+        let sent = ActivityEvent::from_transaction(&transaction, "0xaaaa").unwrap();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0].direction, Direction::Out);
+        assert_eq!(sent[1].kind, ActivityKind::Fee);
+
+        let received = ActivityEvent::from_transaction(&transaction, "0xbbbb").unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].direction, Direction::In);
+    }
+
+    #[test]
+    fn test_export_job_lifecycle() {
+        let service = ExportService::new();
+        let (job, token) = service.submit(request(0, DAY, ExportFormat::Csv)).unwrap();
+        assert_eq!(job.status, ExportJobStatus::Pending);
+        assert!(service.download(&job.id, &token).is_err());
+
+        let job = service.run(&job.id, &[], &PriceHistory::new()).unwrap();
+        assert_eq!(job.status, ExportJobStatus::Completed);
+        assert!(service.run(&job.id, &[], &PriceHistory::new()).is_err());
+        assert!(service.download(&job.id, "wrong").is_err());
+
+        let file = service.download(&job.id, &token).unwrap();
+        assert_eq!(file.content_type, "text/csv");
+        assert!(file.file_name.ends_with(".csv"));
+        assert!(service.download(&job.id, &token).is_err());
+
+        assert!(service.submit(request(DAY, 0, ExportFormat::Json)).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! Wallet metadata
+//!
+//! User-defined labels that help multi-wallet users tell their wallets
+//! apart: a color, an icon, an archived flag and notes on individual
+//! accounts. Metadata is kept apart from the wallet itself, in a
+//! [`MetadataRepository`], so it can change without touching key material.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+
+use serde::{Serialize, Deserialize};
+
+use crate::error::{Error, Result};
+use crate::validation::Validator;
+
+/// Longest wallet name, in characters
+pub const MAX_NAME_LENGTH: usize = 64;
+
+/// Longest icon name, in characters
+pub const MAX_ICON_LENGTH: usize = 32;
+
+/// Longest account note, in characters
+pub const MAX_NOTE_LENGTH: usize = 500;
+
+/// Most notes a wallet may carry
+pub const MAX_NOTES: usize = 100;
+
+/// User-defined labels of a wallet
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletMetadata {
+    /// Color as `#RRGGBB`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Icon name from the app's icon set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Whether the wallet is hidden from the default wallet list
+    #[serde(default)]
+    pub archived: bool,
+    /// Notes keyed by account address
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub notes: BTreeMap<String, String>,
+}
+
+impl WalletMetadata {
+    /// Get the note on an account
+    pub fn note(&self, address: &str) -> Option<&str> {
+        self.notes.get(address).map(String::as_str)
+    }
+
+    /// Set the note on an account, removing it when blank
+    pub fn set_note(&mut self, address: &str, note: &str) {
+        if note.trim().is_empty() {
+            self.notes.remove(address);
+        } else {
+            self.notes.insert(address.to_string(), note.trim().to_string());
+        }
+    }
+}
+
+/// Check a wallet name is not blank and at most [`MAX_NAME_LENGTH`] characters
+pub fn validate_name(name: &str) -> Result<()> {
+    let mut v = Validator::new();
+    v.required("name", name)
+        .check("name", name.chars().count() <= MAX_NAME_LENGTH, format!("must be at most {} characters", MAX_NAME_LENGTH));
+    v.finish()
+}
+
+/// Check a color is written as `#RRGGBB`
+pub fn is_hex_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Check an icon name is lowercase letters, digits and dashes
+pub fn is_icon_name(icon: &str) -> bool {
+    !icon.is_empty()
+        && icon.chars().count() <= MAX_ICON_LENGTH
+        && icon.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+/// Persistent store of wallet metadata, keyed by wallet ID
+pub trait MetadataRepository: Send + Sync {
+    /// Get a wallet's metadata, if any was saved
+    fn get(&self, wallet_id: &str) -> Result<Option<WalletMetadata>>;
+
+    /// Save a wallet's metadata
+    fn put(&self, wallet_id: &str, metadata: &WalletMetadata) -> Result<()>;
+
+    /// Remove a wallet's metadata, returning whether there was any
+    fn remove(&self, wallet_id: &str) -> Result<bool>;
+
+    /// Get a wallet's metadata, or the defaults if none was saved
+    fn get_or_default(&self, wallet_id: &str) -> Result<WalletMetadata> {
+        Ok(self.get(wallet_id)?.unwrap_or_default())
+    }
+}
+
+/// In-memory metadata repository
+#[derive(Debug, Default)]
+pub struct InMemoryMetadataRepository {
+    metadata: RwLock<HashMap<String, WalletMetadata>>,
+}
+
+impl InMemoryMetadataRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MetadataRepository for InMemoryMetadataRepository {
+    fn get(&self, wallet_id: &str) -> Result<Option<WalletMetadata>> {
+        Ok(self.metadata.read().unwrap().get(wallet_id).cloned())
+    }
+
+    fn put(&self, wallet_id: &str, metadata: &WalletMetadata) -> Result<()> {
+        self.metadata.write().unwrap().insert(wallet_id.to_string(), metadata.clone());
+        Ok(())
+    }
+
+    fn remove(&self, wallet_id: &str) -> Result<bool> {
+        Ok(self.metadata.write().unwrap().remove(wallet_id).is_some())
+    }
+}
+
+/// Metadata repository keeping every wallet's metadata in one JSON file
+///
+/// The file is rewritten in full on each change, through a temporary file
+/// so a crash never leaves it half-written.
+#[derive(Debug)]
+pub struct FileMetadataRepository {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileMetadataRepository {
+    /// Open a metadata file, creating it on the first save
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), lock: Mutex::new(()) }
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, WalletMetadata>> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(Error::Provider(format!("Failed to read wallet metadata {}: {}", self.path.display(), e))),
+        };
+        serde_json::from_str(&contents)
+            .map_err(|e| Error::Serialization(format!("Invalid wallet metadata {}: {}", self.path.display(), e)))
+    }
+
+    fn save(&self, metadata: &BTreeMap<String, WalletMetadata>) -> Result<()> {
+        let json = serde_json::to_string_pretty(metadata)
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        let temp = self.path.with_extension("tmp");
+        std::fs::write(&temp, json)
+            .and_then(|_| std::fs::rename(&temp, &self.path))
+            .map_err(|e| Error::Provider(format!("Failed to write wallet metadata {}: {}", self.path.display(), e)))
+    }
+}
+
+impl MetadataRepository for FileMetadataRepository {
+    fn get(&self, wallet_id: &str) -> Result<Option<WalletMetadata>> {
+        let _guard = self.lock.lock().unwrap();
+        Ok(self.load()?.remove(wallet_id))
+    }
+
+    fn put(&self, wallet_id: &str, metadata: &WalletMetadata) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let mut all = self.load()?;
+        all.insert(wallet_id.to_string(), metadata.clone());
+        self.save(&all)
+    }
+
+    fn remove(&self, wallet_id: &str) -> Result<bool> {
+        let _guard = self.lock.lock().unwrap();
+        let mut all = self.load()?;
+        let removed = all.remove(wallet_id).is_some();
+        if removed {
+            self.save(&all)?;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::Validate;
+
+    fn metadata() -> WalletMetadata {
+        let mut metadata = WalletMetadata {
+            color: Some("#1E90FF".to_string()),
+            icon: Some("piggy-bank".to_string()),
+            ..WalletMetadata::default()
+        };
+        metadata.set_note("0x742d35Cc6634C0532925a3b844Bc454e4438f44e", "  Rent savings ");
+        metadata
+    }
+
+    #[test]
+    fn test_notes() {
+        let mut metadata = metadata();
+        assert_eq!(metadata.note("0x742d35Cc6634C0532925a3b844Bc454e4438f44e"), Some("Rent savings"));
+
+        metadata.set_note("0x742d35Cc6634C0532925a3b844Bc454e4438f44e", " ");
+        assert!(metadata.notes.is_empty());
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(metadata().validate().is_ok());
+
+        let invalid = WalletMetadata {
+            color: Some("blue".to_string()),
+            icon: Some("Piggy Bank".to_string()),
+            notes: BTreeMap::from([("0xabc".to_string(), "x".repeat(MAX_NOTE_LENGTH + 1))]),
+            ..WalletMetadata::default()
+        };
+        let Err(Error::Validation(violations)) = invalid.validate() else {
+            panic!("expected validation errors");
+        };
+        let fields: Vec<&str> = violations.iter().map(|violation| violation.field.as_str()).collect();
+        assert_eq!(fields, ["color", "icon", "notes.0xabc"]);
+    }
+
+    #[test]
+    fn test_name() {
+        assert!(validate_name("Savings").is_ok());
+        assert!(validate_name("  ").is_err());
+        assert!(validate_name(&"a".repeat(MAX_NAME_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_file_repository() {
+        let path = std::env::temp_dir().join(format!("fo3-metadata-{}.json", hex::encode(rand::random::<[u8; 8]>())));
+        let repository = FileMetadataRepository::new(&path);
+        assert_eq!(repository.get("wallet_1").unwrap(), None);
+
+        repository.put("wallet_1", &metadata()).unwrap();
+        repository.put("wallet_2", &WalletMetadata { archived: true, ..WalletMetadata::default() }).unwrap();
+
+        // A fresh handle reads what the first one saved
+        let reopened = FileMetadataRepository::new(&path);
+        assert_eq!(reopened.get("wallet_1").unwrap(), Some(metadata()));
+        assert!(reopened.get_or_default("wallet_2").unwrap().archived);
+        assert_eq!(reopened.get_or_default("wallet_3").unwrap(), WalletMetadata::default());
+
+        assert!(reopened.remove("wallet_1").unwrap());
+        assert!(!reopened.remove("wallet_1").unwrap());
+        assert_eq!(repository.get("wallet_1").unwrap(), None);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
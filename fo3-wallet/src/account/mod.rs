@@ -5,5 +5,9 @@
 //! multiple blockchains.
 
 mod wallet;
+pub mod backup;
+pub mod export;
+pub mod metadata;
+pub mod deposit;
 
 pub use wallet::*;
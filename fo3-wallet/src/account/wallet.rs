@@ -27,10 +27,7 @@ impl Wallet {
     pub fn new(name: String) -> Result<(Self, String)> {
         let mnemonic = generate_mnemonic(MnemonicStrength::Words12)?;
         let id = format!("wallet_{}", hex::encode(&rand::random::<[u8; 8]>()));
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| Error::Unknown(e.to_string()))?
-            .as_secs();
+        let now = crate::time::unix_timestamp()?;
 
         let wallet = Self {
             id,
@@ -50,10 +47,7 @@ impl Wallet {
         }
 
         let id = format!("wallet_{}", hex::encode(&rand::random::<[u8; 8]>()));
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| Error::Unknown(e.to_string()))?
-            .as_secs();
+        let now = crate::time::unix_timestamp()?;
 
         let wallet = Self {
             id,
@@ -66,6 +60,22 @@ impl Wallet {
         Ok(wallet)
     }
 
+    /// Restore a wallet from previously persisted parts
+    pub(crate) fn from_parts(id: String, name: String, mnemonic: String, is_backed_up: bool, created_at: u64) -> Self {
+        Self {
+            id,
+            name,
+            encrypted_mnemonic: Some(mnemonic),
+            is_backed_up,
+            created_at,
+        }
+    }
+
+    /// Get the wallet's mnemonic phrase, if available
+    pub(crate) fn mnemonic(&self) -> Option<&str> {
+        self.encrypted_mnemonic.as_deref()
+    }
+
     /// Get the wallet's ID
     pub fn id(&self) -> &str {
         &self.id
@@ -127,6 +137,24 @@ impl Wallet {
         let key_pair = self.derive_key_pair(KeyType::Bitcoin, path, passphrase)?;
         crate::crypto::keys::bitcoin::public_key_to_address(key_pair.public_key(), network)
     }
+
+    /// Get a TON (v4R2 wallet) address for this wallet, derived from its BIP-39 seed
+    pub fn get_ton_address(&self, path: &str, testnet: bool, passphrase: Option<&str>) -> Result<String> {
+        let key_pair = self.derive_key_pair(KeyType::Ton, path, passphrase)?;
+        crate::crypto::keys::ton::public_key_to_address(key_pair.public_key(), testnet)
+    }
+
+    /// Get a Cosmos SDK account address for this wallet under a bech32 prefix (`cosmos`, `osmo`)
+    pub fn get_cosmos_address(&self, path: &str, prefix: &str, passphrase: Option<&str>) -> Result<String> {
+        let key_pair = self.derive_key_pair(KeyType::Cosmos, path, passphrase)?;
+        crate::crypto::keys::cosmos::public_key_to_address(key_pair.public_key(), prefix)
+    }
+
+    /// Get an XRP Ledger classic address for this wallet
+    pub fn get_xrp_address(&self, path: &str, passphrase: Option<&str>) -> Result<String> {
+        let key_pair = self.derive_key_pair(KeyType::Xrp, path, passphrase)?;
+        crate::crypto::keys::xrp::public_key_to_address(key_pair.public_key())
+    }
 }
 
 #[cfg(test)]
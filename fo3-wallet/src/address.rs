@@ -0,0 +1,526 @@
+//! Address validation
+//!
+//! Checks addresses before any funds are sent to them: EIP-55 checksums on
+//! EVM chains, base58check and bech32/bech32m checksums on Bitcoin, and the
+//! ed25519 curve check on Solana, CRC16 checksums of user-friendly TON
+//! addresses, bech32 checksums on Cosmos chains, and base58check on the XRP
+//! Ledger. Failures carry a structured reason rather than a parse error from
+//! whichever library was tried last.
+
+use std::fmt;
+
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::keys::KeyType;
+use crate::error::{Error, FieldViolation};
+use crate::transaction::{is_on_curve, TonAddress, XrpAddress};
+
+/// Bech32 alphabet
+pub(crate) const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Checksum constant of bech32 (BIP-173)
+pub(crate) const BECH32_CONST: u32 = 1;
+
+/// Checksum constant of bech32m (BIP-350)
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+/// Base58 alphabet of the XRP Ledger
+const XRP_ALPHABET: &str = "rpshnaf39wBUDNEGHJKLM4PQRST7VWXYZ2bcdeCg65jkm8oFqi1tuvAxyz";
+
+/// Kind of a valid address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressKind {
+    /// EVM address in mixed-case EIP-55 form
+    EvmChecksummed,
+    /// EVM address in a single case, which carries no checksum
+    EvmUnchecksummed,
+    /// Bitcoin pay-to-pubkey-hash
+    P2pkh,
+    /// Bitcoin pay-to-script-hash
+    P2sh,
+    /// Bitcoin SegWit v0 pay-to-witness-pubkey-hash
+    P2wpkh,
+    /// Bitcoin SegWit v0 pay-to-witness-script-hash
+    P2wsh,
+    /// Bitcoin SegWit v1 pay-to-taproot
+    P2tr,
+    /// Solana ed25519 public key, which has a private key
+    SolanaWallet,
+    /// Solana program derived address, off the ed25519 curve
+    SolanaProgramDerived,
+    /// TON raw address (`<workchain>:<hex>`), which carries no checksum
+    TonRaw,
+    /// TON user-friendly address for contracts, bouncing messages they fail to process
+    TonBounceable,
+    /// TON user-friendly address for wallets, which may not be deployed yet
+    TonNonBounceable,
+    /// Cosmos account, the HASH160 of a public key
+    CosmosAccount,
+    /// Cosmos module, contract or interchain account, a 32-byte hash
+    CosmosModule,
+    /// XRP Ledger classic address (`r...`)
+    XrpClassic,
+    /// XRP Ledger X-address, which embeds the destination tag and network
+    XrpX,
+}
+
+impl fmt::Display for AddressKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::EvmChecksummed => "EIP-55",
+            Self::EvmUnchecksummed => "unchecksummed",
+            Self::P2pkh => "P2PKH",
+            Self::P2sh => "P2SH",
+            Self::P2wpkh => "P2WPKH",
+            Self::P2wsh => "P2WSH",
+            Self::P2tr => "P2TR",
+            Self::SolanaWallet => "wallet",
+            Self::SolanaProgramDerived => "program derived",
+            Self::TonRaw => "raw",
+            Self::TonBounceable => "bounceable",
+            Self::TonNonBounceable => "non-bounceable",
+            Self::CosmosAccount => "account",
+            Self::CosmosModule => "module",
+            Self::XrpClassic => "classic",
+            Self::XrpX => "X-address",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Why an address is invalid
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum AddressError {
+    #[error("must not be empty")]
+    Empty,
+
+    #[error("has an invalid character {character:?} at position {position}")]
+    InvalidCharacter { position: usize, character: char },
+
+    #[error("mixes upper and lower case")]
+    MixedCase,
+
+    #[error("has an invalid length of {0}")]
+    InvalidLength(usize),
+
+    #[error("has an invalid checksum")]
+    InvalidChecksum,
+
+    #[error("has an unknown prefix {0:?}")]
+    UnknownPrefix(String),
+
+    #[error("has an unknown version {0}")]
+    UnknownVersion(u8),
+
+    #[error("has invalid padding")]
+    InvalidPadding,
+}
+
+/// A validated address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressInfo {
+    /// Blockchain type
+    pub key_type: KeyType,
+    /// Kind of address
+    pub kind: AddressKind,
+    /// Canonical form: EIP-55 on EVM chains, lowercase for bech32
+    pub normalized: String,
+    /// Network the address belongs to: the Bitcoin network, the TON network of a
+    /// user-friendly address or XRP X-address, or the bech32 prefix of a Cosmos chain
+    pub network: Option<String>,
+}
+
+impl AddressInfo {
+    /// Whether a private key can sign for the address
+    ///
+    /// Only false for Solana program derived addresses; the key behind
+    /// other addresses can't be known from the address alone.
+    pub fn can_sign(&self) -> bool {
+        self.kind != AddressKind::SolanaProgramDerived
+    }
+}
+
+impl From<AddressError> for Error {
+    fn from(error: AddressError) -> Self {
+        Error::Validation(vec![FieldViolation::new("address", error.to_string())])
+    }
+}
+
+/// Validate an address of the given chain
+pub fn validate_address(key_type: KeyType, address: &str) -> Result<AddressInfo, AddressError> {
+    if address.is_empty() {
+        return Err(AddressError::Empty);
+    }
+    match key_type {
+        KeyType::Ethereum => validate_evm(address),
+        KeyType::Bitcoin => validate_bitcoin(address),
+        KeyType::Solana => validate_solana(address),
+        KeyType::Ton => validate_ton(address),
+        KeyType::Cosmos => validate_cosmos(address),
+        KeyType::Xrp => validate_xrp(address),
+    }
+}
+
+fn validate_evm(address: &str) -> Result<AddressInfo, AddressError> {
+    let hex_part = address.strip_prefix("0x").ok_or_else(|| AddressError::UnknownPrefix(address.chars().take(2).collect()))?;
+    if let Some((position, character)) = hex_part.char_indices().find(|(_, c)| !c.is_ascii_hexdigit()) {
+        return Err(AddressError::InvalidCharacter { position: position + 2, character });
+    }
+    if hex_part.len() != 40 {
+        return Err(AddressError::InvalidLength(hex_part.len() / 2));
+    }
+
+    let bytes = hex::decode(hex_part).map_err(|_| AddressError::InvalidLength(hex_part.len() / 2))?;
+    let normalized = ethers::utils::to_checksum(&ethers::types::Address::from_slice(&bytes), None);
+
+    let single_case = !hex_part.chars().any(|c| c.is_ascii_lowercase()) || !hex_part.chars().any(|c| c.is_ascii_uppercase());
+    let kind = if single_case {
+        AddressKind::EvmUnchecksummed
+    } else if normalized == address {
+        AddressKind::EvmChecksummed
+    } else {
+        return Err(AddressError::InvalidChecksum);
+    };
+
+    Ok(AddressInfo { key_type: KeyType::Ethereum, kind, normalized, network: None })
+}
+
+fn validate_bitcoin(address: &str) -> Result<AddressInfo, AddressError> {
+    let lower = address.to_ascii_lowercase();
+    if ["bc1", "tb1", "bcrt1"].iter().any(|hrp| lower.starts_with(hrp)) {
+        validate_segwit(address)
+    } else {
+        validate_base58check(address)
+    }
+}
+
+fn validate_base58check(address: &str) -> Result<AddressInfo, AddressError> {
+    let bytes = decode_base58(address)?;
+    if bytes.len() != 25 {
+        return Err(AddressError::InvalidLength(bytes.len()));
+    }
+
+    let (payload, checksum) = bytes.split_at(21);
+    let hash = Sha256::digest(Sha256::digest(payload));
+    if checksum != &hash[..4] {
+        return Err(AddressError::InvalidChecksum);
+    }
+
+    let (kind, network) = match payload[0] {
+        0x00 => (AddressKind::P2pkh, "bitcoin"),
+        0x05 => (AddressKind::P2sh, "bitcoin"),
+        0x6f => (AddressKind::P2pkh, "testnet"),
+        0xc4 => (AddressKind::P2sh, "testnet"),
+        version => return Err(AddressError::UnknownVersion(version)),
+    };
+
+    Ok(AddressInfo { key_type: KeyType::Bitcoin, kind, normalized: address.to_string(), network: Some(network.to_string()) })
+}
+
+fn validate_segwit(address: &str) -> Result<AddressInfo, AddressError> {
+    if address.chars().any(|c| c.is_ascii_lowercase()) && address.chars().any(|c| c.is_ascii_uppercase()) {
+        return Err(AddressError::MixedCase);
+    }
+    let normalized = address.to_ascii_lowercase();
+
+    let separator = normalized.rfind('1').ok_or_else(|| AddressError::UnknownPrefix(normalized.clone()))?;
+    let (hrp, data) = (&normalized[..separator], &normalized[separator + 1..]);
+    let network = match hrp {
+        "bc" => "bitcoin",
+        "tb" => "testnet",
+        "bcrt" => "regtest",
+        _ => return Err(AddressError::UnknownPrefix(hrp.to_string())),
+    };
+
+    let mut values = Vec::with_capacity(data.len());
+    for (index, character) in data.char_indices() {
+        let value = BECH32_CHARSET.iter().position(|c| *c as char == character)
+            .ok_or(AddressError::InvalidCharacter { position: separator + 1 + index, character })?;
+        values.push(value as u8);
+    }
+    // Witness version, at least two program bytes (four groups) and the checksum
+    if values.len() < 1 + 4 + 6 {
+        return Err(AddressError::InvalidLength(values.len()));
+    }
+
+    let constant = bech32_polymod(&[hrp_expand(hrp), values.clone()].concat());
+    let (version, program) = (values[0], &values[1..values.len() - 6]);
+    let expected = if version == 0 { BECH32_CONST } else { BECH32M_CONST };
+    if constant != expected {
+        return Err(AddressError::InvalidChecksum);
+    }
+
+    let program = convert_bits(program)?;
+    let kind = match (version, program.len()) {
+        (0, 20) => AddressKind::P2wpkh,
+        (0, 32) => AddressKind::P2wsh,
+        (1, 32) => AddressKind::P2tr,
+        (0, _) | (1, _) => return Err(AddressError::InvalidLength(program.len())),
+        (version, _) => return Err(AddressError::UnknownVersion(version)),
+    };
+
+    Ok(AddressInfo { key_type: KeyType::Bitcoin, kind, normalized, network: Some(network.to_string()) })
+}
+
+fn validate_solana(address: &str) -> Result<AddressInfo, AddressError> {
+    let bytes = decode_base58(address)?;
+    let pubkey: [u8; 32] = bytes.try_into().map_err(|bytes: Vec<u8>| AddressError::InvalidLength(bytes.len()))?;
+    let kind = if is_on_curve(&pubkey) { AddressKind::SolanaWallet } else { AddressKind::SolanaProgramDerived };
+    Ok(AddressInfo { key_type: KeyType::Solana, kind, normalized: address.to_string(), network: None })
+}
+
+/// Validate a raw or user-friendly TON address
+fn validate_ton(address: &str) -> Result<AddressInfo, AddressError> {
+    if let Some((workchain, hash)) = address.split_once(':') {
+        if workchain.parse::<i8>().is_err() {
+            return Err(AddressError::UnknownPrefix(workchain.to_string()));
+        }
+        if let Some((position, character)) = hash.char_indices().find(|(_, c)| !c.is_ascii_hexdigit()) {
+            return Err(AddressError::InvalidCharacter { position: workchain.len() + 1 + position, character });
+        }
+        if hash.len() != 64 {
+            return Err(AddressError::InvalidLength(hash.len() / 2));
+        }
+        let normalized = format!("{}:{}", workchain, hash.to_ascii_lowercase());
+        return Ok(AddressInfo { key_type: KeyType::Ton, kind: AddressKind::TonRaw, normalized, network: None });
+    }
+
+    if let Some((position, character)) = address.char_indices().find(|(_, c)| !(c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '-' | '_'))) {
+        return Err(AddressError::InvalidCharacter { position, character });
+    }
+    if address.len() != 48 {
+        return Err(AddressError::InvalidLength(address.len() * 3 / 4));
+    }
+    let (parsed, flags) = TonAddress::parse(address).map_err(|_| AddressError::InvalidChecksum)?;
+    let flags = flags.expect("user-friendly addresses have flags");
+    let kind = if flags.bounceable { AddressKind::TonBounceable } else { AddressKind::TonNonBounceable };
+    let network = if flags.testnet { "testnet" } else { "mainnet" };
+    Ok(AddressInfo { key_type: KeyType::Ton, kind, normalized: parsed.to_friendly(flags.bounceable, flags.testnet), network: Some(network.to_string()) })
+}
+
+/// Validate a bech32 Cosmos address under any chain prefix
+fn validate_cosmos(address: &str) -> Result<AddressInfo, AddressError> {
+    if address.chars().any(|c| c.is_ascii_lowercase()) && address.chars().any(|c| c.is_ascii_uppercase()) {
+        return Err(AddressError::MixedCase);
+    }
+    let normalized = address.to_ascii_lowercase();
+
+    let separator = normalized.rfind('1').ok_or_else(|| AddressError::UnknownPrefix(normalized.clone()))?;
+    let (hrp, data) = (&normalized[..separator], &normalized[separator + 1..]);
+    if hrp.is_empty() {
+        return Err(AddressError::UnknownPrefix(String::new()));
+    }
+
+    let mut values = Vec::with_capacity(data.len());
+    for (index, character) in data.char_indices() {
+        let value = BECH32_CHARSET.iter().position(|c| *c as char == character)
+            .ok_or(AddressError::InvalidCharacter { position: separator + 1 + index, character })?;
+        values.push(value as u8);
+    }
+    if values.len() < 6 {
+        return Err(AddressError::InvalidLength(values.len()));
+    }
+    if bech32_polymod(&[hrp_expand(hrp), values.clone()].concat()) != BECH32_CONST {
+        return Err(AddressError::InvalidChecksum);
+    }
+
+    let bytes = convert_bits(&values[..values.len() - 6])?;
+    let kind = match bytes.len() {
+        20 => AddressKind::CosmosAccount,
+        32 => AddressKind::CosmosModule,
+        length => return Err(AddressError::InvalidLength(length)),
+    };
+
+    let network = Some(hrp.to_string());
+    Ok(AddressInfo { key_type: KeyType::Cosmos, kind, normalized, network })
+}
+
+/// Validate an XRP Ledger classic address or X-address
+fn validate_xrp(address: &str) -> Result<AddressInfo, AddressError> {
+    if let Some((position, character)) = address.char_indices().find(|(_, c)| !XRP_ALPHABET.contains(*c)) {
+        return Err(AddressError::InvalidCharacter { position, character });
+    }
+    let kind = match address.chars().next() {
+        Some('r') if (25..=35).contains(&address.len()) => AddressKind::XrpClassic,
+        Some('X' | 'T') if address.len() == 47 => AddressKind::XrpX,
+        Some('r' | 'X' | 'T') => return Err(AddressError::InvalidLength(address.len())),
+        _ => return Err(AddressError::UnknownPrefix(address.chars().take(1).collect())),
+    };
+
+    let (_, info) = XrpAddress::parse(address).map_err(|_| AddressError::InvalidChecksum)?;
+    let network = info.map(|info| if info.testnet { "testnet" } else { "mainnet" }.to_string());
+    Ok(AddressInfo { key_type: KeyType::Xrp, kind, normalized: address.to_string(), network })
+}
+
+/// Encode bytes as bech32 (BIP-173) under a human-readable part
+pub(crate) fn bech32_encode(hrp: &str, bytes: &[u8]) -> String {
+    let mut values = Vec::with_capacity(bytes.len() * 8 / 5 + 1);
+    let (mut accumulator, mut bits) = (0u32, 0u32);
+    for byte in bytes {
+        accumulator = ((accumulator << 8) | *byte as u32) & 0xfff;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            values.push(((accumulator >> bits) & 0x1f) as u8);
+        }
+    }
+    if bits > 0 {
+        values.push(((accumulator << (5 - bits)) & 0x1f) as u8);
+    }
+
+    let checksum = bech32_polymod(&[hrp_expand(hrp), values.clone(), vec![0; 6]].concat()) ^ BECH32_CONST;
+    values.extend((0..6).map(|i| ((checksum >> (5 * (5 - i))) & 0x1f) as u8));
+
+    let data: String = values.iter().map(|value| BECH32_CHARSET[*value as usize] as char).collect();
+    format!("{}1{}", hrp, data)
+}
+
+/// Decode base58, reporting the first character outside the alphabet
+fn decode_base58(address: &str) -> Result<Vec<u8>, AddressError> {
+    bs58::decode(address).into_vec().map_err(|error| match error {
+        bs58::decode::Error::InvalidCharacter { character, index } => AddressError::InvalidCharacter { position: index, character },
+        _ => AddressError::InvalidLength(address.len()),
+    })
+}
+
+/// Expand a human-readable part for the bech32 checksum
+pub(crate) fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let high = hrp.bytes().map(|b| b >> 5);
+    let low = hrp.bytes().map(|b| b & 0x1f);
+    high.chain(std::iter::once(0)).chain(low).collect()
+}
+
+/// Bech32 checksum polynomial
+pub(crate) fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+
+    values.iter().fold(1u32, |checksum, value| {
+        let top = checksum >> 25;
+        let checksum = ((checksum & 0x1ff_ffff) << 5) ^ *value as u32;
+        GENERATOR.iter().enumerate()
+            .filter(|(i, _)| (top >> i) & 1 == 1)
+            .fold(checksum, |checksum, (_, generator)| checksum ^ generator)
+    })
+}
+
+/// Regroup 5-bit values into bytes, rejecting non-zero padding
+fn convert_bits(values: &[u8]) -> Result<Vec<u8>, AddressError> {
+    let mut bytes = Vec::with_capacity(values.len() * 5 / 8);
+    let (mut accumulator, mut bits) = (0u32, 0u32);
+    for value in values {
+        accumulator = ((accumulator << 5) | *value as u32) & 0xfff;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((accumulator >> bits) as u8);
+        }
+    }
+    if bits >= 5 || (accumulator << (8 - bits)) & 0xff != 0 {
+        return Err(AddressError::InvalidPadding);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::{associated_token_address, TokenProgram};
+
+    #[test]
+    fn test_evm() {
+        let info = validate_address(KeyType::Ethereum, "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").unwrap();
+        assert_eq!(info.kind, AddressKind::EvmChecksummed);
+
+        let info = validate_address(KeyType::Ethereum, "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap();
+        assert_eq!(info.kind, AddressKind::EvmUnchecksummed);
+        assert_eq!(info.normalized, "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+
+        let invalid = |address| validate_address(KeyType::Ethereum, address).unwrap_err();
+        assert_eq!(invalid("0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed"), AddressError::InvalidChecksum);
+        assert_eq!(invalid("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAe"), AddressError::InvalidLength(19));
+        assert_eq!(invalid("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeg"), AddressError::InvalidCharacter { position: 41, character: 'g' });
+        assert_eq!(invalid(""), AddressError::Empty);
+    }
+
+    #[test]
+    fn test_bitcoin() {
+        let kind = |address| validate_address(KeyType::Bitcoin, address).map(|info| info.kind);
+        assert_eq!(kind("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"), Ok(AddressKind::P2pkh));
+        assert_eq!(kind("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy"), Ok(AddressKind::P2sh));
+        assert_eq!(kind("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4"), Ok(AddressKind::P2wpkh));
+        assert_eq!(kind("bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3"), Ok(AddressKind::P2wsh));
+        assert_eq!(kind("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0"), Ok(AddressKind::P2tr));
+
+        let testnet = validate_address(KeyType::Bitcoin, "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx").unwrap();
+        assert_eq!(testnet.network.as_deref(), Some("testnet"));
+
+        assert_eq!(kind("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb"), Err(AddressError::InvalidChecksum));
+        assert_eq!(kind("1A1zP1eP5QGefi2DMPTfTL5SLmv7Divf0a"), Err(AddressError::InvalidCharacter { position: 32, character: '0' }));
+        assert_eq!(kind("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5"), Err(AddressError::InvalidChecksum));
+        assert_eq!(kind("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7Kv8f3t4"), Err(AddressError::MixedCase));
+        // Taproot encoded with the bech32 constant instead of bech32m
+        assert_eq!(kind("bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7k7grplx"), Err(AddressError::InvalidChecksum));
+    }
+
+    #[test]
+    fn test_solana() {
+        let owner = "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg";
+        let info = validate_address(KeyType::Solana, owner).unwrap();
+        assert_eq!(info.kind, AddressKind::SolanaWallet);
+        assert!(info.can_sign());
+
+        let ata = associated_token_address(owner, "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", TokenProgram::Token).unwrap();
+        assert_eq!(validate_address(KeyType::Solana, &ata).unwrap().kind, AddressKind::SolanaProgramDerived);
+
+        assert!(matches!(validate_address(KeyType::Solana, "vines1vzrYbzLMRdu58ou5"), Err(AddressError::InvalidLength(_))));
+        assert!(matches!(validate_address(KeyType::Solana, "0OIl"), Err(AddressError::InvalidCharacter { position: 0, .. })));
+    }
+
+    #[test]
+    fn test_ton() {
+        let kind = |address| validate_address(KeyType::Ton, address).map(|info| info.kind);
+        assert_eq!(kind("EQDAmzgkCP_QqkZg7shsqi2QM_FSBibVu47gM5vTFk7dnMav"), Ok(AddressKind::TonBounceable));
+        assert_eq!(kind("UQDAmzgkCP_QqkZg7shsqi2QM_FSBibVu47gM5vTFk7dnJtq"), Ok(AddressKind::TonNonBounceable));
+        assert_eq!(kind("0:C09B382408FFD0AA4660EEC86CAA2D9033F1520626D5BB8EE0339BD3164EDD9C"), Ok(AddressKind::TonRaw));
+
+        let testnet = validate_address(KeyType::Ton, "0QDAmzgkCP_QqkZg7shsqi2QM_FSBibVu47gM5vTFk7dnCDg").unwrap();
+        assert_eq!(testnet.network.as_deref(), Some("testnet"));
+
+        assert_eq!(kind("EQDAmzgkCP_QqkZg7shsqi2QM_FSBibVu47gM5vTFk7dnMaw"), Err(AddressError::InvalidChecksum));
+        assert_eq!(kind("EQDAmzgkCP_QqkZg7shsqi2QM_FSBibVu47gM5vTFk7dnMa"), Err(AddressError::InvalidLength(35)));
+        assert_eq!(kind("x:c09b"), Err(AddressError::UnknownPrefix("x".to_string())));
+    }
+
+    #[test]
+    fn test_cosmos() {
+        let info = validate_address(KeyType::Cosmos, "cosmos19rl4cm2hmr8afy4kldpxz3fka4jguq0auqdal4").unwrap();
+        assert_eq!(info.kind, AddressKind::CosmosAccount);
+        assert_eq!(info.network.as_deref(), Some("cosmos"));
+
+        let kind = |address| validate_address(KeyType::Cosmos, address).map(|info| info.kind);
+        assert_eq!(kind("osmo19rl4cm2hmr8afy4kldpxz3fka4jguq0a5m7df8"), Ok(AddressKind::CosmosAccount));
+        assert_eq!(kind("cosmos19rl4cm2hmr8afy4kldpxz3fka4jguq0auqdal5"), Err(AddressError::InvalidChecksum));
+        assert_eq!(kind("cosmos19rl4cm2hmr8afy4kldpxz3fka4jguq0auqdaL4"), Err(AddressError::MixedCase));
+        assert_eq!(kind("cosmos19rl4cm2hmr8afy4kldpxz3fka4jguq0auqdab4"), Err(AddressError::InvalidCharacter { position: 43, character: 'b' }));
+
+        let module = bech32_encode("cosmos", &[7u8; 32]);
+        assert_eq!(kind(&module), Ok(AddressKind::CosmosModule));
+        assert_eq!(kind(&bech32_encode("cosmos", &[7u8; 8])), Err(AddressError::InvalidLength(8)));
+    }
+
+    #[test]
+    fn test_xrp() {
+        let kind = |address| validate_address(KeyType::Xrp, address).map(|info| info.kind);
+        assert_eq!(kind("rHb9CJAWyB4rj91VRWn96DkukG4bwdtyTh"), Ok(AddressKind::XrpClassic));
+        assert_eq!(kind("rHb9CJAWyB4rj91VRWn96DkukG4bwdtyTi"), Err(AddressError::InvalidChecksum));
+        assert_eq!(kind("rHb9CJAWyB4rj91VRWn96DkukG4bwdty0h"), Err(AddressError::InvalidCharacter { position: 32, character: '0' }));
+        assert_eq!(kind("1Hb9CJAWyB4rj91VRWn96DkukG4bwdtyTh"), Err(AddressError::UnknownPrefix("1".to_string())));
+
+        let info = validate_address(KeyType::Xrp, "XVPcpSm47b1CZkf5AkKM9a84dQHe3mTAxgxfLw2qYoe7Boa").unwrap();
+        assert_eq!(info.kind, AddressKind::XrpX);
+        assert_eq!(info.network.as_deref(), Some("mainnet"));
+    }
+}
@@ -0,0 +1,399 @@
+//! Tamper-evident audit log
+//!
+//! Every entry carries the SHA-256 hash of the entry before it, so altering,
+//! removing or reordering past entries breaks the chain. [`verify_chain`]
+//! recomputes the chain from an export to prove the log is intact.
+//!
+//! A plain hash chain only proves the entries are consistent with each
+//! other: whoever can write the store can rewrite it and recompute every
+//! hash. A log opened [`with_key`](AuditLog::with_key) chains HMAC-SHA256
+//! tags instead, so rewriting it also takes the key, which lives apart from
+//! the store.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+
+/// Previous hash of the first entry
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// An audited action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    /// Who performed the action, as authenticated
+    pub actor: String,
+    /// Who the caller said they were, if they said so without authenticating
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claimed_actor: Option<String>,
+    /// What was done (e.g. `wallet.create`)
+    pub action: String,
+    /// What it was done to (e.g. a wallet ID)
+    pub resource: String,
+    /// State before the action
+    pub before: Option<Value>,
+    /// State after the action
+    pub after: Option<Value>,
+    /// ID of the request that caused the action
+    pub request_id: Option<String>,
+    /// Unix timestamp of the action
+    pub timestamp: u64,
+}
+
+impl AuditEvent {
+    /// Create an event at the current time
+    pub fn new(actor: &str, action: &str, resource: &str) -> Result<Self> {
+        Ok(Self {
+            actor: actor.to_string(),
+            action: action.to_string(),
+            resource: resource.to_string(),
+            claimed_actor: None,
+            before: None,
+            after: None,
+            request_id: None,
+            timestamp: crate::time::unix_timestamp()?,
+        })
+    }
+
+    /// Set the state before and after the action
+    pub fn with_change(mut self, before: Option<Value>, after: Option<Value>) -> Self {
+        self.before = before;
+        self.after = after;
+        self
+    }
+
+    /// Set the unverified actor the caller claimed
+    pub fn with_claimed_actor(mut self, claimed_actor: Option<String>) -> Self {
+        self.claimed_actor = claimed_actor;
+        self
+    }
+
+    /// Set the request ID
+    pub fn with_request_id(mut self, request_id: Option<String>) -> Self {
+        self.request_id = request_id;
+        self
+    }
+}
+
+/// An event in the hash chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the log, starting at zero
+    pub sequence: u64,
+    /// The event
+    pub event: AuditEvent,
+    /// Hash of the previous entry
+    pub prev_hash: String,
+    /// Hash of this entry
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// Compute the hash of an entry from its contents
+    ///
+    /// With a key, the hash is an HMAC-SHA256 tag under that key.
+    pub fn compute_hash(sequence: u64, event: &AuditEvent, prev_hash: &str, key: Option<&[u8]>) -> Result<String> {
+        let event = serde_json::to_string(event)
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+
+        match key {
+            Some(key) => {
+                let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key)
+                    .map_err(|e| Error::KeyDerivation(e.to_string()))?;
+                mac.update(prev_hash.as_bytes());
+                mac.update(&sequence.to_be_bytes());
+                mac.update(event.as_bytes());
+                Ok(hex::encode(mac.finalize().into_bytes()))
+            }
+            None => {
+                let mut hasher = Sha256::new();
+                hasher.update(prev_hash.as_bytes());
+                hasher.update(sequence.to_be_bytes());
+                hasher.update(event.as_bytes());
+                Ok(hex::encode(hasher.finalize()))
+            }
+        }
+    }
+}
+
+/// Result of verifying a hash chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditVerification {
+    /// Whether the whole chain is intact
+    pub valid: bool,
+    /// Number of entries checked
+    pub entries: u64,
+    /// Sequence number of the first entry that does not match, if any
+    pub first_invalid: Option<u64>,
+    /// Hash of the last entry
+    pub head: String,
+}
+
+/// Verify the hash chain of entries in log order
+///
+/// A keyed chain only verifies under the key it was written with.
+pub fn verify_chain(entries: &[AuditEntry], key: Option<&[u8]>) -> Result<AuditVerification> {
+    let mut prev_hash = GENESIS_HASH.to_string();
+
+    for (position, entry) in entries.iter().enumerate() {
+        let intact = entry.sequence == position as u64
+            && entry.prev_hash == prev_hash
+            && entry.hash == AuditEntry::compute_hash(entry.sequence, &entry.event, &entry.prev_hash, key)?;
+
+        if !intact {
+            return Ok(AuditVerification {
+                valid: false,
+                entries: entries.len() as u64,
+                first_invalid: Some(position as u64),
+                head: prev_hash,
+            });
+        }
+        prev_hash = entry.hash.clone();
+    }
+
+    Ok(AuditVerification {
+        valid: true,
+        entries: entries.len() as u64,
+        first_invalid: None,
+        head: prev_hash,
+    })
+}
+
+/// Persistent store of audit entries
+pub trait AuditStore: Send + Sync {
+    /// Append an entry
+    fn append(&self, entry: &AuditEntry) -> Result<()>;
+
+    /// Get all entries in log order
+    fn entries(&self) -> Result<Vec<AuditEntry>>;
+}
+
+/// In-memory audit store
+#[derive(Debug, Default)]
+pub struct InMemoryAuditStore {
+    entries: Mutex<Vec<AuditEntry>>,
+}
+
+impl InMemoryAuditStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AuditStore for InMemoryAuditStore {
+    fn append(&self, entry: &AuditEntry) -> Result<()> {
+        self.entries.lock().unwrap().push(entry.clone());
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<AuditEntry>> {
+        Ok(self.entries.lock().unwrap().clone())
+    }
+}
+
+/// Audit store appending JSON lines to a file
+#[derive(Debug)]
+pub struct FileAuditStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileAuditStore {
+    /// Open a log file, creating it on the first append
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), lock: Mutex::new(()) }
+    }
+}
+
+impl AuditStore for FileAuditStore {
+    fn append(&self, entry: &AuditEntry) -> Result<()> {
+        let line = serde_json::to_string(entry)
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+
+        let _guard = self.lock.lock().unwrap();
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)
+            .map_err(|e| Error::Provider(format!("Failed to open audit log {}: {}", self.path.display(), e)))?;
+        writeln!(file, "{}", line)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| Error::Provider(format!("Failed to write audit log {}: {}", self.path.display(), e)))
+    }
+
+    fn entries(&self) -> Result<Vec<AuditEntry>> {
+        let _guard = self.lock.lock().unwrap();
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::Provider(format!("Failed to read audit log {}: {}", self.path.display(), e))),
+        };
+
+        contents.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|e| Error::Serialization(e.to_string())))
+            .collect()
+    }
+}
+
+/// Hash-chained audit log
+pub struct AuditLog {
+    store: Box<dyn AuditStore>,
+    /// Next sequence number and hash of the last entry
+    head: Mutex<(u64, String)>,
+    /// HMAC key of the chain, if keyed
+    key: Option<Vec<u8>>,
+}
+
+impl AuditLog {
+    /// Open a log, resuming the chain after the entries already in the store
+    pub fn open(store: Box<dyn AuditStore>) -> Result<Self> {
+        let head = match store.entries()?.last() {
+            Some(last) => (last.sequence + 1, last.hash.clone()),
+            None => (0, GENESIS_HASH.to_string()),
+        };
+
+        Ok(Self { store, head: Mutex::new(head), key: None })
+    }
+
+    /// Chain entries with HMAC-SHA256 under `key`
+    ///
+    /// Entries already in the store must have been written under the same
+    /// key, or verification fails at the first of them.
+    pub fn with_key(mut self, key: Vec<u8>) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Number of entries and hash of the last one, for anchoring outside the log
+    pub fn head(&self) -> (u64, String) {
+        self.head.lock().unwrap().clone()
+    }
+
+    /// Append an event to the chain
+    pub fn record(&self, event: AuditEvent) -> Result<AuditEntry> {
+        let mut head = self.head.lock().unwrap();
+        let (sequence, prev_hash) = head.clone();
+
+        let entry = AuditEntry {
+            sequence,
+            hash: AuditEntry::compute_hash(sequence, &event, &prev_hash, self.key.as_deref())?,
+            event,
+            prev_hash,
+        };
+        self.store.append(&entry)?;
+
+        *head = (sequence + 1, entry.hash.clone());
+        Ok(entry)
+    }
+
+    /// Get all entries in log order
+    pub fn entries(&self) -> Result<Vec<AuditEntry>> {
+        self.store.entries()
+    }
+
+    /// Verify the stored chain
+    pub fn verify(&self) -> Result<AuditVerification> {
+        verify_chain(&self.store.entries()?, self.key.as_deref())
+    }
+
+    /// Export the log as JSON lines for independent verification
+    pub fn export(&self) -> Result<String> {
+        self.store.entries()?.iter()
+            .map(|entry| serde_json::to_string(entry).map(|line| line + "\n"))
+            .collect::<std::result::Result<String, _>>()
+            .map_err(|e| Error::Serialization(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(action: &str, timestamp: u64) -> AuditEvent {
+        AuditEvent {
+            actor: "ops@example.com".to_string(),
+            action: action.to_string(),
+            resource: "wallet_1".to_string(),
+            claimed_actor: None,
+            before: None,
+            after: Some(json!({ "name": "Treasury" })),
+            request_id: Some("req-1".to_string()),
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_chain_verifies() {
+        let log = AuditLog::open(Box::new(InMemoryAuditStore::new())).unwrap();
+        let first = log.record(event("wallet.create", 1)).unwrap();
+        let second = log.record(event("wallet.rename", 2)).unwrap();
+
+        assert_eq!(first.prev_hash, GENESIS_HASH);
+        assert_eq!(second.prev_hash, first.hash);
+
+        let verification = log.verify().unwrap();
+        assert!(verification.valid);
+        assert_eq!(verification.entries, 2);
+        assert_eq!(verification.head, second.hash);
+    }
+
+    #[test]
+    fn test_tampering_detected() {
+        let log = AuditLog::open(Box::new(InMemoryAuditStore::new())).unwrap();
+        for timestamp in 0..3 {
+            log.record(event("wallet.create", timestamp)).unwrap();
+        }
+
+        let mut altered = log.entries().unwrap();
+        altered[1].event.actor = "mallory".to_string();
+        assert_eq!(verify_chain(&altered, None).unwrap().first_invalid, Some(1));
+
+        let mut removed = log.entries().unwrap();
+        removed.remove(1);
+        assert_eq!(verify_chain(&removed, None).unwrap().first_invalid, Some(1));
+
+        let mut truncated = log.entries().unwrap();
+        truncated.remove(0);
+        assert!(!verify_chain(&truncated, None).unwrap().valid);
+    }
+
+    #[test]
+    fn test_keyed_chain() {
+        let log = AuditLog::open(Box::new(InMemoryAuditStore::new())).unwrap().with_key(b"audit-key".to_vec());
+        log.record(event("wallet.create", 1)).unwrap();
+        let last = log.record(event("wallet.rename", 2)).unwrap();
+
+        assert!(log.verify().unwrap().valid);
+        assert_eq!(log.head(), (2, last.hash));
+
+        // Rehashing an altered entry without the key does not pass
+        let mut altered = log.entries().unwrap();
+        altered[1].event.actor = "mallory".to_string();
+        altered[1].hash = AuditEntry::compute_hash(1, &altered[1].event, &altered[1].prev_hash, None).unwrap();
+        assert_eq!(verify_chain(&altered, Some(b"audit-key")).unwrap().first_invalid, Some(1));
+        assert!(!verify_chain(&log.entries().unwrap(), None).unwrap().valid);
+    }
+
+    #[test]
+    fn test_file_store_resumes_chain() {
+        let path = std::env::temp_dir().join(format!("fo3-audit-{}.jsonl", hex::encode(rand::random::<[u8; 8]>())));
+
+        let log = AuditLog::open(Box::new(FileAuditStore::new(&path))).unwrap();
+        log.record(event("wallet.create", 1)).unwrap();
+        drop(log);
+
+        let log = AuditLog::open(Box::new(FileAuditStore::new(&path))).unwrap();
+        assert_eq!(log.record(event("wallet.rename", 2)).unwrap().sequence, 1);
+        assert!(log.verify().unwrap().valid);
+        assert_eq!(log.export().unwrap().lines().count(), 2);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,380 @@
+//! Chain and asset identifiers
+//!
+//! [CAIP-2] chain IDs (`eip155:1`, `solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp`,
+//! `bip122:000000000019d6689c085ae165831e93`, `cosmos:cosmoshub-4`, `xrpl:0`) and [CAIP-19] asset IDs
+//! (`eip155:1/erc20:0xa0b8…`, `eip155:1/slip44:60`) name a chain or asset
+//! unambiguously, where a [`KeyType`] and a token address leave the EVM
+//! chain or Solana cluster unstated. Converters map to and from the legacy
+//! [`NetworkBinding`] and [`Token`] fields.
+//!
+//! [CAIP-2]: https://github.com/ChainAgnostic/CAIPs/blob/main/CAIPs/caip-2.md
+//! [CAIP-19]: https://github.com/ChainAgnostic/CAIPs/blob/main/CAIPs/caip-19.md
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Deserialize};
+
+use crate::crypto::keys::KeyType;
+use crate::defi::{Token, EVM_NATIVE_TOKEN, SOLANA_NATIVE_TOKEN};
+use crate::error::{Error, Result};
+use crate::transaction::{CosmosChain, NetworkBinding, TonAddress, SOLANA_DEVNET_GENESIS_HASH, SOLANA_MAINNET_GENESIS_HASH, SOLANA_TESTNET_GENESIS_HASH, TON_MAINNET_GLOBAL_ID, TON_TESTNET_GLOBAL_ID};
+
+/// Placeholder used for native TON in TON token lists
+const TON_NATIVE_TOKEN: &str = "TON";
+
+/// Placeholder used for native XRP in XRP Ledger token lists
+const XRP_NATIVE_TOKEN: &str = "XRP";
+
+/// Bitcoin networks with the first 16 bytes of their genesis block hash
+const BITCOIN_NETWORKS: [(&str, &str); 4] = [
+    ("bitcoin", "000000000019d6689c085ae165831e93"),
+    ("testnet", "000000000933ea01ad0ee984209779ba"),
+    ("signet", "00000008819873e925422c1ff0f99f7c"),
+    ("regtest", "0f9188f13cb7b2c71f2a335e3a4fc328"),
+];
+
+/// Solana clusters by genesis hash
+const SOLANA_CLUSTERS: [&str; 3] = [SOLANA_MAINNET_GENESIS_HASH, SOLANA_DEVNET_GENESIS_HASH, SOLANA_TESTNET_GENESIS_HASH];
+
+/// Whether `value` has `min..=max` characters, all allowed by `allowed`
+fn is_segment(value: &str, min: usize, max: usize, allowed: impl Fn(char) -> bool) -> bool {
+    (min..=max).contains(&value.len()) && value.chars().all(allowed)
+}
+
+fn is_namespace(value: &str) -> bool {
+    is_segment(value, 3, 8, |c| c == '-' || c.is_ascii_lowercase() || c.is_ascii_digit())
+}
+
+/// Whether `denom` is the staking denom of a known Cosmos chain
+fn is_cosmos_staking_denom(chain_id: &ChainId, denom: &str) -> bool {
+    CosmosChain::by_chain_id(&chain_id.reference).is_some_and(|chain| chain.staking_denom == denom)
+}
+
+/// A CAIP-2 chain ID
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ChainId {
+    /// Namespace of the chain family (`eip155`, `solana`, `bip122`, `tvm`, `cosmos`, `xrpl`)
+    pub namespace: String,
+    /// Chain within the namespace
+    pub reference: String,
+}
+
+impl ChainId {
+    /// Create a chain ID, checking the CAIP-2 syntax
+    pub fn new(namespace: &str, reference: &str) -> Result<Self> {
+        if !is_namespace(namespace) {
+            return Err(Error::InvalidInput(format!("Invalid CAIP-2 namespace: {}", namespace)));
+        }
+        if !is_segment(reference, 1, 32, |c| c == '-' || c == '_' || c.is_ascii_alphanumeric()) {
+            return Err(Error::InvalidInput(format!("Invalid CAIP-2 reference: {}", reference)));
+        }
+        Ok(Self { namespace: namespace.to_string(), reference: reference.to_string() })
+    }
+
+    /// Chain ID of an EVM chain
+    pub fn evm(chain_id: u64) -> Self {
+        Self { namespace: "eip155".to_string(), reference: chain_id.to_string() }
+    }
+
+    /// Chain ID of a Solana cluster, from its genesis hash
+    pub fn solana(genesis_hash: &str) -> Self {
+        Self { namespace: "solana".to_string(), reference: genesis_hash.chars().take(32).collect() }
+    }
+
+    /// Chain ID of a Bitcoin network (`bitcoin`, `testnet`, `signet` or `regtest`)
+    pub fn bitcoin(network: &str) -> Result<Self> {
+        BITCOIN_NETWORKS.iter()
+            .find(|(name, _)| *name == network)
+            .map(|(_, genesis)| Self { namespace: "bip122".to_string(), reference: genesis.to_string() })
+            .ok_or_else(|| Error::NotSupported(format!("Unknown Bitcoin network: {}", network)))
+    }
+
+    /// Blockchain type of the chain, if supported
+    pub fn key_type(&self) -> Option<KeyType> {
+        match self.namespace.as_str() {
+            "eip155" => Some(KeyType::Ethereum),
+            "solana" => Some(KeyType::Solana),
+            "bip122" => Some(KeyType::Bitcoin),
+            "tvm" => Some(KeyType::Ton),
+            "cosmos" => Some(KeyType::Cosmos),
+            "xrpl" => Some(KeyType::Xrp),
+            _ => None,
+        }
+    }
+
+    /// Network binding of the chain
+    ///
+    /// Solana references are truncated genesis hashes, so only known
+    /// clusters can be bound.
+    pub fn to_binding(&self) -> Result<NetworkBinding> {
+        let unknown = || Error::NotSupported(format!("Unknown chain: {}", self));
+        match self.namespace.as_str() {
+            "eip155" => {
+                let chain_id = self.reference.parse::<u64>().map_err(|_| unknown())?;
+                Ok(NetworkBinding::Evm { chain_id })
+            }
+            "solana" => SOLANA_CLUSTERS.iter()
+                .find(|genesis_hash| genesis_hash.starts_with(&self.reference))
+                .map(|genesis_hash| NetworkBinding::Solana { genesis_hash: genesis_hash.to_string() })
+                .ok_or_else(unknown),
+            "bip122" => BITCOIN_NETWORKS.iter()
+                .find(|(_, genesis)| *genesis == self.reference)
+                .map(|(name, _)| NetworkBinding::Bitcoin { network: name.to_string() })
+                .ok_or_else(unknown),
+            "tvm" => match self.reference.parse::<i32>() {
+                Ok(global_id @ (TON_MAINNET_GLOBAL_ID | TON_TESTNET_GLOBAL_ID)) => Ok(NetworkBinding::Ton { global_id }),
+                _ => Err(unknown()),
+            },
+            "cosmos" => Ok(NetworkBinding::Cosmos { chain_id: self.reference.clone() }),
+            "xrpl" => match self.reference.parse::<u32>() {
+                Ok(network_id) if network_id <= 1024 => Ok(NetworkBinding::Xrp { network_id }),
+                _ => Err(unknown()),
+            },
+            _ => Err(unknown()),
+        }
+    }
+}
+
+impl NetworkBinding {
+    /// CAIP-2 chain ID of the network
+    pub fn chain_id(&self) -> Result<ChainId> {
+        match self {
+            Self::Evm { chain_id } => Ok(ChainId::evm(*chain_id)),
+            Self::Solana { genesis_hash } => Ok(ChainId::solana(genesis_hash)),
+            Self::Bitcoin { network } => ChainId::bitcoin(network),
+            Self::Ton { global_id } => ChainId::new("tvm", &global_id.to_string()),
+            Self::Cosmos { chain_id } => ChainId::new("cosmos", chain_id),
+            Self::Xrp { network_id } => ChainId::new("xrpl", &network_id.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ChainId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.reference)
+    }
+}
+
+impl FromStr for ChainId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (namespace, reference) = s.split_once(':')
+            .ok_or_else(|| Error::InvalidInput(format!("Invalid CAIP-2 chain ID: {}", s)))?;
+        Self::new(namespace, reference)
+    }
+}
+
+impl TryFrom<String> for ChainId {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<ChainId> for String {
+    fn from(chain_id: ChainId) -> Self {
+        chain_id.to_string()
+    }
+}
+
+/// A CAIP-19 asset ID
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AssetId {
+    /// Chain the asset lives on
+    pub chain_id: ChainId,
+    /// Asset namespace (`slip44`, `erc20`, `token`)
+    pub namespace: String,
+    /// Asset within the namespace
+    pub reference: String,
+}
+
+impl AssetId {
+    /// Create an asset ID, checking the CAIP-19 syntax
+    pub fn new(chain_id: ChainId, namespace: &str, reference: &str) -> Result<Self> {
+        if !is_namespace(namespace) {
+            return Err(Error::InvalidInput(format!("Invalid CAIP-19 asset namespace: {}", namespace)));
+        }
+        if !is_segment(reference, 1, 128, |c| matches!(c, '-' | '.' | '%') || c.is_ascii_alphanumeric()) {
+            return Err(Error::InvalidInput(format!("Invalid CAIP-19 asset reference: {}", reference)));
+        }
+        Ok(Self { chain_id, namespace: namespace.to_string(), reference: reference.to_string() })
+    }
+
+    /// Native coin of a chain, by its SLIP-44 coin type
+    pub fn native(chain_id: ChainId) -> Result<Self> {
+        let coin_type = match chain_id.key_type() {
+            Some(KeyType::Ethereum) => 60,
+            Some(KeyType::Solana) => 501,
+            Some(KeyType::Bitcoin) => 0,
+            Some(KeyType::Ton) => 607,
+            Some(KeyType::Cosmos) => CosmosChain::by_chain_id(&chain_id.reference)
+                .ok_or_else(|| Error::NotSupported(format!("No native asset known for {}", chain_id)))?
+                .slip44,
+            Some(KeyType::Xrp) => 144,
+            None => return Err(Error::NotSupported(format!("No native asset known for {}", chain_id))),
+        };
+        Ok(Self { chain_id, namespace: "slip44".to_string(), reference: coin_type.to_string() })
+    }
+
+    /// Asset ID of a token on `chain_id`
+    ///
+    /// The native-token placeholders of token lists map to the chain's
+    /// native coin. EVM contract addresses are lowercased so equal assets
+    /// have equal IDs.
+    pub fn from_token(chain_id: ChainId, token: &Token) -> Result<Self> {
+        if chain_id.key_type() != Some(token.key_type) {
+            return Err(Error::InvalidInput(format!("{:?} token cannot live on {}", token.key_type, chain_id)));
+        }
+        match token.key_type {
+            KeyType::Ethereum if token.address.eq_ignore_ascii_case(EVM_NATIVE_TOKEN) => Self::native(chain_id),
+            KeyType::Ethereum => Self::new(chain_id, "erc20", &token.address.to_ascii_lowercase()),
+            KeyType::Solana if token.address == SOLANA_NATIVE_TOKEN => Self::native(chain_id),
+            KeyType::Solana => Self::new(chain_id, "token", &token.address),
+            KeyType::Bitcoin => Self::native(chain_id),
+            KeyType::Ton if token.address == TON_NATIVE_TOKEN => Self::native(chain_id),
+            // Jetton masters by raw address, its colon escaped as CAIP-19 references require
+            KeyType::Ton => Self::new(chain_id, "jetton", &token.address.parse::<TonAddress>()?.to_raw().replace(':', "%3A")),
+            // Cosmos tokens are denoms: the staking denom, IBC vouchers by hash, or any other denom with slashes escaped
+            KeyType::Cosmos if is_cosmos_staking_denom(&chain_id, &token.address) => Self::native(chain_id),
+            KeyType::Cosmos => match token.address.strip_prefix("ibc/") {
+                Some(hash) => Self::new(chain_id, "ibc", hash),
+                None => Self::new(chain_id, "native", &token.address.replace('/', "%2F")),
+            },
+            KeyType::Xrp if token.address == XRP_NATIVE_TOKEN => Self::native(chain_id),
+            KeyType::Xrp => Err(Error::NotSupported(format!("XRP Ledger issued currencies have no asset ID: {}", token.address))),
+        }
+    }
+
+    /// Whether the asset is the chain's native coin
+    pub fn is_native(&self) -> bool {
+        self.namespace == "slip44"
+    }
+
+    /// Legacy token address of the asset, using the token-list placeholder for native coins
+    pub fn token_address(&self) -> Result<String> {
+        match (self.chain_id.key_type(), self.namespace.as_str()) {
+            (Some(KeyType::Ethereum), "slip44") => Ok(EVM_NATIVE_TOKEN.to_string()),
+            (Some(KeyType::Solana), "slip44") => Ok(SOLANA_NATIVE_TOKEN.to_string()),
+            (Some(KeyType::Bitcoin), "slip44") => Ok("BTC".to_string()),
+            (Some(KeyType::Ton), "slip44") => Ok(TON_NATIVE_TOKEN.to_string()),
+            (Some(KeyType::Ton), "jetton") => Ok(self.reference.replace("%3A", ":")),
+            (Some(KeyType::Cosmos), "slip44") => CosmosChain::by_chain_id(&self.chain_id.reference)
+                .map(|chain| chain.staking_denom)
+                .ok_or_else(|| Error::NotSupported(format!("Unsupported asset: {}", self))),
+            (Some(KeyType::Cosmos), "ibc") => Ok(format!("ibc/{}", self.reference)),
+            (Some(KeyType::Cosmos), "native") => Ok(self.reference.replace("%2F", "/")),
+            (Some(KeyType::Xrp), "slip44") => Ok(XRP_NATIVE_TOKEN.to_string()),
+            (Some(KeyType::Ethereum), "erc20") | (Some(KeyType::Solana), "token") => Ok(self.reference.clone()),
+            _ => Err(Error::NotSupported(format!("Unsupported asset: {}", self))),
+        }
+    }
+}
+
+impl fmt::Display for AssetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}:{}", self.chain_id, self.namespace, self.reference)
+    }
+}
+
+impl FromStr for AssetId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidInput(format!("Invalid CAIP-19 asset ID: {}", s));
+        let (chain_id, asset) = s.split_once('/').ok_or_else(invalid)?;
+        let (namespace, reference) = asset.split_once(':').ok_or_else(invalid)?;
+        Self::new(chain_id.parse()?, namespace, reference)
+    }
+}
+
+impl TryFrom<String> for AssetId {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<AssetId> for String {
+    fn from(asset_id: AssetId) -> Self {
+        asset_id.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(key_type: KeyType, address: &str) -> Token {
+        Token {
+            name: "Token".to_string(),
+            symbol: "TKN".to_string(),
+            decimals: 6,
+            address: address.to_string(),
+            key_type,
+            logo_url: None,
+        }
+    }
+
+    #[test]
+    fn test_chain_ids() {
+        let mainnet = NetworkBinding::Solana { genesis_hash: SOLANA_MAINNET_GENESIS_HASH.to_string() };
+        let chain_id = mainnet.chain_id().unwrap();
+        assert_eq!(chain_id.to_string(), "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp");
+        assert_eq!(chain_id.to_binding().unwrap(), mainnet);
+
+        let bitcoin: ChainId = "bip122:000000000019d6689c085ae165831e93".parse().unwrap();
+        assert_eq!(bitcoin.to_binding().unwrap(), NetworkBinding::Bitcoin { network: "bitcoin".to_string() });
+        assert_eq!(NetworkBinding::Evm { chain_id: 137 }.chain_id().unwrap(), ChainId::evm(137));
+
+        assert!("eip155".parse::<ChainId>().is_err());
+        assert!("EIP155:1".parse::<ChainId>().is_err());
+        assert_eq!("polkadot:91b171bb158e2d3848fa23a9f1c25182".parse::<ChainId>().unwrap().key_type(), None);
+
+        let cosmos_hub: ChainId = "cosmos:cosmoshub-4".parse().unwrap();
+        assert_eq!(cosmos_hub.key_type(), Some(KeyType::Cosmos));
+        assert_eq!(cosmos_hub.to_binding().unwrap().chain_id().unwrap(), cosmos_hub);
+        assert_eq!("xrpl:0".parse::<ChainId>().unwrap().to_binding().unwrap(), NetworkBinding::Xrp { network_id: 0 });
+    }
+
+    #[test]
+    fn test_asset_ids() {
+        let usdc = token(KeyType::Ethereum, "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+        let asset_id = AssetId::from_token(ChainId::evm(1), &usdc).unwrap();
+        assert_eq!(asset_id.to_string(), "eip155:1/erc20:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        assert_eq!(asset_id.to_string().parse::<AssetId>().unwrap(), asset_id);
+
+        let ether = AssetId::from_token(ChainId::evm(10), &token(KeyType::Ethereum, EVM_NATIVE_TOKEN)).unwrap();
+        assert_eq!(ether.to_string(), "eip155:10/slip44:60");
+        assert!(ether.is_native());
+        assert_eq!(ether.token_address().unwrap(), EVM_NATIVE_TOKEN);
+
+        let sol = AssetId::from_token(ChainId::solana(SOLANA_MAINNET_GENESIS_HASH), &token(KeyType::Solana, SOLANA_NATIVE_TOKEN)).unwrap();
+        assert_eq!(sol.reference, "501");
+
+        // Tokens only convert onto a chain of their own type
+        assert!(AssetId::from_token(ChainId::evm(1), &token(KeyType::Solana, SOLANA_NATIVE_TOKEN)).is_err());
+
+        let ton = NetworkBinding::Ton { global_id: TON_MAINNET_GLOBAL_ID }.chain_id().unwrap();
+        assert_eq!(ton.to_string(), "tvm:-239");
+        let usdt = AssetId::from_token(ton, &token(KeyType::Ton, "EQCxE6mUtQJKFnGfaROTKOt1lZbDiiX1kCixRv7Nw2Id_sDs")).unwrap();
+        assert_eq!(usdt.to_string().parse::<AssetId>().unwrap(), usdt);
+        assert_eq!(usdt.token_address().unwrap(), "0:b113a994b5024a16719f69139328eb759596c38a25f59028b146fecdc3621dfe");
+
+        let osmosis = NetworkBinding::Cosmos { chain_id: "osmosis-1".to_string() }.chain_id().unwrap();
+        assert_eq!(AssetId::from_token(osmosis.clone(), &token(KeyType::Cosmos, "uosmo")).unwrap().to_string(), "cosmos:osmosis-1/slip44:118");
+        let atom = AssetId::from_token(osmosis.clone(), &token(KeyType::Cosmos, "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2")).unwrap();
+        assert_eq!(atom.to_string(), "cosmos:osmosis-1/ibc:27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2");
+        assert_eq!(atom.token_address().unwrap(), "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2");
+        let factory = AssetId::from_token(osmosis, &token(KeyType::Cosmos, "factory/osmo1abc/ufoo")).unwrap();
+        assert_eq!(factory.token_address().unwrap(), "factory/osmo1abc/ufoo");
+
+        let json = serde_json::to_string(&asset_id).unwrap();
+        assert_eq!(serde_json::from_str::<AssetId>(&json).unwrap(), asset_id);
+    }
+}
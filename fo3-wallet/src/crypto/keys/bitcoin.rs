@@ -3,7 +3,7 @@
 use hmac::{Hmac, Mac};
 use hmac::digest::KeyInit;
 use sha2::{Sha256, Sha512, Digest};
-use secp256k1::{Secp256k1, SecretKey, PublicKey as Secp256k1PublicKey};
+use secp256k1::{Message, Secp256k1, SecretKey, PublicKey as Secp256k1PublicKey};
 // We'll use the bs58 crate directly
 use bs58;
 pub use bitcoin::Network;
@@ -177,3 +177,45 @@ pub fn public_key_to_address(public_key: &PublicKey, network: Network) -> Result
     // Encode as base58
     Ok(bs58::encode(address).into_string())
 }
+
+/// Sign a message as BIP-137 does for a compressed P2PKH key
+///
+/// Returns 65 bytes `header || r || s`, the header (31-34) carrying the
+/// recovery ID.
+pub fn sign_message(key_pair: &KeyPair, message: &[u8]) -> Result<Vec<u8>> {
+    if key_pair.key_type() != KeyType::Bitcoin {
+        return Err(Error::Signing("Not a Bitcoin key pair".to_string()));
+    }
+    let secret_key = SecretKey::from_slice(key_pair.private_key().as_bytes())
+        .map_err(|e| Error::Signing(format!("Invalid private key: {}", e)))?;
+
+    let (recovery_id, compact) = Secp256k1::signing_only()
+        .sign_ecdsa_recoverable(&Message::from_digest(message_hash(message)), &secret_key)
+        .serialize_compact();
+
+    let mut signature = vec![31 + recovery_id.to_i32() as u8];
+    signature.extend_from_slice(&compact);
+    Ok(signature)
+}
+
+/// Double SHA-256 of a message behind the Bitcoin signed message prefix
+fn message_hash(message: &[u8]) -> [u8; 32] {
+    const PREFIX: &[u8] = b"\x18Bitcoin Signed Message:\n";
+
+    let mut data = PREFIX.to_vec();
+    // Compact size length prefix
+    match message.len() {
+        len @ 0..=0xfc => data.push(len as u8),
+        len @ 0xfd..=0xffff => {
+            data.push(0xfd);
+            data.extend_from_slice(&(len as u16).to_le_bytes());
+        }
+        len => {
+            data.push(0xfe);
+            data.extend_from_slice(&(len as u32).to_le_bytes());
+        }
+    }
+    data.extend_from_slice(message);
+
+    Sha256::digest(Sha256::digest(&data)).into()
+}
@@ -0,0 +1,52 @@
+//! Cosmos SDK key derivation
+//!
+//! Cosmos chains use secp256k1 keys derived with BIP-32 along
+//! `m/44'/118'/0'/0/{index}` (some chains use their own coin type). An
+//! account address is the bech32 encoding of the compressed public key's
+//! HASH160 under the chain's prefix (`cosmos`, `osmo`, ...).
+
+use bitcoin::hashes::{hash160, Hash};
+
+use crate::address::bech32_encode;
+use crate::error::{Error, Result};
+use super::derivation::{KeyPair, PrivateKey, PublicKey, KeyType};
+
+/// Derive a Cosmos key pair from a seed and derivation path
+pub fn derive_cosmos_key_pair(seed: &[u8], path: &str) -> Result<KeyPair> {
+    // Same BIP-32 secp256k1 derivation as Bitcoin, with compressed public keys
+    let key_pair = super::bitcoin::derive_bitcoin_key_pair(seed, path)?;
+
+    let private_key = PrivateKey::new(key_pair.private_key().as_bytes().to_vec(), KeyType::Cosmos);
+    let public_key = PublicKey::new(key_pair.public_key().as_bytes().to_vec(), KeyType::Cosmos);
+
+    KeyPair::new(private_key, public_key)
+}
+
+/// Get the account address of a public key under a bech32 prefix
+pub fn public_key_to_address(public_key: &PublicKey, prefix: &str) -> Result<String> {
+    if public_key.key_type() != KeyType::Cosmos {
+        return Err(Error::KeyDerivation("Not a Cosmos public key".to_string()));
+    }
+    if public_key.as_bytes().len() != 33 {
+        return Err(Error::KeyDerivation("Cosmos public keys must be compressed".to_string()));
+    }
+
+    let hash = hash160::Hash::hash(public_key.as_bytes());
+    Ok(bech32_encode(prefix, hash.as_byte_array()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::mnemonic::mnemonic_to_seed;
+
+    #[test]
+    fn test_derive_address() {
+        let seed = mnemonic_to_seed("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap();
+        let key_pair = derive_cosmos_key_pair(&seed, "m/44'/118'/0'/0/0").unwrap();
+
+        assert_eq!(hex::encode(key_pair.public_key().as_bytes()), "024f4e2ad99c34d60b9ba6283c9431a8418af8673212961f97a77b6377fcd05b62");
+        assert_eq!(public_key_to_address(key_pair.public_key(), "cosmos").unwrap(), "cosmos19rl4cm2hmr8afy4kldpxz3fka4jguq0auqdal4");
+        assert_eq!(public_key_to_address(key_pair.public_key(), "osmo").unwrap(), "osmo19rl4cm2hmr8afy4kldpxz3fka4jguq0a5m7df8");
+    }
+}
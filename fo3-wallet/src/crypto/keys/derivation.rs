@@ -3,7 +3,7 @@
 use crate::error::{Error, Result};
 
 /// Supported key types
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum KeyType {
     /// Ethereum and EVM compatible chains
     Ethereum,
@@ -11,6 +11,12 @@ pub enum KeyType {
     Solana,
     /// Bitcoin
     Bitcoin,
+    /// TON
+    Ton,
+    /// Cosmos SDK chains (Cosmos Hub, Osmosis, ...)
+    Cosmos,
+    /// XRP Ledger
+    Xrp,
 }
 
 /// A private key for a specific blockchain
@@ -105,5 +111,235 @@ pub fn derive_key_pair(seed: &[u8], key_type: KeyType, path: &str) -> Result<Key
         KeyType::Ethereum => crate::crypto::keys::ethereum::derive_ethereum_key_pair(seed, path),
         KeyType::Solana => crate::crypto::keys::solana::derive_solana_key_pair(seed, path),
         KeyType::Bitcoin => crate::crypto::keys::bitcoin::derive_bitcoin_key_pair(seed, path),
+        KeyType::Ton => crate::crypto::keys::ton::derive_ton_key_pair(seed, path),
+        KeyType::Cosmos => crate::crypto::keys::cosmos::derive_cosmos_key_pair(seed, path),
+        KeyType::Xrp => crate::crypto::keys::xrp::derive_xrp_key_pair(seed, path),
+    }
+}
+
+/// Placeholder for the account number in a derivation path template
+pub const ACCOUNT_PLACEHOLDER: &str = "{account}";
+
+/// Placeholder for the address index in a derivation path template
+pub const INDEX_PLACEHOLDER: &str = "{index}";
+
+/// Derivation path conventions of common wallets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum PathPreset {
+    /// MetaMask and most EVM wallets: `m/44'/60'/0'/0/{index}`
+    MetaMask,
+    /// Ledger Live: `m/44'/60'/{account}'/0/0`
+    LedgerLive,
+    /// Ledger legacy, MyEtherWallet and MyCrypto: `m/44'/60'/0'/{index}`
+    LedgerLegacy,
+    /// Phantom: `m/44'/501'/{account}'/0'`
+    Phantom,
+    /// Solflare, Solana CLI and Ledger: `m/44'/501'/{account}'`
+    Solflare,
+    /// BIP-44 legacy P2PKH: `m/44'/0'/{account}'/0/{index}`
+    Bip44,
+    /// BIP-49 nested SegWit: `m/49'/0'/{account}'/0/{index}`
+    Bip49,
+    /// BIP-84 native SegWit: `m/84'/0'/{account}'/0/{index}`
+    Bip84,
+    /// BIP-86 Taproot: `m/86'/0'/{account}'/0/{index}`
+    Bip86,
+    /// Ledger TON app and BIP-39 based TON wallets: `m/44'/607'/{account}'`
+    LedgerTon,
+    /// Keplr, Leap and the Cosmos SDK CLI: `m/44'/118'/0'/0/{index}`
+    Keplr,
+    /// Ledger Live and BIP-39 based XRP wallets: `m/44'/144'/{account}'/0/0`
+    LedgerXrp,
+}
+
+impl PathPreset {
+    /// Get all presets for a key type
+    pub fn for_key_type(key_type: KeyType) -> &'static [PathPreset] {
+        match key_type {
+            KeyType::Ethereum => &[Self::MetaMask, Self::LedgerLive, Self::LedgerLegacy],
+            KeyType::Solana => &[Self::Phantom, Self::Solflare],
+            KeyType::Bitcoin => &[Self::Bip44, Self::Bip49, Self::Bip84, Self::Bip86],
+            KeyType::Ton => &[Self::LedgerTon],
+            KeyType::Cosmos => &[Self::Keplr],
+            KeyType::Xrp => &[Self::LedgerXrp],
+        }
+    }
+
+    /// Get the key type the preset applies to
+    pub fn key_type(&self) -> KeyType {
+        match self {
+            Self::MetaMask | Self::LedgerLive | Self::LedgerLegacy => KeyType::Ethereum,
+            Self::Phantom | Self::Solflare => KeyType::Solana,
+            Self::Bip44 | Self::Bip49 | Self::Bip84 | Self::Bip86 => KeyType::Bitcoin,
+            Self::LedgerTon => KeyType::Ton,
+            Self::Keplr => KeyType::Cosmos,
+            Self::LedgerXrp => KeyType::Xrp,
+        }
+    }
+
+    /// Get the path template
+    pub fn template(&self) -> &'static str {
+        match self {
+            Self::MetaMask => "m/44'/60'/0'/0/{index}",
+            Self::LedgerLive => "m/44'/60'/{account}'/0/0",
+            Self::LedgerLegacy => "m/44'/60'/0'/{index}",
+            Self::Phantom => "m/44'/501'/{account}'/0'",
+            Self::Solflare => "m/44'/501'/{account}'",
+            Self::Bip44 => "m/44'/0'/{account}'/0/{index}",
+            Self::Bip49 => "m/49'/0'/{account}'/0/{index}",
+            Self::Bip84 => "m/84'/0'/{account}'/0/{index}",
+            Self::Bip86 => "m/86'/0'/{account}'/0/{index}",
+            Self::LedgerTon => "m/44'/607'/{account}'",
+            Self::Keplr => "m/44'/118'/0'/0/{index}",
+            Self::LedgerXrp => "m/44'/144'/{account}'/0/0",
+        }
+    }
+}
+
+/// A derivation path with `{account}` and `{index}` placeholders
+///
+/// Templates let wallets imported from other software land on the same
+/// addresses, e.g. Ledger Live increments the account where MetaMask
+/// increments the address index.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DerivationPathTemplate {
+    /// The type of key derived
+    key_type: KeyType,
+    /// The path template
+    template: String,
+}
+
+impl DerivationPathTemplate {
+    /// Create a template, validating it for the key type
+    pub fn new(key_type: KeyType, template: &str) -> Result<Self> {
+        let template = template.trim();
+        let components = template.strip_prefix("m/")
+            .ok_or_else(|| Error::KeyDerivation(format!("Derivation path must start with m/: {}", template)))?;
+
+        let mut seen_account = false;
+        let mut seen_index = false;
+
+        for component in components.split('/') {
+            let (value, hardened) = match component.strip_suffix('\'') {
+                Some(value) => (value, true),
+                None => (component, false),
+            };
+
+            match value {
+                ACCOUNT_PLACEHOLDER if !seen_account => seen_account = true,
+                INDEX_PLACEHOLDER if !seen_index => seen_index = true,
+                ACCOUNT_PLACEHOLDER | INDEX_PLACEHOLDER => {
+                    return Err(Error::KeyDerivation(format!("Duplicate placeholder {} in {}", value, template)));
+                }
+                _ => {
+                    let index = value.parse::<u32>()
+                        .map_err(|_| Error::KeyDerivation(format!("Invalid derivation path component: {}", component)))?;
+                    if index >= 0x80000000 {
+                        return Err(Error::KeyDerivation(format!("Derivation path component out of range: {}", component)));
+                    }
+                }
+            }
+
+            // SLIP-10 ed25519 only defines hardened derivation
+            if matches!(key_type, KeyType::Solana | KeyType::Ton) && !hardened {
+                return Err(Error::KeyDerivation(format!("{:?} paths must be fully hardened: {}", key_type, template)));
+            }
+        }
+
+        Ok(Self {
+            key_type,
+            template: template.to_string(),
+        })
+    }
+
+    /// Create a template from a preset
+    pub fn preset(preset: PathPreset) -> Self {
+        Self {
+            key_type: preset.key_type(),
+            template: preset.template().to_string(),
+        }
+    }
+
+    /// Get the key type
+    pub fn key_type(&self) -> KeyType {
+        self.key_type
+    }
+
+    /// Get the path template
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// Render the path for an account and address index
+    ///
+    /// Placeholders absent from the template ignore their argument.
+    pub fn path(&self, account: u32, index: u32) -> Result<String> {
+        if account >= 0x80000000 || index >= 0x80000000 {
+            return Err(Error::KeyDerivation("Account and index must be below 2^31".to_string()));
+        }
+
+        Ok(self.template
+            .replace(ACCOUNT_PLACEHOLDER, &account.to_string())
+            .replace(INDEX_PLACEHOLDER, &index.to_string()))
+    }
+
+    /// Derive the key pair for an account and address index
+    pub fn derive(&self, seed: &[u8], account: u32, index: u32) -> Result<KeyPair> {
+        derive_key_pair(seed, self.key_type, &self.path(account, index)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preset_paths() {
+        let metamask = DerivationPathTemplate::preset(PathPreset::MetaMask);
+        let ledger_live = DerivationPathTemplate::preset(PathPreset::LedgerLive);
+
+        assert_eq!(metamask.path(0, 0).unwrap(), ledger_live.path(0, 0).unwrap());
+        assert_eq!(metamask.path(0, 1).unwrap(), "m/44'/60'/0'/0/1");
+        assert_eq!(ledger_live.path(1, 0).unwrap(), "m/44'/60'/1'/0/0");
+
+        assert_eq!(DerivationPathTemplate::preset(PathPreset::Phantom).path(2, 0).unwrap(), "m/44'/501'/2'/0'");
+        assert_eq!(DerivationPathTemplate::preset(PathPreset::Bip84).path(0, 5).unwrap(), "m/84'/0'/0'/0/5");
+    }
+
+    #[test]
+    fn test_presets_are_valid() {
+        for key_type in [KeyType::Ethereum, KeyType::Solana, KeyType::Bitcoin, KeyType::Ton, KeyType::Cosmos, KeyType::Xrp] {
+            for preset in PathPreset::for_key_type(key_type) {
+                assert_eq!(preset.key_type(), key_type);
+                assert!(DerivationPathTemplate::new(key_type, preset.template()).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_template_validation() {
+        assert!(DerivationPathTemplate::new(KeyType::Ethereum, "m/44'/60'/{account}'/0/{index}").is_ok());
+        assert!(DerivationPathTemplate::new(KeyType::Ethereum, "44'/60'/0'/0/0").is_err());
+        assert!(DerivationPathTemplate::new(KeyType::Ethereum, "m/44'/60'/{index}'/0/{index}").is_err());
+        assert!(DerivationPathTemplate::new(KeyType::Ethereum, "m/44'/60'/x/0").is_err());
+        assert!(DerivationPathTemplate::new(KeyType::Ethereum, "m/2147483648").is_err());
+
+        // ed25519 cannot derive non-hardened children
+        assert!(DerivationPathTemplate::new(KeyType::Solana, "m/44'/501'/{account}'/0").is_err());
+    }
+
+    #[test]
+    fn test_derive_from_template() {
+        let seed = [7u8; 64];
+        let metamask = DerivationPathTemplate::preset(PathPreset::MetaMask);
+        let ledger_live = DerivationPathTemplate::preset(PathPreset::LedgerLive);
+
+        let first = metamask.derive(&seed, 0, 0).unwrap();
+        assert_eq!(first.public_key().as_bytes(), ledger_live.derive(&seed, 0, 0).unwrap().public_key().as_bytes());
+
+        // Second accounts differ between the two conventions
+        let metamask_second = metamask.derive(&seed, 0, 1).unwrap();
+        let ledger_second = ledger_live.derive(&seed, 1, 0).unwrap();
+        assert_ne!(metamask_second.public_key().as_bytes(), ledger_second.public_key().as_bytes());
     }
 }
@@ -6,6 +6,9 @@
 pub mod ethereum;
 pub mod solana;
 pub mod bitcoin;
+pub mod ton;
+pub mod cosmos;
+pub mod xrp;
 mod derivation;
 
 pub use derivation::*;
@@ -10,6 +10,20 @@ use super::derivation::{KeyPair, PrivateKey, PublicKey, KeyType};
 
 /// Derive a Solana key pair from a seed and derivation path
 pub fn derive_solana_key_pair(seed: &[u8], path: &str) -> Result<KeyPair> {
+    let secret_key = derive_ed25519_secret(seed, path)?;
+    
+    // Create the key pair
+    let signing_key = SigningKey::from_bytes(&secret_key);
+    let verifying_key = VerifyingKey::from(&signing_key);
+    
+    let private_key = PrivateKey::new(signing_key.to_bytes().to_vec(), KeyType::Solana);
+    let public_key = PublicKey::new(verifying_key.to_bytes().to_vec(), KeyType::Solana);
+    
+    KeyPair::new(private_key, public_key)
+}
+
+/// Derive an ed25519 secret key from a seed and derivation path (SLIP-10)
+pub(super) fn derive_ed25519_secret(seed: &[u8], path: &str) -> Result<[u8; 32]> {
     // Parse the derivation path
     let path_components = parse_derivation_path(path)?;
     
@@ -21,14 +35,7 @@ pub fn derive_solana_key_pair(seed: &[u8], path: &str) -> Result<KeyPair> {
         (secret_key, chain_code) = derive_child_key(secret_key, chain_code, component)?;
     }
     
-    // Create the key pair
-    let signing_key = SigningKey::from_bytes(&secret_key);
-    let verifying_key = VerifyingKey::from(&signing_key);
-    
-    let private_key = PrivateKey::new(signing_key.to_bytes().to_vec(), KeyType::Solana);
-    let public_key = PublicKey::new(verifying_key.to_bytes().to_vec(), KeyType::Solana);
-    
-    KeyPair::new(private_key, public_key)
+    Ok(secret_key)
 }
 
 /// Parse a BIP-32 derivation path
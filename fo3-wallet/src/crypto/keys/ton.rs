@@ -0,0 +1,127 @@
+//! TON key derivation
+//!
+//! TON wallets use ed25519 keys from one of two sources: a TON mnemonic
+//! (24 BIP-39 words hashed with TON's own scheme, as in Tonkeeper and
+//! the TON wallet apps) or a BIP-39 seed derived along `m/44'/607'/{account}'`
+//! (SLIP-10, as in Ledger). Addresses are those of a v4R2 wallet contract.
+
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use hmac::{Hmac, Mac};
+use hmac::digest::KeyInit;
+use pbkdf2::pbkdf2_hmac;
+use rand::{rngs::OsRng, seq::SliceRandom};
+use sha2::Sha512;
+
+use crate::error::{Error, Result};
+use crate::transaction::TonWallet;
+use super::derivation::{KeyPair, PrivateKey, PublicKey, KeyType};
+
+/// Number of words in a TON mnemonic
+pub const TON_MNEMONIC_WORDS: usize = 24;
+
+/// PBKDF2 iterations deriving the key from a TON mnemonic
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// Derive a TON key pair from a BIP-39 seed and derivation path (SLIP-10)
+pub fn derive_ton_key_pair(seed: &[u8], path: &str) -> Result<KeyPair> {
+    let secret_key = super::solana::derive_ed25519_secret(seed, path)?;
+    key_pair_from_secret(&secret_key)
+}
+
+/// Generate a TON mnemonic without a password
+pub fn generate_ton_mnemonic() -> Result<String> {
+    let words = bip39::Language::English.word_list();
+    // About one phrase in 256 is a valid basic seed
+    loop {
+        let phrase = (0..TON_MNEMONIC_WORDS)
+            .map(|_| *words.choose(&mut OsRng).expect("word list is not empty"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if is_ton_mnemonic(&phrase) {
+            return Ok(phrase);
+        }
+    }
+}
+
+/// Whether a phrase is a TON mnemonic without a password
+///
+/// TON mnemonics have no checksum word; instead the phrase's entropy must
+/// hash to a zero first byte.
+pub fn is_ton_mnemonic(phrase: &str) -> bool {
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+    if words.len() != TON_MNEMONIC_WORDS || words.iter().any(|word| bip39::Language::English.find_word(word).is_none()) {
+        return false;
+    }
+    let mut seed = [0u8; 64];
+    pbkdf2_hmac::<Sha512>(&entropy(&words.join(" "), ""), b"TON seed version", PBKDF2_ITERATIONS / 256, &mut seed);
+    seed[0] == 0
+}
+
+/// Derive the TON key pair of a TON mnemonic and optional password
+pub fn ton_mnemonic_to_key_pair(phrase: &str, password: Option<&str>) -> Result<KeyPair> {
+    let phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+    if password.is_none() && !is_ton_mnemonic(&phrase) {
+        return Err(Error::Mnemonic("Not a TON mnemonic".to_string()));
+    }
+    let mut seed = [0u8; 64];
+    pbkdf2_hmac::<Sha512>(&entropy(&phrase, password.unwrap_or("")), b"TON default seed", PBKDF2_ITERATIONS, &mut seed);
+    let secret_key: [u8; 32] = seed[..32].try_into().expect("seed is 64 bytes");
+    key_pair_from_secret(&secret_key)
+}
+
+fn entropy(phrase: &str, password: &str) -> Vec<u8> {
+    let mut hmac = <Hmac<Sha512> as KeyInit>::new_from_slice(phrase.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    hmac.update(password.as_bytes());
+    hmac.finalize().into_bytes().to_vec()
+}
+
+fn key_pair_from_secret(secret_key: &[u8; 32]) -> Result<KeyPair> {
+    let signing_key = SigningKey::from_bytes(secret_key);
+    let verifying_key = VerifyingKey::from(&signing_key);
+
+    let private_key = PrivateKey::new(signing_key.to_bytes().to_vec(), KeyType::Ton);
+    let public_key = PublicKey::new(verifying_key.to_bytes().to_vec(), KeyType::Ton);
+
+    KeyPair::new(private_key, public_key)
+}
+
+/// Get the address of the v4R2 wallet of a public key, non-bounceable as wallets display it
+pub fn public_key_to_address(public_key: &PublicKey, testnet: bool) -> Result<String> {
+    if public_key.key_type() != KeyType::Ton {
+        return Err(Error::KeyDerivation("Not a TON public key".to_string()));
+    }
+    let public_key: [u8; 32] = public_key.as_bytes().try_into()
+        .map_err(|_| Error::KeyDerivation("Invalid TON public key length".to_string()))?;
+
+    Ok(TonWallet::new(public_key).address().to_friendly(false, testnet))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str = "affair age advice abuse addict acquire acquire adult airport act acquire advance accuse advance actual age adult acid actor accident age accident album again";
+
+    #[test]
+    fn test_ton_mnemonic() {
+        assert!(is_ton_mnemonic(PHRASE));
+        assert!(!is_ton_mnemonic(&PHRASE.replacen("affair", "age", 1)));
+        assert!(!is_ton_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"));
+
+        let key_pair = ton_mnemonic_to_key_pair(PHRASE, None).unwrap();
+        assert_eq!(hex::encode(key_pair.private_key().as_bytes()), "b839d2a30b47fd21edbcadf732cb4e69bc36d44889c7e731705c9f8fb894cd45");
+        assert_eq!(hex::encode(key_pair.public_key().as_bytes()), "454c4e5fccaf1ff954e7f6e2c3457caec7309480e2ae260203704265551c55b7");
+        assert_eq!(public_key_to_address(key_pair.public_key(), false).unwrap(), "UQDAmzgkCP_QqkZg7shsqi2QM_FSBibVu47gM5vTFk7dnJtq");
+        assert_eq!(public_key_to_address(key_pair.public_key(), true).unwrap(), "0QDAmzgkCP_QqkZg7shsqi2QM_FSBibVu47gM5vTFk7dnCDg");
+    }
+
+    #[test]
+    fn test_derive_from_seed() {
+        let seed = [7u8; 64];
+        let first = derive_ton_key_pair(&seed, "m/44'/607'/0'").unwrap();
+        let second = derive_ton_key_pair(&seed, "m/44'/607'/1'").unwrap();
+        assert_eq!(first.key_type(), KeyType::Ton);
+        assert_ne!(first.public_key().as_bytes(), second.public_key().as_bytes());
+    }
+}
@@ -0,0 +1,55 @@
+//! XRP Ledger key derivation
+//!
+//! BIP-39 based XRP wallets (Ledger, Xaman with a BIP-39 secret) use
+//! secp256k1 keys derived with BIP-32 along `m/44'/144'/{account}'/0/0`. A
+//! classic address is the HASH160 of the compressed public key (the account
+//! ID) in base58check with the XRP Ledger's alphabet.
+
+use bitcoin::hashes::{hash160, Hash};
+
+use crate::error::{Error, Result};
+use crate::transaction::XrpAddress;
+use super::derivation::{KeyPair, PrivateKey, PublicKey, KeyType};
+
+/// Derive an XRP Ledger key pair from a seed and derivation path
+pub fn derive_xrp_key_pair(seed: &[u8], path: &str) -> Result<KeyPair> {
+    // Same BIP-32 secp256k1 derivation as Bitcoin, with compressed public keys
+    let key_pair = super::bitcoin::derive_bitcoin_key_pair(seed, path)?;
+
+    let private_key = PrivateKey::new(key_pair.private_key().as_bytes().to_vec(), KeyType::Xrp);
+    let public_key = PublicKey::new(key_pair.public_key().as_bytes().to_vec(), KeyType::Xrp);
+
+    KeyPair::new(private_key, public_key)
+}
+
+/// Get the account ID of a public key
+pub fn public_key_to_account_id(public_key: &PublicKey) -> Result<[u8; 20]> {
+    if public_key.key_type() != KeyType::Xrp {
+        return Err(Error::KeyDerivation("Not an XRP public key".to_string()));
+    }
+    if public_key.as_bytes().len() != 33 {
+        return Err(Error::KeyDerivation("XRP public keys must be compressed".to_string()));
+    }
+
+    Ok(hash160::Hash::hash(public_key.as_bytes()).to_byte_array())
+}
+
+/// Get the classic address (`r...`) of a public key
+pub fn public_key_to_address(public_key: &PublicKey) -> Result<String> {
+    Ok(XrpAddress::new(public_key_to_account_id(public_key)?).to_classic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::mnemonic::mnemonic_to_seed;
+
+    #[test]
+    fn test_derive_address() {
+        let seed = mnemonic_to_seed("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap();
+        let key_pair = derive_xrp_key_pair(&seed, "m/44'/144'/0'/0/0").unwrap();
+
+        assert_eq!(hex::encode(key_pair.public_key().as_bytes()), "031d68bc1a142e6766b2bdfb006ccfe135ef2e0e2e94abb5cf5c9ab6104776fbae");
+        assert_eq!(public_key_to_address(key_pair.public_key()).unwrap(), "rHsMGQEkVNJmpGWs8XUBoTBiAAbwxZN5v3");
+    }
+}
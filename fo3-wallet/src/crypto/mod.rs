@@ -1,10 +1,11 @@
 //! Cryptographic primitives and operations
 //!
 //! This module provides functionality for mnemonic generation, key derivation,
-//! and cryptographic operations required for wallet management.
+//! key sharding, and cryptographic operations required for wallet management.
 
 pub mod mnemonic;
 pub mod keys;
+pub mod sharding;
 
 pub use mnemonic::*;
 pub use keys::*;
@@ -0,0 +1,201 @@
+//! Two-party ECDSA over secp256k1, after Lindell (CRYPTO 2017)
+//!
+//! The key is shared multiplicatively, `x = x_device * x_server`, and so is
+//! each signature's nonce. The server holds a Paillier key and hands the
+//! device an encryption of its share; the device folds its share and nonce
+//! into that ciphertext, masked, and the server decrypts the result into
+//! the signature. Neither party learns `x` or the full nonce.
+//!
+//! The zero-knowledge proofs of the paper, that the Paillier modulus is well
+//! formed and encrypts the server's share, are left out: they protect the
+//! device against a server that cheats during signing.
+
+use num_bigint::{BigUint, RandBigInt};
+use rand::rngs::OsRng;
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use secp256k1::{Message, PublicKey, Scalar, Secp256k1, SecretKey};
+
+use crate::error::{Error, Result};
+use super::paillier::{PaillierKey, PaillierPublicKey};
+
+/// Smallest Paillier modulus the device accepts, in bits
+///
+/// Decrypted plaintexts are below `2 * n^3` for the curve order `n`, which
+/// must not wrap around the modulus.
+pub const MIN_PAILLIER_MODULUS_BITS: u64 = 1024;
+
+/// Split a secret key into a random share and its complement
+pub(super) fn split(secret: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let secret = to_scalar(secret)?;
+    let share = random_scalar();
+    let complement = secret * invert(&share) % order();
+    Ok((to_bytes(&share), to_bytes(&complement)))
+}
+
+/// The public share of a share, compressed
+pub(super) fn public_share(share: &[u8]) -> Result<Vec<u8>> {
+    let secret = SecretKey::from_slice(share)
+        .map_err(|e| Error::KeyDerivation(format!("Invalid key share: {}", e)))?;
+    Ok(PublicKey::from_secret_key(&Secp256k1::new(), &secret).serialize().to_vec())
+}
+
+/// The public key a share completes with the share behind `peer_public_share`
+pub(super) fn public_key(share: &[u8], peer_public_share: &[u8]) -> Result<Vec<u8>> {
+    Ok(mul_point(&parse_point(peer_public_share)?, &to_scalar(share)?)?.serialize().to_vec())
+}
+
+/// A random factor to refresh a pair of shares with
+pub(super) fn refresh_factor() -> Vec<u8> {
+    to_bytes(&random_scalar())
+}
+
+/// Multiply a share by a refresh factor
+pub(super) fn refresh_up(share: &[u8], factor: &[u8]) -> Result<Vec<u8>> {
+    Ok(to_bytes(&(to_scalar(share)? * to_scalar(factor)? % order())))
+}
+
+/// Divide a share by a refresh factor
+pub(super) fn refresh_down(share: &[u8], factor: &[u8]) -> Result<Vec<u8>> {
+    Ok(to_bytes(&(to_scalar(share)? * invert(&to_scalar(factor)?) % order())))
+}
+
+/// The public share of a share divided by a refresh factor
+pub(super) fn refresh_public_down(public_share: &[u8], factor: &[u8]) -> Result<Vec<u8>> {
+    let inverse = invert(&to_scalar(factor)?);
+    Ok(mul_point(&parse_point(public_share)?, &inverse)?.serialize().to_vec())
+}
+
+/// The public share of a share multiplied by a refresh factor
+pub(super) fn refresh_public_up(public_share: &[u8], factor: &[u8]) -> Result<Vec<u8>> {
+    Ok(mul_point(&parse_point(public_share)?, &to_scalar(factor)?)?.serialize().to_vec())
+}
+
+/// The server's nonce share and its public nonce
+pub(super) fn server_nonce() -> Result<(Vec<u8>, Vec<u8>)> {
+    let nonce = to_bytes(&random_scalar());
+    let public_nonce = public_share(&nonce)?;
+    Ok((nonce, public_nonce))
+}
+
+/// Encrypt the server's share for the device to sign with
+pub(super) fn encrypt_share(paillier: &PaillierKey, share: &[u8]) -> Result<(BigUint, BigUint)> {
+    let public_key = paillier.public_key()?;
+    let encrypted = public_key.encrypt(&to_scalar(share)?)?;
+    Ok((public_key.modulus().clone(), encrypted))
+}
+
+/// The device's half of a signature: its public nonce and the encrypted,
+/// masked signature the server decrypts
+pub(super) fn partial_sign(share: &[u8], server_nonce: &[u8], modulus: &BigUint, encrypted_share: &BigUint, hash: &[u8]) -> Result<(Vec<u8>, BigUint)> {
+    if modulus.bits() < MIN_PAILLIER_MODULUS_BITS {
+        return Err(Error::Signing(format!("Paillier modulus must be at least {} bits", MIN_PAILLIER_MODULUS_BITS)));
+    }
+    let paillier = PaillierPublicKey::new(modulus.clone());
+    let n = order();
+    let share = to_scalar(share)?;
+    let message = message_scalar(hash)?;
+    let server_nonce = parse_point(server_nonce)?;
+
+    loop {
+        let nonce = random_scalar();
+        let r = x_coordinate(&mul_point(&server_nonce, &nonce)?) % &n;
+        if r.bits() == 0 {
+            continue;
+        }
+
+        // Enc(rho * n + k^-1 * m) + Enc(x_server) * (k^-1 * r * x_device)
+        let inverse = invert(&nonce);
+        let mask = OsRng.gen_biguint_below(&(&n * &n));
+        let masked = paillier.encrypt(&(mask * &n + &inverse * &message % &n))?;
+        let weight = inverse * r % &n * &share % &n;
+        let combined = paillier.add(&masked, &paillier.mul(encrypted_share, &weight));
+
+        return Ok((public_share(&to_bytes(&nonce))?, combined));
+    }
+}
+
+/// Complete a signature from the device's half, returning it as
+/// `r || s || recovery_id` with a low `s`
+pub(super) fn complete(paillier: &PaillierKey, nonce: &[u8], device_nonce: &[u8], partial: &BigUint, hash: &[u8], public_key: &[u8]) -> Result<Vec<u8>> {
+    let n = order();
+    let point = mul_point(&parse_point(device_nonce)?, &to_scalar(nonce)?)?;
+    let x = x_coordinate(&point);
+    let r = &x % &n;
+
+    let s = invert(&to_scalar(nonce)?) * (paillier.decrypt(partial)? % &n) % &n;
+    if r.bits() == 0 || s.bits() == 0 {
+        return Err(Error::Signing("Partial signature does not complete a valid signature".to_string()));
+    }
+
+    let mut recovery_id = (point.serialize()[0] == 0x03) as i32 | if x >= n { 2 } else { 0 };
+    let s = if s > &n >> 1 {
+        recovery_id ^= 1;
+        &n - s
+    } else {
+        s
+    };
+
+    let mut signature = [to_bytes(&r), to_bytes(&s)].concat();
+    let message = Message::from_digest_slice(hash)
+        .map_err(|e| Error::Signing(format!("Message must be a 32-byte hash: {}", e)))?;
+    let recoverable = RecoveryId::from_i32(recovery_id)
+        .and_then(|id| RecoverableSignature::from_compact(&signature, id))
+        .map_err(|e| Error::Signing(format!("Invalid signature: {}", e)))?;
+    let recovered = Secp256k1::new().recover_ecdsa(&message, &recoverable).ok();
+    if recovered.map(|key| key.serialize().to_vec()).as_deref() != Some(public_key) {
+        return Err(Error::Signing("Partial signature does not complete a valid signature".to_string()));
+    }
+
+    signature.push(recovery_id as u8);
+    Ok(signature)
+}
+
+fn order() -> BigUint {
+    BigUint::from_bytes_be(&secp256k1::constants::CURVE_ORDER)
+}
+
+fn random_scalar() -> BigUint {
+    OsRng.gen_biguint_range(&BigUint::from(1u32), &order())
+}
+
+fn invert(scalar: &BigUint) -> BigUint {
+    // Scalars are nonzero and below the prime order, so always invertible
+    scalar.modinv(&order()).expect("nonzero scalar")
+}
+
+fn to_scalar(bytes: &[u8]) -> Result<BigUint> {
+    let scalar = BigUint::from_bytes_be(bytes);
+    if bytes.len() != 32 || scalar.bits() == 0 || scalar >= order() {
+        return Err(Error::KeyDerivation("Invalid secp256k1 scalar".to_string()));
+    }
+    Ok(scalar)
+}
+
+fn message_scalar(hash: &[u8]) -> Result<BigUint> {
+    if hash.len() != 32 {
+        return Err(Error::Signing("Message must be a 32-byte hash".to_string()));
+    }
+    Ok(BigUint::from_bytes_be(hash) % order())
+}
+
+fn to_bytes(scalar: &BigUint) -> Vec<u8> {
+    let bytes = scalar.to_bytes_be();
+    let mut padded = vec![0u8; 32 - bytes.len()];
+    padded.extend(bytes);
+    padded
+}
+
+fn parse_point(bytes: &[u8]) -> Result<PublicKey> {
+    PublicKey::from_slice(bytes).map_err(|e| Error::KeyDerivation(format!("Invalid secp256k1 point: {}", e)))
+}
+
+fn mul_point(point: &PublicKey, scalar: &BigUint) -> Result<PublicKey> {
+    let tweak = Scalar::from_be_bytes(to_bytes(scalar).try_into().expect("32 bytes"))
+        .map_err(|e| Error::KeyDerivation(format!("Invalid secp256k1 scalar: {}", e)))?;
+    point.mul_tweak(&Secp256k1::new(), &tweak)
+        .map_err(|e| Error::KeyDerivation(format!("Invalid secp256k1 point: {}", e)))
+}
+
+fn x_coordinate(point: &PublicKey) -> BigUint {
+    BigUint::from_bytes_be(&point.serialize()[1..])
+}
@@ -0,0 +1,139 @@
+//! Two-party Ed25519
+//!
+//! The expanded secret scalar `a` is shared additively, `a = a_device +
+//! a_server`, and so is each signature's nonce. Ed25519 signatures are
+//! linear in both, so each party signs with its own shares and the server
+//! adds the halves. The server commits to its nonce before the device picks
+//! one, and keeps a single pending nonce per share, so sessions cannot be
+//! run concurrently against it.
+
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::{clamp_integer, Scalar};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha512};
+
+use crate::error::{Error, Result};
+
+/// Split an Ed25519 seed into a random share of its secret scalar and the
+/// complement
+pub(super) fn split(seed: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    if seed.len() != 32 {
+        return Err(Error::KeyDerivation("Invalid Ed25519 private key length".to_string()));
+    }
+    let hash = Sha512::digest(seed);
+    let secret = Scalar::from_bytes_mod_order(clamp_integer(hash[..32].try_into().expect("32 bytes")));
+    let share = random_scalar();
+    Ok((share.to_bytes().to_vec(), (secret - share).to_bytes().to_vec()))
+}
+
+/// The public share of a share, compressed
+pub(super) fn public_share(share: &[u8]) -> Result<Vec<u8>> {
+    Ok(EdwardsPoint::mul_base(&to_scalar(share)?).compress().to_bytes().to_vec())
+}
+
+/// The public key a share completes with the share behind `peer_public_share`
+pub(super) fn public_key(share: &[u8], peer_public_share: &[u8]) -> Result<Vec<u8>> {
+    let point = EdwardsPoint::mul_base(&to_scalar(share)?) + parse_point(peer_public_share)?;
+    Ok(point.compress().to_bytes().to_vec())
+}
+
+/// A random offset to refresh a pair of shares with
+pub(super) fn refresh_factor() -> Vec<u8> {
+    random_scalar().to_bytes().to_vec()
+}
+
+/// Add a refresh offset to a share
+pub(super) fn refresh_up(share: &[u8], offset: &[u8]) -> Result<Vec<u8>> {
+    Ok((to_scalar(share)? + to_scalar(offset)?).to_bytes().to_vec())
+}
+
+/// Subtract a refresh offset from a share
+pub(super) fn refresh_down(share: &[u8], offset: &[u8]) -> Result<Vec<u8>> {
+    Ok((to_scalar(share)? - to_scalar(offset)?).to_bytes().to_vec())
+}
+
+/// The public share of a share the offset was subtracted from
+pub(super) fn refresh_public_down(public_share: &[u8], offset: &[u8]) -> Result<Vec<u8>> {
+    let point = parse_point(public_share)? - EdwardsPoint::mul_base(&to_scalar(offset)?);
+    Ok(point.compress().to_bytes().to_vec())
+}
+
+/// The public share of a share the offset was added to
+pub(super) fn refresh_public_up(public_share: &[u8], offset: &[u8]) -> Result<Vec<u8>> {
+    let point = parse_point(public_share)? + EdwardsPoint::mul_base(&to_scalar(offset)?);
+    Ok(point.compress().to_bytes().to_vec())
+}
+
+/// The server's nonce share and its public nonce
+pub(super) fn server_nonce() -> Result<(Vec<u8>, Vec<u8>)> {
+    let nonce = random_scalar().to_bytes().to_vec();
+    let public_nonce = public_share(&nonce)?;
+    Ok((nonce, public_nonce))
+}
+
+/// The device's half of a signature: its public nonce and its share of `S`
+pub(super) fn partial_sign(share: &[u8], server_nonce: &[u8], public_key: &[u8], message: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let share = to_scalar(share)?;
+    let server_nonce = parse_point(server_nonce)?;
+
+    let nonce = random_scalar();
+    let device_nonce = EdwardsPoint::mul_base(&nonce);
+    let challenge = challenge(&(server_nonce + device_nonce), public_key, message);
+
+    Ok((device_nonce.compress().to_bytes().to_vec(), (nonce + challenge * share).to_bytes().to_vec()))
+}
+
+/// Check the device's half of a signature and complete it, returning the
+/// 64-byte signature
+pub(super) fn complete(share: &[u8], nonce: &[u8], device_nonce: &[u8], device_public_share: &[u8], partial: &[u8], message: &[u8], public_key: &[u8]) -> Result<Vec<u8>> {
+    let nonce = to_scalar(nonce)?;
+    let device_nonce = parse_point(device_nonce)?;
+    let partial = to_scalar(partial)?;
+    let point = EdwardsPoint::mul_base(&nonce) + device_nonce;
+    let challenge = challenge(&point, public_key, message);
+
+    if EdwardsPoint::mul_base(&partial) != device_nonce + challenge * parse_point(device_public_share)? {
+        return Err(Error::Signing("Partial signature does not verify against the device share".to_string()));
+    }
+
+    let s = partial + nonce + challenge * to_scalar(share)?;
+    let signature = [point.compress().to_bytes(), s.to_bytes()].concat();
+
+    let verifying_key = public_key.try_into().ok()
+        .and_then(|bytes| VerifyingKey::from_bytes(bytes).ok())
+        .ok_or_else(|| Error::KeyDerivation("Invalid Ed25519 public key".to_string()))?;
+    let parsed = Signature::from_slice(&signature)
+        .map_err(|e| Error::Signing(format!("Invalid signature: {}", e)))?;
+    verifying_key.verify(message, &parsed)
+        .map_err(|_| Error::Signing("Partial signature does not complete a valid signature".to_string()))?;
+
+    Ok(signature)
+}
+
+fn challenge(nonce: &EdwardsPoint, public_key: &[u8], message: &[u8]) -> Scalar {
+    let hash = Sha512::new()
+        .chain_update(nonce.compress().as_bytes())
+        .chain_update(public_key)
+        .chain_update(message)
+        .finalize();
+    Scalar::from_bytes_mod_order_wide(&hash.into())
+}
+
+fn random_scalar() -> Scalar {
+    let mut bytes = [0u8; 64];
+    OsRng.fill_bytes(&mut bytes);
+    Scalar::from_bytes_mod_order_wide(&bytes)
+}
+
+fn to_scalar(bytes: &[u8]) -> Result<Scalar> {
+    bytes.try_into().ok()
+        .and_then(|bytes| Option::from(Scalar::from_canonical_bytes(bytes)))
+        .ok_or_else(|| Error::KeyDerivation("Invalid Ed25519 scalar".to_string()))
+}
+
+fn parse_point(bytes: &[u8]) -> Result<EdwardsPoint> {
+    CompressedEdwardsY::from_slice(bytes).ok()
+        .and_then(|point| point.decompress())
+        .ok_or_else(|| Error::KeyDerivation("Invalid Ed25519 point".to_string()))
+}
@@ -0,0 +1,378 @@
+//! Escrow of server key shares
+//!
+//! Shares are encrypted with AES-256-GCM before they reach the
+//! [`ShareStore`], bound to their share ID so a ciphertext cannot be moved
+//! to another record. Access and recovery tokens are kept only as SHA-256
+//! hashes. Pending signing nonces are held in memory: a restart only means
+//! the device starts its signature again.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+
+use aes_gcm::{Aes256Gcm, KeyInit};
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::aead::generic_array::GenericArray;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use num_bigint::BigUint;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::keys::KeyType;
+use crate::error::{Error, Result};
+use super::paillier::PaillierKey;
+use super::{ecdsa, eddsa, decode_hex, Curve, KeyShare, PartialSignature, ServerShares, ShareHolder, SigningNonce};
+
+/// Bits of the Paillier modulus generated for each secp256k1 key
+pub const PAILLIER_MODULUS_BITS: u64 = 2048;
+
+/// What the device keeps after escrowing its server shares
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowReceipt {
+    /// Share ID
+    pub share_id: String,
+    /// Token required to sign with the shares
+    pub access_token: String,
+    /// Token required to recover a device share, kept with the recovery share
+    pub recovery_token: String,
+}
+
+/// The server's half of a recovery
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recovery {
+    /// New access token; the previous one no longer works
+    pub access_token: String,
+    /// Hex-encoded refresh value to pass to
+    /// [`recover_device_share`](super::recover_device_share)
+    pub refresh: String,
+}
+
+/// Server shares encrypted with AES-256-GCM
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedShares {
+    /// Hex-encoded nonce
+    pub nonce: String,
+    /// Base64-encoded ciphertext
+    pub ciphertext: String,
+}
+
+/// Escrowed shares as stored
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredShares {
+    /// Share ID
+    pub id: String,
+    /// The type of key that was split
+    pub key_type: KeyType,
+    /// Hex-encoded public key of the full key
+    pub public_key: String,
+    /// Hex-encoded SHA-256 hash of the access token
+    pub access_token_hash: String,
+    /// Hex-encoded SHA-256 hash of the recovery token
+    pub recovery_token_hash: String,
+    /// The shares and the Paillier key that goes with them
+    pub secrets: EncryptedShares,
+}
+
+/// What is encrypted in [`StoredShares::secrets`]
+#[derive(Serialize, Deserialize)]
+struct ShareSecrets {
+    signing: KeyShare,
+    recovery: KeyShare,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    paillier: Option<PaillierKey>,
+}
+
+/// Persistent store of escrowed shares
+pub trait ShareStore: Send + Sync {
+    /// Get shares by ID
+    fn get(&self, id: &str) -> Result<Option<StoredShares>>;
+
+    /// Save shares, replacing any with the same ID
+    fn put(&self, stored: &StoredShares) -> Result<()>;
+}
+
+/// In-memory share store
+#[derive(Debug, Default)]
+pub struct InMemoryShareStore {
+    shares: RwLock<HashMap<String, StoredShares>>,
+}
+
+impl InMemoryShareStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ShareStore for InMemoryShareStore {
+    fn get(&self, id: &str) -> Result<Option<StoredShares>> {
+        Ok(self.shares.read().unwrap().get(id).cloned())
+    }
+
+    fn put(&self, stored: &StoredShares) -> Result<()> {
+        self.shares.write().unwrap().insert(stored.id.clone(), stored.clone());
+        Ok(())
+    }
+}
+
+/// Share store kept in a JSON file
+#[derive(Debug)]
+pub struct FileShareStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileShareStore {
+    /// Open a share file, creating it on the first save
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), lock: Mutex::new(()) }
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, StoredShares>> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(Error::Provider(format!("Failed to read key shares {}: {}", self.path.display(), e))),
+        };
+        serde_json::from_str(&contents)
+            .map_err(|e| Error::Serialization(format!("Invalid key shares {}: {}", self.path.display(), e)))
+    }
+
+    fn save(&self, shares: &BTreeMap<String, StoredShares>) -> Result<()> {
+        let json = serde_json::to_string_pretty(shares)
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        let temp = self.path.with_extension("tmp");
+        std::fs::write(&temp, json)
+            .and_then(|_| std::fs::rename(&temp, &self.path))
+            .map_err(|e| Error::Provider(format!("Failed to write key shares {}: {}", self.path.display(), e)))
+    }
+}
+
+impl ShareStore for FileShareStore {
+    fn get(&self, id: &str) -> Result<Option<StoredShares>> {
+        let _guard = self.lock.lock().unwrap();
+        Ok(self.load()?.remove(id))
+    }
+
+    fn put(&self, stored: &StoredShares) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let mut all = self.load()?;
+        all.insert(stored.id.clone(), stored.clone());
+        self.save(&all)
+    }
+}
+
+/// Server shares held in escrow, used to co-sign with device shares
+pub struct ShareEscrow {
+    store: Box<dyn ShareStore>,
+    /// Share encryption key
+    key: [u8; 32],
+    paillier_bits: u64,
+    /// Secret nonce of the signature each share is in the middle of
+    nonces: Mutex<HashMap<String, Vec<u8>>>,
+    /// Held while shares are read and replaced
+    lock: Mutex<()>,
+}
+
+impl ShareEscrow {
+    /// Create an escrow stored in `store`, encrypting shares with `key`
+    pub fn new(store: Box<dyn ShareStore>, key: [u8; 32]) -> Self {
+        Self { store, key, paillier_bits: PAILLIER_MODULUS_BITS, nonces: Mutex::new(HashMap::new()), lock: Mutex::new(()) }
+    }
+
+    /// Generate Paillier keys of `bits` bits instead of [`PAILLIER_MODULUS_BITS`]
+    pub fn with_paillier_bits(mut self, bits: u64) -> Self {
+        self.paillier_bits = bits;
+        self
+    }
+
+    /// Escrow a key's server shares
+    ///
+    /// Generating the Paillier key of a secp256k1 key takes a while; call
+    /// this off the async runtime.
+    pub fn deposit(&self, shares: ServerShares) -> Result<EscrowReceipt> {
+        let ServerShares { signing, recovery } = shares;
+        for share in [&signing, &recovery] {
+            if share.holder != ShareHolder::Server {
+                return Err(Error::InvalidInput("Only server shares can be escrowed".to_string()));
+            }
+            share.verify()?;
+        }
+        if signing.key_type != recovery.key_type || signing.public_key != recovery.public_key {
+            return Err(Error::InvalidInput("Server shares belong to different keys".to_string()));
+        }
+
+        let paillier = match Curve::of(signing.key_type) {
+            Curve::Secp256k1 => Some(PaillierKey::generate(self.paillier_bits)),
+            Curve::Ed25519 => None,
+        };
+
+        let id = format!("share_{}", hex::encode(random_bytes::<8>()));
+        let access_token = hex::encode(random_bytes::<32>());
+        let recovery_token = hex::encode(random_bytes::<32>());
+        let stored = StoredShares {
+            id: id.clone(),
+            key_type: signing.key_type,
+            public_key: signing.public_key.clone(),
+            access_token_hash: hash_token(&access_token),
+            recovery_token_hash: hash_token(&recovery_token),
+            secrets: self.encrypt(&id, &ShareSecrets { signing, recovery, paillier })?,
+        };
+        self.store.put(&stored)?;
+
+        Ok(EscrowReceipt { share_id: id, access_token, recovery_token })
+    }
+
+    /// Start a signature, returning the server's nonce, or `None` if there
+    /// are no shares with this ID
+    ///
+    /// Starting again discards the previous nonce.
+    pub fn start_signing(&self, id: &str, access_token: &str) -> Result<Option<SigningNonce>> {
+        let Some(stored) = self.store.get(id)? else { return Ok(None) };
+        check_token(&stored.access_token_hash, access_token)?;
+        let secrets = self.decrypt(&stored)?;
+
+        let curve = Curve::of(stored.key_type);
+        let share = decode_hex(&secrets.signing.share)?;
+        let (nonce, public_nonce) = curve.server_nonce()?;
+        let (paillier_modulus, encrypted_share) = match (curve, &secrets.paillier) {
+            (Curve::Secp256k1, Some(paillier)) => {
+                let (modulus, encrypted) = ecdsa::encrypt_share(paillier, &share)?;
+                (Some(modulus.to_str_radix(16)), Some(encrypted.to_str_radix(16)))
+            }
+            (Curve::Secp256k1, None) => return Err(Error::KeyDerivation(format!("Key share {} has no Paillier key", id))),
+            (Curve::Ed25519, _) => (None, None),
+        };
+
+        self.nonces.lock().unwrap().insert(id.to_string(), nonce);
+        Ok(Some(SigningNonce { nonce: hex::encode(public_nonce), paillier_modulus, encrypted_share }))
+    }
+
+    /// Complete a signature from the device's partial signature, or `None`
+    /// if there are no shares with this ID
+    ///
+    /// For secp256k1 keys `message` must be a 32-byte hash and the result is
+    /// a 65-byte recoverable signature (`r || s || recovery_id`). For Ed25519
+    /// keys the result is a 64-byte signature over `message`. Each nonce signs
+    /// once, whether or not the signature completes.
+    pub fn co_sign(&self, id: &str, access_token: &str, message: &[u8], partial: &PartialSignature) -> Result<Option<Vec<u8>>> {
+        let Some(stored) = self.store.get(id)? else { return Ok(None) };
+        check_token(&stored.access_token_hash, access_token)?;
+        let nonce = self.nonces.lock().unwrap().remove(id)
+            .ok_or_else(|| Error::Signing("No signature in progress; request a signing nonce first".to_string()))?;
+        let secrets = self.decrypt(&stored)?;
+
+        let public_key = decode_hex(&stored.public_key)?;
+        let device_nonce = decode_hex(&partial.nonce)?;
+        let signature = match Curve::of(stored.key_type) {
+            Curve::Secp256k1 => {
+                let paillier = secrets.paillier.as_ref()
+                    .ok_or_else(|| Error::KeyDerivation(format!("Key share {} has no Paillier key", id)))?;
+                let encrypted = BigUint::parse_bytes(partial.share.as_bytes(), 16)
+                    .ok_or_else(|| Error::InvalidInput("Invalid partial signature".to_string()))?;
+                ecdsa::complete(paillier, &nonce, &device_nonce, &encrypted, message, &public_key)?
+            }
+            Curve::Ed25519 => {
+                let signing = &secrets.signing;
+                eddsa::complete(
+                    &decode_hex(&signing.share)?,
+                    &nonce,
+                    &device_nonce,
+                    &decode_hex(&signing.peer_public_share)?,
+                    &decode_hex(&partial.share)?,
+                    message,
+                    &public_key,
+                )?
+            }
+        };
+
+        Ok(Some(signature))
+    }
+
+    /// Replace the signing share with one paired to a new device share, or
+    /// return `None` if there are no shares with this ID
+    ///
+    /// The device derives its new share from the recovery share and the
+    /// returned refresh value. The old device share and access token stop
+    /// working; the recovery share and token keep working.
+    pub fn recover(&self, id: &str, recovery_token: &str) -> Result<Option<Recovery>> {
+        let _guard = self.lock.lock().unwrap();
+        let Some(stored) = self.store.get(id)? else { return Ok(None) };
+        check_token(&stored.recovery_token_hash, recovery_token)?;
+        let mut secrets = self.decrypt(&stored)?;
+
+        let curve = Curve::of(stored.key_type);
+        let refresh = curve.refresh_factor();
+        let recovery = &secrets.recovery;
+        let share = curve.refresh_down(&decode_hex(&recovery.share)?, &refresh)?;
+        let signing = KeyShare {
+            key_type: recovery.key_type,
+            holder: ShareHolder::Server,
+            public_share: hex::encode(curve.public_share(&share)?),
+            peer_public_share: hex::encode(curve.refresh_public_up(&decode_hex(&recovery.peer_public_share)?, &refresh)?),
+            public_key: recovery.public_key.clone(),
+            share: hex::encode(share),
+        };
+        signing.verify()?;
+        secrets.signing = signing;
+
+        let access_token = hex::encode(random_bytes::<32>());
+        let updated = StoredShares {
+            access_token_hash: hash_token(&access_token),
+            secrets: self.encrypt(id, &secrets)?,
+            ..stored
+        };
+        self.store.put(&updated)?;
+        self.nonces.lock().unwrap().remove(id);
+
+        Ok(Some(Recovery { access_token, refresh: hex::encode(refresh) }))
+    }
+
+    /// Encrypt the secrets of shares
+    fn encrypt(&self, id: &str, secrets: &ShareSecrets) -> Result<EncryptedShares> {
+        let plaintext = serde_json::to_vec(secrets).map_err(|e| Error::Serialization(e.to_string()))?;
+        let nonce = random_bytes::<12>();
+
+        let cipher = Aes256Gcm::new(GenericArray::from_slice(&self.key));
+        let ciphertext = cipher.encrypt(GenericArray::from_slice(&nonce), Payload { msg: &plaintext, aad: id.as_bytes() })
+            .map_err(|_| Error::InvalidInput("Failed to encrypt key shares".to_string()))?;
+
+        Ok(EncryptedShares { nonce: hex::encode(nonce), ciphertext: BASE64.encode(ciphertext) })
+    }
+
+    /// Decrypt the secrets of shares
+    fn decrypt(&self, stored: &StoredShares) -> Result<ShareSecrets> {
+        let id = &stored.id;
+        let nonce = hex::decode(&stored.secrets.nonce).ok().filter(|nonce| nonce.len() == 12)
+            .ok_or_else(|| Error::InvalidInput(format!("Invalid key share nonce for {}", id)))?;
+        let ciphertext = BASE64.decode(&stored.secrets.ciphertext)
+            .map_err(|e| Error::InvalidInput(format!("Invalid key share ciphertext for {}: {}", id, e)))?;
+
+        let cipher = Aes256Gcm::new(GenericArray::from_slice(&self.key));
+        let plaintext = cipher.decrypt(GenericArray::from_slice(&nonce), Payload { msg: &ciphertext, aad: id.as_bytes() })
+            .map_err(|_| Error::InvalidInput(format!("Failed to decrypt key shares {}", id)))?;
+        serde_json::from_slice(&plaintext).map_err(|e| Error::Serialization(e.to_string()))
+    }
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn check_token(token_hash: &str, token: &str) -> Result<()> {
+    // Compare in constant time so response timing says nothing about the hash
+    let hash = hash_token(token);
+    let difference = hash.bytes().zip(token_hash.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    if difference == 0 && hash.len() == token_hash.len() {
+        Ok(())
+    } else {
+        Err(Error::Unauthenticated("Invalid key share token".to_string()))
+    }
+}
@@ -0,0 +1,451 @@
+//! 2-of-2 key sharding with two-party signing and server-assisted recovery
+//!
+//! A key is split twice: into a device share and a server signing share,
+//! and into a recovery share and a server recovery share. Clients split
+//! keys locally and escrow only the two server shares in a [`ShareEscrow`];
+//! the recovery share is kept offline by the user.
+//!
+//! Signing never reassembles the key. Each party signs with its own share
+//! and the server completes the signature: two-party ECDSA for secp256k1
+//! keys (EVM, Bitcoin, Cosmos, XRP) and two-party Ed25519 for Solana and
+//! TON keys. A lost device is recovered from the recovery share: the server
+//! re-randomizes its signing share so that it pairs with a new device share
+//! derived from the recovery share, which retires the old device share
+//! without either side learning the key.
+
+mod ecdsa;
+mod eddsa;
+mod escrow;
+mod paillier;
+
+use serde::{Serialize, Deserialize};
+
+use crate::error::{Error, Result};
+use super::keys::{KeyType, PrivateKey};
+
+pub use ecdsa::MIN_PAILLIER_MODULUS_BITS;
+pub use escrow::{EncryptedShares, EscrowReceipt, FileShareStore, InMemoryShareStore, Recovery, ShareEscrow, ShareStore, StoredShares, PAILLIER_MODULUS_BITS};
+
+/// The party holding a key share
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShareHolder {
+    /// Share kept on the user's device
+    Device,
+    /// Share escrowed with the server
+    Server,
+    /// Share kept offline by the user, to recover a lost device
+    Recovery,
+}
+
+/// One share of a 2-of-2 split key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyShare {
+    /// The type of key that was split
+    pub key_type: KeyType,
+    /// Who holds this share
+    pub holder: ShareHolder,
+    /// Hex-encoded share secret
+    pub share: String,
+    /// Hex-encoded public share
+    pub public_share: String,
+    /// Hex-encoded public share of the share this one pairs with
+    pub peer_public_share: String,
+    /// Hex-encoded public key of the full key (compressed for secp256k1)
+    pub public_key: String,
+}
+
+impl KeyShare {
+    /// Check that the share matches its public share and completes the
+    /// public key with its peer
+    pub fn verify(&self) -> Result<()> {
+        let curve = Curve::of(self.key_type);
+        let share = decode_hex(&self.share)?;
+        if hex::encode(curve.public_share(&share)?) != self.public_share {
+            return Err(Error::KeyDerivation("Key share does not match its public share".to_string()));
+        }
+        if hex::encode(curve.public_key(&share, &decode_hex(&self.peer_public_share)?)?) != self.public_key {
+            return Err(Error::KeyDerivation("Key share does not complete the wallet public key".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// The server's shares of a key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerShares {
+    /// Share that signs with the device share
+    pub signing: KeyShare,
+    /// Share that pairs with the recovery share
+    pub recovery: KeyShare,
+}
+
+/// A key split for escrow
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitKey {
+    /// Share kept on the device
+    pub device: KeyShare,
+    /// Share kept offline by the user
+    pub recovery: KeyShare,
+    /// Shares to escrow with the server
+    pub server: ServerShares,
+}
+
+/// The server's opening of a two-party signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningNonce {
+    /// Hex-encoded public nonce of the server
+    pub nonce: String,
+    /// Hex-encoded Paillier modulus (secp256k1 only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paillier_modulus: Option<String>,
+    /// Hex-encoded Paillier encryption of the server's share (secp256k1 only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted_share: Option<String>,
+}
+
+/// The device's half of a two-party signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialSignature {
+    /// Hex-encoded public nonce of the device
+    pub nonce: String,
+    /// Hex-encoded signature share: the Paillier ciphertext for secp256k1
+    /// keys, the device's share of `S` for Ed25519 keys
+    pub share: String,
+}
+
+/// Split a private key into device, recovery and server shares
+pub fn split_key(private_key: &PrivateKey) -> Result<SplitKey> {
+    let key_type = private_key.key_type();
+    let curve = Curve::of(key_type);
+
+    let (device, server_signing) = curve.split(private_key.as_bytes())?;
+    let (recovery, server_recovery) = curve.split(private_key.as_bytes())?;
+    let public_key = hex::encode(curve.public_key(&device, &curve.public_share(&server_signing)?)?);
+
+    let pair = |holder, own: &[u8], server_holder, peer: &[u8]| -> Result<(KeyShare, KeyShare)> {
+        let share = |holder, share: &[u8], peer: &[u8]| -> Result<KeyShare> {
+            Ok(KeyShare {
+                key_type,
+                holder,
+                share: hex::encode(share),
+                public_share: hex::encode(curve.public_share(share)?),
+                peer_public_share: hex::encode(curve.public_share(peer)?),
+                public_key: public_key.clone(),
+            })
+        };
+        Ok((share(holder, own, peer)?, share(server_holder, peer, own)?))
+    };
+    let (device, signing) = pair(ShareHolder::Device, &device, ShareHolder::Server, &server_signing)?;
+    let (recovery, server_recovery) = pair(ShareHolder::Recovery, &recovery, ShareHolder::Server, &server_recovery)?;
+
+    Ok(SplitKey { device, recovery, server: ServerShares { signing, recovery: server_recovery } })
+}
+
+/// Sign the device's half of a signature over `message`, opened by the
+/// server with `nonce`
+///
+/// For secp256k1 keys `message` must be a 32-byte hash, and the server's
+/// Paillier modulus must be at least [`MIN_PAILLIER_MODULUS_BITS`] long.
+pub fn partial_sign(device: &KeyShare, nonce: &SigningNonce, message: &[u8]) -> Result<PartialSignature> {
+    if device.holder != ShareHolder::Device {
+        return Err(Error::InvalidInput("Only device shares can sign".to_string()));
+    }
+    device.verify()?;
+    let share = decode_hex(&device.share)?;
+    let server_nonce = decode_hex(&nonce.nonce)?;
+
+    match Curve::of(device.key_type) {
+        Curve::Secp256k1 => {
+            let parse = |value: &Option<String>, name: &str| value.as_ref()
+                .and_then(|value| num_bigint::BigUint::parse_bytes(value.as_bytes(), 16))
+                .ok_or_else(|| Error::InvalidInput(format!("Signing nonce has no valid {}", name)));
+            let modulus = parse(&nonce.paillier_modulus, "Paillier modulus")?;
+            let encrypted_share = parse(&nonce.encrypted_share, "encrypted share")?;
+            let (device_nonce, partial) = ecdsa::partial_sign(&share, &server_nonce, &modulus, &encrypted_share, message)?;
+            Ok(PartialSignature { nonce: hex::encode(device_nonce), share: partial.to_str_radix(16) })
+        }
+        Curve::Ed25519 => {
+            let (device_nonce, partial) = eddsa::partial_sign(&share, &server_nonce, &decode_hex(&device.public_key)?, message)?;
+            Ok(PartialSignature { nonce: hex::encode(device_nonce), share: hex::encode(partial) })
+        }
+    }
+}
+
+/// Derive a new device share from the recovery share and the refresh value
+/// of a [`Recovery`]
+pub fn recover_device_share(recovery: &KeyShare, refresh: &str) -> Result<KeyShare> {
+    if recovery.holder != ShareHolder::Recovery {
+        return Err(Error::InvalidInput("Expected a recovery share".to_string()));
+    }
+    recovery.verify()?;
+
+    let curve = Curve::of(recovery.key_type);
+    let refresh = decode_hex(refresh)?;
+    let share = curve.refresh_up(&decode_hex(&recovery.share)?, &refresh)?;
+    let device = KeyShare {
+        key_type: recovery.key_type,
+        holder: ShareHolder::Device,
+        public_share: hex::encode(curve.public_share(&share)?),
+        peer_public_share: hex::encode(curve.refresh_public_down(&decode_hex(&recovery.peer_public_share)?, &refresh)?),
+        public_key: recovery.public_key.clone(),
+        share: hex::encode(share),
+    };
+    device.verify()?;
+    Ok(device)
+}
+
+/// The curve a key type signs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Curve {
+    Secp256k1,
+    Ed25519,
+}
+
+impl Curve {
+    fn of(key_type: KeyType) -> Self {
+        match key_type {
+            KeyType::Ethereum | KeyType::Bitcoin | KeyType::Cosmos | KeyType::Xrp => Self::Secp256k1,
+            KeyType::Solana | KeyType::Ton => Self::Ed25519,
+        }
+    }
+
+    fn split(self, secret: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+        match self {
+            Self::Secp256k1 => ecdsa::split(secret),
+            Self::Ed25519 => eddsa::split(secret),
+        }
+    }
+
+    fn public_share(self, share: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::Secp256k1 => ecdsa::public_share(share),
+            Self::Ed25519 => eddsa::public_share(share),
+        }
+    }
+
+    fn public_key(self, share: &[u8], peer_public_share: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::Secp256k1 => ecdsa::public_key(share, peer_public_share),
+            Self::Ed25519 => eddsa::public_key(share, peer_public_share),
+        }
+    }
+
+    fn server_nonce(self) -> Result<(Vec<u8>, Vec<u8>)> {
+        match self {
+            Self::Secp256k1 => ecdsa::server_nonce(),
+            Self::Ed25519 => eddsa::server_nonce(),
+        }
+    }
+
+    fn refresh_factor(self) -> Vec<u8> {
+        match self {
+            Self::Secp256k1 => ecdsa::refresh_factor(),
+            Self::Ed25519 => eddsa::refresh_factor(),
+        }
+    }
+
+    fn refresh_up(self, share: &[u8], refresh: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::Secp256k1 => ecdsa::refresh_up(share, refresh),
+            Self::Ed25519 => eddsa::refresh_up(share, refresh),
+        }
+    }
+
+    fn refresh_down(self, share: &[u8], refresh: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::Secp256k1 => ecdsa::refresh_down(share, refresh),
+            Self::Ed25519 => eddsa::refresh_down(share, refresh),
+        }
+    }
+
+    fn refresh_public_up(self, public_share: &[u8], refresh: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::Secp256k1 => ecdsa::refresh_public_up(public_share, refresh),
+            Self::Ed25519 => eddsa::refresh_public_up(public_share, refresh),
+        }
+    }
+
+    fn refresh_public_down(self, public_share: &[u8], refresh: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::Secp256k1 => ecdsa::refresh_public_down(public_share, refresh),
+            Self::Ed25519 => eddsa::refresh_public_down(public_share, refresh),
+        }
+    }
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>> {
+    hex::decode(value).map_err(|e| Error::KeyDerivation(format!("Invalid key share encoding: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+    use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+    use secp256k1::{Message, Secp256k1};
+    use crate::crypto::keys::{derive_key_pair, KeyPair};
+    use crate::crypto::mnemonic::mnemonic_to_seed;
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn key_pair(key_type: KeyType, path: &str) -> KeyPair {
+        let seed = mnemonic_to_seed(MNEMONIC, None).unwrap();
+        derive_key_pair(&seed, key_type, path).unwrap()
+    }
+
+    fn escrow() -> ShareEscrow {
+        ShareEscrow::new(Box::new(InMemoryShareStore::new()), rand::random()).with_paillier_bits(MIN_PAILLIER_MODULUS_BITS)
+    }
+
+    fn sign(escrow: &ShareEscrow, id: &str, token: &str, device: &KeyShare, message: &[u8]) -> Result<Vec<u8>> {
+        let nonce = escrow.start_signing(id, token)?.unwrap();
+        let partial = partial_sign(device, &nonce, message)?;
+        Ok(escrow.co_sign(id, token, message, &partial)?.unwrap())
+    }
+
+    fn assert_ecdsa(signature: &[u8], hash: &[u8; 32], key_pair: &KeyPair) {
+        assert_eq!(signature.len(), 65);
+        let recovery_id = RecoveryId::from_i32(signature[64] as i32).unwrap();
+        let signature = RecoverableSignature::from_compact(&signature[..64], recovery_id).unwrap();
+        let recovered = Secp256k1::new().recover_ecdsa(&Message::from_digest_slice(hash).unwrap(), &signature).unwrap();
+        assert_eq!(recovered.serialize_uncompressed().to_vec(), key_pair.public_key().as_bytes());
+    }
+
+    #[test]
+    fn test_split_and_verify() {
+        for (key_type, path) in [(KeyType::Ethereum, "m/44'/60'/0'/0/0"), (KeyType::Solana, "m/44'/501'/0'/0'")] {
+            let key_pair = key_pair(key_type, path);
+            let split = split_key(key_pair.private_key()).unwrap();
+            // Key shares carry compressed secp256k1 public keys
+            let public_key = match key_type {
+                KeyType::Ethereum => hex::encode(secp256k1::PublicKey::from_slice(key_pair.public_key().as_bytes()).unwrap().serialize()),
+                _ => hex::encode(key_pair.public_key().as_bytes()),
+            };
+
+            for share in [&split.device, &split.recovery, &split.server.signing, &split.server.recovery] {
+                share.verify().unwrap();
+                assert_eq!(share.public_key, public_key);
+                assert_ne!(share.share, hex::encode(key_pair.private_key().as_bytes()));
+            }
+            assert_eq!(split.device.peer_public_share, split.server.signing.public_share);
+            assert_ne!(split.device.share, split.recovery.share);
+
+            // A share paired with the wrong peer does not complete the key
+            let mixed = KeyShare { peer_public_share: split.server.recovery.public_share.clone(), ..split.device.clone() };
+            assert!(mixed.verify().is_err());
+        }
+    }
+
+    #[test]
+    fn test_two_party_ecdsa() {
+        let key_pair = key_pair(KeyType::Ethereum, "m/44'/60'/0'/0/0");
+        let split = split_key(key_pair.private_key()).unwrap();
+
+        let escrow = escrow();
+        let receipt = escrow.deposit(split.server.clone()).unwrap();
+
+        let hash = [42u8; 32];
+        let nonce = escrow.start_signing(&receipt.share_id, &receipt.access_token).unwrap().unwrap();
+        let partial = partial_sign(&split.device, &nonce, &hash).unwrap();
+        let signature = escrow.co_sign(&receipt.share_id, &receipt.access_token, &hash, &partial).unwrap().unwrap();
+        assert_ecdsa(&signature, &hash, &key_pair);
+
+        // Each nonce signs once
+        assert!(escrow.co_sign(&receipt.share_id, &receipt.access_token, &hash, &partial).is_err());
+
+        // A partial signature for another message does not complete
+        let nonce = escrow.start_signing(&receipt.share_id, &receipt.access_token).unwrap().unwrap();
+        let partial = partial_sign(&split.device, &nonce, &[7u8; 32]).unwrap();
+        assert!(escrow.co_sign(&receipt.share_id, &receipt.access_token, &hash, &partial).is_err());
+    }
+
+    #[test]
+    fn test_two_party_eddsa() {
+        for (key_type, path) in [(KeyType::Solana, "m/44'/501'/0'/0'"), (KeyType::Ton, "m/44'/607'/0'")] {
+            let key_pair = key_pair(key_type, path);
+            let split = split_key(key_pair.private_key()).unwrap();
+
+            let escrow = escrow();
+            let receipt = escrow.deposit(split.server).unwrap();
+            let signature = sign(&escrow, &receipt.share_id, &receipt.access_token, &split.device, b"transfer").unwrap();
+
+            let public_key: [u8; 32] = key_pair.public_key().as_bytes().try_into().unwrap();
+            let verifying_key = VerifyingKey::from_bytes(&public_key).unwrap();
+            let signature = Signature::from_slice(&signature).unwrap();
+            assert!(verifying_key.verify(b"transfer", &signature).is_ok());
+            assert!(verifying_key.verify(b"other", &signature).is_err());
+        }
+    }
+
+    #[test]
+    fn test_recovery() {
+        for (key_type, path) in [(KeyType::Ethereum, "m/44'/60'/0'/0/0"), (KeyType::Solana, "m/44'/501'/0'/0'")] {
+            let key_pair = key_pair(key_type, path);
+            let split = split_key(key_pair.private_key()).unwrap();
+            let escrow = escrow();
+            let receipt = escrow.deposit(split.server).unwrap();
+
+            assert!(escrow.recover(&receipt.share_id, &receipt.access_token).is_err());
+            let recovery = escrow.recover(&receipt.share_id, &receipt.recovery_token).unwrap().unwrap();
+            let device = recover_device_share(&split.recovery, &recovery.refresh).unwrap();
+            assert_ne!(device.share, split.device.share);
+            assert!(recover_device_share(&split.device, &recovery.refresh).is_err());
+
+            // The old access token and device share are retired
+            assert!(escrow.start_signing(&receipt.share_id, &receipt.access_token).is_err());
+            assert!(sign(&escrow, &receipt.share_id, &recovery.access_token, &split.device, &[42u8; 32]).is_err());
+
+            let signature = sign(&escrow, &receipt.share_id, &recovery.access_token, &device, &[42u8; 32]).unwrap();
+            match key_type {
+                KeyType::Ethereum => assert_ecdsa(&signature, &[42u8; 32], &key_pair),
+                _ => {
+                    let public_key: [u8; 32] = key_pair.public_key().as_bytes().try_into().unwrap();
+                    let signature = Signature::from_slice(&signature).unwrap();
+                    assert!(VerifyingKey::from_bytes(&public_key).unwrap().verify(&[42u8; 32], &signature).is_ok());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_escrow_rejects_bad_input() {
+        let key_pair = key_pair(KeyType::Solana, "m/44'/501'/0'/0'");
+        let split = split_key(key_pair.private_key()).unwrap();
+        let other = split_key(key_pair.private_key()).unwrap();
+        let escrow = escrow();
+
+        let device = ServerShares { signing: split.device.clone(), recovery: split.server.recovery.clone() };
+        assert!(escrow.deposit(device).is_err());
+
+        let receipt = escrow.deposit(split.server.clone()).unwrap();
+        assert!(escrow.start_signing(&receipt.share_id, "wrong").is_err());
+        assert!(escrow.start_signing("share_missing", &receipt.access_token).unwrap().is_none());
+
+        // Only the device share the server share was split with can sign
+        assert!(sign(&escrow, &receipt.share_id, &receipt.access_token, &other.device, b"transfer").is_err());
+    }
+
+    #[test]
+    fn test_file_store() {
+        let path = std::env::temp_dir().join(format!("fo3-shares-{}.json", hex::encode(rand::random::<[u8; 8]>())));
+        let key: [u8; 32] = rand::random();
+        let key_pair = key_pair(KeyType::Solana, "m/44'/501'/0'/0'");
+        let split = split_key(key_pair.private_key()).unwrap();
+
+        let server_share = split.server.signing.share.clone();
+
+        let receipt = ShareEscrow::new(Box::new(FileShareStore::new(&path)), key).deposit(split.server).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains(&server_share));
+        assert!(!contents.contains(&receipt.access_token));
+
+        // A fresh handle signs with the shares the first one saved
+        let reopened = ShareEscrow::new(Box::new(FileShareStore::new(&path)), key);
+        assert!(sign(&reopened, &receipt.share_id, &receipt.access_token, &split.device, b"transfer").is_ok());
+
+        let wrong_key = ShareEscrow::new(Box::new(FileShareStore::new(&path)), rand::random());
+        assert!(wrong_key.start_signing(&receipt.share_id, &receipt.access_token).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
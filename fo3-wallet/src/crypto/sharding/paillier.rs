@@ -0,0 +1,194 @@
+//! Paillier encryption, for two-party ECDSA
+//!
+//! Ciphertexts can be added together and multiplied by plaintext scalars
+//! without the private key, which lets the device fold its key share and
+//! nonce into an encryption of the server's share.
+
+use num_bigint::{BigUint, RandBigInt};
+use rand::rngs::OsRng;
+use serde::{Serialize, Deserialize};
+
+use crate::error::{Error, Result};
+
+/// Miller-Rabin rounds per prime candidate
+const PRIME_TEST_ROUNDS: usize = 32;
+
+/// Small primes tried before Miller-Rabin, to reject most candidates cheaply
+const SMALL_PRIMES: [u32; 24] = [3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97];
+
+/// A Paillier private key
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PaillierKey {
+    /// Hex-encoded first prime factor of the modulus
+    p: String,
+    /// Hex-encoded second prime factor of the modulus
+    q: String,
+}
+
+impl std::fmt::Debug for PaillierKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PaillierKey").finish_non_exhaustive()
+    }
+}
+
+impl PaillierKey {
+    /// Generate a key with a modulus of `bits` bits
+    pub fn generate(bits: u64) -> Self {
+        loop {
+            let p = random_prime(bits / 2);
+            let q = random_prime(bits - bits / 2);
+            if p != q {
+                return Self { p: p.to_str_radix(16), q: q.to_str_radix(16) };
+            }
+        }
+    }
+
+    /// The public half of the key
+    pub fn public_key(&self) -> Result<PaillierPublicKey> {
+        let (p, q) = self.factors()?;
+        Ok(PaillierPublicKey::new(p * q))
+    }
+
+    /// Decrypt a ciphertext
+    pub fn decrypt(&self, ciphertext: &BigUint) -> Result<BigUint> {
+        let (p, q) = self.factors()?;
+        let one = BigUint::from(1u32);
+        let n = &p * &q;
+        let n_squared = &n * &n;
+        if *ciphertext >= n_squared || ciphertext.modinv(&n).is_none() {
+            return Err(Error::InvalidInput("Paillier ciphertext is out of range".to_string()));
+        }
+
+        // With the generator n + 1, mu is the inverse of phi(n) modulo n
+        let phi = (&p - &one) * (&q - &one);
+        let mu = phi.modinv(&n).ok_or_else(|| Error::KeyDerivation("Invalid Paillier key".to_string()))?;
+        let l = (ciphertext.modpow(&phi, &n_squared) - &one) / &n;
+        Ok(l * mu % n)
+    }
+
+    fn factors(&self) -> Result<(BigUint, BigUint)> {
+        let parse = |factor: &str| BigUint::parse_bytes(factor.as_bytes(), 16)
+            .ok_or_else(|| Error::KeyDerivation("Invalid Paillier key".to_string()));
+        Ok((parse(&self.p)?, parse(&self.q)?))
+    }
+}
+
+/// A Paillier public key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaillierPublicKey {
+    n: BigUint,
+    n_squared: BigUint,
+}
+
+impl PaillierPublicKey {
+    /// Create a public key from its modulus
+    pub fn new(n: BigUint) -> Self {
+        let n_squared = &n * &n;
+        Self { n, n_squared }
+    }
+
+    /// The modulus
+    pub fn modulus(&self) -> &BigUint {
+        &self.n
+    }
+
+    /// Encrypt a plaintext smaller than the modulus
+    pub fn encrypt(&self, plaintext: &BigUint) -> Result<BigUint> {
+        if *plaintext >= self.n {
+            return Err(Error::InvalidInput("Paillier plaintext is out of range".to_string()));
+        }
+        let one = BigUint::from(1u32);
+        let r = loop {
+            let r = OsRng.gen_biguint_range(&one, &self.n);
+            if r.modinv(&self.n).is_some() {
+                break r;
+            }
+        };
+        Ok((plaintext * &self.n + one) * r.modpow(&self.n, &self.n_squared) % &self.n_squared)
+    }
+
+    /// Encrypt the sum of two ciphertexts' plaintexts
+    pub fn add(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a * b % &self.n_squared
+    }
+
+    /// Encrypt a ciphertext's plaintext times `scalar`
+    pub fn mul(&self, ciphertext: &BigUint, scalar: &BigUint) -> BigUint {
+        ciphertext.modpow(scalar, &self.n_squared)
+    }
+}
+
+/// Generate a random prime of exactly `bits` bits
+fn random_prime(bits: u64) -> BigUint {
+    loop {
+        let mut candidate = OsRng.gen_biguint(bits);
+        // The top two bits make the product of two primes exactly twice as long
+        candidate.set_bit(bits - 1, true);
+        candidate.set_bit(bits - 2, true);
+        candidate.set_bit(0, true);
+        if is_probable_prime(&candidate) {
+            return candidate;
+        }
+    }
+}
+
+fn is_probable_prime(candidate: &BigUint) -> bool {
+    if SMALL_PRIMES.iter().any(|prime| (candidate % *prime).bits() == 0) {
+        return SMALL_PRIMES.iter().any(|prime| *candidate == BigUint::from(*prime));
+    }
+
+    let one = BigUint::from(1u32);
+    let two = BigUint::from(2u32);
+    let minus_one = candidate - &one;
+    let Some(shift) = minus_one.trailing_zeros() else { return false };
+    let odd = &minus_one >> shift;
+
+    'rounds: for _ in 0..PRIME_TEST_ROUNDS {
+        let base = OsRng.gen_biguint_range(&two, &minus_one);
+        let mut x = base.modpow(&odd, candidate);
+        if x == one || x == minus_one {
+            continue;
+        }
+        for _ in 1..shift {
+            x = x.modpow(&two, candidate);
+            if x == minus_one {
+                continue 'rounds;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primality() {
+        assert!(is_probable_prime(&BigUint::from(7919u32)));
+        assert!(!is_probable_prime(&BigUint::from(7917u32)));
+        // Carmichael number
+        assert!(!is_probable_prime(&BigUint::from(561u32)));
+        assert!(is_probable_prime(&BigUint::parse_bytes(b"170141183460469231731687303715884105727", 10).unwrap()));
+    }
+
+    #[test]
+    fn test_homomorphic_operations() {
+        let key = PaillierKey::generate(512);
+        let public_key = key.public_key().unwrap();
+        assert_eq!(public_key.modulus().bits(), 512);
+
+        let a = public_key.encrypt(&BigUint::from(1234u32)).unwrap();
+        let b = public_key.encrypt(&BigUint::from(4321u32)).unwrap();
+        assert_ne!(a, public_key.encrypt(&BigUint::from(1234u32)).unwrap());
+        assert_eq!(key.decrypt(&a).unwrap(), BigUint::from(1234u32));
+
+        let sum = public_key.add(&a, &b);
+        assert_eq!(key.decrypt(&sum).unwrap(), BigUint::from(5555u32));
+        let product = public_key.mul(&sum, &BigUint::from(3u32));
+        assert_eq!(key.decrypt(&product).unwrap(), BigUint::from(16665u32));
+
+        assert!(public_key.encrypt(public_key.modulus()).is_err());
+    }
+}
@@ -0,0 +1,330 @@
+//! Token allowance pre-flight for EVM swaps
+//!
+//! A swap of an ERC-20 token reverts unless the router may pull the input
+//! amount. Before quoting, [`quote_swap_with_approval`] reads the owner's
+//! allowance and plans what has to happen first: an exact `approve` of the
+//! router, or a Permit2 signature (with a one-time `approve` of Permit2 if
+//! the token has never been used with it). With a smart account the
+//! approvals are batched with the swap into one transaction. The plan and
+//! the combined network fee are returned on the quote.
+
+use ethers::prelude::U256;
+use serde::{Serialize, Deserialize};
+
+use crate::crypto::keys::KeyType;
+use crate::error::{Error, Result};
+use crate::transaction::intents::Intent;
+use crate::transaction::provider::ProviderConfig;
+use super::fees::PlatformFeeConfig;
+use super::provider::DeFiProviderFactory;
+use super::types::{SwapQuote, SwapRequest, Token, EVM_NATIVE_TOKEN};
+
+/// Uniswap's Permit2 contract, at the same address on every EVM chain
+pub const PERMIT2_ADDRESS: &str = "0x000000000022D473030F116dDEE9F6B43aC78BA3";
+
+/// Tokens that refuse to change a non-zero allowance to another non-zero value
+const ALLOWANCE_RESET_TOKENS: &[&str] = &[
+    "0xdAC17F958D2ee523a2206206994597C13D831ec7", // USDT
+];
+
+/// Gas the network fee estimate of a swap is based on
+const SWAP_GAS: u128 = 200_000;
+
+/// Gas of a standalone ERC-20 approval
+const APPROVE_GAS: u128 = 46_000;
+
+/// Base gas of a transaction, saved when an approval is batched
+const TRANSACTION_BASE_GAS: u128 = 21_000;
+
+/// Seconds a Permit2 signature stays valid when the swap has no deadline
+const PERMIT2_SIGNATURE_TTL: u64 = 1800;
+
+/// How the router is allowed to pull the input token
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStrategy {
+    /// Approve the router for exactly the swapped amount
+    #[default]
+    Exact,
+    /// Sign a Permit2 transfer for the swapped amount
+    Permit2,
+}
+
+/// Allowance pre-flight options
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalOptions {
+    /// Approval strategy
+    #[serde(default)]
+    pub strategy: ApprovalStrategy,
+    /// Smart account batching the approvals with the swap, if any
+    #[serde(default)]
+    pub smart_account: Option<String>,
+}
+
+/// A step needed before the router can pull the input token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ApprovalStep {
+    /// Send an ERC-20 `approve`
+    Approve {
+        /// Token contract
+        token: String,
+        /// Spender
+        spender: String,
+        /// Allowance in the smallest unit
+        amount: String,
+    },
+    /// Sign a Permit2 `PermitTransferFrom` off-chain
+    Permit2Signature {
+        /// Token contract
+        token: String,
+        /// Spender
+        spender: String,
+        /// Amount in the smallest unit
+        amount: String,
+        /// Unix timestamp after which the signature is void
+        deadline: u64,
+    },
+}
+
+impl ApprovalStep {
+    /// The intent carrying the step on-chain, if it is a transaction
+    pub fn intent(&self) -> Option<Intent> {
+        match self {
+            Self::Approve { token, spender, amount } => Some(Intent::Approve {
+                token: token.clone(),
+                spender: spender.clone(),
+                amount: amount.clone(),
+            }),
+            Self::Permit2Signature { .. } => None,
+        }
+    }
+}
+
+/// What has to happen before a swap, and what it costs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalPlan {
+    /// Input token contract
+    pub token: String,
+    /// Contract whose allowance was checked (the router, or Permit2)
+    pub spender: String,
+    /// Current allowance
+    pub allowance: String,
+    /// Allowance the swap needs
+    pub required: String,
+    /// Steps to take before the swap, in order
+    pub steps: Vec<ApprovalStep>,
+    /// Whether the on-chain steps are batched with the swap in one transaction
+    pub batched: bool,
+    /// Transactions to send, including the swap
+    pub transactions: u32,
+    /// Estimated network fee of the approvals and the swap together, in the native token
+    pub network_fee: String,
+}
+
+impl ApprovalPlan {
+    /// Whether the swap can go ahead without any step
+    pub fn is_ready(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Get the intents of a batch running the approvals and then the swap
+    pub fn batch_intents(&self, swap: Intent) -> Vec<Intent> {
+        self.steps.iter()
+            .filter_map(ApprovalStep::intent)
+            .chain(std::iter::once(swap))
+            .collect()
+    }
+}
+
+/// Get the contract whose allowance a swap needs, `None` for native tokens
+pub fn approval_spender(request: &SwapRequest, options: &ApprovalOptions) -> Result<Option<&'static str>> {
+    if request.from.token.key_type != KeyType::Ethereum || is_native(&request.from.token) {
+        return Ok(None);
+    }
+    match options.strategy {
+        ApprovalStrategy::Exact => request.protocol.router_address()
+            .map(Some)
+            .ok_or_else(|| Error::DeFi(format!("No router known for {:?}", request.protocol))),
+        ApprovalStrategy::Permit2 => Ok(Some(PERMIT2_ADDRESS)),
+    }
+}
+
+/// Plan the approvals a swap needs given the current allowance of the spender
+///
+/// `swap_fee` is the network fee estimate of the swap alone, in the native
+/// token. Returns `None` for swaps of a native token.
+pub fn plan_approval(request: &SwapRequest, allowance: u128, options: &ApprovalOptions, swap_fee: &str, now: u64) -> Result<Option<ApprovalPlan>> {
+    let Some(spender) = approval_spender(request, options)? else {
+        return Ok(None);
+    };
+    let router = request.protocol.router_address()
+        .ok_or_else(|| Error::DeFi(format!("No router known for {:?}", request.protocol)))?;
+    let required = request.from.amount.parse::<u128>()
+        .map_err(|_| Error::InvalidInput(format!("Invalid amount: {}", request.from.amount)))?;
+    let token = request.from.token.address.clone();
+    let approve = |amount: String| ApprovalStep::Approve { token: token.clone(), spender: spender.to_string(), amount };
+
+    let mut steps = Vec::new();
+    if allowance < required {
+        if allowance > 0 && ALLOWANCE_RESET_TOKENS.iter().any(|reset| reset.eq_ignore_ascii_case(&token)) {
+            steps.push(approve("0".to_string()));
+        }
+        steps.push(match options.strategy {
+            ApprovalStrategy::Exact => approve(required.to_string()),
+            // Permit2 is approved once for good; each swap is then authorised by signature
+            ApprovalStrategy::Permit2 => approve(U256::MAX.to_string()),
+        });
+    }
+    if options.strategy == ApprovalStrategy::Permit2 {
+        steps.push(ApprovalStep::Permit2Signature {
+            token: token.clone(),
+            spender: router.to_string(),
+            amount: required.to_string(),
+            deadline: now + request.deadline.unwrap_or(PERMIT2_SIGNATURE_TTL),
+        });
+    }
+
+    let approvals = steps.iter().filter(|step| step.intent().is_some()).count() as u128;
+    let batched = approvals > 0 && options.smart_account.is_some();
+    let approval_gas = if batched { APPROVE_GAS - TRANSACTION_BASE_GAS } else { APPROVE_GAS };
+    let swap_fee = ethers::utils::parse_ether(swap_fee)
+        .map_err(|e| Error::InvalidInput(format!("Invalid network fee {}: {}", swap_fee, e)))?;
+    let network_fee = swap_fee + swap_fee * U256::from(approvals * approval_gas) / U256::from(SWAP_GAS);
+
+    Ok(Some(ApprovalPlan {
+        token,
+        spender: spender.to_string(),
+        allowance: allowance.to_string(),
+        required: required.to_string(),
+        steps,
+        batched,
+        transactions: if batched { 1 } else { 1 + approvals as u32 },
+        network_fee: format_ether(network_fee),
+    }))
+}
+
+/// Quote a swap, checking the owner's allowance and planning the approvals it needs
+pub fn quote_swap_with_approval(
+    request: &SwapRequest,
+    owner: &str,
+    options: &ApprovalOptions,
+    config: &ProviderConfig,
+    platform_fee: Option<&PlatformFeeConfig>,
+) -> Result<SwapQuote> {
+    let key_type = request.from.token.key_type;
+    let provider = DeFiProviderFactory::create_provider_with_platform_fee(key_type, config.clone(), platform_fee.cloned())?;
+
+    let mut quote = provider.quote_swap(request)?;
+    if let Some(spender) = approval_spender(request, options)? {
+        let allowance = provider.get_allowance(&request.from.token, owner, spender)?;
+        quote.approval = plan_approval(request, allowance, options, &provider.estimate_network_fee()?, quote.quoted_at)?;
+    }
+    Ok(quote)
+}
+
+fn is_native(token: &Token) -> bool {
+    token.address.eq_ignore_ascii_case(EVM_NATIVE_TOKEN)
+}
+
+/// Format wei as ether without trailing zeros
+fn format_ether(wei: U256) -> String {
+    let formatted = ethers::utils::format_ether(wei);
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::defi::{Protocol, TokenAmount};
+
+    const OWNER_ACCOUNT: &str = "0x742d35Cc6634C0532925a3b844Bc454e4438f44e";
+
+    fn token(symbol: &str, address: &str) -> Token {
+        Token {
+            name: symbol.to_string(),
+            symbol: symbol.to_string(),
+            decimals: 6,
+            address: address.to_string(),
+            key_type: KeyType::Ethereum,
+            logo_url: None,
+        }
+    }
+
+    fn request(from: Token) -> SwapRequest {
+        SwapRequest {
+            from: TokenAmount { token: from, amount: "1000000".to_string() },
+            to: token("ETH", EVM_NATIVE_TOKEN),
+            slippage: 0.5,
+            protocol: Protocol::Uniswap,
+            deadline: None,
+            dry_run: false,
+        }
+    }
+
+    fn usdc() -> Token {
+        token("USDC", "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48")
+    }
+
+    #[test]
+    fn test_exact_approval() {
+        let options = ApprovalOptions::default();
+
+        let plan = plan_approval(&request(usdc()), 0, &options, "0.001", 0).unwrap().unwrap();
+        assert_eq!(plan.steps, vec![ApprovalStep::Approve {
+            token: usdc().address,
+            spender: Protocol::Uniswap.router_address().unwrap().to_string(),
+            amount: "1000000".to_string(),
+        }]);
+        assert_eq!(plan.transactions, 2);
+        assert_eq!(plan.network_fee, "0.00123");
+
+        // Enough allowance needs nothing
+        let plan = plan_approval(&request(usdc()), 5_000_000, &options, "0.001", 0).unwrap().unwrap();
+        assert!(plan.is_ready());
+        assert_eq!(plan.network_fee, "0.001");
+
+        // Native tokens are never approved
+        assert!(plan_approval(&request(token("ETH", EVM_NATIVE_TOKEN)), 0, &options, "0.001", 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_allowance_reset() {
+        let usdt = token("USDT", "0xdAC17F958D2ee523a2206206994597C13D831ec7");
+        let plan = plan_approval(&request(usdt), 1, &ApprovalOptions::default(), "0.001", 0).unwrap().unwrap();
+        assert!(matches!(&plan.steps[0], ApprovalStep::Approve { amount, .. } if amount == "0"));
+        assert_eq!(plan.steps.len(), 2);
+        assert_eq!(plan.transactions, 3);
+    }
+
+    #[test]
+    fn test_permit2() {
+        let options = ApprovalOptions { strategy: ApprovalStrategy::Permit2, smart_account: None };
+
+        // First use approves Permit2 once, then signs
+        let plan = plan_approval(&request(usdc()), 0, &options, "0.001", 100).unwrap().unwrap();
+        assert_eq!(plan.spender, PERMIT2_ADDRESS);
+        assert!(matches!(&plan.steps[0], ApprovalStep::Approve { amount, .. } if *amount == U256::MAX.to_string()));
+        assert!(matches!(plan.steps[1], ApprovalStep::Permit2Signature { deadline: 1900, .. }));
+
+        // Afterwards a signature is enough and costs no gas
+        let plan = plan_approval(&request(usdc()), u128::MAX, &options, "0.001", 100).unwrap().unwrap();
+        assert_eq!(plan.steps.len(), 1);
+        assert_eq!(plan.transactions, 1);
+        assert_eq!(plan.network_fee, "0.001");
+    }
+
+    #[test]
+    fn test_batched_approval() {
+        let options = ApprovalOptions { strategy: ApprovalStrategy::Exact, smart_account: Some(OWNER_ACCOUNT.to_string()) };
+        let plan = plan_approval(&request(usdc()), 0, &options, "0.001", 0).unwrap().unwrap();
+        assert!(plan.batched);
+        assert_eq!(plan.transactions, 1);
+        assert_eq!(plan.network_fee, "0.001125");
+
+        let swap = Intent::Call { to: Protocol::Uniswap.router_address().unwrap().to_string(), value: "0".to_string(), data: "0x".to_string() };
+        let intents = plan.batch_intents(swap.clone());
+        assert!(matches!(intents[0], Intent::Approve { .. }));
+        assert_eq!(intents[1], swap);
+    }
+}
@@ -0,0 +1,708 @@
+//! Staking auto-compounding
+//!
+//! An [`AutoCompounder`] claims the rewards of a staking position and
+//! restakes them on the position's schedule. A run is skipped while the
+//! rewards are below the position's threshold or the two transactions would
+//! cost more than the policy's share of them, so small positions are not
+//! eaten by fees. Restaked rewards are summed per token as compounded yield
+//! and reported in an owner's [`EarnAnalytics`].
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Serialize, Deserialize};
+
+use crate::crypto::keys::KeyType;
+use crate::error::{Error, Result};
+use crate::transaction::provider::ProviderConfig;
+use crate::transaction::schedule::{Schedule, ScheduleNotification, ScheduleNotifier};
+use super::provider::DeFiProviderFactory;
+use super::types::{Protocol, StakingAction, StakingRequest, StakingResult, Token, TokenAmount};
+
+/// Transactions in one compounding run: a claim and a stake
+const TRANSACTIONS_PER_RUN: u128 = 2;
+
+/// Get the chain a protocol's rewards can be compounded on
+pub fn compounding_key_type(protocol: &Protocol) -> Result<KeyType> {
+    match protocol {
+        Protocol::Lido => Ok(KeyType::Ethereum),
+        Protocol::Marinade => Ok(KeyType::Solana),
+        _ => Err(Error::InvalidInput(format!("Rewards of {:?} cannot be compounded", protocol))),
+    }
+}
+
+/// A request to compound a staking position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompoundRequest {
+    /// Protocol the position is staked with
+    pub protocol: Protocol,
+    /// Address holding the position
+    pub address: String,
+    /// When to compound
+    pub schedule: Schedule,
+    /// Smallest rewards worth compounding, in the smallest unit
+    pub min_rewards: String,
+}
+
+/// A staking position compounded on a schedule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompoundPosition {
+    /// Position ID
+    pub id: String,
+    /// Owner (wallet or user ID)
+    pub owner: String,
+    /// What to compound and when
+    pub request: CompoundRequest,
+    /// Next run time, or `None` once the schedule is exhausted
+    pub next_run: Option<u64>,
+    /// Whether compounding is active
+    pub enabled: bool,
+    /// Number of consecutive failed runs
+    pub consecutive_failures: u32,
+    /// Rewards claimed by a run whose restake failed, restaked by the next run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unstaked: Option<TokenAmount>,
+    /// Unix timestamp of creation
+    pub created_at: u64,
+}
+
+/// Outcome of a compounding run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CompoundOutcome {
+    /// Rewards were claimed and restaked
+    Compounded {
+        /// Claim transaction hash, if rewards were claimed in this run
+        claim_hash: Option<String>,
+        /// Stake transaction hash
+        stake_hash: String,
+        /// Amount restaked
+        amount: TokenAmount,
+        /// Network fees paid, in the smallest unit of the native token
+        fees: String,
+    },
+    /// The run was not worth its fees
+    Skipped { reason: String },
+    /// Claiming or restaking failed
+    Failed { error: String },
+}
+
+/// Record of a compounding run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompoundRun {
+    /// Position ID
+    pub position_id: String,
+    /// Owner of the position, so its yield is still reported once it is removed
+    pub owner: String,
+    /// Time the run was due
+    pub scheduled_at: u64,
+    /// Time the run happened
+    pub executed_at: u64,
+    /// Outcome
+    pub outcome: CompoundOutcome,
+}
+
+/// Rewards restaked for an owner in one token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompoundedYield {
+    /// Token restaked
+    pub token: Token,
+    /// Total restaked, in the smallest unit
+    pub compounded: String,
+    /// Network fees paid for compounding, in the smallest unit of the native token
+    pub fees: String,
+    /// Number of runs that compounded
+    pub runs: u32,
+}
+
+/// An owner's Earn analytics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EarnAnalytics {
+    /// Owner (wallet or user ID)
+    pub owner: String,
+    /// Positions being compounded
+    pub active_positions: u32,
+    /// Positions paused by the owner or after repeated failures
+    pub paused_positions: u32,
+    /// Next compounding run of any active position
+    pub next_run: Option<u64>,
+    /// Rewards restaked, per token, including positions since removed
+    pub compounded_yield: Vec<CompoundedYield>,
+}
+
+/// Limits applied to every compounding run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompoundPolicy {
+    /// Largest share of the rewards the run's fees may take, in basis points
+    pub max_fee_bps: u32,
+    /// Consecutive failures after which a position is paused
+    pub max_consecutive_failures: u32,
+}
+
+impl Default for CompoundPolicy {
+    fn default() -> Self {
+        Self { max_fee_bps: 1000, max_consecutive_failures: 3 }
+    }
+}
+
+/// Claims and restakes rewards
+pub trait CompoundExecutor {
+    /// Check a position may be compounded now; an error skips the run
+    fn check(&self, _request: &CompoundRequest) -> Result<()> {
+        Ok(())
+    }
+
+    /// Get the rewards a position could claim
+    fn pending_rewards(&self, request: &CompoundRequest) -> Result<TokenAmount>;
+
+    /// Estimate the fee of one transaction, in the smallest unit of the native token
+    fn network_fee(&self, request: &CompoundRequest) -> Result<u128>;
+
+    /// Execute a staking action
+    fn execute(&self, request: &StakingRequest) -> Result<StakingResult>;
+}
+
+/// Compounds through the DeFi staking pipeline
+pub struct PipelineCompoundExecutor<'a> {
+    /// Provider configuration
+    config: &'a ProviderConfig,
+}
+
+impl<'a> PipelineCompoundExecutor<'a> {
+    /// Create a pipeline executor
+    pub fn new(config: &'a ProviderConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl CompoundExecutor for PipelineCompoundExecutor<'_> {
+    fn pending_rewards(&self, request: &CompoundRequest) -> Result<TokenAmount> {
+        let provider = DeFiProviderFactory::create_provider(compounding_key_type(&request.protocol)?, self.config.clone())?;
+        provider.get_pending_rewards(&request.protocol, &request.address)
+    }
+
+    fn network_fee(&self, request: &CompoundRequest) -> Result<u128> {
+        let key_type = compounding_key_type(&request.protocol)?;
+        let provider = DeFiProviderFactory::create_provider(key_type, self.config.clone())?;
+        let decimals = match key_type {
+            KeyType::Solana => 9,
+            _ => 18,
+        };
+        whole_to_units(&provider.estimate_network_fee()?, decimals)
+    }
+
+    fn execute(&self, request: &StakingRequest) -> Result<StakingResult> {
+        super::staking::execute_staking(request, self.config)
+    }
+}
+
+/// Convert a decimal amount of whole tokens to the smallest unit
+fn whole_to_units(amount: &str, decimals: u8) -> Result<u128> {
+    let invalid = || Error::InvalidInput(format!("Invalid amount: {}", amount));
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if fraction.len() > decimals as usize {
+        return Err(invalid());
+    }
+    let whole = if whole.is_empty() { 0 } else { whole.parse::<u128>().map_err(|_| invalid())? };
+    let fraction = format!("{:0<width$}", fraction, width = decimals as usize);
+    let fraction = if fraction.is_empty() { 0 } else { fraction.parse::<u128>().map_err(|_| invalid())? };
+    whole.checked_mul(10u128.pow(decimals as u32))
+        .and_then(|units| units.checked_add(fraction))
+        .ok_or_else(invalid)
+}
+
+/// Parse an amount in the smallest unit
+fn units(amount: &TokenAmount) -> Result<u128> {
+    amount.amount.parse::<u128>()
+        .map_err(|_| Error::InvalidInput(format!("Invalid amount: {}", amount.amount)))
+}
+
+/// Stores compounding positions and compounds them when due
+pub struct AutoCompounder {
+    policy: CompoundPolicy,
+    positions: Mutex<HashMap<String, CompoundPosition>>,
+    runs: Mutex<Vec<CompoundRun>>,
+}
+
+impl AutoCompounder {
+    /// Create an auto-compounder
+    pub fn new(policy: CompoundPolicy) -> Self {
+        Self {
+            policy,
+            positions: Mutex::new(HashMap::new()),
+            runs: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Start compounding a position
+    pub fn add_position(&self, owner: &str, request: CompoundRequest, now: u64) -> Result<CompoundPosition> {
+        compounding_key_type(&request.protocol)?;
+        if request.address.trim().is_empty() {
+            return Err(Error::InvalidInput("Position address is required".to_string()));
+        }
+        request.min_rewards.parse::<u128>()
+            .map_err(|_| Error::InvalidInput(format!("Invalid minimum rewards: {}", request.min_rewards)))?;
+        if let Schedule::Interval { seconds, .. } = request.schedule {
+            if seconds < 3600 {
+                return Err(Error::InvalidInput("Compounding interval must be at least an hour".to_string()));
+            }
+        }
+
+        let next_run = request.schedule.next_after(now)
+            .ok_or_else(|| Error::InvalidInput("Schedule never runs".to_string()))?;
+
+        let position = CompoundPosition {
+            id: format!("compound_{}", hex::encode(rand::random::<[u8; 8]>())),
+            owner: owner.to_string(),
+            request,
+            next_run: Some(next_run),
+            enabled: true,
+            consecutive_failures: 0,
+            unstaked: None,
+            created_at: now,
+        };
+
+        self.positions.lock().unwrap().insert(position.id.clone(), position.clone());
+        Ok(position)
+    }
+
+    /// Get a position
+    pub fn position(&self, id: &str) -> Option<CompoundPosition> {
+        self.positions.lock().unwrap().get(id).cloned()
+    }
+
+    /// Get the positions of an owner
+    pub fn positions(&self, owner: &str) -> Vec<CompoundPosition> {
+        let mut positions: Vec<CompoundPosition> = self.positions.lock().unwrap()
+            .values()
+            .filter(|position| position.owner == owner)
+            .cloned()
+            .collect();
+        positions.sort_by_key(|position| position.created_at);
+        positions
+    }
+
+    /// Stop compounding a position
+    pub fn remove(&self, id: &str) -> Result<()> {
+        self.positions.lock().unwrap().remove(id)
+            .map(|_| ())
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown compounding position: {}", id)))
+    }
+
+    /// Pause or resume a position
+    ///
+    /// Resuming resets the failure count and skips runs missed while paused.
+    pub fn set_enabled(&self, id: &str, enabled: bool, now: u64) -> Result<()> {
+        let mut positions = self.positions.lock().unwrap();
+        let position = positions.get_mut(id)
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown compounding position: {}", id)))?;
+
+        if enabled && !position.enabled {
+            position.consecutive_failures = 0;
+            position.next_run = position.request.schedule.next_after(now);
+        }
+        position.enabled = enabled;
+        Ok(())
+    }
+
+    /// Get the runs of a position, oldest first
+    pub fn runs(&self, position_id: &str) -> Vec<CompoundRun> {
+        self.runs.lock().unwrap().iter()
+            .filter(|run| run.position_id == position_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Get the rewards restaked for an owner, per token
+    pub fn compounded_yield(&self, owner: &str) -> Vec<CompoundedYield> {
+        let mut totals: Vec<(Token, u128, u128, u32)> = Vec::new();
+
+        for run in self.runs.lock().unwrap().iter().filter(|run| run.owner == owner) {
+            let CompoundOutcome::Compounded { amount, fees, .. } = &run.outcome else { continue };
+            let (Ok(amount_units), Ok(fee_units)) = (units(amount), fees.parse::<u128>()) else { continue };
+
+            match totals.iter_mut().find(|(token, ..)| token.key_type == amount.token.key_type && token.address == amount.token.address) {
+                Some((_, compounded, paid, runs)) => {
+                    *compounded += amount_units;
+                    *paid += fee_units;
+                    *runs += 1;
+                }
+                None => totals.push((amount.token.clone(), amount_units, fee_units, 1)),
+            }
+        }
+
+        totals.into_iter()
+            .map(|(token, compounded, fees, runs)| CompoundedYield {
+                token,
+                compounded: compounded.to_string(),
+                fees: fees.to_string(),
+                runs,
+            })
+            .collect()
+    }
+
+    /// Get an owner's Earn analytics
+    pub fn analytics(&self, owner: &str) -> EarnAnalytics {
+        let positions = self.positions(owner);
+        let active = || positions.iter().filter(|position| position.enabled);
+
+        EarnAnalytics {
+            owner: owner.to_string(),
+            active_positions: active().count() as u32,
+            paused_positions: positions.iter().filter(|position| !position.enabled).count() as u32,
+            next_run: active().filter_map(|position| position.next_run).min(),
+            compounded_yield: self.compounded_yield(owner),
+        }
+    }
+
+    /// Compound every position that is due at `now`
+    ///
+    /// Each due position runs at most once per call; runs missed while the
+    /// compounder was down are not replayed.
+    pub fn run_due(&self, now: u64, executor: &dyn CompoundExecutor, notifier: &dyn ScheduleNotifier) -> Vec<CompoundRun> {
+        let due: Vec<CompoundPosition> = self.positions.lock().unwrap()
+            .values()
+            .filter(|position| position.enabled && position.next_run.is_some_and(|run| run <= now))
+            .cloned()
+            .collect();
+
+        let mut records = Vec::new();
+        for position in due {
+            let (outcome, unstaked) = self.compound(&position, executor);
+            let record = CompoundRun {
+                position_id: position.id.clone(),
+                owner: position.owner.clone(),
+                scheduled_at: position.next_run.unwrap_or(now),
+                executed_at: now,
+                outcome,
+            };
+
+            self.finish_run(&position, &record, unstaked, now, notifier);
+            self.runs.lock().unwrap().push(record.clone());
+            records.push(record);
+        }
+
+        records
+    }
+
+    /// Claim and restake a position's rewards
+    ///
+    /// Returns the outcome and any rewards left claimed but not restaked.
+    fn compound(&self, position: &CompoundPosition, executor: &dyn CompoundExecutor) -> (CompoundOutcome, Option<TokenAmount>) {
+        let request = &position.request;
+        let failed = |error: Error, unstaked: Option<TokenAmount>| (CompoundOutcome::Failed { error: error.to_string() }, unstaked);
+
+        if let Err(e) = executor.check(request) {
+            return (CompoundOutcome::Skipped { reason: e.to_string() }, position.unstaked.clone());
+        }
+
+        let fee = match executor.network_fee(request) {
+            Ok(fee) => fee,
+            Err(e) => return failed(e, position.unstaked.clone()),
+        };
+
+        // Rewards a previous run claimed but could not restake only need the stake
+        let (rewards, claim_hash, fees) = match position.unstaked.clone() {
+            Some(unstaked) => (unstaked, None, fee),
+            None => {
+                let pending = match executor.pending_rewards(request) {
+                    Ok(pending) => pending,
+                    Err(e) => return failed(e, None),
+                };
+                if let Err(reason) = self.check_worth_compounding(request, &pending, fee) {
+                    return (CompoundOutcome::Skipped { reason }, None);
+                }
+
+                let claim = StakingRequest { action: StakingAction::ClaimRewards, protocol: request.protocol.clone(), dry_run: false };
+                match executor.execute(&claim) {
+                    Ok(result) => (result.rewards.unwrap_or(pending), Some(result.transaction_hash), fee * TRANSACTIONS_PER_RUN),
+                    Err(e) => return failed(e, None),
+                }
+            }
+        };
+
+        if units(&rewards).unwrap_or(0) == 0 {
+            return (CompoundOutcome::Skipped { reason: "No rewards were claimed".to_string() }, None);
+        }
+        let stake = StakingRequest { action: StakingAction::Stake(rewards.clone()), protocol: request.protocol.clone(), dry_run: false };
+        match executor.execute(&stake) {
+            Ok(result) => (
+                CompoundOutcome::Compounded { claim_hash, stake_hash: result.transaction_hash, amount: rewards, fees: fees.to_string() },
+                None,
+            ),
+            Err(e) => failed(Error::DeFi(format!("Claimed {} but restaking failed: {}", rewards.format(), e)), Some(rewards)),
+        }
+    }
+
+    /// Check pending rewards clear the threshold and outweigh the fees
+    fn check_worth_compounding(&self, request: &CompoundRequest, pending: &TokenAmount, fee: u128) -> std::result::Result<(), String> {
+        let rewards = units(pending).map_err(|e| e.to_string())?;
+        let min_rewards = request.min_rewards.parse::<u128>().unwrap_or(0);
+        if rewards == 0 || rewards < min_rewards {
+            return Err(format!("Pending rewards of {} are below the minimum of {}", pending.format(), min_rewards));
+        }
+
+        let fees = fee.saturating_mul(TRANSACTIONS_PER_RUN);
+        if fees.saturating_mul(10_000) > rewards.saturating_mul(self.policy.max_fee_bps as u128) {
+            return Err(format!(
+                "Fees of {} would take more than {} bps of rewards of {}",
+                fees, self.policy.max_fee_bps, rewards
+            ));
+        }
+        Ok(())
+    }
+
+    fn finish_run(&self, position: &CompoundPosition, record: &CompoundRun, unstaked: Option<TokenAmount>, now: u64, notifier: &dyn ScheduleNotifier) {
+        let mut paused = false;
+
+        {
+            let mut positions = self.positions.lock().unwrap();
+            let Some(stored) = positions.get_mut(&position.id) else {
+                return;
+            };
+
+            stored.next_run = stored.request.schedule.next_after(now);
+            stored.unstaked = unstaked;
+            match record.outcome {
+                CompoundOutcome::Compounded { .. } | CompoundOutcome::Skipped { .. } => stored.consecutive_failures = 0,
+                CompoundOutcome::Failed { .. } => {
+                    stored.consecutive_failures += 1;
+                    if stored.consecutive_failures >= self.policy.max_consecutive_failures {
+                        stored.enabled = false;
+                        paused = true;
+                    }
+                }
+            }
+        }
+
+        let (order_id, owner) = (position.id.clone(), position.owner.clone());
+        match &record.outcome {
+            CompoundOutcome::Compounded { stake_hash, .. } => notifier.notify(ScheduleNotification::Executed {
+                order_id: order_id.clone(),
+                owner: owner.clone(),
+                transaction_hash: stake_hash.clone(),
+            }),
+            CompoundOutcome::Failed { error } => notifier.notify(ScheduleNotification::Failed {
+                order_id: order_id.clone(),
+                owner: owner.clone(),
+                reason: error.clone(),
+            }),
+            // Skipped runs are routine and not worth a notification
+            CompoundOutcome::Skipped { .. } => {}
+        }
+
+        if paused {
+            notifier.notify(ScheduleNotification::Paused { order_id, owner });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    struct Recorder(Mutex<Vec<ScheduleNotification>>);
+
+    impl ScheduleNotifier for Recorder {
+        fn notify(&self, notification: ScheduleNotification) {
+            self.0.lock().unwrap().push(notification);
+        }
+    }
+
+    /// Executor with fixed rewards and fee, whose stakes fail while `stake_fails` is set
+    struct TestExecutor {
+        rewards: u128,
+        fee: u128,
+        stake_fails: Mutex<bool>,
+        staked: Mutex<Vec<String>>,
+    }
+
+    impl TestExecutor {
+        fn new(rewards: u128, fee: u128) -> Self {
+            Self { rewards, fee, stake_fails: Mutex::new(false), staked: Mutex::new(Vec::new()) }
+        }
+    }
+
+    fn sol(amount: u128) -> TokenAmount {
+        TokenAmount {
+            token: Token {
+                name: "Solana".to_string(),
+                symbol: "SOL".to_string(),
+                decimals: 9,
+                address: "So11111111111111111111111111111111111111112".to_string(),
+                key_type: KeyType::Solana,
+                logo_url: None,
+            },
+            amount: amount.to_string(),
+        }
+    }
+
+    impl CompoundExecutor for TestExecutor {
+        fn pending_rewards(&self, _request: &CompoundRequest) -> Result<TokenAmount> {
+            Ok(sol(self.rewards))
+        }
+
+        fn network_fee(&self, _request: &CompoundRequest) -> Result<u128> {
+            Ok(self.fee)
+        }
+
+        fn execute(&self, request: &StakingRequest) -> Result<StakingResult> {
+            let rewards = match &request.action {
+                StakingAction::ClaimRewards => Some(sol(self.rewards)),
+                StakingAction::Stake(amount) => {
+                    if *self.stake_fails.lock().unwrap() {
+                        return Err(Error::Network("node unavailable".to_string()));
+                    }
+                    self.staked.lock().unwrap().push(amount.amount.clone());
+                    None
+                }
+                StakingAction::Unstake(_) => None,
+            };
+            Ok(StakingResult {
+                action: request.action.clone(),
+                transaction_hash: "hash".to_string(),
+                protocol: request.protocol.clone(),
+                fee: "0.000005".to_string(),
+                rewards,
+            })
+        }
+    }
+
+    fn request(min_rewards: u128) -> CompoundRequest {
+        CompoundRequest {
+            protocol: Protocol::Marinade,
+            address: "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg".to_string(),
+            schedule: Schedule::Interval { start: NOW + 3600, seconds: 86_400 },
+            min_rewards: min_rewards.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_whole_to_units() {
+        assert_eq!(whole_to_units("0.001", 18).unwrap(), 1_000_000_000_000_000);
+        assert_eq!(whole_to_units("0.000005", 9).unwrap(), 5_000);
+        assert_eq!(whole_to_units("2", 9).unwrap(), 2_000_000_000);
+        assert!(whole_to_units("0.0000000001", 9).is_err());
+    }
+
+    #[test]
+    fn test_add_position_checks() {
+        let compounder = AutoCompounder::new(CompoundPolicy::default());
+        assert!(compounder.add_position("alice", CompoundRequest { protocol: Protocol::Uniswap, ..request(0) }, NOW).is_err());
+        assert!(compounder.add_position("alice", CompoundRequest { schedule: Schedule::Interval { start: NOW, seconds: 60 }, ..request(0) }, NOW).is_err());
+        assert!(compounder.add_position("alice", CompoundRequest { min_rewards: "lots".to_string(), ..request(0) }, NOW).is_err());
+        assert!(compounder.add_position("alice", request(0), NOW).is_ok());
+    }
+
+    #[test]
+    fn test_compound_and_yield() {
+        let compounder = AutoCompounder::new(CompoundPolicy::default());
+        let notifier = Recorder(Mutex::new(Vec::new()));
+        let executor = TestExecutor::new(1_000_000_000, 5_000);
+        let position = compounder.add_position("alice", request(100_000_000), NOW).unwrap();
+
+        assert!(compounder.run_due(NOW, &executor, &notifier).is_empty());
+        let runs = compounder.run_due(NOW + 3600, &executor, &notifier);
+        assert!(matches!(&runs[0].outcome, CompoundOutcome::Compounded { fees, .. } if fees == "10000"));
+        assert_eq!(*executor.staked.lock().unwrap(), vec!["1000000000".to_string()]);
+        assert_eq!(compounder.position(&position.id).unwrap().next_run, Some(NOW + 3600 + 86_400));
+
+        compounder.run_due(NOW + 3600 + 86_400, &executor, &notifier);
+        let yields = compounder.compounded_yield("alice");
+        assert_eq!(yields.len(), 1);
+        assert_eq!(yields[0].compounded, "2000000000");
+        assert_eq!(yields[0].runs, 2);
+        assert!(compounder.compounded_yield("bob").is_empty());
+        assert!(matches!(notifier.0.lock().unwrap()[0], ScheduleNotification::Executed { .. }));
+
+        let analytics = compounder.analytics("alice");
+        assert_eq!((analytics.active_positions, analytics.paused_positions), (1, 0));
+        assert_eq!(analytics.next_run, Some(NOW + 3600 + 2 * 86_400));
+
+        // Yield outlives the position it was earned by
+        compounder.remove(&position.id).unwrap();
+        let analytics = compounder.analytics("alice");
+        assert_eq!((analytics.active_positions, analytics.next_run), (0, None));
+        assert_eq!(analytics.compounded_yield[0].compounded, "2000000000");
+    }
+
+    #[test]
+    fn test_skips_small_and_fee_heavy_rewards() {
+        let compounder = AutoCompounder::new(CompoundPolicy { max_fee_bps: 100, ..CompoundPolicy::default() });
+        let notifier = Recorder(Mutex::new(Vec::new()));
+
+        let below_threshold = compounder.add_position("alice", request(2_000_000_000), NOW).unwrap();
+        compounder.run_due(NOW + 3600, &TestExecutor::new(1_000_000_000, 5_000), &notifier);
+        assert!(matches!(compounder.runs(&below_threshold.id)[0].outcome, CompoundOutcome::Skipped { .. }));
+        compounder.remove(&below_threshold.id).unwrap();
+
+        // Two transactions at 0.01 SOL take 2% of 1 SOL, over the 1% allowed
+        let fee_heavy = compounder.add_position("alice", request(0), NOW).unwrap();
+        let executor = TestExecutor::new(1_000_000_000, 10_000_000);
+        compounder.run_due(NOW + 3600, &executor, &notifier);
+        assert!(matches!(compounder.runs(&fee_heavy.id)[0].outcome, CompoundOutcome::Skipped { .. }));
+        assert!(executor.staked.lock().unwrap().is_empty());
+        assert!(notifier.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_check_skips_before_claiming() {
+        /// Refuses every run, as when deposits into the protocol are paused
+        struct Paused(TestExecutor);
+
+        impl CompoundExecutor for Paused {
+            fn check(&self, request: &CompoundRequest) -> Result<()> {
+                Err(Error::DeFi(format!("Deposits into {:?} are paused", request.protocol)))
+            }
+
+            fn pending_rewards(&self, request: &CompoundRequest) -> Result<TokenAmount> {
+                self.0.pending_rewards(request)
+            }
+
+            fn network_fee(&self, request: &CompoundRequest) -> Result<u128> {
+                self.0.network_fee(request)
+            }
+
+            fn execute(&self, request: &StakingRequest) -> Result<StakingResult> {
+                self.0.execute(request)
+            }
+        }
+
+        let compounder = AutoCompounder::new(CompoundPolicy::default());
+        let notifier = Recorder(Mutex::new(Vec::new()));
+        let executor = Paused(TestExecutor::new(1_000_000_000, 5_000));
+        let position = compounder.add_position("alice", request(0), NOW).unwrap();
+
+        let runs = compounder.run_due(NOW + 3600, &executor, &notifier);
+        assert!(matches!(&runs[0].outcome, CompoundOutcome::Skipped { reason } if reason.contains("paused")));
+        assert!(executor.0.staked.lock().unwrap().is_empty());
+        assert_eq!(compounder.position(&position.id).unwrap().consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_failed_restake_is_retried_and_pauses() {
+        let compounder = AutoCompounder::new(CompoundPolicy { max_consecutive_failures: 2, ..CompoundPolicy::default() });
+        let notifier = Recorder(Mutex::new(Vec::new()));
+        let executor = TestExecutor::new(1_000_000_000, 5_000);
+        let position = compounder.add_position("alice", request(0), NOW).unwrap();
+
+        *executor.stake_fails.lock().unwrap() = true;
+        compounder.run_due(NOW + 3600, &executor, &notifier);
+        let stored = compounder.position(&position.id).unwrap();
+        assert_eq!(stored.unstaked.unwrap().amount, "1000000000");
+        assert!(stored.enabled);
+
+        compounder.run_due(NOW + 3600 + 86_400, &executor, &notifier);
+        assert!(!compounder.position(&position.id).unwrap().enabled);
+        assert!(matches!(notifier.0.lock().unwrap().last(), Some(ScheduleNotification::Paused { .. })));
+
+        // Resuming restakes the claimed rewards without claiming again
+        *executor.stake_fails.lock().unwrap() = false;
+        compounder.set_enabled(&position.id, true, NOW + 2 * 86_400).unwrap();
+        let next_run = compounder.position(&position.id).unwrap().next_run.unwrap();
+        let runs = compounder.run_due(next_run, &executor, &notifier);
+        assert!(matches!(&runs[0].outcome, CompoundOutcome::Compounded { claim_hash: None, fees, .. } if fees == "5000"));
+        assert!(compounder.position(&position.id).unwrap().unstaked.is_none());
+    }
+}
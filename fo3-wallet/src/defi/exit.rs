@@ -0,0 +1,554 @@
+//! Emergency exits from DeFi positions
+//!
+//! The [`EmergencyExits`] engine tracks users' lending and staking positions
+//! and, when a protocol risk event fires or a user hits the panic button,
+//! unwinds every affected position. Steps run in dependency order: borrows
+//! are repaid before the collateral they lock is withdrawn, and staking
+//! rewards are claimed before the stake is removed. A failed step skips the
+//! steps that depend on it but not the others, and the resulting
+//! [`ExitReport`] records what was recovered and what is still stranded so
+//! the exit can be retried.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Serialize, Deserialize};
+
+use crate::error::{Error, Result};
+use crate::transaction::provider::ProviderConfig;
+use super::risk::{RiskEvent, RiskLevel};
+use super::types::{LendingAction, LendingRequest, Protocol, StakingAction, StakingRequest, TokenAmount};
+
+/// Kind of a DeFi position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionKind {
+    /// Tokens supplied to a lending market
+    Supply,
+    /// Tokens borrowed from a lending market
+    Borrow,
+    /// Tokens staked with a protocol
+    Stake,
+}
+
+/// A request to track a position for emergency exits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackPositionRequest {
+    /// Protocol holding the position
+    pub protocol: Protocol,
+    /// Kind of position
+    pub kind: PositionKind,
+    /// Size of the position
+    pub amount: TokenAmount,
+}
+
+/// A tracked DeFi position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EarnPosition {
+    /// Position ID
+    pub id: String,
+    /// Owner (wallet or user ID)
+    pub owner: String,
+    /// Protocol holding the position
+    pub protocol: Protocol,
+    /// Kind of position
+    pub kind: PositionKind,
+    /// Size of the position
+    pub amount: TokenAmount,
+    /// Unix timestamp of creation
+    pub created_at: u64,
+}
+
+/// Why an exit was started
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExitTrigger {
+    /// A protocol risk event
+    Risk { event: RiskEvent },
+    /// The owner asked to exit everything
+    Panic,
+}
+
+/// Action of an exit step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExitAction {
+    /// A lending market action
+    Lending { action: LendingAction },
+    /// A staking action
+    Staking { action: StakingAction },
+}
+
+/// Outcome of an exit step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum StepOutcome {
+    /// Not run yet
+    Pending,
+    /// The transaction was sent
+    Succeeded { transaction_hash: String, fee: String },
+    /// The transaction failed
+    Failed { error: String },
+    /// Not run because a step it depends on did not succeed
+    Skipped { reason: String },
+}
+
+/// One transaction of an exit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExitStep {
+    /// Position the step unwinds
+    pub position_id: String,
+    /// Protocol the step runs on
+    pub protocol: Protocol,
+    /// What the step does
+    pub action: ExitAction,
+    /// Indexes of the steps that must succeed first
+    pub depends_on: Vec<usize>,
+    /// Outcome
+    pub outcome: StepOutcome,
+    /// Number of times the step was attempted
+    pub attempts: u32,
+}
+
+/// Overall status of an exit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitStatus {
+    /// Every step succeeded
+    Completed,
+    /// Some steps failed or were skipped
+    PartiallyFailed,
+}
+
+/// Post-mortem of an emergency exit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExitReport {
+    /// Exit ID
+    pub id: String,
+    /// Owner of the positions
+    pub owner: String,
+    /// Why the exit started
+    pub trigger: ExitTrigger,
+    /// Steps in execution order
+    pub steps: Vec<ExitStep>,
+    /// Overall status
+    pub status: ExitStatus,
+    /// Tokens withdrawn or unstaked
+    pub recovered: Vec<TokenAmount>,
+    /// Positions not fully unwound
+    pub stranded: Vec<String>,
+    /// Unix timestamp the exit started
+    pub started_at: u64,
+    /// Unix timestamp of the latest attempt
+    pub finished_at: u64,
+}
+
+/// Sends the transactions of an exit
+pub trait ExitExecutor {
+    /// Execute a lending action, returning the transaction hash and fee
+    fn lending(&self, request: &LendingRequest) -> Result<(String, String)>;
+
+    /// Execute a staking action, returning the transaction hash and fee
+    fn staking(&self, request: &StakingRequest) -> Result<(String, String)>;
+}
+
+/// Exits through the DeFi lending and staking pipelines
+pub struct PipelineExitExecutor<'a> {
+    /// Provider configuration
+    config: &'a ProviderConfig,
+}
+
+impl<'a> PipelineExitExecutor<'a> {
+    /// Create a pipeline executor
+    pub fn new(config: &'a ProviderConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl ExitExecutor for PipelineExitExecutor<'_> {
+    fn lending(&self, request: &LendingRequest) -> Result<(String, String)> {
+        super::lending::execute_lending(request, self.config).map(|result| (result.transaction_hash, result.fee))
+    }
+
+    fn staking(&self, request: &StakingRequest) -> Result<(String, String)> {
+        super::staking::execute_staking(request, self.config).map(|result| (result.transaction_hash, result.fee))
+    }
+}
+
+/// Order the steps unwinding a set of positions
+///
+/// Repayments come first, then reward claims, withdrawals and unstakes. A
+/// withdrawal depends on every repayment in the same protocol, since the
+/// borrow may be what locks the collateral.
+pub fn plan_exit(positions: &[EarnPosition]) -> Vec<ExitStep> {
+    let step = |position: &EarnPosition, action: ExitAction| ExitStep {
+        position_id: position.id.clone(),
+        protocol: position.protocol.clone(),
+        action,
+        depends_on: Vec::new(),
+        outcome: StepOutcome::Pending,
+        attempts: 0,
+    };
+    let lending = |action: LendingAction| ExitAction::Lending { action };
+    let staking = |action: StakingAction| ExitAction::Staking { action };
+
+    let mut steps: Vec<ExitStep> = Vec::new();
+    for position in positions.iter().filter(|position| position.kind == PositionKind::Borrow) {
+        steps.push(step(position, lending(LendingAction::Repay(position.amount.clone()))));
+    }
+    let repaid = steps.len();
+
+    for position in positions.iter().filter(|position| position.kind == PositionKind::Stake) {
+        steps.push(step(position, staking(StakingAction::ClaimRewards)));
+    }
+    for position in positions.iter().filter(|position| position.kind == PositionKind::Supply) {
+        let mut withdraw = step(position, lending(LendingAction::Withdraw(position.amount.clone())));
+        withdraw.depends_on = (0..repaid).filter(|&index| steps[index].protocol == position.protocol).collect();
+        steps.push(withdraw);
+    }
+    // Unstaking does not wait for the claim; rewards are secondary in an emergency
+    for position in positions.iter().filter(|position| position.kind == PositionKind::Stake) {
+        steps.push(step(position, staking(StakingAction::Unstake(position.amount.clone()))));
+    }
+    steps
+}
+
+/// Check whether a risk event calls for exiting a protocol
+pub fn exit_protocol(event: &RiskEvent) -> Option<&Protocol> {
+    match event {
+        RiskEvent::Exploited { protocol, .. } => Some(protocol),
+        RiskEvent::LevelChanged { protocol, to: RiskLevel::Critical, .. } => Some(protocol),
+        RiskEvent::LevelChanged { .. } | RiskEvent::TvlDrop { .. } => None,
+    }
+}
+
+/// Tracks positions and unwinds them in emergencies
+pub struct EmergencyExits {
+    positions: Mutex<HashMap<String, EarnPosition>>,
+    reports: Mutex<HashMap<String, ExitReport>>,
+}
+
+impl EmergencyExits {
+    /// Create an engine with no positions
+    pub fn new() -> Self {
+        Self {
+            positions: Mutex::new(HashMap::new()),
+            reports: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Track a position
+    pub fn track(&self, owner: &str, request: TrackPositionRequest, now: u64) -> Result<EarnPosition> {
+        let amount = request.amount.amount.parse::<u128>()
+            .map_err(|_| Error::InvalidInput(format!("Invalid amount: {}", request.amount.amount)))?;
+        if amount == 0 {
+            return Err(Error::InvalidInput("Position amount must be positive".to_string()));
+        }
+
+        let position = EarnPosition {
+            id: format!("position_{}", hex::encode(rand::random::<[u8; 8]>())),
+            owner: owner.to_string(),
+            protocol: request.protocol,
+            kind: request.kind,
+            amount: request.amount,
+            created_at: now,
+        };
+        self.positions.lock().unwrap().insert(position.id.clone(), position.clone());
+        Ok(position)
+    }
+
+    /// Stop tracking a position
+    pub fn untrack(&self, owner: &str, id: &str) -> Result<()> {
+        let mut positions = self.positions.lock().unwrap();
+        match positions.get(id) {
+            Some(position) if position.owner == owner => {
+                positions.remove(id);
+                Ok(())
+            }
+            _ => Err(Error::InvalidInput(format!("Unknown position: {}", id))),
+        }
+    }
+
+    /// Get the positions of an owner
+    pub fn positions(&self, owner: &str) -> Vec<EarnPosition> {
+        let mut positions: Vec<EarnPosition> = self.positions.lock().unwrap()
+            .values()
+            .filter(|position| position.owner == owner)
+            .cloned()
+            .collect();
+        positions.sort_by_key(|position| position.created_at);
+        positions
+    }
+
+    /// Get an exit report
+    pub fn report(&self, id: &str) -> Option<ExitReport> {
+        self.reports.lock().unwrap().get(id).cloned()
+    }
+
+    /// Get the exit reports of an owner, newest first
+    pub fn reports(&self, owner: &str) -> Vec<ExitReport> {
+        let mut reports: Vec<ExitReport> = self.reports.lock().unwrap()
+            .values()
+            .filter(|report| report.owner == owner)
+            .cloned()
+            .collect();
+        reports.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+        reports
+    }
+
+    /// Exit every position of an owner
+    pub fn panic(&self, owner: &str, executor: &dyn ExitExecutor, now: u64) -> Result<ExitReport> {
+        let positions = self.positions(owner);
+        if positions.is_empty() {
+            return Err(Error::InvalidInput("No positions to exit".to_string()));
+        }
+        Ok(self.exit(owner, positions, ExitTrigger::Panic, executor, now))
+    }
+
+    /// Exit the positions in a protocol a risk event calls to leave, one exit per owner
+    pub fn on_risk_event(&self, event: &RiskEvent, executor: &dyn ExitExecutor, now: u64) -> Vec<ExitReport> {
+        let Some(protocol) = exit_protocol(event) else {
+            return Vec::new();
+        };
+
+        let mut by_owner: HashMap<String, Vec<EarnPosition>> = HashMap::new();
+        for position in self.positions.lock().unwrap().values().filter(|position| &position.protocol == protocol) {
+            by_owner.entry(position.owner.clone()).or_default().push(position.clone());
+        }
+
+        let mut owners: Vec<String> = by_owner.keys().cloned().collect();
+        owners.sort();
+        owners.into_iter()
+            .map(|owner| {
+                let mut positions = by_owner.remove(&owner).unwrap_or_default();
+                positions.sort_by_key(|position| position.created_at);
+                self.exit(&owner, positions, ExitTrigger::Risk { event: event.clone() }, executor, now)
+            })
+            .collect()
+    }
+
+    /// Retry the steps of an exit that did not succeed
+    pub fn retry(&self, owner: &str, id: &str, executor: &dyn ExitExecutor, now: u64) -> Result<ExitReport> {
+        let mut report = self.report(id)
+            .filter(|report| report.owner == owner)
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown exit: {}", id)))?;
+        if report.status == ExitStatus::Completed {
+            return Err(Error::InvalidInput(format!("Exit {} already completed", id)));
+        }
+
+        for step in &mut report.steps {
+            if !matches!(step.outcome, StepOutcome::Succeeded { .. }) {
+                step.outcome = StepOutcome::Pending;
+            }
+        }
+        self.run(&mut report, executor, now);
+        Ok(report)
+    }
+
+    fn exit(&self, owner: &str, positions: Vec<EarnPosition>, trigger: ExitTrigger, executor: &dyn ExitExecutor, now: u64) -> ExitReport {
+        let mut report = ExitReport {
+            id: format!("exit_{}", hex::encode(rand::random::<[u8; 8]>())),
+            owner: owner.to_string(),
+            trigger,
+            steps: plan_exit(&positions),
+            status: ExitStatus::PartiallyFailed,
+            recovered: Vec::new(),
+            stranded: Vec::new(),
+            started_at: now,
+            finished_at: now,
+        };
+        self.run(&mut report, executor, now);
+        report
+    }
+
+    /// Run the pending steps, then update the report and stop tracking unwound positions
+    fn run(&self, report: &mut ExitReport, executor: &dyn ExitExecutor, now: u64) {
+        for index in 0..report.steps.len() {
+            if !matches!(report.steps[index].outcome, StepOutcome::Pending) {
+                continue;
+            }
+
+            let blocked = report.steps[index].depends_on.iter()
+                .find(|&&dependency| !matches!(report.steps[dependency].outcome, StepOutcome::Succeeded { .. }))
+                .copied();
+            let step = &mut report.steps[index];
+            if let Some(dependency) = blocked {
+                step.outcome = StepOutcome::Skipped { reason: format!("Step {} did not succeed", dependency) };
+                continue;
+            }
+
+            step.attempts += 1;
+            let result = match &step.action {
+                ExitAction::Lending { action } => executor.lending(&LendingRequest {
+                    action: action.clone(),
+                    protocol: step.protocol.clone(),
+                    dry_run: false,
+                }),
+                ExitAction::Staking { action } => executor.staking(&StakingRequest {
+                    action: action.clone(),
+                    protocol: step.protocol.clone(),
+                    dry_run: false,
+                }),
+            };
+            step.outcome = match result {
+                Ok((transaction_hash, fee)) => StepOutcome::Succeeded { transaction_hash, fee },
+                Err(e) => StepOutcome::Failed { error: e.to_string() },
+            };
+        }
+
+        let succeeded = |step: &ExitStep| matches!(step.outcome, StepOutcome::Succeeded { .. });
+        report.recovered = report.steps.iter()
+            .filter(|step| succeeded(step))
+            .filter_map(|step| match &step.action {
+                ExitAction::Lending { action: LendingAction::Withdraw(amount) }
+                | ExitAction::Staking { action: StakingAction::Unstake(amount) } => Some(amount.clone()),
+                _ => None,
+            })
+            .collect();
+        // Reward claims are best effort and do not strand a position
+        let mut stranded: Vec<String> = report.steps.iter()
+            .filter(|step| !succeeded(step) && !matches!(step.action, ExitAction::Staking { action: StakingAction::ClaimRewards }))
+            .map(|step| step.position_id.clone())
+            .collect();
+        stranded.dedup();
+        report.stranded = stranded;
+        report.status = if report.steps.iter().all(succeeded) { ExitStatus::Completed } else { ExitStatus::PartiallyFailed };
+        report.finished_at = now;
+
+        {
+            let mut positions = self.positions.lock().unwrap();
+            for step in &report.steps {
+                if !report.stranded.contains(&step.position_id) {
+                    positions.remove(&step.position_id);
+                }
+            }
+        }
+        self.reports.lock().unwrap().insert(report.id.clone(), report.clone());
+    }
+}
+
+impl Default for EmergencyExits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyType;
+    use crate::defi::types::Token;
+    use crate::defi::risk::ExploitReport;
+
+    const NOW: u64 = 1_700_000_000;
+
+    /// Executor whose repayments fail while `repay_fails` is set
+    struct TestExecutor {
+        repay_fails: Mutex<bool>,
+        sent: Mutex<Vec<String>>,
+    }
+
+    impl TestExecutor {
+        fn new(repay_fails: bool) -> Self {
+            Self { repay_fails: Mutex::new(repay_fails), sent: Mutex::new(Vec::new()) }
+        }
+    }
+
+    impl ExitExecutor for TestExecutor {
+        fn lending(&self, request: &LendingRequest) -> Result<(String, String)> {
+            if matches!(request.action, LendingAction::Repay(_)) && *self.repay_fails.lock().unwrap() {
+                return Err(Error::DeFi("insufficient balance to repay".to_string()));
+            }
+            self.sent.lock().unwrap().push(format!("{:?}", request.action).split('(').next().unwrap().to_string());
+            Ok(("hash".to_string(), "0.001".to_string()))
+        }
+
+        fn staking(&self, request: &StakingRequest) -> Result<(String, String)> {
+            self.sent.lock().unwrap().push(format!("{:?}", request.action).split('(').next().unwrap().to_string());
+            Ok(("hash".to_string(), "0.001".to_string()))
+        }
+    }
+
+    fn usdc(amount: u128) -> TokenAmount {
+        TokenAmount {
+            token: Token {
+                name: "USD Coin".to_string(),
+                symbol: "USDC".to_string(),
+                decimals: 6,
+                address: "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string(),
+                key_type: KeyType::Ethereum,
+                logo_url: None,
+            },
+            amount: amount.to_string(),
+        }
+    }
+
+    fn track(exits: &EmergencyExits, owner: &str, protocol: Protocol, kind: PositionKind) -> EarnPosition {
+        exits.track(owner, TrackPositionRequest { protocol, kind, amount: usdc(1_000_000) }, NOW).unwrap()
+    }
+
+    #[test]
+    fn test_plan_order() {
+        let exits = EmergencyExits::new();
+        let supply = track(&exits, "alice", Protocol::Aave, PositionKind::Supply);
+        track(&exits, "alice", Protocol::Lido, PositionKind::Stake);
+        track(&exits, "alice", Protocol::Aave, PositionKind::Borrow);
+
+        let steps = plan_exit(&exits.positions("alice"));
+        let actions: Vec<String> = steps.iter().map(|step| format!("{:?}", step.action)).collect();
+        assert!(actions[0].contains("Repay"));
+        assert!(actions[1].contains("ClaimRewards"));
+        assert!(actions[2].contains("Withdraw"));
+        assert!(actions[3].contains("Unstake"));
+        assert_eq!(steps[2].position_id, supply.id);
+        assert_eq!(steps[2].depends_on, vec![0]);
+    }
+
+    #[test]
+    fn test_partial_failure_and_retry() {
+        let exits = EmergencyExits::new();
+        let supply = track(&exits, "alice", Protocol::Compound, PositionKind::Supply);
+        let borrow = track(&exits, "alice", Protocol::Compound, PositionKind::Borrow);
+        track(&exits, "alice", Protocol::Lido, PositionKind::Stake);
+        track(&exits, "bob", Protocol::Lido, PositionKind::Stake);
+
+        let event = RiskEvent::Exploited {
+            protocol: Protocol::Compound,
+            report: ExploitReport {
+                id: "a".to_string(),
+                protocol: Protocol::Compound,
+                reported_at: NOW,
+                loss_usd: 1.0,
+                description: "Oracle manipulation".to_string(),
+                resolved: false,
+            },
+        };
+        let executor = TestExecutor::new(true);
+        let reports = exits.on_risk_event(&event, &executor, NOW);
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!(report.status, ExitStatus::PartiallyFailed);
+        assert!(matches!(report.steps[0].outcome, StepOutcome::Failed { .. }));
+        assert!(matches!(report.steps[1].outcome, StepOutcome::Skipped { .. }));
+        assert_eq!(report.stranded, vec![borrow.id.clone(), supply.id.clone()]);
+        assert!(report.recovered.is_empty());
+        assert!(executor.sent.lock().unwrap().is_empty());
+
+        *executor.repay_fails.lock().unwrap() = false;
+        let report = exits.retry("alice", &report.id, &executor, NOW + 60).unwrap();
+        assert_eq!(report.status, ExitStatus::Completed);
+        assert_eq!(report.recovered.len(), 1);
+        assert_eq!(report.steps[0].attempts, 2);
+        assert_eq!(*executor.sent.lock().unwrap(), vec!["Repay", "Withdraw"]);
+
+        // Unwound positions are no longer tracked; the Lido stake is untouched
+        assert_eq!(exits.positions("alice").len(), 1);
+        assert!(exits.retry("alice", &report.id, &executor, NOW).is_err());
+
+        // Panic exits everything left
+        let report = exits.panic("bob", &executor, NOW).unwrap();
+        assert_eq!(report.status, ExitStatus::Completed);
+        assert!(exits.positions("bob").is_empty());
+        assert!(exits.panic("bob", &executor, NOW).is_err());
+    }
+}
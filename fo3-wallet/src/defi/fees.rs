@@ -0,0 +1,389 @@
+//! Platform fees on swaps
+//!
+//! A deployment may charge a platform (or referral) fee on every swap. The
+//! fee is taken from the input amount before it reaches the pool, paid to
+//! the deployment's recipient in a separate transfer, and recorded in a
+//! [`FeeLedger`] for revenue accounting. [`InMemoryFeeLedger`] forgets its
+//! entries on restart; deployments that report revenue from the ledger use
+//! [`FileFeeLedger`].
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use ethers::abi::Token as AbiToken;
+use ethers::prelude::{Address, U256};
+use serde::{Serialize, Deserialize};
+
+use crate::address::validate_address;
+use crate::crypto::keys::KeyType;
+use crate::error::{Error, Result};
+use crate::transaction::{
+    associated_token_address, create_associated_token_account_instruction, system_transfer_instruction,
+    transfer_checked_instruction, Instruction, NetworkBinding, TokenProgram, TransactionRequest,
+};
+use super::types::{Protocol, SwapResult, Token, TokenAmount, EVM_NATIVE_TOKEN, SOLANA_NATIVE_TOKEN};
+
+/// Highest platform fee a deployment may charge, in basis points
+pub const MAX_PLATFORM_FEE_BPS: u32 = 100;
+
+/// Platform fee settings of a deployment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlatformFeeConfig {
+    /// Fee in basis points of the swap input
+    pub fee_bps: u32,
+    /// Recipient on EVM chains
+    pub evm_recipient: Option<String>,
+    /// Recipient on Solana
+    pub solana_recipient: Option<String>,
+}
+
+impl PlatformFeeConfig {
+    /// Validate the fee and recipients
+    pub fn validate(&self) -> Result<()> {
+        if self.fee_bps > MAX_PLATFORM_FEE_BPS {
+            return Err(Error::InvalidInput(format!("Platform fee of {} bps exceeds the maximum of {} bps", self.fee_bps, MAX_PLATFORM_FEE_BPS)));
+        }
+
+        for (key_type, recipient) in [(KeyType::Ethereum, &self.evm_recipient), (KeyType::Solana, &self.solana_recipient)] {
+            if let Some(recipient) = recipient {
+                validate_address(key_type, recipient)
+                    .map_err(|e| Error::InvalidInput(format!("Invalid {:?} fee recipient {}: {}", key_type, recipient, e)))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the recipient for a chain
+    pub fn recipient(&self, key_type: KeyType) -> Option<&str> {
+        match key_type {
+            KeyType::Ethereum => self.evm_recipient.as_deref(),
+            KeyType::Solana => self.solana_recipient.as_deref(),
+            KeyType::Bitcoin | KeyType::Ton | KeyType::Cosmos | KeyType::Xrp => None,
+        }
+    }
+
+    /// Get the fee charged on an input amount (zero if the chain has no recipient)
+    pub fn fee_amount(&self, key_type: KeyType, amount_in: u128) -> Result<u128> {
+        match self.recipient(key_type) {
+            Some(_) => bps_of(amount_in, self.fee_bps),
+            None => Ok(0),
+        }
+    }
+
+    /// Build the fee transfer for a swap input, if a fee applies
+    pub fn fee_transfer(&self, from: &TokenAmount) -> Result<Option<PlatformFeeTransfer>> {
+        let amount_in = from.amount.parse::<u128>()
+            .map_err(|_| Error::InvalidInput(format!("Invalid amount: {}", from.amount)))?;
+
+        let (Some(recipient), amount) = (self.recipient(from.token.key_type), self.fee_amount(from.token.key_type, amount_in)?) else {
+            return Ok(None);
+        };
+        if amount == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(PlatformFeeTransfer {
+            token: from.token.clone(),
+            amount: amount.to_string(),
+            recipient: recipient.to_string(),
+        }))
+    }
+}
+
+/// Get `bps` basis points of an amount, rounded down
+pub(crate) fn bps_of(amount: u128, bps: u32) -> Result<u128> {
+    amount.checked_mul(bps as u128)
+        .map(|scaled| scaled / 10_000)
+        .ok_or_else(|| Error::InvalidInput(format!("Amount is too large: {}", amount)))
+}
+
+/// Transfer paying the platform fee of a swap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlatformFeeTransfer {
+    /// Token the fee is paid in
+    pub token: Token,
+    /// Fee amount in the smallest unit
+    pub amount: String,
+    /// Fee recipient
+    pub recipient: String,
+}
+
+impl PlatformFeeTransfer {
+    /// Build the EVM transaction paying the fee from `from`
+    ///
+    /// Native fees are a plain value transfer; ERC-20 fees call `transfer`
+    /// on the token contract.
+    pub fn to_evm_request(&self, from: &str, chain_id: u64) -> Result<TransactionRequest> {
+        if self.token.key_type != KeyType::Ethereum {
+            return Err(Error::InvalidInput("Fee transfer is not for an EVM chain".to_string()));
+        }
+
+        let native = self.token.address.eq_ignore_ascii_case(EVM_NATIVE_TOKEN);
+        let (to, value, data) = if native {
+            (self.recipient.clone(), self.amount.clone(), None)
+        } else {
+            let recipient = Address::from_str(&self.recipient)
+                .map_err(|e| Error::InvalidInput(format!("Invalid fee recipient {}: {}", self.recipient, e)))?;
+            let amount = U256::from_dec_str(&self.amount)
+                .map_err(|e| Error::InvalidInput(format!("Invalid fee amount {}: {}", self.amount, e)))?;
+
+            let mut data = ethers::utils::id("transfer(address,uint256)").to_vec();
+            data.extend(ethers::abi::encode(&[AbiToken::Address(recipient), AbiToken::Uint(amount)]));
+            (self.token.address.clone(), "0".to_string(), Some(data))
+        };
+
+        Ok(TransactionRequest {
+            key_type: KeyType::Ethereum,
+            network: NetworkBinding::Evm { chain_id },
+            from: from.to_string(),
+            to,
+            value,
+            gas_price: None,
+            gas_limit: None,
+            nonce: None,
+            data,
+            destination_tag: None,
+            memo: None,
+        })
+    }
+
+    /// Build the Solana instructions paying the fee from `from`
+    ///
+    /// Native fees are a System transfer. SPL fees create the recipient's
+    /// associated token account if needed and `TransferChecked` between the
+    /// associated accounts of the original Token program.
+    pub fn to_solana_instructions(&self, from: &str) -> Result<Vec<Instruction>> {
+        if self.token.key_type != KeyType::Solana {
+            return Err(Error::InvalidInput("Fee transfer is not for Solana".to_string()));
+        }
+
+        let amount = self.amount.parse::<u64>()
+            .map_err(|_| Error::InvalidInput(format!("Invalid fee amount: {}", self.amount)))?;
+        if self.token.address == SOLANA_NATIVE_TOKEN {
+            return Ok(vec![system_transfer_instruction(from, &self.recipient, amount)]);
+        }
+
+        let mint = &self.token.address;
+        let source = associated_token_address(from, mint, TokenProgram::Token)?;
+        let destination = associated_token_address(&self.recipient, mint, TokenProgram::Token)?;
+        Ok(vec![
+            create_associated_token_account_instruction(from, &self.recipient, mint, TokenProgram::Token)?,
+            transfer_checked_instruction(TokenProgram::Token, &source, mint, &destination, from, amount, self.token.decimals),
+        ])
+    }
+}
+
+/// A platform fee recorded for revenue accounting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeLedgerEntry {
+    /// Hash of the swap transaction
+    pub transaction_hash: String,
+    /// Protocol the swap ran on
+    pub protocol: Protocol,
+    /// Token the fee was paid in
+    pub token: Token,
+    /// Fee amount in the smallest unit
+    pub amount: String,
+    /// Fee recipient
+    pub recipient: String,
+    /// Unix timestamp of the swap
+    pub timestamp: u64,
+}
+
+impl FeeLedgerEntry {
+    /// Create the ledger entry for a swap, if it paid a platform fee
+    pub fn from_swap(result: &SwapResult, timestamp: u64) -> Option<Self> {
+        result.platform_fee.as_ref().map(|fee| Self {
+            transaction_hash: result.transaction_hash.clone(),
+            protocol: result.protocol.clone(),
+            token: fee.token.clone(),
+            amount: fee.amount.clone(),
+            recipient: fee.recipient.clone(),
+            timestamp,
+        })
+    }
+}
+
+/// Store of collected platform fees
+pub trait FeeLedger: Send + Sync {
+    /// Record a collected fee
+    fn record(&self, entry: FeeLedgerEntry) -> Result<()>;
+}
+
+/// In-memory fee ledger, lost on restart
+#[derive(Debug, Default)]
+pub struct InMemoryFeeLedger {
+    entries: Mutex<Vec<FeeLedgerEntry>>,
+}
+
+impl InMemoryFeeLedger {
+    /// Create an empty ledger
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get all recorded entries
+    pub fn entries(&self) -> Vec<FeeLedgerEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    /// Get the total revenue collected in a token, in the smallest unit
+    pub fn revenue(&self, key_type: KeyType, token_address: &str) -> u128 {
+        self.entries.lock().unwrap().iter()
+            .filter(|entry| entry.token.key_type == key_type && entry.token.address.eq_ignore_ascii_case(token_address))
+            .filter_map(|entry| entry.amount.parse::<u128>().ok())
+            .sum()
+    }
+}
+
+impl FeeLedger for InMemoryFeeLedger {
+    fn record(&self, entry: FeeLedgerEntry) -> Result<()> {
+        self.entries.lock().unwrap().push(entry);
+        Ok(())
+    }
+}
+
+/// Fee ledger appending JSON lines to a file
+#[derive(Debug)]
+pub struct FileFeeLedger {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileFeeLedger {
+    /// Open a ledger file, creating it on the first record
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), lock: Mutex::new(()) }
+    }
+
+    /// Get all recorded entries
+    pub fn entries(&self) -> Result<Vec<FeeLedgerEntry>> {
+        let _guard = self.lock.lock().unwrap();
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::Provider(format!("Failed to read fee ledger {}: {}", self.path.display(), e))),
+        };
+
+        contents.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|e| Error::Serialization(e.to_string())))
+            .collect()
+    }
+}
+
+impl FeeLedger for FileFeeLedger {
+    fn record(&self, entry: FeeLedgerEntry) -> Result<()> {
+        let line = serde_json::to_string(&entry)
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+
+        let _guard = self.lock.lock().unwrap();
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)
+            .map_err(|e| Error::Provider(format!("Failed to open fee ledger {}: {}", self.path.display(), e)))?;
+        writeln!(file, "{}", line)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| Error::Provider(format!("Failed to write fee ledger {}: {}", self.path.display(), e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> PlatformFeeConfig {
+        PlatformFeeConfig {
+            fee_bps: 50,
+            evm_recipient: Some("0x742d35Cc6634C0532925a3b844Bc454e4438f44e".to_string()),
+            solana_recipient: None,
+        }
+    }
+
+    fn usdc(amount: &str) -> TokenAmount {
+        TokenAmount {
+            token: Token {
+                name: "USD Coin".to_string(),
+                symbol: "USDC".to_string(),
+                decimals: 6,
+                address: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+                key_type: KeyType::Ethereum,
+                logo_url: None,
+            },
+            amount: amount.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(config().validate().is_ok());
+        assert!(PlatformFeeConfig { fee_bps: 500, ..config() }.validate().is_err());
+        assert!(PlatformFeeConfig { solana_recipient: Some("not-base58!".to_string()), ..config() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_fee_transfer() {
+        let transfer = config().fee_transfer(&usdc("1000000")).unwrap().unwrap();
+        assert_eq!(transfer.amount, "5000");
+
+        // ERC-20 fees call transfer(recipient, amount) on the token
+        let request = transfer.to_evm_request("0x9858EfFD232B4033E47d90003D41EC34EcaEda94", 1).unwrap();
+        assert_eq!(request.to, "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+        assert_eq!(request.value, "0");
+        assert_eq!(hex::encode(&request.data.unwrap()[..4]), "a9059cbb");
+
+        // Dust below one basis-point unit pays no fee
+        assert!(config().fee_transfer(&usdc("100")).unwrap().is_none());
+
+        // Chains without a recipient pay no fee
+        let mut sol = usdc("1000000");
+        sol.token.key_type = KeyType::Solana;
+        assert!(config().fee_transfer(&sol).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_solana_fee_transfer() {
+        let config = PlatformFeeConfig { solana_recipient: Some("4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T".to_string()), ..config() };
+        let payer = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+
+        let mut spl = usdc("1000000");
+        spl.token.key_type = KeyType::Solana;
+        spl.token.address = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string();
+        let instructions = config.fee_transfer(&spl).unwrap().unwrap().to_solana_instructions(payer).unwrap();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[1].program_id, TokenProgram::Token.program_id());
+        assert_eq!(instructions[1].data[1..9], 5000u64.to_le_bytes());
+
+        let mut sol = spl.clone();
+        sol.token.address = SOLANA_NATIVE_TOKEN.to_string();
+        let instructions = config.fee_transfer(&sol).unwrap().unwrap().to_solana_instructions(payer).unwrap();
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0].accounts[1].pubkey, "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T");
+
+        // An EVM fee cannot be paid on Solana
+        assert!(config.fee_transfer(&usdc("1000000")).unwrap().unwrap().to_solana_instructions(payer).is_err());
+    }
+
+    #[test]
+    fn test_file_ledger() {
+        let path = std::env::temp_dir().join(format!("fo3-fees-{}.jsonl", hex::encode(rand::random::<[u8; 8]>())));
+        let ledger = FileFeeLedger::new(&path);
+        let entry = FeeLedgerEntry {
+            transaction_hash: "0xabc".to_string(),
+            protocol: Protocol::Uniswap,
+            token: usdc("5000").token,
+            amount: "5000".to_string(),
+            recipient: "0x742d35Cc6634C0532925a3b844Bc454e4438f44e".to_string(),
+            timestamp: 1,
+        };
+        ledger.record(entry).unwrap();
+
+        let entries = FileFeeLedger::new(&path).entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].amount, "5000");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -2,24 +2,36 @@
 
 use crate::error::Result;
 use crate::crypto::keys::KeyType;
-use super::types::{Protocol, LendingRequest, LendingResult, LendingAction};
+use super::types::{DryRunResult, Protocol, LendingRequest, LendingResult, LendingAction};
+use super::swap::ensure_not_dry_run;
 use super::provider::DeFiProviderFactory;
 use crate::transaction::provider::ProviderConfig;
 
-/// Execute lending action
-pub fn execute_lending(request: &LendingRequest, config: &ProviderConfig) -> Result<LendingResult> {
-    let key_type = match &request.action {
+/// Get the chain a lending action runs on
+fn lending_key_type(request: &LendingRequest) -> KeyType {
+    match &request.action {
         LendingAction::Supply(token_amount) => token_amount.token.key_type,
         LendingAction::Withdraw(token_amount) => token_amount.token.key_type,
         LendingAction::Borrow(token_amount) => token_amount.token.key_type,
         LendingAction::Repay(token_amount) => token_amount.token.key_type,
-    };
-    
-    let provider = DeFiProviderFactory::create_provider(key_type, config.clone())?;
+    }
+}
+
+/// Execute lending action
+pub fn execute_lending(request: &LendingRequest, config: &ProviderConfig) -> Result<LendingResult> {
+    ensure_not_dry_run(request.dry_run)?;
+    let provider = DeFiProviderFactory::create_provider(lending_key_type(request), config.clone())?;
     
     provider.execute_lending(request)
 }
 
+/// Preview a lending action without signing or broadcasting
+pub fn dry_run_lending(request: &LendingRequest, config: &ProviderConfig) -> Result<DryRunResult> {
+    let provider = DeFiProviderFactory::create_provider(lending_key_type(request), config.clone())?;
+
+    provider.dry_run_lending(request)
+}
+
 /// Get supported lending protocols
 pub fn get_supported_lending_protocols(key_type: KeyType, config: &ProviderConfig) -> Result<Vec<Protocol>> {
     let provider = DeFiProviderFactory::create_provider(key_type, config.clone())?;
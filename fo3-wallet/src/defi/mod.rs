@@ -8,9 +8,25 @@ mod swap;
 mod lending;
 mod staking;
 mod provider;
+mod fees;
+mod pending;
+mod portfolio;
+mod spam;
+mod approval;
+mod compound;
+mod risk;
+mod exit;
 
 pub use types::*;
 pub use swap::*;
 pub use lending::*;
 pub use staking::*;
 pub use provider::*;
+pub use fees::*;
+pub use pending::*;
+pub use portfolio::*;
+pub use spam::*;
+pub use approval::*;
+pub use compound::*;
+pub use risk::*;
+pub use exit::*;
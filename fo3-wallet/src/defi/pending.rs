@@ -0,0 +1,354 @@
+//! Pending balance overlay
+//!
+//! Confirmed balances lag behind transactions this wallet has just
+//! broadcast. [`PendingBalances`] remembers the balance changes of locally
+//! sent transactions and swaps and applies them on top of confirmed balances
+//! until [`PendingBalances::reconcile`] sees them settle on-chain.
+
+use std::sync::Mutex;
+
+use serde::{Serialize, Deserialize};
+
+use crate::caip::AssetId;
+use crate::crypto::keys::KeyType;
+use crate::error::Result;
+use crate::pricing::FiatAmount;
+use crate::transaction::{TransactionBroadcaster, TransactionRequest, TransactionStatus};
+use super::types::{SwapResult, Token, TokenAmount, EVM_NATIVE_TOKEN, SOLANA_NATIVE_TOKEN};
+
+/// Selector of ERC-20 `transfer(address,uint256)`
+const ERC20_TRANSFER: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+
+/// A balance change of a transaction that has not settled yet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingDelta {
+    /// Blockchain type
+    pub key_type: KeyType,
+    /// Address whose balance changes
+    pub owner: String,
+    /// Token address, or `None` for the native token
+    pub token: Option<String>,
+    /// Change in the smallest unit (negative for outgoing)
+    pub amount: i128,
+    /// Hash of the transaction causing the change
+    pub transaction_hash: String,
+    /// Unix timestamp the change was recorded
+    pub created_at: u64,
+}
+
+impl PendingDelta {
+    fn applies_to(&self, owner: &str, token: &Token) -> bool {
+        if token.key_type != self.key_type || !same_address(self.key_type, &self.owner, owner) {
+            return false;
+        }
+
+        match &self.token {
+            Some(address) => same_address(self.key_type, address, &token.address),
+            None => is_native(token),
+        }
+    }
+}
+
+/// A balance with pending changes applied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdjustedBalance {
+    /// Balance including pending changes
+    #[serde(flatten)]
+    pub balance: TokenAmount,
+    /// Confirmed on-chain balance in the smallest unit
+    pub confirmed_amount: String,
+    /// Whether pending changes were applied
+    pub pending_adjusted: bool,
+    /// CAIP-19 asset ID, when the chain is known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset_id: Option<AssetId>,
+    /// Value in the display currency, when the token's price is known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fiat_value: Option<FiatAmount>,
+}
+
+fn same_address(key_type: KeyType, a: &str, b: &str) -> bool {
+    match key_type {
+        // EVM addresses are case-insensitive (the case only encodes a checksum)
+        KeyType::Ethereum => a.eq_ignore_ascii_case(b),
+        _ => a == b,
+    }
+}
+
+fn is_native(token: &Token) -> bool {
+    match token.key_type {
+        KeyType::Ethereum => token.address.eq_ignore_ascii_case(EVM_NATIVE_TOKEN),
+        KeyType::Solana => token.address == SOLANA_NATIVE_TOKEN,
+        KeyType::Bitcoin => token.symbol == "BTC",
+        KeyType::Ton => token.address == "TON",
+        KeyType::Cosmos => crate::transaction::CosmosChain::known().iter().any(|chain| chain.staking_denom == token.address),
+        KeyType::Xrp => token.address == "XRP",
+    }
+}
+
+/// Decode the recipient and amount of an ERC-20 `transfer` call
+fn decode_erc20_transfer(data: &[u8]) -> Option<(String, u128)> {
+    if data.len() != 68 || data[..4] != ERC20_TRANSFER {
+        return None;
+    }
+
+    // Amounts beyond u128 are not representable as deltas; skip them
+    if data[36..52].iter().any(|&b| b != 0) {
+        return None;
+    }
+    let recipient = format!("0x{}", hex::encode(&data[16..36]));
+    let amount = u128::from_be_bytes(data[52..68].try_into().ok()?);
+    Some((recipient, amount))
+}
+
+/// Locally known balance changes awaiting confirmation
+#[derive(Debug, Default)]
+pub struct PendingBalances {
+    deltas: Mutex<Vec<PendingDelta>>,
+}
+
+impl PendingBalances {
+    /// Create an empty overlay
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get all pending changes
+    pub fn deltas(&self) -> Vec<PendingDelta> {
+        self.deltas.lock().unwrap().clone()
+    }
+
+    /// Record the balance changes of a broadcast transfer
+    ///
+    /// Native transfers debit the value plus the maximum EVM fee (when gas
+    /// price and limit are set) and credit the recipient. ERC-20 `transfer`
+    /// calls move the token between sender and recipient.
+    pub fn record_transaction(&self, request: &TransactionRequest, hash: &str, now: u64) {
+        let delta = |owner: &str, token: Option<String>, amount: i128| PendingDelta {
+            key_type: request.key_type,
+            owner: owner.to_string(),
+            token,
+            amount,
+            transaction_hash: hash.to_string(),
+            created_at: now,
+        };
+        let mut deltas = Vec::new();
+
+        let max_fee = match (request.key_type, &request.gas_price, &request.gas_limit) {
+            (KeyType::Ethereum, Some(price), Some(limit)) => price.parse::<i128>().ok()
+                .zip(limit.parse::<i128>().ok())
+                .and_then(|(price, limit)| price.checked_mul(limit))
+                .unwrap_or(0),
+            _ => 0,
+        };
+        let value = request.value.parse::<i128>().unwrap_or(0);
+
+        if value + max_fee > 0 {
+            deltas.push(delta(&request.from, None, -(value + max_fee)));
+        }
+        if value > 0 {
+            deltas.push(delta(&request.to, None, value));
+        }
+
+        if let Some((recipient, amount)) = request.data.as_deref().and_then(decode_erc20_transfer) {
+            let amount = amount as i128;
+            deltas.push(delta(&request.from, Some(request.to.clone()), -amount));
+            deltas.push(delta(&recipient, Some(request.to.clone()), amount));
+        }
+
+        self.deltas.lock().unwrap().extend(deltas);
+    }
+
+    /// Record the balance changes of an executed swap
+    pub fn record_swap(&self, owner: &str, result: &SwapResult, now: u64) {
+        let delta = |amount: &TokenAmount, sign: i128| PendingDelta {
+            key_type: amount.token.key_type,
+            owner: owner.to_string(),
+            token: (!is_native(&amount.token)).then(|| amount.token.address.clone()),
+            amount: sign * amount.amount.parse::<i128>().unwrap_or(0),
+            transaction_hash: result.transaction_hash.clone(),
+            created_at: now,
+        };
+
+        let mut deltas = vec![delta(&result.from, -1), delta(&result.to, 1)];
+        if let Some(fee) = &result.platform_fee {
+            deltas.push(delta(&TokenAmount { token: fee.token.clone(), amount: fee.amount.clone() }, -1));
+        }
+
+        self.deltas.lock().unwrap().extend(deltas);
+    }
+
+    /// Drop the changes of a settled transaction
+    pub fn settle(&self, hash: &str) {
+        self.deltas.lock().unwrap().retain(|delta| delta.transaction_hash != hash);
+    }
+
+    /// Drop changes of `key_type` whose transactions are confirmed or failed
+    ///
+    /// Changes older than `max_age` seconds are dropped as well, so a
+    /// transaction that never lands cannot skew balances forever.
+    pub fn reconcile<B: TransactionBroadcaster + ?Sized>(&self, key_type: KeyType, chain: &B, now: u64, max_age: u64) -> Result<()> {
+        let mut hashes: Vec<String> = self.deltas.lock().unwrap().iter()
+            .filter(|delta| delta.key_type == key_type)
+            .map(|delta| delta.transaction_hash.clone())
+            .collect();
+        hashes.dedup();
+
+        for hash in hashes {
+            if chain.get_transaction_status(&hash)? != TransactionStatus::Pending {
+                self.settle(&hash);
+            }
+        }
+
+        self.deltas.lock().unwrap().retain(|delta| now < delta.created_at + max_age);
+        Ok(())
+    }
+
+    /// Apply pending changes of `owner` to confirmed balances
+    ///
+    /// Adjusted balances never go below zero.
+    pub fn apply(&self, owner: &str, balances: Vec<TokenAmount>) -> Vec<AdjustedBalance> {
+        let deltas = self.deltas.lock().unwrap();
+
+        balances.into_iter()
+            .map(|balance| {
+                let applicable: Vec<i128> = deltas.iter()
+                    .filter(|delta| delta.applies_to(owner, &balance.token))
+                    .map(|delta| delta.amount)
+                    .collect();
+
+                let confirmed_amount = balance.amount.clone();
+                let amount = match (applicable.is_empty(), balance.amount.parse::<i128>()) {
+                    (false, Ok(confirmed)) => (confirmed + applicable.iter().sum::<i128>()).max(0).to_string(),
+                    _ => balance.amount.clone(),
+                };
+
+                AdjustedBalance {
+                    pending_adjusted: !applicable.is_empty(),
+                    balance: TokenAmount { amount, ..balance },
+                    confirmed_amount,
+                    asset_id: None,
+                    fiat_value: None,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::NetworkBinding;
+
+    const SENDER: &str = "0x9858EfFD232B4033E47d90003D41EC34EcaEda94";
+    const RECIPIENT: &str = "0x742d35Cc6634C0532925a3b844Bc454e4438f44e";
+    const USDC: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+
+    fn token(symbol: &str, address: &str) -> Token {
+        Token {
+            name: symbol.to_string(),
+            symbol: symbol.to_string(),
+            decimals: 18,
+            address: address.to_string(),
+            key_type: KeyType::Ethereum,
+            logo_url: None,
+        }
+    }
+
+    fn balances() -> Vec<TokenAmount> {
+        vec![
+            TokenAmount { token: token("ETH", EVM_NATIVE_TOKEN), amount: "1000000".to_string() },
+            TokenAmount { token: token("USDC", USDC), amount: "500".to_string() },
+        ]
+    }
+
+    fn request(to: &str, value: &str, data: Option<Vec<u8>>) -> TransactionRequest {
+        TransactionRequest {
+            key_type: KeyType::Ethereum,
+            network: NetworkBinding::Evm { chain_id: 1 },
+            from: SENDER.to_string(),
+            to: to.to_string(),
+            value: value.to_string(),
+            gas_price: Some("10".to_string()),
+            gas_limit: Some("21000".to_string()),
+            nonce: None,
+            data,
+            destination_tag: None,
+            memo: None,
+        }
+    }
+
+    struct Chain(TransactionStatus);
+
+    impl TransactionBroadcaster for Chain {
+        fn broadcast_transaction(&self, _signed_transaction: &[u8]) -> Result<String> {
+            unimplemented!()
+        }
+
+        fn get_transaction_status(&self, _hash: &str) -> Result<TransactionStatus> {
+            Ok(self.0)
+        }
+
+        fn get_transaction_receipt(&self, _hash: &str) -> Result<crate::transaction::TransactionReceipt> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_native_transfer_overlay() {
+        let pending = PendingBalances::new();
+        pending.record_transaction(&request(RECIPIENT, "1000", None), "0xaa", 0);
+
+        // Sender balances are read with a differently cased address
+        let adjusted = pending.apply(&SENDER.to_lowercase(), balances());
+        assert_eq!(adjusted[0].balance.amount, (1_000_000 - 1000 - 210_000).to_string());
+        assert_eq!(adjusted[0].confirmed_amount, "1000000");
+        assert!(adjusted[0].pending_adjusted);
+        assert!(!adjusted[1].pending_adjusted);
+
+        let adjusted = pending.apply(RECIPIENT, balances());
+        assert_eq!(adjusted[0].balance.amount, "1001000");
+    }
+
+    #[test]
+    fn test_erc20_transfer_overlay() {
+        let mut data = ERC20_TRANSFER.to_vec();
+        data.extend([0u8; 12]);
+        data.extend(hex::decode(&RECIPIENT[2..]).unwrap());
+        data.extend([0u8; 31]);
+        data.push(200);
+
+        let pending = PendingBalances::new();
+        pending.record_transaction(&request(USDC, "0", Some(data)), "0xbb", 0);
+
+        let adjusted = pending.apply(SENDER, balances());
+        assert_eq!(adjusted[1].balance.amount, "300");
+        // Only the gas is debited from the native balance
+        assert_eq!(adjusted[0].balance.amount, (1_000_000 - 210_000).to_string());
+
+        // Balances never go negative
+        let mut data = ERC20_TRANSFER.to_vec();
+        data.extend([0u8; 32]);
+        data.extend([0u8; 30]);
+        data.extend([0x10, 0x00]);
+        pending.record_transaction(&request(USDC, "0", Some(data)), "0xcc", 0);
+        assert_eq!(pending.apply(SENDER, balances())[1].balance.amount, "0");
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let pending = PendingBalances::new();
+        pending.record_transaction(&request(RECIPIENT, "1000", None), "0xaa", 0);
+
+        pending.reconcile(KeyType::Ethereum, &Chain(TransactionStatus::Pending), 10, 3600).unwrap();
+        assert_eq!(pending.deltas().len(), 2);
+
+        pending.reconcile(KeyType::Ethereum, &Chain(TransactionStatus::Confirmed), 20, 3600).unwrap();
+        assert!(pending.deltas().is_empty());
+        assert!(!pending.apply(SENDER, balances())[0].pending_adjusted);
+
+        pending.record_transaction(&request(RECIPIENT, "1000", None), "0xdd", 0);
+        pending.reconcile(KeyType::Ethereum, &Chain(TransactionStatus::Pending), 3600, 3600).unwrap();
+        assert!(pending.deltas().is_empty());
+    }
+}
@@ -0,0 +1,259 @@
+//! Solana token portfolio
+//!
+//! Builds the full token portfolio of a Solana address from its token
+//! accounts, so callers don't need to know which mints it holds. Balances
+//! of several accounts for the same mint are summed, metadata comes from the
+//! token list, and dust and spam are filtered out.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::crypto::keys::KeyType;
+use crate::transaction::TokenAccount;
+use super::spam::{SpamClassifier, TokenSignals};
+use super::types::{Token, TokenAmount, SOLANA_NATIVE_TOKEN};
+
+/// Decimals of SOL
+const SOL_DECIMALS: u8 = 9;
+
+/// Filter applied to a token portfolio
+#[derive(Debug, Clone)]
+pub struct PortfolioFilter {
+    /// Mints never shown
+    pub denylist: HashSet<String>,
+    /// Show tokens missing from the token list
+    pub include_unlisted: bool,
+    /// Smallest balance of an unlisted token shown, in whole tokens
+    pub min_unlisted_amount: f64,
+    /// Show zero balances of listed tokens
+    pub include_empty: bool,
+    /// Show tokens classified as spam
+    pub include_spam: bool,
+    /// User whose spam overrides apply
+    pub user: Option<String>,
+}
+
+impl Default for PortfolioFilter {
+    fn default() -> Self {
+        Self {
+            denylist: HashSet::new(),
+            include_unlisted: true,
+            min_unlisted_amount: 0.000001,
+            include_empty: false,
+            include_spam: false,
+            user: None,
+        }
+    }
+}
+
+impl PortfolioFilter {
+    /// Hide the given mints
+    pub fn with_denylist<I: IntoIterator<Item = S>, S: Into<String>>(mut self, mints: I) -> Self {
+        self.denylist.extend(mints.into_iter().map(Into::into));
+        self
+    }
+
+    /// Whether a holding should be hidden
+    ///
+    /// Besides spam, unlisted tokens are hidden when they are NFTs (not part
+    /// of the fungible portfolio) or hold less than `min_unlisted_amount`.
+    fn hides(&self, holding: &Holding, token: &Token, token_list: &[Token], classifier: &SpamClassifier) -> bool {
+        if self.denylist.contains(&holding.mint) {
+            return true;
+        }
+        if holding.amount == 0 {
+            return !(holding.listed && self.include_empty);
+        }
+        if !holding.listed {
+            let whole_tokens = holding.amount as f64 / 10f64.powi(holding.decimals as i32);
+            let nft = holding.decimals == 0 && holding.amount == 1;
+            if !self.include_unlisted || nft || whole_tokens < self.min_unlisted_amount {
+                return true;
+            }
+        }
+
+        let signals = TokenSignals { verified: holding.listed, frozen: holding.frozen, liquidity: None };
+        !self.include_spam && classifier.classify(self.user.as_deref(), token, &signals, token_list).is_spam
+    }
+}
+
+/// Balance of one mint across token accounts
+#[derive(Debug)]
+struct Holding {
+    mint: String,
+    amount: u128,
+    decimals: u8,
+    frozen: bool,
+    listed: bool,
+}
+
+/// Build a token portfolio from a SOL balance and token accounts
+///
+/// SOL and wrapped SOL are reported together under the wrapped SOL mint.
+/// Listed tokens come first in token list order, followed by unlisted
+/// tokens ordered by mint.
+pub fn token_portfolio(
+    lamports: u64,
+    accounts: &[TokenAccount],
+    token_list: &[Token],
+    filter: &PortfolioFilter,
+    classifier: &SpamClassifier,
+) -> Vec<TokenAmount> {
+    let mut holdings: HashMap<&str, Holding> = HashMap::new();
+    holdings.insert(SOLANA_NATIVE_TOKEN, Holding {
+        mint: SOLANA_NATIVE_TOKEN.to_string(),
+        amount: lamports as u128,
+        decimals: SOL_DECIMALS,
+        frozen: false,
+        listed: true,
+    });
+
+    for account in accounts {
+        let holding = holdings.entry(account.mint.as_str()).or_insert_with(|| Holding {
+            mint: account.mint.clone(),
+            amount: 0,
+            decimals: account.decimals,
+            frozen: true,
+            listed: false,
+        });
+        holding.amount = holding.amount.saturating_add(account.amount);
+        // A holding is only frozen if none of its accounts can be spent
+        holding.frozen &= account.frozen;
+    }
+
+    let mut portfolio = Vec::new();
+    for token in token_list.iter().filter(|token| token.key_type == KeyType::Solana) {
+        if let Some(mut holding) = holdings.remove(token.address.as_str()) {
+            holding.listed = true;
+            if !filter.hides(&holding, token, token_list, classifier) {
+                portfolio.push(TokenAmount { token: token.clone(), amount: holding.amount.to_string() });
+            }
+        }
+    }
+
+    let mut unlisted: Vec<Holding> = holdings.into_values().collect();
+    unlisted.sort_by(|a, b| a.mint.cmp(&b.mint));
+    for holding in unlisted {
+        let token = unlisted_token(&holding.mint, holding.decimals);
+        if !filter.hides(&holding, &token, token_list, classifier) {
+            portfolio.push(TokenAmount { token, amount: holding.amount.to_string() });
+        }
+    }
+
+    portfolio
+}
+
+/// Token metadata for a mint missing from the token list
+fn unlisted_token(mint: &str, decimals: u8) -> Token {
+    Token {
+        name: "Unknown token".to_string(),
+        symbol: mint.chars().take(4).collect::<String>().to_uppercase(),
+        decimals,
+        address: mint.to_string(),
+        key_type: KeyType::Solana,
+        logo_url: None,
+    }
+}
+
+/// Get every token held by a Solana address
+///
+/// Enumerates the owner's token accounts under both token programs and
+/// resolves metadata from the Solana DeFi provider's token list.
+#[cfg(feature = "rpc")]
+pub fn get_all_token_balances(
+    owner: &str,
+    config: &crate::transaction::provider::ProviderConfig,
+    filter: &PortfolioFilter,
+    classifier: &SpamClassifier,
+) -> crate::error::Result<Vec<TokenAmount>> {
+    let provider = crate::transaction::SolanaProvider::new(config.clone())?;
+    let (lamports, accounts) = provider.get_token_accounts(owner)?;
+    let token_list = super::swap::get_supported_tokens(KeyType::Solana, config)?;
+
+    Ok(token_portfolio(lamports, &accounts, &token_list, filter, classifier))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::TokenProgram;
+
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn account(mint: &str, amount: u128, decimals: u8) -> TokenAccount {
+        TokenAccount {
+            address: format!("{}-account-{}", mint, amount),
+            mint: mint.to_string(),
+            owner: "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg".to_string(),
+            program: TokenProgram::Token,
+            amount,
+            decimals,
+            lamports: 2_039_280,
+            is_native: false,
+            frozen: false,
+            close_authority: None,
+        }
+    }
+
+    fn token_list() -> Vec<Token> {
+        vec![
+            Token {
+                name: "Solana".to_string(),
+                symbol: "SOL".to_string(),
+                decimals: 9,
+                address: SOLANA_NATIVE_TOKEN.to_string(),
+                key_type: KeyType::Solana,
+                logo_url: None,
+            },
+            Token {
+                name: "USD Coin".to_string(),
+                symbol: "USDC".to_string(),
+                decimals: 6,
+                address: USDC.to_string(),
+                key_type: KeyType::Solana,
+                logo_url: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_portfolio_aggregates_and_resolves() {
+        let accounts = vec![
+            account(USDC, 1_500_000, 6),
+            account(USDC, 500_000, 6),
+            account(SOLANA_NATIVE_TOKEN, 250_000_000, 9),
+            account("BonkMint1111111111111111111111111111111111", 4_200_000, 5),
+        ];
+
+        let portfolio = token_portfolio(1_000_000_000, &accounts, &token_list(), &PortfolioFilter::default(), &SpamClassifier::new());
+        let holdings: Vec<(&str, &str)> = portfolio.iter().map(|amount| (amount.token.symbol.as_str(), amount.amount.as_str())).collect();
+        assert_eq!(holdings, vec![("SOL", "1250000000"), ("USDC", "2000000"), ("BONK", "4200000")]);
+        assert_eq!(portfolio[2].token.name, "Unknown token");
+    }
+
+    #[test]
+    fn test_portfolio_filters_dust_and_spam() {
+        let mut frozen = account("Honeypot11111111111111111111111111111111111", 1_000_000_000, 6);
+        frozen.frozen = true;
+        let accounts = vec![
+            account(USDC, 0, 6),
+            frozen,
+            account("Nft1111111111111111111111111111111111111111", 1, 0),
+            account("Dust111111111111111111111111111111111111111", 1, 9),
+            account("Scam111111111111111111111111111111111111111", 1_000_000, 6),
+        ];
+
+        let filter = PortfolioFilter::default().with_denylist(["Scam111111111111111111111111111111111111111"]);
+        let portfolio = token_portfolio(0, &accounts, &token_list(), &filter, &SpamClassifier::new());
+        assert!(portfolio.is_empty());
+
+        let filter = PortfolioFilter { include_empty: true, ..PortfolioFilter::default() };
+        let portfolio = token_portfolio(0, &accounts[..1], &token_list(), &filter, &SpamClassifier::new());
+        let symbols: Vec<&str> = portfolio.iter().map(|amount| amount.token.symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["SOL", "USDC"]);
+
+        // The frozen airdrop is spam, but can be shown on request
+        let filter = PortfolioFilter { include_spam: true, ..PortfolioFilter::default() };
+        let portfolio = token_portfolio(0, &accounts[1..2], &token_list(), &filter, &SpamClassifier::new());
+        assert_eq!(portfolio.len(), 1);
+    }
+}
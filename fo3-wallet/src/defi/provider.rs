@@ -1,9 +1,27 @@
 //! DeFi provider
 
+use ethers::prelude::U256;
+
 use crate::error::{Error, Result};
 use crate::crypto::keys::KeyType;
+use crate::transaction::EthereumProvider;
 use crate::transaction::provider::ProviderConfig;
-use super::types::{Protocol, Token, TokenAmount, SwapRequest, SwapResult, LendingRequest, LendingResult, StakingRequest, StakingResult, DeFiProvider};
+use crate::time::unix_timestamp;
+use super::fees::PlatformFeeConfig;
+use super::swap::simulated_quote;
+use super::types::{Protocol, Token, TokenAmount, SwapQuote, SwapRequest, SwapResult, LendingRequest, LendingResult, StakingRequest, StakingResult, DeFiProvider};
+
+/// Depth of the simulated EVM pools on each side, in USD
+const EVM_POOL_DEPTH_USD: f64 = 50_000_000.0;
+
+/// Depth of the simulated Solana pools on each side, in USD
+const SOLANA_POOL_DEPTH_USD: f64 = 10_000_000.0;
+
+/// Network fee of an EVM DeFi transaction, in ETH
+const EVM_NETWORK_FEE: &str = "0.001";
+
+/// Network fee of a Solana DeFi transaction, in SOL
+const SOLANA_NETWORK_FEE: &str = "0.000005";
 
 /// DeFi provider factory
 pub struct DeFiProviderFactory;
@@ -11,18 +29,40 @@ pub struct DeFiProviderFactory;
 impl DeFiProviderFactory {
     /// Create a new DeFi provider
     pub fn create_provider(key_type: KeyType, config: ProviderConfig) -> Result<Box<dyn DeFiProvider>> {
+        Self::create_provider_with_platform_fee(key_type, config, None)
+    }
+
+    /// Create a new DeFi provider that charges a platform fee on swaps
+    pub fn create_provider_with_platform_fee(
+        key_type: KeyType,
+        config: ProviderConfig,
+        platform_fee: Option<PlatformFeeConfig>,
+    ) -> Result<Box<dyn DeFiProvider>> {
+        if let Some(platform_fee) = &platform_fee {
+            platform_fee.validate()?;
+        }
+
         match key_type {
             KeyType::Ethereum => {
-                let provider = EthereumDeFiProvider::new(config)?;
+                let provider = EthereumDeFiProvider::new(config)?.with_platform_fee(platform_fee);
                 Ok(Box::new(provider))
             }
             KeyType::Solana => {
-                let provider = SolanaDeFiProvider::new(config)?;
+                let provider = SolanaDeFiProvider::new(config)?.with_platform_fee(platform_fee);
                 Ok(Box::new(provider))
             }
             KeyType::Bitcoin => {
                 return Err(Error::DeFi("Bitcoin does not support DeFi operations".to_string()));
             }
+            KeyType::Ton => {
+                return Err(Error::DeFi("TON does not support DeFi operations".to_string()));
+            }
+            KeyType::Cosmos => {
+                return Err(Error::DeFi("Cosmos chains do not support DeFi operations".to_string()));
+            }
+            KeyType::Xrp => {
+                return Err(Error::DeFi("The XRP Ledger does not support DeFi operations".to_string()));
+            }
         }
     }
 }
@@ -31,6 +71,8 @@ impl DeFiProviderFactory {
 pub struct EthereumDeFiProvider {
     /// Provider configuration
     config: ProviderConfig,
+    /// Platform fee charged on swaps
+    platform_fee: Option<PlatformFeeConfig>,
 }
 
 impl EthereumDeFiProvider {
@@ -38,8 +80,15 @@ impl EthereumDeFiProvider {
     pub fn new(config: ProviderConfig) -> Result<Self> {
         Ok(Self {
             config,
+            platform_fee: None,
         })
     }
+
+    /// Charge a platform fee on swaps
+    pub fn with_platform_fee(mut self, platform_fee: Option<PlatformFeeConfig>) -> Self {
+        self.platform_fee = platform_fee;
+        self
+    }
 }
 
 impl DeFiProvider for EthereumDeFiProvider {
@@ -118,38 +167,43 @@ impl DeFiProvider for EthereumDeFiProvider {
         Ok(price)
     }
 
-    fn get_swap_quote(&self, request: &SwapRequest) -> Result<TokenAmount> {
+    fn get_allowance(&self, token: &Token, owner: &str, spender: &str) -> Result<u128> {
+        let provider = EthereumProvider::new(self.config.clone())?;
+        let allowance = provider.get_allowances(owner, spender, &[&token.address])?
+            .pop()
+            .flatten()
+            .ok_or_else(|| Error::Provider(format!("Failed to read the {} allowance of {}", token.symbol, owner)))?;
+
+        Ok(allowance.min(U256::from(u128::MAX)).as_u128())
+    }
+
+    fn quote_swap(&self, request: &SwapRequest) -> Result<SwapQuote> {
         // In a real implementation, we would call the protocol's router
         // This is a simplified implementation
 
         let from_price = self.get_token_price(&request.from.token)?;
         let to_price = self.get_token_price(&request.to)?;
 
-        let from_amount = request.from.amount.parse::<f64>().unwrap_or(0.0);
-        let from_decimals = request.from.token.decimals;
-        let from_value = from_amount / 10f64.powi(from_decimals as i32) * from_price;
-
-        let to_decimals = request.to.decimals;
-        let to_amount = from_value / to_price * 10f64.powi(to_decimals as i32);
-
-        Ok(TokenAmount {
-            token: request.to.clone(),
-            amount: to_amount.to_string(),
-        })
+        simulated_quote(request, from_price, to_price, EVM_POOL_DEPTH_USD, self.platform_fee.as_ref(), unix_timestamp()?)
     }
 
     fn execute_swap(&self, request: &SwapRequest) -> Result<SwapResult> {
         // In a real implementation, we would call the protocol's router
         // This is a simplified implementation
 
-        let to_amount = self.get_swap_quote(request)?;
+        let quote = self.quote_swap(request)?;
+        let platform_fee = match &self.platform_fee {
+            Some(platform_fee) => platform_fee.fee_transfer(&request.from)?,
+            None => None,
+        };
 
         Ok(SwapResult {
             from: request.from.clone(),
-            to: to_amount,
+            to: quote.to,
+            platform_fee,
             transaction_hash: format!("0x{}", hex::encode(&[0u8; 32])),
             protocol: request.protocol.clone(),
-            fee: "0.001".to_string(),
+            fee: EVM_NETWORK_FEE.to_string(),
         })
     }
 
@@ -161,7 +215,7 @@ impl DeFiProvider for EthereumDeFiProvider {
             action: request.action.clone(),
             transaction_hash: format!("0x{}", hex::encode(&[0u8; 32])),
             protocol: request.protocol.clone(),
-            fee: "0.001".to_string(),
+            fee: EVM_NETWORK_FEE.to_string(),
         })
     }
 
@@ -170,19 +224,7 @@ impl DeFiProvider for EthereumDeFiProvider {
         // This is a simplified implementation
 
         let rewards = match request.action {
-            super::types::StakingAction::ClaimRewards => {
-                Some(TokenAmount {
-                    token: Token {
-                        name: "Ethereum".to_string(),
-                        symbol: "ETH".to_string(),
-                        decimals: 18,
-                        address: "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE".to_string(),
-                        key_type: KeyType::Ethereum,
-                        logo_url: None,
-                    },
-                    amount: "1000000000000000000".to_string(), // 1 ETH
-                })
-            }
+            super::types::StakingAction::ClaimRewards => Some(self.get_pending_rewards(&request.protocol, "")?),
             _ => None,
         };
 
@@ -190,16 +232,37 @@ impl DeFiProvider for EthereumDeFiProvider {
             action: request.action.clone(),
             transaction_hash: format!("0x{}", hex::encode(&[0u8; 32])),
             protocol: request.protocol.clone(),
-            fee: "0.001".to_string(),
+            fee: EVM_NETWORK_FEE.to_string(),
             rewards,
         })
     }
+
+    fn get_pending_rewards(&self, _protocol: &Protocol, _owner: &str) -> Result<TokenAmount> {
+        // In a real implementation, we would read the protocol's reward accounting
+        Ok(TokenAmount {
+            token: Token {
+                name: "Ethereum".to_string(),
+                symbol: "ETH".to_string(),
+                decimals: 18,
+                address: "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE".to_string(),
+                key_type: KeyType::Ethereum,
+                logo_url: None,
+            },
+            amount: "1000000000000000000".to_string(), // 1 ETH
+        })
+    }
+
+    fn estimate_network_fee(&self) -> Result<String> {
+        Ok(EVM_NETWORK_FEE.to_string())
+    }
 }
 
 /// Solana DeFi provider
 pub struct SolanaDeFiProvider {
     /// Provider configuration
     config: ProviderConfig,
+    /// Platform fee charged on swaps
+    platform_fee: Option<PlatformFeeConfig>,
 }
 
 impl SolanaDeFiProvider {
@@ -207,8 +270,15 @@ impl SolanaDeFiProvider {
     pub fn new(config: ProviderConfig) -> Result<Self> {
         Ok(Self {
             config,
+            platform_fee: None,
         })
     }
+
+    /// Charge a platform fee on swaps
+    pub fn with_platform_fee(mut self, platform_fee: Option<PlatformFeeConfig>) -> Self {
+        self.platform_fee = platform_fee;
+        self
+    }
 }
 
 impl DeFiProvider for SolanaDeFiProvider {
@@ -275,38 +345,33 @@ impl DeFiProvider for SolanaDeFiProvider {
         Ok(price)
     }
 
-    fn get_swap_quote(&self, request: &SwapRequest) -> Result<TokenAmount> {
+    fn quote_swap(&self, request: &SwapRequest) -> Result<SwapQuote> {
         // In a real implementation, we would call the protocol's router
         // This is a simplified implementation
 
         let from_price = self.get_token_price(&request.from.token)?;
         let to_price = self.get_token_price(&request.to)?;
 
-        let from_amount = request.from.amount.parse::<f64>().unwrap_or(0.0);
-        let from_decimals = request.from.token.decimals;
-        let from_value = from_amount / 10f64.powi(from_decimals as i32) * from_price;
-
-        let to_decimals = request.to.decimals;
-        let to_amount = from_value / to_price * 10f64.powi(to_decimals as i32);
-
-        Ok(TokenAmount {
-            token: request.to.clone(),
-            amount: to_amount.to_string(),
-        })
+        simulated_quote(request, from_price, to_price, SOLANA_POOL_DEPTH_USD, self.platform_fee.as_ref(), unix_timestamp()?)
     }
 
     fn execute_swap(&self, request: &SwapRequest) -> Result<SwapResult> {
         // In a real implementation, we would call the protocol's router
         // This is a simplified implementation
 
-        let to_amount = self.get_swap_quote(request)?;
+        let quote = self.quote_swap(request)?;
+        let platform_fee = match &self.platform_fee {
+            Some(platform_fee) => platform_fee.fee_transfer(&request.from)?,
+            None => None,
+        };
 
         Ok(SwapResult {
             from: request.from.clone(),
-            to: to_amount,
+            to: quote.to,
+            platform_fee,
             transaction_hash: bs58::encode(&[0u8; 32]).into_string(),
             protocol: request.protocol.clone(),
-            fee: "0.000005".to_string(),
+            fee: SOLANA_NETWORK_FEE.to_string(),
         })
     }
 
@@ -318,7 +383,7 @@ impl DeFiProvider for SolanaDeFiProvider {
             action: request.action.clone(),
             transaction_hash: bs58::encode(&[0u8; 32]).into_string(),
             protocol: request.protocol.clone(),
-            fee: "0.000005".to_string(),
+            fee: SOLANA_NETWORK_FEE.to_string(),
         })
     }
 
@@ -327,19 +392,7 @@ impl DeFiProvider for SolanaDeFiProvider {
         // This is a simplified implementation
 
         let rewards = match request.action {
-            super::types::StakingAction::ClaimRewards => {
-                Some(TokenAmount {
-                    token: Token {
-                        name: "Solana".to_string(),
-                        symbol: "SOL".to_string(),
-                        decimals: 9,
-                        address: "So11111111111111111111111111111111111111112".to_string(),
-                        key_type: KeyType::Solana,
-                        logo_url: None,
-                    },
-                    amount: "1000000000".to_string(), // 1 SOL
-                })
-            }
+            super::types::StakingAction::ClaimRewards => Some(self.get_pending_rewards(&request.protocol, "")?),
             _ => None,
         };
 
@@ -347,8 +400,27 @@ impl DeFiProvider for SolanaDeFiProvider {
             action: request.action.clone(),
             transaction_hash: bs58::encode(&[0u8; 32]).into_string(),
             protocol: request.protocol.clone(),
-            fee: "0.000005".to_string(),
+            fee: SOLANA_NETWORK_FEE.to_string(),
             rewards,
         })
     }
+
+    fn get_pending_rewards(&self, _protocol: &Protocol, _owner: &str) -> Result<TokenAmount> {
+        // In a real implementation, we would read the protocol's reward accounting
+        Ok(TokenAmount {
+            token: Token {
+                name: "Solana".to_string(),
+                symbol: "SOL".to_string(),
+                decimals: 9,
+                address: "So11111111111111111111111111111111111111112".to_string(),
+                key_type: KeyType::Solana,
+                logo_url: None,
+            },
+            amount: "1000000000".to_string(), // 1 SOL
+        })
+    }
+
+    fn estimate_network_fee(&self) -> Result<String> {
+        Ok(SOLANA_NETWORK_FEE.to_string())
+    }
 }
@@ -0,0 +1,306 @@
+//! Spam token classification
+//!
+//! Airdropped junk tokens and phishing NFTs clutter balances and lure users
+//! to scam sites. [`SpamClassifier`] scores tokens on heuristics and known
+//! scam lists, and per-user overrides let users show or hide tokens the
+//! heuristics get wrong.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+use serde::{Serialize, Deserialize};
+
+use crate::crypto::keys::KeyType;
+use crate::error::{Error, Result};
+use super::types::{Token, TokenAmount};
+
+/// Score at which a token is classified as spam
+const SPAM_THRESHOLD: u32 = 3;
+
+/// Words luring users to phishing sites from token names
+const PHISHING_WORDS: [&str; 8] = ["claim", "visit", "reward", "airdrop", "voucher", "bonus", "free", "redeem"];
+
+/// Reason a token looks like spam
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpamReason {
+    /// The token is on a known scam list
+    KnownScam,
+    /// The user hid the token
+    UserBlocked,
+    /// The token is missing from the verified token list
+    Unverified,
+    /// The name or symbol carries a URL or lure
+    PhishingMetadata,
+    /// The symbol copies a verified token's symbol
+    Impersonation,
+    /// The holder cannot move the token
+    Honeypot,
+    /// The token has no market to sell into
+    NoLiquidity,
+}
+
+impl SpamReason {
+    /// Score contributed by the reason
+    fn score(&self) -> u32 {
+        match self {
+            Self::KnownScam | Self::UserBlocked => SPAM_THRESHOLD,
+            Self::PhishingMetadata | Self::Impersonation => 3,
+            Self::Honeypot => 2,
+            Self::Unverified | Self::NoLiquidity => 1,
+        }
+    }
+}
+
+/// On-chain signals about a held token
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenSignals {
+    /// The token is on the verified token list
+    pub verified: bool,
+    /// Every account holding the token is frozen
+    pub frozen: bool,
+    /// Liquidity available for the token in fiat, if known
+    pub liquidity: Option<f64>,
+}
+
+/// Outcome of classifying a token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpamVerdict {
+    /// Whether the token should be hidden by default
+    pub is_spam: bool,
+    /// Reasons found, empty for clean or user-allowed tokens
+    pub reasons: Vec<SpamReason>,
+}
+
+/// A user's decision about a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpamOverride {
+    /// Always show the token
+    Allow,
+    /// Always hide the token
+    Block,
+}
+
+/// A user's override of a token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenOverride {
+    /// Blockchain type
+    pub key_type: KeyType,
+    /// Token address
+    pub address: String,
+    /// The decision
+    pub action: SpamOverride,
+}
+
+/// Classifies tokens as spam
+#[derive(Debug, Default)]
+pub struct SpamClassifier {
+    /// Known scam token addresses, normalized
+    scam_list: RwLock<HashSet<(KeyType, String)>>,
+    /// Overrides by user
+    overrides: RwLock<HashMap<String, HashMap<(KeyType, String), SpamOverride>>>,
+}
+
+/// Normalize a token address for lookups
+///
+/// EVM addresses are case-insensitive; base58 addresses are not.
+fn normalize(key_type: KeyType, address: &str) -> (KeyType, String) {
+    match key_type {
+        KeyType::Ethereum => (key_type, address.to_ascii_lowercase()),
+        _ => (key_type, address.to_string()),
+    }
+}
+
+impl SpamClassifier {
+    /// Create a classifier without a scam list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add addresses to the known scam list
+    pub fn add_known_scams<'a>(&self, key_type: KeyType, addresses: impl IntoIterator<Item = &'a str>) {
+        let mut scam_list = self.scam_list.write().unwrap();
+        scam_list.extend(addresses.into_iter().map(|address| normalize(key_type, address)));
+    }
+
+    /// Load a scam list from JSON: an array of `{"key_type": ..., "address": ...}`
+    pub fn load_known_scams(&self, json: &str) -> Result<usize> {
+        #[derive(Deserialize)]
+        struct Entry {
+            key_type: KeyType,
+            address: String,
+        }
+
+        let entries: Vec<Entry> = serde_json::from_str(json)
+            .map_err(|e| Error::Serialization(format!("Invalid scam list: {}", e)))?;
+        let count = entries.len();
+        for entry in entries {
+            self.add_known_scams(entry.key_type, [entry.address.as_str()]);
+        }
+        Ok(count)
+    }
+
+    /// Set a user's override for a token
+    pub fn set_override(&self, user: &str, key_type: KeyType, address: &str, action: SpamOverride) {
+        self.overrides.write().unwrap()
+            .entry(user.to_string())
+            .or_default()
+            .insert(normalize(key_type, address), action);
+    }
+
+    /// Remove a user's override for a token, returning whether there was one
+    pub fn remove_override(&self, user: &str, key_type: KeyType, address: &str) -> bool {
+        self.overrides.write().unwrap()
+            .get_mut(user)
+            .is_some_and(|overrides| overrides.remove(&normalize(key_type, address)).is_some())
+    }
+
+    /// Get a user's overrides
+    pub fn overrides(&self, user: &str) -> Vec<TokenOverride> {
+        let overrides = self.overrides.read().unwrap();
+        let mut overrides: Vec<TokenOverride> = overrides.get(user)
+            .into_iter()
+            .flatten()
+            .map(|((key_type, address), action)| TokenOverride { key_type: *key_type, address: address.clone(), action: *action })
+            .collect();
+        overrides.sort_by(|a, b| a.address.cmp(&b.address));
+        overrides
+    }
+
+    /// Classify a token for a user
+    ///
+    /// `token_list` holds the verified tokens; an unverified token reusing a
+    /// verified symbol is treated as an impersonation.
+    pub fn classify(&self, user: Option<&str>, token: &Token, signals: &TokenSignals, token_list: &[Token]) -> SpamVerdict {
+        let key = normalize(token.key_type, &token.address);
+
+        let user_override = user.and_then(|user| {
+            self.overrides.read().unwrap().get(user).and_then(|overrides| overrides.get(&key).copied())
+        });
+        match user_override {
+            Some(SpamOverride::Allow) => return SpamVerdict { is_spam: false, reasons: vec![] },
+            Some(SpamOverride::Block) => return SpamVerdict { is_spam: true, reasons: vec![SpamReason::UserBlocked] },
+            None => {}
+        }
+
+        let mut reasons = Vec::new();
+        if self.scam_list.read().unwrap().contains(&key) {
+            reasons.push(SpamReason::KnownScam);
+        }
+
+        if !signals.verified {
+            reasons.push(SpamReason::Unverified);
+            if has_phishing_metadata(token) {
+                reasons.push(SpamReason::PhishingMetadata);
+            }
+            let impersonates = token_list.iter().any(|listed| {
+                listed.key_type == token.key_type && listed.symbol.eq_ignore_ascii_case(token.symbol.trim())
+            });
+            if impersonates {
+                reasons.push(SpamReason::Impersonation);
+            }
+            if signals.frozen {
+                reasons.push(SpamReason::Honeypot);
+            }
+            if matches!(signals.liquidity, Some(liquidity) if liquidity <= 0.0) {
+                reasons.push(SpamReason::NoLiquidity);
+            }
+        }
+
+        let score: u32 = reasons.iter().map(SpamReason::score).sum();
+        SpamVerdict { is_spam: score >= SPAM_THRESHOLD, reasons }
+    }
+
+    /// Drop spam from balances, treating tokens on `token_list` as verified
+    pub fn filter_balances(&self, user: Option<&str>, balances: Vec<TokenAmount>, token_list: &[Token]) -> Vec<TokenAmount> {
+        balances.into_iter()
+            .filter(|balance| {
+                let verified = token_list.iter().any(|listed| {
+                    normalize(listed.key_type, &listed.address) == normalize(balance.token.key_type, &balance.token.address)
+                });
+                let signals = TokenSignals { verified, ..TokenSignals::default() };
+                !self.classify(user, &balance.token, &signals, token_list).is_spam
+            })
+            .collect()
+    }
+}
+
+/// Whether a token's name or symbol carries a URL or a lure
+fn has_phishing_metadata(token: &Token) -> bool {
+    [&token.name, &token.symbol].iter().any(|text| {
+        let text = text.to_lowercase();
+        let has_url = text.contains("://")
+            || text.contains("www.")
+            || [".com", ".io", ".org", ".net", ".xyz", ".app", ".gift"].iter().any(|tld| text.contains(tld));
+        has_url || PHISHING_WORDS.iter().any(|word| text.split(|c: char| !c.is_alphanumeric()).any(|part| part == *word))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(symbol: &str, name: &str, address: &str) -> Token {
+        Token {
+            name: name.to_string(),
+            symbol: symbol.to_string(),
+            decimals: 6,
+            address: address.to_string(),
+            key_type: KeyType::Ethereum,
+            logo_url: None,
+        }
+    }
+
+    fn token_list() -> Vec<Token> {
+        vec![token("USDC", "USD Coin", "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48")]
+    }
+
+    #[test]
+    fn test_heuristics() {
+        let classifier = SpamClassifier::new();
+        let unverified = TokenSignals::default();
+
+        let verified = classifier.classify(None, &token_list()[0], &TokenSignals { verified: true, ..unverified }, &token_list());
+        assert!(!verified.is_spam && verified.reasons.is_empty());
+
+        // Unverified alone isn't enough
+        let unknown = classifier.classify(None, &token("ABC", "Some Token", "0x01"), &unverified, &token_list());
+        assert_eq!(unknown, SpamVerdict { is_spam: false, reasons: vec![SpamReason::Unverified] });
+
+        let phishing = classifier.classify(None, &token("VISIT", "Claim at usdc-reward.xyz", "0x02"), &unverified, &token_list());
+        assert!(phishing.is_spam && phishing.reasons.contains(&SpamReason::PhishingMetadata));
+
+        let fake = classifier.classify(None, &token("usdc", "USD Coin", "0x03"), &unverified, &token_list());
+        assert!(fake.is_spam && fake.reasons.contains(&SpamReason::Impersonation));
+
+        let honeypot = classifier.classify(None, &token("XYZ", "Token", "0x04"), &TokenSignals { frozen: true, ..unverified }, &token_list());
+        assert!(honeypot.is_spam);
+
+        let illiquid = classifier.classify(None, &token("XYZ", "Token", "0x05"), &TokenSignals { liquidity: Some(0.0), ..unverified }, &token_list());
+        assert!(!illiquid.is_spam);
+    }
+
+    #[test]
+    fn test_scam_list_and_overrides() {
+        let classifier = SpamClassifier::new();
+        let count = classifier.load_known_scams(r#"[{"key_type": "Ethereum", "address": "0xAbC0000000000000000000000000000000000001"}]"#).unwrap();
+        assert_eq!(count, 1);
+
+        let scam = token("ABC", "Token", "0xabc0000000000000000000000000000000000001");
+        let signals = TokenSignals { verified: true, ..TokenSignals::default() };
+        assert!(classifier.classify(Some("alice"), &scam, &signals, &[]).is_spam);
+
+        classifier.set_override("alice", KeyType::Ethereum, &scam.address, SpamOverride::Allow);
+        assert!(!classifier.classify(Some("alice"), &scam, &signals, &[]).is_spam);
+        assert!(classifier.classify(Some("bob"), &scam, &signals, &[]).is_spam);
+
+        let listed = &token_list()[0];
+        classifier.set_override("bob", KeyType::Ethereum, &listed.address, SpamOverride::Block);
+        assert_eq!(classifier.classify(Some("bob"), listed, &signals, &[]).reasons, vec![SpamReason::UserBlocked]);
+        assert_eq!(classifier.overrides("bob").len(), 1);
+
+        assert!(classifier.remove_override("bob", KeyType::Ethereum, &listed.address));
+        assert!(!classifier.classify(Some("bob"), listed, &signals, &[]).is_spam);
+    }
+}
@@ -2,12 +2,13 @@
 
 use crate::error::Result;
 use crate::crypto::keys::KeyType;
-use super::types::{Protocol, StakingRequest, StakingResult, StakingAction};
+use super::types::{DryRunResult, Protocol, StakingRequest, StakingResult, StakingAction};
+use super::swap::ensure_not_dry_run;
 use super::provider::DeFiProviderFactory;
 use crate::transaction::provider::ProviderConfig;
 
-/// Execute staking action
-pub fn execute_staking(request: &StakingRequest, config: &ProviderConfig) -> Result<StakingResult> {
+/// Get the chain a staking action runs on
+fn staking_key_type(request: &StakingRequest) -> Result<KeyType> {
     let key_type = match &request.action {
         StakingAction::Stake(token_amount) => token_amount.token.key_type,
         StakingAction::Unstake(token_amount) => token_amount.token.key_type,
@@ -20,12 +21,25 @@ pub fn execute_staking(request: &StakingRequest, config: &ProviderConfig) -> Res
             }
         }
     };
-    
-    let provider = DeFiProviderFactory::create_provider(key_type, config.clone())?;
+
+    Ok(key_type)
+}
+
+/// Execute staking action
+pub fn execute_staking(request: &StakingRequest, config: &ProviderConfig) -> Result<StakingResult> {
+    ensure_not_dry_run(request.dry_run)?;
+    let provider = DeFiProviderFactory::create_provider(staking_key_type(request)?, config.clone())?;
     
     provider.execute_staking(request)
 }
 
+/// Preview a staking action without signing or broadcasting
+pub fn dry_run_staking(request: &StakingRequest, config: &ProviderConfig) -> Result<DryRunResult> {
+    let provider = DeFiProviderFactory::create_provider(staking_key_type(request)?, config.clone())?;
+
+    provider.dry_run_staking(request)
+}
+
 /// Get supported staking protocols
 pub fn get_supported_staking_protocols(key_type: KeyType, config: &ProviderConfig) -> Result<Vec<Protocol>> {
     let provider = DeFiProviderFactory::create_provider(key_type, config.clone())?;
@@ -1,13 +1,26 @@
 //! Swap functionality
 
-use crate::error::Result;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::error::{Error, Result};
 use crate::crypto::keys::KeyType;
-use super::types::{Protocol, Token, TokenAmount, SwapRequest, SwapResult};
+use super::types::{DryRunResult, Protocol, RouteLeg, Token, TokenAmount, SwapFees, SwapQuote, SwapRequest, SwapResult, SANDWICH_PRICE_IMPACT, SWAP_QUOTE_TTL};
+use super::fees::{bps_of, FeeLedger, FeeLedgerEntry, PlatformFeeConfig};
 use super::provider::DeFiProviderFactory;
 use crate::transaction::provider::ProviderConfig;
 
+/// Refuse to execute a request meant as a dry run
+pub(crate) fn ensure_not_dry_run(dry_run: bool) -> Result<()> {
+    if dry_run {
+        return Err(Error::InvalidInput("Dry-run requests are previewed, not executed".to_string()));
+    }
+    Ok(())
+}
+
 /// Swap tokens
 pub fn swap_tokens(request: &SwapRequest, config: &ProviderConfig) -> Result<SwapResult> {
+    ensure_not_dry_run(request.dry_run)?;
     let key_type = request.from.token.key_type;
     let provider = DeFiProviderFactory::create_provider(key_type, config.clone())?;
     
@@ -22,6 +35,113 @@ pub fn get_swap_quote(request: &SwapRequest, config: &ProviderConfig) -> Result<
     provider.get_swap_quote(request)
 }
 
+/// Get a detailed swap quote with price impact, route and fees
+pub fn quote_swap(request: &SwapRequest, config: &ProviderConfig) -> Result<SwapQuote> {
+    let key_type = request.from.token.key_type;
+    let provider = DeFiProviderFactory::create_provider(key_type, config.clone())?;
+
+    provider.quote_swap(request)
+}
+
+/// Get a detailed swap quote including the deployment's platform fee
+pub fn quote_swap_with_platform_fee(request: &SwapRequest, config: &ProviderConfig, platform_fee: &PlatformFeeConfig) -> Result<SwapQuote> {
+    let key_type = request.from.token.key_type;
+    let provider = DeFiProviderFactory::create_provider_with_platform_fee(key_type, config.clone(), Some(platform_fee.clone()))?;
+
+    provider.quote_swap(request)
+}
+
+/// Swap tokens, charging the deployment's platform fee and recording it in the ledger
+pub fn swap_tokens_with_platform_fee(
+    request: &SwapRequest,
+    config: &ProviderConfig,
+    platform_fee: &PlatformFeeConfig,
+    ledger: &dyn FeeLedger,
+) -> Result<SwapResult> {
+    ensure_not_dry_run(request.dry_run)?;
+    let key_type = request.from.token.key_type;
+    let provider = DeFiProviderFactory::create_provider_with_platform_fee(key_type, config.clone(), Some(platform_fee.clone()))?;
+
+    let result = provider.execute_swap(request)?;
+    if let Some(entry) = FeeLedgerEntry::from_swap(&result, crate::time::unix_timestamp()?) {
+        ledger.record(entry)?;
+    }
+
+    Ok(result)
+}
+
+/// Quote a swap and preview its outcome without signing or broadcasting
+pub fn dry_run_swap(request: &SwapRequest, config: &ProviderConfig, platform_fee: Option<&PlatformFeeConfig>) -> Result<DryRunResult> {
+    let key_type = request.from.token.key_type;
+    let provider = DeFiProviderFactory::create_provider_with_platform_fee(key_type, config.clone(), platform_fee.cloned())?;
+
+    provider.dry_run_swap(request)
+}
+
+/// Execute a swap against a previously obtained quote, re-quoting if it has expired
+///
+/// The quote must come from the server (see [`SwapQuoteBook`]): its minimum
+/// output and expiry are trusted as given. Any platform fee is recorded in
+/// the ledger.
+pub fn execute_quoted_swap(
+    request: &SwapRequest,
+    quote: &SwapQuote,
+    config: &ProviderConfig,
+    platform_fee: Option<&PlatformFeeConfig>,
+    ledger: &dyn FeeLedger,
+) -> Result<SwapResult> {
+    ensure_not_dry_run(request.dry_run)?;
+    let key_type = request.from.token.key_type;
+    let provider = DeFiProviderFactory::create_provider_with_platform_fee(key_type, config.clone(), platform_fee.cloned())?;
+
+    let result = provider.execute_quoted_swap(request, quote)?;
+    if let Some(entry) = FeeLedgerEntry::from_swap(&result, crate::time::unix_timestamp()?) {
+        ledger.record(entry)?;
+    }
+
+    Ok(result)
+}
+
+/// Most quotes a [`SwapQuoteBook`] holds at once
+pub const MAX_OPEN_SWAP_QUOTES: usize = 100_000;
+
+/// Swap quotes issued by the server, kept until executed or stale
+///
+/// Swaps execute against a quote looked up by ID rather than one sent back
+/// by the client, so its expiry and minimum output cannot be forged. Each
+/// quote executes at most once.
+#[derive(Debug, Default)]
+pub struct SwapQuoteBook {
+    quotes: RwLock<HashMap<String, SwapQuote>>,
+}
+
+impl SwapQuoteBook {
+    /// Create an empty quote book
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a quote, returning it with its ID set
+    pub fn issue(&self, mut quote: SwapQuote, now: u64) -> Result<SwapQuote> {
+        let mut quotes = self.quotes.write().unwrap();
+        // An expired quote can still be re-quoted for one more TTL, then it is dropped
+        quotes.retain(|_, quote| now < quote.expires_at.saturating_add(SWAP_QUOTE_TTL));
+        if quotes.len() >= MAX_OPEN_SWAP_QUOTES {
+            return Err(Error::DeFi("Too many open swap quotes; retry shortly".to_string()));
+        }
+
+        let id = format!("quote_{}", hex::encode(rand::random::<[u8; 12]>()));
+        quote.id = Some(id.clone());
+        quotes.insert(id, quote.clone());
+        Ok(quote)
+    }
+
+    /// Remove and return a quote for execution
+    pub fn take(&self, id: &str) -> Option<SwapQuote> {
+        self.quotes.write().unwrap().remove(id)
+    }
+}
+
 /// Get supported tokens
 pub fn get_supported_tokens(key_type: KeyType, config: &ProviderConfig) -> Result<Vec<Token>> {
     let provider = DeFiProviderFactory::create_provider(key_type, config.clone())?;
@@ -29,9 +149,95 @@ pub fn get_supported_tokens(key_type: KeyType, config: &ProviderConfig) -> Resul
     provider.get_supported_tokens()
 }
 
+/// Get the balances of all supported tokens held by an address
+pub fn get_token_balances(key_type: KeyType, address: &str, config: &ProviderConfig) -> Result<Vec<TokenAmount>> {
+    let provider = DeFiProviderFactory::create_provider(key_type, config.clone())?;
+
+    provider.get_supported_tokens()?
+        .iter()
+        .map(|token| provider.get_token_balance(token, address))
+        .collect()
+}
+
 /// Get supported protocols
 pub fn get_supported_protocols(key_type: KeyType, config: &ProviderConfig) -> Result<Vec<Protocol>> {
     let provider = DeFiProviderFactory::create_provider(key_type, config.clone())?;
     
     Ok(provider.get_supported_protocols())
 }
+
+/// Quote a swap against a simulated constant-product pool
+///
+/// Used by the providers until they read pool reserves on-chain: the pool
+/// holds `pool_depth` (in fiat) on each side at oracle prices, so the price
+/// impact grows with the trade size. The platform fee is taken from the
+/// input before it reaches the pool.
+pub(crate) fn simulated_quote(
+    request: &SwapRequest,
+    from_price: f64,
+    to_price: f64,
+    pool_depth: f64,
+    platform_fee: Option<&PlatformFeeConfig>,
+    now: u64,
+) -> Result<SwapQuote> {
+    if !(0.0..=50.0).contains(&request.slippage) {
+        return Err(Error::InvalidInput(format!("Slippage must be between 0 and 50%: {}", request.slippage)));
+    }
+    if from_price <= 0.0 || to_price <= 0.0 {
+        return Err(Error::DeFi(format!("No price for {}/{}", request.from.token.symbol, request.to.symbol)));
+    }
+
+    let amount_in = request.from.amount.parse::<u128>()
+        .map_err(|_| Error::InvalidInput(format!("Invalid amount: {}", request.from.amount)))?;
+    let platform_fee = match platform_fee {
+        Some(fee) => fee.fee_amount(request.from.token.key_type, amount_in)?,
+        None => 0,
+    };
+    let pool_in = amount_in.checked_sub(platform_fee)
+        .ok_or_else(|| Error::DeFi("Platform fee exceeds the swap amount".to_string()))?;
+
+    let (lp_bps, protocol_bps) = request.protocol.swap_fee_bps();
+    let lp_fee = bps_of(pool_in, lp_bps)?;
+    let protocol_fee = bps_of(pool_in, protocol_bps)?;
+    let net_in = pool_in.checked_sub(lp_fee + protocol_fee)
+        .ok_or_else(|| Error::DeFi("Swap fees exceed the swap amount".to_string()))?;
+
+    // Constant product: out = reserve_out * in / (reserve_in + in)
+    let value_in = net_in as f64 / 10f64.powi(request.from.token.decimals as i32) * from_price;
+    let price_impact = value_in / (pool_depth + value_in);
+    let value_out = value_in * (1.0 - price_impact);
+    let amount_out = (value_out / to_price * 10f64.powi(request.to.decimals as i32)).floor();
+    let min_amount_out = (amount_out * (1.0 - request.slippage / 100.0)).floor();
+
+    let fee = |amount: u128| TokenAmount {
+        token: request.from.token.clone(),
+        amount: amount.to_string(),
+    };
+
+    Ok(SwapQuote {
+        from: request.from.clone(),
+        to: TokenAmount {
+            token: request.to.clone(),
+            amount: format!("{:.0}", amount_out),
+        },
+        min_amount_out: format!("{:.0}", min_amount_out),
+        price_impact: price_impact * 100.0,
+        route: vec![RouteLeg {
+            protocol: request.protocol.clone(),
+            from: request.from.token.clone(),
+            to: request.to.clone(),
+            portion_bps: 10_000,
+        }],
+        fees: SwapFees {
+            lp_fee: fee(lp_fee),
+            protocol_fee: fee(protocol_fee),
+            platform_fee: fee(platform_fee),
+        },
+        quoted_at: now,
+        expires_at: now + SWAP_QUOTE_TTL,
+        // Only EVM mempools are public and ordered by fee
+        suggest_private_submission: request.from.token.key_type == KeyType::Ethereum && price_impact * 100.0 >= SANDWICH_PRICE_IMPACT,
+        approval: None,
+        id: None,
+    })
+}
@@ -2,10 +2,18 @@
 
 use serde::{Serialize, Deserialize};
 use crate::crypto::keys::KeyType;
-use crate::error::Result;
+use crate::error::{Error, Result};
+use super::fees::PlatformFeeTransfer;
+use super::approval::ApprovalPlan;
+
+/// Address standing for the native token in EVM token lists
+pub const EVM_NATIVE_TOKEN: &str = "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE";
+
+/// Wrapped SOL mint, standing for native SOL in Solana token lists
+pub const SOLANA_NATIVE_TOKEN: &str = "So11111111111111111111111111111111111111112";
 
 /// DeFi protocol
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Protocol {
     /// Uniswap
     Uniswap,
@@ -29,6 +37,30 @@ pub enum Protocol {
     Other(String),
 }
 
+impl Protocol {
+    /// Get the swap fee as (liquidity provider, protocol) basis points
+    pub fn swap_fee_bps(&self) -> (u32, u32) {
+        match self {
+            Self::Uniswap => (30, 0),
+            Self::SushiSwap => (25, 5),
+            Self::PancakeSwap => (17, 8),
+            Self::Raydium => (22, 3),
+            Self::Orca => (25, 5),
+            _ => (0, 0),
+        }
+    }
+
+    /// Get the Ethereum mainnet router that pulls the input token of a swap
+    pub fn router_address(&self) -> Option<&'static str> {
+        match self {
+            Self::Uniswap => Some("0x3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD"),
+            Self::SushiSwap => Some("0xd9e1cE17f2641f24aE83637ab66a2cca9C378B9F"),
+            Self::PancakeSwap => Some("0x13f4EA83D0bd40E75C8222255bc855a974568Dd4"),
+            _ => None,
+        }
+    }
+}
+
 /// Token information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Token {
@@ -55,6 +87,21 @@ pub struct TokenAmount {
     pub amount: String,
 }
 
+impl TokenAmount {
+    /// Format the amount in whole tokens with the symbol, e.g. `1.5 USDC`
+    pub fn format(&self) -> String {
+        let Ok(amount) = self.amount.parse::<u128>() else {
+            return format!("{} {}", self.amount, self.token.symbol);
+        };
+        let unit = 10u128.pow(self.token.decimals as u32);
+        let fraction = format!("{:0width$}", amount % unit, width = self.token.decimals as usize);
+        match fraction.trim_end_matches('0') {
+            "" => format!("{} {}", amount / unit, self.token.symbol),
+            fraction => format!("{}.{} {}", amount / unit, fraction, self.token.symbol),
+        }
+    }
+}
+
 /// Swap request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SwapRequest {
@@ -68,6 +115,9 @@ pub struct SwapRequest {
     pub protocol: Protocol,
     /// Deadline in seconds
     pub deadline: Option<u64>,
+    /// Quote and preview the swap without signing or broadcasting it
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
 /// Swap result
@@ -83,6 +133,95 @@ pub struct SwapResult {
     pub protocol: Protocol,
     /// Fee paid
     pub fee: String,
+    /// Platform fee transferred to the deployment, if any
+    #[serde(default)]
+    pub platform_fee: Option<PlatformFeeTransfer>,
+}
+
+/// Number of seconds a swap quote stays valid
+pub const SWAP_QUOTE_TTL: u64 = 30;
+
+/// Price impact (in percent) above which an EVM swap is worth sandwiching
+pub const SANDWICH_PRICE_IMPACT: f64 = 1.0;
+
+/// One leg of a swap route
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteLeg {
+    /// Protocol executing the leg
+    pub protocol: Protocol,
+    /// Token sold
+    pub from: Token,
+    /// Token bought
+    pub to: Token,
+    /// Share of the input routed through this leg, in basis points
+    pub portion_bps: u32,
+}
+
+/// Fees charged on a swap, denominated in the input token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapFees {
+    /// Fee paid to liquidity providers
+    pub lp_fee: TokenAmount,
+    /// Fee kept by the protocol
+    pub protocol_fee: TokenAmount,
+    /// Fee charged by this deployment
+    pub platform_fee: TokenAmount,
+}
+
+/// Detailed swap quote
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapQuote {
+    /// Input token amount
+    pub from: TokenAmount,
+    /// Expected output after fees and price impact
+    pub to: TokenAmount,
+    /// Minimum output accepted after slippage
+    pub min_amount_out: String,
+    /// Price impact in percentage (e.g., 0.3 for 0.3%)
+    pub price_impact: f64,
+    /// Route the swap takes
+    pub route: Vec<RouteLeg>,
+    /// Fee breakdown
+    pub fees: SwapFees,
+    /// Unix timestamp the quote was made
+    pub quoted_at: u64,
+    /// Unix timestamp after which the quote must not be executed
+    pub expires_at: u64,
+    /// Whether the price impact makes the swap sandwich-prone, so it should go through a private relay
+    #[serde(default)]
+    pub suggest_private_submission: bool,
+    /// Approvals the swap needs first, when the owner's allowance was checked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval: Option<ApprovalPlan>,
+    /// ID to execute the quote by, once issued through a [`SwapQuoteBook`](super::SwapQuoteBook)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+impl SwapQuote {
+    /// Check whether the quote has expired
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Check that the quote was made for a request
+    pub fn matches(&self, request: &SwapRequest) -> Result<()> {
+        if self.from.token.key_type != request.from.token.key_type
+            || self.from.token.address != request.from.token.address
+            || self.from.amount != request.from.amount
+            || self.to.token.address != request.to.address
+            || self.route.first().map(|leg| &leg.protocol) != Some(&request.protocol)
+        {
+            return Err(Error::InvalidInput("Swap quote does not match the request".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Get the minimum output in the smallest unit
+    pub fn min_amount_out(&self) -> Result<u128> {
+        self.min_amount_out.parse::<u128>()
+            .map_err(|_| Error::DeFi(format!("Invalid minimum output: {}", self.min_amount_out)))
+    }
 }
 
 /// Lending request
@@ -105,6 +244,9 @@ pub struct LendingRequest {
     pub action: LendingAction,
     /// Protocol to use
     pub protocol: Protocol,
+    /// Preview the action without signing or broadcasting it
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
 /// Lending result
@@ -138,6 +280,9 @@ pub struct StakingRequest {
     pub action: StakingAction,
     /// Protocol to use
     pub protocol: Protocol,
+    /// Preview the action without signing or broadcasting it
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
 /// Staking result
@@ -155,6 +300,23 @@ pub struct StakingResult {
     pub rewards: Option<TokenAmount>,
 }
 
+/// Expected outcome of a DeFi operation run without signing or broadcasting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DryRunResult {
+    /// Protocol the operation would run on
+    pub protocol: Protocol,
+    /// Tokens that would leave the wallet
+    pub debits: Vec<TokenAmount>,
+    /// Tokens the wallet would receive
+    pub credits: Vec<TokenAmount>,
+    /// Estimated network fee, in the native token
+    pub network_fee: String,
+    /// Quote a swap's outcome is based on, with its fee breakdown
+    pub quote: Option<SwapQuote>,
+    /// What the transaction would do, step by step
+    pub preview: Vec<String>,
+}
+
 /// DeFi provider
 pub trait DeFiProvider {
     /// Get supported protocols
@@ -169,15 +331,142 @@ pub trait DeFiProvider {
     /// Get token price
     fn get_token_price(&self, token: &Token) -> Result<f64>;
 
+    /// Get the allowance `owner` has granted `spender` over a token
+    fn get_allowance(&self, _token: &Token, _owner: &str, _spender: &str) -> Result<u128> {
+        Err(Error::NotSupported("Token allowances are not supported by this provider".to_string()))
+    }
+
     /// Get swap quote
-    fn get_swap_quote(&self, request: &SwapRequest) -> Result<TokenAmount>;
+    fn get_swap_quote(&self, request: &SwapRequest) -> Result<TokenAmount> {
+        Ok(self.quote_swap(request)?.to)
+    }
+
+    /// Get a detailed swap quote with price impact, route and fees
+    fn quote_swap(&self, request: &SwapRequest) -> Result<SwapQuote>;
 
     /// Execute swap
     fn execute_swap(&self, request: &SwapRequest) -> Result<SwapResult>;
 
+    /// Execute a swap against a previously obtained quote
+    ///
+    /// A stale quote is refreshed rather than trusted: the swap only goes
+    /// ahead if the fresh minimum output is still at least the quoted one.
+    /// The executed output is checked against the minimum as well.
+    fn execute_quoted_swap(&self, request: &SwapRequest, quote: &SwapQuote) -> Result<SwapResult> {
+        quote.matches(request)?;
+
+        let min_amount_out = if quote.is_expired(crate::time::unix_timestamp()?) {
+            let fresh = self.quote_swap(request)?;
+            if fresh.min_amount_out()? < quote.min_amount_out()? {
+                return Err(Error::DeFi(format!(
+                    "Quote expired and the price moved: minimum output fell from {} to {}",
+                    quote.min_amount_out, fresh.min_amount_out,
+                )));
+            }
+            fresh.min_amount_out()?
+        } else {
+            quote.min_amount_out()?
+        };
+
+        let result = self.execute_swap(request)?;
+        let amount_out = result.to.amount.parse::<u128>()
+            .map_err(|_| Error::DeFi(format!("Invalid swap output: {}", result.to.amount)))?;
+        if amount_out < min_amount_out {
+            return Err(Error::DeFi(format!("Swap output {} is below the minimum of {}", amount_out, min_amount_out)));
+        }
+
+        Ok(result)
+    }
+
     /// Execute lending action
     fn execute_lending(&self, request: &LendingRequest) -> Result<LendingResult>;
 
     /// Execute staking action
     fn execute_staking(&self, request: &StakingRequest) -> Result<StakingResult>;
+
+    /// Get the staking rewards `owner` could claim from a protocol
+    fn get_pending_rewards(&self, _protocol: &Protocol, _owner: &str) -> Result<TokenAmount> {
+        Err(Error::NotSupported("Pending staking rewards are not supported by this provider".to_string()))
+    }
+
+    /// Estimate the network fee of a DeFi transaction, in the native token
+    fn estimate_network_fee(&self) -> Result<String>;
+
+    /// Fail unless the provider supports a protocol
+    fn ensure_supported(&self, protocol: &Protocol) -> Result<()> {
+        if !self.get_supported_protocols().contains(protocol) {
+            return Err(Error::DeFi(format!("Unsupported protocol: {:?}", protocol)));
+        }
+        Ok(())
+    }
+
+    /// Quote a swap and preview it without signing or broadcasting
+    fn dry_run_swap(&self, request: &SwapRequest) -> Result<DryRunResult> {
+        self.ensure_supported(&request.protocol)?;
+        let quote = self.quote_swap(request)?;
+
+        let min_out = TokenAmount { token: request.to.clone(), amount: quote.min_amount_out.clone() };
+        let mut preview = vec![format!("Swap {} for {} (at least {}) on {:?}", request.from.format(), quote.to.format(), min_out.format(), request.protocol)];
+        if quote.fees.platform_fee.amount != "0" {
+            preview.push(format!("Pay a platform fee of {}", quote.fees.platform_fee.format()));
+        }
+        if quote.suggest_private_submission {
+            preview.push(format!("Submit through a private relay: a {:.2}% price impact invites sandwich attacks", quote.price_impact));
+        }
+
+        Ok(DryRunResult {
+            protocol: request.protocol.clone(),
+            debits: vec![request.from.clone()],
+            credits: vec![quote.to.clone()],
+            network_fee: self.estimate_network_fee()?,
+            quote: Some(quote),
+            preview,
+        })
+    }
+
+    /// Preview a lending action without signing or broadcasting
+    fn dry_run_lending(&self, request: &LendingRequest) -> Result<DryRunResult> {
+        self.ensure_supported(&request.protocol)?;
+        let (verb, amount, outgoing) = match &request.action {
+            LendingAction::Supply(amount) => ("Supply", amount, true),
+            LendingAction::Withdraw(amount) => ("Withdraw", amount, false),
+            LendingAction::Borrow(amount) => ("Borrow", amount, false),
+            LendingAction::Repay(amount) => ("Repay", amount, true),
+        };
+        amount.amount.parse::<u128>()
+            .map_err(|_| Error::InvalidInput(format!("Invalid amount: {}", amount.amount)))?;
+
+        let (debits, credits) = if outgoing { (vec![amount.clone()], vec![]) } else { (vec![], vec![amount.clone()]) };
+        Ok(DryRunResult {
+            protocol: request.protocol.clone(),
+            debits,
+            credits,
+            network_fee: self.estimate_network_fee()?,
+            quote: None,
+            preview: vec![format!("{} {} on {:?}", verb, amount.format(), request.protocol)],
+        })
+    }
+
+    /// Preview a staking action without signing or broadcasting
+    fn dry_run_staking(&self, request: &StakingRequest) -> Result<DryRunResult> {
+        self.ensure_supported(&request.protocol)?;
+        let (debits, credits, step) = match &request.action {
+            StakingAction::Stake(amount) => (vec![amount.clone()], vec![], format!("Stake {} with {:?}", amount.format(), request.protocol)),
+            StakingAction::Unstake(amount) => (vec![], vec![amount.clone()], format!("Unstake {} from {:?}", amount.format(), request.protocol)),
+            StakingAction::ClaimRewards => (vec![], vec![], format!("Claim pending rewards from {:?}", request.protocol)),
+        };
+        for amount in debits.iter().chain(&credits) {
+            amount.amount.parse::<u128>()
+                .map_err(|_| Error::InvalidInput(format!("Invalid amount: {}", amount.amount)))?;
+        }
+
+        Ok(DryRunResult {
+            protocol: request.protocol.clone(),
+            debits,
+            credits,
+            network_fee: self.estimate_network_fee()?,
+            quote: None,
+            preview: vec![step],
+        })
+    }
 }
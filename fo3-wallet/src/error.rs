@@ -1,7 +1,14 @@
 //! Error types for the wallet-core library
+//!
+//! Every error maps to a stable [`ErrorCode`] and a canonical [`Status`]
+//! (following `google.rpc.Code`), so API layers can report machine-readable
+//! failures without parsing messages.
 
+use serde::{Serialize, Deserialize};
 use thiserror::Error;
 
+use crate::crypto::keys::KeyType;
+
 /// Custom error type for wallet-core operations
 #[derive(Error, Debug)]
 pub enum Error {
@@ -32,9 +39,24 @@ pub enum Error {
     #[error("DeFi error: {0}")]
     DeFi(String),
 
+    #[error("Backup error: {0}")]
+    Backup(String),
+
+    #[error("Compliance error: {0}")]
+    Compliance(String),
+
+    #[error("Unauthenticated: {0}")]
+    Unauthenticated(String),
+
     #[error("Invalid input: {0}")]
     InvalidInput(String),
 
+    #[error("Invalid input: {}", format_violations(.0))]
+    Validation(Vec<FieldViolation>),
+
+    #[error("Fee budget exceeded: {0}")]
+    FeeBudget(FeeBudgetExceeded),
+
     #[error("Not supported: {0}")]
     NotSupported(String),
 
@@ -44,3 +66,252 @@ pub enum Error {
 
 /// Result type for wallet-core operations
 pub type Result<T> = std::result::Result<T, Error>;
+
+/// A violation of a request field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldViolation {
+    /// Path of the field (e.g. `request.to`)
+    pub field: String,
+    /// What is wrong with it
+    pub description: String,
+}
+
+impl FieldViolation {
+    /// Create a violation
+    pub fn new(field: &str, description: impl Into<String>) -> Self {
+        Self { field: field.to_string(), description: description.into() }
+    }
+}
+
+/// Fee limit a transaction broke
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeBudgetRule {
+    /// Highest fee per transaction
+    Absolute,
+    /// Highest fee as a share of the value sent
+    PercentageOfValue,
+}
+
+/// A transaction whose fee is over the caller's budget
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeBudgetExceeded {
+    /// Blockchain type
+    pub key_type: KeyType,
+    /// Computed fee, in the smallest unit of the native token
+    pub fee: String,
+    /// Highest fee the budget allows
+    pub limit: String,
+    /// Value sent
+    pub value: String,
+    /// Limit that was exceeded
+    pub rule: FeeBudgetRule,
+}
+
+impl std::fmt::Display for FeeBudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rule = match self.rule {
+            FeeBudgetRule::Absolute => "the maximum fee",
+            FeeBudgetRule::PercentageOfValue => "the maximum share of the value",
+        };
+        write!(f, "{:?} fee of {} exceeds {} ({})", self.key_type, self.fee, rule, self.limit)
+    }
+}
+
+fn format_violations(violations: &[FieldViolation]) -> String {
+    violations.iter()
+        .map(|violation| format!("{}: {}", violation.field, violation.description))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Stable machine-readable error code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    InvalidMnemonic,
+    KeyDerivationFailed,
+    SigningFailed,
+    TransactionFailed,
+    ChainError,
+    NetworkUnavailable,
+    ProviderUnavailable,
+    SerializationFailed,
+    DefiFailed,
+    BackupFailed,
+    ComplianceRejected,
+    Unauthenticated,
+    FeeBudgetExceeded,
+    InvalidArgument,
+    Unsupported,
+    Internal,
+}
+
+impl ErrorCode {
+    /// Get the code as a string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InvalidMnemonic => "INVALID_MNEMONIC",
+            Self::KeyDerivationFailed => "KEY_DERIVATION_FAILED",
+            Self::SigningFailed => "SIGNING_FAILED",
+            Self::TransactionFailed => "TRANSACTION_FAILED",
+            Self::ChainError => "CHAIN_ERROR",
+            Self::NetworkUnavailable => "NETWORK_UNAVAILABLE",
+            Self::ProviderUnavailable => "PROVIDER_UNAVAILABLE",
+            Self::SerializationFailed => "SERIALIZATION_FAILED",
+            Self::DefiFailed => "DEFI_FAILED",
+            Self::BackupFailed => "BACKUP_FAILED",
+            Self::ComplianceRejected => "COMPLIANCE_REJECTED",
+            Self::Unauthenticated => "UNAUTHENTICATED",
+            Self::FeeBudgetExceeded => "FEE_BUDGET_EXCEEDED",
+            Self::InvalidArgument => "INVALID_ARGUMENT",
+            Self::Unsupported => "UNSUPPORTED",
+            Self::Internal => "INTERNAL",
+        }
+    }
+}
+
+/// Canonical status of an error, following `google.rpc.Code`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Status {
+    InvalidArgument,
+    FailedPrecondition,
+    PermissionDenied,
+    Unauthenticated,
+    Unavailable,
+    Unimplemented,
+    Internal,
+}
+
+impl Status {
+    /// Get the status name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InvalidArgument => "INVALID_ARGUMENT",
+            Self::FailedPrecondition => "FAILED_PRECONDITION",
+            Self::PermissionDenied => "PERMISSION_DENIED",
+            Self::Unauthenticated => "UNAUTHENTICATED",
+            Self::Unavailable => "UNAVAILABLE",
+            Self::Unimplemented => "UNIMPLEMENTED",
+            Self::Internal => "INTERNAL",
+        }
+    }
+
+    /// Get the HTTP status code of the status
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Self::InvalidArgument | Self::FailedPrecondition => 400,
+            Self::PermissionDenied => 403,
+            Self::Unauthenticated => 401,
+            Self::Unavailable => 503,
+            Self::Unimplemented => 501,
+            Self::Internal => 500,
+        }
+    }
+}
+
+impl Error {
+    /// Whether the failure is transient and the operation may be retried
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Error::Network(_) | Error::Provider(_))
+    }
+
+    /// Get the error code
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::Mnemonic(_) => ErrorCode::InvalidMnemonic,
+            Error::KeyDerivation(_) => ErrorCode::KeyDerivationFailed,
+            Error::Signing(_) => ErrorCode::SigningFailed,
+            Error::Transaction(_) => ErrorCode::TransactionFailed,
+            Error::Chain(_) => ErrorCode::ChainError,
+            Error::Network(_) => ErrorCode::NetworkUnavailable,
+            Error::Provider(_) => ErrorCode::ProviderUnavailable,
+            Error::Serialization(_) => ErrorCode::SerializationFailed,
+            Error::DeFi(_) => ErrorCode::DefiFailed,
+            Error::Backup(_) => ErrorCode::BackupFailed,
+            Error::Compliance(_) => ErrorCode::ComplianceRejected,
+            Error::Unauthenticated(_) => ErrorCode::Unauthenticated,
+            Error::FeeBudget(_) => ErrorCode::FeeBudgetExceeded,
+            Error::InvalidInput(_) | Error::Validation(_) => ErrorCode::InvalidArgument,
+            Error::NotSupported(_) => ErrorCode::Unsupported,
+            Error::Unknown(_) => ErrorCode::Internal,
+        }
+    }
+
+    /// Get the canonical status
+    pub fn status(&self) -> Status {
+        match self.code() {
+            ErrorCode::InvalidMnemonic | ErrorCode::KeyDerivationFailed | ErrorCode::BackupFailed | ErrorCode::InvalidArgument => Status::InvalidArgument,
+            ErrorCode::SigningFailed | ErrorCode::TransactionFailed | ErrorCode::ChainError | ErrorCode::DefiFailed | ErrorCode::FeeBudgetExceeded => Status::FailedPrecondition,
+            ErrorCode::ComplianceRejected => Status::PermissionDenied,
+            ErrorCode::Unauthenticated => Status::Unauthenticated,
+            ErrorCode::NetworkUnavailable | ErrorCode::ProviderUnavailable => Status::Unavailable,
+            ErrorCode::Unsupported => Status::Unimplemented,
+            ErrorCode::SerializationFailed | ErrorCode::Internal => Status::Internal,
+        }
+    }
+
+    /// Get the message to show end users
+    ///
+    /// Internal and upstream failures may carry endpoint URLs or response
+    /// bodies, so only a generic message is returned for them; log the
+    /// `Display` form instead.
+    pub fn public_message(&self) -> String {
+        match self.status() {
+            Status::Unavailable => "An upstream service is temporarily unavailable; retry later".to_string(),
+            Status::Internal => "Internal error".to_string(),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Get the fee and limit of a transaction refused by a fee budget
+    pub fn fee_budget_exceeded(&self) -> Option<&FeeBudgetExceeded> {
+        match self {
+            Error::FeeBudget(exceeded) => Some(exceeded),
+            _ => None,
+        }
+    }
+
+    /// Get the field violations of a validation error
+    pub fn violations(&self) -> &[FieldViolation] {
+        match self {
+            Error::Validation(violations) => violations,
+            _ => &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_and_status() {
+        let error = Error::Provider("https://rpc.example.com/key returned 502".to_string());
+        assert_eq!(error.code().as_str(), "PROVIDER_UNAVAILABLE");
+        assert_eq!(error.status().http_status(), 503);
+        assert!(error.is_retryable());
+        assert!(!error.public_message().contains("rpc.example.com"));
+
+        let error = Error::Compliance("Transfer blocked".to_string());
+        assert_eq!(error.status(), Status::PermissionDenied);
+        assert_eq!(error.public_message(), "Compliance error: Transfer blocked");
+
+        let error = Error::Unauthenticated("Invalid token".to_string());
+        assert_eq!(error.status().http_status(), 401);
+        assert!(!error.is_retryable());
+    }
+
+    #[test]
+    fn test_validation() {
+        let error = Error::Validation(vec![
+            FieldViolation::new("to", "must not be empty"),
+            FieldViolation::new("value", "must be a decimal integer"),
+        ]);
+
+        assert_eq!(error.to_string(), "Invalid input: to: must not be empty; value: must be a decimal integer");
+        assert_eq!(error.code(), ErrorCode::InvalidArgument);
+        assert_eq!(error.violations().len(), 2);
+    }
+}
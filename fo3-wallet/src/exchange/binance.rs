@@ -0,0 +1,235 @@
+//! Binance spot account connector
+//!
+//! Requests are signed with HMAC-SHA256 over the query string and the key
+//! is sent in the `X-MBX-APIKEY` header. Binance limits request weight per
+//! minute and IP; each endpoint's weight is reserved before it is called.
+
+#[cfg(feature = "rpc")]
+use hmac::{Hmac, Mac};
+#[cfg(feature = "rpc")]
+use hmac::digest::KeyInit;
+#[cfg(feature = "rpc")]
+use sha2::Sha256;
+
+use crate::error::{Error, Result};
+#[cfg(feature = "rpc")]
+use crate::secrets::Secret;
+use super::{amount, ExchangeBalance, ExchangeTrade, KeyPermissions, TradeSide};
+#[cfg(feature = "rpc")]
+use super::{ExchangeConnector, ExchangeKind, RateLimiter};
+
+/// Request weight Binance allows per minute
+pub const BINANCE_WEIGHT_PER_MINUTE: f64 = 6000.0;
+
+/// Weight of the account endpoint
+const ACCOUNT_WEIGHT: f64 = 20.0;
+
+/// Weight of the trade list endpoint
+const TRADES_WEIGHT: f64 = 20.0;
+
+/// Weight of the API key restrictions endpoint
+const RESTRICTIONS_WEIGHT: f64 = 1.0;
+
+/// Sign a query string with an API secret
+#[cfg(feature = "rpc")]
+pub fn sign_binance_query(secret: &str, query: &str) -> String {
+    let mut mac = <Hmac<Sha256> as KeyInit>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(query.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Parse an account response into its non-zero balances
+pub fn parse_binance_balances(response: &serde_json::Value) -> Result<Vec<ExchangeBalance>> {
+    let invalid = || Error::Provider(format!("Invalid Binance account response: {}", response));
+
+    let mut balances = Vec::new();
+    for balance in response.get("balances").and_then(|v| v.as_array()).ok_or_else(invalid)? {
+        let asset = balance.get("asset").and_then(|v| v.as_str()).ok_or_else(invalid)?;
+        let free = balance.get("free").and_then(amount).ok_or_else(invalid)?;
+        let locked = balance.get("locked").and_then(amount).ok_or_else(invalid)?;
+        if free > 0.0 || locked > 0.0 {
+            balances.push(ExchangeBalance { asset: asset.to_string(), free, locked });
+        }
+    }
+    Ok(balances)
+}
+
+/// Parse a trade list response
+pub fn parse_binance_trades(response: &serde_json::Value) -> Result<Vec<ExchangeTrade>> {
+    let invalid = || Error::Provider(format!("Invalid Binance trades response: {}", response));
+
+    response.as_array().ok_or_else(invalid)?
+        .iter()
+        .map(|trade| {
+            let is_buyer = trade.get("isBuyer").and_then(|v| v.as_bool()).ok_or_else(invalid)?;
+            Ok(ExchangeTrade {
+                id: trade.get("id").and_then(|v| v.as_u64()).ok_or_else(invalid)?.to_string(),
+                symbol: trade.get("symbol").and_then(|v| v.as_str()).ok_or_else(invalid)?.to_string(),
+                side: if is_buyer { TradeSide::Buy } else { TradeSide::Sell },
+                price: trade.get("price").and_then(amount).ok_or_else(invalid)?,
+                quantity: trade.get("qty").and_then(amount).ok_or_else(invalid)?,
+                fee: trade.get("commission").and_then(amount).unwrap_or(0.0),
+                fee_asset: trade.get("commissionAsset").and_then(|v| v.as_str()).map(str::to_string),
+                timestamp: trade.get("time").and_then(|v| v.as_u64()).ok_or_else(invalid)? / 1000,
+            })
+        })
+        .collect()
+}
+
+/// Parse an API key restrictions response
+pub fn parse_binance_permissions(response: &serde_json::Value) -> Result<KeyPermissions> {
+    let flag = |name: &str| {
+        response.get(name).and_then(|v| v.as_bool())
+            .ok_or_else(|| Error::Provider(format!("Invalid Binance API restrictions response: {}", response)))
+    };
+
+    Ok(KeyPermissions {
+        trading: flag("enableSpotAndMarginTrading")? || flag("enableFutures").unwrap_or(false),
+        withdrawals: flag("enableWithdrawals")?,
+    })
+}
+
+/// Binance account read with an API key
+#[cfg(feature = "rpc")]
+pub struct BinanceConnector {
+    /// API base URL
+    url: String,
+    /// API key
+    api_key: String,
+    /// API secret
+    api_secret: Secret,
+    /// Request weight budget
+    limiter: RateLimiter,
+    /// HTTP client
+    http: reqwest::Client,
+}
+
+#[cfg(feature = "rpc")]
+impl BinanceConnector {
+    /// Public Binance API
+    pub const DEFAULT_URL: &'static str = "https://api.binance.com";
+
+    /// Create a connector for the public API
+    pub fn new(api_key: &str, api_secret: Secret) -> Self {
+        Self::with_url(Self::DEFAULT_URL, api_key, api_secret)
+    }
+
+    /// Create a connector for a custom endpoint
+    pub fn with_url(url: &str, api_key: &str, api_secret: Secret) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            api_secret,
+            limiter: RateLimiter::new(BINANCE_WEIGHT_PER_MINUTE, BINANCE_WEIGHT_PER_MINUTE / 60.0),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Send a signed GET request
+    fn signed_get(&self, path: &str, params: &[(&str, String)], weight: f64) -> Result<serde_json::Value> {
+        let timestamp = crate::time::unix_timestamp()? * 1000;
+        let mut query = params.iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .chain(std::iter::once(format!("timestamp={}", timestamp)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let signature = sign_binance_query(self.api_secret.expose(), &query);
+        query.push_str(&format!("&signature={}", signature));
+
+        self.limiter.acquire(weight);
+        let url = format!("{}{}?{}", self.url, path, query);
+        crate::transaction::block_on(async {
+            let response = self.http.get(&url)
+                .header("X-MBX-APIKEY", &self.api_key)
+                .send()
+                .await?;
+            let status = response.status();
+            Ok::<_, reqwest::Error>((status, response.json::<serde_json::Value>().await?))
+        })?
+        .map_err(|e| Error::Network(format!("Binance request failed: {}", e)))
+        .and_then(|(status, body)| {
+            if status.is_success() {
+                Ok(body)
+            } else {
+                let message = body.get("msg").and_then(|v| v.as_str()).unwrap_or("unknown error");
+                Err(Error::Provider(format!("Binance rejected {}: {}", path, message)))
+            }
+        })
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl ExchangeConnector for BinanceConnector {
+    fn kind(&self) -> ExchangeKind {
+        ExchangeKind::Binance
+    }
+
+    fn permissions(&self) -> Result<Option<KeyPermissions>> {
+        let response = self.signed_get("/sapi/v1/account/apiRestrictions", &[], RESTRICTIONS_WEIGHT)?;
+        parse_binance_permissions(&response).map(Some)
+    }
+
+    fn balances(&self) -> Result<Vec<ExchangeBalance>> {
+        let response = self.signed_get("/api/v3/account", &[("omitZeroBalances", "true".to_string())], ACCOUNT_WEIGHT)?;
+        parse_binance_balances(&response)
+    }
+
+    fn trades(&self, markets: &[String], since: u64) -> Result<Vec<ExchangeTrade>> {
+        let mut trades = Vec::new();
+        for market in markets {
+            let params = [
+                ("symbol", market.to_uppercase()),
+                ("startTime", (since * 1000).to_string()),
+                ("limit", "1000".to_string()),
+            ];
+            let response = self.signed_get("/api/v3/myTrades", &params, TRADES_WEIGHT)?;
+            trades.extend(parse_binance_trades(&response)?);
+        }
+        trades.sort_by_key(|trade| trade.timestamp);
+        Ok(trades)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "rpc")]
+    #[test]
+    fn test_sign_binance_query() {
+        // Example from the Binance API documentation
+        let secret = "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j";
+        let query = "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1&recvWindow=5000&timestamp=1499827319559";
+        assert_eq!(
+            sign_binance_query(secret, query),
+            "c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71"
+        );
+    }
+
+    #[test]
+    fn test_parse_binance_responses() {
+        let account = serde_json::json!({
+            "balances": [
+                { "asset": "BTC", "free": "0.50000000", "locked": "0.25000000" },
+                { "asset": "LTC", "free": "0.00000000", "locked": "0.00000000" },
+            ]
+        });
+        let balances = parse_binance_balances(&account).unwrap();
+        assert_eq!(balances, vec![ExchangeBalance { asset: "BTC".to_string(), free: 0.5, locked: 0.25 }]);
+
+        let trades = serde_json::json!([{
+            "symbol": "BNBBTC", "id": 28457, "price": "4.00000100", "qty": "12.00000000",
+            "commission": "10.10000000", "commissionAsset": "BNB", "time": 1499865549590u64, "isBuyer": true,
+        }]);
+        let trades = parse_binance_trades(&trades).unwrap();
+        assert_eq!(trades[0].id, "28457");
+        assert_eq!(trades[0].side, TradeSide::Buy);
+        assert_eq!(trades[0].timestamp, 1_499_865_549);
+        assert_eq!(trades[0].fee_asset.as_deref(), Some("BNB"));
+
+        let restrictions = serde_json::json!({ "enableReading": true, "enableSpotAndMarginTrading": false, "enableWithdrawals": false });
+        assert!(parse_binance_permissions(&restrictions).unwrap().is_read_only());
+        assert!(parse_binance_balances(&serde_json::json!({ "code": -2014 })).is_err());
+    }
+}
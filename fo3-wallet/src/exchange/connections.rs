@@ -0,0 +1,454 @@
+//! Linked exchange accounts
+//!
+//! Credentials are encrypted with AES-256-GCM before they reach the
+//! [`ConnectionStore`], bound to their connection ID so a ciphertext cannot
+//! be moved to another record. Connectors are built once per connection and
+//! kept, so their rate limiters see every request made to the exchange.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use aes_gcm::{Aes256Gcm, KeyInit};
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::aead::generic_array::GenericArray;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Serialize, Deserialize};
+
+use crate::error::{Error, Result};
+use crate::secrets::Secret;
+use super::{aggregate_balances, AggregatedBalance, ExchangeBalance, ExchangeConnector, ExchangeKind, ExchangeTrade};
+
+/// Most exchange accounts a user may link
+pub const MAX_CONNECTIONS_PER_USER: usize = 20;
+
+/// Longest connection label, in characters
+pub const MAX_LABEL_LENGTH: usize = 64;
+
+/// API credentials of an exchange account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeCredentials {
+    /// API key
+    pub api_key: String,
+    /// API secret
+    #[serde(with = "secret_value")]
+    pub api_secret: Secret,
+}
+
+/// Serialize a [`Secret`] as its bare value, for encryption
+mod secret_value {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::secrets::Secret;
+
+    pub fn serialize<S: Serializer>(secret: &Secret, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(secret.expose())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Secret, D::Error> {
+        String::deserialize(deserializer).map(Secret::new)
+    }
+}
+
+/// A linked exchange account, without its credentials
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExchangeConnection {
+    /// Connection ID
+    pub id: String,
+    /// User who linked the account
+    pub user: String,
+    /// Exchange
+    pub exchange: ExchangeKind,
+    /// User-defined label
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Last four characters of the API key, to tell keys apart
+    pub api_key_hint: String,
+    /// When the account was linked
+    pub created_at: u64,
+    /// When balances were last read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synced_at: Option<u64>,
+}
+
+/// Credentials encrypted with AES-256-GCM
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedCredentials {
+    /// Hex-encoded nonce
+    pub nonce: String,
+    /// Base64-encoded ciphertext
+    pub ciphertext: String,
+}
+
+/// A connection as stored, with its encrypted credentials
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredConnection {
+    /// The connection
+    pub connection: ExchangeConnection,
+    /// Its credentials
+    pub credentials: EncryptedCredentials,
+}
+
+/// Persistent store of linked exchange accounts
+pub trait ConnectionStore: Send + Sync {
+    /// Save a connection, replacing any with the same ID
+    fn put(&self, stored: &StoredConnection) -> Result<()>;
+
+    /// Get a connection by ID
+    fn get(&self, id: &str) -> Result<Option<StoredConnection>>;
+
+    /// List a user's connections in the order they were linked
+    fn list(&self, user: &str) -> Result<Vec<StoredConnection>>;
+
+    /// Remove a connection, returning whether it existed
+    fn remove(&self, id: &str) -> Result<bool>;
+}
+
+/// In-memory connection store
+#[derive(Debug, Default)]
+pub struct InMemoryConnectionStore {
+    connections: RwLock<HashMap<String, StoredConnection>>,
+}
+
+impl InMemoryConnectionStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ConnectionStore for InMemoryConnectionStore {
+    fn put(&self, stored: &StoredConnection) -> Result<()> {
+        self.connections.write().unwrap().insert(stored.connection.id.clone(), stored.clone());
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<StoredConnection>> {
+        Ok(self.connections.read().unwrap().get(id).cloned())
+    }
+
+    fn list(&self, user: &str) -> Result<Vec<StoredConnection>> {
+        let mut connections: Vec<StoredConnection> = self.connections.read().unwrap()
+            .values()
+            .filter(|stored| stored.connection.user == user)
+            .cloned()
+            .collect();
+        connections.sort_by(|a, b| a.connection.created_at.cmp(&b.connection.created_at).then_with(|| a.connection.id.cmp(&b.connection.id)));
+        Ok(connections)
+    }
+
+    fn remove(&self, id: &str) -> Result<bool> {
+        Ok(self.connections.write().unwrap().remove(id).is_some())
+    }
+}
+
+/// Builds a connector for an exchange from its credentials
+pub type ConnectorFactory = Box<dyn Fn(ExchangeKind, &ExchangeCredentials) -> Result<Box<dyn ExchangeConnector>> + Send + Sync>;
+
+/// Build a connector for an exchange's public API
+pub fn connector(exchange: ExchangeKind, credentials: &ExchangeCredentials) -> Result<Box<dyn ExchangeConnector>> {
+    #[cfg(feature = "rpc")]
+    {
+        let secret = credentials.api_secret.clone();
+        Ok(match exchange {
+            ExchangeKind::Binance => Box::new(super::BinanceConnector::new(&credentials.api_key, secret)),
+            ExchangeKind::Kraken => Box::new(super::KrakenConnector::new(&credentials.api_key, secret)),
+        })
+    }
+    #[cfg(not(feature = "rpc"))]
+    {
+        let _ = credentials;
+        Err(Error::NotSupported(format!("{} connector requires the rpc feature", exchange)))
+    }
+}
+
+/// A connection whose balances could not be read
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionError {
+    /// Connection ID
+    pub connection_id: String,
+    /// Exchange
+    pub exchange: ExchangeKind,
+    /// What went wrong
+    pub message: String,
+}
+
+/// Balances of a user's exchange accounts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeBalances {
+    /// Balances summed per asset
+    pub balances: Vec<AggregatedBalance>,
+    /// Connections left out because they failed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ConnectionError>,
+}
+
+/// Users' linked exchange accounts
+pub struct ExchangeConnections<S: ConnectionStore> {
+    store: S,
+    /// Credential encryption key
+    key: [u8; 32],
+    factory: ConnectorFactory,
+    /// Connectors built so far, by connection ID
+    connectors: RwLock<HashMap<String, Arc<dyn ExchangeConnector>>>,
+}
+
+impl<S: ConnectionStore> ExchangeConnections<S> {
+    /// Create connections stored in `store`, encrypting credentials with `key`
+    pub fn new(store: S, key: [u8; 32]) -> Self {
+        Self::with_factory(store, key, Box::new(connector))
+    }
+
+    /// Create connections whose connectors are built by `factory`
+    pub fn with_factory(store: S, key: [u8; 32], factory: ConnectorFactory) -> Self {
+        Self { store, key, factory, connectors: RwLock::new(HashMap::new()) }
+    }
+
+    /// Link an exchange account
+    ///
+    /// The key is checked with the exchange first: it must work and, where
+    /// the exchange reports permissions, must not allow trading or
+    /// withdrawals.
+    pub fn link(
+        &self,
+        user: &str,
+        exchange: ExchangeKind,
+        label: Option<String>,
+        credentials: ExchangeCredentials,
+        now: u64,
+    ) -> Result<ExchangeConnection> {
+        if credentials.api_key.trim().is_empty() || credentials.api_secret.expose().trim().is_empty() {
+            return Err(Error::InvalidInput("API key and secret are required".to_string()));
+        }
+        let label = label.map(|label| label.trim().to_string()).filter(|label| !label.is_empty());
+        if label.as_ref().is_some_and(|label| label.chars().count() > MAX_LABEL_LENGTH) {
+            return Err(Error::InvalidInput(format!("Label must be at most {} characters", MAX_LABEL_LENGTH)));
+        }
+        if self.store.list(user)?.len() >= MAX_CONNECTIONS_PER_USER {
+            return Err(Error::InvalidInput(format!("At most {} exchange accounts may be linked", MAX_CONNECTIONS_PER_USER)));
+        }
+
+        let connector = (self.factory)(exchange, &credentials)?;
+        match connector.permissions()? {
+            Some(permissions) if !permissions.is_read_only() => {
+                return Err(Error::InvalidInput(format!(
+                    "{} API key must be read-only; disable trading and withdrawals for it",
+                    exchange
+                )));
+            }
+            Some(_) => {}
+            // Reading balances at least proves the key works
+            None => {
+                connector.balances()?;
+            }
+        }
+
+        let hint_start = credentials.api_key.char_indices().rev().nth(3).map(|(i, _)| i).unwrap_or(0);
+        let connection = ExchangeConnection {
+            id: format!("exc_{}", hex::encode(rand::random::<[u8; 8]>())),
+            user: user.to_string(),
+            exchange,
+            label,
+            api_key_hint: credentials.api_key[hint_start..].to_string(),
+            created_at: now,
+            synced_at: None,
+        };
+        let encrypted = self.encrypt(&connection.id, &credentials)?;
+        self.store.put(&StoredConnection { connection: connection.clone(), credentials: encrypted })?;
+        self.connectors.write().unwrap().insert(connection.id.clone(), Arc::from(connector));
+        Ok(connection)
+    }
+
+    /// List a user's linked accounts
+    pub fn list(&self, user: &str) -> Result<Vec<ExchangeConnection>> {
+        Ok(self.store.list(user)?.into_iter().map(|stored| stored.connection).collect())
+    }
+
+    /// Unlink an account, returning whether the user had it linked
+    pub fn unlink(&self, user: &str, id: &str) -> Result<bool> {
+        if self.stored(user, id)?.is_none() {
+            return Ok(false);
+        }
+        self.connectors.write().unwrap().remove(id);
+        self.store.remove(id)
+    }
+
+    /// Read the balances of every account a user linked, summed per asset
+    ///
+    /// An exchange that fails is reported in `errors` rather than failing
+    /// the whole portfolio.
+    pub fn balances(&self, user: &str, now: u64) -> Result<ExchangeBalances> {
+        let mut accounts: Vec<(String, Vec<ExchangeBalance>)> = Vec::new();
+        let mut errors = Vec::new();
+        for mut stored in self.store.list(user)? {
+            match self.connector_for(&stored).and_then(|connector| connector.balances()) {
+                Ok(balances) => {
+                    accounts.push((stored.connection.id.clone(), balances));
+                    stored.connection.synced_at = Some(now);
+                    self.store.put(&stored)?;
+                }
+                Err(e) => errors.push(ConnectionError {
+                    connection_id: stored.connection.id.clone(),
+                    exchange: stored.connection.exchange,
+                    message: e.to_string(),
+                }),
+            }
+        }
+        Ok(ExchangeBalances { balances: aggregate_balances(&accounts), errors })
+    }
+
+    /// Read the trades filled on an account since a Unix timestamp
+    pub fn trades(&self, user: &str, id: &str, markets: &[String], since: u64) -> Result<Vec<ExchangeTrade>> {
+        let stored = self.stored(user, id)?
+            .ok_or_else(|| Error::InvalidInput(format!("Exchange connection not found: {}", id)))?;
+        self.connector_for(&stored)?.trades(markets, since)
+    }
+
+    /// Get a user's stored connection
+    fn stored(&self, user: &str, id: &str) -> Result<Option<StoredConnection>> {
+        Ok(self.store.get(id)?.filter(|stored| stored.connection.user == user))
+    }
+
+    /// Get the connector of a connection, building it on first use
+    fn connector_for(&self, stored: &StoredConnection) -> Result<Arc<dyn ExchangeConnector>> {
+        let id = &stored.connection.id;
+        if let Some(connector) = self.connectors.read().unwrap().get(id) {
+            return Ok(connector.clone());
+        }
+
+        let credentials = self.decrypt(id, &stored.credentials)?;
+        let connector: Arc<dyn ExchangeConnector> = Arc::from((self.factory)(stored.connection.exchange, &credentials)?);
+        Ok(self.connectors.write().unwrap().entry(id.clone()).or_insert(connector).clone())
+    }
+
+    /// Encrypt credentials, bound to a connection ID
+    fn encrypt(&self, id: &str, credentials: &ExchangeCredentials) -> Result<EncryptedCredentials> {
+        let plaintext = serde_json::to_vec(credentials).map_err(|e| Error::Serialization(e.to_string()))?;
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+
+        let cipher = Aes256Gcm::new(GenericArray::from_slice(&self.key));
+        let ciphertext = cipher.encrypt(GenericArray::from_slice(&nonce), Payload { msg: &plaintext, aad: id.as_bytes() })
+            .map_err(|_| Error::InvalidInput("Failed to encrypt exchange credentials".to_string()))?;
+
+        Ok(EncryptedCredentials { nonce: hex::encode(nonce), ciphertext: BASE64.encode(ciphertext) })
+    }
+
+    /// Decrypt the credentials of a connection
+    fn decrypt(&self, id: &str, encrypted: &EncryptedCredentials) -> Result<ExchangeCredentials> {
+        let nonce = hex::decode(&encrypted.nonce).ok().filter(|nonce| nonce.len() == 12)
+            .ok_or_else(|| Error::InvalidInput(format!("Invalid credential nonce for {}", id)))?;
+        let ciphertext = BASE64.decode(&encrypted.ciphertext)
+            .map_err(|e| Error::InvalidInput(format!("Invalid credential ciphertext for {}: {}", id, e)))?;
+
+        let cipher = Aes256Gcm::new(GenericArray::from_slice(&self.key));
+        let plaintext = cipher.decrypt(GenericArray::from_slice(&nonce), Payload { msg: &ciphertext, aad: id.as_bytes() })
+            .map_err(|_| Error::InvalidInput(format!("Failed to decrypt credentials for {}", id)))?;
+        serde_json::from_slice(&plaintext).map_err(|e| Error::Serialization(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::KeyPermissions;
+
+    /// Connector with fixed permissions and balances, failing for the key `broken`
+    struct TestConnector {
+        exchange: ExchangeKind,
+        api_key: String,
+        permissions: Option<KeyPermissions>,
+    }
+
+    impl ExchangeConnector for TestConnector {
+        fn kind(&self) -> ExchangeKind {
+            self.exchange
+        }
+
+        fn permissions(&self) -> Result<Option<KeyPermissions>> {
+            Ok(self.permissions)
+        }
+
+        fn balances(&self) -> Result<Vec<ExchangeBalance>> {
+            if self.api_key == "broken" {
+                return Err(Error::Network("unavailable".to_string()));
+            }
+            Ok(vec![ExchangeBalance { asset: "BTC".to_string(), free: 0.5, locked: 0.0 }])
+        }
+
+        fn trades(&self, _markets: &[String], _since: u64) -> Result<Vec<ExchangeTrade>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn connections() -> ExchangeConnections<InMemoryConnectionStore> {
+        ExchangeConnections::with_factory(InMemoryConnectionStore::new(), [7u8; 32], Box::new(|exchange, credentials| {
+            let permissions = match exchange {
+                ExchangeKind::Binance => Some(KeyPermissions { trading: credentials.api_key == "trader", withdrawals: false }),
+                ExchangeKind::Kraken => None,
+            };
+            Ok(Box::new(TestConnector { exchange, api_key: credentials.api_key.clone(), permissions }))
+        }))
+    }
+
+    fn credentials(api_key: &str) -> ExchangeCredentials {
+        ExchangeCredentials { api_key: api_key.to_string(), api_secret: Secret::new("secret") }
+    }
+
+    #[test]
+    fn test_link_requires_read_only_keys() {
+        let connections = connections();
+        assert!(connections.link("alice", ExchangeKind::Binance, None, credentials("trader"), 1).is_err());
+        assert!(connections.link("alice", ExchangeKind::Binance, None, credentials(""), 1).is_err());
+
+        let linked = connections.link("alice", ExchangeKind::Binance, Some(" Main ".to_string()), credentials("readonly-key1234"), 1).unwrap();
+        assert_eq!(linked.label.as_deref(), Some("Main"));
+        assert_eq!(linked.api_key_hint, "1234");
+        assert_eq!(connections.list("alice").unwrap(), vec![linked.clone()]);
+        assert!(connections.list("bob").unwrap().is_empty());
+
+        // Kraken keys are proven by reading balances
+        assert!(connections.link("alice", ExchangeKind::Kraken, None, credentials("broken"), 2).is_err());
+
+        assert!(!connections.unlink("bob", &linked.id).unwrap());
+        assert!(connections.unlink("alice", &linked.id).unwrap());
+        assert!(connections.list("alice").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_credentials_are_encrypted() {
+        let connections = connections();
+        let linked = connections.link("alice", ExchangeKind::Kraken, None, credentials("key"), 1).unwrap();
+
+        let stored = connections.store.get(&linked.id).unwrap().unwrap();
+        assert!(!serde_json::to_string(&stored).unwrap().contains("secret"));
+        let decrypted = connections.decrypt(&linked.id, &stored.credentials).unwrap();
+        assert_eq!(decrypted.api_secret.expose(), "secret");
+
+        // Ciphertexts are bound to their connection
+        assert!(connections.decrypt("exc_other", &stored.credentials).is_err());
+    }
+
+    #[test]
+    fn test_balances_across_connections() {
+        let connections = connections();
+        connections.link("alice", ExchangeKind::Binance, None, credentials("one"), 1).unwrap();
+        let kraken = connections.link("alice", ExchangeKind::Kraken, None, credentials("two"), 2).unwrap();
+
+        let balances = connections.balances("alice", 10).unwrap();
+        assert_eq!(balances.balances.len(), 1);
+        assert_eq!(balances.balances[0].total, 1.0);
+        assert!(balances.errors.is_empty());
+        assert_eq!(connections.list("alice").unwrap()[1].synced_at, Some(10));
+
+        // A connector rebuilt from stored credentials that fails is reported, not fatal
+        let broken = StoredConnection {
+            connection: ExchangeConnection { id: "exc_broken".to_string(), created_at: 3, ..kraken },
+            credentials: connections.encrypt("exc_broken", &credentials("broken")).unwrap(),
+        };
+        connections.store.put(&broken).unwrap();
+        let balances = connections.balances("alice", 20).unwrap();
+        assert_eq!(balances.balances[0].total, 1.0);
+        assert_eq!(balances.errors[0].connection_id, "exc_broken");
+    }
+}
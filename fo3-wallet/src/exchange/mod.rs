@@ -0,0 +1,260 @@
+//! Centralized exchange accounts
+//!
+//! Users link exchange accounts with read-only API keys so funds held on
+//! exchanges show up next to their on-chain balances. Each exchange has an
+//! [`ExchangeConnector`] that pulls balances and trade history within the
+//! exchange's rate limits. API secrets are stored encrypted by
+//! [`ExchangeConnections`] and only decrypted to build a connector.
+
+mod binance;
+mod kraken;
+mod connections;
+
+pub use binance::*;
+pub use kraken::*;
+pub use connections::*;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+#[cfg(feature = "rpc")]
+use std::sync::Mutex;
+#[cfg(feature = "rpc")]
+use std::time::{Duration, Instant};
+
+use serde::{Serialize, Deserialize};
+
+use crate::error::{Error, Result};
+
+/// Supported exchanges
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExchangeKind {
+    /// Binance
+    Binance,
+    /// Kraken
+    Kraken,
+}
+
+impl ExchangeKind {
+    /// Get the exchange's name as used in paths and configuration
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Binance => "binance",
+            Self::Kraken => "kraken",
+        }
+    }
+}
+
+impl fmt::Display for ExchangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ExchangeKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "binance" => Ok(Self::Binance),
+            "kraken" => Ok(Self::Kraken),
+            _ => Err(Error::InvalidInput(format!("Unsupported exchange: {}", s))),
+        }
+    }
+}
+
+/// What an API key is allowed to do, as reported by the exchange
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyPermissions {
+    /// The key can place orders
+    pub trading: bool,
+    /// The key can withdraw funds
+    pub withdrawals: bool,
+}
+
+impl KeyPermissions {
+    /// Check the key can only read
+    pub fn is_read_only(&self) -> bool {
+        !self.trading && !self.withdrawals
+    }
+}
+
+/// Balance of one asset on an exchange
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeBalance {
+    /// Asset symbol, normalized (e.g. `BTC` rather than Kraken's `XXBT`)
+    pub asset: String,
+    /// Amount available
+    pub free: f64,
+    /// Amount held in open orders
+    pub locked: f64,
+}
+
+impl ExchangeBalance {
+    /// Get the total held
+    pub fn total(&self) -> f64 {
+        self.free + self.locked
+    }
+}
+
+/// Side of a trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeSide {
+    /// Bought the base asset
+    Buy,
+    /// Sold the base asset
+    Sell,
+}
+
+/// A filled trade on an exchange
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeTrade {
+    /// Exchange trade ID
+    pub id: String,
+    /// Market symbol as named by the exchange
+    pub symbol: String,
+    /// Side
+    pub side: TradeSide,
+    /// Price in the quote asset
+    pub price: f64,
+    /// Quantity of the base asset
+    pub quantity: f64,
+    /// Fee charged
+    pub fee: f64,
+    /// Asset the fee was charged in, if reported
+    pub fee_asset: Option<String>,
+    /// Unix timestamp of the fill
+    pub timestamp: u64,
+}
+
+/// Read-only access to an exchange account
+pub trait ExchangeConnector: Send + Sync {
+    /// Get the exchange
+    fn kind(&self) -> ExchangeKind;
+
+    /// Get the API key's permissions, if the exchange reports them
+    fn permissions(&self) -> Result<Option<KeyPermissions>>;
+
+    /// Get the account's non-zero balances
+    fn balances(&self) -> Result<Vec<ExchangeBalance>>;
+
+    /// Get trades filled since a Unix timestamp
+    ///
+    /// Exchanges that only list trades per market use `markets`; others
+    /// return every market's trades.
+    fn trades(&self, markets: &[String], since: u64) -> Result<Vec<ExchangeTrade>>;
+}
+
+/// Token bucket limiting the request weight sent to an exchange
+///
+/// Exchanges weigh endpoints differently, so each call reserves its weight
+/// and waits until the bucket holds enough. Only built with the connectors,
+/// since `Instant::now` and blocking sleeps are unavailable on wasm32.
+#[cfg(feature = "rpc")]
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    state: Mutex<(f64, Instant)>,
+}
+
+#[cfg(feature = "rpc")]
+impl RateLimiter {
+    /// Create a full bucket
+    pub fn new(capacity: f64, refill_per_second: f64) -> Self {
+        Self { capacity, refill_per_second, state: Mutex::new((capacity, Instant::now())) }
+    }
+
+    /// Reserve `weight` at `now`, returning how long to wait before sending
+    pub fn reserve(&self, weight: f64, now: Instant) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let (tokens, updated) = *state;
+        let elapsed = now.saturating_duration_since(updated).as_secs_f64();
+        let tokens = (tokens + elapsed * self.refill_per_second).min(self.capacity) - weight;
+        *state = (tokens, now.max(updated));
+
+        if tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-tokens / self.refill_per_second)
+        }
+    }
+
+    /// Reserve `weight`, sleeping until it may be sent
+    pub fn acquire(&self, weight: f64) {
+        let wait = self.reserve(weight, Instant::now());
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+/// An asset's balance summed across exchange accounts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggregatedBalance {
+    /// Asset symbol
+    pub asset: String,
+    /// Total held across accounts
+    pub total: f64,
+    /// Total held per connection ID
+    pub sources: BTreeMap<String, f64>,
+}
+
+/// Sum balances of several exchange accounts per asset, sorted by asset
+pub fn aggregate_balances(accounts: &[(String, Vec<ExchangeBalance>)]) -> Vec<AggregatedBalance> {
+    let mut assets: BTreeMap<String, AggregatedBalance> = BTreeMap::new();
+    for (connection_id, balances) in accounts {
+        for balance in balances {
+            let aggregated = assets.entry(balance.asset.clone()).or_insert_with(|| AggregatedBalance {
+                asset: balance.asset.clone(),
+                total: 0.0,
+                sources: BTreeMap::new(),
+            });
+            aggregated.total += balance.total();
+            *aggregated.sources.entry(connection_id.clone()).or_default() += balance.total();
+        }
+    }
+    assets.into_values().collect()
+}
+
+/// Parse a decimal amount the exchange sent as a string
+fn amount(value: &serde_json::Value) -> Option<f64> {
+    value.as_str().and_then(|value| value.parse().ok()).or_else(|| value.as_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "rpc")]
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(10.0, 2.0);
+        let start = Instant::now();
+
+        assert_eq!(limiter.reserve(10.0, start), Duration::ZERO);
+        // The bucket is empty, so 4 more need 2 seconds of refill
+        assert_eq!(limiter.reserve(4.0, start), Duration::from_secs(2));
+        // Reservations queue behind each other
+        assert_eq!(limiter.reserve(2.0, start + Duration::from_secs(2)), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_aggregate_balances() {
+        let accounts = vec![
+            ("binance".to_string(), vec![
+                ExchangeBalance { asset: "BTC".to_string(), free: 0.5, locked: 0.25 },
+                ExchangeBalance { asset: "USDT".to_string(), free: 100.0, locked: 0.0 },
+            ]),
+            ("kraken".to_string(), vec![ExchangeBalance { asset: "BTC".to_string(), free: 0.25, locked: 0.0 }]),
+        ];
+
+        let aggregated = aggregate_balances(&accounts);
+        assert_eq!(aggregated.len(), 2);
+        assert_eq!(aggregated[0].asset, "BTC");
+        assert_eq!(aggregated[0].total, 1.0);
+        assert_eq!(aggregated[0].sources["binance"], 0.75);
+    }
+}
@@ -0,0 +1,430 @@
+//! Payment requests
+//!
+//! Merchant-style receive flows: a [`PaymentRequest`] asks for an amount of
+//! one asset on one chain before it expires. Each request is paid into a
+//! deposit address assigned to it alone, and is encoded as a wallet URI
+//! (EIP-681, Solana Pay or BIP-21) for the payer to scan as a QR code.
+//! Payments the chain watcher attributes to the deposit address are
+//! recorded with [`PaymentRequests::record_payment`].
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use serde::{Serialize, Deserialize};
+
+use crate::account::Wallet;
+use crate::account::deposit::{DepositAddress, DepositAddresses, DepositRequest, DepositStore};
+use crate::account::export::format_units;
+use crate::crypto::keys::KeyType;
+use crate::error::{Error, Result};
+use crate::validation::Validate;
+
+/// Expiry of a payment request unless one is given, in seconds
+pub const DEFAULT_EXPIRY: u64 = 60 * 60;
+
+/// Shortest expiry of a payment request, in seconds
+pub const MIN_EXPIRY: u64 = 60;
+
+/// Longest expiry of a payment request, in seconds
+pub const MAX_EXPIRY: u64 = 30 * 24 * 60 * 60;
+
+fn default_expiry() -> u64 {
+    DEFAULT_EXPIRY
+}
+
+/// A request to be paid
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePaymentRequest {
+    /// Chain to be paid on
+    pub key_type: KeyType,
+    /// Token contract or mint, or none for the native asset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    /// Decimals of the token, required with `asset`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+    /// Amount in the smallest unit
+    pub amount: String,
+    /// Seconds until the request expires
+    #[serde(default = "default_expiry")]
+    pub expires_in: u64,
+    /// Message shown to the payer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// Merchant name shown to the payer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// EVM chain ID (default 1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+}
+
+impl CreatePaymentRequest {
+    /// Get the decimals of the requested asset
+    pub fn decimals(&self) -> Option<u8> {
+        match (&self.asset, self.key_type) {
+            (Some(_), _) => self.decimals,
+            (None, KeyType::Ethereum) => Some(18),
+            (None, KeyType::Solana | KeyType::Ton) => Some(9),
+            (None, KeyType::Bitcoin) => Some(8),
+            (None, KeyType::Cosmos | KeyType::Xrp) => Some(6),
+        }
+    }
+}
+
+/// Status of a payment request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentRequestStatus {
+    /// Nothing received yet
+    Pending,
+    /// Part of the amount received
+    PartiallyPaid,
+    /// The full amount received
+    Paid,
+    /// Expired before the full amount was received
+    Expired,
+}
+
+/// A payment received towards a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceivedPayment {
+    /// Transaction hash
+    pub hash: String,
+    /// Amount in the smallest unit
+    pub amount: String,
+    /// When the payment was made
+    pub timestamp: u64,
+}
+
+/// A payment request and what has been paid towards it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentRequest {
+    /// Request ID
+    pub id: String,
+    /// Wallet receiving the payment
+    pub wallet_id: String,
+    /// What was requested
+    #[serde(flatten)]
+    pub request: CreatePaymentRequest,
+    /// Where to pay, with the destination tag or memo to attach
+    pub deposit: DepositAddress,
+    /// Wallet URI to show as a QR code, on chains with a URI standard
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    /// Current status
+    pub status: PaymentRequestStatus,
+    /// Payments received
+    #[serde(default)]
+    pub payments: Vec<ReceivedPayment>,
+    /// When the request was created
+    pub created_at: u64,
+    /// When the request expires
+    pub expires_at: u64,
+}
+
+impl PaymentRequest {
+    /// Get the total received, in the smallest unit
+    pub fn received(&self) -> u128 {
+        self.payments.iter().filter_map(|payment| payment.amount.parse::<u128>().ok()).sum()
+    }
+
+    /// Work out the status at a time
+    ///
+    /// Payments arriving after the expiry still count: the funds reached an
+    /// address assigned to this request alone.
+    pub fn status_at(&self, now: u64) -> PaymentRequestStatus {
+        let requested = self.request.amount.parse::<u128>().unwrap_or(u128::MAX);
+        let received = self.received();
+        if received >= requested {
+            PaymentRequestStatus::Paid
+        } else if now >= self.expires_at {
+            PaymentRequestStatus::Expired
+        } else if received > 0 {
+            PaymentRequestStatus::PartiallyPaid
+        } else {
+            PaymentRequestStatus::Pending
+        }
+    }
+
+    fn refresh(&mut self, now: u64) {
+        self.status = self.status_at(now);
+    }
+}
+
+/// Percent-encode a URI query value
+fn encode(value: &str) -> String {
+    value.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Append query parameters to a URI, skipping absent ones
+fn with_query(uri: String, params: &[(&str, Option<String>)]) -> String {
+    let query: Vec<String> = params.iter()
+        .filter_map(|(name, value)| value.as_ref().map(|value| format!("{}={}", name, encode(value))))
+        .collect();
+    if query.is_empty() {
+        uri
+    } else {
+        format!("{}?{}", uri, query.join("&"))
+    }
+}
+
+/// Build an EIP-681 URI paying `amount` (smallest unit) to `to`
+///
+/// Token payments call the token's `transfer` with the recipient and amount.
+pub fn eip681_uri(to: &str, chain_id: u64, token: Option<&str>, amount: &str) -> String {
+    match token {
+        Some(token) => with_query(format!("ethereum:{}@{}/transfer", token, chain_id), &[
+            ("address", Some(to.to_string())),
+            ("uint256", Some(amount.to_string())),
+        ]),
+        None => with_query(format!("ethereum:{}@{}", to, chain_id), &[("value", Some(amount.to_string()))]),
+    }
+}
+
+/// Build a Solana Pay transfer request URI
+///
+/// The amount is in whole SOL or tokens, and `recipient` is the owner
+/// wallet even for token transfers.
+pub fn solana_pay_uri(recipient: &str, amount: &str, spl_token: Option<&str>, label: Option<&str>, message: Option<&str>) -> String {
+    with_query(format!("solana:{}", recipient), &[
+        ("amount", Some(amount.to_string())),
+        ("spl-token", spl_token.map(str::to_string)),
+        ("label", label.map(str::to_string)),
+        ("message", message.map(str::to_string)),
+    ])
+}
+
+/// Build a BIP-21 URI; the amount is in whole bitcoin
+pub fn bip21_uri(address: &str, amount: &str, label: Option<&str>, message: Option<&str>) -> String {
+    with_query(format!("bitcoin:{}", address), &[
+        ("amount", Some(amount.to_string())),
+        ("label", label.map(str::to_string)),
+        ("message", message.map(str::to_string)),
+    ])
+}
+
+/// Encode a request paid to a deposit address as a wallet URI
+///
+/// TON, Cosmos and XRP wallets have no URI standard that every wallet
+/// honors, so payers there enter the address with its memo or tag.
+pub fn payment_uri(request: &CreatePaymentRequest, deposit: &DepositAddress) -> Result<Option<String>> {
+    let amount = request.amount.parse::<u128>()
+        .map_err(|_| Error::InvalidInput(format!("Invalid amount: {}", request.amount)))?;
+    let decimals = request.decimals()
+        .ok_or_else(|| Error::InvalidInput("Token decimals are required".to_string()))?;
+    let whole = format_units(amount, decimals);
+    let (label, message) = (request.label.as_deref(), request.memo.as_deref());
+
+    Ok(match request.key_type {
+        KeyType::Ethereum => Some(eip681_uri(&deposit.address, request.chain_id.unwrap_or(1), request.asset.as_deref(), &request.amount)),
+        KeyType::Solana => {
+            let recipient = deposit.owner.as_deref().unwrap_or(&deposit.address);
+            Some(solana_pay_uri(recipient, &whole, request.asset.as_deref(), label, message))
+        }
+        KeyType::Bitcoin => Some(bip21_uri(&deposit.address, &whole, label, message)),
+        KeyType::Ton | KeyType::Cosmos | KeyType::Xrp => None,
+    })
+}
+
+/// Open payment requests, paid into their own deposit addresses
+#[derive(Debug, Default)]
+pub struct PaymentRequests {
+    requests: RwLock<HashMap<String, PaymentRequest>>,
+}
+
+impl PaymentRequests {
+    /// Create an empty set of requests
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a payment request, assigning it a deposit address of the wallet
+    pub fn create<S: DepositStore>(&self, wallet: &Wallet, deposits: &DepositAddresses<S>, request: CreatePaymentRequest, now: u64) -> Result<PaymentRequest> {
+        request.validate()?;
+
+        let id = format!("pr_{}", hex::encode(rand::random::<[u8; 8]>()));
+        let deposit = deposits.assign(wallet, &DepositRequest {
+            reference: id.clone(),
+            key_type: request.key_type,
+            // Solana tokens are paid to the token account of a fresh owner
+            mint: request.asset.clone().filter(|_| request.key_type == KeyType::Solana),
+        }, now)?;
+        let uri = payment_uri(&request, &deposit)?;
+
+        let payment_request = PaymentRequest {
+            id: id.clone(),
+            wallet_id: wallet.id().to_string(),
+            expires_at: now + request.expires_in,
+            request,
+            deposit,
+            uri,
+            status: PaymentRequestStatus::Pending,
+            payments: Vec::new(),
+            created_at: now,
+        };
+        self.requests.write().unwrap().insert(id, payment_request.clone());
+        Ok(payment_request)
+    }
+
+    /// Get a request with its status as of `now`
+    pub fn get(&self, id: &str, now: u64) -> Option<PaymentRequest> {
+        let mut requests = self.requests.write().unwrap();
+        let request = requests.get_mut(id)?;
+        request.refresh(now);
+        Some(request.clone())
+    }
+
+    /// List a wallet's requests, newest first
+    pub fn list(&self, wallet_id: &str, now: u64) -> Vec<PaymentRequest> {
+        let mut requests = self.requests.write().unwrap();
+        let mut listed: Vec<PaymentRequest> = requests.values_mut()
+            .filter(|request| request.wallet_id == wallet_id)
+            .map(|request| {
+                request.refresh(now);
+                request.clone()
+            })
+            .collect();
+        listed.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| a.id.cmp(&b.id)));
+        listed
+    }
+
+    /// Record a payment the chain watcher attributed to a deposit address
+    ///
+    /// Returns the updated request, or `None` if the deposit address isn't a
+    /// payment request's. Payments in another asset than the one requested
+    /// are rejected, and a transaction already recorded is ignored.
+    pub fn record_payment(&self, deposit: &DepositAddress, asset: Option<&str>, payment: ReceivedPayment, now: u64) -> Result<Option<PaymentRequest>> {
+        let mut requests = self.requests.write().unwrap();
+        let Some(request) = requests.get_mut(&deposit.reference).filter(|request| request.deposit == *deposit) else {
+            return Ok(None);
+        };
+
+        let same_asset = match (request.request.asset.as_deref(), asset) {
+            (None, None) => true,
+            (Some(requested), Some(paid)) => requested.eq_ignore_ascii_case(paid),
+            _ => false,
+        };
+        if !same_asset {
+            return Err(Error::InvalidInput(format!("Payment request {} asks for {}", request.id, request.request.asset.as_deref().unwrap_or("the native asset"))));
+        }
+        if payment.amount.parse::<u128>().is_err() {
+            return Err(Error::InvalidInput(format!("Invalid amount: {}", payment.amount)));
+        }
+
+        if !request.payments.iter().any(|recorded| recorded.hash == payment.hash) {
+            request.payments.push(payment);
+        }
+        request.refresh(now);
+        Ok(Some(request.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::deposit::InMemoryDepositStore;
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    const USDC: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+
+    fn request(key_type: KeyType, amount: &str) -> CreatePaymentRequest {
+        CreatePaymentRequest {
+            key_type,
+            asset: None,
+            decimals: None,
+            amount: amount.to_string(),
+            expires_in: DEFAULT_EXPIRY,
+            memo: None,
+            label: None,
+            chain_id: None,
+        }
+    }
+
+    fn payment(hash: &str, amount: &str) -> ReceivedPayment {
+        ReceivedPayment { hash: hash.to_string(), amount: amount.to_string(), timestamp: 1_000 }
+    }
+
+    #[test]
+    fn test_uris() {
+        let to = "0x742d35Cc6634C0532925a3b844Bc454e4438f44e";
+        assert_eq!(eip681_uri(to, 1, None, "1000"), format!("ethereum:{}@1?value=1000", to));
+        assert_eq!(
+            eip681_uri(to, 137, Some(USDC), "2500000"),
+            format!("ethereum:{}@137/transfer?address={}&uint256=2500000", USDC, to),
+        );
+        assert_eq!(
+            bip21_uri("1BoatSLRHtKNngkdXEeobR76b53LETtpyT", "0.0015", Some("Corner Café"), None),
+            "bitcoin:1BoatSLRHtKNngkdXEeobR76b53LETtpyT?amount=0.0015&label=Corner%20Caf%C3%A9",
+        );
+        assert_eq!(
+            solana_pay_uri("mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN", "1.5", None, None, Some("Order #42")),
+            "solana:mvines9iiHiQTysrwkJjGf2gb9Ex9jXJX8ns3qwf2kN?amount=1.5&message=Order%20%2342",
+        );
+    }
+
+    #[test]
+    fn test_create() {
+        let wallet = Wallet::from_mnemonic("Shop".to_string(), MNEMONIC).unwrap();
+        let deposits = DepositAddresses::new(InMemoryDepositStore::new());
+        let requests = PaymentRequests::new();
+
+        let bitcoin = requests.create(&wallet, &deposits, request(KeyType::Bitcoin, "150000"), 1_000).unwrap();
+        assert_eq!(bitcoin.status, PaymentRequestStatus::Pending);
+        assert_eq!(bitcoin.expires_at, 1_000 + DEFAULT_EXPIRY);
+        assert_eq!(bitcoin.deposit.reference, bitcoin.id);
+        assert_eq!(bitcoin.uri, Some(format!("bitcoin:{}?amount=0.0015", bitcoin.deposit.address)));
+
+        // Each request gets its own address
+        let second = requests.create(&wallet, &deposits, request(KeyType::Bitcoin, "1"), 1_000).unwrap();
+        assert_ne!(second.deposit.address, bitcoin.deposit.address);
+
+        let xrp = requests.create(&wallet, &deposits, request(KeyType::Xrp, "1000000"), 1_000).unwrap();
+        assert!(xrp.uri.is_none());
+        assert!(xrp.deposit.destination_tag.is_some());
+
+        // Tokens need their decimals
+        let token = CreatePaymentRequest { asset: Some(USDC.to_string()), ..request(KeyType::Ethereum, "1000000") };
+        assert!(requests.create(&wallet, &deposits, token, 1_000).is_err());
+    }
+
+    #[test]
+    fn test_fulfillment() {
+        let wallet = Wallet::from_mnemonic("Shop".to_string(), MNEMONIC).unwrap();
+        let deposits = DepositAddresses::new(InMemoryDepositStore::new());
+        let requests = PaymentRequests::new();
+        let created = requests.create(&wallet, &deposits, request(KeyType::Ethereum, "1000"), 1_000).unwrap();
+
+        let updated = requests.record_payment(&created.deposit, None, payment("0x01", "400"), 1_100).unwrap().unwrap();
+        assert_eq!(updated.status, PaymentRequestStatus::PartiallyPaid);
+
+        // The watcher may report the same transaction twice
+        let updated = requests.record_payment(&created.deposit, None, payment("0x01", "400"), 1_200).unwrap().unwrap();
+        assert_eq!(updated.received(), 400);
+
+        // Payments in another asset don't count
+        assert!(requests.record_payment(&created.deposit, Some(USDC), payment("0x02", "600"), 1_200).is_err());
+
+        let updated = requests.record_payment(&created.deposit, None, payment("0x03", "600"), 1_300).unwrap().unwrap();
+        assert_eq!(updated.status, PaymentRequestStatus::Paid);
+    }
+
+    #[test]
+    fn test_expiry() {
+        let wallet = Wallet::from_mnemonic("Shop".to_string(), MNEMONIC).unwrap();
+        let deposits = DepositAddresses::new(InMemoryDepositStore::new());
+        let requests = PaymentRequests::new();
+        let created = requests.create(&wallet, &deposits, request(KeyType::Solana, "1000000000"), 1_000).unwrap();
+
+        assert_eq!(requests.get(&created.id, created.expires_at).unwrap().status, PaymentRequestStatus::Expired);
+        assert_eq!(requests.list(wallet.id(), created.expires_at).len(), 1);
+
+        let late = requests.record_payment(&created.deposit, None, payment("sig", "1000000000"), created.expires_at + 10).unwrap().unwrap();
+        assert_eq!(late.status, PaymentRequestStatus::Paid);
+    }
+}
@@ -9,6 +9,20 @@ pub mod crypto;
 pub mod account;
 pub mod transaction;
 pub mod defi;
+pub mod secrets;
+pub mod audit;
+pub mod validation;
+pub mod address;
+pub mod caip;
+pub mod pagination;
+pub mod pricing;
+pub mod invoice;
+pub mod exchange;
+pub mod time;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "test-vectors")]
+pub mod testing;
 
 // Re-export commonly used types for convenience
 pub use error::{Error, Result};
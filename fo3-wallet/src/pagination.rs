@@ -0,0 +1,298 @@
+//! Cursor-based pagination
+//!
+//! List endpoints return a [`Page`] with an opaque `next_cursor` instead of
+//! taking page numbers or offsets. The cursor records the sort key of the
+//! last item returned, so items inserted while a client pages through a list
+//! are neither skipped nor repeated, plus the item's position as a hint for
+//! offset-based sources.
+
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD as BASE64};
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+
+use crate::error::{Error, FieldViolation, Result};
+
+/// Default number of items per page
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+
+/// Largest number of items per page
+pub const MAX_PAGE_SIZE: usize = 100;
+
+/// Most fetches from an offset-based source for one page
+const MAX_SOURCE_FETCHES: usize = 10;
+
+/// Cursor format version
+const CURSOR_VERSION: u8 = 1;
+
+/// Position after the last item of a page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cursor<K> {
+    /// Format version
+    #[serde(rename = "v")]
+    version: u8,
+    /// Sort key of the last item returned
+    #[serde(rename = "k")]
+    pub key: K,
+    /// Position of the next item in the source, as a hint
+    #[serde(rename = "o")]
+    pub offset: usize,
+}
+
+impl<K: Serialize> Cursor<K> {
+    /// Create a cursor
+    pub fn new(key: K, offset: usize) -> Self {
+        Self { version: CURSOR_VERSION, key, offset }
+    }
+
+    /// Encode the cursor as an opaque string
+    pub fn encode(&self) -> Result<String> {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        Ok(BASE64.encode(json))
+    }
+}
+
+impl<K: DeserializeOwned> Cursor<K> {
+    /// Decode a cursor returned by [`Cursor::encode`]
+    pub fn decode(cursor: &str) -> Result<Self> {
+        let invalid = || Error::Validation(vec![FieldViolation::new("cursor", "is not a valid cursor")]);
+
+        let json = BASE64.decode(cursor).map_err(|_| invalid())?;
+        let cursor: Self = serde_json::from_slice(&json).map_err(|_| invalid())?;
+        if cursor.version != CURSOR_VERSION {
+            return Err(invalid());
+        }
+        Ok(cursor)
+    }
+}
+
+/// A request for one page of a list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageRequest {
+    /// Number of items per page
+    #[serde(default = "default_page_size")]
+    pub limit: usize,
+    /// Cursor returned with the previous page
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+fn default_page_size() -> usize {
+    DEFAULT_PAGE_SIZE
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self { limit: DEFAULT_PAGE_SIZE, cursor: None }
+    }
+}
+
+impl PageRequest {
+    /// Request the first page
+    pub fn first(limit: usize) -> Self {
+        Self { limit, cursor: None }
+    }
+
+    /// Request the page after `page`, if there is one
+    pub fn after<T>(limit: usize, page: &Page<T>) -> Option<Self> {
+        page.next_cursor.clone().map(|cursor| Self { limit, cursor: Some(cursor) })
+    }
+
+    /// Check the page size and decode the cursor
+    fn decode<K: DeserializeOwned>(&self) -> Result<Option<Cursor<K>>> {
+        if self.limit == 0 || self.limit > MAX_PAGE_SIZE {
+            return Err(Error::Validation(vec![FieldViolation::new("limit", format!("must be between 1 and {}", MAX_PAGE_SIZE))]));
+        }
+        self.cursor.as_deref().map(Cursor::decode).transpose()
+    }
+}
+
+/// One page of a list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    /// Items of the page
+    pub items: Vec<T>,
+    /// Cursor of the next page, or `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Paginate an in-memory collection in ascending order of `key`
+///
+/// Keys must be unique; use `std::cmp::Reverse` for descending order and
+/// add a tie-breaker such as an ID to keys that may repeat.
+pub fn paginate<T, K>(items: impl IntoIterator<Item = T>, key: impl Fn(&T) -> K, request: &PageRequest) -> Result<Page<T>>
+where
+    K: Ord + Serialize + DeserializeOwned,
+{
+    let after = request.decode::<K>()?;
+
+    let mut items: Vec<(K, T)> = items.into_iter().map(|item| (key(&item), item)).collect();
+    items.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let start = match &after {
+        Some(cursor) => items.partition_point(|(key, _)| *key <= cursor.key),
+        None => 0,
+    };
+    let mut page: Vec<(K, T)> = items.into_iter().skip(start).take(request.limit + 1).collect();
+
+    let next_cursor = if page.len() > request.limit {
+        page.truncate(request.limit);
+        let (last_key, _) = page.last().expect("limit is at least one");
+        Some(Cursor::new(last_key, start + request.limit).encode()?)
+    } else {
+        None
+    };
+
+    Ok(Page {
+        items: page.into_iter().map(|(_, item)| item).collect(),
+        next_cursor,
+    })
+}
+
+/// Paginate an offset-based source ordered by `key`
+///
+/// `fetch(limit, offset)` returns up to `limit` items starting at `offset`.
+/// Items that shifted past the cursor's offset hint because new items were
+/// inserted before it are recognised by their key and skipped.
+pub fn paginate_source<T, K>(
+    request: &PageRequest,
+    key: impl Fn(&T) -> K,
+    mut fetch: impl FnMut(usize, usize) -> Result<Vec<T>>,
+) -> Result<Page<T>>
+where
+    K: Ord + Serialize + DeserializeOwned,
+{
+    let after = request.decode::<K>()?;
+    let mut offset = after.as_ref().map_or(0, |cursor| cursor.offset);
+    let mut page: Vec<(K, T, usize)> = Vec::new();
+    let mut exhausted = false;
+
+    for _ in 0..MAX_SOURCE_FETCHES {
+        let batch_size = request.limit + 1;
+        let batch = fetch(batch_size, offset)?;
+        exhausted = batch.len() < batch_size;
+
+        for (position, item) in (offset..).zip(batch) {
+            let item_key = key(&item);
+            if !matches!(&after, Some(cursor) if item_key <= cursor.key) {
+                page.push((item_key, item, position));
+            }
+        }
+        offset += batch_size;
+
+        if exhausted || page.len() > request.limit {
+            break;
+        }
+    }
+
+    let next_cursor = if page.len() > request.limit {
+        page.truncate(request.limit);
+        let (last_key, _, position) = page.last().expect("limit is at least one");
+        Some(Cursor::new(last_key, position + 1).encode()?)
+    } else if !exhausted {
+        // The fetch limit was reached before the page filled up, so more
+        // items may follow: resume where this scan stopped
+        match (page.last(), &after) {
+            (Some((last_key, _, position)), _) => Some(Cursor::new(last_key, position + 1).encode()?),
+            (None, Some(cursor)) => Some(Cursor::new(&cursor.key, offset).encode()?),
+            (None, None) => None,
+        }
+    } else {
+        None
+    };
+
+    Ok(Page {
+        items: page.into_iter().map(|(_, item, _)| item).collect(),
+        next_cursor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Reverse;
+
+    #[test]
+    fn test_paginate_all_items_once() {
+        let items: Vec<u32> = (0..45).rev().collect();
+        let mut request = PageRequest::first(20);
+        let mut seen = Vec::new();
+
+        loop {
+            let page = paginate(items.clone(), |item| *item, &request).unwrap();
+            seen.extend(page.items.iter().copied());
+            match PageRequest::after(20, &page) {
+                Some(next) => request = next,
+                None => break,
+            }
+        }
+
+        assert_eq!(seen, (0..45).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_paginate_stable_under_inserts() {
+        // Newest first, keyed by (timestamp, id)
+        let mut items: Vec<(u64, u32)> = vec![(30, 3), (20, 2), (10, 1)];
+        let key = |item: &(u64, u32)| Reverse(*item);
+
+        let first = paginate(items.clone(), key, &PageRequest::first(2)).unwrap();
+        assert_eq!(first.items, vec![(30, 3), (20, 2)]);
+
+        items.insert(0, (40, 4));
+        let second = paginate(items, key, &PageRequest::after(2, &first).unwrap()).unwrap();
+        assert_eq!(second.items, vec![(10, 1)]);
+        assert!(second.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_paginate_source_skips_shifted_items() {
+        fn fetch(source: &[u64]) -> impl FnMut(usize, usize) -> Result<Vec<u64>> + '_ {
+            move |limit, offset| Ok(source.iter().skip(offset).take(limit).copied().collect())
+        }
+
+        let mut source: Vec<u64> = vec![50, 40, 30, 20, 10];
+        let key = |item: &u64| Reverse(*item);
+
+        let first = paginate_source(&PageRequest::first(2), key, fetch(&source)).unwrap();
+        assert_eq!(first.items, vec![50, 40]);
+
+        // A new item shifts everything one position back
+        source.insert(0, 60);
+        let second = paginate_source(&PageRequest::after(2, &first).unwrap(), key, fetch(&source)).unwrap();
+        assert_eq!(second.items, vec![30, 20]);
+
+        let third = paginate_source(&PageRequest::after(2, &second).unwrap(), key, fetch(&source)).unwrap();
+        assert_eq!(third.items, vec![10]);
+        assert!(third.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_paginate_source_resumes_after_fetch_limit() {
+        let source: Vec<u64> = (0..50).collect();
+        let fetch = |limit: usize, offset: usize| Ok(source.iter().skip(offset).take(limit).copied().collect());
+
+        // A stale offset hint leaves many already-seen items to skip
+        let mut request = PageRequest { limit: 1, cursor: Some(Cursor::new(30u64, 0).encode().unwrap()) };
+        let mut seen = Vec::new();
+        loop {
+            let page = paginate_source(&request, |item: &u64| *item, fetch).unwrap();
+            seen.extend(page.items.iter().copied());
+            match PageRequest::after(1, &page) {
+                Some(next) => request = next,
+                None => break,
+            }
+        }
+
+        assert_eq!(seen, (31..50).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_invalid_requests() {
+        assert!(paginate(vec![1u32], |item| *item, &PageRequest::first(0)).is_err());
+
+        let tampered = PageRequest { limit: 10, cursor: Some("not-a-cursor".to_string()) };
+        let error = paginate(vec![1u32], |item| *item, &tampered).unwrap_err();
+        assert_eq!(error.violations()[0].field, "cursor");
+    }
+}
@@ -0,0 +1,599 @@
+//! Historical OHLCV candles
+//!
+//! Candles are stored per asset at a configurable set of intervals and
+//! backfilled from an upstream [`CandleSource`] (Binance klines by default).
+//! [`CandleService::price_history`] serves charts from the store, fetching
+//! only the ranges it does not hold yet and downsampling to the requested
+//! interval and point count, so repeated requests never reach the upstream
+//! API. The service is also a [`PriceSource`] for the export P&L engine.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use serde::{Serialize, Deserialize};
+
+use crate::account::export::PriceSource;
+use crate::error::{Error, Result};
+
+/// Most candles a single history request may cover at the stored interval
+pub const MAX_HISTORY_CANDLES: u64 = 10_000;
+
+/// Most candles a single backfill may fetch
+pub const MAX_BACKFILL_CANDLES: u64 = 100_000;
+
+/// Candle interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Interval {
+    /// One minute
+    #[serde(rename = "1m")]
+    Minute,
+    /// Five minutes
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    /// Fifteen minutes
+    #[serde(rename = "15m")]
+    FifteenMinutes,
+    /// One hour
+    #[serde(rename = "1h")]
+    Hour,
+    /// Four hours
+    #[serde(rename = "4h")]
+    FourHours,
+    /// One day
+    #[serde(rename = "1d")]
+    Day,
+}
+
+impl Interval {
+    /// Get the interval length in seconds
+    pub fn seconds(self) -> u64 {
+        match self {
+            Self::Minute => 60,
+            Self::FiveMinutes => 300,
+            Self::FifteenMinutes => 900,
+            Self::Hour => 3600,
+            Self::FourHours => 14_400,
+            Self::Day => 86_400,
+        }
+    }
+
+    /// Get the interval's name, e.g. `1h`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Minute => "1m",
+            Self::FiveMinutes => "5m",
+            Self::FifteenMinutes => "15m",
+            Self::Hour => "1h",
+            Self::FourHours => "4h",
+            Self::Day => "1d",
+        }
+    }
+
+    /// Get the open time of the candle containing a timestamp
+    pub fn align(self, timestamp: u64) -> u64 {
+        timestamp - timestamp % self.seconds()
+    }
+
+    /// Whether candles of this interval combine exactly into candles of `coarser`
+    pub fn divides(self, coarser: Interval) -> bool {
+        coarser.seconds() % self.seconds() == 0
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Interval {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "1m" => Ok(Self::Minute),
+            "5m" => Ok(Self::FiveMinutes),
+            "15m" => Ok(Self::FifteenMinutes),
+            "1h" => Ok(Self::Hour),
+            "4h" => Ok(Self::FourHours),
+            "1d" => Ok(Self::Day),
+            _ => Err(Error::InvalidInput(format!("Unknown interval: {}", value))),
+        }
+    }
+}
+
+/// Open, high, low and close prices and traded volume over an interval
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    /// Unix timestamp the interval starts at
+    pub open_time: u64,
+    /// First price
+    pub open: f64,
+    /// Highest price
+    pub high: f64,
+    /// Lowest price
+    pub low: f64,
+    /// Last price
+    pub close: f64,
+    /// Traded volume, in the asset
+    pub volume: f64,
+}
+
+impl Candle {
+    /// Get the candle with its prices multiplied by an exchange rate
+    pub fn converted(&self, rate: f64) -> Candle {
+        Candle {
+            open: self.open * rate,
+            high: self.high * rate,
+            low: self.low * rate,
+            close: self.close * rate,
+            ..*self
+        }
+    }
+
+    /// Extend the candle with the following one
+    fn merge(&mut self, next: &Candle) {
+        self.high = self.high.max(next.high);
+        self.low = self.low.min(next.low);
+        self.close = next.close;
+        self.volume += next.volume;
+    }
+}
+
+/// Combine time-ordered candles into candles of a coarser interval
+pub fn downsample(candles: &[Candle], interval: Interval) -> Vec<Candle> {
+    let mut combined: Vec<Candle> = Vec::new();
+    for candle in candles {
+        let open_time = interval.align(candle.open_time);
+        match combined.last_mut() {
+            Some(last) if last.open_time == open_time => last.merge(candle),
+            _ => combined.push(Candle { open_time, ..*candle }),
+        }
+    }
+    combined
+}
+
+/// Combine runs of consecutive candles so at most `max_points` remain
+pub fn downsample_to(candles: &[Candle], max_points: usize) -> Vec<Candle> {
+    if max_points == 0 || candles.len() <= max_points {
+        return candles.to_vec();
+    }
+    let run = candles.len().div_ceil(max_points);
+    candles.chunks(run)
+        .map(|chunk| {
+            let mut candle = chunk[0];
+            chunk[1..].iter().for_each(|next| candle.merge(next));
+            candle
+        })
+        .collect()
+}
+
+/// Storage of candles
+pub trait CandleStore: Send + Sync {
+    /// Store candles, replacing any with the same open time
+    fn insert(&self, asset: &str, interval: Interval, candles: &[Candle]) -> Result<()>;
+
+    /// Get the candles opening in `[from, to)`, in time order
+    fn range(&self, asset: &str, interval: Interval, from: u64, to: u64) -> Result<Vec<Candle>>;
+
+    /// Get the latest candle opening at or before a timestamp
+    fn latest(&self, asset: &str, interval: Interval, at: u64) -> Result<Option<Candle>>;
+}
+
+/// In-memory candle store
+#[derive(Debug, Default)]
+pub struct InMemoryCandleStore {
+    candles: RwLock<HashMap<(String, Interval), BTreeMap<u64, Candle>>>,
+}
+
+impl InMemoryCandleStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CandleStore for InMemoryCandleStore {
+    fn insert(&self, asset: &str, interval: Interval, candles: &[Candle]) -> Result<()> {
+        let mut stored = self.candles.write().unwrap();
+        let series = stored.entry((asset.to_string(), interval)).or_default();
+        for candle in candles {
+            series.insert(candle.open_time, *candle);
+        }
+        Ok(())
+    }
+
+    fn range(&self, asset: &str, interval: Interval, from: u64, to: u64) -> Result<Vec<Candle>> {
+        if from >= to {
+            return Ok(vec![]);
+        }
+        Ok(self.candles.read().unwrap()
+            .get(&(asset.to_string(), interval))
+            .map(|series| series.range(from..to).map(|(_, candle)| *candle).collect())
+            .unwrap_or_default())
+    }
+
+    fn latest(&self, asset: &str, interval: Interval, at: u64) -> Result<Option<Candle>> {
+        Ok(self.candles.read().unwrap()
+            .get(&(asset.to_string(), interval))
+            .and_then(|series| series.range(..=at).next_back())
+            .map(|(_, candle)| *candle))
+    }
+}
+
+/// Upstream source of historical candles
+pub trait CandleSource: Send + Sync {
+    /// Get the source name
+    fn name(&self) -> &str;
+
+    /// Most candles returned by a single fetch
+    fn max_candles(&self) -> usize {
+        1000
+    }
+
+    /// Fetch the candles opening in `[from, to)`
+    fn fetch(&self, asset: &str, interval: Interval, from: u64, to: u64) -> Result<Vec<Candle>>;
+}
+
+/// Candles from the Binance klines API, priced in a stablecoin
+#[cfg(feature = "rpc")]
+pub struct BinanceCandleSource {
+    /// API base URL
+    url: String,
+    /// Quote asset the pair is priced in
+    quote: String,
+    /// HTTP client
+    http: reqwest::Client,
+}
+
+#[cfg(feature = "rpc")]
+impl BinanceCandleSource {
+    /// Public Binance API
+    pub const DEFAULT_URL: &'static str = "https://api.binance.com";
+
+    /// Create a source for the public API, pricing in USDT
+    pub fn new() -> Self {
+        Self::with_url(Self::DEFAULT_URL, "USDT")
+    }
+
+    /// Create a source for a custom endpoint and quote asset
+    pub fn with_url(url: &str, quote: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            quote: quote.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Parse a klines response
+    ///
+    /// Each kline is an array of open time (milliseconds), open, high, low,
+    /// close and volume, followed by fields that are ignored.
+    pub fn parse_klines(response: &serde_json::Value) -> Result<Vec<Candle>> {
+        let invalid = || Error::Provider(format!("Invalid klines response: {}", response));
+        let price = |value: &serde_json::Value| value.as_str().and_then(|v| v.parse::<f64>().ok()).ok_or_else(invalid);
+
+        response.as_array().ok_or_else(invalid)?
+            .iter()
+            .map(|kline| {
+                let kline = kline.as_array().filter(|kline| kline.len() >= 6).ok_or_else(invalid)?;
+                Ok(Candle {
+                    open_time: kline[0].as_u64().ok_or_else(invalid)? / 1000,
+                    open: price(&kline[1])?,
+                    high: price(&kline[2])?,
+                    low: price(&kline[3])?,
+                    close: price(&kline[4])?,
+                    volume: price(&kline[5])?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl Default for BinanceCandleSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl CandleSource for BinanceCandleSource {
+    fn name(&self) -> &str {
+        "binance"
+    }
+
+    fn fetch(&self, asset: &str, interval: Interval, from: u64, to: u64) -> Result<Vec<Candle>> {
+        let symbol = format!("{}{}", asset.to_uppercase(), self.quote);
+        let url = format!("{}/api/v3/klines", self.url);
+        let query = [
+            ("symbol", symbol),
+            ("interval", interval.to_string()),
+            ("startTime", (from * 1000).to_string()),
+            ("endTime", (to * 1000 - 1).to_string()),
+            ("limit", self.max_candles().to_string()),
+        ];
+        let response: serde_json::Value = crate::transaction::block_on(async {
+            self.http.get(&url)
+                .query(&query)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+        })?
+        .map_err(|e| Error::Network(format!("Klines request failed: {}", e)))?;
+
+        Self::parse_klines(&response)
+    }
+}
+
+/// A request for an asset's price history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceHistoryRequest {
+    /// Asset symbol
+    pub asset: String,
+    /// Candle interval
+    pub interval: Interval,
+    /// Unix timestamp of the first candle
+    pub from: u64,
+    /// Unix timestamp the history ends at (exclusive)
+    pub to: u64,
+    /// Most candles to return, combining neighbours if there are more
+    #[serde(default)]
+    pub max_points: Option<usize>,
+}
+
+/// Outcome of a backfill
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackfillReport {
+    /// Asset symbol
+    pub asset: String,
+    /// Candle interval
+    pub interval: Interval,
+    /// Candles the range spans
+    pub expected: u64,
+    /// Candles that were already stored
+    pub already_stored: u64,
+    /// Candles fetched from the source
+    pub fetched: u64,
+    /// Upstream requests made
+    pub requests: u64,
+}
+
+/// Stored candles with backfill from an upstream source
+pub struct CandleService<S> {
+    /// Candle storage
+    store: S,
+    /// Upstream source, if backfill is enabled
+    source: Option<Box<dyn CandleSource>>,
+    /// Intervals candles are stored at, finest first
+    intervals: Vec<Interval>,
+}
+
+impl<S: CandleStore> CandleService<S> {
+    /// Create a service storing candles at the given intervals
+    pub fn new(store: S, mut intervals: Vec<Interval>) -> Result<Self> {
+        if intervals.is_empty() {
+            return Err(Error::InvalidInput("At least one candle interval must be stored".to_string()));
+        }
+        intervals.sort();
+        intervals.dedup();
+        Ok(Self { store, source: None, intervals })
+    }
+
+    /// Backfill missing candles from a source
+    pub fn with_source(mut self, source: Box<dyn CandleSource>) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Get the intervals candles are stored at
+    pub fn intervals(&self) -> &[Interval] {
+        &self.intervals
+    }
+
+    /// Get the store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Fetch the stored interval's candles missing in `[from, to)` from the source
+    ///
+    /// Only complete candles are fetched: the range is cut at `now`.
+    pub fn backfill(&self, asset: &str, interval: Interval, from: u64, to: u64, now: u64) -> Result<BackfillReport> {
+        if !self.intervals.contains(&interval) {
+            return Err(Error::InvalidInput(format!("Candles are not stored at {}", interval)));
+        }
+        let source = self.source.as_ref()
+            .ok_or_else(|| Error::NotSupported("No candle source is configured".to_string()))?;
+        let step = interval.seconds();
+        let start = interval.align(from);
+        let end = interval.align(to.min(now));
+        let expected = end.saturating_sub(start) / step;
+        if expected > MAX_BACKFILL_CANDLES {
+            return Err(Error::InvalidInput(format!("A backfill covers at most {} candles", MAX_BACKFILL_CANDLES)));
+        }
+
+        let stored: Vec<u64> = self.store.range(asset, interval, start, end)?.iter().map(|candle| candle.open_time).collect();
+        let mut report = BackfillReport {
+            asset: asset.to_string(),
+            interval,
+            expected,
+            already_stored: stored.len() as u64,
+            fetched: 0,
+            requests: 0,
+        };
+
+        // Fetch each gap between stored candles, in pages the source accepts
+        let page = source.max_candles() as u64 * step;
+        let mut gap_start = start;
+        for boundary in stored.iter().copied().chain(std::iter::once(end)) {
+            let mut cursor = gap_start;
+            while cursor < boundary {
+                let page_end = (cursor + page).min(boundary);
+                let candles: Vec<Candle> = source.fetch(asset, interval, cursor, page_end)?
+                    .into_iter()
+                    .filter(|candle| candle.open_time >= cursor && candle.open_time < page_end)
+                    .collect();
+                self.store.insert(asset, interval, &candles)?;
+                report.fetched += candles.len() as u64;
+                report.requests += 1;
+                cursor = page_end;
+            }
+            gap_start = boundary + step;
+        }
+
+        Ok(report)
+    }
+
+    /// Get an asset's candles, backfilling gaps and downsampling as requested
+    pub fn price_history(&self, request: &PriceHistoryRequest, now: u64) -> Result<Vec<Candle>> {
+        if request.from >= request.to {
+            return Err(Error::InvalidInput("History must end after it starts".to_string()));
+        }
+        // Serve from the coarsest stored interval the requested one is made of
+        let stored = self.intervals.iter().rev()
+            .find(|interval| interval.divides(request.interval))
+            .copied()
+            .ok_or_else(|| Error::InvalidInput(format!("No stored interval combines into {}", request.interval)))?;
+        let from = request.interval.align(request.from);
+        if (request.to - from) / stored.seconds() > MAX_HISTORY_CANDLES {
+            return Err(Error::InvalidInput(format!("History covers at most {} candles of {}", MAX_HISTORY_CANDLES, stored)));
+        }
+
+        if self.source.is_some() {
+            self.backfill(&request.asset, stored, from, request.to, now)?;
+        }
+        let candles = self.store.range(&request.asset, stored, from, request.to)?;
+        let candles = if stored == request.interval { candles } else { downsample(&candles, request.interval) };
+
+        Ok(match request.max_points {
+            Some(max_points) => downsample_to(&candles, max_points),
+            None => candles,
+        })
+    }
+}
+
+impl<S: CandleStore> PriceSource for CandleService<S> {
+    fn price_at(&self, asset: &str, timestamp: u64) -> Result<Option<f64>> {
+        // The finest interval gives the closest close before the timestamp
+        let candle = self.store.latest(asset, self.intervals[0], timestamp)?;
+        Ok(candle.map(|candle| candle.close))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    const HOUR: u64 = 3600;
+
+    fn candle(open_time: u64, price: f64) -> Candle {
+        Candle { open_time, open: price, high: price + 1.0, low: price - 1.0, close: price + 0.5, volume: 10.0 }
+    }
+
+    /// Source returning a candle per interval at a price equal to its hour, recording requests
+    #[derive(Default)]
+    struct TestSource {
+        requests: Mutex<Vec<(u64, u64)>>,
+    }
+
+    impl CandleSource for TestSource {
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn max_candles(&self) -> usize {
+            24
+        }
+
+        fn fetch(&self, _asset: &str, interval: Interval, from: u64, to: u64) -> Result<Vec<Candle>> {
+            self.requests.lock().unwrap().push((from, to));
+            Ok((from..to).step_by(interval.seconds() as usize).map(|t| candle(t, (t / HOUR) as f64)).collect())
+        }
+    }
+
+    #[test]
+    fn test_interval() {
+        assert_eq!("4h".parse::<Interval>().unwrap(), Interval::FourHours);
+        assert!("2h".parse::<Interval>().is_err());
+        assert_eq!(Interval::Hour.align(2 * HOUR + 59), 2 * HOUR);
+        assert!(Interval::Hour.divides(Interval::Day));
+        assert!(!Interval::FourHours.divides(Interval::Hour));
+        assert_eq!(serde_json::to_string(&Interval::FifteenMinutes).unwrap(), "\"15m\"");
+    }
+
+    #[test]
+    fn test_downsample() {
+        let hourly: Vec<Candle> = (0..8).map(|hour| candle(hour * HOUR, hour as f64)).collect();
+
+        let four_hourly = downsample(&hourly, Interval::FourHours);
+        assert_eq!(four_hourly.len(), 2);
+        assert_eq!(four_hourly[1], Candle { open_time: 4 * HOUR, open: 4.0, high: 8.0, low: 3.0, close: 7.5, volume: 40.0 });
+
+        let points = downsample_to(&hourly, 3);
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0].volume, 30.0);
+        assert_eq!(points[2].close, 7.5);
+        assert_eq!(downsample_to(&hourly, 100).len(), 8);
+    }
+
+    #[test]
+    fn test_backfill_fetches_only_gaps() {
+        let service = CandleService::new(InMemoryCandleStore::new(), vec![Interval::Hour, Interval::Day]).unwrap()
+            .with_source(Box::new(TestSource::default()));
+        service.store().insert("ETH", Interval::Hour, &[candle(10 * HOUR, 0.0)]).unwrap();
+
+        // Two days at 24 candles per request, around the stored candle
+        let report = service.backfill("ETH", Interval::Hour, 0, 48 * HOUR, 100 * HOUR).unwrap();
+        assert_eq!(report.expected, 48);
+        assert_eq!(report.already_stored, 1);
+        assert_eq!(report.fetched, 47);
+        assert_eq!(report.requests, 3);
+
+        // A second run has nothing to fetch
+        let report = service.backfill("ETH", Interval::Hour, 0, 48 * HOUR, 100 * HOUR).unwrap();
+        assert_eq!((report.fetched, report.requests), (0, 0));
+
+        // Incomplete candles are left for later
+        let report = service.backfill("ETH", Interval::Hour, 48 * HOUR, 60 * HOUR, 50 * HOUR + 1).unwrap();
+        assert_eq!(report.fetched, 2);
+        assert!(service.backfill("ETH", Interval::Minute, 0, HOUR, HOUR).is_err());
+    }
+
+    #[test]
+    fn test_price_history() {
+        let service = CandleService::new(InMemoryCandleStore::new(), vec![Interval::Hour]).unwrap()
+            .with_source(Box::new(TestSource::default()));
+
+        let request = PriceHistoryRequest { asset: "ETH".to_string(), interval: Interval::Day, from: 0, to: 48 * HOUR, max_points: None };
+        let daily = service.price_history(&request, 100 * HOUR).unwrap();
+        assert_eq!(daily.len(), 2);
+        assert_eq!((daily[1].open, daily[1].close), (24.0, 47.5));
+
+        let request = PriceHistoryRequest { interval: Interval::Hour, max_points: Some(12), ..request };
+        assert_eq!(service.price_history(&request, 100 * HOUR).unwrap().len(), 12);
+
+        let request = PriceHistoryRequest { interval: Interval::Minute, ..request };
+        assert!(service.price_history(&request, 100 * HOUR).is_err());
+
+        assert_eq!(service.price_at("ETH", 5 * HOUR + 30).unwrap(), Some(5.5));
+        assert_eq!(service.price_at("BTC", 5 * HOUR).unwrap(), None);
+    }
+
+    #[cfg(feature = "rpc")]
+    #[test]
+    fn test_parse_klines() {
+        let response = serde_json::json!([
+            [1_700_000_000_000u64, "2000.1", "2010.0", "1990.5", "2005.0", "123.4", 1_700_003_599_999u64, "0", 10, "0", "0", "0"],
+        ]);
+        let candles = BinanceCandleSource::parse_klines(&response).unwrap();
+        assert_eq!(candles, vec![Candle { open_time: 1_700_000_000, open: 2000.1, high: 2010.0, low: 1990.5, close: 2005.0, volume: 123.4 }]);
+
+        assert!(BinanceCandleSource::parse_klines(&serde_json::json!([[1, "x"]])).is_err());
+    }
+}
@@ -0,0 +1,417 @@
+//! Fiat exchange rates
+//!
+//! Crypto prices are quoted in USD. A [`FiatRateService`] converts them to
+//! other fiat currencies using daily reference rates from a
+//! [`FiatRateSource`] (the ECB, or Open Exchange Rates), refreshed at most
+//! once a day. Each user's preferred display currency is kept in
+//! [`DisplayCurrencies`].
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use serde::{Serialize, Deserialize};
+
+use crate::error::{Error, Result};
+
+/// Currency crypto prices are quoted in
+pub const PRICE_CURRENCY: &str = "USD";
+
+/// Seconds in a day
+const DAY: u64 = 86_400;
+
+/// Check that a currency code is three uppercase letters (ISO 4217)
+pub fn validate_currency(code: &str) -> Result<()> {
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(Error::InvalidInput(format!("Invalid fiat currency code: {}", code)));
+    }
+    Ok(())
+}
+
+/// Reference exchange rates on a given day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FiatRates {
+    /// Currency the rates are relative to
+    pub base: String,
+    /// Unix timestamp of the day the rates are for
+    pub date: u64,
+    /// Units of each currency per unit of the base
+    pub rates: HashMap<String, f64>,
+}
+
+impl FiatRates {
+    /// Get the units of `to` per unit of `from`
+    pub fn rate(&self, from: &str, to: &str) -> Result<f64> {
+        let per_base = |currency: &str| -> Result<f64> {
+            if currency == self.base {
+                return Ok(1.0);
+            }
+            self.rates.get(currency).copied()
+                .filter(|rate| *rate > 0.0)
+                .ok_or_else(|| Error::InvalidInput(format!("No exchange rate for {}", currency)))
+        };
+        Ok(per_base(to)? / per_base(from)?)
+    }
+
+    /// Whether a currency can be converted to and from
+    pub fn supports(&self, currency: &str) -> bool {
+        currency == self.base || self.rates.contains_key(currency)
+    }
+}
+
+/// An amount of fiat money
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FiatAmount {
+    /// Amount in whole units, rounded to cents
+    pub amount: f64,
+    /// Currency code
+    pub currency: String,
+}
+
+/// Source of daily exchange rates
+pub trait FiatRateSource: Send + Sync {
+    /// Get the source name
+    fn name(&self) -> &str;
+
+    /// Fetch the latest rates
+    fn fetch_rates(&self) -> Result<FiatRates>;
+}
+
+/// European Central Bank euro foreign exchange reference rates
+#[cfg(feature = "rpc")]
+pub struct EcbRateSource {
+    /// Daily rates XML URL
+    url: String,
+    /// HTTP client
+    http: reqwest::Client,
+}
+
+#[cfg(feature = "rpc")]
+impl EcbRateSource {
+    /// Daily reference rates published by the ECB
+    pub const DEFAULT_URL: &'static str = "https://www.ecb.europa.eu/stats/eurofxref/eurofxref-daily.xml";
+
+    /// Create a source for the ECB's daily rates
+    pub fn new() -> Self {
+        Self::with_url(Self::DEFAULT_URL)
+    }
+
+    /// Create a source for a mirror of the daily rates
+    pub fn with_url(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl Default for EcbRateSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parse the ECB's daily reference rates XML
+///
+/// Only the `Cube` elements are read: one carries the `time` of the rates
+/// and the others a `currency` and its `rate` per euro.
+pub fn parse_ecb_rates(xml: &str) -> Result<FiatRates> {
+    let attribute = |element: &str, name: &str| -> Option<String> {
+        let start = element.find(&format!("{}='", name)).map(|i| (i, '\''))
+            .or_else(|| element.find(&format!("{}=\"", name)).map(|i| (i, '"')))?;
+        let value = &element[start.0 + name.len() + 2..];
+        Some(value[..value.find(start.1)?].to_string())
+    };
+
+    let mut date = None;
+    let mut rates = HashMap::new();
+    for element in xml.split("<Cube").skip(1) {
+        let element = &element[..element.find('>').unwrap_or(element.len())];
+        if let Some(time) = attribute(element, "time") {
+            date = crate::time::parse_rfc3339(&format!("{}T00:00:00Z", time));
+        }
+        if let (Some(currency), Some(rate)) = (attribute(element, "currency"), attribute(element, "rate")) {
+            let rate = rate.parse::<f64>()
+                .map_err(|_| Error::Provider(format!("Invalid ECB rate for {}: {}", currency, rate)))?;
+            rates.insert(currency, rate);
+        }
+    }
+
+    let date = date.ok_or_else(|| Error::Provider("ECB rates carry no date".to_string()))?;
+    if rates.is_empty() {
+        return Err(Error::Provider("ECB rates carry no currencies".to_string()));
+    }
+    Ok(FiatRates { base: "EUR".to_string(), date, rates })
+}
+
+#[cfg(feature = "rpc")]
+impl FiatRateSource for EcbRateSource {
+    fn name(&self) -> &str {
+        "ecb"
+    }
+
+    fn fetch_rates(&self) -> Result<FiatRates> {
+        let xml = crate::transaction::block_on(async {
+            self.http.get(&self.url)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await
+        })?
+        .map_err(|e| Error::Network(format!("ECB rates request failed: {}", e)))?;
+
+        parse_ecb_rates(&xml)
+    }
+}
+
+/// Open Exchange Rates latest rates
+#[cfg(feature = "rpc")]
+pub struct OpenExchangeRatesSource {
+    /// API base URL
+    url: String,
+    /// App ID
+    app_id: String,
+    /// HTTP client
+    http: reqwest::Client,
+}
+
+#[cfg(feature = "rpc")]
+impl OpenExchangeRatesSource {
+    /// Open Exchange Rates API
+    pub const DEFAULT_URL: &'static str = "https://openexchangerates.org/api";
+
+    /// Create a source for the public API
+    pub fn new(app_id: &str) -> Self {
+        Self {
+            url: Self::DEFAULT_URL.to_string(),
+            app_id: app_id.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+}
+
+/// Parse an Open Exchange Rates `latest.json` response
+pub fn parse_openexchange_rates(response: &serde_json::Value) -> Result<FiatRates> {
+    let invalid = || Error::Provider(format!("Invalid exchange rates response: {}", response));
+    let timestamp = response.get("timestamp").and_then(|v| v.as_u64()).ok_or_else(invalid)?;
+    let base = response.get("base").and_then(|v| v.as_str()).ok_or_else(invalid)?;
+    let rates = response.get("rates").and_then(|v| v.as_object()).ok_or_else(invalid)?
+        .iter()
+        .map(|(currency, rate)| rate.as_f64().map(|rate| (currency.clone(), rate)).ok_or_else(invalid))
+        .collect::<Result<HashMap<_, _>>>()?;
+
+    Ok(FiatRates { base: base.to_string(), date: timestamp - timestamp % DAY, rates })
+}
+
+#[cfg(feature = "rpc")]
+impl FiatRateSource for OpenExchangeRatesSource {
+    fn name(&self) -> &str {
+        "openexchangerates"
+    }
+
+    fn fetch_rates(&self) -> Result<FiatRates> {
+        let url = format!("{}/latest.json", self.url);
+        let response: serde_json::Value = crate::transaction::block_on(async {
+            self.http.get(&url)
+                .query(&[("app_id", &self.app_id)])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+        })?
+        .map_err(|e| Error::Network(format!("Exchange rates request failed: {}", e)))?;
+
+        parse_openexchange_rates(&response)
+    }
+}
+
+/// Rates cached by the service, with when they were fetched
+#[derive(Debug, Clone)]
+struct CachedRates {
+    rates: FiatRates,
+    fetched_at: u64,
+}
+
+/// Daily exchange rates converting USD prices to display currencies
+#[derive(Default)]
+pub struct FiatRateService {
+    /// Rate source, if conversion is enabled
+    source: Option<Box<dyn FiatRateSource>>,
+    /// Last rates fetched
+    cached: RwLock<Option<CachedRates>>,
+}
+
+impl FiatRateService {
+    /// Create a service fetching rates from a source
+    pub fn new(source: Box<dyn FiatRateSource>) -> Self {
+        Self { source: Some(source), cached: RwLock::new(None) }
+    }
+
+    /// Create a service with fixed rates
+    pub fn with_rates(rates: FiatRates) -> Self {
+        Self { source: None, cached: RwLock::new(Some(CachedRates { fetched_at: rates.date, rates })) }
+    }
+
+    /// Get the current rates, fetching them once a day
+    ///
+    /// When a refresh fails, the previous day's rates are kept for the rest
+    /// of the day rather than failing every conversion.
+    pub fn rates(&self, now: u64) -> Result<FiatRates> {
+        let cached = self.cached.read().unwrap().clone();
+        let fresh = cached.as_ref().filter(|cached| cached.fetched_at / DAY == now / DAY);
+        if let Some(cached) = fresh {
+            return Ok(cached.rates.clone());
+        }
+        let Some(source) = &self.source else {
+            return cached.map(|cached| cached.rates)
+                .ok_or_else(|| Error::NotSupported("No exchange rate source is configured".to_string()));
+        };
+
+        match source.fetch_rates() {
+            Ok(rates) => {
+                *self.cached.write().unwrap() = Some(CachedRates { rates: rates.clone(), fetched_at: now });
+                Ok(rates)
+            }
+            Err(e) => {
+                let cached = cached.ok_or(e)?;
+                *self.cached.write().unwrap() = Some(CachedRates { rates: cached.rates.clone(), fetched_at: now });
+                Ok(cached.rates)
+            }
+        }
+    }
+
+    /// Get the units of `currency` per US dollar
+    pub fn usd_rate(&self, currency: &str, now: u64) -> Result<f64> {
+        validate_currency(currency)?;
+        if currency == PRICE_CURRENCY {
+            return Ok(1.0);
+        }
+        self.rates(now)?.rate(PRICE_CURRENCY, currency)
+    }
+
+    /// Convert a USD amount to a currency
+    pub fn convert(&self, usd: f64, currency: &str, now: u64) -> Result<FiatAmount> {
+        let amount = usd * self.usd_rate(currency, now)?;
+        Ok(FiatAmount { amount: (amount * 100.0).round() / 100.0, currency: currency.to_string() })
+    }
+}
+
+/// Display currency of each user, falling back to USD
+#[derive(Debug, Default)]
+pub struct DisplayCurrencies {
+    users: RwLock<HashMap<String, String>>,
+}
+
+impl DisplayCurrencies {
+    /// Create an empty set of preferences
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get a user's display currency
+    pub fn currency_for(&self, user: Option<&str>) -> String {
+        user.and_then(|user| self.users.read().unwrap().get(user).cloned())
+            .unwrap_or_else(|| PRICE_CURRENCY.to_string())
+    }
+
+    /// Set a user's display currency
+    pub fn set(&self, user: &str, currency: &str) -> Result<()> {
+        validate_currency(currency)?;
+        self.users.write().unwrap().insert(user.to_string(), currency.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    const ECB_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01" xmlns="http://www.ecb.int/vocabulary/2002-08-01/eurofxref">
+	<gesmes:subject>Reference rates</gesmes:subject>
+	<Cube>
+		<Cube time='2024-01-02'>
+			<Cube currency='USD' rate='1.0956'/>
+			<Cube currency='JPY' rate='155.00'/>
+			<Cube currency='GBP' rate='0.86558'/>
+		</Cube>
+	</Cube>
+</gesmes:Envelope>"#;
+
+    /// Source returning the ECB sample, counting fetches, failing once drained
+    struct TestSource {
+        fetches: Arc<Mutex<u32>>,
+        available: u32,
+    }
+
+    impl FiatRateSource for TestSource {
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn fetch_rates(&self) -> Result<FiatRates> {
+            let mut fetches = self.fetches.lock().unwrap();
+            *fetches += 1;
+            if *fetches > self.available {
+                return Err(Error::Network("unavailable".to_string()));
+            }
+            parse_ecb_rates(ECB_XML)
+        }
+    }
+
+    #[test]
+    fn test_parse_ecb_rates() {
+        let rates = parse_ecb_rates(ECB_XML).unwrap();
+        assert_eq!(rates.base, "EUR");
+        assert_eq!(rates.date, 1_704_153_600);
+        assert_eq!(rates.rates.len(), 3);
+
+        // Cross rates go through the base
+        assert!((rates.rate("USD", "GBP").unwrap() - 0.86558 / 1.0956).abs() < 1e-12);
+        assert_eq!(rates.rate("EUR", "JPY").unwrap(), 155.0);
+        assert!(rates.rate("USD", "CHF").is_err());
+        assert!(parse_ecb_rates("<Cube time='2024-01-02'/>").is_err());
+    }
+
+    #[test]
+    fn test_parse_openexchange_rates() {
+        let response = serde_json::json!({ "timestamp": 1_704_200_000, "base": "USD", "rates": { "EUR": 0.9127, "JPY": 141.47 } });
+        let rates = parse_openexchange_rates(&response).unwrap();
+        assert_eq!(rates.date, 1_704_153_600);
+        assert_eq!(rates.rate("USD", "EUR").unwrap(), 0.9127);
+    }
+
+    #[test]
+    fn test_daily_refresh() {
+        let fetches = Arc::new(Mutex::new(0));
+        let service = FiatRateService::new(Box::new(TestSource { fetches: fetches.clone(), available: 1 }));
+
+        let converted = service.convert(1095.6, "EUR", DAY).unwrap();
+        assert_eq!(converted, FiatAmount { amount: 1000.0, currency: "EUR".to_string() });
+        service.convert(10.0, "GBP", DAY + 3600).unwrap();
+
+        // The next day's refresh fails, so yesterday's rates are kept without retrying all day
+        assert_eq!(service.convert(1095.6, "EUR", 2 * DAY).unwrap().amount, 1000.0);
+        service.convert(10.0, "GBP", 2 * DAY + 3600).unwrap();
+        assert_eq!(*fetches.lock().unwrap(), 2);
+        assert_eq!(service.convert(5.0, "USD", 2 * DAY).unwrap().amount, 5.0);
+        assert!(service.convert(5.0, "usd", 2 * DAY).is_err());
+
+        let unconfigured = FiatRateService::default();
+        assert!(unconfigured.convert(5.0, "EUR", DAY).is_err());
+        assert!(unconfigured.convert(5.0, "USD", DAY).is_ok());
+    }
+
+    #[test]
+    fn test_display_currencies() {
+        let currencies = DisplayCurrencies::new();
+        assert_eq!(currencies.currency_for(Some("alice")), "USD");
+        currencies.set("alice", "JPY").unwrap();
+        assert_eq!(currencies.currency_for(Some("alice")), "JPY");
+        assert_eq!(currencies.currency_for(None), "USD");
+        assert!(currencies.set("alice", "Yen").is_err());
+    }
+}
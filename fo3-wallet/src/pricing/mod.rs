@@ -0,0 +1,10 @@
+//! Pricing
+//!
+//! Historical OHLCV candles of crypto assets, and fiat exchange rates for
+//! showing values in each user's display currency.
+
+mod candles;
+mod fiat;
+
+pub use candles::*;
+pub use fiat::*;
@@ -0,0 +1,535 @@
+//! Secrets management
+//!
+//! Credentials such as RPC API keys are read through a [`SecretProvider`]
+//! rather than directly from the environment, so a deployment can keep them
+//! in files mounted by its orchestrator, HashiCorp Vault, or as ciphertext
+//! decrypted by AWS KMS. [`CachedSecrets`] re-reads secrets after a TTL so
+//! rotated values are picked up without a restart.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[cfg(feature = "rpc")]
+use hmac::{Hmac, Mac};
+#[cfg(feature = "rpc")]
+use hmac::digest::KeyInit;
+#[cfg(feature = "rpc")]
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+#[cfg(feature = "rpc")]
+use crate::time::civil_from_days;
+
+/// A secret value
+///
+/// The value is redacted from `Debug` output.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret {
+    value: String,
+    /// Version reported by the backend, if any
+    pub version: Option<String>,
+}
+
+impl Secret {
+    /// Create a secret
+    pub fn new(value: impl Into<String>) -> Self {
+        Self { value: value.into(), version: None }
+    }
+
+    /// Create a secret with a backend version
+    pub fn with_version(value: impl Into<String>, version: impl Into<String>) -> Self {
+        Self { value: value.into(), version: Some(version.into()) }
+    }
+
+    /// Get the secret value
+    pub fn expose(&self) -> &str {
+        &self.value
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Secret")
+            .field("value", &"[REDACTED]")
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+/// Source of secrets
+pub trait SecretProvider: Send + Sync {
+    /// Provider name, for logs
+    fn name(&self) -> &str;
+
+    /// Look up a secret by name, returning `None` if this provider does not have it
+    fn get(&self, name: &str) -> Result<Option<Secret>>;
+
+    /// Look up a secret that must exist
+    fn require(&self, name: &str) -> Result<Secret> {
+        self.get(name)?
+            .ok_or_else(|| Error::InvalidInput(format!("Secret not found: {}", name)))
+    }
+}
+
+/// Secrets from environment variables
+///
+/// The secret `rpc_api_key` is read from `{prefix}RPC_API_KEY`.
+#[derive(Debug, Clone)]
+pub struct EnvSecrets {
+    prefix: String,
+}
+
+impl EnvSecrets {
+    /// Create a provider reading variables with a prefix such as `FO3_`
+    pub fn new(prefix: &str) -> Self {
+        Self { prefix: prefix.to_string() }
+    }
+
+    /// Get the variable a secret is read from
+    pub fn variable(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name.to_ascii_uppercase().replace(['-', '.', '/'], "_"))
+    }
+}
+
+impl SecretProvider for EnvSecrets {
+    fn name(&self) -> &str {
+        "env"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Secret>> {
+        Ok(std::env::var(self.variable(name)).ok().map(Secret::new))
+    }
+}
+
+/// Secrets from files in a directory, one file per secret
+///
+/// Matches the layout of Docker and Kubernetes secret mounts. Trailing
+/// newlines are trimmed.
+#[derive(Debug, Clone)]
+pub struct FileSecrets {
+    dir: PathBuf,
+}
+
+impl FileSecrets {
+    /// Create a provider reading from a directory
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl SecretProvider for FileSecrets {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Secret>> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(Error::InvalidInput(format!("Invalid secret name: {}", name)));
+        }
+
+        match std::fs::read_to_string(self.dir.join(name)) {
+            Ok(value) => Ok(Some(Secret::new(value.trim_end_matches(['\r', '\n'])))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Error::Provider(format!("Failed to read secret {}: {}", name, e))),
+        }
+    }
+}
+
+/// Secrets from several providers, in order of precedence
+pub struct SecretChain {
+    providers: Vec<Box<dyn SecretProvider>>,
+}
+
+impl SecretChain {
+    /// Create a chain; earlier providers take precedence
+    pub fn new(providers: Vec<Box<dyn SecretProvider>>) -> Self {
+        Self { providers }
+    }
+}
+
+impl SecretProvider for SecretChain {
+    fn name(&self) -> &str {
+        "chain"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Secret>> {
+        for provider in &self.providers {
+            if let Some(secret) = provider.get(name)? {
+                return Ok(Some(secret));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Secrets cached for a TTL
+///
+/// Values are re-read from the inner provider once the TTL elapses or on
+/// [`CachedSecrets::refresh`], so rotated secrets are picked up in place.
+pub struct CachedSecrets<P> {
+    inner: P,
+    ttl: u64,
+    cache: Mutex<HashMap<String, (Secret, u64)>>,
+}
+
+impl<P: SecretProvider> CachedSecrets<P> {
+    /// Cache secrets of a provider for `ttl` seconds
+    pub fn new(inner: P, ttl: u64) -> Self {
+        Self { inner, ttl, cache: Mutex::new(HashMap::new()) }
+    }
+
+    /// Get a secret as of `now`, re-reading it if the cached value is stale
+    pub fn get_at(&self, name: &str, now: u64) -> Result<Option<Secret>> {
+        if let Some((secret, fetched_at)) = self.cache.lock().unwrap().get(name) {
+            if now < fetched_at + self.ttl {
+                return Ok(Some(secret.clone()));
+            }
+        }
+
+        let secret = self.inner.get(name)?;
+        let mut cache = self.cache.lock().unwrap();
+        match &secret {
+            Some(secret) => { cache.insert(name.to_string(), (secret.clone(), now)); }
+            None => { cache.remove(name); }
+        }
+        Ok(secret)
+    }
+
+    /// Re-read a secret now, returning whether its value changed
+    pub fn refresh(&self, name: &str) -> Result<bool> {
+        let previous = self.cache.lock().unwrap().remove(name).map(|(secret, _)| secret);
+        let current = self.get_at(name, crate::time::unix_timestamp()?)?;
+        Ok(previous != current)
+    }
+}
+
+impl<P: SecretProvider> SecretProvider for CachedSecrets<P> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Secret>> {
+        self.get_at(name, crate::time::unix_timestamp()?)
+    }
+}
+
+/// Secrets from a HashiCorp Vault KV version 2 engine
+///
+/// All secrets are fields of a single Vault secret at `{mount}/data/{path}`.
+#[cfg(feature = "rpc")]
+pub struct VaultSecrets {
+    addr: String,
+    token: String,
+    mount: String,
+    path: String,
+}
+
+#[cfg(feature = "rpc")]
+impl VaultSecrets {
+    /// Create a provider reading the fields of `path` in the KV engine at `mount`
+    pub fn new(addr: &str, token: &str, mount: &str, path: &str) -> Self {
+        Self {
+            addr: addr.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            mount: mount.trim_matches('/').to_string(),
+            path: path.trim_matches('/').to_string(),
+        }
+    }
+
+    /// Extract a field from a KV v2 read response
+    pub fn parse_response(response: &serde_json::Value, name: &str) -> Option<Secret> {
+        let value = response["data"]["data"][name].as_str()?;
+        match response["data"]["metadata"]["version"].as_u64() {
+            Some(version) => Some(Secret::with_version(value, version.to_string())),
+            None => Some(Secret::new(value)),
+        }
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl SecretProvider for VaultSecrets {
+    fn name(&self) -> &str {
+        "vault"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Secret>> {
+        let url = format!("{}/v1/{}/data/{}", self.addr, self.mount, self.path);
+        // Secrets are read from async handlers too, where block_on would panic
+        let response = crate::transaction::block_on_detached(async {
+            let response = reqwest::Client::new().get(&url)
+                .header("X-Vault-Token", &self.token)
+                .send()
+                .await?;
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            response.error_for_status()?.json::<serde_json::Value>().await.map(Some)
+        })?
+        .map_err(|e| Error::Network(format!("Vault request failed: {}", e)))?;
+
+        Ok(response.and_then(|response| Self::parse_response(&response, name)))
+    }
+}
+
+/// AWS access credentials
+#[derive(Clone)]
+pub struct AwsCredentials {
+    /// Access key ID
+    pub access_key_id: String,
+    /// Secret access key
+    pub secret_access_key: String,
+    /// Session token of temporary credentials
+    pub session_token: Option<String>,
+}
+
+impl fmt::Debug for AwsCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AwsCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"[REDACTED]")
+            .finish()
+    }
+}
+
+/// Format a Unix timestamp as an AWS `x-amz-date` (`YYYYMMDD'T'HHMMSS'Z'`)
+#[cfg(feature = "rpc")]
+fn amz_date(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days((timestamp / 86_400) as i64);
+    let seconds = timestamp % 86_400;
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z", year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+#[cfg(feature = "rpc")]
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = <Hmac::<Sha256> as KeyInit>::new_from_slice(key)
+        .expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// A request to sign with AWS Signature Version 4
+#[cfg(feature = "rpc")]
+struct SigV4Request<'a> {
+    method: &'a str,
+    path: &'a str,
+    /// Canonical (sorted, encoded) query string
+    query: &'a str,
+    /// Headers to sign, including `host` and `x-amz-date`
+    headers: &'a [(&'a str, &'a str)],
+    payload: &'a [u8],
+}
+
+/// Compute the `Authorization` header of a request with AWS Signature Version 4
+#[cfg(feature = "rpc")]
+fn sigv4_authorization(credentials: &AwsCredentials, region: &str, service: &str, request: &SigV4Request, timestamp: u64) -> String {
+    let amz_date = amz_date(timestamp);
+    let date = &amz_date[..8];
+
+    let mut headers: Vec<(String, String)> = request.headers.iter()
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    headers.sort();
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+    let signed_headers = headers.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        request.method, request.path, request.query, canonical_headers, signed_headers,
+        hex::encode(Sha256::digest(request.payload)),
+    );
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, hex::encode(Sha256::digest(canonical_request.as_bytes())));
+
+    let key = [date, region, service, "aws4_request"].iter()
+        .fold(format!("AWS4{}", credentials.secret_access_key).into_bytes(), |key, part| hmac_sha256(&key, part.as_bytes()));
+    let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    format!("AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}", credentials.access_key_id, scope, signed_headers, signature)
+}
+
+/// Secrets stored as AWS KMS ciphertext
+///
+/// The inner provider holds base64 `CiphertextBlob`s (e.g. in environment
+/// variables or files), which are decrypted with the KMS `Decrypt` API.
+/// Rotating the KMS key does not change the ciphertext that must be stored.
+#[cfg(feature = "rpc")]
+pub struct KmsSecrets<P> {
+    inner: P,
+    region: String,
+    credentials: AwsCredentials,
+}
+
+#[cfg(feature = "rpc")]
+impl<P: SecretProvider> KmsSecrets<P> {
+    /// Create a provider decrypting the ciphertexts of `inner` in a region
+    pub fn new(inner: P, region: &str, credentials: AwsCredentials) -> Self {
+        Self {
+            inner,
+            region: region.to_string(),
+            credentials,
+        }
+    }
+
+    fn decrypt(&self, ciphertext: &str) -> Result<String> {
+        use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+
+        let host = format!("kms.{}.amazonaws.com", self.region);
+        let body = serde_json::json!({ "CiphertextBlob": ciphertext.trim() }).to_string();
+        let timestamp = crate::time::unix_timestamp()?;
+        let amz_date = amz_date(timestamp);
+
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1"),
+            ("host", host.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+            ("x-amz-target", "TrentService.Decrypt"),
+        ];
+        if let Some(token) = &self.credentials.session_token {
+            headers.push(("x-amz-security-token", token.as_str()));
+        }
+        let authorization = sigv4_authorization(&self.credentials, &self.region, "kms", &SigV4Request {
+            method: "POST",
+            path: "/",
+            query: "",
+            headers: &headers,
+            payload: body.as_bytes(),
+        }, timestamp);
+
+        let response: serde_json::Value = crate::transaction::block_on_detached(async {
+            let mut request = reqwest::Client::new().post(format!("https://{}/", host))
+                .header("Authorization", &authorization)
+                .body(body.clone());
+            for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+                request = request.header(*name, *value);
+            }
+            request.send().await?.error_for_status()?.json().await
+        })?
+        .map_err(|e| Error::Network(format!("KMS decrypt failed: {}", e)))?;
+
+        let plaintext = response["Plaintext"].as_str()
+            .ok_or_else(|| Error::Provider(format!("Invalid KMS response: {}", response)))?;
+        let plaintext = BASE64.decode(plaintext)
+            .map_err(|e| Error::Provider(format!("Invalid KMS plaintext: {}", e)))?;
+        String::from_utf8(plaintext)
+            .map_err(|e| Error::Provider(format!("KMS plaintext is not UTF-8: {}", e)))
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl<P: SecretProvider> SecretProvider for KmsSecrets<P> {
+    fn name(&self) -> &str {
+        "aws-kms"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Secret>> {
+        match self.inner.get(name)? {
+            Some(ciphertext) => Ok(Some(Secret::new(self.decrypt(ciphertext.expose())?))),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Rotating(Mutex<u32>);
+
+    impl SecretProvider for Rotating {
+        fn name(&self) -> &str {
+            "rotating"
+        }
+
+        fn get(&self, name: &str) -> Result<Option<Secret>> {
+            let version = *self.0.lock().unwrap();
+            Ok((name == "rpc_api_key").then(|| Secret::with_version(format!("key-v{}", version), version.to_string())))
+        }
+    }
+
+    #[test]
+    fn test_secret_redacted() {
+        let secret = Secret::new("hunter2");
+        assert_eq!(secret.expose(), "hunter2");
+        assert!(!format!("{:?}", secret).contains("hunter2"));
+    }
+
+    #[test]
+    fn test_env_and_file_secrets() {
+        assert_eq!(EnvSecrets::new("FO3_").variable("chainalysis-api.key"), "FO3_CHAINALYSIS_API_KEY");
+
+        let dir = std::env::temp_dir().join(format!("fo3-secrets-{}", hex::encode(rand::random::<[u8; 8]>())));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("rpc_api_key"), "abc123\n").unwrap();
+
+        let files = FileSecrets::new(&dir);
+        assert_eq!(files.require("rpc_api_key").unwrap().expose(), "abc123");
+        assert!(files.get("missing").unwrap().is_none());
+        assert!(files.get("../etc/passwd").is_err());
+
+        let chain = SecretChain::new(vec![Box::new(FileSecrets::new(dir.join("absent"))), Box::new(files)]);
+        assert_eq!(chain.require("rpc_api_key").unwrap().expose(), "abc123");
+        assert!(chain.require("missing").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cached_secrets_rotation() {
+        let cached = CachedSecrets::new(Rotating(Mutex::new(1)), 300);
+        assert_eq!(cached.get_at("rpc_api_key", 0).unwrap().unwrap().expose(), "key-v1");
+
+        *cached.inner.0.lock().unwrap() = 2;
+        assert_eq!(cached.get_at("rpc_api_key", 299).unwrap().unwrap().expose(), "key-v1");
+        assert_eq!(cached.get_at("rpc_api_key", 300).unwrap().unwrap().version.as_deref(), Some("2"));
+
+        *cached.inner.0.lock().unwrap() = 3;
+        assert!(cached.refresh("rpc_api_key").unwrap());
+        assert!(!cached.refresh("rpc_api_key").unwrap());
+    }
+
+    #[cfg(feature = "rpc")]
+    #[test]
+    fn test_vault_response() {
+        let response = serde_json::json!({
+            "data": { "data": { "rpc_api_key": "abc123" }, "metadata": { "version": 4 } }
+        });
+        let secret = VaultSecrets::parse_response(&response, "rpc_api_key").unwrap();
+        assert_eq!(secret.expose(), "abc123");
+        assert_eq!(secret.version.as_deref(), Some("4"));
+        assert!(VaultSecrets::parse_response(&response, "missing").is_none());
+    }
+
+    #[cfg(feature = "rpc")]
+    #[test]
+    fn test_sigv4() {
+        // Example request from the AWS Signature Version 4 documentation
+        let credentials = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        };
+        let timestamp = 1_440_938_160;
+        assert_eq!(amz_date(timestamp), "20150830T123600Z");
+
+        let authorization = sigv4_authorization(&credentials, "us-east-1", "iam", &SigV4Request {
+            method: "GET",
+            path: "/",
+            query: "Action=ListUsers&Version=2010-05-08",
+            headers: &[
+                ("Content-Type", "application/x-www-form-urlencoded; charset=utf-8"),
+                ("Host", "iam.amazonaws.com"),
+                ("X-Amz-Date", "20150830T123600Z"),
+            ],
+            payload: b"",
+        }, timestamp);
+
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, \
+             Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+    }
+}
@@ -0,0 +1,40 @@
+//! Golden-file test harness
+
+use std::fs;
+use std::path::PathBuf;
+
+/// Environment variable that makes golden assertions rewrite their files
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN";
+
+/// Get the path of a golden file under `tests/golden`
+pub fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(name)
+}
+
+/// Assert that `actual` matches the contents of the named golden file
+///
+/// Run the tests with `UPDATE_GOLDEN=1` to (re)generate golden files after an
+/// intended change; review the resulting diff before committing it.
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = golden_path(name);
+
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("failed to create golden directory");
+        }
+        fs::write(&path, format!("{}\n", actual.trim_end())).expect("failed to write golden file");
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!("missing golden file {} ({}); run with {}=1 to create it", path.display(), e, UPDATE_GOLDEN_ENV)
+    });
+
+    assert_eq!(
+        expected.trim_end(),
+        actual.trim_end(),
+        "golden file {} does not match; run with {}=1 if the change is intended",
+        name,
+        UPDATE_GOLDEN_ENV,
+    );
+}
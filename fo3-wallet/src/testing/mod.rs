@@ -0,0 +1,11 @@
+//! Testing support
+//!
+//! This module is only available with the `test-vectors` feature. It provides
+//! standard key derivation test vectors and a golden-file harness so that
+//! regressions in `crypto::keys` and the transaction signers are caught without
+//! hitting any RPC.
+
+pub mod vectors;
+mod golden;
+
+pub use golden::*;
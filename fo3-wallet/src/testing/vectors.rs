@@ -0,0 +1,210 @@
+//! Standard key derivation test vectors
+
+use crate::crypto::keys::{derive_key_pair, KeyType};
+use crate::error::{Error, Result};
+
+/// The BIP-39 test mnemonic used by the wallet vectors
+pub const TEST_MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+/// BIP-39 seed of [`TEST_MNEMONIC`] with an empty passphrase
+pub const TEST_MNEMONIC_SEED: &str = "5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc19a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4";
+
+/// Seed of BIP-32 test vector 1 and SLIP-10 ed25519 test vector 1
+pub const BIP32_TEST_SEED: &str = "000102030405060708090a0b0c0d0e0f";
+
+/// A single key derivation test vector
+#[derive(Debug, Clone, Copy)]
+pub struct DerivationVector {
+    /// Hex-encoded seed
+    pub seed: &'static str,
+    /// The type of key to derive
+    pub key_type: KeyType,
+    /// Derivation path
+    pub path: &'static str,
+    /// Expected hex-encoded private key
+    pub private_key: &'static str,
+    /// Expected hex-encoded public key (compressed for secp256k1, raw for ed25519)
+    pub public_key: &'static str,
+    /// Expected address, where the vector defines one
+    pub address: Option<&'static str>,
+}
+
+/// BIP-32 test vector 1 (secp256k1)
+pub const BIP32_VECTORS: &[DerivationVector] = &[
+    DerivationVector {
+        seed: BIP32_TEST_SEED,
+        key_type: KeyType::Bitcoin,
+        path: "m/0'",
+        private_key: "edb2e14f9ee77d26dd93b4ecede8d16ed408ce149b6cd80b0715a2d911a0afea",
+        public_key: "035a784662a4a20a65bf6aab9ae98a6c068a81c52e4b032c0fb5400c706cfccc56",
+        address: None,
+    },
+    DerivationVector {
+        seed: BIP32_TEST_SEED,
+        key_type: KeyType::Bitcoin,
+        path: "m/0'/1",
+        private_key: "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368",
+        public_key: "03501e454bf00751f24b1b489aa925215d66af2234e3891c3b21a52bedb3cd711c",
+        address: None,
+    },
+    DerivationVector {
+        seed: BIP32_TEST_SEED,
+        key_type: KeyType::Bitcoin,
+        path: "m/0'/1/2'",
+        private_key: "cbce0d719ecf7431d88e6a89fa1483e02e35092af60c042b1df2ff59fa424dca",
+        public_key: "0357bfe1e341d01c69fe5654309956cbea516822fba8a601743a012a7896ee8dc2",
+        address: None,
+    },
+    DerivationVector {
+        seed: BIP32_TEST_SEED,
+        key_type: KeyType::Bitcoin,
+        path: "m/0'/1/2'/2",
+        private_key: "0f479245fb19a38a1954c5c7c0ebab2f9bdfd96a17563ef28a6a4b1a2a764ef4",
+        public_key: "02e8445082a72f29b75ca48748a914df60622a609cacfce8ed0e35804560741d29",
+        address: None,
+    },
+    DerivationVector {
+        seed: BIP32_TEST_SEED,
+        key_type: KeyType::Bitcoin,
+        path: "m/0'/1/2'/2/1000000000",
+        private_key: "471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8",
+        public_key: "022a471424da5e657499d1ff51cb43c47481a03b1e77f951fe64cec9f5a48f7011",
+        address: None,
+    },
+];
+
+/// SLIP-10 ed25519 test vector 1
+pub const SLIP10_ED25519_VECTORS: &[DerivationVector] = &[
+    DerivationVector {
+        seed: BIP32_TEST_SEED,
+        key_type: KeyType::Solana,
+        path: "m/0'",
+        private_key: "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3",
+        public_key: "8c8a13df77a28f3445213a0f432fde644acaa215fc72dcdf300d5efaa85d350c",
+        address: None,
+    },
+    DerivationVector {
+        seed: BIP32_TEST_SEED,
+        key_type: KeyType::Solana,
+        path: "m/0'/1'",
+        private_key: "b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2",
+        public_key: "1932a5270f335bed617d5b935c80aedb1a35bd9fc1e31acafd5372c30f5c1187",
+        address: None,
+    },
+    DerivationVector {
+        seed: BIP32_TEST_SEED,
+        key_type: KeyType::Solana,
+        path: "m/0'/1'/2'",
+        private_key: "92a5b23c0b8a99e37d07df3fb9966917f5d06e02ddbd909c7e184371463e9fc9",
+        public_key: "ae98736566d30ed0e9d2f4486a64bc95740d89c7db33f52121f8ea8f76ff0fc1",
+        address: None,
+    },
+    DerivationVector {
+        seed: BIP32_TEST_SEED,
+        key_type: KeyType::Solana,
+        path: "m/0'/1'/2'/2'",
+        private_key: "30d1dc7e5fc04c31219ab25a27ae00b50f6fd66622f6e9c913253d6511d1e662",
+        public_key: "8abae2d66361c879b900d204ad2cc4984fa2aa344dd7ddc46007329ac76c429c",
+        address: None,
+    },
+    DerivationVector {
+        seed: BIP32_TEST_SEED,
+        key_type: KeyType::Solana,
+        path: "m/0'/1'/2'/2'/1000000000'",
+        private_key: "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793",
+        public_key: "3c24da049451555d51a7014a37337aa4e12d41e485abccfa46b47dfb2af54b7a",
+        address: None,
+    },
+];
+
+/// Wallet-level vectors for [`TEST_MNEMONIC`] (BIP-44/84/86 and Solana)
+pub const WALLET_VECTORS: &[DerivationVector] = &[
+    DerivationVector {
+        seed: TEST_MNEMONIC_SEED,
+        key_type: KeyType::Ethereum,
+        path: "m/44'/60'/0'/0/0",
+        private_key: "1ab42cc412b618bdea3a599e3c9bae199ebf030895b039e9db1e30dafb12b727",
+        public_key: "0237b0bb7a8288d38ed49a524b5dc98cff3eb5ca824c9f9dc0dfdb3d9cd600f299",
+        address: Some("0x9858effd232b4033e47d90003d41ec34ecaeda94"),
+    },
+    DerivationVector {
+        seed: TEST_MNEMONIC_SEED,
+        key_type: KeyType::Bitcoin,
+        path: "m/44'/0'/0'/0/0",
+        private_key: "e284129cc0922579a535bbf4d1a3b25773090d28c909bc0fed73b5e0222cc372",
+        public_key: "03aaeb52dd7494c361049de67cc680e83ebcbbbdbeb13637d92cd845f70308af5e",
+        address: None,
+    },
+    DerivationVector {
+        seed: TEST_MNEMONIC_SEED,
+        key_type: KeyType::Bitcoin,
+        path: "m/84'/0'/0'/0/0",
+        private_key: "4604b4b710fe91f584fff084e1a9159fe4f8408fff380596a604948474ce4fa3",
+        public_key: "0330d54fd0dd420a6e5f8d3624f5f3482cae350f79d5f0753bf5beef9c2d91af3c",
+        address: None,
+    },
+    DerivationVector {
+        seed: TEST_MNEMONIC_SEED,
+        key_type: KeyType::Bitcoin,
+        path: "m/86'/0'/0'/0/0",
+        private_key: "41f41d69260df4cf277826a9b65a3717e4eeddbeedf637f212ca096576479361",
+        public_key: "03cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115",
+        address: None,
+    },
+    DerivationVector {
+        seed: TEST_MNEMONIC_SEED,
+        key_type: KeyType::Solana,
+        path: "m/44'/501'/0'/0'",
+        private_key: "37df573b3ac4ad5b522e064e25b63ea16bcbe79d449e81a0268d1047948bb445",
+        public_key: "f036276246a75b9de3349ed42b15e232f6518fc20f5fcd4f1d64e81f9bd258f7",
+        address: Some("HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk"),
+    },
+];
+
+/// Check a single derivation vector, returning a descriptive error on mismatch
+pub fn check_vector(vector: &DerivationVector) -> Result<()> {
+    let seed = hex::decode(vector.seed)
+        .map_err(|e| Error::InvalidInput(format!("Invalid vector seed: {}", e)))?;
+    let key_pair = derive_key_pair(&seed, vector.key_type, vector.path)?;
+
+    let mismatch = |field: &str, expected: &str, actual: &str| {
+        Error::KeyDerivation(format!(
+            "{:?} {} {} mismatch: expected {}, got {}",
+            vector.key_type, vector.path, field, expected, actual
+        ))
+    };
+
+    let private_key = hex::encode(key_pair.private_key().as_bytes());
+    if private_key != vector.private_key {
+        return Err(mismatch("private key", vector.private_key, &private_key));
+    }
+
+    // Ethereum keys are stored uncompressed; compare in compressed form
+    let public_key = match vector.key_type {
+        KeyType::Ethereum => {
+            let public_key = secp256k1::PublicKey::from_slice(key_pair.public_key().as_bytes())
+                .map_err(|e| Error::KeyDerivation(format!("Invalid public key: {}", e)))?;
+            hex::encode(public_key.serialize())
+        }
+        _ => hex::encode(key_pair.public_key().as_bytes()),
+    };
+    if public_key != vector.public_key {
+        return Err(mismatch("public key", vector.public_key, &public_key));
+    }
+
+    if let Some(expected) = vector.address {
+        let address = match vector.key_type {
+            KeyType::Ethereum => crate::crypto::keys::ethereum::public_key_to_address(key_pair.public_key())?,
+            KeyType::Solana => crate::crypto::keys::solana::public_key_to_address(key_pair.public_key())?,
+            KeyType::Bitcoin => crate::crypto::keys::bitcoin::public_key_to_address(key_pair.public_key(), bitcoin::Network::Bitcoin)?,
+            KeyType::Ton => crate::crypto::keys::ton::public_key_to_address(key_pair.public_key(), false)?,
+            KeyType::Cosmos => crate::crypto::keys::cosmos::public_key_to_address(key_pair.public_key(), "cosmos")?,
+            KeyType::Xrp => crate::crypto::keys::xrp::public_key_to_address(key_pair.public_key())?,
+        };
+        if address.to_lowercase() != expected.to_lowercase() {
+            return Err(mismatch("address", expected, &address));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,323 @@
+//! Batched RPC calls
+//!
+//! This module provides JSON-RPC batch requests and Multicall3 aggregation,
+//! so that many read-only contract calls collapse into a few round-trips.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use ethers::abi::{self, ParamType, Token};
+use ethers::prelude::{Address, U256};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::error::{Error, Result};
+
+/// Multicall3 contract address (identical on all major EVM chains)
+pub const MULTICALL3_ADDRESS: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
+
+/// Maximum number of calls aggregated into a single Multicall3 call
+pub const MULTICALL_CHUNK_SIZE: usize = 100;
+
+/// A read-only contract call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallRequest {
+    /// Contract address
+    pub to: String,
+    /// ABI-encoded call data
+    pub data: Vec<u8>,
+}
+
+/// Result of a single call in a batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallResult {
+    /// Whether the call succeeded
+    pub success: bool,
+    /// Returned data (revert data if the call failed)
+    pub return_data: Vec<u8>,
+}
+
+/// ERC-20 token metadata
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    /// Token address
+    pub address: String,
+    /// Token name
+    pub name: Option<String>,
+    /// Token symbol
+    pub symbol: Option<String>,
+    /// Token decimals
+    pub decimals: Option<u8>,
+}
+
+/// A JSON-RPC 2.0 batch request
+#[derive(Debug, Clone, Default)]
+pub struct JsonRpcBatch {
+    requests: Vec<Value>,
+}
+
+impl JsonRpcBatch {
+    /// Create an empty batch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a request to the batch, returning its ID
+    pub fn push(&mut self, method: &str, params: Value) -> u64 {
+        let id = self.requests.len() as u64;
+        self.requests.push(json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        }));
+        id
+    }
+
+    /// Get the number of requests in the batch
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Check whether the batch is empty
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Get the distinct methods called by the batch, comma separated
+    pub fn methods(&self) -> String {
+        let mut methods: Vec<&str> = Vec::new();
+        for method in self.requests.iter().filter_map(|request| request["method"].as_str()) {
+            if !methods.contains(&method) {
+                methods.push(method);
+            }
+        }
+        methods.join(",")
+    }
+
+    /// Get the request body
+    pub fn body(&self) -> Value {
+        Value::Array(self.requests.clone())
+    }
+
+    /// Match a batch response to the requests, in request order
+    ///
+    /// Servers may answer batch members in any order, so responses are matched by ID.
+    pub fn parse_response(&self, response: Value) -> Result<Vec<Result<Value>>> {
+        let responses = match response {
+            Value::Array(responses) => responses,
+            // A single error object means the whole batch was rejected
+            other => return Err(Error::Provider(format!("Invalid batch response: {}", other))),
+        };
+
+        let mut by_id: HashMap<u64, Value> = HashMap::new();
+        for response in responses {
+            let id = response.get("id").and_then(Value::as_u64)
+                .ok_or_else(|| Error::Provider(format!("Batch response without ID: {}", response)))?;
+            by_id.insert(id, response);
+        }
+
+        (0..self.requests.len() as u64)
+            .map(|id| {
+                let mut response = by_id.remove(&id)
+                    .ok_or_else(|| Error::Provider(format!("Missing response for batch request {}", id)))?;
+
+                if let Some(error) = response.get("error") {
+                    return Ok(Err(Error::Provider(format!("RPC error: {}", error))));
+                }
+
+                match response.get_mut("result") {
+                    Some(result) => Ok(Ok(result.take())),
+                    None => Err(Error::Provider(format!("Batch response {} has neither result nor error", id))),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Encode a Multicall3 `aggregate3` call, allowing each call to fail independently
+pub fn encode_aggregate3(calls: &[CallRequest]) -> Result<Vec<u8>> {
+    let calls = calls
+        .iter()
+        .map(|call| {
+            let target = Address::from_str(&call.to)
+                .map_err(|e| Error::InvalidInput(format!("Invalid call target {}: {}", call.to, e)))?;
+            Ok(Token::Tuple(vec![Token::Address(target), Token::Bool(true), Token::Bytes(call.data.clone())]))
+        })
+        .collect::<Result<Vec<Token>>>()?;
+
+    let mut data = ethers::utils::id("aggregate3((address,bool,bytes)[])").to_vec();
+    data.extend(abi::encode(&[Token::Array(calls)]));
+    Ok(data)
+}
+
+/// Decode the return data of a Multicall3 `aggregate3` call
+pub fn decode_aggregate3(data: &[u8]) -> Result<Vec<CallResult>> {
+    let result_type = ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Bool, ParamType::Bytes])));
+    let tokens = abi::decode(&[result_type], data)
+        .map_err(|e| Error::Provider(format!("Invalid aggregate3 response: {}", e)))?;
+
+    let results = match tokens.into_iter().next() {
+        Some(Token::Array(results)) => results,
+        _ => return Err(Error::Provider("Invalid aggregate3 response".to_string())),
+    };
+
+    results
+        .into_iter()
+        .map(|result| match result {
+            Token::Tuple(fields) => match fields.as_slice() {
+                [Token::Bool(success), Token::Bytes(return_data)] => Ok(CallResult {
+                    success: *success,
+                    return_data: return_data.clone(),
+                }),
+                _ => Err(Error::Provider("Invalid aggregate3 result".to_string())),
+            },
+            _ => Err(Error::Provider("Invalid aggregate3 result".to_string())),
+        })
+        .collect()
+}
+
+/// Encode an ERC-20 `balanceOf(owner)` call
+pub fn encode_balance_of(token: &str, owner: &str) -> Result<CallRequest> {
+    let owner = parse_address(owner)?;
+    Ok(encode_call(token, "balanceOf(address)", &[Token::Address(owner)]))
+}
+
+/// Encode an ERC-20 `allowance(owner, spender)` call
+pub fn encode_allowance(token: &str, owner: &str, spender: &str) -> Result<CallRequest> {
+    let owner = parse_address(owner)?;
+    let spender = parse_address(spender)?;
+    Ok(encode_call(token, "allowance(address,address)", &[Token::Address(owner), Token::Address(spender)]))
+}
+
+/// Encode the ERC-20 `name()`, `symbol()` and `decimals()` calls for a token
+pub fn encode_metadata_calls(token: &str) -> [CallRequest; 3] {
+    [
+        encode_call(token, "name()", &[]),
+        encode_call(token, "symbol()", &[]),
+        encode_call(token, "decimals()", &[]),
+    ]
+}
+
+/// Decode a `uint256` call result, or `None` if the call failed
+pub fn decode_uint(result: &CallResult) -> Option<U256> {
+    if !result.success || result.return_data.len() < 32 {
+        return None;
+    }
+
+    Some(U256::from_big_endian(&result.return_data[..32]))
+}
+
+/// Decode a string call result, accepting both ABI strings and legacy `bytes32` values
+pub fn decode_string(result: &CallResult) -> Option<String> {
+    if !result.success {
+        return None;
+    }
+
+    if let Ok(tokens) = abi::decode(&[ParamType::String], &result.return_data) {
+        if let Some(Token::String(value)) = tokens.into_iter().next() {
+            return Some(value);
+        }
+    }
+
+    // Tokens such as MKR return their name and symbol as bytes32
+    if result.return_data.len() == 32 {
+        let end = result.return_data.iter().position(|b| *b == 0).unwrap_or(32);
+        return String::from_utf8(result.return_data[..end].to_vec()).ok();
+    }
+
+    None
+}
+
+fn encode_call(to: &str, signature: &str, args: &[Token]) -> CallRequest {
+    let mut data = ethers::utils::id(signature).to_vec();
+    data.extend(abi::encode(args));
+    CallRequest {
+        to: to.to_string(),
+        data,
+    }
+}
+
+fn parse_address(address: &str) -> Result<Address> {
+    Address::from_str(address)
+        .map_err(|e| Error::InvalidInput(format!("Invalid address {}: {}", address, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+    const OWNER: &str = "0x742d35Cc6634C0532925a3b844Bc454e4438f44e";
+
+    #[test]
+    fn test_call_selectors() {
+        assert_eq!(hex::encode(&encode_balance_of(TOKEN, OWNER).unwrap().data[..4]), "70a08231");
+        assert_eq!(hex::encode(&encode_allowance(TOKEN, OWNER, OWNER).unwrap().data[..4]), "dd62ed3e");
+        assert_eq!(hex::encode(&encode_aggregate3(&[]).unwrap()[..4]), "82ad56cb");
+
+        let [name, symbol, decimals] = encode_metadata_calls(TOKEN);
+        assert_eq!(hex::encode(name.data), "06fdde03");
+        assert_eq!(hex::encode(symbol.data), "95d89b41");
+        assert_eq!(hex::encode(decimals.data), "313ce567");
+    }
+
+    #[test]
+    fn test_decode_aggregate3() {
+        let encoded = abi::encode(&[Token::Array(vec![
+            Token::Tuple(vec![Token::Bool(true), Token::Bytes(abi::encode(&[Token::Uint(U256::from(42))]))]),
+            Token::Tuple(vec![Token::Bool(false), Token::Bytes(vec![])]),
+        ])]);
+
+        let results = decode_aggregate3(&encoded).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(decode_uint(&results[0]), Some(U256::from(42)));
+        assert_eq!(decode_uint(&results[1]), None);
+    }
+
+    #[test]
+    fn test_decode_string_variants() {
+        let abi_string = CallResult {
+            success: true,
+            return_data: abi::encode(&[Token::String("USD Coin".to_string())]),
+        };
+        assert_eq!(decode_string(&abi_string), Some("USD Coin".to_string()));
+
+        let mut bytes32 = b"MKR".to_vec();
+        bytes32.resize(32, 0);
+        let legacy = CallResult {
+            success: true,
+            return_data: bytes32,
+        };
+        assert_eq!(decode_string(&legacy), Some("MKR".to_string()));
+    }
+
+    #[test]
+    fn test_batch_response_matched_by_id() {
+        let mut batch = JsonRpcBatch::new();
+        batch.push("eth_blockNumber", json!([]));
+        batch.push("eth_chainId", json!([]));
+
+        let response = json!([
+            { "jsonrpc": "2.0", "id": 1, "result": "0x1" },
+            { "jsonrpc": "2.0", "id": 0, "error": { "code": -32000, "message": "busy" } },
+        ]);
+
+        let results = batch.parse_response(response).unwrap();
+        assert!(results[0].is_err());
+        assert_eq!(results[1].as_ref().unwrap(), "0x1");
+
+        assert!(batch.parse_response(json!([{ "jsonrpc": "2.0", "id": 0, "result": "0x0" }])).is_err());
+    }
+
+    #[test]
+    fn test_batch_methods() {
+        let mut batch = JsonRpcBatch::new();
+        batch.push("eth_call", json!([]));
+        batch.push("eth_blockNumber", json!([]));
+        batch.push("eth_call", json!([]));
+
+        assert_eq!(batch.methods(), "eth_call,eth_blockNumber");
+        assert_eq!(JsonRpcBatch::new().methods(), "");
+    }
+}
@@ -13,8 +13,11 @@ use secp256k1::Secp256k1;
 
 use crate::error::{Error, Result};
 use crate::crypto::keys::KeyType;
-use super::types::{Transaction, TransactionRequest, TransactionReceipt, TransactionStatus, TransactionSigner, TransactionBroadcaster, TransactionManager, TransactionType};
+use super::types::{Transaction, TransactionRequest, TransactionReceipt, TransactionStatus, TransactionSigner, TransactionBroadcaster, TransactionManager, TransactionType, NetworkBinding};
 use super::provider::{ProviderConfig, ProviderType};
+use super::dust::UtxoConsolidation;
+#[cfg(feature = "rpc")]
+use super::utxo::{self, FeeEstimates, UtxoBackend};
 
 /// Bitcoin transaction
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,6 +58,9 @@ pub struct BitcoinProvider {
     network: Network,
     /// Secp256k1 context
     secp: Secp256k1<secp256k1::All>,
+    /// Address index, when the provider type selects one
+    #[cfg(feature = "rpc")]
+    backend: Option<Box<dyn UtxoBackend>>,
 }
 
 impl BitcoinProvider {
@@ -64,22 +70,58 @@ impl BitcoinProvider {
         let network = match config.url.as_str() {
             url if url.contains("mainnet") => Network::Bitcoin,
             url if url.contains("testnet") => Network::Testnet,
+            url if url.contains("signet") => Network::Signet,
             url if url.contains("regtest") => Network::Regtest,
             _ => Network::Bitcoin, // Default to mainnet
         };
 
+        #[cfg(feature = "rpc")]
+        let backend = if utxo::is_utxo_backend(&config) {
+            Some(utxo::utxo_backend(&config)?)
+        } else {
+            None
+        };
+
         Ok(Self {
             config,
             network,
             secp: Secp256k1::new(),
+            #[cfg(feature = "rpc")]
+            backend,
         })
     }
 
+    /// Get the address index, failing if the provider type selects none
+    #[cfg(feature = "rpc")]
+    fn backend(&self) -> Result<&dyn UtxoBackend> {
+        self.backend.as_deref()
+            .ok_or_else(|| Error::NotSupported("Bitcoin address lookups need an Esplora or Electrum provider".to_string()))
+    }
+
+    /// Get the spendable outputs of an address as transaction inputs
+    #[cfg(feature = "rpc")]
+    pub fn get_utxos(&self, address: &str) -> Result<Vec<BitcoinInput>> {
+        let script_pubkey = utxo::address_script_pubkey(address)?;
+        let utxos = self.backend()?.get_utxos(address)?;
+        Ok(utxos.iter().map(|utxo| utxo.to_input(&script_pubkey)).collect())
+    }
+
+    /// Get current fee estimates
+    #[cfg(feature = "rpc")]
+    pub fn get_fee_estimates(&self) -> Result<FeeEstimates> {
+        self.backend()?.get_fee_estimates()
+    }
+
     /// Get the network
     pub fn network(&self) -> Network {
         self.network
     }
 
+    /// Get the network binding transactions must carry to be signed by this provider
+    pub fn network_binding(&self) -> NetworkBinding {
+        NetworkBinding::Bitcoin { network: self.network.to_string() }
+    }
+
     /// Create a Bitcoin transaction
     fn create_transaction(&self, request: &TransactionRequest, inputs: Vec<BitcoinInput>) -> Result<BtcTransaction> {
         // Parse addresses
@@ -162,6 +204,38 @@ impl BitcoinProvider {
 
         Ok(tx)
     }
+
+    /// Create the unsigned transaction of a UTXO consolidation, paying the consolidated output to `to`
+    pub fn create_consolidation_transaction(&self, consolidation: &UtxoConsolidation, to: &str) -> Result<BtcTransaction> {
+        let to_address = Address::from_str(to)
+            .map_err(|e| Error::Transaction(format!("Invalid to address: {}", e)))?
+            .require_network(self.network)
+            .map_err(|e| Error::Transaction(format!("Invalid to address network: {}", e)))?;
+
+        let input = consolidation.inputs.iter()
+            .map(|input| {
+                let txid = Txid::from_str(&input.txid)
+                    .map_err(|e| Error::Transaction(format!("Invalid txid: {}", e)))?;
+                Ok(TxIn {
+                    previous_output: OutPoint::new(txid, input.vout),
+                    script_sig: ScriptBuf::new(),
+                    // Signal replaceability so a stuck consolidation can be fee-bumped
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: Witness::new(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(BtcTransaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input,
+            output: vec![TxOut {
+                value: Amount::from_sat(consolidation.output_value),
+                script_pubkey: to_address.script_pubkey(),
+            }],
+        })
+    }
 }
 
 impl TransactionSigner for BitcoinProvider {
@@ -171,6 +245,9 @@ impl TransactionSigner for BitcoinProvider {
             return Err(Error::Transaction("Not a Bitcoin transaction".to_string()));
         }
 
+        // Refuse to sign for any network other than the one we are connected to
+        request.network.ensure_matches(&self.network_binding())?;
+
         // In a real implementation, we would:
         // 1. Get the private key from the request
         // 2. Get the UTXOs for the from address
@@ -186,6 +263,11 @@ impl TransactionSigner for BitcoinProvider {
 
 impl TransactionBroadcaster for BitcoinProvider {
     fn broadcast_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+        #[cfg(feature = "rpc")]
+        if let Some(backend) = &self.backend {
+            return backend.broadcast(signed_transaction);
+        }
+
         // In a real implementation, we would:
         // 1. Deserialize the signed transaction
         // 2. Broadcast it to the Bitcoin network
@@ -198,6 +280,11 @@ impl TransactionBroadcaster for BitcoinProvider {
     }
 
     fn get_transaction_status(&self, _hash: &str) -> Result<TransactionStatus> {
+        #[cfg(feature = "rpc")]
+        if let Some(backend) = &self.backend {
+            return backend.get_transaction_status(_hash);
+        }
+
         // In a real implementation, we would:
         // 1. Query the Bitcoin network for the transaction
         // 2. Check if it's confirmed
@@ -250,12 +337,23 @@ impl TransactionManager for BitcoinProvider {
             block_number: Some(12345678),
             timestamp: Some(1620000000),
             fee: Some("0.0001".to_string()),
+            memo: None,
         };
 
         Ok(transaction)
     }
 
     fn get_transactions(&self, address: &str, _limit: usize, _offset: usize) -> Result<Vec<Transaction>> {
+        #[cfg(feature = "rpc")]
+        if let Some(backend) = &self.backend {
+            let history = backend.get_address_history(address)?;
+            return Ok(history.into_iter()
+                .skip(_offset)
+                .take(_limit)
+                .map(|transaction| address_transaction(address, transaction))
+                .collect());
+        }
+
         // In a real implementation, we would:
         // 1. Query the Bitcoin network for transactions related to the address
         // 2. Convert them to our Transaction type
@@ -277,12 +375,42 @@ impl TransactionManager for BitcoinProvider {
             block_number: Some(12345678),
             timestamp: Some(1620000000),
             fee: Some("0.0001".to_string()),
+            memo: None,
         };
 
         Ok(vec![transaction])
     }
 }
 
+/// Convert an address history entry to a transaction, from the address's point of view
+#[cfg(feature = "rpc")]
+fn address_transaction(address: &str, transaction: utxo::AddressTransaction) -> Transaction {
+    let net_value = transaction.net_value.unwrap_or_default();
+    let (from, to) = if net_value < 0 {
+        (address.to_string(), String::new())
+    } else {
+        (String::new(), address.to_string())
+    };
+
+    Transaction {
+        hash: transaction.txid,
+        transaction_type: TransactionType::Transfer,
+        key_type: KeyType::Bitcoin,
+        from,
+        to,
+        value: net_value.unsigned_abs().to_string(),
+        gas_price: None,
+        gas_limit: None,
+        nonce: None,
+        data: None,
+        status: if transaction.block_height.is_some() { TransactionStatus::Confirmed } else { TransactionStatus::Pending },
+        block_number: transaction.block_height,
+        timestamp: transaction.block_time,
+        fee: transaction.fee.map(|fee| Amount::from_sat(fee).to_btc().to_string()),
+        memo: None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -313,6 +441,7 @@ mod tests {
 
         let request = TransactionRequest {
             key_type: KeyType::Bitcoin,
+            network: NetworkBinding::Bitcoin { network: "bitcoin".to_string() },
             from: "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".to_string(),
             to: "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".to_string(),
             value: "50000000".to_string(), // 0.5 BTC
@@ -320,6 +449,8 @@ mod tests {
             gas_limit: None,
             nonce: None,
             data: None,
+            destination_tag: None,
+            memo: None,
         };
 
         let inputs = vec![
@@ -338,4 +469,31 @@ mod tests {
         assert_eq!(tx.output[0].value, Amount::from_sat(50000000)); // 0.5 BTC
         assert_eq!(tx.output[1].value, Amount::from_sat(49990000)); // Change (1 BTC - 0.5 BTC - 0.0001 BTC fee)
     }
+
+    #[test]
+    fn test_create_consolidation_transaction() {
+        let config = ProviderConfig {
+            provider_type: ProviderType::Http,
+            url: "https://btc.getblock.io/mainnet".to_string(),
+            api_key: None,
+            timeout: Some(30),
+        };
+
+        let provider = BitcoinProvider::new(config).unwrap();
+
+        let utxos: Vec<BitcoinInput> = [20_000, 30_000].iter().enumerate()
+            .map(|(vout, amount)| BitcoinInput {
+                txid: "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b".to_string(),
+                vout: vout as u32,
+                amount: *amount,
+                script_pubkey: "0014751e76e8199196d454941c45d1b3a323f1433bd6".to_string(),
+            })
+            .collect();
+        let plan = crate::transaction::dust::plan_utxo_consolidation(&utxos, &Default::default()).unwrap();
+
+        let tx = provider.create_consolidation_transaction(&plan, "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap();
+        assert_eq!(tx.input.len(), 2);
+        assert_eq!(tx.output.len(), 1);
+        assert_eq!(tx.output[0].value, Amount::from_sat(50_000 - plan.fee));
+    }
 }
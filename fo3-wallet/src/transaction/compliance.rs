@@ -0,0 +1,653 @@
+//! Sanctions and compliance screening
+//!
+//! Counterparty addresses are screened before a transaction is signed (and
+//! therefore before it can be broadcast) and when deposits are seen.
+//! Screening providers are pluggable: a local sanctions list, the
+//! Chainalysis sanctions API, or several combined. A [`CompliancePolicy`]
+//! turns the reported risk into allow, flag or block, and every outcome is
+//! written to a [`ScreeningAudit`] trail.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::{Serialize, Deserialize};
+
+use crate::crypto::keys::KeyType;
+use crate::error::{Error, Result};
+use super::batch::{CallRequest, CallResult};
+use super::types::{Transaction, TransactionRequest, TransactionReceipt, TransactionStatus, TransactionSigner, TransactionBroadcaster, TransactionManager};
+
+/// Risk assigned to an address by a screening provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum RiskLevel {
+    /// No known exposure
+    Low,
+    /// Indirect exposure to risky entities
+    Medium,
+    /// Direct exposure to risky entities
+    High,
+    /// Sanctioned
+    Severe,
+}
+
+/// Result of screening a single address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreeningResult {
+    /// Screened address
+    pub address: String,
+    /// Assigned risk
+    pub risk: RiskLevel,
+    /// Categories reported by the provider (e.g. "sanctions")
+    pub categories: Vec<String>,
+    /// Name of the provider that produced the result
+    pub source: String,
+}
+
+impl ScreeningResult {
+    /// A result with no findings
+    pub fn clear(address: &str, source: &str) -> Self {
+        Self {
+            address: address.to_string(),
+            risk: RiskLevel::Low,
+            categories: Vec::new(),
+            source: source.to_string(),
+        }
+    }
+}
+
+/// Source of address risk information
+pub trait ScreeningProvider: Send + Sync {
+    /// Get the provider name recorded in the audit trail
+    fn name(&self) -> &str;
+
+    /// Screen an address
+    fn screen(&self, key_type: KeyType, address: &str) -> Result<ScreeningResult>;
+}
+
+/// Screening against a locally maintained list
+#[derive(Debug, Default)]
+pub struct LocalListScreener {
+    /// Listed addresses (normalized) and their category and risk
+    entries: HashMap<String, (String, RiskLevel)>,
+}
+
+impl LocalListScreener {
+    /// Create an empty list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a list with one `address,category` entry per line
+    ///
+    /// Entries in the `sanctions` category are severe, everything else is
+    /// high risk. Blank lines and lines starting with `#` are ignored.
+    pub fn from_csv(list: &str) -> Result<Self> {
+        let mut screener = Self::new();
+
+        for (number, line) in list.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (address, category) = line.split_once(',')
+                .ok_or_else(|| Error::InvalidInput(format!("Invalid screening list entry on line {}: {}", number + 1, line)))?;
+            let category = category.trim();
+            let risk = if category.eq_ignore_ascii_case("sanctions") { RiskLevel::Severe } else { RiskLevel::High };
+            screener.add(address.trim(), category, risk);
+        }
+
+        Ok(screener)
+    }
+
+    /// Add an address to the list
+    pub fn add(&mut self, address: &str, category: &str, risk: RiskLevel) {
+        self.entries.insert(normalize_address(address), (category.to_string(), risk));
+    }
+
+    /// Get the number of listed addresses
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether the list is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl ScreeningProvider for LocalListScreener {
+    fn name(&self) -> &str {
+        "local-list"
+    }
+
+    fn screen(&self, _key_type: KeyType, address: &str) -> Result<ScreeningResult> {
+        Ok(match self.entries.get(&normalize_address(address)) {
+            Some((category, risk)) => ScreeningResult {
+                address: address.to_string(),
+                risk: *risk,
+                categories: vec![category.clone()],
+                source: self.name().to_string(),
+            },
+            None => ScreeningResult::clear(address, self.name()),
+        })
+    }
+}
+
+/// EVM addresses are case-insensitive; other chains are case-sensitive
+fn normalize_address(address: &str) -> String {
+    if address.starts_with("0x") || address.starts_with("0X") {
+        address.to_ascii_lowercase()
+    } else {
+        address.to_string()
+    }
+}
+
+/// Screening through the Chainalysis sanctions screening API
+#[cfg(feature = "rpc")]
+pub struct ChainalysisScreener {
+    /// API base URL
+    url: String,
+    /// API key
+    api_key: String,
+    /// HTTP client
+    http: reqwest::Client,
+}
+
+#[cfg(feature = "rpc")]
+impl ChainalysisScreener {
+    /// Public sanctions screening endpoint
+    pub const DEFAULT_URL: &'static str = "https://public.chainalysis.com/api/v1/address";
+
+    /// Create a screener for the public endpoint
+    pub fn new(api_key: &str) -> Self {
+        Self::with_url(Self::DEFAULT_URL, api_key)
+    }
+
+    /// Create a screener for a custom endpoint
+    pub fn with_url(url: &str, api_key: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Parse a screening response
+    ///
+    /// Any identification means the address is sanctioned.
+    pub fn parse_response(address: &str, response: &serde_json::Value) -> Result<ScreeningResult> {
+        let identifications = response.get("identifications").and_then(|v| v.as_array())
+            .ok_or_else(|| Error::Provider(format!("Invalid screening response: {}", response)))?;
+
+        let categories: Vec<String> = identifications.iter()
+            .filter_map(|identification| identification.get("category").and_then(|v| v.as_str()))
+            .map(str::to_string)
+            .collect();
+
+        Ok(ScreeningResult {
+            address: address.to_string(),
+            risk: if identifications.is_empty() { RiskLevel::Low } else { RiskLevel::Severe },
+            categories,
+            source: "chainalysis".to_string(),
+        })
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl ScreeningProvider for ChainalysisScreener {
+    fn name(&self) -> &str {
+        "chainalysis"
+    }
+
+    fn screen(&self, _key_type: KeyType, address: &str) -> Result<ScreeningResult> {
+        let url = format!("{}/{}", self.url, address);
+        let response: serde_json::Value = super::ethereum::block_on(async {
+            self.http.get(&url)
+                .header("X-API-Key", &self.api_key)
+                .header("Accept", "application/json")
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+        })?
+        .map_err(|e| Error::Network(format!("Screening request failed: {}", e)))?;
+
+        Self::parse_response(address, &response)
+    }
+}
+
+/// Screening through several providers, keeping the highest risk
+pub struct CompositeScreener {
+    providers: Vec<Box<dyn ScreeningProvider>>,
+}
+
+impl CompositeScreener {
+    /// Combine providers
+    pub fn new(providers: Vec<Box<dyn ScreeningProvider>>) -> Self {
+        Self { providers }
+    }
+}
+
+impl ScreeningProvider for CompositeScreener {
+    fn name(&self) -> &str {
+        "composite"
+    }
+
+    fn screen(&self, key_type: KeyType, address: &str) -> Result<ScreeningResult> {
+        let mut combined = ScreeningResult::clear(address, self.name());
+        let mut sources = Vec::new();
+
+        for provider in &self.providers {
+            let result = provider.screen(key_type, address)?;
+            if result.risk > RiskLevel::Low {
+                sources.push(result.source);
+            }
+            combined.risk = combined.risk.max(result.risk);
+            combined.categories.extend(result.categories);
+        }
+
+        combined.categories.sort();
+        combined.categories.dedup();
+        if !sources.is_empty() {
+            combined.source = sources.join(",");
+        }
+        Ok(combined)
+    }
+}
+
+/// Action taken on a screened transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScreeningAction {
+    /// The transaction may proceed
+    Allow,
+    /// The transaction may proceed but needs review
+    Flag,
+    /// The transaction must not proceed
+    Block,
+}
+
+/// How screening results are acted on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompliancePolicy {
+    /// Lowest risk that flags a transaction
+    pub flag_at: RiskLevel,
+    /// Lowest risk that blocks a transaction
+    pub block_at: RiskLevel,
+    /// Block when the provider cannot be reached
+    pub fail_closed: bool,
+}
+
+impl Default for CompliancePolicy {
+    fn default() -> Self {
+        Self {
+            flag_at: RiskLevel::Medium,
+            block_at: RiskLevel::Severe,
+            fail_closed: true,
+        }
+    }
+}
+
+impl CompliancePolicy {
+    /// Get the action for a risk level
+    pub fn action(&self, risk: RiskLevel) -> ScreeningAction {
+        if risk >= self.block_at {
+            ScreeningAction::Block
+        } else if risk >= self.flag_at {
+            ScreeningAction::Flag
+        } else {
+            ScreeningAction::Allow
+        }
+    }
+}
+
+/// Direction of a screened transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScreeningDirection {
+    /// Funds leaving the wallet
+    Outgoing,
+    /// Funds arriving at the wallet
+    Deposit,
+}
+
+/// Audit record of a screening decision
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreeningRecord {
+    /// Blockchain type
+    pub key_type: KeyType,
+    /// Direction of the transfer
+    pub direction: ScreeningDirection,
+    /// Counterparty address
+    pub counterparty: String,
+    /// Transaction hash, for deposits
+    pub transaction_hash: Option<String>,
+    /// Provider result, or `None` if screening failed
+    pub result: Option<ScreeningResult>,
+    /// Provider error, if screening failed
+    pub error: Option<String>,
+    /// Action taken
+    pub action: ScreeningAction,
+    /// Unix timestamp of the decision
+    pub timestamp: u64,
+}
+
+/// Audit trail for screening decisions
+pub trait ScreeningAudit: Send + Sync {
+    /// Record a screening decision
+    fn record(&self, record: ScreeningRecord);
+}
+
+impl<T: ScreeningAudit + ?Sized> ScreeningAudit for Arc<T> {
+    fn record(&self, record: ScreeningRecord) {
+        (**self).record(record);
+    }
+}
+
+/// In-memory screening audit trail
+#[derive(Debug, Default)]
+pub struct InMemoryScreeningAudit {
+    records: Mutex<Vec<ScreeningRecord>>,
+}
+
+impl InMemoryScreeningAudit {
+    /// Create an empty trail
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get all records
+    pub fn records(&self) -> Vec<ScreeningRecord> {
+        self.records.lock().unwrap().clone()
+    }
+
+    /// Get the records awaiting review (flagged or blocked)
+    pub fn review_queue(&self) -> Vec<ScreeningRecord> {
+        self.records.lock().unwrap().iter()
+            .filter(|record| record.action != ScreeningAction::Allow)
+            .cloned()
+            .collect()
+    }
+}
+
+impl ScreeningAudit for InMemoryScreeningAudit {
+    fn record(&self, record: ScreeningRecord) {
+        self.records.lock().unwrap().push(record);
+    }
+}
+
+/// Screens counterparties and records every decision
+pub struct ComplianceScreener {
+    provider: Box<dyn ScreeningProvider>,
+    policy: CompliancePolicy,
+    audit: Box<dyn ScreeningAudit>,
+}
+
+impl ComplianceScreener {
+    /// Create a screener
+    pub fn new(provider: Box<dyn ScreeningProvider>, policy: CompliancePolicy, audit: Box<dyn ScreeningAudit>) -> Self {
+        Self { provider, policy, audit }
+    }
+
+    /// Screen the recipient of an outgoing transaction
+    pub fn screen_outgoing(&self, request: &TransactionRequest) -> Result<ScreeningRecord> {
+        self.screen(request.key_type, &request.to, ScreeningDirection::Outgoing, None)
+    }
+
+    /// Screen the sender of a deposit to `owner`
+    ///
+    /// Returns `None` if the transaction is not a deposit to `owner`.
+    pub fn screen_deposit(&self, transaction: &Transaction, owner: &str) -> Result<Option<ScreeningRecord>> {
+        if !transaction.to.eq_ignore_ascii_case(owner) || transaction.from.eq_ignore_ascii_case(owner) {
+            return Ok(None);
+        }
+
+        self.screen_sender(transaction.key_type, &transaction.from, &transaction.hash).map(Some)
+    }
+
+    /// Screen the sender of a deposit reported by a chain watcher
+    pub fn screen_sender(&self, key_type: KeyType, sender: &str, transaction_hash: &str) -> Result<ScreeningRecord> {
+        self.screen(key_type, sender, ScreeningDirection::Deposit, Some(transaction_hash.to_string()))
+    }
+
+    fn screen(&self, key_type: KeyType, counterparty: &str, direction: ScreeningDirection, transaction_hash: Option<String>) -> Result<ScreeningRecord> {
+        let (result, error, action) = match self.provider.screen(key_type, counterparty) {
+            Ok(result) => {
+                let action = self.policy.action(result.risk);
+                (Some(result), None, action)
+            }
+            Err(e) => {
+                let action = if self.policy.fail_closed { ScreeningAction::Block } else { ScreeningAction::Flag };
+                (None, Some(e.to_string()), action)
+            }
+        };
+
+        let record = ScreeningRecord {
+            key_type,
+            direction,
+            counterparty: counterparty.to_string(),
+            transaction_hash,
+            result,
+            error,
+            action,
+            timestamp: crate::time::unix_timestamp()?,
+        };
+
+        self.audit.record(record.clone());
+        Ok(record)
+    }
+}
+
+/// A provider wrapper that screens recipients before signing
+///
+/// Blocked transactions fail with [`Error::Compliance`]; flagged ones go
+/// ahead and are left in the audit trail for review.
+pub struct ScreenedProvider<P> {
+    /// Wrapped provider
+    inner: P,
+    /// Screener applied to outgoing transactions
+    screener: ComplianceScreener,
+}
+
+impl<P: TransactionManager> ScreenedProvider<P> {
+    /// Wrap a provider
+    pub fn new(inner: P, screener: ComplianceScreener) -> Self {
+        Self { inner, screener }
+    }
+
+    /// Get the wrapped provider
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Get the screener
+    pub fn screener(&self) -> &ComplianceScreener {
+        &self.screener
+    }
+
+    fn check(&self, request: &TransactionRequest) -> Result<()> {
+        let record = self.screener.screen_outgoing(request)?;
+        if record.action == ScreeningAction::Block {
+            let reason = match (&record.result, &record.error) {
+                (Some(result), _) => format!("{:?} risk ({})", result.risk, result.categories.join(", ")),
+                (None, Some(error)) => format!("screening unavailable: {}", error),
+                (None, None) => "screening failed".to_string(),
+            };
+            return Err(Error::Compliance(format!("Transfer to {} blocked: {}", request.to, reason)));
+        }
+        Ok(())
+    }
+}
+
+impl<P: TransactionManager> TransactionSigner for ScreenedProvider<P> {
+    fn sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        self.check(request)?;
+        self.inner.sign_transaction(request)
+    }
+}
+
+impl<P: TransactionManager> TransactionBroadcaster for ScreenedProvider<P> {
+    fn broadcast_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+        self.inner.broadcast_transaction(signed_transaction)
+    }
+
+    fn get_transaction_status(&self, hash: &str) -> Result<TransactionStatus> {
+        self.inner.get_transaction_status(hash)
+    }
+
+    fn get_transaction_receipt(&self, hash: &str) -> Result<TransactionReceipt> {
+        self.inner.get_transaction_receipt(hash)
+    }
+}
+
+impl<P: TransactionManager> TransactionManager for ScreenedProvider<P> {
+    fn create_and_sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        self.check(request)?;
+        self.inner.create_and_sign_transaction(request)
+    }
+
+    fn get_transaction(&self, hash: &str) -> Result<Transaction> {
+        self.inner.get_transaction(hash)
+    }
+
+    fn get_transactions(&self, address: &str, limit: usize, offset: usize) -> Result<Vec<Transaction>> {
+        self.inner.get_transactions(address, limit, offset)
+    }
+
+    fn batch_call(&self, calls: &[CallRequest]) -> Result<Vec<CallResult>> {
+        self.inner.batch_call(calls)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::mock::MockEvmProvider;
+    use crate::transaction::NetworkBinding;
+
+    const SANCTIONED: &str = "0x8589427373D6D84E98730D7795D8f6f8731FDA16";
+
+    struct Unavailable;
+
+    impl ScreeningProvider for Unavailable {
+        fn name(&self) -> &str {
+            "unavailable"
+        }
+
+        fn screen(&self, _key_type: KeyType, _address: &str) -> Result<ScreeningResult> {
+            Err(Error::Network("timeout".to_string()))
+        }
+    }
+
+    fn list() -> LocalListScreener {
+        LocalListScreener::from_csv(&format!("# test list\n{},sanctions\n0x1111111111111111111111111111111111111111,mixer\n", SANCTIONED)).unwrap()
+    }
+
+    fn request(to: &str) -> TransactionRequest {
+        TransactionRequest {
+            key_type: KeyType::Ethereum,
+            network: NetworkBinding::Evm { chain_id: 1 },
+            from: "0xfrom".to_string(),
+            to: to.to_string(),
+            value: "1".to_string(),
+            gas_price: None,
+            gas_limit: None,
+            nonce: None,
+            data: None,
+            destination_tag: None,
+            memo: None,
+        }
+    }
+
+    #[test]
+    fn test_local_list() {
+        let list = list();
+        assert_eq!(list.len(), 2);
+
+        // EVM addresses match regardless of checksum casing
+        let result = list.screen(KeyType::Ethereum, &SANCTIONED.to_lowercase()).unwrap();
+        assert_eq!(result.risk, RiskLevel::Severe);
+        assert_eq!(result.categories, vec!["sanctions".to_string()]);
+
+        assert_eq!(list.screen(KeyType::Ethereum, "0x2222222222222222222222222222222222222222").unwrap().risk, RiskLevel::Low);
+        assert!(LocalListScreener::from_csv("no-category").is_err());
+    }
+
+    #[test]
+    fn test_policy_actions() {
+        let policy = CompliancePolicy::default();
+        assert_eq!(policy.action(RiskLevel::Low), ScreeningAction::Allow);
+        assert_eq!(policy.action(RiskLevel::High), ScreeningAction::Flag);
+        assert_eq!(policy.action(RiskLevel::Severe), ScreeningAction::Block);
+    }
+
+    #[test]
+    fn test_screened_provider_blocks_before_signing() {
+        let audit = Arc::new(InMemoryScreeningAudit::new());
+        let screener = ComplianceScreener::new(Box::new(list()), CompliancePolicy::default(), Box::new(audit.clone()));
+
+        let mock = MockEvmProvider::new(1);
+        mock.set_balance("0xfrom", 10u128.pow(18));
+        let provider = ScreenedProvider::new(mock, screener);
+
+        let error = provider.send_transaction(&request(SANCTIONED)).unwrap_err();
+        assert!(matches!(error, Error::Compliance(_)));
+
+        // Flagged transfers go through but await review
+        assert!(provider.send_transaction(&request("0x1111111111111111111111111111111111111111")).is_ok());
+        assert!(provider.send_transaction(&request("0x2222222222222222222222222222222222222222")).is_ok());
+
+        let records = audit.records();
+        assert_eq!(records.len(), 3);
+        assert_eq!(audit.review_queue().len(), 2);
+        assert_eq!(records[0].action, ScreeningAction::Block);
+    }
+
+    #[test]
+    fn test_fail_closed_and_deposits() {
+        let audit = Arc::new(InMemoryScreeningAudit::new());
+        let closed = ComplianceScreener::new(Box::new(Unavailable), CompliancePolicy::default(), Box::new(audit.clone()));
+        assert_eq!(closed.screen_outgoing(&request("0xto")).unwrap().action, ScreeningAction::Block);
+
+        let open_policy = CompliancePolicy { fail_closed: false, ..CompliancePolicy::default() };
+        let open = ComplianceScreener::new(Box::new(Unavailable), open_policy, Box::new(audit.clone()));
+        assert_eq!(open.screen_outgoing(&request("0xto")).unwrap().action, ScreeningAction::Flag);
+
+        let screener = ComplianceScreener::new(Box::new(list()), CompliancePolicy::default(), Box::new(audit.clone()));
+        let deposit = Transaction {
+            hash: "0xdeposit".to_string(),
+            transaction_type: crate::transaction::TransactionType::Transfer,
+            key_type: KeyType::Ethereum,
+            from: SANCTIONED.to_string(),
+            to: "0xowner".to_string(),
+            value: "1".to_string(),
+            gas_price: None,
+            gas_limit: None,
+            nonce: None,
+            data: None,
+            status: TransactionStatus::Confirmed,
+            block_number: None,
+            timestamp: None,
+            fee: None,
+            memo: None,
+        };
+
+        let record = screener.screen_deposit(&deposit, "0xOWNER").unwrap().unwrap();
+        assert_eq!(record.direction, ScreeningDirection::Deposit);
+        assert_eq!(record.action, ScreeningAction::Block);
+        assert!(screener.screen_deposit(&deposit, "0xsomeone-else").unwrap().is_none());
+    }
+
+    #[cfg(feature = "rpc")]
+    #[test]
+    fn test_parse_chainalysis_response() {
+        let sanctioned = serde_json::json!({
+            "identifications": [{ "category": "sanctions", "name": "SANCTIONS: OFAC SDN", "description": "", "url": "" }]
+        });
+        let result = ChainalysisScreener::parse_response(SANCTIONED, &sanctioned).unwrap();
+        assert_eq!(result.risk, RiskLevel::Severe);
+
+        let clear = serde_json::json!({ "identifications": [] });
+        assert_eq!(ChainalysisScreener::parse_response(SANCTIONED, &clear).unwrap().risk, RiskLevel::Low);
+    }
+}
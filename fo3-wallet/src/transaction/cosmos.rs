@@ -0,0 +1,989 @@
+//! Cosmos SDK transaction functionality
+//!
+//! Builds bank sends, staking delegations and IBC transfers as protobuf
+//! messages, signs them with `SIGN_MODE_DIRECT` and talks to a chain's REST
+//! (LCD) API. Chains are described the way the Cosmos chain registry
+//! describes them, so a new chain needs a `chain.json` rather than code.
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::crypto::keys::{KeyPair, KeyType};
+use crate::error::{Error, Result};
+use super::types::{Memo, Transaction, TransactionRequest, TransactionReceipt, TransactionStatus, TransactionSigner, TransactionBroadcaster, TransactionManager, TransactionType, NetworkBinding};
+use super::provider::ProviderConfig;
+
+/// Type URL of a secp256k1 public key
+const SECP256K1_PUBKEY_TYPE_URL: &str = "/cosmos.crypto.secp256k1.PubKey";
+
+/// `SIGN_MODE_DIRECT` in the `SignMode` enum
+const SIGN_MODE_DIRECT: u64 = 1;
+
+/// Gas limits are padded by this factor over the defaults of each message
+const GAS_ADJUSTMENT: f64 = 1.3;
+
+/// A Cosmos SDK chain, as described by the chain registry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CosmosChain {
+    /// Registry name (`cosmoshub`, `osmosis`)
+    pub chain_name: String,
+    /// Chain ID (`cosmoshub-4`)
+    pub chain_id: String,
+    /// Bech32 prefix of account addresses
+    pub bech32_prefix: String,
+    /// SLIP-44 coin type used in derivation paths
+    pub slip44: u32,
+    /// Denom fees are paid in
+    pub fee_denom: String,
+    /// Average gas price, in `fee_denom`
+    pub gas_price: f64,
+    /// Denom that is staked
+    pub staking_denom: String,
+    /// Decimals of the staking denom's display unit
+    pub decimals: u8,
+    /// REST (LCD) endpoint, if the registry lists one
+    pub rest: Option<String>,
+}
+
+impl CosmosChain {
+    /// Cosmos Hub (ATOM)
+    pub fn cosmos_hub() -> Self {
+        Self {
+            chain_name: "cosmoshub".to_string(),
+            chain_id: "cosmoshub-4".to_string(),
+            bech32_prefix: "cosmos".to_string(),
+            slip44: 118,
+            fee_denom: "uatom".to_string(),
+            gas_price: 0.025,
+            staking_denom: "uatom".to_string(),
+            decimals: 6,
+            rest: Some("https://rest.cosmos.directory/cosmoshub".to_string()),
+        }
+    }
+
+    /// Osmosis (OSMO)
+    pub fn osmosis() -> Self {
+        Self {
+            chain_name: "osmosis".to_string(),
+            chain_id: "osmosis-1".to_string(),
+            bech32_prefix: "osmo".to_string(),
+            slip44: 118,
+            fee_denom: "uosmo".to_string(),
+            gas_price: 0.025,
+            staking_denom: "uosmo".to_string(),
+            decimals: 6,
+            rest: Some("https://rest.cosmos.directory/osmosis".to_string()),
+        }
+    }
+
+    /// Chains known without a registry file
+    pub fn known() -> Vec<Self> {
+        vec![Self::cosmos_hub(), Self::osmosis()]
+    }
+
+    /// Find a known chain by chain ID
+    pub fn by_chain_id(chain_id: &str) -> Option<Self> {
+        Self::known().into_iter().find(|chain| chain.chain_id == chain_id)
+    }
+
+    /// Parse a chain registry `chain.json`
+    ///
+    /// The registry keeps decimals in a separate asset list, so they default
+    /// to 6, which nearly all Cosmos SDK chains use.
+    pub fn from_registry(chain_json: &str) -> Result<Self> {
+        let chain: Value = serde_json::from_str(chain_json)
+            .map_err(|e| Error::Serialization(format!("Invalid chain registry entry: {}", e)))?;
+        let field = |name: &str| chain[name].as_str().map(str::to_string)
+            .ok_or_else(|| Error::InvalidInput(format!("Chain registry entry has no {}", name)));
+
+        let fee_token = &chain["fees"]["fee_tokens"][0];
+        let fee_denom = fee_token["denom"].as_str()
+            .ok_or_else(|| Error::InvalidInput("Chain registry entry has no fee token".to_string()))?
+            .to_string();
+        let gas_price = fee_token["average_gas_price"].as_f64()
+            .or_else(|| fee_token["fixed_min_gas_price"].as_f64())
+            .unwrap_or(0.0);
+
+        Ok(Self {
+            chain_name: field("chain_name")?,
+            chain_id: field("chain_id")?,
+            bech32_prefix: field("bech32_prefix")?,
+            slip44: chain["slip44"].as_u64().unwrap_or(118) as u32,
+            staking_denom: chain["staking"]["staking_tokens"][0]["denom"].as_str().unwrap_or(&fee_denom).to_string(),
+            fee_denom,
+            gas_price,
+            decimals: 6,
+            rest: chain["apis"]["rest"][0]["address"].as_str().map(str::to_string),
+        })
+    }
+
+    /// Fee for a gas limit at the chain's average gas price
+    pub fn fee(&self, gas_limit: u64) -> CosmosFee {
+        let amount = (gas_limit as f64 * self.gas_price).ceil() as u128;
+        CosmosFee { amount: vec![Coin::new(&self.fee_denom, amount)], gas_limit }
+    }
+}
+
+/// An amount of a denom
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Coin {
+    /// Denom (`uatom`, `ibc/...`)
+    pub denom: String,
+    /// Amount in the denom's base unit
+    pub amount: String,
+}
+
+impl Coin {
+    /// Create a coin
+    pub fn new(denom: &str, amount: u128) -> Self {
+        Self { denom: denom.to_string(), amount: amount.to_string() }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut writer = ProtoWriter::default();
+        writer.string(1, &self.denom);
+        writer.string(2, &self.amount);
+        writer.finish()
+    }
+}
+
+/// Height on a counterparty chain, for IBC timeouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct IbcHeight {
+    /// Revision of the chain (the number after the chain ID's last dash)
+    pub revision_number: u64,
+    /// Block height within the revision
+    pub revision_height: u64,
+}
+
+/// A message of a Cosmos transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CosmosMsg {
+    /// `cosmos.bank.v1beta1.MsgSend`
+    Send {
+        from_address: String,
+        to_address: String,
+        amount: Vec<Coin>,
+    },
+    /// `cosmos.staking.v1beta1.MsgDelegate`
+    Delegate {
+        delegator_address: String,
+        validator_address: String,
+        amount: Coin,
+    },
+    /// `cosmos.staking.v1beta1.MsgUndelegate`
+    Undelegate {
+        delegator_address: String,
+        validator_address: String,
+        amount: Coin,
+    },
+    /// `cosmos.staking.v1beta1.MsgBeginRedelegate`
+    BeginRedelegate {
+        delegator_address: String,
+        validator_src_address: String,
+        validator_dst_address: String,
+        amount: Coin,
+    },
+    /// `ibc.applications.transfer.v1.MsgTransfer`
+    IbcTransfer {
+        source_port: String,
+        source_channel: String,
+        token: Coin,
+        sender: String,
+        receiver: String,
+        timeout_height: IbcHeight,
+        /// Timeout as Unix nanoseconds, 0 for none
+        timeout_timestamp: u64,
+        memo: String,
+    },
+}
+
+impl CosmosMsg {
+    /// Protobuf type URL of the message
+    pub fn type_url(&self) -> &'static str {
+        match self {
+            Self::Send { .. } => "/cosmos.bank.v1beta1.MsgSend",
+            Self::Delegate { .. } => "/cosmos.staking.v1beta1.MsgDelegate",
+            Self::Undelegate { .. } => "/cosmos.staking.v1beta1.MsgUndelegate",
+            Self::BeginRedelegate { .. } => "/cosmos.staking.v1beta1.MsgBeginRedelegate",
+            Self::IbcTransfer { .. } => "/ibc.applications.transfer.v1.MsgTransfer",
+        }
+    }
+
+    /// Gas the message typically needs, before adjustment
+    pub fn default_gas(&self) -> u64 {
+        match self {
+            Self::Send { .. } => 80_000,
+            Self::Delegate { .. } => 200_000,
+            Self::Undelegate { .. } => 250_000,
+            Self::BeginRedelegate { .. } => 300_000,
+            Self::IbcTransfer { .. } => 150_000,
+        }
+    }
+
+    /// Address that must sign the message
+    pub fn signer(&self) -> &str {
+        match self {
+            Self::Send { from_address, .. } => from_address,
+            Self::Delegate { delegator_address, .. }
+            | Self::Undelegate { delegator_address, .. }
+            | Self::BeginRedelegate { delegator_address, .. } => delegator_address,
+            Self::IbcTransfer { sender, .. } => sender,
+        }
+    }
+
+    /// Protobuf encoding of the message
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = ProtoWriter::default();
+        match self {
+            Self::Send { from_address, to_address, amount } => {
+                writer.string(1, from_address);
+                writer.string(2, to_address);
+                for coin in amount {
+                    writer.message(3, &coin.encode());
+                }
+            }
+            Self::Delegate { delegator_address, validator_address, amount }
+            | Self::Undelegate { delegator_address, validator_address, amount } => {
+                writer.string(1, delegator_address);
+                writer.string(2, validator_address);
+                writer.message(3, &amount.encode());
+            }
+            Self::BeginRedelegate { delegator_address, validator_src_address, validator_dst_address, amount } => {
+                writer.string(1, delegator_address);
+                writer.string(2, validator_src_address);
+                writer.string(3, validator_dst_address);
+                writer.message(4, &amount.encode());
+            }
+            Self::IbcTransfer { source_port, source_channel, token, sender, receiver, timeout_height, timeout_timestamp, memo } => {
+                writer.string(1, source_port);
+                writer.string(2, source_channel);
+                writer.message(3, &token.encode());
+                writer.string(4, sender);
+                writer.string(5, receiver);
+                let mut height = ProtoWriter::default();
+                height.uint64(1, timeout_height.revision_number);
+                height.uint64(2, timeout_height.revision_height);
+                writer.message(6, &height.finish());
+                writer.uint64(7, *timeout_timestamp);
+                writer.string(8, memo);
+            }
+        }
+        writer.finish()
+    }
+}
+
+/// Fee of a Cosmos transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CosmosFee {
+    /// Fee paid
+    pub amount: Vec<Coin>,
+    /// Gas limit
+    pub gas_limit: u64,
+}
+
+/// Account number and sequence, which a signature commits to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CosmosAccount {
+    /// Account number, assigned when the account first receives funds
+    pub account_number: u64,
+    /// Number of transactions the account has sent
+    pub sequence: u64,
+}
+
+/// Minimal protobuf encoder; proto3 scalars equal to their default are omitted
+#[derive(Default)]
+struct ProtoWriter {
+    buffer: Vec<u8>,
+}
+
+impl ProtoWriter {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buffer.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        self.buffer.push(value as u8);
+    }
+
+    fn uint64(&mut self, field: u32, value: u64) {
+        if value != 0 {
+            self.varint((field as u64) << 3);
+            self.varint(value);
+        }
+    }
+
+    fn bytes(&mut self, field: u32, bytes: &[u8]) {
+        if !bytes.is_empty() {
+            self.message(field, bytes);
+        }
+    }
+
+    fn string(&mut self, field: u32, value: &str) {
+        self.bytes(field, value.as_bytes());
+    }
+
+    /// Embedded message, written even when empty
+    fn message(&mut self, field: u32, bytes: &[u8]) {
+        self.varint((field as u64) << 3 | 2);
+        self.varint(bytes.len() as u64);
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+fn encode_any(type_url: &str, value: &[u8]) -> Vec<u8> {
+    let mut writer = ProtoWriter::default();
+    writer.string(1, type_url);
+    writer.bytes(2, value);
+    writer.finish()
+}
+
+/// `TxBody` bytes of messages with a memo
+pub fn encode_tx_body(messages: &[CosmosMsg], memo: &str) -> Vec<u8> {
+    let mut writer = ProtoWriter::default();
+    for message in messages {
+        writer.message(1, &encode_any(message.type_url(), &message.encode()));
+    }
+    writer.string(2, memo);
+    writer.finish()
+}
+
+/// `AuthInfo` bytes of a single `SIGN_MODE_DIRECT` secp256k1 signer
+pub fn encode_auth_info(public_key: &[u8], sequence: u64, fee: &CosmosFee) -> Vec<u8> {
+    let mut key = ProtoWriter::default();
+    key.bytes(1, public_key);
+
+    let mut single = ProtoWriter::default();
+    single.uint64(1, SIGN_MODE_DIRECT);
+    let mut mode_info = ProtoWriter::default();
+    mode_info.message(1, &single.finish());
+
+    let mut signer_info = ProtoWriter::default();
+    signer_info.message(1, &encode_any(SECP256K1_PUBKEY_TYPE_URL, &key.finish()));
+    signer_info.message(2, &mode_info.finish());
+    signer_info.uint64(3, sequence);
+
+    let mut fee_writer = ProtoWriter::default();
+    for coin in &fee.amount {
+        fee_writer.message(1, &coin.encode());
+    }
+    fee_writer.uint64(2, fee.gas_limit);
+
+    let mut writer = ProtoWriter::default();
+    writer.message(1, &signer_info.finish());
+    writer.message(2, &fee_writer.finish());
+    writer.finish()
+}
+
+/// Sign messages with `SIGN_MODE_DIRECT`, returning `TxRaw` bytes ready to broadcast
+pub fn sign_direct(key_pair: &KeyPair, chain_id: &str, account: CosmosAccount, messages: &[CosmosMsg], memo: &str, fee: &CosmosFee) -> Result<Vec<u8>> {
+    if key_pair.key_type() != KeyType::Cosmos {
+        return Err(Error::Signing("Not a Cosmos key pair".to_string()));
+    }
+    if messages.is_empty() {
+        return Err(Error::Transaction("A transaction needs at least one message".to_string()));
+    }
+
+    let body = encode_tx_body(messages, memo);
+    let auth_info = encode_auth_info(key_pair.public_key().as_bytes(), account.sequence, fee);
+
+    let mut sign_doc = ProtoWriter::default();
+    sign_doc.bytes(1, &body);
+    sign_doc.bytes(2, &auth_info);
+    sign_doc.string(3, chain_id);
+    sign_doc.uint64(4, account.account_number);
+
+    let secret_key = secp256k1::SecretKey::from_slice(key_pair.private_key().as_bytes())
+        .map_err(|e| Error::Signing(format!("Invalid private key: {}", e)))?;
+    let digest: [u8; 32] = Sha256::digest(sign_doc.finish()).into();
+    let message = secp256k1::Message::from_digest(digest);
+    let signature = secp256k1::Secp256k1::signing_only().sign_ecdsa(&message, &secret_key);
+
+    let mut tx_raw = ProtoWriter::default();
+    tx_raw.bytes(1, &body);
+    tx_raw.bytes(2, &auth_info);
+    tx_raw.message(3, &signature.serialize_compact());
+    Ok(tx_raw.finish())
+}
+
+/// Hash of a broadcast transaction, as explorers show it
+pub fn cosmos_tx_hash(tx_bytes: &[u8]) -> String {
+    hex::encode_upper(Sha256::digest(tx_bytes))
+}
+
+/// Cosmos provider, backed by a chain's REST (LCD) API
+pub struct CosmosProvider {
+    /// Provider configuration
+    config: ProviderConfig,
+    /// Chain the API serves
+    chain: CosmosChain,
+    /// HTTP client
+    #[cfg(feature = "rpc")]
+    http: reqwest::Client,
+}
+
+impl CosmosProvider {
+    /// Create a provider for the known chain named in the URL, defaulting to the Cosmos Hub
+    pub fn new(config: ProviderConfig) -> Result<Self> {
+        let chain = CosmosChain::known().into_iter()
+            .find(|chain| config.url.contains(&chain.chain_name))
+            .unwrap_or_else(CosmosChain::cosmos_hub);
+        Self::with_chain(config, chain)
+    }
+
+    /// Create a provider for a chain, e.g. one read from the chain registry
+    pub fn with_chain(config: ProviderConfig, chain: CosmosChain) -> Result<Self> {
+        #[cfg(feature = "rpc")]
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(config.timeout.unwrap_or(30)))
+            .build()
+            .map_err(|e| Error::Provider(format!("Failed to create HTTP client: {}", e)))?;
+
+        Ok(Self {
+            config,
+            chain,
+            #[cfg(feature = "rpc")]
+            http,
+        })
+    }
+
+    /// Get the chain this provider serves
+    pub fn chain(&self) -> &CosmosChain {
+        &self.chain
+    }
+
+    /// Get the network this provider is connected to
+    pub fn network_binding(&self) -> NetworkBinding {
+        NetworkBinding::Cosmos { chain_id: self.chain.chain_id.clone() }
+    }
+
+    /// Fetch a REST path, returning `None` for 404s
+    #[cfg(feature = "rpc")]
+    fn call(&self, path: &str, query: &[(&str, String)], body: Option<Value>) -> Result<Option<Value>> {
+        let url = format!("{}{}", self.config.url.trim_end_matches('/'), path);
+        let request = match body {
+            Some(body) => self.http.post(&url).json(&body),
+            None => self.http.get(&url).query(query),
+        };
+        super::ethereum::block_on(super::ethereum::traced(super::ethereum::rpc_span("cosmos", &url, path), async {
+            let response = request.send().await
+                .map_err(|e| Error::Network(format!("Cosmos REST request failed: {}", e)))?;
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            let status = response.status();
+            let value: Value = response.json().await
+                .map_err(|e| Error::Provider(format!("Invalid Cosmos REST response: {}", e)))?;
+            if !status.is_success() {
+                return Err(Error::Provider(format!("Cosmos REST {} failed ({}): {}", path, status, value["message"])));
+            }
+            Ok(Some(value))
+        }))?
+    }
+
+    #[cfg(not(feature = "rpc"))]
+    fn call(&self, _path: &str, _query: &[(&str, String)], _body: Option<Value>) -> Result<Option<Value>> {
+        Err(Error::NotSupported("Cosmos chains require the rpc feature".to_string()))
+    }
+
+    /// Get the account number and sequence of an address
+    pub fn get_account(&self, address: &str) -> Result<CosmosAccount> {
+        let response = self.call(&format!("/cosmos/auth/v1beta1/accounts/{}", address), &[], None)?
+            .ok_or_else(|| Error::Transaction(format!("Account {} does not exist until it receives funds", address)))?;
+        parse_account(&response["account"])
+    }
+
+    /// Get the balance of a denom, in its base unit
+    pub fn get_balance(&self, address: &str, denom: &str) -> Result<u128> {
+        let response = self.call(&format!("/cosmos/bank/v1beta1/balances/{}/by_denom", address), &[("denom", denom.to_string())], None)?;
+        Ok(response
+            .and_then(|response| response["balance"]["amount"].as_str().and_then(|amount| amount.parse().ok()))
+            .unwrap_or(0))
+    }
+
+    /// Address of a key pair on this chain
+    pub fn address(&self, key_pair: &KeyPair) -> Result<String> {
+        crate::crypto::keys::cosmos::public_key_to_address(key_pair.public_key(), &self.chain.bech32_prefix)
+    }
+
+    /// Sign and broadcast messages, returning the transaction hash
+    ///
+    /// Without a gas limit, the messages' default gas is padded by 30%.
+    pub fn send(&self, key_pair: &KeyPair, messages: &[CosmosMsg], memo: &str, gas_limit: Option<u64>) -> Result<String> {
+        let address = self.address(key_pair)?;
+        if let Some(message) = messages.iter().find(|message| message.signer() != address) {
+            return Err(Error::Transaction(format!("{} must be signed by {}", message.type_url(), message.signer())));
+        }
+
+        let gas_limit = gas_limit.unwrap_or_else(|| {
+            (messages.iter().map(CosmosMsg::default_gas).sum::<u64>() as f64 * GAS_ADJUSTMENT) as u64
+        });
+        let account = self.get_account(&address)?;
+        let tx_bytes = sign_direct(key_pair, &self.chain.chain_id, account, messages, memo, &self.chain.fee(gas_limit))?;
+        self.broadcast_transaction(&tx_bytes)
+    }
+
+    /// Send the fee denom to an address
+    pub fn transfer(&self, key_pair: &KeyPair, to: &str, amount: u128, memo: &str) -> Result<String> {
+        let message = CosmosMsg::Send {
+            from_address: self.address(key_pair)?,
+            to_address: to.to_string(),
+            amount: vec![Coin::new(&self.chain.fee_denom, amount)],
+        };
+        self.send(key_pair, &[message], memo, None)
+    }
+
+    /// Send the fee denom as described by a request, with its memo
+    pub fn send_request(&self, key_pair: &KeyPair, request: &TransactionRequest) -> Result<String> {
+        if request.key_type != KeyType::Cosmos {
+            return Err(Error::Transaction("Not a Cosmos transaction".to_string()));
+        }
+        request.network.ensure_matches(&self.network_binding())?;
+        let amount = request.value.parse::<u128>()
+            .map_err(|e| Error::InvalidInput(format!("Invalid amount: {}", e)))?;
+        let gas_limit = request.gas_limit.as_deref()
+            .map(|gas_limit| gas_limit.parse::<u64>().map_err(|e| Error::InvalidInput(format!("Invalid gas limit: {}", e))))
+            .transpose()?;
+
+        let message = CosmosMsg::Send {
+            from_address: self.address(key_pair)?,
+            to_address: request.to.clone(),
+            amount: vec![Coin::new(&self.chain.fee_denom, amount)],
+        };
+        self.send(key_pair, &[message], request.memo_text()?.unwrap_or_default(), gas_limit)
+    }
+
+    /// Delegate the staking denom to a validator
+    pub fn delegate(&self, key_pair: &KeyPair, validator: &str, amount: u128) -> Result<String> {
+        let message = CosmosMsg::Delegate {
+            delegator_address: self.address(key_pair)?,
+            validator_address: validator.to_string(),
+            amount: Coin::new(&self.chain.staking_denom, amount),
+        };
+        self.send(key_pair, &[message], "", None)
+    }
+
+    /// Undelegate the staking denom from a validator
+    pub fn undelegate(&self, key_pair: &KeyPair, validator: &str, amount: u128) -> Result<String> {
+        let message = CosmosMsg::Undelegate {
+            delegator_address: self.address(key_pair)?,
+            validator_address: validator.to_string(),
+            amount: Coin::new(&self.chain.staking_denom, amount),
+        };
+        self.send(key_pair, &[message], "", None)
+    }
+
+    /// Move a delegation from one validator to another without unbonding
+    pub fn redelegate(&self, key_pair: &KeyPair, from_validator: &str, to_validator: &str, amount: u128) -> Result<String> {
+        let message = CosmosMsg::BeginRedelegate {
+            delegator_address: self.address(key_pair)?,
+            validator_src_address: from_validator.to_string(),
+            validator_dst_address: to_validator.to_string(),
+            amount: Coin::new(&self.chain.staking_denom, amount),
+        };
+        self.send(key_pair, &[message], "", None)
+    }
+
+    /// Get the delegations of an address
+    pub fn get_delegations(&self, delegator: &str) -> Result<Vec<CosmosDelegation>> {
+        let response = self.call(&format!("/cosmos/staking/v1beta1/delegations/{}", delegator), &[("pagination.limit", "200".to_string())], None)?;
+        match response {
+            Some(response) => parse_delegations(&response),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Get a validator by operator address
+    pub fn get_validator(&self, operator_address: &str) -> Result<CosmosValidator> {
+        let response = self.call(&format!("/cosmos/staking/v1beta1/validators/{}", operator_address), &[], None)?
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown validator: {}", operator_address)))?;
+        parse_validator(&response["validator"])
+    }
+
+    /// Get the bonded validators
+    pub fn get_bonded_validators(&self) -> Result<Vec<CosmosValidator>> {
+        let query = [("status", "BOND_STATUS_BONDED".to_string()), ("pagination.limit", "500".to_string())];
+        let response = self.call("/cosmos/staking/v1beta1/validators", &query, None)?.unwrap_or_default();
+        response["validators"].as_array().map(Vec::as_slice).unwrap_or_default()
+            .iter()
+            .map(parse_validator)
+            .collect()
+    }
+
+    /// Send a denom over an IBC transfer channel, timing out ten minutes after `now` (Unix seconds)
+    pub fn ibc_transfer(&self, key_pair: &KeyPair, source_channel: &str, receiver: &str, token: Coin, memo: &str, now: u64) -> Result<String> {
+        let message = CosmosMsg::IbcTransfer {
+            source_port: "transfer".to_string(),
+            source_channel: source_channel.to_string(),
+            token,
+            sender: self.address(key_pair)?,
+            receiver: receiver.to_string(),
+            timeout_height: IbcHeight::default(),
+            timeout_timestamp: (now + 600) * 1_000_000_000,
+            memo: memo.to_string(),
+        };
+        self.send(key_pair, &[message], "", None)
+    }
+}
+
+/// A delegation to a validator
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CosmosDelegation {
+    /// Delegator address
+    pub delegator_address: String,
+    /// Validator operator address
+    pub validator_address: String,
+    /// Tokens the delegation is worth
+    pub balance: Coin,
+}
+
+/// Bonding status of a validator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BondStatus {
+    /// In the active set, earning rewards
+    Bonded,
+    /// Leaving the active set
+    Unbonding,
+    /// Outside the active set
+    Unbonded,
+}
+
+/// A validator's public state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CosmosValidator {
+    /// Operator address (`cosmosvaloper1...`)
+    pub operator_address: String,
+    /// Display name
+    pub moniker: String,
+    /// Whether the validator is jailed for downtime or double signing
+    pub jailed: bool,
+    /// Bonding status
+    pub status: BondStatus,
+    /// Commission rate, as a fraction
+    pub commission_rate: f64,
+    /// Bonded tokens, in the staking denom's base unit
+    pub tokens: u128,
+    /// Shares issued to delegators
+    pub delegator_shares: f64,
+}
+
+impl CosmosValidator {
+    /// Get the tokens each share is worth, which only falls when the validator is slashed
+    pub fn tokens_per_share(&self) -> f64 {
+        if self.delegator_shares > 0.0 {
+            self.tokens as f64 / self.delegator_shares
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Parse `/cosmos/staking/v1beta1/delegations/{delegator}`
+pub fn parse_delegations(response: &Value) -> Result<Vec<CosmosDelegation>> {
+    let invalid = || Error::Provider(format!("Invalid delegations response: {}", response));
+    response["delegation_responses"].as_array().ok_or_else(invalid)?
+        .iter()
+        .map(|entry| {
+            let text = |value: &Value| value.as_str().map(str::to_string).ok_or_else(invalid);
+            Ok(CosmosDelegation {
+                delegator_address: text(&entry["delegation"]["delegator_address"])?,
+                validator_address: text(&entry["delegation"]["validator_address"])?,
+                balance: Coin {
+                    denom: text(&entry["balance"]["denom"])?,
+                    amount: text(&entry["balance"]["amount"])?,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Parse a validator from `/cosmos/staking/v1beta1/validators`
+pub fn parse_validator(validator: &Value) -> Result<CosmosValidator> {
+    let invalid = || Error::Provider(format!("Invalid validator: {}", validator));
+    let decimal = |value: &Value| value.as_str().and_then(|value| value.parse::<f64>().ok()).ok_or_else(invalid);
+
+    Ok(CosmosValidator {
+        operator_address: validator["operator_address"].as_str().ok_or_else(invalid)?.to_string(),
+        moniker: validator["description"]["moniker"].as_str().unwrap_or_default().to_string(),
+        jailed: validator["jailed"].as_bool().unwrap_or(false),
+        status: match validator["status"].as_str() {
+            Some("BOND_STATUS_BONDED") => BondStatus::Bonded,
+            Some("BOND_STATUS_UNBONDING") => BondStatus::Unbonding,
+            Some("BOND_STATUS_UNBONDED") => BondStatus::Unbonded,
+            _ => return Err(invalid()),
+        },
+        commission_rate: decimal(&validator["commission"]["commission_rates"]["rate"])?,
+        tokens: validator["tokens"].as_str().and_then(|tokens| tokens.parse().ok()).ok_or_else(invalid)?,
+        delegator_shares: decimal(&validator["delegator_shares"])?,
+    })
+}
+
+/// Parse an account from `/cosmos/auth/v1beta1/accounts`, including vesting accounts
+pub fn parse_account(account: &Value) -> Result<CosmosAccount> {
+    let base = [&account["base_vesting_account"]["base_account"], &account["base_account"], account].into_iter()
+        .find(|candidate| candidate["account_number"].is_string())
+        .ok_or_else(|| Error::Provider(format!("Unsupported account type: {}", account["@type"])))?;
+    let number = |field: &str| base[field].as_str().and_then(|value| value.parse::<u64>().ok())
+        .ok_or_else(|| Error::Provider(format!("Invalid account {}: {}", field, base[field])));
+
+    Ok(CosmosAccount { account_number: number("account_number")?, sequence: number("sequence")? })
+}
+
+/// Parse a `tx_response` into a transaction, described by its first message
+pub fn parse_tx_response(response: &Value) -> Result<Transaction> {
+    let hash = response["txhash"].as_str()
+        .ok_or_else(|| Error::Provider(format!("Invalid transaction response: {}", response)))?;
+    let message = &response["tx"]["body"]["messages"][0];
+    let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+
+    let (transaction_type, from, to, value) = match message["@type"].as_str().unwrap_or_default() {
+        "/cosmos.bank.v1beta1.MsgSend" => (TransactionType::Transfer, &message["from_address"], &message["to_address"], &message["amount"][0]["amount"]),
+        "/cosmos.staking.v1beta1.MsgDelegate" | "/cosmos.staking.v1beta1.MsgUndelegate" => {
+            (TransactionType::Staking, &message["delegator_address"], &message["validator_address"], &message["amount"]["amount"])
+        }
+        "/cosmos.staking.v1beta1.MsgBeginRedelegate" => {
+            (TransactionType::Staking, &message["delegator_address"], &message["validator_dst_address"], &message["amount"]["amount"])
+        }
+        "/ibc.applications.transfer.v1.MsgTransfer" => (TransactionType::TokenTransfer, &message["sender"], &message["receiver"], &message["token"]["amount"]),
+        _ => (TransactionType::Other, &Value::Null, &Value::Null, &Value::Null),
+    };
+    let memo = response["tx"]["body"]["memo"].as_str().filter(|memo| !memo.is_empty());
+
+    Ok(Transaction {
+        hash: hash.to_string(),
+        transaction_type,
+        key_type: KeyType::Cosmos,
+        from: text(from),
+        to: text(to),
+        value: value.as_str().unwrap_or("0").to_string(),
+        gas_price: None,
+        gas_limit: response["gas_wanted"].as_str().map(str::to_string),
+        nonce: response["tx"]["auth_info"]["signer_infos"][0]["sequence"].as_str().and_then(|sequence| sequence.parse().ok()),
+        data: None,
+        status: if response["code"].as_u64().unwrap_or(0) == 0 { TransactionStatus::Confirmed } else { TransactionStatus::Failed },
+        block_number: response["height"].as_str().and_then(|height| height.parse().ok()),
+        timestamp: response["timestamp"].as_str().and_then(crate::time::parse_rfc3339),
+        fee: response["tx"]["auth_info"]["fee"]["amount"][0]["amount"].as_str().map(str::to_string),
+        memo: memo.map(|memo| Memo::Text(memo.to_string())),
+    })
+}
+
+impl TransactionSigner for CosmosProvider {
+    fn sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        if request.key_type != KeyType::Cosmos {
+            return Err(Error::Transaction("Not a Cosmos transaction".to_string()));
+        }
+        request.network.ensure_matches(&self.network_binding())?;
+
+        // SIGN_MODE_DIRECT signs the account number and sequence with the owner's key, which requests don't carry
+        Err(Error::NotSupported("Cosmos transactions are signed by CosmosProvider::send_request with the account's key pair".to_string()))
+    }
+}
+
+impl TransactionBroadcaster for CosmosProvider {
+    fn broadcast_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+        use base64::Engine;
+
+        let body = serde_json::json!({
+            "tx_bytes": base64::engine::general_purpose::STANDARD.encode(signed_transaction),
+            "mode": "BROADCAST_MODE_SYNC",
+        });
+        let response = self.call("/cosmos/tx/v1beta1/txs", &[], Some(body))?
+            .ok_or_else(|| Error::Provider("Cosmos REST API does not accept transactions".to_string()))?;
+        let tx_response = &response["tx_response"];
+        if tx_response["code"].as_u64().unwrap_or(0) != 0 {
+            return Err(Error::Transaction(format!("Transaction rejected: {}", tx_response["raw_log"])));
+        }
+        Ok(tx_response["txhash"].as_str().map(str::to_string).unwrap_or_else(|| cosmos_tx_hash(signed_transaction)))
+    }
+
+    fn get_transaction_status(&self, hash: &str) -> Result<TransactionStatus> {
+        match self.call(&format!("/cosmos/tx/v1beta1/txs/{}", hash), &[], None)? {
+            Some(response) => Ok(parse_tx_response(&response["tx_response"])?.status),
+            None => Ok(TransactionStatus::Pending),
+        }
+    }
+
+    fn get_transaction_receipt(&self, hash: &str) -> Result<TransactionReceipt> {
+        let transaction = self.get_transaction(hash)?;
+        Ok(TransactionReceipt {
+            hash: transaction.hash,
+            status: transaction.status,
+            block_number: transaction.block_number,
+            timestamp: transaction.timestamp,
+            fee: transaction.fee,
+            logs: vec![],
+        })
+    }
+}
+
+impl TransactionManager for CosmosProvider {
+    fn get_transaction(&self, hash: &str) -> Result<Transaction> {
+        let response = self.call(&format!("/cosmos/tx/v1beta1/txs/{}", hash), &[], None)?
+            .ok_or_else(|| Error::Transaction(format!("Transaction not found: {}", hash)))?;
+        parse_tx_response(&response["tx_response"])
+    }
+
+    fn get_transactions(&self, address: &str, limit: usize, offset: usize) -> Result<Vec<Transaction>> {
+        // Transactions the address sent; SDK 0.50 renamed `events` to `query`
+        let filter = format!("message.sender='{}'", address);
+        let page = |name: &'static str| vec![
+            (name, filter.clone()),
+            ("order_by", "ORDER_BY_DESC".to_string()),
+            ("pagination.limit", limit.to_string()),
+            ("pagination.offset", offset.to_string()),
+        ];
+        let response = match self.call("/cosmos/tx/v1beta1/txs", &page("events"), None) {
+            Ok(response) => response,
+            Err(_) => self.call("/cosmos/tx/v1beta1/txs", &page("query"), None)?,
+        };
+        response
+            .and_then(|response| response["tx_responses"].as_array().cloned())
+            .unwrap_or_default()
+            .iter()
+            .map(parse_tx_response)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::cosmos::{derive_cosmos_key_pair, public_key_to_address};
+    use crate::crypto::mnemonic::mnemonic_to_seed;
+
+    fn key_pair() -> KeyPair {
+        let seed = mnemonic_to_seed("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap();
+        derive_cosmos_key_pair(&seed, "m/44'/118'/0'/0/0").unwrap()
+    }
+
+    #[test]
+    fn test_sign_direct() {
+        let key_pair = key_pair();
+        let send = CosmosMsg::Send {
+            from_address: public_key_to_address(key_pair.public_key(), "cosmos").unwrap(),
+            to_address: "cosmos1pkptre7fdkl6gfrzlesjjvhxhlc3r4gmmk8rs6".to_string(),
+            amount: vec![Coin::new("uatom", 1000)],
+        };
+        let fee = CosmosFee { amount: vec![Coin::new("uatom", 2500)], gas_limit: 100_000 };
+
+        let auth_info = encode_auth_info(key_pair.public_key().as_bytes(), 7, &fee);
+        assert_eq!(hex::encode(&auth_info), "0a500a460a1f2f636f736d6f732e63727970746f2e736563703235366b312e5075624b657912230a21024f4e2ad99c34d60b9ba6283c9431a8418af8673212961f97a77b6377fcd05b6212040a020801180712130a0d0a057561746f6d12043235303010a08d06");
+
+        let account = CosmosAccount { account_number: 12345, sequence: 7 };
+        let tx_bytes = sign_direct(&key_pair, "cosmoshub-4", account, &[send], "fo3", &fee).unwrap();
+        assert!(hex::encode(&tx_bytes).ends_with("1c596a6a60f165506c6cf5abf00720a2c2ace3adee7b3063c79e64a05c88b7662e2f110c95dd84a6fbf2a8e69c09c6c4efa60c5c9fa57c4e5565a6659be9e402"));
+        assert_eq!(cosmos_tx_hash(&tx_bytes), "8A390A50F0A6C77A9D71B863DB51B244F54F7DF25266482950E68B304C03EB80");
+    }
+
+    #[test]
+    fn test_ibc_transfer_encoding() {
+        let transfer = CosmosMsg::IbcTransfer {
+            source_port: "transfer".to_string(),
+            source_channel: "channel-141".to_string(),
+            token: Coin::new("uatom", 1),
+            sender: "cosmos1a".to_string(),
+            receiver: "osmo1b".to_string(),
+            timeout_height: IbcHeight::default(),
+            timeout_timestamp: 1,
+            memo: String::new(),
+        };
+        // The timeout height is always present, even when zero
+        assert!(hex::encode(transfer.encode()).ends_with("32003801"));
+        assert_eq!(transfer.signer(), "cosmos1a");
+    }
+
+    #[test]
+    fn test_chain_registry() {
+        let chain = CosmosChain::from_registry(r#"{
+            "chain_name": "juno",
+            "chain_id": "juno-1",
+            "bech32_prefix": "juno",
+            "slip44": 118,
+            "fees": { "fee_tokens": [{ "denom": "ujuno", "fixed_min_gas_price": 0.075, "average_gas_price": 0.1 }] },
+            "staking": { "staking_tokens": [{ "denom": "ujuno" }] },
+            "apis": { "rest": [{ "address": "https://lcd-juno.example.com" }] }
+        }"#).unwrap();
+        assert_eq!(chain.bech32_prefix, "juno");
+        assert_eq!(chain.fee(200_000).amount, vec![Coin::new("ujuno", 20_000)]);
+        assert_eq!(chain.rest.as_deref(), Some("https://lcd-juno.example.com"));
+
+        assert!(CosmosChain::from_registry(r#"{ "chain_name": "x" }"#).is_err());
+        assert_eq!(CosmosChain::by_chain_id("osmosis-1").unwrap().bech32_prefix, "osmo");
+    }
+
+    #[test]
+    fn test_parse_responses() {
+        let vesting = serde_json::json!({
+            "@type": "/cosmos.vesting.v1beta1.ContinuousVestingAccount",
+            "base_vesting_account": { "base_account": { "account_number": "42", "sequence": "3" } },
+        });
+        assert_eq!(parse_account(&vesting).unwrap(), CosmosAccount { account_number: 42, sequence: 3 });
+
+        let response = serde_json::json!({
+            "txhash": "ABC",
+            "height": "19000000",
+            "code": 0,
+            "gas_wanted": "104000",
+            "timestamp": "2023-11-14T22:13:20Z",
+            "tx": {
+                "body": {
+                    "messages": [{ "@type": "/cosmos.staking.v1beta1.MsgDelegate", "delegator_address": "cosmos1a", "validator_address": "cosmosvaloper1b", "amount": { "denom": "uatom", "amount": "5000" } }],
+                    "memo": "",
+                },
+                "auth_info": { "signer_infos": [{ "sequence": "3" }], "fee": { "amount": [{ "denom": "uatom", "amount": "2600" }] } },
+            },
+        });
+        let transaction = parse_tx_response(&response).unwrap();
+        assert_eq!(transaction.transaction_type, TransactionType::Staking);
+        assert_eq!(transaction.to, "cosmosvaloper1b");
+        assert_eq!(transaction.value, "5000");
+        assert_eq!(transaction.nonce, Some(3));
+        assert_eq!(transaction.timestamp, Some(1_700_000_000));
+        assert_eq!(transaction.fee.as_deref(), Some("2600"));
+
+        let delegations = serde_json::json!({
+            "delegation_responses": [{
+                "delegation": { "delegator_address": "cosmos1a", "validator_address": "cosmosvaloper1b", "shares": "5000.000000000000000000" },
+                "balance": { "denom": "uatom", "amount": "5000" },
+            }],
+        });
+        let delegations = parse_delegations(&delegations).unwrap();
+        assert_eq!(delegations[0].validator_address, "cosmosvaloper1b");
+        assert_eq!(delegations[0].balance, Coin::new("uatom", 5000));
+
+        let validator = serde_json::json!({
+            "operator_address": "cosmosvaloper1b",
+            "jailed": false,
+            "status": "BOND_STATUS_BONDED",
+            "tokens": "990000",
+            "delegator_shares": "1000000.000000000000000000",
+            "description": { "moniker": "Validator B" },
+            "commission": { "commission_rates": { "rate": "0.050000000000000000" } },
+        });
+        let validator = parse_validator(&validator).unwrap();
+        assert_eq!(validator.status, BondStatus::Bonded);
+        assert_eq!(validator.commission_rate, 0.05);
+        assert_eq!(validator.tokens_per_share(), 0.99);
+        assert!(parse_validator(&serde_json::json!({ "operator_address": "x" })).is_err());
+    }
+
+    #[test]
+    fn test_redelegate_encoding() {
+        let redelegate = CosmosMsg::BeginRedelegate {
+            delegator_address: "a".to_string(),
+            validator_src_address: "b".to_string(),
+            validator_dst_address: "c".to_string(),
+            amount: Coin::new("uatom", 1),
+        };
+        assert_eq!(hex::encode(redelegate.encode()), "0a01611201621a0163220a0a057561746f6d120131");
+        assert_eq!(redelegate.signer(), "a");
+    }
+}
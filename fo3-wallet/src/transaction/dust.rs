@@ -0,0 +1,254 @@
+//! Dust consolidation
+//!
+//! Wallets accumulate small Bitcoin UTXOs that cost more to spend the
+//! higher fees climb, and Solana token accounts whose rent deposit stays
+//! locked after the tokens are gone. These helpers find both and plan the
+//! transactions that clean them up: consolidating small UTXOs while fees
+//! are low, and closing empty token accounts to reclaim their rent.
+
+use serde::{Serialize, Deserialize};
+
+use crate::error::{Error, Result};
+use super::bitcoin::BitcoinInput;
+use super::solana::{AccountMeta, Instruction};
+use super::spl::TokenAccount;
+
+/// Smallest output Bitcoin Core relays, in satoshis
+pub const BITCOIN_DUST_LIMIT: u64 = 546;
+
+/// Size of a transaction's version, locktime and counts, in virtual bytes
+pub const TX_OVERHEAD_VSIZE: u64 = 11;
+
+/// Size of a P2WPKH output, in virtual bytes
+pub const OUTPUT_VSIZE: u64 = 31;
+
+/// Instruction index of SPL Token `CloseAccount`
+pub const CLOSE_ACCOUNT_INSTRUCTION: u8 = 9;
+
+/// Fee per signature on Solana, in lamports
+pub const SOLANA_SIGNATURE_FEE: u64 = 5_000;
+
+/// Most `CloseAccount` instructions that fit in one transaction
+pub const MAX_CLOSES_PER_TRANSACTION: usize = 20;
+
+/// Virtual size of an input spending `script_pubkey` (hex)
+///
+/// Unknown scripts are sized as P2SH-wrapped SegWit.
+pub fn input_vsize(script_pubkey: &str) -> u64 {
+    match script_pubkey.len() / 2 {
+        // OP_0 <20 bytes>
+        22 if script_pubkey.starts_with("0014") => 68,
+        // OP_1 <32 bytes>
+        34 if script_pubkey.starts_with("5120") => 58,
+        // OP_0 <32 bytes>, assuming a 2-of-3 multisig witness
+        34 if script_pubkey.starts_with("0020") => 105,
+        // OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG
+        25 if script_pubkey.starts_with("76a914") => 148,
+        _ => 91,
+    }
+}
+
+/// Policy for consolidating small UTXOs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsolidationPolicy {
+    /// Fee rate of the consolidation transaction, in sat/vB
+    pub fee_rate: u64,
+    /// UTXOs below this value are consolidated, in satoshis
+    pub small_utxo_threshold: u64,
+    /// Most inputs in one consolidation transaction
+    pub max_inputs: usize,
+}
+
+impl Default for ConsolidationPolicy {
+    fn default() -> Self {
+        Self { fee_rate: 2, small_utxo_threshold: 100_000, max_inputs: 200 }
+    }
+}
+
+/// A planned UTXO consolidation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UtxoConsolidation {
+    /// UTXOs to spend, smallest first
+    pub inputs: Vec<BitcoinInput>,
+    /// Dust UTXOs worth less than the fee to spend them, left alone
+    pub uneconomical: Vec<BitcoinInput>,
+    /// Estimated size of the transaction, in virtual bytes
+    pub vsize: u64,
+    /// Fee, in satoshis
+    pub fee: u64,
+    /// Value of the single consolidated output, in satoshis
+    pub output_value: u64,
+}
+
+impl UtxoConsolidation {
+    /// Total value of the inputs, in satoshis
+    pub fn total_input(&self) -> u64 {
+        self.inputs.iter().map(|input| input.amount).sum()
+    }
+}
+
+/// Plan the consolidation of small UTXOs into one output
+///
+/// UTXOs worth less than their own input fee at `policy.fee_rate` are dust
+/// and listed as uneconomical instead of spent. Returns `None` when no
+/// consolidation is worth doing: fewer than two inputs, or an output below
+/// the dust limit.
+pub fn plan_utxo_consolidation(utxos: &[BitcoinInput], policy: &ConsolidationPolicy) -> Option<UtxoConsolidation> {
+    let mut candidates: Vec<&BitcoinInput> = utxos.iter()
+        .filter(|utxo| utxo.amount < policy.small_utxo_threshold)
+        .collect();
+    candidates.sort_by_key(|utxo| utxo.amount);
+
+    let (economical, uneconomical): (Vec<&BitcoinInput>, Vec<&BitcoinInput>) = candidates.into_iter()
+        .partition(|utxo| utxo.amount > input_vsize(&utxo.script_pubkey) * policy.fee_rate);
+
+    let inputs: Vec<BitcoinInput> = economical.into_iter().take(policy.max_inputs).cloned().collect();
+    if inputs.len() < 2 {
+        return None;
+    }
+
+    let vsize = TX_OVERHEAD_VSIZE + OUTPUT_VSIZE + inputs.iter().map(|input| input_vsize(&input.script_pubkey)).sum::<u64>();
+    let fee = vsize * policy.fee_rate;
+    let total: u64 = inputs.iter().map(|input| input.amount).sum();
+    let output_value = total.checked_sub(fee).filter(|value| *value >= BITCOIN_DUST_LIMIT)?;
+
+    Some(UtxoConsolidation {
+        inputs,
+        uneconomical: uneconomical.into_iter().cloned().collect(),
+        vsize,
+        fee,
+        output_value,
+    })
+}
+
+/// Build an SPL Token `CloseAccount` instruction sending the rent to `destination`
+pub fn close_account_instruction(account: &TokenAccount, destination: &str) -> Instruction {
+    Instruction {
+        program_id: account.program.program_id().to_string(),
+        accounts: vec![
+            AccountMeta { pubkey: account.address.clone(), is_signer: false, is_writable: true },
+            AccountMeta { pubkey: destination.to_string(), is_signer: false, is_writable: true },
+            AccountMeta { pubkey: account.owner.clone(), is_signer: true, is_writable: false },
+        ],
+        data: vec![CLOSE_ACCOUNT_INSTRUCTION],
+    }
+}
+
+/// A planned cleanup of Solana token accounts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenAccountCleanup {
+    /// Accounts to close
+    pub accounts: Vec<TokenAccount>,
+    /// Instructions, grouped into transactions
+    pub transactions: Vec<Vec<Instruction>>,
+    /// Lamports returned by closing the accounts
+    pub recovered_lamports: u64,
+    /// Estimated fees, in lamports
+    pub fee_lamports: u64,
+}
+
+impl TokenAccountCleanup {
+    /// Lamports gained after fees
+    pub fn net_lamports(&self) -> i64 {
+        self.recovered_lamports as i64 - self.fee_lamports as i64
+    }
+}
+
+/// Whether `owner` can close a token account and reclaim its rent
+///
+/// Empty accounts can be closed, as can wrapped SOL accounts, which unwrap
+/// their balance. Frozen accounts and accounts with another close authority
+/// cannot be closed by the owner.
+pub fn is_reclaimable(account: &TokenAccount, owner: &str) -> bool {
+    account.owner == owner
+        && !account.frozen
+        && (account.is_empty() || account.is_native)
+        && !matches!(account.close_authority.as_deref(), Some(authority) if authority != owner)
+}
+
+/// Plan closing every reclaimable token account of `owner`, sending the rent back to it
+pub fn plan_token_account_cleanup(accounts: &[TokenAccount], owner: &str) -> Result<TokenAccountCleanup> {
+    if owner.is_empty() {
+        return Err(Error::InvalidInput("Owner must not be empty".to_string()));
+    }
+
+    let accounts: Vec<TokenAccount> = accounts.iter()
+        .filter(|account| is_reclaimable(account, owner))
+        .cloned()
+        .collect();
+    let transactions: Vec<Vec<Instruction>> = accounts.chunks(MAX_CLOSES_PER_TRANSACTION)
+        .map(|chunk| chunk.iter().map(|account| close_account_instruction(account, owner)).collect())
+        .collect();
+
+    Ok(TokenAccountCleanup {
+        recovered_lamports: accounts.iter().map(|account| account.lamports).sum(),
+        fee_lamports: transactions.len() as u64 * SOLANA_SIGNATURE_FEE,
+        accounts,
+        transactions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::TokenProgram;
+
+    const P2WPKH: &str = "0014751e76e8199196d454941c45d1b3a323f1433bd6";
+
+    fn utxo(vout: u32, amount: u64) -> BitcoinInput {
+        BitcoinInput {
+            txid: "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b".to_string(),
+            vout,
+            amount,
+            script_pubkey: P2WPKH.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_plan_utxo_consolidation() {
+        let utxos = vec![utxo(0, 50_000), utxo(1, 100), utxo(2, 2_000), utxo(3, 5_000_000), utxo(4, 30_000)];
+        let policy = ConsolidationPolicy { fee_rate: 5, ..ConsolidationPolicy::default() };
+
+        let plan = plan_utxo_consolidation(&utxos, &policy).unwrap();
+        let amounts: Vec<u64> = plan.inputs.iter().map(|input| input.amount).collect();
+        assert_eq!(amounts, vec![2_000, 30_000, 50_000]);
+        assert_eq!(plan.uneconomical.len(), 1);
+        assert_eq!(plan.vsize, 11 + 31 + 3 * 68);
+        assert_eq!(plan.fee, plan.vsize * 5);
+        assert_eq!(plan.output_value, plan.total_input() - plan.fee);
+
+        // A single small UTXO needs no consolidation
+        assert!(plan_utxo_consolidation(&utxos[..2], &policy).is_none());
+    }
+
+    #[test]
+    fn test_plan_token_account_cleanup() {
+        let owner = "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg";
+        let account = |address: &str, amount: u128| TokenAccount {
+            address: address.to_string(),
+            mint: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(),
+            owner: owner.to_string(),
+            program: TokenProgram::Token,
+            amount,
+            decimals: 6,
+            lamports: 2_039_280,
+            is_native: false,
+            frozen: false,
+            close_authority: None,
+        };
+
+        let mut frozen = account("frozen", 0);
+        frozen.frozen = true;
+        let mut delegated = account("delegated", 0);
+        delegated.close_authority = Some("someone-else".to_string());
+        let accounts = vec![account("empty", 0), account("funded", 10), frozen, delegated, account("empty-2022", 0)];
+
+        let plan = plan_token_account_cleanup(&accounts, owner).unwrap();
+        let closed: Vec<&str> = plan.accounts.iter().map(|account| account.address.as_str()).collect();
+        assert_eq!(closed, vec!["empty", "empty-2022"]);
+        assert_eq!(plan.transactions.len(), 1);
+        assert_eq!(plan.transactions[0][0].data, vec![CLOSE_ACCOUNT_INSTRUCTION]);
+        assert_eq!(plan.recovered_lamports, 2 * 2_039_280);
+        assert_eq!(plan.net_lamports(), 2 * 2_039_280 - 5_000);
+    }
+}
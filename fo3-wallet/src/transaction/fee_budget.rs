@@ -0,0 +1,314 @@
+//! Fee budgets
+//!
+//! A [`FeeBudget`] caps the fee a single transaction may pay, absolutely
+//! (per chain, in the native token's smallest unit) and as a share of the
+//! value sent, so a gas spike cannot turn a small transfer into an expensive
+//! one. [`FeeBudgetedProvider`] enforces a budget before signing; requests
+//! over budget fail with [`Error::FeeBudget`] carrying the computed fee.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use serde::{Serialize, Deserialize};
+
+use crate::crypto::keys::KeyType;
+use crate::error::{Error, FeeBudgetExceeded, FeeBudgetRule, Result};
+use super::batch::{CallRequest, CallResult};
+use super::types::{Transaction, TransactionRequest, TransactionReceipt, TransactionStatus, TransactionSigner, TransactionBroadcaster, TransactionManager};
+
+/// Gas of a plain EVM value transfer
+const EVM_TRANSFER_GAS: u128 = 21_000;
+
+/// Fee the Bitcoin provider pays when none is given, in satoshis
+const BITCOIN_DEFAULT_FEE: u128 = 10_000;
+
+/// Fee the XRP provider pays when none is given, in drops
+const XRP_DEFAULT_FEE: u128 = 12;
+
+/// Base fee of a single-signature Solana transaction, in lamports
+const SOLANA_BASE_FEE: u128 = 5_000;
+
+/// Get the most a request can pay in fees, in the smallest unit of the native token
+///
+/// EVM and Cosmos fees are the gas price times the gas limit; Bitcoin and
+/// XRP requests carry the fee itself as `gas_price`. Returns `None` when the
+/// fee is left to the provider and cannot be known before signing.
+pub fn max_fee(request: &TransactionRequest) -> Result<Option<u128>> {
+    let parse = |field: &str, value: &str| value.parse::<u128>()
+        .map_err(|_| Error::InvalidInput(format!("Invalid {}: {}", field, value)));
+    let gas_price = request.gas_price.as_deref().map(|gas_price| parse("gas price", gas_price)).transpose()?;
+    let gas_limit = request.gas_limit.as_deref().map(|gas_limit| parse("gas limit", gas_limit)).transpose()?;
+
+    let fee = match request.key_type {
+        KeyType::Ethereum => {
+            let gas_limit = gas_limit.or(request.data.is_none().then_some(EVM_TRANSFER_GAS));
+            gas_price.zip(gas_limit).map(|(price, limit)| price.saturating_mul(limit))
+        }
+        KeyType::Bitcoin => Some(gas_price.unwrap_or(BITCOIN_DEFAULT_FEE)),
+        KeyType::Xrp => Some(gas_price.unwrap_or(XRP_DEFAULT_FEE)),
+        KeyType::Solana => match gas_price.zip(gas_limit) {
+            // Priority fees are priced in micro-lamports per compute unit
+            Some((price, limit)) => Some(SOLANA_BASE_FEE + price.saturating_mul(limit) / 1_000_000),
+            None => Some(SOLANA_BASE_FEE),
+        },
+        KeyType::Cosmos => gas_price.zip(gas_limit).map(|(price, limit)| price.saturating_mul(limit)),
+        KeyType::Ton => None,
+    };
+    Ok(fee)
+}
+
+/// Limits on the fee of a single transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeBudget {
+    /// Highest fee per chain, in the smallest unit of its native token
+    #[serde(default)]
+    pub max_fee: HashMap<KeyType, String>,
+    /// Highest fee in basis points of the value sent (e.g. 200 for 2%)
+    #[serde(default)]
+    pub max_fee_bps: Option<u32>,
+}
+
+impl FeeBudget {
+    /// Validate the limits
+    pub fn validate(&self) -> Result<()> {
+        for (key_type, limit) in &self.max_fee {
+            limit.parse::<u128>()
+                .map_err(|_| Error::InvalidInput(format!("Invalid {:?} fee limit: {}", key_type, limit)))?;
+        }
+        Ok(())
+    }
+
+    /// Whether the budget limits fees on a chain
+    pub fn limits(&self, key_type: KeyType) -> bool {
+        self.max_fee.contains_key(&key_type) || self.max_fee_bps.is_some()
+    }
+
+    /// Check a request against the budget
+    ///
+    /// The percentage limit only applies to requests moving value; contract
+    /// calls sending nothing are held to the absolute limit alone. Requests
+    /// whose fee cannot be known before signing are refused on limited chains.
+    pub fn check(&self, request: &TransactionRequest) -> Result<()> {
+        if !self.limits(request.key_type) {
+            return Ok(());
+        }
+        let fee = max_fee(request)?.ok_or_else(|| {
+            Error::InvalidInput(format!("Set the gas price and limit of {:?} transactions so the fee budget can be enforced", request.key_type))
+        })?;
+        let exceeded = |limit: u128, rule: FeeBudgetRule| Error::FeeBudget(FeeBudgetExceeded {
+            key_type: request.key_type,
+            fee: fee.to_string(),
+            limit: limit.to_string(),
+            value: request.value.clone(),
+            rule,
+        });
+
+        if let Some(limit) = self.max_fee.get(&request.key_type) {
+            let limit = limit.parse::<u128>()
+                .map_err(|_| Error::InvalidInput(format!("Invalid {:?} fee limit: {}", request.key_type, limit)))?;
+            if fee > limit {
+                return Err(exceeded(limit, FeeBudgetRule::Absolute));
+            }
+        }
+        if let Some(bps) = self.max_fee_bps {
+            let value = request.value.parse::<u128>()
+                .map_err(|_| Error::InvalidInput(format!("Invalid value: {}", request.value)))?;
+            let limit = value.saturating_mul(bps as u128) / 10_000;
+            if value > 0 && fee > limit {
+                return Err(exceeded(limit, FeeBudgetRule::PercentageOfValue));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fee budgets of each user, falling back to a default
+#[derive(Debug, Default)]
+pub struct FeeBudgets {
+    default: RwLock<FeeBudget>,
+    users: RwLock<HashMap<String, FeeBudget>>,
+}
+
+impl FeeBudgets {
+    /// Create budgets with a default for users without their own
+    pub fn new(default: FeeBudget) -> Self {
+        Self { default: RwLock::new(default), users: RwLock::new(HashMap::new()) }
+    }
+
+    /// Replace the default budget, keeping users' own budgets
+    pub fn set_default(&self, default: FeeBudget) -> Result<()> {
+        default.validate()?;
+        *self.default.write().unwrap() = default;
+        Ok(())
+    }
+
+    /// Get the budget applying to a user
+    pub fn budget_for(&self, user: Option<&str>) -> FeeBudget {
+        user.and_then(|user| self.users.read().unwrap().get(user).cloned())
+            .unwrap_or_else(|| self.default.read().unwrap().clone())
+    }
+
+    /// Set a user's budget
+    pub fn set(&self, user: &str, budget: FeeBudget) -> Result<()> {
+        budget.validate()?;
+        self.users.write().unwrap().insert(user.to_string(), budget);
+        Ok(())
+    }
+
+    /// Remove a user's budget, returning whether one was set
+    pub fn remove(&self, user: &str) -> bool {
+        self.users.write().unwrap().remove(user).is_some()
+    }
+}
+
+/// A provider wrapper that refuses to sign transactions over a fee budget
+pub struct FeeBudgetedProvider<P> {
+    /// Wrapped provider
+    inner: P,
+    /// Budget applied to every transaction
+    budget: FeeBudget,
+}
+
+impl<P: TransactionManager> FeeBudgetedProvider<P> {
+    /// Wrap a provider
+    pub fn new(inner: P, budget: FeeBudget) -> Self {
+        Self { inner, budget }
+    }
+
+    /// Get the wrapped provider
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Get the budget
+    pub fn budget(&self) -> &FeeBudget {
+        &self.budget
+    }
+}
+
+impl<P: TransactionManager> TransactionSigner for FeeBudgetedProvider<P> {
+    fn sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        self.budget.check(request)?;
+        self.inner.sign_transaction(request)
+    }
+}
+
+impl<P: TransactionManager> TransactionBroadcaster for FeeBudgetedProvider<P> {
+    fn broadcast_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+        self.inner.broadcast_transaction(signed_transaction)
+    }
+
+    fn get_transaction_status(&self, hash: &str) -> Result<TransactionStatus> {
+        self.inner.get_transaction_status(hash)
+    }
+
+    fn get_transaction_receipt(&self, hash: &str) -> Result<TransactionReceipt> {
+        self.inner.get_transaction_receipt(hash)
+    }
+}
+
+impl<P: TransactionManager> TransactionManager for FeeBudgetedProvider<P> {
+    fn create_and_sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        self.budget.check(request)?;
+        self.inner.create_and_sign_transaction(request)
+    }
+
+    fn get_transaction(&self, hash: &str) -> Result<Transaction> {
+        self.inner.get_transaction(hash)
+    }
+
+    fn get_transactions(&self, address: &str, limit: usize, offset: usize) -> Result<Vec<Transaction>> {
+        self.inner.get_transactions(address, limit, offset)
+    }
+
+    fn batch_call(&self, calls: &[CallRequest]) -> Result<Vec<CallResult>> {
+        self.inner.batch_call(calls)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::mock::MockEvmProvider;
+    use crate::transaction::NetworkBinding;
+
+    fn request(value: &str, gas_price: Option<&str>) -> TransactionRequest {
+        TransactionRequest {
+            key_type: KeyType::Ethereum,
+            network: NetworkBinding::Evm { chain_id: 1 },
+            from: "0xfrom".to_string(),
+            to: "0xto".to_string(),
+            value: value.to_string(),
+            gas_price: gas_price.map(str::to_string),
+            gas_limit: None,
+            nonce: None,
+            data: None,
+            destination_tag: None,
+            memo: None,
+        }
+    }
+
+    fn budget() -> FeeBudget {
+        // 0.01 ETH, or 2% of the value sent
+        FeeBudget {
+            max_fee: HashMap::from([(KeyType::Ethereum, "10000000000000000".to_string())]),
+            max_fee_bps: Some(200),
+        }
+    }
+
+    #[test]
+    fn test_max_fee() {
+        assert_eq!(max_fee(&request("1", Some("20000000000"))).unwrap(), Some(420_000_000_000_000));
+        assert_eq!(max_fee(&request("1", None)).unwrap(), None);
+
+        let xrp = TransactionRequest { key_type: KeyType::Xrp, ..request("1", None) };
+        assert_eq!(max_fee(&xrp).unwrap(), Some(12));
+    }
+
+    #[test]
+    fn test_budget_rules() {
+        let one_eth = "1000000000000000000";
+        assert!(budget().check(&request(one_eth, Some("20000000000"))).is_ok());
+
+        // 1000 gwei during a spike costs 0.021 ETH, over the absolute limit
+        let error = budget().check(&request(one_eth, Some("1000000000000"))).unwrap_err();
+        let Error::FeeBudget(exceeded) = error else { panic!("unexpected error: {}", error) };
+        assert_eq!(exceeded.fee, "21000000000000000");
+        assert_eq!(exceeded.rule, FeeBudgetRule::Absolute);
+
+        // 0.00042 ETH is more than 2% of 0.01 ETH
+        let error = budget().check(&request("10000000000000000", Some("20000000000"))).unwrap_err();
+        assert!(matches!(error, Error::FeeBudget(FeeBudgetExceeded { rule: FeeBudgetRule::PercentageOfValue, .. })));
+
+        // Fees left to the provider cannot be checked
+        assert!(matches!(budget().check(&request(one_eth, None)), Err(Error::InvalidInput(_))));
+        assert!(FeeBudget::default().check(&request(one_eth, None)).is_ok());
+    }
+
+    #[test]
+    fn test_budgeted_provider_refuses_before_signing() {
+        let mock = MockEvmProvider::new(1);
+        mock.set_balance("0xfrom", 2 * 10u128.pow(18));
+        let provider = FeeBudgetedProvider::new(mock, budget());
+
+        assert!(provider.send_transaction(&request("1000000000000000000", Some("1000000000000"))).is_err());
+        assert!(provider.inner().get_transactions("0xfrom", 10, 0).unwrap().is_empty());
+        assert!(provider.send_transaction(&request("1000000000000000000", Some("20000000000"))).is_ok());
+    }
+
+    #[test]
+    fn test_user_budgets() {
+        let budgets = FeeBudgets::new(FeeBudget::default());
+        budgets.set("alice", budget()).unwrap();
+        assert_eq!(budgets.budget_for(Some("alice")), budget());
+        assert_eq!(budgets.budget_for(Some("bob")), FeeBudget::default());
+
+        let invalid = FeeBudget { max_fee: HashMap::from([(KeyType::Bitcoin, "0.1".to_string())]), max_fee_bps: None };
+        assert!(budgets.set("alice", invalid).is_err());
+        assert!(budgets.remove("alice"));
+
+        let capped = FeeBudget { max_fee_bps: Some(200), ..FeeBudget::default() };
+        budgets.set_default(capped.clone()).unwrap();
+        assert_eq!(budgets.budget_for(None), capped);
+    }
+}
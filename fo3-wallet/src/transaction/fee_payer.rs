@@ -0,0 +1,577 @@
+//! Sponsored Solana fees
+//!
+//! Users holding SPL tokens but no SOL cannot pay for the transaction that
+//! moves their tokens. A [`FeePayer`] holding a service account signs such
+//! transactions as fee payer: it compiles the user's instructions with
+//! itself first and hands the message back for the user to sign. Once the
+//! user's signatures are in, it adds its own and broadcasts the transaction
+//! itself, so its signature never leaves without the quota being counted.
+//! Daily quotas per signing key and for the service account as a whole, a
+//! program allowlist and a cap on priority fees keep the service account
+//! from being drained.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Serialize, Deserialize};
+
+use crate::error::{Error, Result};
+use super::dust::SOLANA_SIGNATURE_FEE;
+use super::solana::{decode_pubkey, encode_length, Instruction, Message};
+use super::spl::{TokenProgram, ASSOCIATED_TOKEN_PROGRAM_ID};
+use super::types::TransactionBroadcaster;
+
+/// Program ID of the Memo program
+pub const MEMO_PROGRAM_ID: &str = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr";
+
+/// Program ID of the Compute Budget program
+pub const COMPUTE_BUDGET_PROGRAM_ID: &str = "ComputeBudget111111111111111111111111111111";
+
+/// Seconds in a quota day
+const DAY: u64 = 86_400;
+
+/// Seconds a sponsored message waits for its signers; its blockhash expires
+/// after 150 slots, about a minute
+const PENDING_TTL: u64 = 120;
+
+/// Compute units an instruction may use when the transaction sets no limit
+const DEFAULT_INSTRUCTION_COMPUTE_UNITS: u64 = 200_000;
+
+/// Most compute units a transaction may use
+const MAX_TRANSACTION_COMPUTE_UNITS: u64 = 1_400_000;
+
+/// Limits on what the fee payer sponsors
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeePayerPolicy {
+    /// Most transactions sponsored per signing key per day
+    pub max_transactions_per_day: u32,
+    /// Most fees paid per signing key per day, in lamports
+    pub max_fees_per_day: u64,
+    /// Most transactions sponsored per day across all users
+    pub max_total_transactions_per_day: u32,
+    /// Most fees paid per day across all users, in lamports
+    pub max_total_fees_per_day: u64,
+    /// Highest compute unit price a sponsored transaction may set, in micro-lamports
+    pub max_compute_unit_price: u64,
+    /// Most instructions in a sponsored transaction
+    pub max_instructions: usize,
+    /// Programs sponsored transactions may invoke
+    pub allowed_programs: HashSet<String>,
+}
+
+impl Default for FeePayerPolicy {
+    fn default() -> Self {
+        let programs = TokenProgram::ALL.iter().map(|program| program.program_id())
+            .chain([ASSOCIATED_TOKEN_PROGRAM_ID, MEMO_PROGRAM_ID, COMPUTE_BUDGET_PROGRAM_ID]);
+        Self {
+            max_transactions_per_day: 20,
+            max_fees_per_day: 20 * 2 * SOLANA_SIGNATURE_FEE,
+            max_total_transactions_per_day: 2_000,
+            max_total_fees_per_day: 100_000_000,
+            max_compute_unit_price: 10_000,
+            max_instructions: 8,
+            allowed_programs: programs.map(str::to_string).collect(),
+        }
+    }
+}
+
+/// Sponsored usage on one day, of a signing key or of the service account
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeePayerUsage {
+    /// Day number since the Unix epoch
+    pub day: u64,
+    /// Transactions sponsored
+    pub transactions: u32,
+    /// Fees paid, in lamports
+    pub fees: u64,
+}
+
+impl FeePayerUsage {
+    /// Usage on `day`, starting from zero if this usage is from an earlier day
+    fn on(self, day: u64) -> Self {
+        if self.day == day { self } else { Self { day, ..Self::default() } }
+    }
+
+    fn add(self, fee: u64) -> Self {
+        Self { day: self.day, transactions: self.transactions + 1, fees: self.fees + fee }
+    }
+
+    fn sub(self, fee: u64) -> Self {
+        Self { day: self.day, transactions: self.transactions.saturating_sub(1), fees: self.fees.saturating_sub(fee) }
+    }
+}
+
+/// Compute budget a transaction requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ComputeBudget {
+    /// Compute unit limit, if set
+    pub unit_limit: Option<u32>,
+    /// Compute unit price in micro-lamports
+    pub unit_price: u64,
+}
+
+impl ComputeBudget {
+    /// Read the Compute Budget instructions among `instructions`
+    pub fn parse(instructions: &[Instruction]) -> Result<Self> {
+        let mut budget = Self::default();
+        for instruction in instructions.iter().filter(|instruction| instruction.program_id == COMPUTE_BUDGET_PROGRAM_ID) {
+            match instruction.data.split_first() {
+                // RequestHeapFrame and SetLoadedAccountsDataSizeLimit do not change the fee
+                Some((1 | 4, value)) if value.len() == 4 => {}
+                Some((2, value)) if value.len() == 4 => budget.unit_limit = Some(u32::from_le_bytes(value.try_into().unwrap())),
+                Some((3, value)) if value.len() == 8 => budget.unit_price = u64::from_le_bytes(value.try_into().unwrap()),
+                _ => return Err(Error::InvalidInput("Unrecognized Compute Budget instruction".to_string())),
+            }
+        }
+        Ok(budget)
+    }
+
+    /// Priority fee in lamports for a transaction of `instructions`
+    pub fn priority_fee(&self, instructions: &[Instruction]) -> u64 {
+        let units = match self.unit_limit {
+            Some(limit) => limit as u64,
+            None => {
+                let count = instructions.iter().filter(|instruction| instruction.program_id != COMPUTE_BUDGET_PROGRAM_ID).count();
+                count as u64 * DEFAULT_INSTRUCTION_COMPUTE_UNITS
+            }
+        }.min(MAX_TRANSACTION_COMPUTE_UNITS);
+        // Micro-lamports, rounded up to whole lamports as the runtime does
+        ((self.unit_price as u128 * units as u128).div_ceil(1_000_000)) as u64
+    }
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    total: FeePayerUsage,
+    signers: HashMap<String, FeePayerUsage>,
+    /// Sponsored transactions awaiting their signers, by message, with the time they were sponsored
+    pending: HashMap<String, (SponsoredTransaction, u64)>,
+}
+
+/// A transaction paid for by the fee payer, awaiting signatures
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SponsoredTransaction {
+    /// The message
+    pub message: Message,
+    /// Serialized message to sign, base64
+    pub message_base64: String,
+    /// Signatures in signer order, base58, `None` until signed
+    pub signatures: Vec<Option<String>>,
+    /// Fee paid by the fee payer, in lamports
+    pub fee: u64,
+}
+
+impl SponsoredTransaction {
+    /// Addresses whose signatures are still missing
+    pub fn missing_signers(&self) -> Vec<&str> {
+        self.message.signers().iter()
+            .zip(&self.signatures)
+            .filter(|(_, signature)| signature.is_none())
+            .map(|(signer, _)| signer.as_str())
+            .collect()
+    }
+
+    /// Add a signer's signature over the message, checking it
+    pub fn add_signature(&mut self, signer: &str, signature: &[u8]) -> Result<()> {
+        let position = self.message.signers().iter().position(|key| key == signer)
+            .ok_or_else(|| Error::Signing(format!("{} is not a signer of the transaction", signer)))?;
+        let key = VerifyingKey::from_bytes(&decode_pubkey(signer)?)
+            .map_err(|e| Error::Signing(format!("Invalid signer {}: {}", signer, e)))?;
+        let signature = Signature::from_slice(signature)
+            .map_err(|e| Error::Signing(format!("Invalid signature: {}", e)))?;
+        key.verify(&self.message.serialize()?, &signature)
+            .map_err(|_| Error::Signing(format!("Signature of {} does not match the message", signer)))?;
+
+        self.signatures[position] = Some(bs58::encode(signature.to_bytes()).into_string());
+        Ok(())
+    }
+
+    /// Sign as `signing_key`
+    pub fn sign(&mut self, signing_key: &SigningKey) -> Result<()> {
+        let signer = bs58::encode(signing_key.verifying_key().to_bytes()).into_string();
+        let signature = signing_key.sign(&self.message.serialize()?);
+        self.add_signature(&signer, &signature.to_bytes())
+    }
+
+    /// Serialize the fully signed transaction for broadcast
+    pub fn serialize(&self) -> Result<Vec<u8>> {
+        if let Some(signer) = self.missing_signers().first() {
+            return Err(Error::Signing(format!("Missing signature of {}", signer)));
+        }
+
+        let mut bytes = Vec::new();
+        encode_length(&mut bytes, self.signatures.len());
+        for signature in self.signatures.iter().flatten() {
+            bytes.extend(bs58::decode(signature).into_vec().map_err(|e| Error::Signing(e.to_string()))?);
+        }
+        bytes.extend(self.message.serialize()?);
+        Ok(bytes)
+    }
+}
+
+/// A service account paying the fees of user transactions
+#[derive(Debug)]
+pub struct FeePayer {
+    signing_key: SigningKey,
+    address: String,
+    policy: FeePayerPolicy,
+    usage: Mutex<Usage>,
+}
+
+impl FeePayer {
+    /// Create a fee payer from its 32-byte ed25519 secret
+    pub fn new(secret: [u8; 32], policy: FeePayerPolicy) -> Self {
+        let signing_key = SigningKey::from_bytes(&secret);
+        let address = bs58::encode(signing_key.verifying_key().to_bytes()).into_string();
+        Self { signing_key, address, policy, usage: Mutex::new(Usage::default()) }
+    }
+
+    /// Create a fee payer from a base58 keypair, as exported by Solana wallets
+    ///
+    /// Accepts the 64-byte secret-and-public-key form or a bare 32-byte secret.
+    pub fn from_base58(keypair: &str, policy: FeePayerPolicy) -> Result<Self> {
+        let bytes = bs58::decode(keypair.trim()).into_vec()
+            .map_err(|e| Error::InvalidInput(format!("Invalid fee payer keypair: {}", e)))?;
+        let secret: [u8; 32] = match bytes.len() {
+            32 | 64 => bytes[..32].try_into().unwrap(),
+            len => return Err(Error::InvalidInput(format!("Invalid fee payer keypair length: {}", len))),
+        };
+
+        let fee_payer = Self::new(secret, policy);
+        if bytes.len() == 64 && bytes[32..] != fee_payer.signing_key.verifying_key().to_bytes() {
+            return Err(Error::InvalidInput("Fee payer public key does not match its secret".to_string()));
+        }
+        Ok(fee_payer)
+    }
+
+    /// Address of the service account
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Get a signing key's usage on the day of `now` (Unix seconds)
+    pub fn usage(&self, signer: &str, now: u64) -> FeePayerUsage {
+        let usage = self.usage.lock().unwrap();
+        usage.signers.get(signer).copied().unwrap_or_default().on(now / DAY)
+    }
+
+    /// Get the service account's usage across all users on the day of `now`
+    pub fn total_usage(&self, now: u64) -> FeePayerUsage {
+        self.usage.lock().unwrap().total.on(now / DAY)
+    }
+
+    /// Sponsor instructions, returning the transaction for its signers to sign
+    ///
+    /// Rejects instructions invoking programs outside the policy or touching
+    /// the service account (which would let a user spend its SOL), priority
+    /// fees above the policy's unit price, and requests beyond the daily
+    /// quota of any of the transaction's signers or of the service account.
+    /// Quotas are kept per signing key rather than per caller, since a
+    /// caller's claimed identity costs nothing to change. They are only
+    /// counted once the transaction is submitted; see [`FeePayer::submit`].
+    pub fn sponsor(&self, instructions: &[Instruction], recent_blockhash: &str, now: u64) -> Result<SponsoredTransaction> {
+        if instructions.is_empty() {
+            return Err(Error::InvalidInput("A sponsored transaction needs at least one instruction".to_string()));
+        }
+        if instructions.len() > self.policy.max_instructions {
+            return Err(Error::InvalidInput(format!("A sponsored transaction holds at most {} instructions", self.policy.max_instructions)));
+        }
+        for instruction in instructions {
+            if !self.policy.allowed_programs.contains(&instruction.program_id) {
+                return Err(Error::NotSupported(format!("Program {} is not sponsored", instruction.program_id)));
+            }
+            if instruction.accounts.iter().any(|account| account.pubkey == self.address) {
+                return Err(Error::InvalidInput("Sponsored instructions must not use the fee payer's account".to_string()));
+            }
+        }
+
+        let budget = ComputeBudget::parse(instructions)?;
+        if budget.unit_price > self.policy.max_compute_unit_price {
+            return Err(Error::InvalidInput(format!("Compute unit price above the sponsored limit of {} micro-lamports", self.policy.max_compute_unit_price)));
+        }
+
+        let message = Message::compile(&self.address, instructions, recent_blockhash)?;
+        if message.signers().len() < 2 {
+            return Err(Error::InvalidInput("A sponsored transaction needs a signer besides the fee payer".to_string()));
+        }
+        let fee = message.num_required_signatures as u64 * SOLANA_SIGNATURE_FEE + budget.priority_fee(instructions);
+
+        let message_bytes = message.serialize()?;
+        let transaction = SponsoredTransaction {
+            message_base64: BASE64.encode(&message_bytes),
+            signatures: vec![None; message.num_required_signatures as usize],
+            message,
+            fee,
+        };
+
+        let mut usage = self.usage.lock().unwrap();
+        self.check_quota(&usage, &transaction, now / DAY)?;
+        usage.pending.retain(|_, (_, sponsored)| *sponsored + PENDING_TTL > now);
+        usage.pending.insert(transaction.message_base64.clone(), (transaction.clone(), now));
+        Ok(transaction)
+    }
+
+    /// Add the fee payer's signature to a sponsored transaction its other
+    /// signers have signed, and broadcast it, returning its signature
+    ///
+    /// The quotas are counted here rather than when sponsoring, so messages
+    /// that are never signed cost nothing, and given back if the broadcast
+    /// fails.
+    pub fn submit<B: TransactionBroadcaster + ?Sized>(&self, signed: &SponsoredTransaction, now: u64, broadcaster: &B) -> Result<String> {
+        let (mut transaction, sponsored) = {
+            let mut usage = self.usage.lock().unwrap();
+            usage.pending.retain(|_, (_, sponsored)| *sponsored + PENDING_TTL > now);
+            usage.pending.get(&signed.message_base64)
+                .cloned()
+                .ok_or_else(|| Error::InvalidInput("Transaction was not sponsored or its blockhash has expired".to_string()))?
+        };
+
+        let signers = transaction.message.signers()[1..].to_vec();
+        for (position, signer) in signers.iter().enumerate() {
+            let signature = signed.signatures.get(position + 1).cloned().flatten()
+                .ok_or_else(|| Error::Signing(format!("Missing signature of {}", signer)))?;
+            let signature = bs58::decode(&signature).into_vec()
+                .map_err(|e| Error::Signing(format!("Invalid signature of {}: {}", signer, e)))?;
+            transaction.add_signature(signer, &signature)?;
+        }
+        transaction.sign(&self.signing_key)?;
+        let bytes = transaction.serialize()?;
+
+        let day = now / DAY;
+        {
+            let mut usage = self.usage.lock().unwrap();
+            self.check_quota(&usage, &transaction, day)?;
+            // Another request may have submitted the same message meanwhile
+            if usage.pending.remove(&transaction.message_base64).is_none() {
+                return Err(Error::InvalidInput("Transaction was already submitted".to_string()));
+            }
+            usage.total = usage.total.on(day).add(transaction.fee);
+            for signer in &signers {
+                let today = usage.signers.get(signer).copied().unwrap_or_default().on(day);
+                usage.signers.insert(signer.clone(), today.add(transaction.fee));
+            }
+            // Forget keys last used on earlier days
+            usage.signers.retain(|_, signer_usage| signer_usage.day == day);
+        }
+
+        broadcaster.broadcast_transaction(&bytes).inspect_err(|_| {
+            let mut usage = self.usage.lock().unwrap();
+            if usage.total.day == day {
+                usage.total = usage.total.sub(transaction.fee);
+            }
+            for signer in &signers {
+                if let Some(today) = usage.signers.get_mut(signer).filter(|today| today.day == day) {
+                    *today = today.sub(transaction.fee);
+                }
+            }
+            // Let the caller retry while the blockhash lasts
+            let mut unsigned = transaction.clone();
+            unsigned.signatures.iter_mut().for_each(|signature| *signature = None);
+            usage.pending.insert(unsigned.message_base64.clone(), (unsigned, sponsored));
+        })
+    }
+
+    /// Check that a transaction fits the quotas of the service account and of each of its signers
+    fn check_quota(&self, usage: &Usage, transaction: &SponsoredTransaction, day: u64) -> Result<()> {
+        let fee = transaction.fee;
+        let total = usage.total.on(day);
+        if total.transactions >= self.policy.max_total_transactions_per_day || total.fees + fee > self.policy.max_total_fees_per_day {
+            return Err(Error::InvalidInput("The fee payer's daily budget is spent; try again tomorrow".to_string()));
+        }
+        for signer in &transaction.message.signers()[1..] {
+            let today = usage.signers.get(signer).copied().unwrap_or_default().on(day);
+            if today.transactions >= self.policy.max_transactions_per_day {
+                return Err(Error::InvalidInput(format!("Daily limit of {} sponsored transactions reached for {}", self.policy.max_transactions_per_day, signer)));
+            }
+            if today.fees + fee > self.policy.max_fees_per_day {
+                return Err(Error::InvalidInput(format!("Daily limit of {} sponsored lamports reached for {}", self.policy.max_fees_per_day, signer)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::{system_transfer_instruction, transfer_checked_instruction, TransactionReceipt, TransactionStatus};
+
+    const MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const BLOCKHASH: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
+
+    fn user_key() -> SigningKey {
+        SigningKey::from_bytes(&[7u8; 32])
+    }
+
+    fn transfer(owner: &str) -> Instruction {
+        let source = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+        let destination = "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg";
+        transfer_checked_instruction(TokenProgram::Token, source, MINT, destination, owner, 1_000_000, 6)
+    }
+
+    /// Broadcaster recording what it was sent, or failing
+    #[derive(Default)]
+    struct TestBroadcaster {
+        sent: Mutex<Vec<Vec<u8>>>,
+        fail: bool,
+    }
+
+    impl TransactionBroadcaster for TestBroadcaster {
+        fn broadcast_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+            if self.fail {
+                return Err(Error::Network("Node unreachable".to_string()));
+            }
+            self.sent.lock().unwrap().push(signed_transaction.to_vec());
+            Ok(bs58::encode(&signed_transaction[1..65]).into_string())
+        }
+
+        fn get_transaction_status(&self, _hash: &str) -> Result<TransactionStatus> {
+            Err(Error::NotSupported("Not tracked".to_string()))
+        }
+
+        fn get_transaction_receipt(&self, _hash: &str) -> Result<TransactionReceipt> {
+            Err(Error::NotSupported("Not tracked".to_string()))
+        }
+    }
+
+    /// Sponsor a transfer of `key`'s tokens, sign it and submit it
+    fn send(fee_payer: &FeePayer, key: &SigningKey, now: u64) -> Result<String> {
+        let owner = bs58::encode(key.verifying_key().to_bytes()).into_string();
+        let mut transaction = fee_payer.sponsor(&[transfer(&owner)], BLOCKHASH, now)?;
+        transaction.sign(key)?;
+        fee_payer.submit(&transaction, now, &TestBroadcaster::default())
+    }
+
+    #[test]
+    fn test_sponsor_and_complete() {
+        let fee_payer = FeePayer::new([1u8; 32], FeePayerPolicy::default());
+        let owner = bs58::encode(user_key().verifying_key().to_bytes()).into_string();
+
+        let mut transaction = fee_payer.sponsor(&[transfer(&owner)], BLOCKHASH, 0).unwrap();
+        assert_eq!(transaction.message.account_keys[0], fee_payer.address());
+        assert_eq!(transaction.missing_signers(), vec![fee_payer.address(), owner.as_str()]);
+        assert_eq!(transaction.fee, 2 * SOLANA_SIGNATURE_FEE);
+        assert!(transaction.serialize().is_err());
+        assert_eq!(fee_payer.total_usage(0).transactions, 0);
+
+        // A signature from the wrong key is rejected
+        assert!(transaction.add_signature(&owner, &[0u8; 64]).is_err());
+        let broadcaster = TestBroadcaster::default();
+        assert!(fee_payer.submit(&transaction, 0, &broadcaster).is_err());
+
+        transaction.sign(&user_key()).unwrap();
+        assert_eq!(transaction.missing_signers(), vec![fee_payer.address()]);
+
+        // Only the message that was sponsored is signed
+        let mut forged = transaction.clone();
+        forged.message_base64 = BASE64.encode(b"forged");
+        assert!(fee_payer.submit(&forged, 0, &broadcaster).is_err());
+
+        fee_payer.submit(&transaction, 0, &broadcaster).unwrap();
+        let sent = broadcaster.sent.lock().unwrap()[0].clone();
+        assert_eq!(sent[0], 2);
+        assert_eq!(fee_payer.usage(&owner, 0).transactions, 1);
+        assert_eq!(fee_payer.total_usage(0).fees, 2 * SOLANA_SIGNATURE_FEE);
+
+        // A sponsored message is submitted once
+        assert!(fee_payer.submit(&transaction, 0, &broadcaster).is_err());
+    }
+
+    #[test]
+    fn test_quota_counts_broadcasts() {
+        let policy = FeePayerPolicy { max_transactions_per_day: 1, ..FeePayerPolicy::default() };
+        let fee_payer = FeePayer::new([1u8; 32], policy);
+        let owner = bs58::encode(user_key().verifying_key().to_bytes()).into_string();
+
+        // Messages that are never signed cost nothing
+        for _ in 0..3 {
+            assert!(fee_payer.sponsor(&[transfer(&owner)], BLOCKHASH, 0).is_ok());
+        }
+        assert_eq!(fee_payer.usage(&owner, 0).transactions, 0);
+
+        // A failed broadcast gives the quota back and can be retried
+        let mut transaction = fee_payer.sponsor(&[transfer(&owner)], BLOCKHASH, 0).unwrap();
+        transaction.sign(&user_key()).unwrap();
+        let unreachable = TestBroadcaster { fail: true, ..TestBroadcaster::default() };
+        assert!(fee_payer.submit(&transaction, 0, &unreachable).is_err());
+        assert_eq!(fee_payer.usage(&owner, 0), FeePayerUsage::default());
+        assert_eq!(fee_payer.total_usage(0).transactions, 0);
+        assert!(fee_payer.submit(&transaction, 0, &TestBroadcaster::default()).is_ok());
+        assert_eq!(fee_payer.usage(&owner, 0).transactions, 1);
+
+        // Messages expire with their blockhash
+        let mut transaction = fee_payer.sponsor(&[transfer(&owner)], BLOCKHASH, DAY).unwrap();
+        transaction.sign(&user_key()).unwrap();
+        assert!(fee_payer.submit(&transaction, DAY + PENDING_TTL, &TestBroadcaster::default()).is_err());
+    }
+
+    #[test]
+    fn test_abuse_protection() {
+        let policy = FeePayerPolicy { max_transactions_per_day: 2, ..FeePayerPolicy::default() };
+        let fee_payer = FeePayer::new([1u8; 32], policy);
+        let owner = bs58::encode(user_key().verifying_key().to_bytes()).into_string();
+
+        // Draining the service account through a System transfer
+        let drain = system_transfer_instruction(fee_payer.address(), &owner, 1_000_000_000);
+        assert!(fee_payer.sponsor(&[drain], BLOCKHASH, 0).is_err());
+
+        // Token instructions naming the fee payer as an account
+        assert!(fee_payer.sponsor(&[transfer(fee_payer.address())], BLOCKHASH, 0).is_err());
+
+        assert!(send(&fee_payer, &user_key(), 0).is_ok());
+        assert!(send(&fee_payer, &user_key(), 60).is_ok());
+        assert!(send(&fee_payer, &user_key(), 120).is_err());
+
+        // Quotas reset the next day
+        assert!(send(&fee_payer, &user_key(), DAY).is_ok());
+    }
+
+    #[test]
+    fn test_total_quota() {
+        let policy = FeePayerPolicy { max_total_transactions_per_day: 2, ..FeePayerPolicy::default() };
+        let fee_payer = FeePayer::new([1u8; 32], policy);
+
+        // Fresh keys each time still count against the service account's budget
+        for seed in 10..12u8 {
+            assert!(send(&fee_payer, &SigningKey::from_bytes(&[seed; 32]), 0).is_ok());
+        }
+        assert!(send(&fee_payer, &SigningKey::from_bytes(&[12u8; 32]), 0).is_err());
+        assert_eq!(fee_payer.total_usage(0).transactions, 2);
+        assert!(send(&fee_payer, &SigningKey::from_bytes(&[12u8; 32]), DAY).is_ok());
+    }
+
+    #[test]
+    fn test_priority_fee() {
+        let fee_payer = FeePayer::new([1u8; 32], FeePayerPolicy::default());
+        let owner = bs58::encode(user_key().verifying_key().to_bytes()).into_string();
+        let compute_budget = |data: Vec<u8>| Instruction { program_id: COMPUTE_BUDGET_PROGRAM_ID.to_string(), accounts: vec![], data };
+        let unit_limit = compute_budget([vec![2], 300_000u32.to_le_bytes().to_vec()].concat());
+        let unit_price = |price: u64| compute_budget([vec![3], price.to_le_bytes().to_vec()].concat());
+
+        // 300,000 units at 5,000 micro-lamports each
+        let transaction = fee_payer.sponsor(&[unit_limit.clone(), unit_price(5_000), transfer(&owner)], BLOCKHASH, 0).unwrap();
+        assert_eq!(transaction.fee, 2 * SOLANA_SIGNATURE_FEE + 1_500);
+
+        // Without a limit each instruction may use 200,000 units
+        let instructions = [unit_price(5_000), transfer(&owner)];
+        assert_eq!(ComputeBudget::parse(&instructions).unwrap().priority_fee(&instructions), 1_000);
+
+        assert!(fee_payer.sponsor(&[unit_limit, unit_price(1_000_000), transfer(&owner)], BLOCKHASH, 0).is_err());
+        assert!(fee_payer.sponsor(&[compute_budget(vec![9]), transfer(&owner)], BLOCKHASH, 0).is_err());
+    }
+
+    #[test]
+    fn test_from_base58() {
+        let fee_payer = FeePayer::new([1u8; 32], FeePayerPolicy::default());
+        let mut keypair = vec![1u8; 32];
+        keypair.extend(bs58::decode(fee_payer.address()).into_vec().unwrap());
+
+        let loaded = FeePayer::from_base58(&bs58::encode(&keypair).into_string(), FeePayerPolicy::default()).unwrap();
+        assert_eq!(loaded.address(), fee_payer.address());
+
+        keypair[63] ^= 1;
+        assert!(FeePayer::from_base58(&bs58::encode(&keypair).into_string(), FeePayerPolicy::default()).is_err());
+    }
+}
@@ -0,0 +1,481 @@
+//! Batched transactions
+//!
+//! [`build_batch`] combines several user intents, such as an approval
+//! followed by a swap or a handful of transfers, into a single transaction.
+//! On EVM chains the calls are composed through the user's smart account or
+//! Multicall3; on Solana each intent becomes one or more instructions of the
+//! same transaction. The returned [`Batch`] maps every intent back to its
+//! part of the transaction so results can be reported per item.
+
+use std::ops::Range;
+use std::str::FromStr;
+
+use ethers::abi::{self, Token};
+use ethers::prelude::{Address, U256};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::crypto::keys::KeyType;
+use crate::error::{Error, Result};
+use super::batch::{self, MULTICALL3_ADDRESS};
+use super::solana::{self, Instruction};
+use super::spl::{self, TokenProgram};
+use super::types::{NetworkBinding, TransactionRequest};
+
+/// Most intents in one batch
+pub const MAX_BATCH_SIZE: usize = 32;
+
+/// One action of a batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Intent {
+    /// Send the native token
+    Transfer {
+        /// Recipient
+        to: String,
+        /// Amount in the smallest unit
+        amount: String,
+    },
+    /// Send an ERC-20 token (EVM)
+    TokenTransfer {
+        /// Token contract
+        token: String,
+        /// Recipient
+        to: String,
+        /// Amount in the smallest unit
+        amount: String,
+    },
+    /// Approve an ERC-20 spender (EVM)
+    Approve {
+        /// Token contract
+        token: String,
+        /// Spender
+        spender: String,
+        /// Allowance in the smallest unit
+        amount: String,
+    },
+    /// Call a contract (EVM)
+    Call {
+        /// Contract address
+        to: String,
+        /// Native value sent with the call, in wei
+        value: String,
+        /// Hex-encoded call data
+        data: String,
+    },
+    /// Send an SPL token between token accounts (Solana)
+    SplTransfer {
+        /// Token program of the mint
+        program: TokenProgram,
+        /// Mint
+        mint: String,
+        /// Sender's token account
+        source: String,
+        /// Recipient's token account
+        destination: String,
+        /// Amount in the smallest unit
+        amount: String,
+        /// Decimals of the mint
+        decimals: u8,
+    },
+    /// Run an instruction (Solana)
+    Instruction(Instruction),
+}
+
+impl Intent {
+    /// Whether the intent acts on behalf of the caller, so it breaks when a
+    /// forwarding contract becomes `msg.sender`
+    fn depends_on_sender(&self) -> bool {
+        matches!(self, Self::TokenTransfer { .. } | Self::Approve { .. })
+    }
+}
+
+/// How EVM intents are composed into one transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EvmBatchMode {
+    /// Call `executeBatch((address,uint256,bytes)[])` on the sender's smart account
+    ///
+    /// Calls run as the account, so approvals and token transfers work. The
+    /// batch is atomic.
+    SmartAccount {
+        /// Smart account address
+        account: String,
+    },
+    /// Call Multicall3 `aggregate3Value`, letting each call fail on its own
+    ///
+    /// Calls run as Multicall3, so only sender-independent intents (native
+    /// transfers and plain contract calls) are allowed.
+    Multicall,
+}
+
+/// A request to combine intents into one transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRequest {
+    /// Blockchain type
+    pub key_type: KeyType,
+    /// Network the transaction is bound to
+    pub network: NetworkBinding,
+    /// Sender (and fee payer)
+    pub from: String,
+    /// Intents, in execution order
+    pub intents: Vec<Intent>,
+    /// Composition on EVM chains
+    #[serde(default)]
+    pub evm_mode: Option<EvmBatchMode>,
+}
+
+/// The part of a batch transaction carrying one intent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchItem {
+    /// Position of the intent in the request
+    pub index: usize,
+    /// The intent
+    pub intent: Intent,
+    /// Calls (EVM) or instructions (Solana) carrying the intent
+    pub parts: Range<usize>,
+}
+
+/// Transaction of a batch, ready to sign
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchPayload {
+    /// An EVM transaction
+    Evm {
+        /// The transaction
+        request: TransactionRequest,
+        /// Whether a failing call reverts the whole batch
+        atomic: bool,
+    },
+    /// A Solana transaction
+    Solana {
+        /// Fee payer
+        fee_payer: String,
+        /// Instructions, in order
+        instructions: Vec<Instruction>,
+    },
+}
+
+/// A built batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Batch {
+    /// The transaction
+    pub payload: BatchPayload,
+    /// The intents and where they ended up
+    pub items: Vec<BatchItem>,
+}
+
+/// Outcome of one batch item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ItemStatus {
+    /// The item took effect
+    Succeeded,
+    /// The item failed
+    Failed {
+        /// Failure reason
+        reason: String,
+    },
+    /// The item was reverted because another item failed
+    RolledBack,
+}
+
+impl Batch {
+    /// Report per-item results from Multicall3 `aggregate3Value` return data
+    pub fn multicall_results(&self, return_data: &[u8]) -> Result<Vec<ItemStatus>> {
+        let results = batch::decode_aggregate3(return_data)?;
+        if results.len() != self.items.len() {
+            return Err(Error::Provider(format!("Expected {} call results, got {}", self.items.len(), results.len())));
+        }
+
+        Ok(results.into_iter()
+            .map(|result| if result.success {
+                ItemStatus::Succeeded
+            } else {
+                ItemStatus::Failed { reason: format!("reverted: 0x{}", hex::encode(result.return_data)) }
+            })
+            .collect())
+    }
+
+    /// Report per-item results from the `err` of a Solana transaction status
+    ///
+    /// Solana transactions are atomic: on `InstructionError` the item owning
+    /// the failed instruction failed and every other item was rolled back.
+    pub fn solana_results(&self, err: &Value) -> Vec<ItemStatus> {
+        if err.is_null() {
+            return vec![ItemStatus::Succeeded; self.items.len()];
+        }
+
+        let failed = err["InstructionError"][0].as_u64().map(|index| index as usize);
+        let reason = match &err["InstructionError"][1] {
+            Value::Null => err.to_string(),
+            detail => detail.to_string(),
+        };
+
+        self.items.iter()
+            .map(|item| match failed {
+                Some(index) if item.parts.contains(&index) => ItemStatus::Failed { reason: reason.clone() },
+                Some(_) => ItemStatus::RolledBack,
+                // The transaction failed outside any instruction
+                None => ItemStatus::Failed { reason: reason.clone() },
+            })
+            .collect()
+    }
+}
+
+/// Combine intents into a single transaction
+pub fn build_batch(request: &BatchRequest) -> Result<Batch> {
+    if request.intents.is_empty() {
+        return Err(Error::InvalidInput("A batch needs at least one intent".to_string()));
+    }
+    if request.intents.len() > MAX_BATCH_SIZE {
+        return Err(Error::InvalidInput(format!("A batch holds at most {} intents", MAX_BATCH_SIZE)));
+    }
+    if request.network.key_type() != request.key_type {
+        return Err(Error::InvalidInput(format!("Network does not belong to {:?}", request.key_type)));
+    }
+
+    match request.key_type {
+        KeyType::Ethereum => build_evm_batch(request),
+        KeyType::Solana => build_solana_batch(request),
+        KeyType::Bitcoin => Err(Error::NotSupported("Bitcoin transactions cannot batch intents".to_string())),
+        KeyType::Ton => Err(Error::NotSupported("TON wallets send messages, not intents; use TonProvider::send".to_string())),
+        KeyType::Cosmos => Err(Error::NotSupported("Cosmos transactions carry messages, not intents; use CosmosProvider::send".to_string())),
+        KeyType::Xrp => Err(Error::NotSupported("XRP payments cannot batch intents".to_string())),
+    }
+}
+
+/// A call of an EVM batch
+struct EvmCall {
+    to: Address,
+    value: U256,
+    data: Vec<u8>,
+}
+
+fn build_evm_batch(request: &BatchRequest) -> Result<Batch> {
+    let calls = request.intents.iter()
+        .enumerate()
+        .map(|(index, intent)| evm_call(index, intent))
+        .collect::<Result<Vec<_>>>()?;
+    let total_value = calls.iter().try_fold(U256::zero(), |total, call| total.checked_add(call.value))
+        .ok_or_else(|| Error::InvalidInput("Total value overflows".to_string()))?;
+
+    let mode = request.evm_mode.clone()
+        .ok_or_else(|| Error::InvalidInput("EVM batches need an evm_mode".to_string()))?;
+    let (to, data, atomic) = match mode {
+        EvmBatchMode::SmartAccount { account } => {
+            let calls = calls.into_iter()
+                .map(|call| Token::Tuple(vec![Token::Address(call.to), Token::Uint(call.value), Token::Bytes(call.data)]))
+                .collect();
+            let mut data = ethers::utils::id("executeBatch((address,uint256,bytes)[])").to_vec();
+            data.extend(abi::encode(&[Token::Array(calls)]));
+            (account, data, true)
+        }
+        EvmBatchMode::Multicall => {
+            if let Some(index) = request.intents.iter().position(Intent::depends_on_sender) {
+                return Err(Error::InvalidInput(format!(
+                    "Intent {} acts on behalf of the sender and needs a smart account, not Multicall3", index
+                )));
+            }
+            let calls = calls.into_iter()
+                .map(|call| Token::Tuple(vec![Token::Address(call.to), Token::Bool(true), Token::Uint(call.value), Token::Bytes(call.data)]))
+                .collect();
+            let mut data = ethers::utils::id("aggregate3Value((address,bool,uint256,bytes)[])").to_vec();
+            data.extend(abi::encode(&[Token::Array(calls)]));
+            (MULTICALL3_ADDRESS.to_string(), data, false)
+        }
+    };
+
+    Ok(Batch {
+        payload: BatchPayload::Evm {
+            request: TransactionRequest {
+                key_type: request.key_type,
+                network: request.network.clone(),
+                from: request.from.clone(),
+                to,
+                value: total_value.to_string(),
+                gas_price: None,
+                gas_limit: None,
+                nonce: None,
+                data: Some(data),
+                destination_tag: None,
+                memo: None,
+            },
+            atomic,
+        },
+        items: request.intents.iter()
+            .enumerate()
+            .map(|(index, intent)| BatchItem { index, intent: intent.clone(), parts: index..index + 1 })
+            .collect(),
+    })
+}
+
+fn evm_call(index: usize, intent: &Intent) -> Result<EvmCall> {
+    let address = |field: &str, value: &str| {
+        Address::from_str(value).map_err(|e| Error::InvalidInput(format!("Intent {}: invalid {} {}: {}", index, field, value, e)))
+    };
+    let amount = |field: &str, value: &str| {
+        U256::from_dec_str(value).map_err(|e| Error::InvalidInput(format!("Intent {}: invalid {} {}: {}", index, field, value, e)))
+    };
+
+    Ok(match intent {
+        Intent::Transfer { to, amount: value } => EvmCall { to: address("to", to)?, value: amount("amount", value)?, data: vec![] },
+        Intent::TokenTransfer { token, to, amount: value } => {
+            let mut data = ethers::utils::id("transfer(address,uint256)").to_vec();
+            data.extend(abi::encode(&[Token::Address(address("to", to)?), Token::Uint(amount("amount", value)?)]));
+            EvmCall { to: address("token", token)?, value: U256::zero(), data }
+        }
+        Intent::Approve { token, spender, amount: value } => {
+            let mut data = ethers::utils::id("approve(address,uint256)").to_vec();
+            data.extend(abi::encode(&[Token::Address(address("spender", spender)?), Token::Uint(amount("amount", value)?)]));
+            EvmCall { to: address("token", token)?, value: U256::zero(), data }
+        }
+        Intent::Call { to, value, data } => EvmCall {
+            to: address("to", to)?,
+            value: amount("value", value)?,
+            data: hex::decode(data.trim_start_matches("0x"))
+                .map_err(|e| Error::InvalidInput(format!("Intent {}: invalid data: {}", index, e)))?,
+        },
+        Intent::SplTransfer { .. } | Intent::Instruction(_) => {
+            return Err(Error::InvalidInput(format!("Intent {} is Solana-only", index)));
+        }
+    })
+}
+
+fn build_solana_batch(request: &BatchRequest) -> Result<Batch> {
+    let lamports = |index: usize, value: &str| {
+        value.parse::<u64>().map_err(|e| Error::InvalidInput(format!("Intent {}: invalid amount {}: {}", index, value, e)))
+    };
+
+    let mut instructions = Vec::new();
+    let mut items = Vec::with_capacity(request.intents.len());
+    for (index, intent) in request.intents.iter().enumerate() {
+        let start = instructions.len();
+        match intent {
+            Intent::Transfer { to, amount } => {
+                instructions.push(solana::system_transfer_instruction(&request.from, to, lamports(index, amount)?));
+            }
+            Intent::SplTransfer { program, mint, source, destination, amount, decimals } => {
+                instructions.push(spl::transfer_checked_instruction(
+                    *program, source, mint, destination, &request.from, lamports(index, amount)?, *decimals,
+                ));
+            }
+            Intent::Instruction(instruction) => instructions.push(instruction.clone()),
+            Intent::TokenTransfer { .. } | Intent::Approve { .. } | Intent::Call { .. } => {
+                return Err(Error::InvalidInput(format!("Intent {} is EVM-only", index)));
+            }
+        }
+        items.push(BatchItem { index, intent: intent.clone(), parts: start..instructions.len() });
+    }
+
+    Ok(Batch {
+        payload: BatchPayload::Solana { fee_payer: request.from.clone(), instructions },
+        items,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SENDER: &str = "0x9858EfFD232B4033E47d90003D41EC34EcaEda94";
+    const TOKEN: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+    const ROUTER: &str = "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D";
+
+    fn evm_request(intents: Vec<Intent>, evm_mode: EvmBatchMode) -> BatchRequest {
+        BatchRequest {
+            key_type: KeyType::Ethereum,
+            network: NetworkBinding::Evm { chain_id: 1 },
+            from: SENDER.to_string(),
+            intents,
+            evm_mode: Some(evm_mode),
+        }
+    }
+
+    #[test]
+    fn test_approve_and_swap_through_smart_account() {
+        let intents = vec![
+            Intent::Approve { token: TOKEN.to_string(), spender: ROUTER.to_string(), amount: "1000000".to_string() },
+            Intent::Call { to: ROUTER.to_string(), value: "0".to_string(), data: "0x38ed1739".to_string() },
+        ];
+        let account = "0x742d35Cc6634C0532925a3b844Bc454e4438f44e".to_string();
+        let batch = build_batch(&evm_request(intents.clone(), EvmBatchMode::SmartAccount { account: account.clone() })).unwrap();
+
+        match &batch.payload {
+            BatchPayload::Evm { request, atomic } => {
+                assert!(*atomic);
+                assert_eq!(request.to, account);
+                assert_eq!(hex::encode(&request.data.as_ref().unwrap()[..4]), hex::encode(&ethers::utils::id("executeBatch((address,uint256,bytes)[])")));
+            }
+            other => panic!("unexpected payload {:?}", other),
+        }
+        assert_eq!(batch.items.len(), 2);
+
+        // Approvals through Multicall3 would approve Multicall3 itself
+        assert!(build_batch(&evm_request(intents, EvmBatchMode::Multicall)).is_err());
+    }
+
+    #[test]
+    fn test_multicall_transfers_report_per_item() {
+        let intents = vec![
+            Intent::Transfer { to: ROUTER.to_string(), amount: "100".to_string() },
+            Intent::Transfer { to: TOKEN.to_string(), amount: "250".to_string() },
+        ];
+        let batch = build_batch(&evm_request(intents, EvmBatchMode::Multicall)).unwrap();
+
+        match &batch.payload {
+            BatchPayload::Evm { request, atomic } => {
+                assert!(!*atomic);
+                assert_eq!(request.to, MULTICALL3_ADDRESS);
+                assert_eq!(request.value, "350");
+            }
+            other => panic!("unexpected payload {:?}", other),
+        }
+
+        let return_data = abi::encode(&[Token::Array(vec![
+            Token::Tuple(vec![Token::Bool(true), Token::Bytes(vec![])]),
+            Token::Tuple(vec![Token::Bool(false), Token::Bytes(vec![0xde, 0xad])]),
+        ])]);
+        let results = batch.multicall_results(&return_data).unwrap();
+        assert_eq!(results[0], ItemStatus::Succeeded);
+        assert_eq!(results[1], ItemStatus::Failed { reason: "reverted: 0xdead".to_string() });
+    }
+
+    #[test]
+    fn test_solana_batch() {
+        let owner = "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg";
+        let request = BatchRequest {
+            key_type: KeyType::Solana,
+            network: NetworkBinding::Solana { genesis_hash: solana::SOLANA_MAINNET_GENESIS_HASH.to_string() },
+            from: owner.to_string(),
+            intents: vec![
+                Intent::Transfer { to: "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM".to_string(), amount: "5000".to_string() },
+                Intent::SplTransfer {
+                    program: TokenProgram::Token,
+                    mint: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(),
+                    source: "C2gJg6tKpQs41PRS1nC8aw3ZKNZK3HQQZGVrDFDup5nx".to_string(),
+                    destination: "3Fo7nK8v2gF4XZ3f7dXWbDmbvY3b3HUW3KMSc1q1wB9r".to_string(),
+                    amount: "2500000".to_string(),
+                    decimals: 6,
+                },
+            ],
+            evm_mode: None,
+        };
+
+        let batch = build_batch(&request).unwrap();
+        match &batch.payload {
+            BatchPayload::Solana { instructions, .. } => {
+                assert_eq!(instructions[0].program_id, solana::SYSTEM_PROGRAM_ID);
+                assert_eq!(instructions[0].data, [2, 0, 0, 0, 136, 19, 0, 0, 0, 0, 0, 0]);
+                assert_eq!(instructions[1].data[0], 12);
+            }
+            other => panic!("unexpected payload {:?}", other),
+        }
+
+        assert_eq!(batch.solana_results(&Value::Null), vec![ItemStatus::Succeeded; 2]);
+        let results = batch.solana_results(&json!({ "InstructionError": [1, { "Custom": 1 }] }));
+        assert_eq!(results[0], ItemStatus::RolledBack);
+        assert_eq!(results[1], ItemStatus::Failed { reason: r#"{"Custom":1}"#.to_string() });
+    }
+}
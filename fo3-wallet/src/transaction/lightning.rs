@@ -0,0 +1,654 @@
+//! Lightning Network payments
+//!
+//! Decodes BOLT-11 invoices and pays or issues them through a node's REST
+//! API. [`LightningBackend`] abstracts over the node; LND is supported,
+//! selected by [`ProviderType::Lnd`]. Payments are reported as
+//! [`TransactionType::Lightning`] transactions keyed by payment hash.
+
+use secp256k1::{Message, PublicKey, Secp256k1};
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::address::{bech32_polymod, hrp_expand, BECH32_CHARSET, BECH32_CONST};
+use crate::crypto::keys::KeyType;
+use crate::error::{Error, Result};
+#[cfg(feature = "rpc")]
+use super::provider::{ProviderConfig, ProviderType};
+use super::types::{Transaction, TransactionStatus, TransactionType};
+
+/// Invoice currency prefixes and the networks they name, longest first
+const CURRENCIES: [(&str, &str); 4] = [("bcrt", "regtest"), ("tbs", "signet"), ("bc", "bitcoin"), ("tb", "testnet")];
+
+/// Length of the timestamp, in 5-bit groups
+const TIMESTAMP_LENGTH: usize = 7;
+
+/// Length of the recoverable signature, in 5-bit groups
+const SIGNATURE_LENGTH: usize = 104;
+
+/// Expiry of invoices without an `x` field, in seconds
+const DEFAULT_EXPIRY: u64 = 3600;
+
+/// Final CLTV delta of invoices without a `c` field, in blocks
+const DEFAULT_MIN_FINAL_CLTV_EXPIRY: u64 = 18;
+
+/// A decoded BOLT-11 invoice
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invoice {
+    /// The encoded invoice, lowercased
+    pub payment_request: String,
+    /// Network: bitcoin, testnet, signet or regtest
+    pub network: String,
+    /// Amount in millisatoshis, `None` if the payer chooses
+    pub amount_msat: Option<u64>,
+    /// Creation time (Unix seconds)
+    pub timestamp: u64,
+    /// Seconds after `timestamp` that the invoice expires
+    pub expiry: u64,
+    /// Payment hash (hex)
+    pub payment_hash: String,
+    /// Payment secret (hex)
+    pub payment_secret: Option<String>,
+    /// Description
+    pub description: Option<String>,
+    /// SHA-256 of a description too long for the invoice (hex)
+    pub description_hash: Option<String>,
+    /// Node ID of the payee (hex), recovered from the signature if not given
+    pub payee: String,
+    /// CLTV delta for the final hop, in blocks
+    pub min_final_cltv_expiry: u64,
+}
+
+impl Invoice {
+    /// Expiry time (Unix seconds)
+    pub fn expires_at(&self) -> u64 {
+        self.timestamp.saturating_add(self.expiry)
+    }
+
+    /// Whether the invoice has expired at `now`
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at()
+    }
+}
+
+/// Direction of a Lightning payment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentDirection {
+    /// Paid by this node
+    Outgoing,
+    /// Received by this node, through one of its invoices
+    Incoming,
+}
+
+/// A Lightning payment and its status
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LightningPayment {
+    /// Payment hash (hex)
+    pub payment_hash: String,
+    /// Direction
+    pub direction: PaymentDirection,
+    /// Amount in millisatoshis (for incoming payments, the amount received so far)
+    pub amount_msat: u64,
+    /// Routing fee in millisatoshis, for outgoing payments
+    pub fee_msat: Option<u64>,
+    /// Pending while in flight (or while the invoice is open), confirmed once settled
+    pub status: TransactionStatus,
+    /// Preimage of the payment hash (hex), once settled
+    pub preimage: Option<String>,
+    /// Why the payment failed
+    pub failure: Option<String>,
+    /// Settlement or creation time (Unix seconds)
+    pub timestamp: Option<u64>,
+}
+
+impl LightningPayment {
+    /// Report the payment as a transaction of `node`, with the value in millisatoshis
+    pub fn to_transaction(&self, node: &str) -> Transaction {
+        let (from, to) = match self.direction {
+            PaymentDirection::Outgoing => (node.to_string(), String::new()),
+            PaymentDirection::Incoming => (String::new(), node.to_string()),
+        };
+        Transaction {
+            hash: self.payment_hash.clone(),
+            transaction_type: TransactionType::Lightning,
+            key_type: KeyType::Bitcoin,
+            from,
+            to,
+            value: self.amount_msat.to_string(),
+            gas_price: None,
+            gas_limit: None,
+            nonce: None,
+            data: None,
+            status: self.status,
+            block_number: None,
+            timestamp: self.timestamp,
+            fee: self.fee_msat.map(|fee| fee.to_string()),
+            memo: None,
+        }
+    }
+}
+
+/// A Lightning node that pays and issues invoices
+pub trait LightningBackend: Send + Sync {
+    /// Backend name, for logs
+    fn name(&self) -> &str;
+
+    /// Node ID (hex)
+    fn node_id(&self) -> Result<String>;
+
+    /// Create an invoice for `amount_msat` (or any amount), expiring after `expiry` seconds
+    fn create_invoice(&self, amount_msat: Option<u64>, description: &str, expiry: u64) -> Result<Invoice>;
+
+    /// Pay an invoice, paying at most `max_fee_msat` in routing fees
+    ///
+    /// `amount_msat` is required for invoices without an amount and ignored
+    /// otherwise. Returns once the payment settles or fails.
+    fn pay_invoice(&self, invoice: &Invoice, amount_msat: Option<u64>, max_fee_msat: u64) -> Result<LightningPayment>;
+
+    /// Get a payment by payment hash (hex)
+    fn get_payment(&self, payment_hash: &str, direction: PaymentDirection) -> Result<LightningPayment>;
+}
+
+/// Create the backend selected by the provider type
+#[cfg(feature = "rpc")]
+pub fn lightning_backend(config: &ProviderConfig) -> Result<Box<dyn LightningBackend>> {
+    match config.provider_type {
+        ProviderType::Lnd => Ok(Box::new(LndBackend::new(config)?)),
+        other => Err(Error::NotSupported(format!("{:?} providers cannot make Lightning payments", other))),
+    }
+}
+
+/// Decode and validate an invoice, then pay it
+///
+/// Rejects expired invoices, invoices for another network, and amounts
+/// that are missing or conflict with the invoice's.
+pub fn pay(
+    backend: &dyn LightningBackend,
+    payment_request: &str,
+    network: &str,
+    amount_msat: Option<u64>,
+    max_fee_msat: u64,
+    now: u64,
+) -> Result<LightningPayment> {
+    let invoice = decode_invoice(payment_request)?;
+    if invoice.network != network {
+        return Err(Error::InvalidInput(format!("Invoice is for {}, not {}", invoice.network, network)));
+    }
+    if invoice.is_expired(now) {
+        return Err(Error::InvalidInput(format!("Invoice expired at {}", invoice.expires_at())));
+    }
+    match (invoice.amount_msat, amount_msat) {
+        (None, None) => return Err(Error::InvalidInput("Invoice has no amount; an amount is required".to_string())),
+        (None, Some(0)) => return Err(Error::InvalidInput("Amount must be positive".to_string())),
+        (Some(expected), Some(amount)) if expected != amount => {
+            return Err(Error::InvalidInput(format!("Invoice is for {} msat, not {}", expected, amount)));
+        }
+        _ => {}
+    }
+    backend.pay_invoice(&invoice, amount_msat, max_fee_msat)
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidInput(format!("Invalid Lightning invoice: {}", reason))
+}
+
+/// Decode a BOLT-11 invoice, checking its checksum and signature
+pub fn decode_invoice(payment_request: &str) -> Result<Invoice> {
+    let payment_request = payment_request.trim();
+    let payment_request = payment_request.strip_prefix("lightning:")
+        .or_else(|| payment_request.strip_prefix("LIGHTNING:"))
+        .unwrap_or(payment_request);
+    if payment_request.chars().any(|c| c.is_ascii_lowercase()) && payment_request.chars().any(|c| c.is_ascii_uppercase()) {
+        return Err(invalid("mixed case"));
+    }
+    let normalized = payment_request.to_ascii_lowercase();
+
+    let separator = normalized.rfind('1').ok_or_else(|| invalid("missing separator"))?;
+    let (hrp, data) = (&normalized[..separator], &normalized[separator + 1..]);
+    let prefix = hrp.strip_prefix("ln").ok_or_else(|| invalid("missing ln prefix"))?;
+    let (currency, network) = CURRENCIES.iter()
+        .find(|(currency, _)| prefix.starts_with(currency))
+        .ok_or_else(|| invalid("unknown currency"))?;
+    let amount_msat = parse_amount(&prefix[currency.len()..])?;
+
+    let values: Vec<u8> = data.chars()
+        .map(|c| BECH32_CHARSET.iter().position(|b| *b as char == c).map(|value| value as u8))
+        .collect::<Option<_>>()
+        .ok_or_else(|| invalid("invalid character"))?;
+    if values.len() < TIMESTAMP_LENGTH + SIGNATURE_LENGTH + 6 {
+        return Err(invalid("too short"));
+    }
+    if bech32_polymod(&[hrp_expand(hrp), values.clone()].concat()) != BECH32_CONST {
+        return Err(invalid("invalid checksum"));
+    }
+    let values = &values[..values.len() - 6];
+    let (data, signature) = values.split_at(values.len() - SIGNATURE_LENGTH);
+
+    let mut payment_hash = None;
+    let mut payment_secret = None;
+    let mut description = None;
+    let mut description_hash = None;
+    let mut expiry = DEFAULT_EXPIRY;
+    let mut min_final_cltv_expiry = DEFAULT_MIN_FINAL_CLTV_EXPIRY;
+    let mut payee = None;
+
+    let mut fields = &data[TIMESTAMP_LENGTH..];
+    while !fields.is_empty() {
+        if fields.len() < 3 {
+            return Err(invalid("truncated field"));
+        }
+        let (tag, length) = (BECH32_CHARSET[fields[0] as usize], (fields[1] as usize) << 5 | fields[2] as usize);
+        let value = fields.get(3..3 + length).ok_or_else(|| invalid("truncated field"))?;
+        fields = &fields[3 + length..];
+
+        // Unknown fields, and known fields of the wrong length, are skipped as BOLT-11 requires
+        match (tag, length) {
+            (b'p', 52) => payment_hash = Some(hex::encode(to_bytes(value, false))),
+            (b's', 52) => payment_secret = Some(hex::encode(to_bytes(value, false))),
+            (b'h', 52) => description_hash = Some(hex::encode(to_bytes(value, false))),
+            (b'n', 53) => payee = Some(to_bytes(value, false)),
+            (b'd', _) => description = Some(String::from_utf8(to_bytes(value, false)).map_err(|_| invalid("description is not UTF-8"))?),
+            (b'x', _) => expiry = read_int(value),
+            (b'c', _) => min_final_cltv_expiry = read_int(value),
+            _ => {}
+        }
+    }
+
+    let payment_hash = payment_hash.ok_or_else(|| invalid("missing payment hash"))?;
+    if description.is_none() && description_hash.is_none() {
+        return Err(invalid("missing description"));
+    }
+
+    let signature = to_bytes(signature, false);
+    let recovery_id = RecoveryId::from_i32(signature[64] as i32).map_err(|_| invalid("invalid recovery ID"))?;
+    let signature = RecoverableSignature::from_compact(&signature[..64], recovery_id)
+        .map_err(|_| invalid("invalid signature"))?;
+    let digest: [u8; 32] = Sha256::digest([hrp.as_bytes(), &to_bytes(data, true)].concat()).into();
+    let recovered = Secp256k1::verification_only()
+        .recover_ecdsa(&Message::from_digest(digest), &signature)
+        .map_err(|_| invalid("invalid signature"))?;
+    if let Some(payee) = &payee {
+        let payee = PublicKey::from_slice(payee).map_err(|_| invalid("invalid payee"))?;
+        if payee != recovered {
+            return Err(invalid("signature does not match payee"));
+        }
+    }
+
+    Ok(Invoice {
+        payment_request: normalized.clone(),
+        network: network.to_string(),
+        amount_msat,
+        timestamp: read_int(&data[..TIMESTAMP_LENGTH]),
+        expiry,
+        payment_hash,
+        payment_secret,
+        description,
+        description_hash,
+        payee: hex::encode(recovered.serialize()),
+        min_final_cltv_expiry,
+    })
+}
+
+/// Parse the amount of the human-readable part into millisatoshis
+fn parse_amount(amount: &str) -> Result<Option<u64>> {
+    if amount.is_empty() {
+        return Ok(None);
+    }
+    let (digits, multiplier) = match amount.as_bytes()[amount.len() - 1] {
+        multiplier @ (b'm' | b'u' | b'n' | b'p') => (&amount[..amount.len() - 1], Some(multiplier)),
+        _ => (amount, None),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid("invalid amount"));
+    }
+    let value: u64 = digits.parse().map_err(|_| invalid("invalid amount"))?;
+    let amount_msat = match multiplier {
+        None => value.checked_mul(100_000_000_000),
+        Some(b'm') => value.checked_mul(100_000_000),
+        Some(b'u') => value.checked_mul(100_000),
+        Some(b'n') => value.checked_mul(100),
+        _ if value % 10 == 0 => Some(value / 10),
+        _ => return Err(invalid("amount is not a whole number of millisatoshis")),
+    };
+    amount_msat.map(Some).ok_or_else(|| invalid("amount too large"))
+}
+
+/// Big-endian integer of 5-bit groups
+fn read_int(values: &[u8]) -> u64 {
+    values.iter().fold(0u64, |int, value| int << 5 | *value as u64)
+}
+
+/// Regroup 5-bit values into bytes, zero-padding the last byte if `pad` and dropping it otherwise
+fn to_bytes(values: &[u8], pad: bool) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(values.len() * 5 / 8 + 1);
+    let (mut accumulator, mut bits) = (0u32, 0u32);
+    for value in values {
+        accumulator = ((accumulator << 5) | *value as u32) & 0xfff;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((accumulator >> bits) as u8);
+        }
+    }
+    if pad && bits > 0 {
+        bytes.push((accumulator << (8 - bits)) as u8);
+    }
+    bytes
+}
+
+/// Read a uint64, which LND's REST API encodes as a string
+fn uint(value: &Value) -> Option<u64> {
+    value.as_u64().or_else(|| value.as_str()?.parse().ok())
+}
+
+/// Decode a base64 byte string of LND's REST API to hex
+fn base64_to_hex(value: &Value) -> Option<String> {
+    use base64::Engine;
+    let bytes = base64::engine::general_purpose::STANDARD.decode(value.as_str()?).ok()?;
+    (!bytes.is_empty() && bytes.iter().any(|b| *b != 0)).then(|| hex::encode(bytes))
+}
+
+/// Parse LND's `GET /v1/invoice/:r_hash` response as an incoming payment
+pub fn parse_lnd_invoice(response: &Value) -> Result<LightningPayment> {
+    let payment_hash = base64_to_hex(&response["r_hash"])
+        .ok_or_else(|| Error::Provider(format!("Invalid LND invoice: {}", response)))?;
+    let status = match response["state"].as_str() {
+        Some("SETTLED") => TransactionStatus::Confirmed,
+        Some("CANCELED") => TransactionStatus::Failed,
+        Some("OPEN") | Some("ACCEPTED") => TransactionStatus::Pending,
+        _ => return Err(Error::Provider(format!("Invalid LND invoice state: {}", response["state"]))),
+    };
+    let settle_date = uint(&response["settle_date"]).filter(|date| *date > 0);
+    Ok(LightningPayment {
+        payment_hash,
+        direction: PaymentDirection::Incoming,
+        amount_msat: uint(&response["amt_paid_msat"]).unwrap_or(0),
+        fee_msat: None,
+        status,
+        preimage: (status == TransactionStatus::Confirmed).then(|| base64_to_hex(&response["r_preimage"])).flatten(),
+        failure: None,
+        timestamp: settle_date.or_else(|| uint(&response["creation_date"])),
+    })
+}
+
+/// Parse a payment of LND's `GET /v1/payments` response
+pub fn parse_lnd_payment(payment: &Value) -> Result<LightningPayment> {
+    let invalid = || Error::Provider(format!("Invalid LND payment: {}", payment));
+    let status = match payment["status"].as_str() {
+        Some("SUCCEEDED") => TransactionStatus::Confirmed,
+        Some("FAILED") => TransactionStatus::Failed,
+        Some("IN_FLIGHT") | Some("INITIATED") | Some("UNKNOWN") => TransactionStatus::Pending,
+        _ => return Err(invalid()),
+    };
+    let preimage = payment["payment_preimage"].as_str()
+        .filter(|preimage| preimage.bytes().any(|b| b != b'0'))
+        .map(str::to_string);
+    Ok(LightningPayment {
+        payment_hash: payment["payment_hash"].as_str().ok_or_else(invalid)?.to_string(),
+        direction: PaymentDirection::Outgoing,
+        amount_msat: uint(&payment["value_msat"]).ok_or_else(invalid)?,
+        fee_msat: uint(&payment["fee_msat"]),
+        status,
+        preimage,
+        failure: payment["failure_reason"].as_str()
+            .filter(|reason| *reason != "FAILURE_REASON_NONE")
+            .map(str::to_string),
+        timestamp: uint(&payment["creation_time_ns"]).map(|ns| ns / 1_000_000_000),
+    })
+}
+
+/// Parse LND's `POST /v1/channels/transactions` response for `invoice`
+pub fn parse_lnd_send_response(response: &Value, invoice: &Invoice, amount_msat: u64) -> LightningPayment {
+    let error = response["payment_error"].as_str().filter(|error| !error.is_empty());
+    let route = &response["payment_route"];
+    LightningPayment {
+        payment_hash: invoice.payment_hash.clone(),
+        direction: PaymentDirection::Outgoing,
+        amount_msat,
+        fee_msat: if error.is_none() { uint(&route["total_fees_msat"]) } else { None },
+        status: if error.is_none() { TransactionStatus::Confirmed } else { TransactionStatus::Failed },
+        preimage: if error.is_none() { base64_to_hex(&response["payment_preimage"]) } else { None },
+        failure: error.map(str::to_string),
+        timestamp: None,
+    }
+}
+
+/// LND node, through its REST API
+///
+/// The API key of the provider config is the hex-encoded macaroon.
+#[cfg(feature = "rpc")]
+pub struct LndBackend {
+    base_url: String,
+    macaroon: Option<String>,
+    http: reqwest::Client,
+}
+
+#[cfg(feature = "rpc")]
+impl LndBackend {
+    /// Create a backend for the LND REST API at `config.url` (e.g. `https://localhost:8080`)
+    pub fn new(config: &ProviderConfig) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(config.timeout.unwrap_or(60)))
+            .build()
+            .map_err(|e| Error::Provider(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(Self { base_url: config.url.trim_end_matches('/').to_string(), macaroon: config.api_key.clone(), http })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str, body: Option<Value>) -> Result<Value> {
+        let mut request = self.http.request(method, format!("{}{}", self.base_url, path));
+        if let Some(macaroon) = &self.macaroon {
+            request = request.header("Grpc-Metadata-macaroon", macaroon);
+        }
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        super::ethereum::block_on(super::ethereum::traced(super::ethereum::rpc_span("lightning", &self.base_url, path), async {
+            let response = request.send().await
+                .map_err(|e| Error::Network(format!("LND request failed: {}", e)))?;
+            let status = response.status();
+            let body = response.text().await
+                .map_err(|e| Error::Network(format!("LND request failed: {}", e)))?;
+            if !status.is_success() {
+                return Err(Error::Provider(format!("LND returned {}: {}", status, body)));
+            }
+            serde_json::from_str(&body).map_err(|e| Error::Provider(format!("Invalid LND response: {}", e)))
+        }))?
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl LightningBackend for LndBackend {
+    fn name(&self) -> &str {
+        "lnd"
+    }
+
+    fn node_id(&self) -> Result<String> {
+        let info = self.request(reqwest::Method::GET, "/v1/getinfo", None)?;
+        info["identity_pubkey"].as_str()
+            .map(str::to_string)
+            .ok_or_else(|| Error::Provider(format!("Invalid LND node info: {}", info)))
+    }
+
+    fn create_invoice(&self, amount_msat: Option<u64>, description: &str, expiry: u64) -> Result<Invoice> {
+        let body = serde_json::json!({
+            "value_msat": amount_msat.unwrap_or(0).to_string(),
+            "memo": description,
+            "expiry": expiry.to_string(),
+        });
+        let response = self.request(reqwest::Method::POST, "/v1/invoices", Some(body))?;
+        let payment_request = response["payment_request"].as_str()
+            .ok_or_else(|| Error::Provider(format!("Invalid LND invoice: {}", response)))?;
+        decode_invoice(payment_request)
+    }
+
+    fn pay_invoice(&self, invoice: &Invoice, amount_msat: Option<u64>, max_fee_msat: u64) -> Result<LightningPayment> {
+        let amount = invoice.amount_msat.or(amount_msat)
+            .ok_or_else(|| Error::InvalidInput("Invoice has no amount; an amount is required".to_string()))?;
+        let mut body = serde_json::json!({
+            "payment_request": invoice.payment_request,
+            "fee_limit": { "fixed_msat": max_fee_msat.to_string() },
+        });
+        if invoice.amount_msat.is_none() {
+            body["amt_msat"] = Value::String(amount.to_string());
+        }
+        let response = self.request(reqwest::Method::POST, "/v1/channels/transactions", Some(body))?;
+        Ok(parse_lnd_send_response(&response, invoice, amount))
+    }
+
+    fn get_payment(&self, payment_hash: &str, direction: PaymentDirection) -> Result<LightningPayment> {
+        let not_found = || Error::InvalidInput(format!("Unknown Lightning payment {}", payment_hash));
+        if !matches!(hex::decode(payment_hash), Ok(hash) if hash.len() == 32) {
+            return Err(Error::InvalidInput(format!("Invalid payment hash: {}", payment_hash)));
+        }
+        match direction {
+            PaymentDirection::Incoming => parse_lnd_invoice(&self.request(reqwest::Method::GET, &format!("/v1/invoice/{}", payment_hash), None)?),
+            PaymentDirection::Outgoing => {
+                let response = self.request(reqwest::Method::GET, "/v1/payments?include_incomplete=true&reversed=true", None)?;
+                response["payments"].as_array()
+                    .ok_or_else(|| Error::Provider(format!("Invalid LND payments: {}", response)))?
+                    .iter()
+                    .find(|payment| payment["payment_hash"].as_str() == Some(payment_hash))
+                    .ok_or_else(not_found)
+                    .and_then(parse_lnd_payment)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BOLT-11 test vectors
+    const DONATION: &str = "lnbc1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2pkx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq9qrsgq357wnc5r2ueh7ck6q93dj32dlqnls087fxdwk8qakdyafkq3yap9us6v52vjjsrvywa6rt52cm9r9zqt8r2t7mlcwspyetp5h2tztugp9lfyql";
+    const COFFEE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+    const PAYEE: &str = "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad";
+    const PAYMENT_HASH: &str = "0001020304050607080900010203040506070809000102030405060708090102";
+
+    struct MockBackend;
+
+    impl LightningBackend for MockBackend {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        fn node_id(&self) -> Result<String> {
+            Ok(PAYEE.to_string())
+        }
+
+        fn create_invoice(&self, _amount_msat: Option<u64>, _description: &str, _expiry: u64) -> Result<Invoice> {
+            decode_invoice(COFFEE)
+        }
+
+        fn pay_invoice(&self, invoice: &Invoice, amount_msat: Option<u64>, _max_fee_msat: u64) -> Result<LightningPayment> {
+            Ok(parse_lnd_send_response(&serde_json::json!({ "payment_error": "" }), invoice, invoice.amount_msat.or(amount_msat).unwrap()))
+        }
+
+        fn get_payment(&self, _payment_hash: &str, _direction: PaymentDirection) -> Result<LightningPayment> {
+            Err(Error::NotSupported("mock".to_string()))
+        }
+    }
+
+    #[test]
+    fn test_decode_invoice() {
+        let invoice = decode_invoice(DONATION).unwrap();
+        assert_eq!(invoice.network, "bitcoin");
+        assert_eq!(invoice.amount_msat, None);
+        assert_eq!(invoice.timestamp, 1_496_314_658);
+        assert_eq!(invoice.expiry, DEFAULT_EXPIRY);
+        assert_eq!(invoice.payment_hash, PAYMENT_HASH);
+        assert_eq!(invoice.payment_secret.as_deref(), Some("1111111111111111111111111111111111111111111111111111111111111111"));
+        assert_eq!(invoice.description.as_deref(), Some("Please consider supporting this project"));
+        assert_eq!(invoice.payee, PAYEE);
+
+        let invoice = decode_invoice(&format!("lightning:{}", COFFEE.to_uppercase())).unwrap();
+        assert_eq!(invoice.amount_msat, Some(250_000_000));
+        assert_eq!(invoice.description.as_deref(), Some("1 cup coffee"));
+        assert_eq!(invoice.expiry, 60);
+        assert_eq!(invoice.expires_at(), 1_496_314_718);
+        assert_eq!(invoice.payee, PAYEE);
+    }
+
+    #[test]
+    fn test_decode_invoice_rejects_corruption() {
+        let mut corrupted = COFFEE.to_string();
+        corrupted.replace_range(20..21, "q");
+        assert!(decode_invoice(&corrupted).is_err());
+        assert!(decode_invoice(&COFFEE.replacen("lnbc", "lntb", 1)).is_err());
+        assert!(decode_invoice("lnbc1qqqq").is_err());
+        assert!(decode_invoice("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").is_err());
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("").unwrap(), None);
+        assert_eq!(parse_amount("1").unwrap(), Some(100_000_000_000));
+        assert_eq!(parse_amount("20m").unwrap(), Some(2_000_000_000));
+        assert_eq!(parse_amount("2500u").unwrap(), Some(250_000_000));
+        assert_eq!(parse_amount("10n").unwrap(), Some(1_000));
+        assert_eq!(parse_amount("10p").unwrap(), Some(1));
+        assert!(parse_amount("1p").is_err());
+        assert!(parse_amount("u").is_err());
+        assert!(parse_amount("+1m").is_err());
+    }
+
+    #[test]
+    fn test_pay_checks_invoice() {
+        let now = 1_496_314_660;
+        assert!(pay(&MockBackend, COFFEE, "testnet", None, 1_000, now).is_err());
+        assert!(pay(&MockBackend, COFFEE, "bitcoin", None, 1_000, now + 60).is_err());
+        assert!(pay(&MockBackend, COFFEE, "bitcoin", Some(1), 1_000, now).is_err());
+        assert!(pay(&MockBackend, DONATION, "bitcoin", None, 1_000, now).is_err());
+
+        let payment = pay(&MockBackend, COFFEE, "bitcoin", None, 1_000, now).unwrap();
+        assert_eq!(payment.amount_msat, 250_000_000);
+        assert_eq!(payment.status, TransactionStatus::Confirmed);
+
+        let payment = pay(&MockBackend, DONATION, "bitcoin", Some(5_000), 1_000, now).unwrap();
+        assert_eq!(payment.amount_msat, 5_000);
+        let transaction = payment.to_transaction(PAYEE);
+        assert_eq!(transaction.transaction_type, TransactionType::Lightning);
+        assert_eq!(transaction.hash, PAYMENT_HASH);
+        assert_eq!(transaction.from, PAYEE);
+    }
+
+    #[test]
+    fn test_parse_lnd_responses() {
+        let invoice = serde_json::json!({
+            "r_hash": "AAECAwQFBgcICQABAgMEBQYHCAkAAQIDBAUGBwgJAQI=",
+            "r_preimage": "ERERERERERERERERERERERERERERERERERERERERERE=",
+            "state": "SETTLED",
+            "amt_paid_msat": "250000000",
+            "creation_date": "1496314658",
+            "settle_date": "1496314700",
+        });
+        let payment = parse_lnd_invoice(&invoice).unwrap();
+        assert_eq!(payment.payment_hash, PAYMENT_HASH);
+        assert_eq!(payment.direction, PaymentDirection::Incoming);
+        assert_eq!(payment.status, TransactionStatus::Confirmed);
+        assert_eq!(payment.amount_msat, 250_000_000);
+        assert_eq!(payment.timestamp, Some(1_496_314_700));
+        assert!(payment.preimage.is_some());
+
+        let payment = parse_lnd_payment(&serde_json::json!({
+            "payment_hash": PAYMENT_HASH,
+            "value_msat": "250000000",
+            "fee_msat": "1200",
+            "status": "FAILED",
+            "payment_preimage": "0000000000000000000000000000000000000000000000000000000000000000",
+            "failure_reason": "FAILURE_REASON_NO_ROUTE",
+            "creation_time_ns": "1496314658000000000",
+        })).unwrap();
+        assert_eq!(payment.status, TransactionStatus::Failed);
+        assert_eq!(payment.preimage, None);
+        assert_eq!(payment.failure.as_deref(), Some("FAILURE_REASON_NO_ROUTE"));
+        assert_eq!(payment.timestamp, Some(1_496_314_658));
+
+        let invoice = decode_invoice(COFFEE).unwrap();
+        let failed = parse_lnd_send_response(&serde_json::json!({ "payment_error": "no_route" }), &invoice, 250_000_000);
+        assert_eq!(failed.status, TransactionStatus::Failed);
+        assert_eq!(failed.failure.as_deref(), Some("no_route"));
+    }
+}
@@ -0,0 +1,405 @@
+//! MEV protection for EVM transactions
+//!
+//! Transactions sent to the public mempool can be front-run or sandwiched
+//! before they are mined. With [`SubmissionMode::Private`] signed EVM
+//! transactions are sent to a private relay (a Flashbots Protect-style RPC)
+//! that forwards them straight to block builders instead, falling back to the
+//! public mempool if the relay is unavailable and the wallet allows it.
+//! Protection is configured per wallet through [`MevProtections`].
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use serde::{Serialize, Deserialize};
+
+use crate::crypto::keys::KeyType;
+use crate::error::{Error, Result};
+use super::batch::{CallRequest, CallResult};
+use super::types::{Transaction, TransactionRequest, TransactionReceipt, TransactionStatus, TransactionSigner, TransactionBroadcaster, TransactionManager};
+
+/// Flashbots Protect RPC endpoint
+pub const FLASHBOTS_PROTECT_URL: &str = "https://rpc.flashbots.net";
+
+/// Where signed transactions are submitted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmissionMode {
+    /// The public mempool of the connected node
+    #[default]
+    Public,
+    /// A private relay
+    Private,
+}
+
+/// Route a transaction actually took
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmissionRoute {
+    /// Sent to a private relay
+    PrivateRelay,
+    /// Sent to the public mempool
+    Public,
+}
+
+/// MEV protection settings of a wallet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MevProtection {
+    /// Where EVM transactions are submitted
+    #[serde(default)]
+    pub mode: SubmissionMode,
+    /// Private relay RPC URL, Flashbots Protect if not set
+    #[serde(default)]
+    pub relay_url: Option<String>,
+    /// Submit to the public mempool when the relay fails
+    #[serde(default = "default_fallback_to_public")]
+    pub fallback_to_public: bool,
+}
+
+fn default_fallback_to_public() -> bool {
+    true
+}
+
+impl Default for MevProtection {
+    fn default() -> Self {
+        Self {
+            mode: SubmissionMode::Public,
+            relay_url: None,
+            fallback_to_public: default_fallback_to_public(),
+        }
+    }
+}
+
+impl MevProtection {
+    /// Protection sending transactions through Flashbots Protect
+    pub fn private() -> Self {
+        Self { mode: SubmissionMode::Private, ..Self::default() }
+    }
+
+    /// Validate the settings
+    pub fn validate(&self) -> Result<()> {
+        if let Some(url) = &self.relay_url {
+            if !url.starts_with("https://") {
+                return Err(Error::InvalidInput(format!("Relay URL must use https: {}", url)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the relay URL
+    pub fn relay_url(&self) -> &str {
+        self.relay_url.as_deref().unwrap_or(FLASHBOTS_PROTECT_URL)
+    }
+
+    /// Whether transactions of a chain go through the private relay
+    ///
+    /// Only EVM chains have a public mempool ordered by fee.
+    pub fn applies(&self, key_type: KeyType) -> bool {
+        self.mode == SubmissionMode::Private && key_type == KeyType::Ethereum
+    }
+
+    /// Submit a signed transaction according to the settings
+    ///
+    /// Only an unreachable relay triggers the fallback; a transaction the
+    /// relay rejects would be rejected by the public mempool as well.
+    pub fn submit<R, B>(&self, relay: &R, public: &B, signed_transaction: &[u8]) -> Result<Submission>
+    where
+        R: PrivateRelay + ?Sized,
+        B: TransactionBroadcaster + ?Sized,
+    {
+        if self.mode == SubmissionMode::Public {
+            return Ok(Submission::public(public.broadcast_transaction(signed_transaction)?, None));
+        }
+
+        match relay.send_raw_transaction(signed_transaction) {
+            Ok(hash) => Ok(Submission { hash, route: SubmissionRoute::PrivateRelay, relay_error: None }),
+            Err(e) if self.fallback_to_public && e.is_retryable() => {
+                Ok(Submission::public(public.broadcast_transaction(signed_transaction)?, Some(e.to_string())))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Outcome of submitting a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Submission {
+    /// Transaction hash
+    pub hash: String,
+    /// Route the transaction took
+    pub route: SubmissionRoute,
+    /// Why the private relay was bypassed, if it was
+    pub relay_error: Option<String>,
+}
+
+impl Submission {
+    fn public(hash: String, relay_error: Option<String>) -> Self {
+        Self { hash, route: SubmissionRoute::Public, relay_error }
+    }
+}
+
+/// Relay accepting signed transactions without publishing them to the mempool
+pub trait PrivateRelay: Send + Sync {
+    /// Submit a signed transaction, returning its hash
+    fn send_raw_transaction(&self, signed_transaction: &[u8]) -> Result<String>;
+}
+
+/// Private relay speaking the `eth_sendRawTransaction` JSON-RPC method
+#[cfg(feature = "rpc")]
+pub struct RpcRelay {
+    /// Relay RPC URL
+    url: String,
+    /// HTTP client
+    http: reqwest::Client,
+}
+
+#[cfg(feature = "rpc")]
+impl RpcRelay {
+    /// Create a relay client for a URL
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Parse a JSON-RPC response to `eth_sendRawTransaction`
+    pub fn parse_response(response: &serde_json::Value) -> Result<String> {
+        if let Some(error) = response.get("error") {
+            let message = error.get("message").and_then(|v| v.as_str()).unwrap_or("unknown error");
+            return Err(Error::Transaction(format!("Private relay rejected the transaction: {}", message)));
+        }
+        response.get("result").and_then(|v| v.as_str()).map(str::to_string)
+            .ok_or_else(|| Error::Provider(format!("Invalid relay response: {}", response)))
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl PrivateRelay for RpcRelay {
+    fn send_raw_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_sendRawTransaction",
+            "params": [format!("0x{}", hex::encode(signed_transaction))],
+        });
+        let response: serde_json::Value = super::ethereum::block_on(async {
+            self.http.post(&self.url)
+                .json(&body)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+        })?
+        .map_err(|e| Error::Network(format!("Private relay request failed: {}", e)))?;
+
+        Self::parse_response(&response)
+    }
+}
+
+/// MEV protection settings of each wallet, falling back to a default
+#[derive(Debug, Default)]
+pub struct MevProtections {
+    default: MevProtection,
+    wallets: RwLock<HashMap<String, MevProtection>>,
+}
+
+impl MevProtections {
+    /// Create settings with a default for wallets without their own
+    pub fn new(default: MevProtection) -> Self {
+        Self { default, wallets: RwLock::new(HashMap::new()) }
+    }
+
+    /// Get the settings applying to a wallet
+    pub fn protection_for(&self, wallet_id: Option<&str>) -> MevProtection {
+        wallet_id.and_then(|id| self.wallets.read().unwrap().get(id).cloned())
+            .unwrap_or_else(|| self.default.clone())
+    }
+
+    /// Set a wallet's settings
+    pub fn set(&self, wallet_id: &str, protection: MevProtection) -> Result<()> {
+        protection.validate()?;
+        self.wallets.write().unwrap().insert(wallet_id.to_string(), protection);
+        Ok(())
+    }
+
+    /// Remove a wallet's settings, returning whether any were set
+    pub fn remove(&self, wallet_id: &str) -> bool {
+        self.wallets.write().unwrap().remove(wallet_id).is_some()
+    }
+}
+
+/// A provider wrapper that broadcasts through a private relay
+pub struct MevProtectedProvider<P, R> {
+    /// Wrapped provider, used for the public mempool
+    inner: P,
+    /// Private relay
+    relay: R,
+    /// Submission settings
+    protection: MevProtection,
+}
+
+impl<P: TransactionManager, R: PrivateRelay> MevProtectedProvider<P, R> {
+    /// Wrap a provider
+    pub fn new(inner: P, relay: R, protection: MevProtection) -> Self {
+        Self { inner, relay, protection }
+    }
+
+    /// Get the wrapped provider
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Submit a signed transaction, reporting the route it took
+    pub fn submit(&self, signed_transaction: &[u8]) -> Result<Submission> {
+        self.protection.submit(&self.relay, &self.inner, signed_transaction)
+    }
+}
+
+impl<P: TransactionManager, R: PrivateRelay> TransactionSigner for MevProtectedProvider<P, R> {
+    fn sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        self.inner.sign_transaction(request)
+    }
+}
+
+impl<P: TransactionManager, R: PrivateRelay> TransactionBroadcaster for MevProtectedProvider<P, R> {
+    fn broadcast_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+        Ok(self.submit(signed_transaction)?.hash)
+    }
+
+    fn get_transaction_status(&self, hash: &str) -> Result<TransactionStatus> {
+        self.inner.get_transaction_status(hash)
+    }
+
+    fn get_transaction_receipt(&self, hash: &str) -> Result<TransactionReceipt> {
+        self.inner.get_transaction_receipt(hash)
+    }
+}
+
+impl<P: TransactionManager, R: PrivateRelay> TransactionManager for MevProtectedProvider<P, R> {
+    fn create_and_sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        self.inner.create_and_sign_transaction(request)
+    }
+
+    fn get_transaction(&self, hash: &str) -> Result<Transaction> {
+        self.inner.get_transaction(hash)
+    }
+
+    fn get_transactions(&self, address: &str, limit: usize, offset: usize) -> Result<Vec<Transaction>> {
+        self.inner.get_transactions(address, limit, offset)
+    }
+
+    fn batch_call(&self, calls: &[CallRequest]) -> Result<Vec<CallResult>> {
+        self.inner.batch_call(calls)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use crate::transaction::mock::MockEvmProvider;
+    use crate::transaction::NetworkBinding;
+
+    /// Relay recording what it was sent, or failing with a fixed error
+    #[derive(Default)]
+    struct TestRelay {
+        sent: Mutex<Vec<Vec<u8>>>,
+        error: Option<fn() -> Error>,
+    }
+
+    impl PrivateRelay for TestRelay {
+        fn send_raw_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+            if let Some(error) = self.error {
+                return Err(error());
+            }
+            self.sent.lock().unwrap().push(signed_transaction.to_vec());
+            Ok("0xrelayed".to_string())
+        }
+    }
+
+    fn request() -> TransactionRequest {
+        TransactionRequest {
+            key_type: KeyType::Ethereum,
+            network: NetworkBinding::Evm { chain_id: 1 },
+            from: "0xfrom".to_string(),
+            to: "0xto".to_string(),
+            value: "1000".to_string(),
+            gas_price: None,
+            gas_limit: None,
+            nonce: None,
+            data: None,
+            destination_tag: None,
+            memo: None,
+        }
+    }
+
+    fn provider_with(relay: TestRelay, protection: MevProtection) -> MevProtectedProvider<MockEvmProvider, TestRelay> {
+        let mock = MockEvmProvider::new(1);
+        mock.set_balance("0xfrom", 10u128.pow(18));
+        MevProtectedProvider::new(mock, relay, protection)
+    }
+
+    #[test]
+    fn test_private_submission() {
+        let provider = provider_with(TestRelay::default(), MevProtection::private());
+        let signed = provider.create_and_sign_transaction(&request()).unwrap();
+
+        let submission = provider.submit(&signed).unwrap();
+        assert_eq!(submission.route, SubmissionRoute::PrivateRelay);
+        assert_eq!(submission.hash, "0xrelayed");
+        assert_eq!(provider.relay.sent.lock().unwrap().len(), 1);
+        assert!(provider.inner().get_transactions("0xfrom", 10, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_public_fallback() {
+        let unavailable = || TestRelay { error: Some(|| Error::Network("connection refused".to_string())), ..TestRelay::default() };
+
+        let provider = provider_with(unavailable(), MevProtection::private());
+        let signed = provider.create_and_sign_transaction(&request()).unwrap();
+        let submission = provider.submit(&signed).unwrap();
+        assert_eq!(submission.route, SubmissionRoute::Public);
+        assert!(submission.relay_error.unwrap().contains("connection refused"));
+        assert_eq!(provider.inner().get_transactions("0xfrom", 10, 0).unwrap().len(), 1);
+
+        // Without fallback the relay error is returned
+        let strict = MevProtection { fallback_to_public: false, ..MevProtection::private() };
+        let provider = provider_with(unavailable(), strict);
+        let signed = provider.create_and_sign_transaction(&request()).unwrap();
+        assert!(provider.submit(&signed).is_err());
+
+        // A relay rejecting the transaction is not bypassed either
+        let rejecting = TestRelay { error: Some(|| Error::Transaction("nonce too low".to_string())), ..TestRelay::default() };
+        let provider = provider_with(rejecting, MevProtection::private());
+        let signed = provider.create_and_sign_transaction(&request()).unwrap();
+        assert!(provider.submit(&signed).is_err());
+    }
+
+    #[test]
+    fn test_wallet_settings() {
+        let protections = MevProtections::new(MevProtection::default());
+        protections.set("wallet_1", MevProtection::private()).unwrap();
+
+        let protection = protections.protection_for(Some("wallet_1"));
+        assert!(protection.applies(KeyType::Ethereum));
+        assert!(!protection.applies(KeyType::Solana));
+        assert_eq!(protection.relay_url(), FLASHBOTS_PROTECT_URL);
+        assert!(!protections.protection_for(Some("wallet_2")).applies(KeyType::Ethereum));
+
+        let insecure = MevProtection { relay_url: Some("http://relay.example".to_string()), ..MevProtection::private() };
+        assert!(protections.set("wallet_1", insecure).is_err());
+        assert!(protections.remove("wallet_1"));
+        assert!(!protections.remove("wallet_1"));
+    }
+
+    #[cfg(feature = "rpc")]
+    #[test]
+    fn test_parse_relay_response() {
+        let response = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": "0xabc" });
+        assert_eq!(RpcRelay::parse_response(&response).unwrap(), "0xabc");
+
+        let response = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32000, "message": "nonce too low" } });
+        assert!(RpcRelay::parse_response(&response).unwrap_err().to_string().contains("nonce too low"));
+    }
+}
@@ -0,0 +1,538 @@
+//! In-process mock chain providers
+//!
+//! The mock providers implement the transaction provider traits against an
+//! in-memory ledger, so integration tests run without any RPC endpoint.
+//! Balances are programmable, signatures and hashes are deterministic, and
+//! faults (timeouts, RPC errors, dropped transactions) can be injected.
+//!
+//! Only built for tests and with the `test-vectors` feature.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+use crate::crypto::keys::KeyType;
+use super::types::{Transaction, TransactionRequest, TransactionReceipt, TransactionStatus, TransactionSigner, TransactionBroadcaster, TransactionManager, TransactionType, NetworkBinding};
+use super::solana::SOLANA_MAINNET_GENESIS_HASH;
+
+/// Base timestamp of the mock chain's genesis block
+const MOCK_GENESIS_TIMESTAMP: u64 = 1_700_000_000;
+
+/// A fault to inject into the next provider call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockFault {
+    /// The call times out
+    Timeout,
+    /// The RPC endpoint returns an error
+    RpcError(String),
+}
+
+/// Mutable state of a mock chain
+#[derive(Debug, Default)]
+struct MockChainState {
+    balances: HashMap<String, u128>,
+    nonces: HashMap<String, u64>,
+    transactions: HashMap<String, Transaction>,
+    faults: VecDeque<MockFault>,
+    drop_transactions: bool,
+    manual_confirm: bool,
+    block_number: u64,
+}
+
+/// An in-memory chain shared by the mock providers
+#[derive(Debug)]
+pub struct MockChain {
+    key_type: KeyType,
+    network: NetworkBinding,
+    fee: u128,
+    state: Mutex<MockChainState>,
+}
+
+impl MockChain {
+    /// Create a new mock chain charging a flat fee per transaction
+    pub fn new(key_type: KeyType, network: NetworkBinding, fee: u128) -> Self {
+        Self {
+            key_type,
+            network,
+            fee,
+            state: Mutex::new(MockChainState::default()),
+        }
+    }
+
+    /// Get the network binding transactions must carry
+    pub fn network_binding(&self) -> NetworkBinding {
+        self.network.clone()
+    }
+
+    /// Get the flat fee charged per transaction
+    pub fn fee(&self) -> u128 {
+        self.fee
+    }
+
+    /// Set the balance of an address
+    pub fn set_balance(&self, address: &str, amount: u128) {
+        self.state.lock().unwrap().balances.insert(address.to_string(), amount);
+    }
+
+    /// Get the balance of an address
+    pub fn balance(&self, address: &str) -> u128 {
+        self.state.lock().unwrap().balances.get(address).copied().unwrap_or(0)
+    }
+
+    /// Queue a fault for the next provider call
+    pub fn inject_fault(&self, fault: MockFault) {
+        self.state.lock().unwrap().faults.push_back(fault);
+    }
+
+    /// Accept broadcasts but never include the transactions
+    pub fn set_drop_transactions(&self, drop: bool) {
+        self.state.lock().unwrap().drop_transactions = drop;
+    }
+
+    /// Keep broadcast transactions pending until [`MockChain::confirm_pending`] is called
+    pub fn set_manual_confirm(&self, manual: bool) {
+        self.state.lock().unwrap().manual_confirm = manual;
+    }
+
+    /// Confirm all pending transactions in a new block, returning how many were confirmed
+    pub fn confirm_pending(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        state.block_number += 1;
+        let block_number = state.block_number;
+
+        let mut confirmed = 0;
+        for transaction in state.transactions.values_mut() {
+            if transaction.status == TransactionStatus::Pending {
+                transaction.status = TransactionStatus::Confirmed;
+                transaction.block_number = Some(block_number);
+                transaction.timestamp = Some(MOCK_GENESIS_TIMESTAMP + block_number);
+                confirmed += 1;
+            }
+        }
+
+        confirmed
+    }
+
+    fn take_fault(&self) -> Result<()> {
+        match self.state.lock().unwrap().faults.pop_front() {
+            Some(MockFault::Timeout) => Err(Error::Network("Request timed out".to_string())),
+            Some(MockFault::RpcError(message)) => Err(Error::Provider(message)),
+            None => Ok(()),
+        }
+    }
+
+    fn format_hash(&self, digest: &[u8]) -> String {
+        match self.key_type {
+            KeyType::Ethereum => format!("0x{}", hex::encode(digest)),
+            KeyType::Solana => bs58::encode(digest).into_string(),
+            KeyType::Bitcoin | KeyType::Ton => hex::encode(digest),
+            KeyType::Cosmos | KeyType::Xrp => hex::encode_upper(digest),
+        }
+    }
+
+    fn sign(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        self.take_fault()?;
+
+        if request.key_type != self.key_type {
+            return Err(Error::Transaction(format!("Not a {:?} transaction", self.key_type)));
+        }
+        request.network.ensure_matches(&self.network)?;
+
+        // Pin the nonce so identical requests produce distinct, deterministic payloads
+        let mut request = request.clone();
+        if request.nonce.is_none() {
+            request.nonce = Some(self.state.lock().unwrap().nonces.get(&request.from).copied().unwrap_or(0));
+        }
+
+        serde_json::to_vec(&request).map_err(|e| Error::Serialization(e.to_string()))
+    }
+
+    fn broadcast(&self, signed_transaction: &[u8]) -> Result<String> {
+        self.take_fault()?;
+
+        let request: TransactionRequest = serde_json::from_slice(signed_transaction)
+            .map_err(|e| Error::Transaction(format!("Invalid signed transaction: {}", e)))?;
+        let hash = self.format_hash(&Sha256::digest(signed_transaction));
+
+        let value = request.value.parse::<u128>()
+            .map_err(|e| Error::Transaction(format!("Invalid value: {}", e)))?;
+
+        let mut state = self.state.lock().unwrap();
+
+        if state.transactions.contains_key(&hash) {
+            return Err(Error::Transaction(format!("Transaction already known: {}", hash)));
+        }
+
+        let balance = state.balances.get(&request.from).copied().unwrap_or(0);
+        if balance < value + self.fee {
+            return Err(Error::Transaction("Insufficient funds".to_string()));
+        }
+
+        if state.drop_transactions {
+            // Accepted by the node but never included
+            return Ok(hash);
+        }
+
+        state.balances.insert(request.from.clone(), balance - value - self.fee);
+        *state.balances.entry(request.to.clone()).or_insert(0) += value;
+        *state.nonces.entry(request.from.clone()).or_insert(0) += 1;
+
+        let (status, block_number, timestamp) = if state.manual_confirm {
+            (TransactionStatus::Pending, None, None)
+        } else {
+            state.block_number += 1;
+            (TransactionStatus::Confirmed, Some(state.block_number), Some(MOCK_GENESIS_TIMESTAMP + state.block_number))
+        };
+
+        let transaction = Transaction {
+            hash: hash.clone(),
+            transaction_type: if request.data.is_some() { TransactionType::ContractCall } else { TransactionType::Transfer },
+            key_type: self.key_type,
+            from: request.from,
+            to: request.to,
+            value: request.value,
+            gas_price: request.gas_price,
+            gas_limit: request.gas_limit,
+            nonce: request.nonce,
+            data: request.data,
+            status,
+            block_number,
+            timestamp,
+            fee: Some(self.fee.to_string()),
+            memo: request.memo,
+        };
+        state.transactions.insert(hash.clone(), transaction);
+
+        Ok(hash)
+    }
+
+    fn transaction(&self, hash: &str) -> Result<Transaction> {
+        self.take_fault()?;
+
+        self.state.lock().unwrap().transactions.get(hash).cloned()
+            .ok_or_else(|| Error::Transaction(format!("Transaction not found: {}", hash)))
+    }
+
+    fn receipt(&self, hash: &str) -> Result<TransactionReceipt> {
+        let transaction = self.transaction(hash)?;
+
+        Ok(TransactionReceipt {
+            hash: transaction.hash,
+            status: transaction.status,
+            block_number: transaction.block_number,
+            timestamp: transaction.timestamp,
+            fee: transaction.fee,
+            logs: vec![],
+        })
+    }
+
+    fn transactions(&self, address: &str, limit: usize, offset: usize) -> Result<Vec<Transaction>> {
+        self.take_fault()?;
+
+        let state = self.state.lock().unwrap();
+        let mut transactions: Vec<Transaction> = state.transactions.values()
+            .filter(|t| t.from == address || t.to == address)
+            .cloned()
+            .collect();
+
+        // Newest first, with the hash as a stable tie-breaker
+        transactions.sort_by(|a, b| b.nonce.cmp(&a.nonce).then_with(|| a.hash.cmp(&b.hash)));
+
+        Ok(transactions.into_iter().skip(offset).take(limit).collect())
+    }
+}
+
+/// Mock EVM provider
+#[derive(Debug)]
+pub struct MockEvmProvider {
+    chain: MockChain,
+}
+
+impl MockEvmProvider {
+    /// Create a mock provider for the given EVM chain ID, charging 21000 gas at 20 Gwei
+    pub fn new(chain_id: u64) -> Self {
+        Self {
+            chain: MockChain::new(KeyType::Ethereum, NetworkBinding::Evm { chain_id }, 21_000 * 20_000_000_000),
+        }
+    }
+}
+
+impl std::ops::Deref for MockEvmProvider {
+    type Target = MockChain;
+
+    fn deref(&self) -> &MockChain {
+        &self.chain
+    }
+}
+
+/// Mock Solana provider
+#[derive(Debug)]
+pub struct MockSolanaProvider {
+    chain: MockChain,
+}
+
+impl MockSolanaProvider {
+    /// Create a mock provider for mainnet-beta, charging 5000 lamports per signature
+    pub fn new() -> Self {
+        Self {
+            chain: MockChain::new(KeyType::Solana, NetworkBinding::Solana { genesis_hash: SOLANA_MAINNET_GENESIS_HASH.to_string() }, 5_000),
+        }
+    }
+}
+
+impl Default for MockSolanaProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::ops::Deref for MockSolanaProvider {
+    type Target = MockChain;
+
+    fn deref(&self) -> &MockChain {
+        &self.chain
+    }
+}
+
+/// Mock Bitcoin provider
+#[derive(Debug)]
+pub struct MockBitcoinProvider {
+    chain: MockChain,
+}
+
+impl MockBitcoinProvider {
+    /// Create a mock provider for the given network, charging a flat 10000 satoshi fee
+    pub fn new(network: bitcoin::Network) -> Self {
+        Self {
+            chain: MockChain::new(KeyType::Bitcoin, NetworkBinding::Bitcoin { network: network.to_string() }, 10_000),
+        }
+    }
+}
+
+impl std::ops::Deref for MockBitcoinProvider {
+    type Target = MockChain;
+
+    fn deref(&self) -> &MockChain {
+        &self.chain
+    }
+}
+
+impl TransactionSigner for MockChain {
+    fn sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        self.sign(request)
+    }
+}
+
+impl TransactionBroadcaster for MockChain {
+    fn broadcast_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+        self.broadcast(signed_transaction)
+    }
+
+    fn get_transaction_status(&self, hash: &str) -> Result<TransactionStatus> {
+        Ok(self.transaction(hash)?.status)
+    }
+
+    fn get_transaction_receipt(&self, hash: &str) -> Result<TransactionReceipt> {
+        self.receipt(hash)
+    }
+}
+
+impl TransactionManager for MockChain {
+    fn get_transaction(&self, hash: &str) -> Result<Transaction> {
+        self.transaction(hash)
+    }
+
+    fn get_transactions(&self, address: &str, limit: usize, offset: usize) -> Result<Vec<Transaction>> {
+        self.transactions(address, limit, offset)
+    }
+}
+
+impl TransactionSigner for MockEvmProvider {
+    fn sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        self.chain.sign_transaction(request)
+    }
+}
+
+impl TransactionBroadcaster for MockEvmProvider {
+    fn broadcast_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+        self.chain.broadcast_transaction(signed_transaction)
+    }
+
+    fn get_transaction_status(&self, hash: &str) -> Result<TransactionStatus> {
+        self.chain.get_transaction_status(hash)
+    }
+
+    fn get_transaction_receipt(&self, hash: &str) -> Result<TransactionReceipt> {
+        self.chain.get_transaction_receipt(hash)
+    }
+}
+
+impl TransactionManager for MockEvmProvider {
+    fn get_transaction(&self, hash: &str) -> Result<Transaction> {
+        self.chain.get_transaction(hash)
+    }
+
+    fn get_transactions(&self, address: &str, limit: usize, offset: usize) -> Result<Vec<Transaction>> {
+        self.chain.get_transactions(address, limit, offset)
+    }
+}
+
+impl TransactionSigner for MockSolanaProvider {
+    fn sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        self.chain.sign_transaction(request)
+    }
+}
+
+impl TransactionBroadcaster for MockSolanaProvider {
+    fn broadcast_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+        self.chain.broadcast_transaction(signed_transaction)
+    }
+
+    fn get_transaction_status(&self, hash: &str) -> Result<TransactionStatus> {
+        self.chain.get_transaction_status(hash)
+    }
+
+    fn get_transaction_receipt(&self, hash: &str) -> Result<TransactionReceipt> {
+        self.chain.get_transaction_receipt(hash)
+    }
+}
+
+impl TransactionManager for MockSolanaProvider {
+    fn get_transaction(&self, hash: &str) -> Result<Transaction> {
+        self.chain.get_transaction(hash)
+    }
+
+    fn get_transactions(&self, address: &str, limit: usize, offset: usize) -> Result<Vec<Transaction>> {
+        self.chain.get_transactions(address, limit, offset)
+    }
+}
+
+impl TransactionSigner for MockBitcoinProvider {
+    fn sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        self.chain.sign_transaction(request)
+    }
+}
+
+impl TransactionBroadcaster for MockBitcoinProvider {
+    fn broadcast_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+        self.chain.broadcast_transaction(signed_transaction)
+    }
+
+    fn get_transaction_status(&self, hash: &str) -> Result<TransactionStatus> {
+        self.chain.get_transaction_status(hash)
+    }
+
+    fn get_transaction_receipt(&self, hash: &str) -> Result<TransactionReceipt> {
+        self.chain.get_transaction_receipt(hash)
+    }
+}
+
+impl TransactionManager for MockBitcoinProvider {
+    fn get_transaction(&self, hash: &str) -> Result<Transaction> {
+        self.chain.get_transaction(hash)
+    }
+
+    fn get_transactions(&self, address: &str, limit: usize, offset: usize) -> Result<Vec<Transaction>> {
+        self.chain.get_transactions(address, limit, offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(chain_id: u64, value: &str) -> TransactionRequest {
+        TransactionRequest {
+            key_type: KeyType::Ethereum,
+            network: NetworkBinding::Evm { chain_id },
+            from: "0xfrom".to_string(),
+            to: "0xto".to_string(),
+            value: value.to_string(),
+            gas_price: None,
+            gas_limit: None,
+            nonce: None,
+            data: None,
+            destination_tag: None,
+            memo: None,
+        }
+    }
+
+    #[test]
+    fn test_transfer_moves_balances() {
+        let provider = MockEvmProvider::new(1);
+        provider.set_balance("0xfrom", 10u128.pow(18));
+
+        let hash = provider.send_transaction(&request(1, "1000")).unwrap();
+
+        assert_eq!(provider.get_transaction_status(&hash).unwrap(), TransactionStatus::Confirmed);
+        assert_eq!(provider.balance("0xto"), 1000);
+        assert_eq!(provider.balance("0xfrom"), 10u128.pow(18) - 1000 - provider.fee());
+    }
+
+    #[test]
+    fn test_signatures_are_deterministic() {
+        let first = MockEvmProvider::new(1);
+        let second = MockEvmProvider::new(1);
+
+        assert_eq!(
+            first.sign_transaction(&request(1, "1")).unwrap(),
+            second.sign_transaction(&request(1, "1")).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_replayed_broadcast_rejected() {
+        let provider = MockEvmProvider::new(1);
+        provider.set_balance("0xfrom", 10u128.pow(18));
+
+        let signed = provider.sign_transaction(&request(1, "1")).unwrap();
+        provider.broadcast_transaction(&signed).unwrap();
+        assert!(provider.broadcast_transaction(&signed).is_err());
+    }
+
+    #[test]
+    fn test_fault_injection() {
+        let provider = MockEvmProvider::new(1);
+        provider.set_balance("0xfrom", 10u128.pow(18));
+
+        provider.inject_fault(MockFault::Timeout);
+        assert!(matches!(provider.send_transaction(&request(1, "1")), Err(Error::Network(_))));
+
+        // Faults only apply to a single call
+        assert!(provider.send_transaction(&request(1, "1")).is_ok());
+    }
+
+    #[test]
+    fn test_dropped_transactions_never_land() {
+        let provider = MockEvmProvider::new(1);
+        provider.set_balance("0xfrom", 10u128.pow(18));
+        provider.set_drop_transactions(true);
+
+        let hash = provider.send_transaction(&request(1, "1")).unwrap();
+        assert!(provider.get_transaction_status(&hash).is_err());
+        assert_eq!(provider.balance("0xto"), 0);
+    }
+
+    #[test]
+    fn test_manual_confirmation() {
+        let provider = MockEvmProvider::new(1);
+        provider.set_balance("0xfrom", 10u128.pow(18));
+        provider.set_manual_confirm(true);
+
+        let hash = provider.send_transaction(&request(1, "1")).unwrap();
+        assert_eq!(provider.get_transaction_status(&hash).unwrap(), TransactionStatus::Pending);
+
+        assert_eq!(provider.confirm_pending(), 1);
+        assert_eq!(provider.get_transaction_status(&hash).unwrap(), TransactionStatus::Confirmed);
+    }
+
+    #[test]
+    fn test_insufficient_funds_and_wrong_network() {
+        let provider = MockEvmProvider::new(1);
+
+        assert!(provider.send_transaction(&request(1, "1")).is_err());
+        assert!(provider.sign_transaction(&request(5, "1")).is_err());
+    }
+}
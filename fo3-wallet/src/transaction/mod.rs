@@ -4,13 +4,39 @@
 //! transactions across multiple blockchains.
 
 mod types;
+mod batch;
 mod ethereum;
 mod solana;
 mod bitcoin;
+mod spl;
+mod ton;
+mod cosmos;
+mod xrp;
 pub mod provider;
+#[cfg(any(test, feature = "test-vectors"))]
+pub mod mock;
+pub mod schedule;
+pub mod compliance;
+pub mod fee_budget;
+pub mod mev;
+pub mod travel_rule;
+pub mod dust;
+pub mod intents;
+pub mod sweep;
+pub mod fee_payer;
+pub mod utxo;
+pub mod lightning;
+pub mod validators;
+#[cfg(feature = "rpc")]
+pub mod resilience;
 
 pub use types::*;
+pub use batch::*;
 pub use ethereum::*;
 pub use solana::*;
 pub use bitcoin::*;
+pub use spl::*;
+pub use ton::*;
+pub use cosmos::*;
+pub use xrp::*;
 pub use provider::*;
@@ -1,8 +1,14 @@
 //! Transaction provider
 
+#[cfg(feature = "rpc")]
+use std::time::Duration;
+
 use crate::error::Result;
 use crate::crypto::keys::KeyType;
-use super::types::TransactionManager;
+use crate::secrets::SecretProvider;
+use super::types::{NetworkBinding, TransactionManager};
+#[cfg(feature = "rpc")]
+use super::resilience::{ResilientProvider, RetryPolicy};
 
 /// Provider type
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -13,6 +19,12 @@ pub enum ProviderType {
     WebSocket,
     /// IPC provider
     Ipc,
+    /// Esplora REST API (Bitcoin address index)
+    Esplora,
+    /// Electrum server (Bitcoin address index)
+    Electrum,
+    /// LND REST API (Lightning node)
+    Lnd,
 }
 
 /// Provider configuration
@@ -28,24 +40,92 @@ pub struct ProviderConfig {
     pub timeout: Option<u64>,
 }
 
+impl ProviderConfig {
+    /// Load the API key from a secret, if the secrets provider has it
+    ///
+    /// The key is copied into the configuration, so a rotated secret is only
+    /// picked up by calling this again: build the configuration per use from
+    /// a [`CachedSecrets`](crate::secrets::CachedSecrets) rather than keeping
+    /// the result.
+    pub fn with_api_key_from(mut self, secrets: &dyn SecretProvider, name: &str) -> Result<Self> {
+        if let Some(secret) = secrets.get(name)? {
+            self.api_key = Some(secret.expose().to_string());
+        }
+        Ok(self)
+    }
+}
+
 /// Provider factory
 pub struct ProviderFactory;
 
 impl ProviderFactory {
+    /// Create a new provider with the default retry policy
+    #[cfg(feature = "rpc")]
+    pub fn create_provider(key_type: KeyType, config: ProviderConfig) -> Result<Box<dyn TransactionManager>> {
+        let mut policy = RetryPolicy::default();
+        if let Some(timeout) = config.timeout {
+            policy.timeout_budget = Some(Duration::from_secs(timeout));
+        }
+
+        Self::create_provider_with_policy(key_type, config, policy)
+    }
+
     /// Create a new provider
+    #[cfg(not(feature = "rpc"))]
     pub fn create_provider(key_type: KeyType, config: ProviderConfig) -> Result<Box<dyn TransactionManager>> {
+        match key_type {
+            KeyType::Ethereum => Ok(Box::new(super::ethereum::EthereumProvider::new(config)?)),
+            KeyType::Solana => Ok(Box::new(super::solana::SolanaProvider::new(config)?)),
+            KeyType::Bitcoin => Ok(Box::new(super::bitcoin::BitcoinProvider::new(config)?)),
+            KeyType::Ton => Ok(Box::new(super::ton::TonProvider::new(config)?)),
+            KeyType::Cosmos => Ok(Box::new(super::cosmos::CosmosProvider::new(config)?)),
+            KeyType::Xrp => Ok(Box::new(super::xrp::XrpProvider::new(config)?)),
+        }
+    }
+
+    /// Get the network a provider created from `config` is bound to
+    pub fn network_binding(key_type: KeyType, config: &ProviderConfig) -> Result<NetworkBinding> {
+        Ok(match key_type {
+            KeyType::Ethereum => super::ethereum::EthereumProvider::new(config.clone())?.network_binding(),
+            KeyType::Solana => super::solana::SolanaProvider::new(config.clone())?.network_binding(),
+            KeyType::Bitcoin => super::bitcoin::BitcoinProvider::new(config.clone())?.network_binding(),
+            KeyType::Ton => super::ton::TonProvider::new(config.clone())?.network_binding(),
+            KeyType::Cosmos => super::cosmos::CosmosProvider::new(config.clone())?.network_binding(),
+            KeyType::Xrp => super::xrp::XrpProvider::new(config.clone())?.network_binding(),
+        })
+    }
+
+    /// Create a new provider whose RPC calls are retried according to `policy`
+    ///
+    /// Calls share the circuit breaker of the configured endpoint URL.
+    #[cfg(feature = "rpc")]
+    pub fn create_provider_with_policy(key_type: KeyType, config: ProviderConfig, policy: RetryPolicy) -> Result<Box<dyn TransactionManager>> {
+        let endpoint = config.url.clone();
+
         match key_type {
             KeyType::Ethereum => {
                 let provider = super::ethereum::EthereumProvider::new(config)?;
-                Ok(Box::new(provider))
+                Ok(Box::new(ResilientProvider::new(provider, &endpoint, policy)))
             }
             KeyType::Solana => {
                 let provider = super::solana::SolanaProvider::new(config)?;
-                Ok(Box::new(provider))
+                Ok(Box::new(ResilientProvider::new(provider, &endpoint, policy)))
             }
             KeyType::Bitcoin => {
                 let provider = super::bitcoin::BitcoinProvider::new(config)?;
-                Ok(Box::new(provider))
+                Ok(Box::new(ResilientProvider::new(provider, &endpoint, policy)))
+            }
+            KeyType::Ton => {
+                let provider = super::ton::TonProvider::new(config)?;
+                Ok(Box::new(ResilientProvider::new(provider, &endpoint, policy)))
+            }
+            KeyType::Cosmos => {
+                let provider = super::cosmos::CosmosProvider::new(config)?;
+                Ok(Box::new(ResilientProvider::new(provider, &endpoint, policy)))
+            }
+            KeyType::Xrp => {
+                let provider = super::xrp::XrpProvider::new(config)?;
+                Ok(Box::new(ResilientProvider::new(provider, &endpoint, policy)))
             }
         }
     }
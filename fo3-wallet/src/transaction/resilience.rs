@@ -0,0 +1,482 @@
+//! Retry and circuit breaker policies for provider RPC calls
+//!
+//! [`ResilientProvider`] wraps any [`TransactionManager`] and retries
+//! retryable failures with jittered exponential backoff, within an overall
+//! timeout budget. Each endpoint has a shared [`CircuitBreaker`] that stops
+//! calling a failing endpoint until a cool-down has elapsed.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Serialize, Deserialize};
+
+use crate::error::{Error, Result};
+use super::batch::{CallRequest, CallResult};
+use super::types::{Transaction, TransactionRequest, TransactionReceipt, TransactionStatus, TransactionSigner, TransactionBroadcaster, TransactionManager};
+
+/// Retry policy for provider calls
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry
+    pub base_delay: Duration,
+    /// Upper bound on the delay between retries
+    pub max_delay: Duration,
+    /// Fraction of each delay that is randomized (0.0 - 1.0)
+    pub jitter: f64,
+    /// Total time budget for a call including all retries
+    pub timeout_budget: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            jitter: 0.5,
+            timeout_budget: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Get the backoff delay before the given retry (starting at 1)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(16);
+        let delay = self.base_delay.saturating_mul(1 << exponent).min(self.max_delay);
+
+        // Randomize the upper `jitter` fraction of the delay to spread out retries
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        delay.mul_f64(1.0 - jitter * rand::random::<f64>())
+    }
+}
+
+/// Circuit breaker configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open the breaker
+    pub failure_threshold: u32,
+    /// How long the breaker stays open before allowing a trial call
+    pub open_duration: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Circuit breaker state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BreakerState {
+    /// Calls pass through
+    Closed,
+    /// Calls are rejected without reaching the endpoint
+    Open,
+    /// A single trial call is allowed to probe the endpoint
+    HalfOpen,
+}
+
+/// Snapshot of a circuit breaker's metrics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakerMetrics {
+    /// Endpoint the breaker guards
+    pub endpoint: String,
+    /// Current state
+    pub state: BreakerState,
+    /// Current run of consecutive failures
+    pub consecutive_failures: u32,
+    /// Calls that reached the endpoint
+    pub total_calls: u64,
+    /// Calls that failed with a retryable error
+    pub total_failures: u64,
+    /// Calls rejected while the breaker was open
+    pub rejected_calls: u64,
+    /// Number of times the breaker has opened
+    pub times_opened: u64,
+}
+
+#[derive(Debug)]
+struct BreakerInner {
+    state: BreakerState,
+    opened_at: Option<Instant>,
+    trial_in_flight: bool,
+    consecutive_failures: u32,
+    total_calls: u64,
+    total_failures: u64,
+    rejected_calls: u64,
+    times_opened: u64,
+}
+
+/// Circuit breaker for a single endpoint
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    endpoint: String,
+    config: CircuitBreakerConfig,
+    inner: Mutex<BreakerInner>,
+}
+
+impl CircuitBreaker {
+    /// Create a new circuit breaker
+    pub fn new(endpoint: &str, config: CircuitBreakerConfig) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            config,
+            inner: Mutex::new(BreakerInner {
+                state: BreakerState::Closed,
+                opened_at: None,
+                trial_in_flight: false,
+                consecutive_failures: 0,
+                total_calls: 0,
+                total_failures: 0,
+                rejected_calls: 0,
+                times_opened: 0,
+            }),
+        }
+    }
+
+    /// Get the shared breaker for an endpoint, creating it on first use
+    pub fn for_endpoint(endpoint: &str) -> Arc<CircuitBreaker> {
+        let mut breakers = registry().lock().unwrap();
+        breakers
+            .entry(endpoint.to_string())
+            .or_insert_with(|| Arc::new(CircuitBreaker::new(endpoint, CircuitBreakerConfig::default())))
+            .clone()
+    }
+
+    /// Get the endpoint this breaker guards
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Get the current state
+    pub fn state(&self) -> BreakerState {
+        self.inner.lock().unwrap().state
+    }
+
+    /// Check whether a call may proceed, moving an expired open breaker to half-open
+    pub fn acquire(&self) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.state == BreakerState::Open {
+            let expired = match inner.opened_at {
+                Some(at) => at.elapsed() >= self.config.open_duration,
+                None => true,
+            };
+            if expired {
+                inner.state = BreakerState::HalfOpen;
+                inner.trial_in_flight = false;
+            }
+        }
+
+        let allowed = match inner.state {
+            BreakerState::Closed => true,
+            BreakerState::Open => false,
+            BreakerState::HalfOpen => !std::mem::replace(&mut inner.trial_in_flight, true),
+        };
+
+        if !allowed {
+            inner.rejected_calls += 1;
+            return Err(Error::Network(format!("Circuit breaker open for {}", self.endpoint)));
+        }
+
+        inner.total_calls += 1;
+        Ok(())
+    }
+
+    /// Record a successful call
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.state = BreakerState::Closed;
+        inner.opened_at = None;
+        inner.trial_in_flight = false;
+        inner.consecutive_failures = 0;
+    }
+
+    /// Record a failed call
+    pub fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.total_failures += 1;
+        inner.consecutive_failures += 1;
+        inner.trial_in_flight = false;
+
+        let trip = inner.state == BreakerState::HalfOpen
+            || inner.consecutive_failures >= self.config.failure_threshold;
+        if trip && inner.state != BreakerState::Open {
+            inner.state = BreakerState::Open;
+            inner.opened_at = Some(Instant::now());
+            inner.times_opened += 1;
+        }
+    }
+
+    /// Get a snapshot of this breaker's metrics
+    pub fn metrics(&self) -> BreakerMetrics {
+        let inner = self.inner.lock().unwrap();
+        BreakerMetrics {
+            endpoint: self.endpoint.clone(),
+            state: inner.state,
+            consecutive_failures: inner.consecutive_failures,
+            total_calls: inner.total_calls,
+            total_failures: inner.total_failures,
+            rejected_calls: inner.rejected_calls,
+            times_opened: inner.times_opened,
+        }
+    }
+}
+
+fn registry() -> &'static Mutex<HashMap<String, Arc<CircuitBreaker>>> {
+    static BREAKERS: OnceLock<Mutex<HashMap<String, Arc<CircuitBreaker>>>> = OnceLock::new();
+    BREAKERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Get metrics for every endpoint breaker created so far
+pub fn breaker_metrics() -> Vec<BreakerMetrics> {
+    let breakers = registry().lock().unwrap();
+    let mut metrics: Vec<BreakerMetrics> = breakers.values().map(|b| b.metrics()).collect();
+    metrics.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+    metrics
+}
+
+/// Run a call through a circuit breaker, retrying retryable failures
+///
+/// The calling thread sleeps between retries, so async code should run
+/// wrapped providers on a blocking thread (e.g. `tokio::task::spawn_blocking`).
+pub fn call_with_retry<T>(breaker: &CircuitBreaker, policy: &RetryPolicy, mut call: impl FnMut() -> Result<T>) -> Result<T> {
+    let started = Instant::now();
+    let mut retry = 0;
+
+    loop {
+        breaker.acquire()?;
+
+        let error = match call() {
+            Ok(value) => {
+                breaker.record_success();
+                return Ok(value);
+            }
+            Err(e) if !e.is_retryable() => {
+                // The endpoint answered; the request itself was bad
+                breaker.record_success();
+                return Err(e);
+            }
+            Err(e) => e,
+        };
+
+        breaker.record_failure();
+        retry += 1;
+        if retry > policy.max_retries {
+            return Err(error);
+        }
+
+        let delay = policy.delay(retry);
+        if let Some(budget) = policy.timeout_budget {
+            if started.elapsed() + delay > budget {
+                return Err(Error::Network(format!("Timeout budget exhausted after {} attempts: {}", retry, error)));
+            }
+        }
+        std::thread::sleep(delay);
+    }
+}
+
+/// A provider wrapper applying retries and a circuit breaker to every RPC call
+pub struct ResilientProvider<P> {
+    /// Wrapped provider
+    inner: P,
+    /// Retry policy
+    policy: RetryPolicy,
+    /// Breaker for the provider's endpoint
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl<P: TransactionManager> ResilientProvider<P> {
+    /// Wrap a provider using the shared breaker for its endpoint
+    pub fn new(inner: P, endpoint: &str, policy: RetryPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            breaker: CircuitBreaker::for_endpoint(endpoint),
+        }
+    }
+
+    /// Wrap a provider with a dedicated circuit breaker
+    pub fn with_breaker(inner: P, breaker: Arc<CircuitBreaker>, policy: RetryPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            breaker,
+        }
+    }
+
+    /// Get the wrapped provider
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Get the circuit breaker guarding this provider
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    fn call<T>(&self, call: impl FnMut() -> Result<T>) -> Result<T> {
+        call_with_retry(&self.breaker, &self.policy, call)
+    }
+}
+
+impl<P: TransactionManager> TransactionSigner for ResilientProvider<P> {
+    fn sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        // Signing is local; there is nothing to retry
+        self.inner.sign_transaction(request)
+    }
+}
+
+impl<P: TransactionManager> TransactionBroadcaster for ResilientProvider<P> {
+    fn broadcast_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+        // Rebroadcasting the same signed bytes is idempotent
+        self.call(|| self.inner.broadcast_transaction(signed_transaction))
+    }
+
+    fn get_transaction_status(&self, hash: &str) -> Result<TransactionStatus> {
+        self.call(|| self.inner.get_transaction_status(hash))
+    }
+
+    fn get_transaction_receipt(&self, hash: &str) -> Result<TransactionReceipt> {
+        self.call(|| self.inner.get_transaction_receipt(hash))
+    }
+}
+
+impl<P: TransactionManager> TransactionManager for ResilientProvider<P> {
+    fn create_and_sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        self.inner.create_and_sign_transaction(request)
+    }
+
+    fn get_transaction(&self, hash: &str) -> Result<Transaction> {
+        self.call(|| self.inner.get_transaction(hash))
+    }
+
+    fn get_transactions(&self, address: &str, limit: usize, offset: usize) -> Result<Vec<Transaction>> {
+        self.call(|| self.inner.get_transactions(address, limit, offset))
+    }
+
+    fn batch_call(&self, calls: &[CallRequest]) -> Result<Vec<CallResult>> {
+        self.call(|| self.inner.batch_call(calls))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyType;
+    use crate::transaction::mock::{MockEvmProvider, MockFault};
+    use crate::transaction::NetworkBinding;
+
+    fn fast_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+            jitter: 0.5,
+            timeout_budget: None,
+        }
+    }
+
+    fn funded_provider() -> MockEvmProvider {
+        let provider = MockEvmProvider::new(1);
+        provider.set_balance("0xfrom", 10u128.pow(18));
+        provider
+    }
+
+    fn request() -> TransactionRequest {
+        TransactionRequest {
+            key_type: KeyType::Ethereum,
+            network: NetworkBinding::Evm { chain_id: 1 },
+            from: "0xfrom".to_string(),
+            to: "0xto".to_string(),
+            value: "1".to_string(),
+            gas_price: None,
+            gas_limit: None,
+            nonce: None,
+            data: None,
+            destination_tag: None,
+            memo: None,
+        }
+    }
+
+    #[test]
+    fn test_retries_transient_failures() {
+        let breaker = Arc::new(CircuitBreaker::new("test-retries", CircuitBreakerConfig::default()));
+        let provider = ResilientProvider::with_breaker(funded_provider(), breaker, fast_policy(3));
+        let signed = provider.sign_transaction(&request()).unwrap();
+
+        // The first broadcast attempt times out and the second hits the RPC error
+        provider.inner().inject_fault(MockFault::Timeout);
+        provider.inner().inject_fault(MockFault::RpcError("busy".to_string()));
+        assert!(provider.broadcast_transaction(&signed).is_ok());
+        assert_eq!(provider.breaker().metrics().total_failures, 2);
+        assert_eq!(provider.breaker().state(), BreakerState::Closed);
+    }
+
+    #[test]
+    fn test_does_not_retry_permanent_failures() {
+        let breaker = Arc::new(CircuitBreaker::new("test-permanent", CircuitBreakerConfig::default()));
+        let provider = ResilientProvider::with_breaker(MockEvmProvider::new(1), breaker, fast_policy(3));
+
+        // Unfunded sender: rejected once, not retried
+        assert!(matches!(provider.send_transaction(&request()), Err(Error::Transaction(_))));
+        assert_eq!(provider.breaker().metrics().total_calls, 1);
+    }
+
+    #[test]
+    fn test_breaker_opens_and_recovers() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 2,
+            open_duration: Duration::from_millis(20),
+        };
+        let breaker = CircuitBreaker::new("test-breaker", config);
+
+        breaker.acquire().unwrap();
+        breaker.record_failure();
+        breaker.acquire().unwrap();
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(breaker.acquire().is_err());
+
+        std::thread::sleep(Duration::from_millis(25));
+
+        // One trial call is allowed while half-open
+        breaker.acquire().unwrap();
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(breaker.acquire().is_err());
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+
+        let metrics = breaker.metrics();
+        assert_eq!(metrics.times_opened, 1);
+        assert_eq!(metrics.rejected_calls, 2);
+    }
+
+    #[test]
+    fn test_backoff_is_bounded() {
+        let policy = RetryPolicy {
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        };
+
+        assert_eq!(policy.delay(1), Duration::from_millis(200));
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+        assert_eq!(policy.delay(20), policy.max_delay);
+    }
+}
@@ -0,0 +1,1004 @@
+//! Scheduled and recurring transactions
+//!
+//! This module stores user-defined recurring transfers and dollar-cost
+//! averaging (DCA) swap orders, works out when each is next due from a cron
+//! expression or fixed interval, and executes due orders through the
+//! transaction pipeline after policy checks. Upcoming, executed and failed
+//! runs are reported through a [`ScheduleNotifier`]. Orders and run records
+//! are kept in a [`ScheduleStore`], so schedules and daily limits survive a
+//! restart.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Serialize, Deserialize};
+
+use crate::caip::AssetId;
+use crate::defi::SwapRequest;
+use crate::error::{Error, Result};
+use crate::time::civil_from_days;
+use super::provider::ProviderConfig;
+use super::types::{TransactionManager, TransactionRequest};
+
+/// Number of minutes searched for the next cron match (a little over four years)
+const CRON_SEARCH_MINUTES: u64 = 4 * 366 * 24 * 60;
+
+/// Seconds run records are kept; at least a day so daily limits see every run
+pub const RUN_RETENTION: u64 = 30 * 86_400;
+
+/// A five-field cron expression (`minute hour day-of-month month day-of-week`)
+///
+/// Fields accept `*`, numbers, ranges (`1-5`), lists (`1,15`) and steps
+/// (`*/15`, `0-30/10`). Day-of-week runs from 0 (Sunday) to 6; 7 is also
+/// Sunday. As in Vixie cron, when both day fields are restricted a day
+/// matching either one is due. All times are UTC.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    day_of_month_restricted: bool,
+    day_of_week_restricted: bool,
+}
+
+impl CronSchedule {
+    /// Parse a cron expression
+    pub fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(Error::InvalidInput(format!("Cron expression must have 5 fields: {}", expression)));
+        }
+
+        let mut days_of_week = parse_cron_field(fields[4], 0, 7)?;
+        // Fold 7 onto Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(Self {
+            expression: fields.join(" "),
+            minutes: parse_cron_field(fields[0], 0, 59)?,
+            hours: parse_cron_field(fields[1], 0, 23)?,
+            days_of_month: parse_cron_field(fields[2], 1, 31)?,
+            months: parse_cron_field(fields[3], 1, 12)?,
+            days_of_week,
+            day_of_month_restricted: fields[2] != "*",
+            day_of_week_restricted: fields[4] != "*",
+        })
+    }
+
+    /// Get the expression
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// Get the first matching time strictly after `timestamp`
+    pub fn next_after(&self, timestamp: u64) -> Option<u64> {
+        let mut minute = timestamp / 60 + 1;
+        let end = minute + CRON_SEARCH_MINUTES;
+
+        while minute < end {
+            let days = minute / 1440;
+            if !self.matches_day(days) {
+                minute = (days + 1) * 1440;
+                continue;
+            }
+
+            let hour = minute % 1440 / 60;
+            if self.hours & (1 << hour) == 0 {
+                minute = (minute / 60 + 1) * 60;
+                continue;
+            }
+
+            if self.minutes & (1 << (minute % 60)) != 0 {
+                return Some(minute * 60);
+            }
+            minute += 1;
+        }
+
+        None
+    }
+
+    fn matches_day(&self, days: u64) -> bool {
+        let (_, month, day) = civil_from_days(days as i64);
+        if self.months & (1 << month) == 0 {
+            return false;
+        }
+
+        // 1970-01-01 was a Thursday
+        let weekday = (days + 4) % 7;
+        let day_of_month = self.days_of_month & (1 << day) != 0;
+        let day_of_week = self.days_of_week & (1 << weekday) != 0;
+
+        match (self.day_of_month_restricted, self.day_of_week_restricted) {
+            (true, true) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+}
+
+impl TryFrom<String> for CronSchedule {
+    type Error = Error;
+
+    fn try_from(expression: String) -> Result<Self> {
+        Self::parse(&expression)
+    }
+}
+
+impl From<CronSchedule> for String {
+    fn from(schedule: CronSchedule) -> Self {
+        schedule.expression
+    }
+}
+
+/// Parse a cron field into a bit set of allowed values
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let invalid = || Error::InvalidInput(format!("Invalid cron field: {}", field));
+    let mut bits = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (start.parse::<u32>().map_err(|_| invalid())?, end.parse::<u32>().map_err(|_| invalid())?)
+        } else {
+            let value = range.parse::<u32>().map_err(|_| invalid())?;
+            // `5/15` means "from 5 to the maximum, every 15"
+            (value, if part.contains('/') { max } else { value })
+        };
+
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+/// When a scheduled order runs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Schedule {
+    /// Run once at a fixed time
+    Once { at: u64 },
+    /// Run every `seconds` starting at `start`
+    Interval { start: u64, seconds: u64 },
+    /// Run whenever a cron expression matches
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    /// Get the first run time strictly after `timestamp`
+    pub fn next_after(&self, timestamp: u64) -> Option<u64> {
+        match self {
+            Self::Once { at } => (*at > timestamp).then_some(*at),
+            Self::Interval { start, seconds } => {
+                if timestamp < *start {
+                    Some(*start)
+                } else {
+                    let elapsed = ((timestamp - start).checked_div(*seconds)? + 1).checked_mul(*seconds)?;
+                    start.checked_add(elapsed)
+                }
+            }
+            Self::Cron(cron) => cron.next_after(timestamp),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self {
+            Self::Interval { seconds, .. } if *seconds < 60 => {
+                Err(Error::InvalidInput("Schedule interval must be at least 60 seconds".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// What a scheduled order does
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum OrderKind {
+    /// Recurring transfer; the nonce is left empty so each run fetches a fresh one
+    Transfer(TransactionRequest),
+    /// Dollar-cost averaging swap
+    Dca(SwapRequest),
+}
+
+impl OrderKind {
+    /// Get the amount spent per run, in the smallest unit
+    pub fn amount(&self) -> Result<u128> {
+        let amount = match self {
+            Self::Transfer(request) => &request.value,
+            Self::Dca(request) => &request.from.amount,
+        };
+
+        amount.parse::<u128>()
+            .map_err(|_| Error::InvalidInput(format!("Invalid amount: {}", amount)))
+    }
+
+    /// Get the asset spent, which daily limits are counted per
+    ///
+    /// Transfers spend the native asset of their network, named by its
+    /// CAIP-19 ID when the chain's coin type is known. Swap requests do not name the chain, so DCA orders name
+    /// the input token by key type and address.
+    pub fn asset(&self) -> Result<String> {
+        match self {
+            Self::Transfer(request) => {
+                let chain_id = request.network.chain_id()?;
+                Ok(AssetId::native(chain_id.clone()).map_or_else(|_| format!("{}/native", chain_id), |asset| asset.to_string()))
+            }
+            Self::Dca(request) => Ok(format!("{:?}/{}", request.from.token.key_type, request.from.token.address)),
+        }
+    }
+
+    /// Get the destination address, if the order sends funds to one
+    pub fn destination(&self) -> Option<&str> {
+        match self {
+            Self::Transfer(request) => Some(&request.to),
+            Self::Dca(_) => None,
+        }
+    }
+}
+
+/// A stored scheduled order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledOrder {
+    /// Order ID
+    pub id: String,
+    /// Owner (wallet or user ID)
+    pub owner: String,
+    /// What the order does
+    pub kind: OrderKind,
+    /// When the order runs
+    pub schedule: Schedule,
+    /// Next run time, or `None` once the schedule is exhausted
+    pub next_run: Option<u64>,
+    /// Whether the order is active
+    pub enabled: bool,
+    /// Number of consecutive failed runs
+    pub consecutive_failures: u32,
+    /// Run time the owner was last told is upcoming, so each run is announced once
+    #[serde(default)]
+    pub notified_run: Option<u64>,
+    /// Unix timestamp of creation
+    pub created_at: u64,
+}
+
+/// Outcome of a scheduled run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RunOutcome {
+    /// The order was executed
+    Executed { transaction_hash: String },
+    /// A policy check blocked the run
+    Blocked { reason: String },
+    /// Execution failed
+    Failed { error: String },
+}
+
+/// Record of a scheduled run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// Order ID
+    pub order_id: String,
+    /// Owner of the order
+    pub owner: String,
+    /// Asset spent (see [`OrderKind::asset`])
+    pub asset: String,
+    /// Amount spent by the run, in the smallest unit
+    pub amount: String,
+    /// Time the run was due
+    pub scheduled_at: u64,
+    /// Time the run happened
+    pub executed_at: u64,
+    /// Outcome
+    pub outcome: RunOutcome,
+}
+
+/// Limits applied to every scheduled run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulePolicy {
+    /// Maximum amount per run, in the smallest unit
+    pub max_amount_per_run: Option<u128>,
+    /// Maximum amount per owner and asset over a rolling 24 hours, in the smallest unit
+    pub max_amount_per_day: Option<u128>,
+    /// Destinations transfers may be sent to (any if `None`)
+    pub allowed_destinations: Option<Vec<String>>,
+    /// Consecutive failures after which an order is paused
+    pub max_consecutive_failures: u32,
+}
+
+impl Default for SchedulePolicy {
+    fn default() -> Self {
+        Self {
+            max_amount_per_run: None,
+            max_amount_per_day: None,
+            allowed_destinations: None,
+            max_consecutive_failures: 3,
+        }
+    }
+}
+
+/// A notification about a scheduled order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScheduleNotification {
+    /// An order will run soon
+    Upcoming { order_id: String, owner: String, run_at: u64 },
+    /// An order ran
+    Executed { order_id: String, owner: String, transaction_hash: String },
+    /// An order was blocked by policy or failed
+    Failed { order_id: String, owner: String, reason: String },
+    /// An order was paused after repeated failures
+    Paused { order_id: String, owner: String },
+}
+
+/// Receiver of schedule notifications
+pub trait ScheduleNotifier {
+    /// Deliver a notification
+    fn notify(&self, notification: ScheduleNotification);
+}
+
+/// Executes scheduled orders
+pub trait OrderExecutor {
+    /// Check an order may run, after the scheduler's own policy; an error blocks the run
+    fn check(&self, _order: &ScheduledOrder) -> Result<()> {
+        Ok(())
+    }
+
+    /// Execute an order, returning the transaction hash
+    fn execute(&self, kind: &OrderKind) -> Result<String>;
+}
+
+/// Executes orders through a transaction manager and the DeFi swap pipeline
+pub struct PipelineExecutor<'a> {
+    /// Transaction manager for transfers
+    transactions: &'a dyn TransactionManager,
+    /// Provider configuration for swaps
+    config: &'a ProviderConfig,
+}
+
+impl<'a> PipelineExecutor<'a> {
+    /// Create a pipeline executor
+    pub fn new(transactions: &'a dyn TransactionManager, config: &'a ProviderConfig) -> Self {
+        Self { transactions, config }
+    }
+}
+
+impl OrderExecutor for PipelineExecutor<'_> {
+    fn execute(&self, kind: &OrderKind) -> Result<String> {
+        match kind {
+            OrderKind::Transfer(request) => self.transactions.send_transaction(request),
+            OrderKind::Dca(request) => Ok(crate::defi::swap_tokens(request, self.config)?.transaction_hash),
+        }
+    }
+}
+
+/// Scheduled orders and run records, as stored
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScheduleSnapshot {
+    /// Orders, oldest first
+    pub orders: Vec<ScheduledOrder>,
+    /// Run records within [`RUN_RETENTION`], oldest first
+    pub runs: Vec<RunRecord>,
+}
+
+/// Storage of a scheduler's orders and run records
+pub trait ScheduleStore: Send + Sync {
+    /// Load the stored orders and runs
+    fn load(&self) -> Result<ScheduleSnapshot>;
+
+    /// Replace the stored orders and runs
+    fn save(&self, snapshot: &ScheduleSnapshot) -> Result<()>;
+}
+
+/// In-memory schedule store, lost on restart
+#[derive(Default)]
+pub struct InMemoryScheduleStore {
+    snapshot: Mutex<ScheduleSnapshot>,
+}
+
+impl InMemoryScheduleStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ScheduleStore for InMemoryScheduleStore {
+    fn load(&self) -> Result<ScheduleSnapshot> {
+        Ok(self.snapshot.lock().unwrap().clone())
+    }
+
+    fn save(&self, snapshot: &ScheduleSnapshot) -> Result<()> {
+        *self.snapshot.lock().unwrap() = snapshot.clone();
+        Ok(())
+    }
+}
+
+/// Schedule store backed by a JSON file, replaced atomically on every save
+pub struct FileScheduleStore {
+    path: PathBuf,
+}
+
+impl FileScheduleStore {
+    /// Open a schedule file, creating it on the first save
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl ScheduleStore for FileScheduleStore {
+    fn load(&self) -> Result<ScheduleSnapshot> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(ScheduleSnapshot::default()),
+            Err(e) => return Err(Error::Provider(format!("Failed to read schedules {}: {}", self.path.display(), e))),
+        };
+        serde_json::from_str(&contents)
+            .map_err(|e| Error::Serialization(format!("Invalid schedules {}: {}", self.path.display(), e)))
+    }
+
+    fn save(&self, snapshot: &ScheduleSnapshot) -> Result<()> {
+        let json = serde_json::to_string_pretty(snapshot)
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        let temp = self.path.with_extension("tmp");
+        std::fs::write(&temp, json)
+            .and_then(|_| std::fs::rename(&temp, &self.path))
+            .map_err(|e| Error::Provider(format!("Failed to write schedules {}: {}", self.path.display(), e)))
+    }
+}
+
+/// Stores scheduled orders and runs them when due
+pub struct Scheduler {
+    policy: SchedulePolicy,
+    store: Box<dyn ScheduleStore>,
+    orders: Mutex<HashMap<String, ScheduledOrder>>,
+    runs: Mutex<Vec<RunRecord>>,
+}
+
+impl Scheduler {
+    /// Create a scheduler that keeps its orders in memory
+    pub fn new(policy: SchedulePolicy) -> Self {
+        Self {
+            policy,
+            store: Box::new(InMemoryScheduleStore::new()),
+            orders: Mutex::new(HashMap::new()),
+            runs: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Create a scheduler backed by a store, loading the orders and runs it holds
+    pub fn with_store(policy: SchedulePolicy, store: Box<dyn ScheduleStore>) -> Result<Self> {
+        let snapshot = store.load()?;
+        Ok(Self {
+            policy,
+            store,
+            orders: Mutex::new(snapshot.orders.into_iter().map(|order| (order.id.clone(), order)).collect()),
+            runs: Mutex::new(snapshot.runs),
+        })
+    }
+
+    /// Add an order
+    pub fn add_order(&self, owner: &str, kind: OrderKind, schedule: Schedule, now: u64) -> Result<ScheduledOrder> {
+        schedule.validate()?;
+        kind.amount()?;
+
+        if let OrderKind::Transfer(request) = &kind {
+            if request.nonce.is_some() {
+                return Err(Error::InvalidInput("Recurring transfers must not pin a nonce".to_string()));
+            }
+        }
+
+        let next_run = schedule.next_after(now)
+            .ok_or_else(|| Error::InvalidInput("Schedule never runs".to_string()))?;
+
+        let order = ScheduledOrder {
+            id: format!("order_{}", hex::encode(rand::random::<[u8; 8]>())),
+            owner: owner.to_string(),
+            kind,
+            schedule,
+            next_run: Some(next_run),
+            enabled: true,
+            consecutive_failures: 0,
+            notified_run: None,
+            created_at: now,
+        };
+
+        self.orders.lock().unwrap().insert(order.id.clone(), order.clone());
+        if let Err(e) = self.persist() {
+            self.orders.lock().unwrap().remove(&order.id);
+            return Err(e);
+        }
+        Ok(order)
+    }
+
+    /// Get an order
+    pub fn order(&self, id: &str) -> Option<ScheduledOrder> {
+        self.orders.lock().unwrap().get(id).cloned()
+    }
+
+    /// Get the orders of an owner
+    pub fn orders(&self, owner: &str) -> Vec<ScheduledOrder> {
+        let mut orders: Vec<ScheduledOrder> = self.orders.lock().unwrap()
+            .values()
+            .filter(|order| order.owner == owner)
+            .cloned()
+            .collect();
+        orders.sort_by_key(|order| order.created_at);
+        orders
+    }
+
+    /// Cancel an order
+    pub fn cancel(&self, id: &str) -> Result<()> {
+        self.orders.lock().unwrap().remove(id)
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown scheduled order: {}", id)))?;
+        self.persist()
+    }
+
+    /// Pause or resume an order
+    ///
+    /// Resuming resets the failure count and skips runs missed while paused.
+    pub fn set_enabled(&self, id: &str, enabled: bool, now: u64) -> Result<()> {
+        {
+            let mut orders = self.orders.lock().unwrap();
+            let order = orders.get_mut(id)
+                .ok_or_else(|| Error::InvalidInput(format!("Unknown scheduled order: {}", id)))?;
+
+            if enabled && !order.enabled {
+                order.consecutive_failures = 0;
+                order.next_run = order.schedule.next_after(now);
+            }
+            order.enabled = enabled;
+        }
+        self.persist()
+    }
+
+    /// Get the runs of an order, oldest first
+    pub fn runs(&self, order_id: &str) -> Vec<RunRecord> {
+        self.runs.lock().unwrap().iter()
+            .filter(|run| run.order_id == order_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Get active orders due within `horizon` seconds of `now`, soonest first
+    pub fn upcoming(&self, now: u64, horizon: u64) -> Vec<ScheduledOrder> {
+        let mut orders: Vec<ScheduledOrder> = self.orders.lock().unwrap()
+            .values()
+            .filter(|order| order.enabled && order.next_run.is_some_and(|run| run <= now + horizon))
+            .cloned()
+            .collect();
+        orders.sort_by_key(|order| order.next_run);
+        orders
+    }
+
+    /// Notify owners of orders due within `horizon` seconds
+    ///
+    /// Each run is announced once, however often this is called.
+    pub fn notify_upcoming(&self, now: u64, horizon: u64, notifier: &dyn ScheduleNotifier) -> Result<()> {
+        let mut announced = Vec::new();
+        {
+            let mut orders = self.orders.lock().unwrap();
+            for order in orders.values_mut() {
+                let Some(run_at) = order.next_run.filter(|run| *run > now && *run <= now + horizon) else { continue };
+                if order.enabled && order.notified_run != Some(run_at) {
+                    order.notified_run = Some(run_at);
+                    announced.push(ScheduleNotification::Upcoming { order_id: order.id.clone(), owner: order.owner.clone(), run_at });
+                }
+            }
+        }
+
+        if announced.is_empty() {
+            return Ok(());
+        }
+        self.persist()?;
+        for notification in announced {
+            notifier.notify(notification);
+        }
+        Ok(())
+    }
+
+    /// Run every order that is due at `now`
+    ///
+    /// Each due order runs at most once per call; runs missed while the
+    /// scheduler was down are not replayed. Records older than
+    /// [`RUN_RETENTION`] are dropped. The store is updated after every run,
+    /// and a failure to update it stops the remaining runs so a restart does
+    /// not repeat them.
+    pub fn run_due(&self, now: u64, executor: &dyn OrderExecutor, notifier: &dyn ScheduleNotifier) -> Result<Vec<RunRecord>> {
+        let due: Vec<ScheduledOrder> = self.orders.lock().unwrap()
+            .values()
+            .filter(|order| order.enabled && order.next_run.is_some_and(|run| run <= now))
+            .cloned()
+            .collect();
+
+        let mut records = Vec::new();
+        for order in due {
+            let outcome = match self.check_policy(&order, now).and_then(|_| executor.check(&order)) {
+                Err(e) => RunOutcome::Blocked { reason: e.to_string() },
+                Ok(()) => match executor.execute(&order.kind) {
+                    Ok(transaction_hash) => RunOutcome::Executed { transaction_hash },
+                    Err(e) => RunOutcome::Failed { error: e.to_string() },
+                },
+            };
+
+            let record = RunRecord {
+                order_id: order.id.clone(),
+                owner: order.owner.clone(),
+                asset: order.kind.asset().unwrap_or_default(),
+                amount: order.kind.amount().unwrap_or_default().to_string(),
+                scheduled_at: order.next_run.unwrap_or(now),
+                executed_at: now,
+                outcome,
+            };
+
+            self.runs.lock().unwrap().push(record.clone());
+            self.finish_run(&order, &record, now, notifier)?;
+            records.push(record);
+        }
+
+        self.runs.lock().unwrap().retain(|run| run.executed_at.saturating_add(RUN_RETENTION) > now);
+        self.persist()?;
+        Ok(records)
+    }
+
+    /// Write the orders and runs to the store
+    fn persist(&self) -> Result<()> {
+        let orders = self.orders.lock().unwrap();
+        let runs = self.runs.lock().unwrap();
+        let mut snapshot = ScheduleSnapshot {
+            orders: orders.values().cloned().collect(),
+            runs: runs.clone(),
+        };
+        snapshot.orders.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        // Held until saved, so a concurrent save cannot overwrite this one with older state
+        self.store.save(&snapshot)
+    }
+
+    fn check_policy(&self, order: &ScheduledOrder, now: u64) -> Result<()> {
+        let amount = order.kind.amount()?;
+
+        if let Some(max) = self.policy.max_amount_per_run {
+            if amount > max {
+                return Err(Error::InvalidInput(format!("Amount {} exceeds the per-run limit of {}", amount, max)));
+            }
+        }
+
+        if let (Some(allowed), Some(destination)) = (&self.policy.allowed_destinations, order.kind.destination()) {
+            if !allowed.iter().any(|address| address.eq_ignore_ascii_case(destination)) {
+                return Err(Error::InvalidInput(format!("Destination {} is not allowed", destination)));
+            }
+        }
+
+        if let Some(max) = self.policy.max_amount_per_day {
+            let spent = self.spent_since(&order.owner, &order.kind.asset()?, now.saturating_sub(86_400));
+            if spent.saturating_add(amount) > max {
+                return Err(Error::InvalidInput(format!("Amount {} exceeds the remaining daily limit of {}", amount, max.saturating_sub(spent))));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Total amount of an asset an owner's runs spent since `since`
+    ///
+    /// Runs carry their own owner and amount, so cancelling an order does
+    /// not free up the limit its runs used.
+    fn spent_since(&self, owner: &str, asset: &str, since: u64) -> u128 {
+        self.runs.lock().unwrap().iter()
+            .filter(|run| run.executed_at > since && matches!(run.outcome, RunOutcome::Executed { .. }))
+            .filter(|run| run.owner == owner && run.asset == asset)
+            .filter_map(|run| run.amount.parse::<u128>().ok())
+            .fold(0, u128::saturating_add)
+    }
+
+    fn finish_run(&self, order: &ScheduledOrder, record: &RunRecord, now: u64, notifier: &dyn ScheduleNotifier) -> Result<()> {
+        let mut paused = false;
+
+        {
+            let mut orders = self.orders.lock().unwrap();
+            let Some(stored) = orders.get_mut(&order.id) else {
+                return Ok(());
+            };
+
+            stored.next_run = stored.schedule.next_after(now);
+            match record.outcome {
+                RunOutcome::Executed { .. } => stored.consecutive_failures = 0,
+                _ => {
+                    stored.consecutive_failures += 1;
+                    if stored.consecutive_failures >= self.policy.max_consecutive_failures {
+                        stored.enabled = false;
+                        paused = true;
+                    }
+                }
+            }
+        }
+
+        let (order_id, owner) = (order.id.clone(), order.owner.clone());
+        notifier.notify(match &record.outcome {
+            RunOutcome::Executed { transaction_hash } => ScheduleNotification::Executed {
+                order_id: order_id.clone(),
+                owner: owner.clone(),
+                transaction_hash: transaction_hash.clone(),
+            },
+            RunOutcome::Blocked { reason } | RunOutcome::Failed { error: reason } => ScheduleNotification::Failed {
+                order_id: order_id.clone(),
+                owner: owner.clone(),
+                reason: reason.clone(),
+            },
+        });
+
+        if paused {
+            notifier.notify(ScheduleNotification::Paused { order_id, owner });
+        }
+        self.persist()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::KeyType;
+    use crate::transaction::NetworkBinding;
+
+    /// 2023-11-14T22:13:20Z, a Tuesday
+    const NOW: u64 = 1_700_000_000;
+
+    struct Recorder(Mutex<Vec<ScheduleNotification>>);
+
+    impl ScheduleNotifier for Recorder {
+        fn notify(&self, notification: ScheduleNotification) {
+            self.0.lock().unwrap().push(notification);
+        }
+    }
+
+    struct Executor(bool);
+
+    impl OrderExecutor for Executor {
+        fn execute(&self, _kind: &OrderKind) -> Result<String> {
+            if self.0 {
+                Ok("0xhash".to_string())
+            } else {
+                Err(Error::Network("node unavailable".to_string()))
+            }
+        }
+    }
+
+    fn transfer(to: &str, value: &str) -> OrderKind {
+        OrderKind::Transfer(TransactionRequest {
+            key_type: KeyType::Ethereum,
+            network: NetworkBinding::Evm { chain_id: 1 },
+            from: "0x742d35Cc6634C0532925a3b844Bc454e4438f44e".to_string(),
+            to: to.to_string(),
+            value: value.to_string(),
+            gas_price: None,
+            gas_limit: None,
+            nonce: None,
+            data: None,
+            destination_tag: None,
+            memo: None,
+        })
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        // Every Monday at 09:00
+        let weekly = CronSchedule::parse("0 9 * * 1").unwrap();
+        assert_eq!(weekly.next_after(NOW), Some(1_700_470_800)); // 2023-11-20T09:00:00Z
+
+        // Every 15 minutes
+        let quarterly = CronSchedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(quarterly.next_after(NOW), Some(1_700_000_100)); // 22:15
+
+        // First of the month or any Sunday
+        let either = CronSchedule::parse("0 0 1 * 7").unwrap();
+        assert_eq!(either.next_after(NOW), Some(1_700_352_000)); // Sunday 2023-11-19
+
+        // February 29th only exists in leap years
+        let leap = CronSchedule::parse("0 0 29 2 *").unwrap();
+        assert_eq!(leap.next_after(NOW), Some(1_709_164_800)); // 2024-02-29
+        assert_eq!(CronSchedule::parse("0 0 31 2 *").unwrap().next_after(NOW), None);
+    }
+
+    #[test]
+    fn test_cron_parse_errors() {
+        assert!(CronSchedule::parse("0 9 * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+        assert!(CronSchedule::parse("0,30 9-17 * 1-12 1-5").is_ok());
+    }
+
+    #[test]
+    fn test_interval_schedule() {
+        let schedule = Schedule::Interval { start: NOW, seconds: 3600 };
+        assert_eq!(schedule.next_after(NOW - 1), Some(NOW));
+        assert_eq!(schedule.next_after(NOW), Some(NOW + 3600));
+        assert_eq!(schedule.next_after(NOW + 5000), Some(NOW + 7200));
+
+        assert_eq!(Schedule::Once { at: NOW }.next_after(NOW), None);
+        assert_eq!(Schedule::Interval { start: 0, seconds: u64::MAX / 2 }.next_after(u64::MAX - 1), None);
+    }
+
+    #[test]
+    fn test_run_due_and_notifications() {
+        let scheduler = Scheduler::new(SchedulePolicy::default());
+        let notifier = Recorder(Mutex::new(Vec::new()));
+        let order = scheduler.add_order("alice", transfer("0xbob", "100"), Schedule::Interval { start: NOW + 60, seconds: 3600 }, NOW).unwrap();
+
+        scheduler.notify_upcoming(NOW, 120, &notifier).unwrap();
+        assert!(matches!(notifier.0.lock().unwrap()[0], ScheduleNotification::Upcoming { run_at, .. } if run_at == NOW + 60));
+        // Each run is announced once
+        scheduler.notify_upcoming(NOW + 30, 120, &notifier).unwrap();
+        assert_eq!(notifier.0.lock().unwrap().len(), 1);
+
+        assert!(scheduler.run_due(NOW, &Executor(true), &notifier).unwrap().is_empty());
+
+        let records = scheduler.run_due(NOW + 60, &Executor(true), &notifier).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].outcome, RunOutcome::Executed { transaction_hash: "0xhash".to_string() });
+        assert_eq!(scheduler.order(&order.id).unwrap().next_run, Some(NOW + 3660));
+        assert_eq!(scheduler.runs(&order.id).len(), 1);
+    }
+
+    #[test]
+    fn test_policy_blocks_and_pauses() {
+        let policy = SchedulePolicy {
+            max_amount_per_run: Some(1_000),
+            allowed_destinations: Some(vec!["0xBOB".to_string()]),
+            max_consecutive_failures: 2,
+            ..SchedulePolicy::default()
+        };
+        let scheduler = Scheduler::new(policy);
+        let notifier = Recorder(Mutex::new(Vec::new()));
+
+        let too_large = scheduler.add_order("alice", transfer("0xbob", "5000"), Schedule::Once { at: NOW + 60 }, NOW).unwrap();
+        let not_allowed = scheduler.add_order("alice", transfer("0xcarol", "10"), Schedule::Once { at: NOW + 60 }, NOW).unwrap();
+        scheduler.run_due(NOW + 60, &Executor(true), &notifier).unwrap();
+
+        assert!(matches!(scheduler.runs(&too_large.id)[0].outcome, RunOutcome::Blocked { .. }));
+        assert!(matches!(scheduler.runs(&not_allowed.id)[0].outcome, RunOutcome::Blocked { .. }));
+
+        let failing = scheduler.add_order("alice", transfer("0xbob", "10"), Schedule::Interval { start: NOW, seconds: 60 }, NOW - 60).unwrap();
+        scheduler.run_due(NOW, &Executor(false), &notifier).unwrap();
+        scheduler.run_due(NOW + 60, &Executor(false), &notifier).unwrap();
+
+        assert!(!scheduler.order(&failing.id).unwrap().enabled);
+        assert!(notifier.0.lock().unwrap().iter().any(|n| matches!(n, ScheduleNotification::Paused { order_id, .. } if *order_id == failing.id)));
+        assert!(scheduler.run_due(NOW + 120, &Executor(true), &notifier).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_daily_limit() {
+        let policy = SchedulePolicy {
+            max_amount_per_day: Some(150),
+            ..SchedulePolicy::default()
+        };
+        let scheduler = Scheduler::new(policy);
+        let notifier = Recorder(Mutex::new(Vec::new()));
+        let order = scheduler.add_order("alice", transfer("0xbob", "100"), Schedule::Interval { start: NOW, seconds: 3600 }, NOW - 1).unwrap();
+
+        scheduler.run_due(NOW, &Executor(true), &notifier).unwrap();
+        scheduler.run_due(NOW + 3600, &Executor(true), &notifier).unwrap();
+        scheduler.run_due(NOW + 86_400, &Executor(true), &notifier).unwrap();
+
+        let outcomes: Vec<bool> = scheduler.runs(&order.id).iter()
+            .map(|run| matches!(run.outcome, RunOutcome::Executed { .. }))
+            .collect();
+        assert_eq!(outcomes, vec![true, false, true]);
+    }
+
+    #[test]
+    fn test_daily_limit_survives_cancel_and_is_per_asset() {
+        let policy = SchedulePolicy {
+            max_amount_per_day: Some(150),
+            ..SchedulePolicy::default()
+        };
+        let scheduler = Scheduler::new(policy);
+        let notifier = Recorder(Mutex::new(Vec::new()));
+
+        let first = scheduler.add_order("alice", transfer("0xbob", "100"), Schedule::Once { at: NOW }, NOW - 1).unwrap();
+        scheduler.run_due(NOW, &Executor(true), &notifier).unwrap();
+        scheduler.cancel(&first.id).unwrap();
+
+        // A new order cannot reuse the limit the cancelled one spent
+        let second = scheduler.add_order("alice", transfer("0xbob", "100"), Schedule::Once { at: NOW + 60 }, NOW).unwrap();
+        scheduler.run_due(NOW + 60, &Executor(true), &notifier).unwrap();
+        assert!(matches!(scheduler.runs(&second.id)[0].outcome, RunOutcome::Blocked { .. }));
+
+        // Spending on another chain counts against that chain's limit
+        let mut kind = transfer("0xbob", "100");
+        if let OrderKind::Transfer(request) = &mut kind {
+            request.network = NetworkBinding::Evm { chain_id: 137 };
+        }
+        let polygon = scheduler.add_order("alice", kind, Schedule::Once { at: NOW + 120 }, NOW).unwrap();
+        scheduler.run_due(NOW + 120, &Executor(true), &notifier).unwrap();
+        assert!(matches!(scheduler.runs(&polygon.id)[0].outcome, RunOutcome::Executed { .. }));
+        assert_eq!(scheduler.runs(&polygon.id)[0].asset, "eip155:137/slip44:60");
+
+        // Old records are pruned
+        scheduler.run_due(NOW + RUN_RETENTION + 120, &Executor(true), &notifier).unwrap();
+        assert!(scheduler.runs(&polygon.id).is_empty());
+    }
+
+    #[test]
+    fn test_rejects_pinned_nonce() {
+        let scheduler = Scheduler::new(SchedulePolicy::default());
+        let mut kind = transfer("0xbob", "1");
+        if let OrderKind::Transfer(request) = &mut kind {
+            request.nonce = Some(7);
+        }
+
+        assert!(scheduler.add_order("alice", kind, Schedule::Once { at: NOW + 60 }, NOW).is_err());
+        assert!(scheduler.add_order("alice", transfer("0xbob", "1"), Schedule::Interval { start: NOW, seconds: 10 }, NOW).is_err());
+    }
+
+    #[test]
+    fn test_executor_check_blocks() {
+        struct Screened;
+
+        impl OrderExecutor for Screened {
+            fn check(&self, order: &ScheduledOrder) -> Result<()> {
+                match order.kind.destination() {
+                    Some("0xmallory") => Err(Error::Compliance("Destination is sanctioned".to_string())),
+                    _ => Ok(()),
+                }
+            }
+
+            fn execute(&self, _kind: &OrderKind) -> Result<String> {
+                Ok("0xhash".to_string())
+            }
+        }
+
+        let scheduler = Scheduler::new(SchedulePolicy::default());
+        let notifier = Recorder(Mutex::new(Vec::new()));
+        let blocked = scheduler.add_order("alice", transfer("0xmallory", "10"), Schedule::Once { at: NOW }, NOW - 1).unwrap();
+        let sent = scheduler.add_order("alice", transfer("0xbob", "10"), Schedule::Once { at: NOW }, NOW - 1).unwrap();
+        scheduler.run_due(NOW, &Screened, &notifier).unwrap();
+
+        assert!(matches!(&scheduler.runs(&blocked.id)[0].outcome, RunOutcome::Blocked { reason } if reason.contains("sanctioned")));
+        assert!(matches!(scheduler.runs(&sent.id)[0].outcome, RunOutcome::Executed { .. }));
+    }
+
+    #[test]
+    fn test_file_store() {
+        let path = std::env::temp_dir().join(format!("fo3-schedules-{}.json", hex::encode(rand::random::<[u8; 8]>())));
+        let policy = SchedulePolicy {
+            max_amount_per_day: Some(150),
+            ..SchedulePolicy::default()
+        };
+        let scheduler = Scheduler::with_store(policy.clone(), Box::new(FileScheduleStore::new(&path))).unwrap();
+        let notifier = Recorder(Mutex::new(Vec::new()));
+        let order = scheduler.add_order("alice", transfer("0xbob", "100"), Schedule::Interval { start: NOW, seconds: 3600 }, NOW - 1).unwrap();
+        let paused = scheduler.add_order("alice", transfer("0xbob", "1"), Schedule::Once { at: NOW + 60 }, NOW).unwrap();
+        scheduler.set_enabled(&paused.id, false, NOW).unwrap();
+        scheduler.run_due(NOW, &Executor(true), &notifier).unwrap();
+
+        // A restarted scheduler keeps the schedule, pause and daily spend
+        let reopened = Scheduler::with_store(policy, Box::new(FileScheduleStore::new(&path))).unwrap();
+        assert_eq!(reopened.order(&order.id).unwrap().next_run, Some(NOW + 3600));
+        assert!(!reopened.order(&paused.id).unwrap().enabled);
+        assert_eq!(reopened.runs(&order.id).len(), 1);
+        reopened.run_due(NOW + 3600, &Executor(true), &notifier).unwrap();
+        assert!(matches!(reopened.runs(&order.id)[1].outcome, RunOutcome::Blocked { .. }));
+
+        reopened.cancel(&order.id).unwrap();
+        assert!(Scheduler::with_store(SchedulePolicy::default(), Box::new(FileScheduleStore::new(&path))).unwrap().order(&order.id).is_none());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
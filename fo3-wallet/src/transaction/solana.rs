@@ -2,6 +2,9 @@
 
 use std::str::FromStr;
 use std::sync::Arc;
+#[cfg(feature = "rpc")]
+use std::time::Duration;
+use ed25519_dalek::{Signer, SigningKey};
 use serde::{Serialize, Deserialize};
 
 // Solana imports are commented out due to dependency conflicts
@@ -18,9 +21,272 @@ use serde::{Serialize, Deserialize};
 // use solana_transaction_status::{UiTransactionStatusMeta, EncodedConfirmedTransaction};
 
 use crate::error::{Error, Result};
-use crate::crypto::keys::KeyType;
-use super::types::{Transaction, TransactionRequest, TransactionReceipt, TransactionStatus, TransactionSigner, TransactionBroadcaster, TransactionManager, TransactionType};
+use crate::crypto::keys::{KeyPair, KeyType};
+use super::types::{Memo, Transaction, TransactionRequest, TransactionReceipt, TransactionStatus, TransactionSigner, TransactionBroadcaster, TransactionManager, TransactionType, NetworkBinding};
 use super::provider::{ProviderConfig, ProviderType};
+#[cfg(feature = "rpc")]
+use super::batch::JsonRpcBatch;
+#[cfg(feature = "rpc")]
+use super::ethereum::{block_on, rpc_span, traced};
+#[cfg(feature = "rpc")]
+use super::spl::{self, TokenAccount, TokenProgram};
+
+/// Genesis hash of Solana mainnet-beta
+pub const SOLANA_MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+
+/// Genesis hash of Solana devnet
+pub const SOLANA_DEVNET_GENESIS_HASH: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
+
+/// Genesis hash of Solana testnet
+pub const SOLANA_TESTNET_GENESIS_HASH: &str = "4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY";
+
+/// Program ID of the System program
+pub const SYSTEM_PROGRAM_ID: &str = "11111111111111111111111111111111";
+
+/// Program ID of the SPL Memo program (v2)
+pub const MEMO_PROGRAM_ID: &str = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr";
+
+/// Instruction index of System `Transfer`
+const SYSTEM_TRANSFER_INSTRUCTION: u32 = 2;
+
+/// Account passed to a Solana instruction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountMeta {
+    /// Account address
+    pub pubkey: String,
+    /// Whether the account signs the transaction
+    pub is_signer: bool,
+    /// Whether the instruction writes to the account
+    pub is_writable: bool,
+}
+
+/// A Solana instruction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Instruction {
+    /// Program to invoke
+    pub program_id: String,
+    /// Accounts the program reads or writes
+    pub accounts: Vec<AccountMeta>,
+    /// Instruction data
+    pub data: Vec<u8>,
+}
+
+/// Build a System `Transfer` instruction moving `lamports` from `from` to `to`
+pub fn system_transfer_instruction(from: &str, to: &str, lamports: u64) -> Instruction {
+    let mut data = SYSTEM_TRANSFER_INSTRUCTION.to_le_bytes().to_vec();
+    data.extend(lamports.to_le_bytes());
+
+    Instruction {
+        program_id: SYSTEM_PROGRAM_ID.to_string(),
+        accounts: vec![
+            AccountMeta { pubkey: from.to_string(), is_signer: true, is_writable: true },
+            AccountMeta { pubkey: to.to_string(), is_signer: false, is_writable: true },
+        ],
+        data,
+    }
+}
+
+/// Build a Memo program instruction recording `memo`, signed by `signer`
+pub fn memo_instruction(memo: &Memo, signer: &str) -> Result<Instruction> {
+    // The Memo program rejects data that isn't UTF-8
+    let text = memo.as_text()
+        .ok_or_else(|| Error::InvalidInput("Solana memos must be UTF-8 text".to_string()))?;
+
+    Ok(Instruction {
+        program_id: MEMO_PROGRAM_ID.to_string(),
+        accounts: vec![AccountMeta { pubkey: signer.to_string(), is_signer: true, is_writable: false }],
+        data: text.as_bytes().to_vec(),
+    })
+}
+
+/// Build the instructions of a SOL transfer request: the System transfer, then its memo if any
+pub fn transfer_instructions(request: &TransactionRequest) -> Result<Vec<Instruction>> {
+    let lamports = request.value.parse::<u64>()
+        .map_err(|e| Error::Transaction(format!("Invalid value: {}", e)))?;
+
+    let mut instructions = vec![system_transfer_instruction(&request.from, &request.to, lamports)];
+    if let Some(memo) = &request.memo {
+        memo.check(KeyType::Solana)?;
+        instructions.push(memo_instruction(memo, &request.from)?);
+    }
+    Ok(instructions)
+}
+
+/// Decode a base58 Solana address into its 32 bytes
+pub fn decode_pubkey(address: &str) -> Result<[u8; 32]> {
+    let bytes = bs58::decode(address).into_vec()
+        .map_err(|e| Error::InvalidInput(format!("Invalid Solana address {}: {}", address, e)))?;
+    bytes.try_into()
+        .map_err(|bytes: Vec<u8>| Error::InvalidInput(format!("Invalid Solana address {}: {} bytes", address, bytes.len())))
+}
+
+/// Whether 32 bytes are a point on the ed25519 curve, i.e. a key with a private key
+pub fn is_on_curve(pubkey: &[u8; 32]) -> bool {
+    ed25519_dalek::VerifyingKey::from_bytes(pubkey).is_ok()
+}
+
+/// Find the program derived address of `seeds` under `program_id`, with its bump seed
+pub fn find_program_address(seeds: &[&[u8]], program_id: &str) -> Result<(String, u8)> {
+    use sha2::{Digest, Sha256};
+
+    let program = decode_pubkey(program_id)?;
+    for bump in (0..=u8::MAX).rev() {
+        let mut hasher = Sha256::new();
+        for seed in seeds {
+            hasher.update(seed);
+        }
+        hasher.update([bump]);
+        hasher.update(program);
+        hasher.update(b"ProgramDerivedAddress");
+        let address: [u8; 32] = hasher.finalize().into();
+
+        // Program derived addresses must not have a private key
+        if !is_on_curve(&address) {
+            return Ok((bs58::encode(address).into_string(), bump));
+        }
+    }
+
+    Err(Error::InvalidInput("No program derived address for the seeds".to_string()))
+}
+
+/// An instruction with its accounts replaced by indexes into the message's account keys
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompiledInstruction {
+    /// Index of the program
+    pub program_id_index: u8,
+    /// Indexes of the accounts
+    pub accounts: Vec<u8>,
+    /// Instruction data
+    pub data: Vec<u8>,
+}
+
+/// A legacy Solana transaction message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Message {
+    /// Number of signatures the transaction needs
+    pub num_required_signatures: u8,
+    /// Number of signers whose accounts are read-only
+    pub num_readonly_signed: u8,
+    /// Number of non-signers whose accounts are read-only
+    pub num_readonly_unsigned: u8,
+    /// Accounts: writable signers, read-only signers, writable and read-only non-signers
+    pub account_keys: Vec<String>,
+    /// Blockhash the transaction expires with
+    pub recent_blockhash: String,
+    /// Instructions
+    pub instructions: Vec<CompiledInstruction>,
+}
+
+impl Message {
+    /// Compile instructions into a message paid for by `fee_payer`
+    pub fn compile(fee_payer: &str, instructions: &[Instruction], recent_blockhash: &str) -> Result<Self> {
+        // (address, is_signer, is_writable) in first-seen order, the fee payer first
+        let mut keys: Vec<(String, bool, bool)> = vec![(fee_payer.to_string(), true, true)];
+        let mut add = |pubkey: &str, is_signer: bool, is_writable: bool| {
+            match keys.iter_mut().find(|(key, _, _)| key == pubkey) {
+                Some((_, signer, writable)) => {
+                    *signer |= is_signer;
+                    *writable |= is_writable;
+                }
+                None => keys.push((pubkey.to_string(), is_signer, is_writable)),
+            }
+        };
+        for instruction in instructions {
+            for account in &instruction.accounts {
+                add(&account.pubkey, account.is_signer, account.is_writable);
+            }
+            add(&instruction.program_id, false, false);
+        }
+
+        // Stable sort keeps the fee payer first and first-seen order within each group
+        keys.sort_by_key(|(_, is_signer, is_writable)| (!is_signer, !is_writable));
+        if keys.len() > u8::MAX as usize {
+            return Err(Error::Transaction(format!("A transaction holds at most {} accounts", u8::MAX)));
+        }
+        for (key, _, _) in &keys {
+            decode_pubkey(key)?;
+        }
+        decode_pubkey(recent_blockhash)?;
+
+        let count = |signer: bool, writable: bool| keys.iter().filter(|(_, s, w)| *s == signer && *w == writable).count() as u8;
+        let account_keys: Vec<String> = keys.iter().map(|(key, _, _)| key.clone()).collect();
+        let index = |pubkey: &str| account_keys.iter().position(|key| key == pubkey).unwrap_or_default() as u8;
+        let instructions = instructions.iter()
+            .map(|instruction| CompiledInstruction {
+                program_id_index: index(&instruction.program_id),
+                accounts: instruction.accounts.iter().map(|account| index(&account.pubkey)).collect(),
+                data: instruction.data.clone(),
+            })
+            .collect();
+
+        Ok(Self {
+            num_required_signatures: count(true, true) + count(true, false),
+            num_readonly_signed: count(true, false),
+            num_readonly_unsigned: count(false, false),
+            account_keys,
+            recent_blockhash: recent_blockhash.to_string(),
+            instructions,
+        })
+    }
+
+    /// Addresses that must sign, in signature order
+    pub fn signers(&self) -> &[String] {
+        &self.account_keys[..self.num_required_signatures as usize]
+    }
+
+    /// Serialize the message in the wire format signatures are made over
+    pub fn serialize(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![self.num_required_signatures, self.num_readonly_signed, self.num_readonly_unsigned];
+        encode_length(&mut bytes, self.account_keys.len());
+        for key in &self.account_keys {
+            bytes.extend(decode_pubkey(key)?);
+        }
+        bytes.extend(decode_pubkey(&self.recent_blockhash)?);
+        encode_length(&mut bytes, self.instructions.len());
+        for instruction in &self.instructions {
+            bytes.push(instruction.program_id_index);
+            encode_length(&mut bytes, instruction.accounts.len());
+            bytes.extend(&instruction.accounts);
+            encode_length(&mut bytes, instruction.data.len());
+            bytes.extend(&instruction.data);
+        }
+        Ok(bytes)
+    }
+}
+
+/// Sign a message whose only signer is `key_pair`, returning the transaction ready to broadcast
+pub fn sign_solana_message(key_pair: &KeyPair, message: &Message) -> Result<Vec<u8>> {
+    if key_pair.key_type() != KeyType::Solana {
+        return Err(Error::Signing("Not a Solana key pair".to_string()));
+    }
+    let secret: [u8; 32] = key_pair.private_key().as_bytes().try_into()
+        .map_err(|_| Error::Signing("Invalid ed25519 private key length".to_string()))?;
+    let signing_key = SigningKey::from_bytes(&secret);
+    let signer = bs58::encode(signing_key.verifying_key().to_bytes()).into_string();
+    if message.signers() != [signer.as_str()] {
+        return Err(Error::Signing(format!("{} must be the only signer of the message", signer)));
+    }
+
+    let message = message.serialize()?;
+    let mut bytes = Vec::new();
+    encode_length(&mut bytes, 1);
+    bytes.extend(signing_key.sign(&message).to_bytes());
+    bytes.extend(message);
+    Ok(bytes)
+}
+
+/// Append a length in Solana's compact-u16 encoding
+pub fn encode_length(bytes: &mut Vec<u8>, mut length: usize) {
+    loop {
+        let byte = (length & 0x7f) as u8;
+        length >>= 7;
+        if length == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
 
 /// Solana transaction
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,6 +322,11 @@ pub struct SolanaProvider {
     /// Mock RPC client
     #[allow(dead_code)]
     client: Arc<MockRpcClient>,
+    /// Genesis hash of the cluster
+    genesis_hash: String,
+    /// HTTP client for batched JSON-RPC requests
+    #[cfg(feature = "rpc")]
+    http: reqwest::Client,
 }
 
 /// Mock RPC client for testing
@@ -80,15 +351,84 @@ impl MockRpcClient {
 impl SolanaProvider {
     /// Create a new Solana provider
     pub fn new(config: ProviderConfig) -> Result<Self> {
+        // Determine the cluster from the URL
+        let genesis_hash = match config.url.as_str() {
+            url if url.contains("devnet") => SOLANA_DEVNET_GENESIS_HASH,
+            url if url.contains("testnet") => SOLANA_TESTNET_GENESIS_HASH,
+            _ => SOLANA_MAINNET_GENESIS_HASH, // Default to mainnet-beta
+        };
+
         // Create the mock RPC client
         let client = MockRpcClient::new(config.url.clone());
+
+        #[cfg(feature = "rpc")]
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout.unwrap_or(30)))
+            .build()
+            .map_err(|e| Error::Provider(format!("Failed to create HTTP client: {}", e)))?;
         
         Ok(Self {
             config,
             client: Arc::new(client),
+            genesis_hash: genesis_hash.to_string(),
+            #[cfg(feature = "rpc")]
+            http,
         })
     }
     
+    /// Get the network binding transactions must carry to be signed by this provider
+    pub fn network_binding(&self) -> NetworkBinding {
+        NetworkBinding::Solana { genesis_hash: self.genesis_hash.clone() }
+    }
+
+    /// Send a JSON-RPC batch request, returning the responses in request order
+    #[cfg(feature = "rpc")]
+    pub async fn send_batch(&self, batch: &JsonRpcBatch) -> Result<Vec<Result<serde_json::Value>>> {
+        if batch.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let response = traced(rpc_span("solana", &self.config.url, &batch.methods()), async {
+            self.http.post(&self.config.url)
+                .json(&batch.body())
+                .send()
+                .await
+                .map_err(|e| Error::Network(format!("Batch request failed: {}", e)))?
+                .json::<serde_json::Value>()
+                .await
+                .map_err(|e| Error::Provider(format!("Invalid batch response: {}", e)))
+        }).await?;
+
+        batch.parse_response(response)
+    }
+
+    /// Get the SOL balance of `owner` in lamports and its token accounts under both token programs
+    #[cfg(feature = "rpc")]
+    pub fn get_token_accounts(&self, owner: &str) -> Result<(u64, Vec<TokenAccount>)> {
+        let mut rpc_batch = JsonRpcBatch::new();
+        rpc_batch.push("getBalance", serde_json::json!([owner]));
+        for program in TokenProgram::ALL {
+            rpc_batch.push("getTokenAccountsByOwner", serde_json::json!([
+                owner,
+                { "programId": program.program_id() },
+                { "encoding": "jsonParsed" },
+            ]));
+        }
+
+        let mut responses = block_on(self.send_batch(&rpc_batch))??.into_iter();
+        let balance = responses.next()
+            .ok_or_else(|| Error::Provider("Missing getBalance response".to_string()))??;
+        let lamports = balance["value"].as_u64()
+            .ok_or_else(|| Error::Provider(format!("Invalid getBalance result: {}", balance)))?;
+
+        let mut accounts = Vec::new();
+        for (program, response) in TokenProgram::ALL.into_iter().zip(responses) {
+            accounts.extend(spl::parse_token_accounts(&response?, program)?);
+        }
+
+        Ok((lamports, accounts))
+    }
+    
     /// Create a Solana transaction
     fn create_transaction(&self, request: &TransactionRequest) -> Result<MockSolTransaction> {
         // Parse value
@@ -136,6 +476,9 @@ impl TransactionSigner for SolanaProvider {
             return Err(Error::Transaction("Not a Solana transaction".to_string()));
         }
         
+        // Refuse to sign for any cluster other than the one we are connected to
+        request.network.ensure_matches(&self.network_binding())?;
+        
         // In a real implementation, we would:
         // 1. Get the private key from the request
         // 2. Create a transaction
@@ -216,6 +559,7 @@ impl TransactionManager for SolanaProvider {
             block_number: Some(12345678),
             timestamp: Some(1620000000),
             fee: Some("0.000005".to_string()),
+            memo: None,
         };
         
         Ok(transaction)
@@ -244,6 +588,7 @@ impl TransactionManager for SolanaProvider {
             block_number: Some(12345678),
             timestamp: Some(1620000000),
             fee: Some("0.000005".to_string()),
+            memo: None,
         };
         
         Ok(vec![transaction])
@@ -254,6 +599,80 @@ impl TransactionManager for SolanaProvider {
 mod tests {
     use super::*;
     
+    #[test]
+    fn test_compile_message() {
+        let payer = "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg";
+        let owner = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+        let to = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let message = Message::compile(payer, &[system_transfer_instruction(owner, to, 1_000)], SYSTEM_PROGRAM_ID).unwrap();
+
+        assert_eq!(message.account_keys, vec![payer, owner, to, SYSTEM_PROGRAM_ID]);
+        assert_eq!(message.signers(), [payer.to_string(), owner.to_string()]);
+        assert_eq!((message.num_readonly_signed, message.num_readonly_unsigned), (0, 1));
+        assert_eq!(message.instructions[0].program_id_index, 3);
+        assert_eq!(message.instructions[0].accounts, vec![1, 2]);
+
+        let bytes = message.serialize().unwrap();
+        assert_eq!(bytes[..4], [2, 0, 1, 4]);
+        assert_eq!(bytes.len(), 3 + 1 + 4 * 32 + 32 + 1 + 1 + 1 + 2 + 1 + 12);
+
+        let mut length = Vec::new();
+        encode_length(&mut length, 300);
+        assert_eq!(length, vec![0xac, 0x02]);
+    }
+
+    #[test]
+    fn test_transfer_with_memo() {
+        let from = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+        let to = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let mut request = TransactionRequest {
+            key_type: KeyType::Solana,
+            network: NetworkBinding::Solana { genesis_hash: SOLANA_MAINNET_GENESIS_HASH.to_string() },
+            from: from.to_string(),
+            to: to.to_string(),
+            value: "1000".to_string(),
+            gas_price: None,
+            gas_limit: None,
+            nonce: None,
+            data: None,
+            destination_tag: None,
+            memo: Some(Memo::Text("invoice 42".to_string())),
+        };
+
+        let instructions = transfer_instructions(&request).unwrap();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[1].program_id, MEMO_PROGRAM_ID);
+        assert_eq!(instructions[1].accounts, vec![AccountMeta { pubkey: from.to_string(), is_signer: true, is_writable: false }]);
+        assert_eq!(instructions[1].data, b"invoice 42");
+
+        let message = Message::compile(from, &instructions, SYSTEM_PROGRAM_ID).unwrap();
+        assert_eq!(message.account_keys, vec![from, to, SYSTEM_PROGRAM_ID, MEMO_PROGRAM_ID]);
+
+        request.memo = Some(Memo::Bytes(vec![0xff, 0xfe]));
+        assert!(transfer_instructions(&request).is_err());
+    }
+
+    #[test]
+    fn test_sign_solana_message() {
+        let seed = crate::crypto::mnemonic::mnemonic_to_seed("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap();
+        let key_pair = crate::crypto::keys::solana::derive_solana_key_pair(&seed, "m/44'/501'/0'/0'").unwrap();
+        let from = "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk";
+        let to = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+        let message = Message::compile(from, &[system_transfer_instruction(from, to, 1_000)], SYSTEM_PROGRAM_ID).unwrap();
+        let bytes = sign_solana_message(&key_pair, &message).unwrap();
+        assert_eq!(bytes[0], 1);
+        assert_eq!(bytes[65..], message.serialize().unwrap()[..]);
+
+        let verifying_key = ed25519_dalek::VerifyingKey::from_bytes(&decode_pubkey(from).unwrap()).unwrap();
+        let signature = ed25519_dalek::Signature::from_slice(&bytes[1..65]).unwrap();
+        assert!(ed25519_dalek::Verifier::verify(&verifying_key, &bytes[65..], &signature).is_ok());
+
+        // Another signer's message cannot be completed with this key
+        let message = Message::compile(to, &[system_transfer_instruction(to, from, 1_000)], SYSTEM_PROGRAM_ID).unwrap();
+        assert!(sign_solana_message(&key_pair, &message).is_err());
+    }
+
     #[test]
     fn test_create_transaction() {
         let config = ProviderConfig {
@@ -267,6 +686,7 @@ mod tests {
         
         let request = TransactionRequest {
             key_type: KeyType::Solana,
+            network: NetworkBinding::Solana { genesis_hash: SOLANA_MAINNET_GENESIS_HASH.to_string() },
             from: "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg".to_string(),
             to: "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg".to_string(),
             value: "1000000".to_string(), // 0.001 SOL
@@ -274,6 +694,8 @@ mod tests {
             gas_limit: None,
             nonce: None,
             data: None,
+            destination_tag: None,
+            memo: None,
         };
         
         let tx = provider.create_transaction(&request).unwrap();
@@ -0,0 +1,223 @@
+//! SPL token accounts
+//!
+//! Parses the `jsonParsed` token accounts returned by Solana's
+//! `getTokenAccountsByOwner` for both the original Token program and
+//! Token-2022.
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+use super::solana::{self, AccountMeta, Instruction, SYSTEM_PROGRAM_ID};
+
+/// Program ID of the SPL Token program
+pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+/// Program ID of the SPL Token-2022 program
+pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+
+/// Program ID of the Associated Token Account program
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+
+/// Lamports held by a rent-exempt token account (165 bytes)
+pub const TOKEN_ACCOUNT_RENT: u64 = 2_039_280;
+
+/// SPL token program owning a token account
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TokenProgram {
+    /// The original Token program
+    Token,
+    /// Token-2022 (token extensions)
+    Token2022,
+}
+
+impl TokenProgram {
+    /// Both token programs
+    pub const ALL: [TokenProgram; 2] = [TokenProgram::Token, TokenProgram::Token2022];
+
+    /// Get the program ID
+    pub fn program_id(&self) -> &'static str {
+        match self {
+            Self::Token => TOKEN_PROGRAM_ID,
+            Self::Token2022 => TOKEN_2022_PROGRAM_ID,
+        }
+    }
+}
+
+/// An SPL token account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenAccount {
+    /// Token account address
+    pub address: String,
+    /// Token mint
+    pub mint: String,
+    /// Owner of the account
+    pub owner: String,
+    /// Program owning the account
+    pub program: TokenProgram,
+    /// Balance in the smallest unit
+    pub amount: u128,
+    /// Decimals of the mint
+    pub decimals: u8,
+    /// Lamports held by the account (rent deposit plus wrapped SOL)
+    pub lamports: u64,
+    /// Whether the account holds wrapped SOL
+    pub is_native: bool,
+    /// Whether the account is frozen by the mint's freeze authority
+    pub frozen: bool,
+    /// Close authority, if different from the owner
+    pub close_authority: Option<String>,
+}
+
+impl TokenAccount {
+    /// Whether the account holds no tokens
+    pub fn is_empty(&self) -> bool {
+        self.amount == 0
+    }
+
+    /// Whether the account looks like an NFT (a single indivisible token)
+    pub fn is_nft(&self) -> bool {
+        self.decimals == 0 && self.amount == 1
+    }
+}
+
+/// Instruction index of SPL Token `TransferChecked`
+const TRANSFER_CHECKED_INSTRUCTION: u8 = 12;
+
+/// Build an SPL Token `TransferChecked` instruction between two token accounts
+pub fn transfer_checked_instruction(
+    program: TokenProgram,
+    source: &str,
+    mint: &str,
+    destination: &str,
+    owner: &str,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    let mut data = vec![TRANSFER_CHECKED_INSTRUCTION];
+    data.extend(amount.to_le_bytes());
+    data.push(decimals);
+
+    Instruction {
+        program_id: program.program_id().to_string(),
+        accounts: vec![
+            AccountMeta { pubkey: source.to_string(), is_signer: false, is_writable: true },
+            AccountMeta { pubkey: mint.to_string(), is_signer: false, is_writable: false },
+            AccountMeta { pubkey: destination.to_string(), is_signer: false, is_writable: true },
+            AccountMeta { pubkey: owner.to_string(), is_signer: true, is_writable: false },
+        ],
+        data,
+    }
+}
+
+/// Get the associated token account of `owner` for `mint`
+pub fn associated_token_address(owner: &str, mint: &str, program: TokenProgram) -> Result<String> {
+    let owner = solana::decode_pubkey(owner)?;
+    let mint = solana::decode_pubkey(mint)?;
+    let token_program = solana::decode_pubkey(program.program_id())?;
+
+    let (address, _) = solana::find_program_address(&[&owner, &token_program, &mint], ASSOCIATED_TOKEN_PROGRAM_ID)?;
+    Ok(address)
+}
+
+/// Build an instruction creating the associated token account of `owner` for `mint` unless it exists
+pub fn create_associated_token_account_instruction(payer: &str, owner: &str, mint: &str, program: TokenProgram) -> Result<Instruction> {
+    let address = associated_token_address(owner, mint, program)?;
+
+    Ok(Instruction {
+        program_id: ASSOCIATED_TOKEN_PROGRAM_ID.to_string(),
+        accounts: vec![
+            AccountMeta { pubkey: payer.to_string(), is_signer: true, is_writable: true },
+            AccountMeta { pubkey: address, is_signer: false, is_writable: true },
+            AccountMeta { pubkey: owner.to_string(), is_signer: false, is_writable: false },
+            AccountMeta { pubkey: mint.to_string(), is_signer: false, is_writable: false },
+            AccountMeta { pubkey: SYSTEM_PROGRAM_ID.to_string(), is_signer: false, is_writable: false },
+            AccountMeta { pubkey: program.program_id().to_string(), is_signer: false, is_writable: false },
+        ],
+        // CreateIdempotent
+        data: vec![1],
+    })
+}
+
+/// Parse a `getTokenAccountsByOwner` result in `jsonParsed` encoding
+pub fn parse_token_accounts(result: &Value, program: TokenProgram) -> Result<Vec<TokenAccount>> {
+    let accounts = result["value"].as_array()
+        .ok_or_else(|| Error::Provider(format!("Invalid getTokenAccountsByOwner result: {}", result)))?;
+
+    accounts.iter()
+        .map(|account| {
+            let invalid = || Error::Provider(format!("Invalid token account: {}", account));
+            let info = &account["account"]["data"]["parsed"]["info"];
+            let token_amount = &info["tokenAmount"];
+
+            Ok(TokenAccount {
+                address: account["pubkey"].as_str().ok_or_else(invalid)?.to_string(),
+                mint: info["mint"].as_str().ok_or_else(invalid)?.to_string(),
+                owner: info["owner"].as_str().ok_or_else(invalid)?.to_string(),
+                program,
+                amount: token_amount["amount"].as_str().and_then(|amount| amount.parse().ok()).ok_or_else(invalid)?,
+                decimals: token_amount["decimals"].as_u64().and_then(|decimals| u8::try_from(decimals).ok()).ok_or_else(invalid)?,
+                lamports: account["account"]["lamports"].as_u64().unwrap_or(0),
+                is_native: info["isNative"].as_bool().unwrap_or(false),
+                frozen: info["state"].as_str() == Some("frozen"),
+                close_authority: info["closeAuthority"].as_str().map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_token_accounts() {
+        let result = json!({
+            "context": { "slot": 250000000 },
+            "value": [{
+                "pubkey": "C2gJg6tKpQs41PRS1nC8aw3ZKNZK3HQQZGVrDFDup5nx",
+                "account": {
+                    "lamports": 2039280,
+                    "owner": TOKEN_PROGRAM_ID,
+                    "data": {
+                        "program": "spl-token",
+                        "parsed": {
+                            "type": "account",
+                            "info": {
+                                "isNative": false,
+                                "mint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+                                "owner": "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg",
+                                "state": "initialized",
+                                "tokenAmount": { "amount": "2500000", "decimals": 6, "uiAmountString": "2.5" },
+                            },
+                        },
+                    },
+                },
+            }],
+        });
+
+        let accounts = parse_token_accounts(&result, TokenProgram::Token).unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].amount, 2_500_000);
+        assert_eq!(accounts[0].decimals, 6);
+        assert_eq!(accounts[0].lamports, 2_039_280);
+        assert!(!accounts[0].frozen && !accounts[0].is_empty() && !accounts[0].is_nft());
+
+        assert!(parse_token_accounts(&json!({ "value": [{ "pubkey": "x" }] }), TokenProgram::Token).is_err());
+    }
+
+    #[test]
+    fn test_associated_token_address() {
+        let owner = "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg";
+        let mint = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+        let address = associated_token_address(owner, mint, TokenProgram::Token).unwrap();
+        assert_eq!(address, associated_token_address(owner, mint, TokenProgram::Token).unwrap());
+        assert_ne!(address, associated_token_address(owner, mint, TokenProgram::Token2022).unwrap());
+        assert!(!solana::is_on_curve(&solana::decode_pubkey(&address).unwrap()));
+
+        let instruction = create_associated_token_account_instruction(owner, owner, mint, TokenProgram::Token).unwrap();
+        assert_eq!(instruction.accounts[1].pubkey, address);
+    }
+}
@@ -0,0 +1,504 @@
+//! Fund sweeping
+//!
+//! [`sweep_wallet`] plans moving everything held by a set of addresses,
+//! such as those of an old derivation path or a compromised key, to one
+//! target address per chain. Fees are reserved from the native balance,
+//! token accounts are emptied and closed, and Bitcoin UTXOs that cost more
+//! to spend than they hold are left behind. The [`SweepReport`] lists the
+//! unsigned transactions and every asset moved or skipped.
+
+use std::str::FromStr;
+
+use ethers::abi::{self, Token};
+use ethers::prelude::{Address, U256};
+use serde::{Serialize, Deserialize};
+
+use crate::crypto::keys::KeyType;
+use crate::error::{Error, Result};
+use super::bitcoin::BitcoinInput;
+use super::dust::{self, BITCOIN_DUST_LIMIT, OUTPUT_VSIZE, SOLANA_SIGNATURE_FEE, TX_OVERHEAD_VSIZE};
+use super::solana::{self, Instruction};
+use super::spl::{self, TokenAccount, TOKEN_ACCOUNT_RENT};
+use super::types::{NetworkBinding, TransactionRequest};
+
+/// Gas of a native EVM transfer
+const EVM_TRANSFER_GAS: u64 = 21_000;
+
+/// Gas reserved for an ERC-20 transfer
+const EVM_TOKEN_TRANSFER_GAS: u64 = 65_000;
+
+/// Token accounts swept per Solana transaction (create, transfer and close each)
+const TOKEN_ACCOUNTS_PER_TRANSACTION: usize = 6;
+
+/// What an address holds on one chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Holdings {
+    /// EVM balances
+    Evm {
+        /// Native balance, in wei
+        native: String,
+        /// ERC-20 balances as (token, amount)
+        tokens: Vec<(String, String)>,
+    },
+    /// Solana balances
+    Solana {
+        /// SOL balance, in lamports
+        lamports: u64,
+        /// Token accounts
+        token_accounts: Vec<TokenAccount>,
+    },
+    /// Bitcoin UTXOs
+    Bitcoin {
+        /// Unspent outputs
+        utxos: Vec<BitcoinInput>,
+    },
+}
+
+/// An address to sweep
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepSource {
+    /// Address
+    pub address: String,
+    /// What the address holds
+    pub holdings: Holdings,
+}
+
+/// Fee rates of a sweep
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SweepFees {
+    /// EVM gas price, in wei
+    pub gas_price: String,
+    /// Bitcoin fee rate, in sat/vB
+    pub fee_rate: u64,
+}
+
+/// A sweep of one chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepRequest {
+    /// Blockchain type
+    pub key_type: KeyType,
+    /// Network the transactions are bound to
+    pub network: NetworkBinding,
+    /// Addresses to empty
+    pub sources: Vec<SweepSource>,
+    /// Address receiving everything
+    pub target: String,
+    /// Fee rates
+    pub fees: SweepFees,
+}
+
+/// An unsigned sweep transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SweepTransaction {
+    /// An EVM transaction
+    Evm {
+        /// The transaction
+        request: TransactionRequest,
+    },
+    /// A Solana transaction paid and signed by `fee_payer`
+    Solana {
+        /// Fee payer and signer
+        fee_payer: String,
+        /// Instructions, in order
+        instructions: Vec<Instruction>,
+    },
+    /// A Bitcoin transaction spending UTXOs of several sources
+    Bitcoin {
+        /// UTXOs to spend
+        inputs: Vec<BitcoinInput>,
+        /// Value paid to the target, in satoshis
+        output_value: u64,
+        /// Fee, in satoshis
+        fee: u64,
+    },
+}
+
+/// An asset moved or left behind
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SweptAsset {
+    /// Source address
+    pub source: String,
+    /// Token address or mint, or `None` for the native token
+    pub token: Option<String>,
+    /// Amount in the smallest unit
+    pub amount: String,
+    /// Why the asset was skipped, if it was
+    pub skipped: Option<String>,
+}
+
+/// Plan of one chain's sweep
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainSweep {
+    /// Blockchain type
+    pub key_type: KeyType,
+    /// Target address
+    pub target: String,
+    /// Transactions, in submission order
+    pub transactions: Vec<SweepTransaction>,
+    /// Assets moved to the target
+    pub moved: Vec<SweptAsset>,
+    /// Assets left behind
+    pub skipped: Vec<SweptAsset>,
+    /// Estimated fees, in the smallest native unit
+    pub fees: String,
+}
+
+/// Consolidated report of a sweep
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepReport {
+    /// Plan per chain
+    pub chains: Vec<ChainSweep>,
+}
+
+impl ChainSweep {
+    fn new(request: &SweepRequest) -> Self {
+        Self {
+            key_type: request.key_type,
+            target: request.target.clone(),
+            transactions: vec![],
+            moved: vec![],
+            skipped: vec![],
+            fees: "0".to_string(),
+        }
+    }
+
+    fn moved(&mut self, source: &str, token: Option<&str>, amount: impl ToString) {
+        self.moved.push(SweptAsset { source: source.to_string(), token: token.map(str::to_string), amount: amount.to_string(), skipped: None });
+    }
+
+    fn skipped(&mut self, source: &str, token: Option<&str>, amount: impl ToString, reason: &str) {
+        self.skipped.push(SweptAsset {
+            source: source.to_string(),
+            token: token.map(str::to_string),
+            amount: amount.to_string(),
+            skipped: Some(reason.to_string()),
+        });
+    }
+}
+
+/// Plan moving all funds of the sources to the target, chain by chain
+pub fn sweep_wallet(requests: &[SweepRequest]) -> Result<SweepReport> {
+    let chains = requests.iter()
+        .map(|request| {
+            if request.network.key_type() != request.key_type {
+                return Err(Error::InvalidInput(format!("Network does not belong to {:?}", request.key_type)));
+            }
+            if request.sources.iter().any(|source| source.address == request.target) {
+                return Err(Error::InvalidInput("The target cannot be one of the sources".to_string()));
+            }
+
+            match request.key_type {
+                KeyType::Ethereum => sweep_evm(request),
+                KeyType::Solana => sweep_solana(request),
+                KeyType::Bitcoin => sweep_bitcoin(request),
+                KeyType::Ton => Err(Error::NotSupported("TON wallets cannot be swept yet".to_string())),
+                KeyType::Cosmos => Err(Error::NotSupported("Cosmos accounts cannot be swept yet".to_string())),
+                KeyType::Xrp => Err(Error::NotSupported("XRP accounts cannot be swept yet".to_string())),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(SweepReport { chains })
+}
+
+fn mismatched_holdings(source: &SweepSource, key_type: KeyType) -> Error {
+    Error::InvalidInput(format!("Holdings of {} are not {:?} holdings", source.address, key_type))
+}
+
+fn sweep_evm(request: &SweepRequest) -> Result<ChainSweep> {
+    let parse = |field: &str, value: &str| {
+        U256::from_dec_str(value).map_err(|e| Error::InvalidInput(format!("Invalid {} {}: {}", field, value, e)))
+    };
+    let gas_price = parse("gas price", &request.fees.gas_price)?;
+    let target = Address::from_str(&request.target)
+        .map_err(|e| Error::InvalidInput(format!("Invalid target {}: {}", request.target, e)))?;
+
+    let mut sweep = ChainSweep::new(request);
+    let mut total_fees = U256::zero();
+
+    for source in &request.sources {
+        let Holdings::Evm { native, tokens } = &source.holdings else {
+            return Err(mismatched_holdings(source, request.key_type));
+        };
+        let mut remaining = parse("native balance", native)?;
+
+        let tokens: Vec<(&String, U256)> = tokens.iter()
+            .map(|(token, amount)| Ok((token, parse("token balance", amount)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let token_gas = gas_price * U256::from(EVM_TOKEN_TRANSFER_GAS);
+
+        for (token, amount) in tokens.into_iter().filter(|(_, amount)| !amount.is_zero()) {
+            // Token transfers are paid from the native balance
+            if remaining < token_gas {
+                sweep.skipped(&source.address, Some(token), amount, "not enough native balance for gas");
+                continue;
+            }
+            remaining -= token_gas;
+            total_fees += token_gas;
+
+            let mut data = ethers::utils::id("transfer(address,uint256)").to_vec();
+            data.extend(abi::encode(&[Token::Address(target), Token::Uint(amount)]));
+            sweep.transactions.push(SweepTransaction::Evm {
+                request: evm_request(request, &source.address, token, U256::zero(), EVM_TOKEN_TRANSFER_GAS, Some(data)),
+            });
+            sweep.moved(&source.address, Some(token), amount);
+        }
+
+        // The native transfer goes last and leaves nothing behind
+        let transfer_gas = gas_price * U256::from(EVM_TRANSFER_GAS);
+        if remaining > transfer_gas {
+            let value = remaining - transfer_gas;
+            total_fees += transfer_gas;
+            sweep.transactions.push(SweepTransaction::Evm {
+                request: evm_request(request, &source.address, &request.target, value, EVM_TRANSFER_GAS, None),
+            });
+            sweep.moved(&source.address, None, value);
+        } else if !remaining.is_zero() {
+            sweep.skipped(&source.address, None, remaining, "balance does not cover the transfer fee");
+        }
+    }
+
+    sweep.fees = total_fees.to_string();
+    Ok(sweep)
+}
+
+fn evm_request(request: &SweepRequest, from: &str, to: &str, value: U256, gas_limit: u64, data: Option<Vec<u8>>) -> TransactionRequest {
+    TransactionRequest {
+        key_type: request.key_type,
+        network: request.network.clone(),
+        from: from.to_string(),
+        to: to.to_string(),
+        value: value.to_string(),
+        gas_price: Some(request.fees.gas_price.clone()),
+        gas_limit: Some(gas_limit.to_string()),
+        nonce: None,
+        data,
+        destination_tag: None,
+        memo: None,
+    }
+}
+
+fn sweep_solana(request: &SweepRequest) -> Result<ChainSweep> {
+    let mut sweep = ChainSweep::new(request);
+    let mut total_fees = 0u64;
+
+    for source in &request.sources {
+        let Holdings::Solana { lamports, token_accounts } = &source.holdings else {
+            return Err(mismatched_holdings(source, request.key_type));
+        };
+
+        // Lamports the source ends up with once its token accounts are closed
+        let mut available = *lamports;
+        let mut transactions: Vec<Vec<Instruction>> = Vec::new();
+
+        let closable: Vec<&TokenAccount> = token_accounts.iter()
+            .filter(|account| {
+                if account.frozen {
+                    sweep.skipped(&source.address, Some(&account.mint), account.amount, "token account is frozen");
+                    return false;
+                }
+                true
+            })
+            .collect();
+
+        for chunk in closable.chunks(TOKEN_ACCOUNTS_PER_TRANSACTION) {
+            let mut instructions = Vec::new();
+            for account in chunk {
+                if account.amount > 0 && !account.is_native {
+                    let amount = u64::try_from(account.amount)
+                        .map_err(|_| Error::InvalidInput(format!("Token amount {} exceeds u64", account.amount)))?;
+                    let destination = spl::associated_token_address(&request.target, &account.mint, account.program)?;
+
+                    // The source pays for the target's token account if it is missing
+                    instructions.push(spl::create_associated_token_account_instruction(&source.address, &request.target, &account.mint, account.program)?);
+                    instructions.push(spl::transfer_checked_instruction(
+                        account.program, &account.address, &account.mint, &destination, &source.address, amount, account.decimals,
+                    ));
+                    available = available.saturating_sub(TOKEN_ACCOUNT_RENT);
+                    sweep.moved(&source.address, Some(&account.mint), account.amount);
+                }
+
+                // Closing returns the rent, and unwraps wrapped SOL
+                instructions.push(dust::close_account_instruction(account, &source.address));
+                available += account.lamports;
+            }
+            transactions.push(instructions);
+        }
+
+        if transactions.is_empty() {
+            transactions.push(Vec::new());
+        }
+        let fees = transactions.len() as u64 * SOLANA_SIGNATURE_FEE;
+        total_fees += fees;
+
+        match available.checked_sub(fees).filter(|lamports| *lamports > 0) {
+            Some(sweepable) => {
+                transactions.last_mut().expect("at least one transaction")
+                    .push(solana::system_transfer_instruction(&source.address, &request.target, sweepable));
+                sweep.moved(&source.address, None, sweepable);
+            }
+            None => sweep.skipped(&source.address, None, available, "balance does not cover the fees"),
+        }
+
+        sweep.transactions.extend(transactions.into_iter()
+            .filter(|instructions| !instructions.is_empty())
+            .map(|instructions| SweepTransaction::Solana { fee_payer: source.address.clone(), instructions }));
+    }
+
+    sweep.fees = total_fees.to_string();
+    Ok(sweep)
+}
+
+fn sweep_bitcoin(request: &SweepRequest) -> Result<ChainSweep> {
+    let mut sweep = ChainSweep::new(request);
+    let fee_rate = request.fees.fee_rate;
+    let mut inputs = Vec::new();
+
+    for source in &request.sources {
+        let Holdings::Bitcoin { utxos } = &source.holdings else {
+            return Err(mismatched_holdings(source, request.key_type));
+        };
+
+        for utxo in utxos {
+            if utxo.amount > dust::input_vsize(&utxo.script_pubkey) * fee_rate {
+                inputs.push((source.address.as_str(), utxo.clone()));
+            } else {
+                sweep.skipped(&source.address, None, utxo.amount, "worth less than the fee to spend it");
+            }
+        }
+    }
+
+    if inputs.is_empty() {
+        return Ok(sweep);
+    }
+
+    let vsize = TX_OVERHEAD_VSIZE + OUTPUT_VSIZE + inputs.iter().map(|(_, utxo)| dust::input_vsize(&utxo.script_pubkey)).sum::<u64>();
+    let fee = vsize * fee_rate;
+    let total: u64 = inputs.iter().map(|(_, utxo)| utxo.amount).sum();
+
+    match total.checked_sub(fee).filter(|value| *value >= BITCOIN_DUST_LIMIT) {
+        Some(output_value) => {
+            for (source, utxo) in &inputs {
+                sweep.moved(source, None, utxo.amount);
+            }
+            sweep.transactions.push(SweepTransaction::Bitcoin {
+                inputs: inputs.into_iter().map(|(_, utxo)| utxo).collect(),
+                output_value,
+                fee,
+            });
+            sweep.fees = fee.to_string();
+        }
+        None => {
+            for (source, utxo) in &inputs {
+                sweep.skipped(source, None, utxo.amount, "total does not cover the fee");
+            }
+        }
+    }
+
+    Ok(sweep)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::TokenProgram;
+
+    fn request(key_type: KeyType, network: NetworkBinding, sources: Vec<SweepSource>, target: &str) -> SweepRequest {
+        SweepRequest {
+            key_type,
+            network,
+            sources,
+            target: target.to_string(),
+            fees: SweepFees { gas_price: "10000000000".to_string(), fee_rate: 5 },
+        }
+    }
+
+    #[test]
+    fn test_sweep_evm_reserves_gas() {
+        let source = SweepSource {
+            address: "0x9858EfFD232B4033E47d90003D41EC34EcaEda94".to_string(),
+            holdings: Holdings::Evm {
+                // 0.01 ETH
+                native: "10000000000000000".to_string(),
+                tokens: vec![("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(), "5000000".to_string())],
+            },
+        };
+        let request = request(KeyType::Ethereum, NetworkBinding::Evm { chain_id: 1 }, vec![source], "0x742d35Cc6634C0532925a3b844Bc454e4438f44e");
+
+        let report = sweep_wallet(&[request]).unwrap();
+        let chain = &report.chains[0];
+        assert_eq!(chain.transactions.len(), 2);
+        assert_eq!(chain.moved[0].amount, "5000000");
+
+        // 0.01 ETH minus 86,000 gas at 10 gwei
+        assert_eq!(chain.moved[1].amount, (10_000_000_000_000_000u64 - 86_000 * 10_000_000_000).to_string());
+        assert_eq!(chain.fees, (86_000u64 * 10_000_000_000).to_string());
+    }
+
+    #[test]
+    fn test_sweep_solana_closes_token_accounts() {
+        let owner = "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg";
+        let account = |address: &str, amount: u128| TokenAccount {
+            address: address.to_string(),
+            mint: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(),
+            owner: owner.to_string(),
+            program: TokenProgram::Token,
+            amount,
+            decimals: 6,
+            lamports: TOKEN_ACCOUNT_RENT,
+            is_native: false,
+            frozen: false,
+            close_authority: None,
+        };
+        let source = SweepSource {
+            address: owner.to_string(),
+            holdings: Holdings::Solana {
+                lamports: 10_000_000,
+                token_accounts: vec![account("C2gJg6tKpQs41PRS1nC8aw3ZKNZK3HQQZGVrDFDup5nx", 2_500_000), account("3Fo7nK8v2gF4XZ3f7dXWbDmbvY3b3HUW3KMSc1q1wB9r", 0)],
+            },
+        };
+        let genesis_hash = solana::SOLANA_MAINNET_GENESIS_HASH.to_string();
+        let request = request(KeyType::Solana, NetworkBinding::Solana { genesis_hash }, vec![source], "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM");
+
+        let report = sweep_wallet(&[request]).unwrap();
+        let chain = &report.chains[0];
+        match &chain.transactions[..] {
+            // Create + transfer + close, close, then the SOL transfer
+            [SweepTransaction::Solana { instructions, .. }] => assert_eq!(instructions.len(), 5),
+            other => panic!("unexpected transactions {:?}", other),
+        }
+
+        // Both rents come back, one is spent on the target's token account
+        let sol = chain.moved.iter().find(|asset| asset.token.is_none()).unwrap();
+        assert_eq!(sol.amount, (10_000_000 + TOKEN_ACCOUNT_RENT - SOLANA_SIGNATURE_FEE).to_string());
+    }
+
+    #[test]
+    fn test_sweep_bitcoin_leaves_dust() {
+        let utxo = |vout: u32, amount: u64| BitcoinInput {
+            txid: "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b".to_string(),
+            vout,
+            amount,
+            script_pubkey: "0014751e76e8199196d454941c45d1b3a323f1433bd6".to_string(),
+        };
+        let sources = vec![
+            SweepSource { address: "bc1qold1".to_string(), holdings: Holdings::Bitcoin { utxos: vec![utxo(0, 40_000), utxo(1, 200)] } },
+            SweepSource { address: "bc1qold2".to_string(), holdings: Holdings::Bitcoin { utxos: vec![utxo(2, 60_000)] } },
+        ];
+        let request = request(KeyType::Bitcoin, NetworkBinding::Bitcoin { network: "bitcoin".to_string() }, sources, "bc1qnew");
+
+        let report = sweep_wallet(&[request]).unwrap();
+        let chain = &report.chains[0];
+        assert_eq!(chain.skipped.len(), 1);
+        match &chain.transactions[..] {
+            [SweepTransaction::Bitcoin { inputs, output_value, fee }] => {
+                assert_eq!(inputs.len(), 2);
+                assert_eq!(*fee, (11 + 31 + 2 * 68) * 5);
+                assert_eq!(*output_value, 100_000 - fee);
+            }
+            other => panic!("unexpected transactions {:?}", other),
+        }
+    }
+}
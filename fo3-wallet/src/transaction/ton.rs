@@ -0,0 +1,1068 @@
+//! TON transaction functionality
+//!
+//! TON stores everything, messages included, as trees of cells serialized
+//! into bags of cells (BOC). This module builds the cells of v4R2 wallet
+//! transfers (TON with an optional comment, and TEP-74 jetton transfers),
+//! deploys the wallet contract with its first transfer, and reads balances
+//! and history from a toncenter-compatible HTTP API.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use base64::Engine;
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::crypto::keys::{KeyPair, KeyType};
+use crate::error::{Error, Result};
+use super::types::{Memo, Transaction, TransactionRequest, TransactionReceipt, TransactionStatus, TransactionSigner, TransactionBroadcaster, TransactionManager, TransactionType, NetworkBinding};
+use super::provider::ProviderConfig;
+
+/// Global ID of TON mainnet
+pub const TON_MAINNET_GLOBAL_ID: i32 = -239;
+
+/// Global ID of TON testnet
+pub const TON_TESTNET_GLOBAL_ID: i32 = -3;
+
+/// Code of the v4R2 wallet contract, as a BOC
+const WALLET_V4R2_CODE: &str = "te6cckECFAEAAtQAART/APSkE/S88sgLAQIBIAIDAgFIBAUE+PKDCNcYINMf0x/THwL4I7vyZO1E0NMf0x/T//QE0VFDuvKhUVG68qIF+QFUEGT5EPKj+AAkpMjLH1JAyx9SMMv/UhD0AMntVPgPAdMHIcAAn2xRkyDXSpbTB9QC+wDoMOAhwAHjACHAAuMAAcADkTDjDQOkyMsfEssfy/8QERITAubQAdDTAyFxsJJfBOAi10nBIJJfBOAC0x8hghBwbHVnvSKCEGRzdHK9sJJfBeAD+kAwIPpEAcjKB8v/ydDtRNCBAUDXIfQEMFyBAQj0Cm+hMbOSXwfgBdM/yCWCEHBsdWe6kjgw4w0DghBkc3RyupJfBuMNBgcCASAICQB4AfoA9AQw+CdvIjBQCqEhvvLgUIIQcGx1Z4MesXCAGFAEywUmzxZY+gIZ9ADLaRfLH1Jgyz8gyYBA+wAGAIpQBIEBCPRZMO1E0IEBQNcgyAHPFvQAye1UAXKwjiOCEGRzdHKDHrFwgBhQBcsFUAPPFiP6AhPLassfyz/JgED7AJJfA+ICASAKCwBZvSQrb2omhAgKBrkPoCGEcNQICEekk30pkQzmkD6f+YN4EoAbeBAUiYcVnzGEAgFYDA0AEbjJftRNDXCx+AA9sp37UTQgQFA1yH0BDACyMoHy//J0AGBAQj0Cm+hMYAIBIA4PABmtznaiaEAga5Drhf/AABmvHfaiaEAQa5DrhY/AAG7SB/oA1NQi+QAFyMoHFcv/ydB3dIAYyMsFywIizxZQBfoCFMtrEszMyXP7AMhAFIEBCPRR8qcCAHCBAQjXGPoA0z/IVCBHgQEI9FHyp4IQbm90ZXB0gBjIywXLAlAGzxZQBPoCFMtqEssfyz/Jc/sAAgBsgQEI1xj6ANM/MFIkgQEI9Fnyp4IQZHN0cnB0gBjIywXLAlAFzxZQA/oCE8tqyx8Syz/Jc/sAAAr0AMntVGliJeU=";
+
+/// Subwallet ID of wallets on the basechain, offset by the workchain elsewhere
+pub const DEFAULT_WALLET_ID: u32 = 698_983_191;
+
+/// Most messages a v4 wallet sends in one transfer
+pub const MAX_WALLET_MESSAGES: usize = 4;
+
+/// Default send mode: pay forwarding fees separately, ignore errors
+pub const SEND_MODE_PAY_FEES_SEPARATELY: u8 = 3;
+
+/// Opcode of a TEP-74 jetton transfer
+pub const JETTON_TRANSFER_OP: u32 = 0x0f8a_7ea5;
+
+/// TON attached to a jetton transfer for the jetton wallets' fees, in nanotons
+pub const JETTON_TRANSFER_TON: u128 = 50_000_000;
+
+/// Maximum bits in a cell
+const MAX_CELL_BITS: usize = 1023;
+
+/// Maximum references of a cell
+const MAX_CELL_REFS: usize = 4;
+
+/// Magic prefix of a serialized bag of cells
+const BOC_MAGIC: [u8; 4] = [0xb5, 0xee, 0x9c, 0x72];
+
+/// A TON cell: up to 1023 bits and four references to other cells
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    data: Vec<u8>,
+    bit_len: usize,
+    refs: Vec<Cell>,
+}
+
+impl Cell {
+    /// Data bits, packed big-endian
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Number of data bits
+    pub fn bit_len(&self) -> usize {
+        self.bit_len
+    }
+
+    /// Referenced cells
+    pub fn refs(&self) -> &[Cell] {
+        &self.refs
+    }
+
+    /// Read `bits` bits starting at `offset` as an unsigned integer
+    pub fn read_uint(&self, offset: usize, bits: usize) -> Result<u128> {
+        if bits > 128 || offset + bits > self.bit_len {
+            return Err(Error::Serialization(format!("Cannot read {} bits at {} of a {}-bit cell", bits, offset, self.bit_len)));
+        }
+        Ok((offset..offset + bits).fold(0u128, |value, bit| value << 1 | self.bit(bit) as u128))
+    }
+
+    /// Read a standard address (`addr_std`) starting at `offset`
+    pub fn read_address(&self, offset: usize) -> Result<TonAddress> {
+        if self.read_uint(offset, 3)? != 0b100 {
+            return Err(Error::Serialization("Not a standard address without anycast".to_string()));
+        }
+        let workchain = self.read_uint(offset + 3, 8)? as u8 as i8;
+        let mut hash = [0u8; 32];
+        for (i, byte) in hash.iter_mut().enumerate() {
+            *byte = self.read_uint(offset + 11 + i * 8, 8)? as u8;
+        }
+        Ok(TonAddress { workchain, hash })
+    }
+
+    fn bit(&self, index: usize) -> bool {
+        self.data[index / 8] & (0x80 >> (index % 8)) != 0
+    }
+
+    /// Depth of the tree below the cell
+    pub fn depth(&self) -> u16 {
+        self.refs.iter().map(|cell| cell.depth() + 1).max().unwrap_or(0)
+    }
+
+    /// Representation hash, which identifies the cell on-chain
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.descriptors());
+        hasher.update(self.padded_data());
+        for cell in &self.refs {
+            hasher.update(cell.depth().to_be_bytes());
+        }
+        for cell in &self.refs {
+            hasher.update(cell.hash());
+        }
+        hasher.finalize().into()
+    }
+
+    fn descriptors(&self) -> [u8; 2] {
+        [self.refs.len() as u8, (self.bit_len.div_ceil(8) + self.bit_len / 8) as u8]
+    }
+
+    /// Data with an incomplete last byte completed by a 1 bit and zeros
+    fn padded_data(&self) -> Vec<u8> {
+        let mut data = self.data.clone();
+        if self.bit_len % 8 != 0 {
+            let last = data.len() - 1;
+            data[last] |= 0x80 >> (self.bit_len % 8);
+        }
+        data
+    }
+
+    /// Serialize the tree as a bag of cells, with a CRC32-C
+    pub fn to_boc(&self) -> Vec<u8> {
+        // Order cells so each precedes the cells it references, storing shared cells once
+        let mut order = Vec::new();
+        let mut indices = HashMap::new();
+        collect_post_order(self, &mut order, &mut indices);
+        order.reverse();
+        let count = order.len();
+        let index_of = |cell: &Cell| count - 1 - indices[&cell.hash()];
+
+        let size = bytes_needed(count as u64);
+        let mut cells = Vec::new();
+        for cell in &order {
+            cells.extend_from_slice(&cell.descriptors());
+            cells.extend_from_slice(&cell.padded_data());
+            for child in &cell.refs {
+                cells.extend_from_slice(&(index_of(child) as u64).to_be_bytes()[8 - size..]);
+            }
+        }
+        let offset_size = bytes_needed(cells.len() as u64);
+
+        let mut boc = BOC_MAGIC.to_vec();
+        boc.push(0x40 | size as u8);
+        boc.push(offset_size as u8);
+        for value in [count, 1, 0] {
+            boc.extend_from_slice(&(value as u64).to_be_bytes()[8 - size..]);
+        }
+        boc.extend_from_slice(&(cells.len() as u64).to_be_bytes()[8 - offset_size..]);
+        boc.extend_from_slice(&0u64.to_be_bytes()[8 - size..]);
+        boc.extend_from_slice(&cells);
+        boc.extend_from_slice(&crc32c(&boc).to_le_bytes());
+        boc
+    }
+
+    /// Parse a bag of cells with a single root
+    pub fn from_boc(boc: &[u8]) -> Result<Cell> {
+        let invalid = |reason: &str| Error::Serialization(format!("Invalid BOC: {}", reason));
+        let mut reader = BocReader { boc, position: 0 };
+        if reader.bytes(4)? != BOC_MAGIC {
+            return Err(invalid("unknown magic"));
+        }
+        let flags = reader.bytes(1)?[0];
+        let (has_index, has_crc, size) = (flags & 0x80 != 0, flags & 0x40 != 0, (flags & 0x07) as usize);
+        let offset_size = reader.bytes(1)?[0] as usize;
+        let count = reader.uint(size)? as usize;
+        let roots = reader.uint(size)?;
+        let _absent = reader.uint(size)?;
+        let _total_size = reader.uint(offset_size)?;
+        if roots != 1 {
+            return Err(invalid("expected a single root"));
+        }
+        let root = reader.uint(size)? as usize;
+        if has_index {
+            reader.bytes(count * offset_size)?;
+        }
+        if has_crc {
+            let (content, checksum) = boc.split_at(boc.len().checked_sub(4).ok_or_else(|| invalid("truncated"))?);
+            if crc32c(content).to_le_bytes() != checksum {
+                return Err(invalid("CRC mismatch"));
+            }
+        }
+
+        let mut raw = Vec::with_capacity(count);
+        for _ in 0..count {
+            let descriptors = reader.bytes(2)?;
+            let (ref_count, data_descriptor) = (descriptors[0] as usize, descriptors[1] as usize);
+            if ref_count > MAX_CELL_REFS || descriptors[0] & 0xf8 != 0 {
+                return Err(Error::NotSupported("Exotic cells and cells with levels are not supported".to_string()));
+            }
+            let mut data = reader.bytes(data_descriptor.div_ceil(2))?.to_vec();
+            let bit_len = if data_descriptor % 2 == 0 {
+                data.len() * 8
+            } else {
+                // Strip the completion tag: the last 1 bit and the zeros after it
+                let last = data.pop().ok_or_else(|| invalid("empty padded data"))?;
+                if last == 0 {
+                    return Err(invalid("missing completion tag"));
+                }
+                let bits = 7 - last.trailing_zeros() as usize;
+                data.push(last & !(0xff >> bits));
+                data.len() * 8 - 8 + bits
+            };
+            if bit_len % 8 == 0 {
+                data.truncate(bit_len / 8);
+            }
+            let refs = (0..ref_count).map(|_| reader.uint(size).map(|index| index as usize)).collect::<Result<Vec<_>>>()?;
+            raw.push((data, bit_len, refs));
+        }
+
+        // Cells only reference later cells, so build them back to front
+        let mut cells: Vec<Option<Cell>> = vec![None; count];
+        for (index, (data, bit_len, refs)) in raw.into_iter().enumerate().rev() {
+            let refs = refs.iter()
+                .map(|child| match child {
+                    child if *child > index => cells[*child].clone().ok_or_else(|| invalid("dangling reference")),
+                    _ => Err(invalid("reference to an earlier cell")),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            cells[index] = Some(Cell { data, bit_len, refs });
+        }
+        cells.get(root).cloned().flatten().ok_or_else(|| invalid("root out of range"))
+    }
+
+    /// Serialize as a base64 BOC, as TON APIs take it
+    pub fn to_base64(&self) -> String {
+        base64::engine::general_purpose::STANDARD.encode(self.to_boc())
+    }
+
+    /// Parse a base64 BOC
+    pub fn from_base64(boc: &str) -> Result<Cell> {
+        let bytes = base64::engine::general_purpose::STANDARD.decode(boc)
+            .map_err(|e| Error::Serialization(format!("Invalid BOC base64: {}", e)))?;
+        Self::from_boc(&bytes)
+    }
+}
+
+fn collect_post_order(cell: &Cell, order: &mut Vec<Cell>, indices: &mut HashMap<[u8; 32], usize>) {
+    let hash = cell.hash();
+    if indices.contains_key(&hash) {
+        return;
+    }
+    for child in &cell.refs {
+        collect_post_order(child, order, indices);
+    }
+    indices.insert(hash, order.len());
+    order.push(cell.clone());
+}
+
+fn bytes_needed(value: u64) -> usize {
+    ((64 - value.leading_zeros() as usize).div_ceil(8)).max(1)
+}
+
+struct BocReader<'a> {
+    boc: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BocReader<'a> {
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        let bytes = self.boc.get(self.position..self.position + length)
+            .ok_or_else(|| Error::Serialization("Invalid BOC: truncated".to_string()))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn uint(&mut self, length: usize) -> Result<u64> {
+        Ok(self.bytes(length)?.iter().fold(0u64, |value, byte| value << 8 | *byte as u64))
+    }
+}
+
+/// CRC32-C (Castagnoli), as BOCs are checksummed
+fn crc32c(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| if crc & 1 != 0 { (crc >> 1) ^ 0x82f6_3b78 } else { crc >> 1 })
+    })
+}
+
+/// CRC16-XMODEM, as user-friendly addresses are checksummed
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, byte| {
+        (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 })
+    })
+}
+
+/// Builder of a cell
+#[derive(Debug, Clone, Default)]
+pub struct CellBuilder {
+    data: Vec<u8>,
+    bit_len: usize,
+    refs: Vec<Cell>,
+}
+
+impl CellBuilder {
+    /// Create an empty builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bits still free in the cell
+    pub fn remaining_bits(&self) -> usize {
+        MAX_CELL_BITS - self.bit_len
+    }
+
+    /// Store a bit
+    pub fn store_bit(&mut self, bit: bool) -> Result<&mut Self> {
+        if self.bit_len == MAX_CELL_BITS {
+            return Err(Error::Serialization("Cell overflow".to_string()));
+        }
+        if self.bit_len % 8 == 0 {
+            self.data.push(0);
+        }
+        if bit {
+            let last = self.data.len() - 1;
+            self.data[last] |= 0x80 >> (self.bit_len % 8);
+        }
+        self.bit_len += 1;
+        Ok(self)
+    }
+
+    /// Store an unsigned integer in `bits` bits
+    pub fn store_uint(&mut self, value: u128, bits: usize) -> Result<&mut Self> {
+        if bits < 128 && value >> bits != 0 {
+            return Err(Error::Serialization(format!("{} does not fit in {} bits", value, bits)));
+        }
+        for bit in (0..bits).rev() {
+            self.store_bit(bit < 128 && value >> bit & 1 == 1)?;
+        }
+        Ok(self)
+    }
+
+    /// Store bytes
+    pub fn store_bytes(&mut self, bytes: &[u8]) -> Result<&mut Self> {
+        for byte in bytes {
+            self.store_uint(*byte as u128, 8)?;
+        }
+        Ok(self)
+    }
+
+    /// Store an amount of nanotons or jettons (`VarUInteger 16`)
+    pub fn store_coins(&mut self, amount: u128) -> Result<&mut Self> {
+        let length = (128 - amount.leading_zeros() as usize).div_ceil(8);
+        if length > 15 {
+            return Err(Error::Serialization(format!("{} is too large for coins", amount)));
+        }
+        self.store_uint(length as u128, 4)?;
+        self.store_uint(amount, length * 8)
+    }
+
+    /// Store an address, or `addr_none` for `None`
+    pub fn store_address(&mut self, address: Option<&TonAddress>) -> Result<&mut Self> {
+        match address {
+            None => self.store_uint(0, 2),
+            Some(address) => {
+                self.store_uint(0b100, 3)?;
+                self.store_uint(address.workchain as u8 as u128, 8)?;
+                self.store_bytes(&address.hash)
+            }
+        }
+    }
+
+    /// Store a reference to a cell
+    pub fn store_ref(&mut self, cell: Cell) -> Result<&mut Self> {
+        if self.refs.len() == MAX_CELL_REFS {
+            return Err(Error::Serialization("Cell has too many references".to_string()));
+        }
+        self.refs.push(cell);
+        Ok(self)
+    }
+
+    /// Store `Maybe ^Cell`
+    pub fn store_maybe_ref(&mut self, cell: Option<Cell>) -> Result<&mut Self> {
+        match cell {
+            Some(cell) => self.store_bit(true)?.store_ref(cell),
+            None => self.store_bit(false),
+        }
+    }
+
+    /// Store the bits and references of another cell
+    pub fn store_cell(&mut self, cell: &Cell) -> Result<&mut Self> {
+        for bit in 0..cell.bit_len {
+            self.store_bit(cell.bit(bit))?;
+        }
+        for child in &cell.refs {
+            self.store_ref(child.clone())?;
+        }
+        Ok(self)
+    }
+
+    /// Finish the cell
+    pub fn build(&self) -> Cell {
+        Cell { data: self.data.clone(), bit_len: self.bit_len, refs: self.refs.clone() }
+    }
+}
+
+/// A TON account address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TonAddress {
+    /// Workchain: 0 for the basechain, -1 for the masterchain
+    pub workchain: i8,
+    /// Hash of the account's initial state
+    pub hash: [u8; 32],
+}
+
+/// Flags of a user-friendly TON address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TonAddressFlags {
+    /// Whether messages to the address should bounce if it has no contract
+    pub bounceable: bool,
+    /// Whether the address is for testnet
+    pub testnet: bool,
+}
+
+impl TonAddress {
+    /// Parse a raw (`0:<hex>`) or user-friendly (base64) address, with the friendly form's flags
+    pub fn parse(address: &str) -> Result<(Self, Option<TonAddressFlags>)> {
+        let invalid = |reason: &str| Error::InvalidInput(format!("Invalid TON address {}: {}", address, reason));
+        if let Some((workchain, hash)) = address.split_once(':') {
+            let workchain = workchain.parse::<i8>().map_err(|_| invalid("invalid workchain"))?;
+            let hash: [u8; 32] = hex::decode(hash).ok()
+                .and_then(|hash| hash.try_into().ok())
+                .ok_or_else(|| invalid("hash must be 32 bytes of hex"))?;
+            return Ok((Self { workchain, hash }, None));
+        }
+
+        if address.len() != 48 {
+            return Err(invalid("user-friendly addresses are 48 characters"));
+        }
+        let normalized = address.replace('-', "+").replace('_', "/");
+        let bytes = base64::engine::general_purpose::STANDARD.decode(normalized)
+            .map_err(|_| invalid("invalid base64"))?;
+        if crc16(&bytes[..34]).to_be_bytes() != bytes[34..36] {
+            return Err(invalid("invalid checksum"));
+        }
+        let testnet = bytes[0] & 0x80 != 0;
+        let bounceable = match bytes[0] & 0x7f {
+            0x11 => true,
+            0x51 => false,
+            _ => return Err(invalid("unknown flags")),
+        };
+        let hash = bytes[2..34].try_into().expect("slice is 32 bytes");
+        Ok((Self { workchain: bytes[1] as i8, hash }, Some(TonAddressFlags { bounceable, testnet })))
+    }
+
+    /// Raw form: `<workchain>:<hex hash>`
+    pub fn to_raw(&self) -> String {
+        format!("{}:{}", self.workchain, hex::encode(self.hash))
+    }
+
+    /// User-friendly form (URL-safe base64)
+    pub fn to_friendly(&self, bounceable: bool, testnet: bool) -> String {
+        let mut bytes = Vec::with_capacity(36);
+        bytes.push(if bounceable { 0x11 } else { 0x51 } | if testnet { 0x80 } else { 0 });
+        bytes.push(self.workchain as u8);
+        bytes.extend_from_slice(&self.hash);
+        let checksum = crc16(&bytes);
+        bytes.extend_from_slice(&checksum.to_be_bytes());
+        base64::engine::general_purpose::URL_SAFE.encode(bytes)
+    }
+}
+
+impl FromStr for TonAddress {
+    type Err = Error;
+
+    fn from_str(address: &str) -> Result<Self> {
+        Self::parse(address).map(|(address, _)| address)
+    }
+}
+
+impl fmt::Display for TonAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_raw())
+    }
+}
+
+/// An internal message sent by a wallet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InternalMessage {
+    /// Destination
+    pub to: TonAddress,
+    /// Whether the message bounces back if the destination fails to process it
+    pub bounce: bool,
+    /// Value in nanotons
+    pub value: u128,
+    /// Body, if any
+    pub body: Option<Cell>,
+    /// Send mode
+    pub send_mode: u8,
+}
+
+impl InternalMessage {
+    /// A transfer of TON, with a text comment if given
+    ///
+    /// Transfers bounce only to bounceable user-friendly addresses, so TON
+    /// sent to a wallet that isn't deployed yet isn't returned.
+    pub fn transfer(to: &str, value: u128, comment: Option<&str>) -> Result<Self> {
+        let (address, flags) = TonAddress::parse(to)?;
+        Ok(Self {
+            to: address,
+            bounce: flags.map(|flags| flags.bounceable).unwrap_or(false),
+            value,
+            body: comment.map(comment_cell).transpose()?,
+            send_mode: SEND_MODE_PAY_FEES_SEPARATELY,
+        })
+    }
+
+    /// A TEP-74 jetton transfer, sent to the sender's jetton wallet
+    ///
+    /// `response` receives the TON left over after fees; a comment is
+    /// forwarded to the recipient with a notification.
+    pub fn jetton_transfer(jetton_wallet: &TonAddress, to: &TonAddress, amount: u128, response: &TonAddress, comment: Option<&str>, query_id: u64) -> Result<Self> {
+        let mut body = CellBuilder::new();
+        body.store_uint(JETTON_TRANSFER_OP as u128, 32)?
+            .store_uint(query_id as u128, 64)?
+            .store_coins(amount)?
+            .store_address(Some(to))?
+            .store_address(Some(response))?
+            .store_maybe_ref(None)?;
+        match comment {
+            Some(comment) => body.store_coins(1)?.store_maybe_ref(Some(comment_cell(comment)?))?,
+            None => body.store_coins(0)?.store_bit(false)?,
+        };
+        Ok(Self {
+            to: *jetton_wallet,
+            bounce: true,
+            value: JETTON_TRANSFER_TON,
+            body: Some(body.build()),
+            send_mode: SEND_MODE_PAY_FEES_SEPARATELY,
+        })
+    }
+
+    /// Serialize as `Message` with `int_msg_info`
+    pub fn to_cell(&self) -> Result<Cell> {
+        let mut builder = CellBuilder::new();
+        builder.store_bit(false)? // int_msg_info$0
+            .store_bit(true)? // ihr_disabled
+            .store_bit(self.bounce)?
+            .store_bit(false)? // bounced
+            .store_address(None)? // src, filled in by the wallet
+            .store_address(Some(&self.to))?
+            .store_coins(self.value)?
+            .store_bit(false)? // no extra currencies
+            .store_coins(0)? // ihr_fee
+            .store_coins(0)? // fwd_fee
+            .store_uint(0, 64)? // created_lt
+            .store_uint(0, 32)? // created_at
+            .store_bit(false)?; // no state init
+        match &self.body {
+            Some(body) => builder.store_bit(true)?.store_ref(body.clone())?,
+            None => builder.store_bit(false)?,
+        };
+        Ok(builder.build())
+    }
+}
+
+/// Body of a text comment: opcode 0, then the UTF-8 text continued through references
+pub fn comment_cell(comment: &str) -> Result<Cell> {
+    let bytes = comment.as_bytes();
+    // The first cell holds the opcode; each cell holds whole bytes
+    let first = (MAX_CELL_BITS - 32) / 8;
+    let rest = MAX_CELL_BITS / 8;
+    let (head, tail) = bytes.split_at(bytes.len().min(first));
+
+    let chunks: Vec<&[u8]> = tail.chunks(rest).collect();
+    let mut next: Option<Cell> = None;
+    for chunk in chunks.iter().rev() {
+        let mut builder = CellBuilder::new();
+        builder.store_bytes(chunk)?;
+        if let Some(cell) = next.take() {
+            builder.store_ref(cell)?;
+        }
+        next = Some(builder.build());
+    }
+
+    let mut builder = CellBuilder::new();
+    builder.store_uint(0, 32)?.store_bytes(head)?;
+    if let Some(cell) = next {
+        builder.store_ref(cell)?;
+    }
+    Ok(builder.build())
+}
+
+fn wallet_v4r2_code() -> &'static Cell {
+    static CODE: OnceLock<Cell> = OnceLock::new();
+    CODE.get_or_init(|| Cell::from_base64(WALLET_V4R2_CODE).expect("wallet code is a valid BOC"))
+}
+
+/// A v4R2 wallet contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TonWallet {
+    /// Owner's ed25519 public key
+    pub public_key: [u8; 32],
+    /// Workchain
+    pub workchain: i8,
+    /// Subwallet ID, distinguishing wallets of the same key
+    pub wallet_id: u32,
+}
+
+impl TonWallet {
+    /// The basechain wallet of a public key
+    pub fn new(public_key: [u8; 32]) -> Self {
+        Self { public_key, workchain: 0, wallet_id: DEFAULT_WALLET_ID }
+    }
+
+    /// The wallet of a TON key pair
+    pub fn from_key_pair(key_pair: &KeyPair) -> Result<Self> {
+        if key_pair.key_type() != KeyType::Ton {
+            return Err(Error::Transaction("Not a TON key pair".to_string()));
+        }
+        let public_key = key_pair.public_key().as_bytes().try_into()
+            .map_err(|_| Error::Transaction("Invalid TON public key length".to_string()))?;
+        Ok(Self::new(public_key))
+    }
+
+    /// Initial data: seqno 0, subwallet ID, public key and no plugins
+    pub fn data(&self) -> Cell {
+        let build = || -> Result<Cell> {
+            let mut builder = CellBuilder::new();
+            builder.store_uint(0, 32)?
+                .store_uint(self.wallet_id as u128, 32)?
+                .store_bytes(&self.public_key)?
+                .store_bit(false)?;
+            Ok(builder.build())
+        };
+        build().expect("wallet data fits in a cell")
+    }
+
+    /// Initial state: code and data
+    pub fn state_init(&self) -> Cell {
+        let build = || -> Result<Cell> {
+            let mut builder = CellBuilder::new();
+            builder.store_uint(0b00110, 5)? // no split depth or special, code, data, no library
+                .store_ref(wallet_v4r2_code().clone())?
+                .store_ref(self.data())?;
+            Ok(builder.build())
+        };
+        build().expect("state init fits in a cell")
+    }
+
+    /// Address of the wallet
+    pub fn address(&self) -> TonAddress {
+        TonAddress { workchain: self.workchain, hash: self.state_init().hash() }
+    }
+
+    /// Sign an external message sending `messages`
+    ///
+    /// `deploy` attaches the initial state, deploying the contract; it must be
+    /// set while the wallet is uninitialized (seqno 0). The transfer is
+    /// rejected after `valid_until` (Unix seconds).
+    pub fn transfer(&self, signing_key: &SigningKey, seqno: u32, valid_until: u32, messages: &[InternalMessage], deploy: bool) -> Result<Cell> {
+        if messages.len() > MAX_WALLET_MESSAGES {
+            return Err(Error::Transaction(format!("A wallet sends at most {} messages at once", MAX_WALLET_MESSAGES)));
+        }
+        if signing_key.verifying_key().to_bytes() != self.public_key {
+            return Err(Error::Signing("Key does not own this wallet".to_string()));
+        }
+
+        let mut signing_message = CellBuilder::new();
+        signing_message.store_uint(self.wallet_id as u128, 32)?
+            .store_uint(valid_until as u128, 32)?
+            .store_uint(seqno as u128, 32)?
+            .store_uint(0, 8)?; // simple send
+        for message in messages {
+            signing_message.store_uint(message.send_mode as u128, 8)?.store_ref(message.to_cell()?)?;
+        }
+        let signing_message = signing_message.build();
+        let signature = signing_key.sign(&signing_message.hash());
+
+        let mut body = CellBuilder::new();
+        body.store_bytes(&signature.to_bytes())?.store_cell(&signing_message)?;
+
+        let mut external = CellBuilder::new();
+        external.store_uint(0b10, 2)? // ext_in_msg_info$10
+            .store_address(None)?
+            .store_address(Some(&self.address()))?
+            .store_coins(0)?; // import_fee
+        if deploy {
+            external.store_bit(true)?.store_bit(true)?.store_ref(self.state_init())?;
+        } else {
+            external.store_bit(false)?;
+        }
+        external.store_bit(true)?.store_ref(body.build())?;
+        Ok(external.build())
+    }
+}
+
+/// State of a wallet as reported by the API
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TonWalletInfo {
+    /// Balance in nanotons
+    pub balance: u128,
+    /// Whether the contract is deployed
+    pub active: bool,
+    /// Sequence number the next transfer must carry
+    pub seqno: u32,
+}
+
+/// TON provider, backed by a toncenter-compatible API (e.g. `https://toncenter.com/api/v2`)
+pub struct TonProvider {
+    /// Provider configuration
+    config: ProviderConfig,
+    /// Whether the API serves testnet
+    testnet: bool,
+    /// HTTP client
+    #[cfg(feature = "rpc")]
+    http: reqwest::Client,
+}
+
+impl TonProvider {
+    /// Create a new TON provider
+    pub fn new(config: ProviderConfig) -> Result<Self> {
+        let testnet = config.url.contains("testnet");
+        #[cfg(feature = "rpc")]
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(config.timeout.unwrap_or(30)))
+            .build()
+            .map_err(|e| Error::Provider(format!("Failed to create HTTP client: {}", e)))?;
+
+        Ok(Self {
+            config,
+            testnet,
+            #[cfg(feature = "rpc")]
+            http,
+        })
+    }
+
+    /// Get the network this provider is connected to
+    pub fn network_binding(&self) -> NetworkBinding {
+        NetworkBinding::Ton { global_id: if self.testnet { TON_TESTNET_GLOBAL_ID } else { TON_MAINNET_GLOBAL_ID } }
+    }
+
+    #[cfg(feature = "rpc")]
+    fn call(&self, method: &str, query: &[(&str, String)], body: Option<Value>) -> Result<Value> {
+        let url = format!("{}/{}", self.config.url.trim_end_matches('/'), method);
+        let mut request = match body {
+            Some(body) => self.http.post(&url).json(&body),
+            None => self.http.get(&url).query(query),
+        };
+        if let Some(api_key) = &self.config.api_key {
+            request = request.header("X-API-Key", api_key);
+        }
+        let response: Value = super::ethereum::block_on(super::ethereum::traced(super::ethereum::rpc_span("ton", &url, method), async {
+            request.send().await
+                .map_err(|e| Error::Network(format!("toncenter request failed: {}", e)))?
+                .json().await
+                .map_err(|e| Error::Provider(format!("Invalid toncenter response: {}", e)))
+        }))??;
+        if response["ok"].as_bool() != Some(true) {
+            return Err(Error::Provider(format!("toncenter {} failed: {}", method, response["error"])));
+        }
+        Ok(response["result"].clone())
+    }
+
+    #[cfg(not(feature = "rpc"))]
+    fn call(&self, _method: &str, _query: &[(&str, String)], _body: Option<Value>) -> Result<Value> {
+        Err(Error::NotSupported("TON requires the rpc feature".to_string()))
+    }
+
+    /// Get the balance, state and seqno of a wallet
+    pub fn get_wallet_info(&self, address: &str) -> Result<TonWalletInfo> {
+        parse_wallet_info(&self.call("getWalletInformation", &[("address", address.to_string())], None)?)
+    }
+
+    /// Get the jetton wallet of `owner` for a jetton master contract
+    pub fn get_jetton_wallet(&self, owner: &TonAddress, jetton_master: &str) -> Result<TonAddress> {
+        let mut slice = CellBuilder::new();
+        slice.store_address(Some(owner))?;
+        let body = serde_json::json!({
+            "address": jetton_master,
+            "method": "get_wallet_address",
+            "stack": [["tvm.Slice", slice.build().to_base64()]],
+        });
+        let result = self.call("runGetMethod", &[], Some(body))?;
+        let boc = result["stack"][0][1]["bytes"].as_str()
+            .ok_or_else(|| Error::Provider(format!("Invalid get_wallet_address result: {}", result)))?;
+        Cell::from_base64(boc)?.read_address(0)
+    }
+
+    /// Send TON with an optional comment, deploying the wallet if needed
+    pub fn transfer(&self, key_pair: &KeyPair, to: &str, value: u128, comment: Option<&str>, now: u64) -> Result<String> {
+        let message = InternalMessage::transfer(to, value, comment)?;
+        self.send(key_pair, &[message], now)
+    }
+
+    /// Send TON as described by a request, its memo becoming the comment
+    pub fn send_request(&self, key_pair: &KeyPair, request: &TransactionRequest, now: u64) -> Result<String> {
+        if request.key_type != KeyType::Ton {
+            return Err(Error::Transaction("Not a TON transaction".to_string()));
+        }
+        request.network.ensure_matches(&self.network_binding())?;
+        let value = request.value.parse::<u128>()
+            .map_err(|e| Error::InvalidInput(format!("Invalid value: {}", e)))?;
+        self.transfer(key_pair, &request.to, value, request.memo_text()?, now)
+    }
+
+    /// Send jettons with an optional comment, deploying the wallet if needed
+    pub fn transfer_jetton(&self, key_pair: &KeyPair, jetton_master: &str, to: &str, amount: u128, comment: Option<&str>, now: u64) -> Result<String> {
+        let owner = TonWallet::from_key_pair(key_pair)?.address();
+        let jetton_wallet = self.get_jetton_wallet(&owner, jetton_master)?;
+        let message = InternalMessage::jetton_transfer(&jetton_wallet, &to.parse()?, amount, &owner, comment, now)?;
+        self.send(key_pair, &[message], now)
+    }
+
+    /// Deploy the wallet contract without sending anything
+    pub fn deploy(&self, key_pair: &KeyPair, now: u64) -> Result<String> {
+        self.send(key_pair, &[], now)
+    }
+
+    /// Sign and broadcast messages from a wallet, returning the external message hash (hex)
+    pub fn send(&self, key_pair: &KeyPair, messages: &[InternalMessage], now: u64) -> Result<String> {
+        let wallet = TonWallet::from_key_pair(key_pair)?;
+        let info = self.get_wallet_info(&wallet.address().to_raw())?;
+        if !info.active && messages.is_empty() && info.balance == 0 {
+            return Err(Error::Transaction("Fund the wallet before deploying it".to_string()));
+        }
+        let secret: [u8; 32] = key_pair.private_key().as_bytes().try_into()
+            .map_err(|_| Error::Signing("Invalid ed25519 private key length".to_string()))?;
+        let external = wallet.transfer(&SigningKey::from_bytes(&secret), info.seqno, (now + 60) as u32, messages, !info.active)?;
+        self.broadcast_transaction(&external.to_boc())
+    }
+}
+
+/// Parse toncenter's `getWalletInformation` result
+pub fn parse_wallet_info(result: &Value) -> Result<TonWalletInfo> {
+    let balance = result["balance"].as_str().and_then(|balance| balance.parse().ok())
+        .or_else(|| result["balance"].as_u64().map(u128::from))
+        .ok_or_else(|| Error::Provider(format!("Invalid wallet information: {}", result)))?;
+    Ok(TonWalletInfo {
+        balance,
+        active: result["account_state"].as_str() == Some("active"),
+        seqno: result["seqno"].as_u64().unwrap_or(0) as u32,
+    })
+}
+
+/// Parse toncenter's `getTransactions` result for `address`
+pub fn parse_transactions(result: &Value, address: &str) -> Result<Vec<Transaction>> {
+    let entries = result.as_array()
+        .ok_or_else(|| Error::Provider(format!("Invalid transactions: {}", result)))?;
+    let nanotons = |value: &Value| value.as_str().and_then(|value| value.parse::<u128>().ok()).unwrap_or(0);
+
+    entries.iter()
+        .map(|entry| {
+            let hash = base64::engine::general_purpose::STANDARD.decode(entry["transaction_id"]["hash"].as_str().unwrap_or_default())
+                .map_err(|_| Error::Provider(format!("Invalid transaction: {}", entry)))?;
+            let incoming = &entry["in_msg"];
+            let outgoing = entry["out_msgs"].as_array().and_then(|messages| messages.first());
+            // Internal messages carry a source; external ones (the wallet's own transfers) don't
+            let (from, to, value, message) = match (incoming["source"].as_str().filter(|source| !source.is_empty()), outgoing) {
+                (Some(source), _) => (source.to_string(), address.to_string(), nanotons(&incoming["value"]), &incoming["message"]),
+                (None, Some(outgoing)) => (address.to_string(), outgoing["destination"].as_str().unwrap_or_default().to_string(), nanotons(&outgoing["value"]), &outgoing["message"]),
+                (None, None) => (address.to_string(), String::new(), 0, &Value::Null),
+            };
+            Ok(Transaction {
+                hash: hex::encode(hash),
+                transaction_type: TransactionType::Transfer,
+                key_type: KeyType::Ton,
+                from,
+                to,
+                value: value.to_string(),
+                gas_price: None,
+                gas_limit: None,
+                nonce: None,
+                data: None,
+                status: TransactionStatus::Confirmed,
+                block_number: entry["transaction_id"]["lt"].as_str().and_then(|lt| lt.parse().ok()),
+                timestamp: entry["utime"].as_u64(),
+                fee: entry["fee"].as_str().map(str::to_string),
+                memo: message.as_str().filter(|comment| !comment.is_empty()).map(|comment| Memo::Text(comment.to_string())),
+            })
+        })
+        .collect()
+}
+
+impl TransactionSigner for TonProvider {
+    fn sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        if request.key_type != KeyType::Ton {
+            return Err(Error::Transaction("Not a TON transaction".to_string()));
+        }
+        request.network.ensure_matches(&self.network_binding())?;
+
+        // Wallet transfers need the seqno and the owner's key, which requests don't carry
+        Err(Error::NotSupported("TON transfers are signed by TonProvider::send_request with the wallet's key pair".to_string()))
+    }
+}
+
+impl TransactionBroadcaster for TonProvider {
+    fn broadcast_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+        let hash = Cell::from_boc(signed_transaction)?.hash();
+        let boc = base64::engine::general_purpose::STANDARD.encode(signed_transaction);
+        self.call("sendBoc", &[], Some(serde_json::json!({ "boc": boc })))?;
+        Ok(hex::encode(hash))
+    }
+
+    fn get_transaction_status(&self, _hash: &str) -> Result<TransactionStatus> {
+        Err(Error::NotSupported("TON transactions are looked up by account; use get_transactions".to_string()))
+    }
+
+    fn get_transaction_receipt(&self, _hash: &str) -> Result<TransactionReceipt> {
+        Err(Error::NotSupported("TON transactions are looked up by account; use get_transactions".to_string()))
+    }
+}
+
+impl TransactionManager for TonProvider {
+    fn get_transaction(&self, _hash: &str) -> Result<Transaction> {
+        Err(Error::NotSupported("TON transactions are looked up by account; use get_transactions".to_string()))
+    }
+
+    fn get_transactions(&self, address: &str, limit: usize, offset: usize) -> Result<Vec<Transaction>> {
+        // toncenter pages by logical time, so fetch through the offset and skip it
+        let result = self.call("getTransactions", &[("address", address.to_string()), ("limit", (limit + offset).to_string())], None)?;
+        Ok(parse_transactions(&result, address)?.into_iter().skip(offset).take(limit).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY: &str = "454c4e5fccaf1ff954e7f6e2c3457caec7309480e2ae260203704265551c55b7";
+    const ADDRESS: &str = "0:c09b382408ffd0aa4660eec86caa2d9033f1520626d5bb8ee0339bd3164edd9c";
+
+    fn wallet() -> TonWallet {
+        TonWallet::new(hex::decode(PUBLIC_KEY).unwrap().try_into().unwrap())
+    }
+
+    #[test]
+    fn test_wallet_code() {
+        let code = wallet_v4r2_code();
+        assert_eq!(hex::encode(code.hash()), "feb5ff6820e2ff0d9483e7e0d62c817d846789fb4ae580c878866d959dabd5c0");
+        assert_eq!(code.depth(), 7);
+        assert_eq!(&Cell::from_boc(&code.to_boc()).unwrap(), code);
+    }
+
+    #[test]
+    fn test_wallet_address() {
+        let address = wallet().address();
+        assert_eq!(address.to_raw(), ADDRESS);
+        assert_eq!(address.to_friendly(true, false), "EQDAmzgkCP_QqkZg7shsqi2QM_FSBibVu47gM5vTFk7dnMav");
+        assert_eq!(address.to_friendly(false, false), "UQDAmzgkCP_QqkZg7shsqi2QM_FSBibVu47gM5vTFk7dnJtq");
+
+        let (parsed, flags) = TonAddress::parse("EQDAmzgkCP_QqkZg7shsqi2QM_FSBibVu47gM5vTFk7dnMav").unwrap();
+        assert_eq!(parsed, address);
+        assert_eq!(flags, Some(TonAddressFlags { bounceable: true, testnet: false }));
+        assert_eq!(ADDRESS.parse::<TonAddress>().unwrap(), address);
+        assert!(TonAddress::parse("EQDAmzgkCP_QqkZg7shsqi2QM_FSBibVu47gM5vTFk7dnMaw").is_err());
+    }
+
+    #[test]
+    fn test_cell_builder() {
+        let mut builder = CellBuilder::new();
+        builder.store_uint(0b101, 3).unwrap().store_coins(1_000_000_000).unwrap();
+        let cell = builder.build();
+        assert_eq!(cell.bit_len(), 3 + 4 + 32);
+        assert_eq!(cell.read_uint(0, 3).unwrap(), 0b101);
+        assert_eq!(cell.read_uint(3, 4).unwrap(), 4);
+        assert_eq!(cell.read_uint(7, 32).unwrap(), 1_000_000_000);
+        assert_eq!(Cell::from_boc(&cell.to_boc()).unwrap(), cell);
+
+        assert!(CellBuilder::new().store_uint(4, 2).is_err());
+        let mut full = CellBuilder::new();
+        full.store_uint(0, 1000).unwrap();
+        assert!(full.store_uint(0, 24).is_err());
+    }
+
+    #[test]
+    fn test_comment_cell() {
+        let short = comment_cell("hello").unwrap();
+        assert_eq!(short.read_uint(0, 32).unwrap(), 0);
+        assert_eq!(short.bit_len(), 32 + 40);
+        assert!(short.refs().is_empty());
+
+        let long = comment_cell(&"a".repeat(300)).unwrap();
+        assert_eq!(long.bit_len(), 32 + 123 * 8);
+        assert_eq!(long.refs()[0].bit_len(), 127 * 8);
+        assert_eq!(long.refs()[0].refs()[0].bit_len(), 50 * 8);
+    }
+
+    #[test]
+    fn test_transfer() {
+        let signing_key = SigningKey::from_bytes(&hex::decode("b839d2a30b47fd21edbcadf732cb4e69bc36d44889c7e731705c9f8fb894cd45").unwrap().try_into().unwrap());
+        let wallet = wallet();
+        let message = InternalMessage::transfer("UQDAmzgkCP_QqkZg7shsqi2QM_FSBibVu47gM5vTFk7dnJtq", 1_000_000, Some("thanks")).unwrap();
+        assert!(!message.bounce);
+
+        let external = wallet.transfer(&signing_key, 0, 1_700_000_000, &[message.clone()], true).unwrap();
+        let boc = external.to_boc();
+        assert_eq!(Cell::from_boc(&boc).unwrap(), external);
+        // Deploying attaches the state init, then the signed body
+        assert_eq!(external.refs().len(), 2);
+        assert_eq!(external.refs()[0], wallet.state_init());
+        assert_eq!(external.read_address(4).unwrap(), wallet.address());
+
+        let body = &external.refs()[1];
+        let signature = ed25519_dalek::Signature::from_slice(&body.data()[..64]).unwrap();
+        let mut signed = CellBuilder::new();
+        for bit in 512..body.bit_len() {
+            signed.store_uint(body.read_uint(bit, 1).unwrap(), 1).unwrap();
+        }
+        signed.store_ref(body.refs()[0].clone()).unwrap();
+        signing_key.verifying_key().verify_strict(&signed.build().hash(), &signature).unwrap();
+
+        let other = SigningKey::from_bytes(&[1u8; 32]);
+        assert!(wallet.transfer(&other, 0, 1_700_000_000, &[message.clone()], false).is_err());
+        assert!(wallet.transfer(&signing_key, 0, 1_700_000_000, &vec![message; 5], false).is_err());
+    }
+
+    #[test]
+    fn test_jetton_transfer() {
+        let owner = wallet().address();
+        let message = InternalMessage::jetton_transfer(&owner, &owner, 5_000_000, &owner, Some("memo"), 7).unwrap();
+        let body = message.body.unwrap();
+        assert_eq!(body.read_uint(0, 32).unwrap(), JETTON_TRANSFER_OP as u128);
+        assert_eq!(body.read_uint(32, 64).unwrap(), 7);
+        assert_eq!(body.read_uint(96, 4).unwrap(), 3);
+        assert_eq!(body.read_uint(100, 24).unwrap(), 5_000_000);
+        assert_eq!(body.read_address(124).unwrap(), owner);
+        assert_eq!(body.refs().len(), 1);
+        assert_eq!(message.value, JETTON_TRANSFER_TON);
+    }
+
+    #[test]
+    fn test_parse_transactions() {
+        let result = serde_json::json!([
+            {
+                "utime": 1_700_000_000u64,
+                "transaction_id": { "lt": "4000000000001", "hash": "AAECAwQFBgcICQABAgMEBQYHCAkAAQIDBAUGBwgJAQI=" },
+                "fee": "1000",
+                "in_msg": { "source": "EQSender", "destination": ADDRESS, "value": "2500000000", "message": "hi" },
+                "out_msgs": [],
+            },
+            {
+                "utime": 1_700_000_100u64,
+                "transaction_id": { "lt": "4000000000002", "hash": "AAECAwQFBgcICQABAgMEBQYHCAkAAQIDBAUGBwgJAQI=" },
+                "fee": "2000",
+                "in_msg": { "source": "", "destination": ADDRESS, "value": "0", "message": "" },
+                "out_msgs": [{ "source": ADDRESS, "destination": "EQRecipient", "value": "1000000", "message": "" }],
+            },
+        ]);
+        let transactions = parse_transactions(&result, ADDRESS).unwrap();
+        assert_eq!(transactions[0].from, "EQSender");
+        assert_eq!(transactions[0].value, "2500000000");
+        assert_eq!(transactions[0].memo, Some(Memo::Text("hi".to_string())));
+        assert_eq!(transactions[1].from, ADDRESS);
+        assert_eq!(transactions[1].to, "EQRecipient");
+        assert_eq!(transactions[1].block_number, Some(4_000_000_000_002));
+
+        let info = parse_wallet_info(&serde_json::json!({ "balance": "1500", "account_state": "uninitialized", "seqno": null })).unwrap();
+        assert_eq!(info, TonWalletInfo { balance: 1_500, active: false, seqno: 0 });
+    }
+}
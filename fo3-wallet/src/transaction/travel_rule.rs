@@ -0,0 +1,758 @@
+//! Travel rule data exchange for withdrawals
+//!
+//! For deployments operating as a VASP, withdrawals at or above a fiat
+//! threshold carry IVMS101 originator and beneficiary data to the
+//! beneficiary's VASP through a [`TravelRuleMessenger`]. The withdrawal is
+//! held by a [`TravelRuleGate`] until the counterparty accepts it, and every
+//! step is written to a [`TravelRuleAudit`] trail.
+//!
+//! The gate values withdrawals itself from the deployment's prices. Contract
+//! calls, which may move tokens the value field does not show, and assets
+//! without a price are treated as above the threshold.
+//!
+//! The bundled [`TrpMessenger`] speaks the OpenVASP Travel Rule Protocol:
+//! it posts an inquiry to the beneficiary VASP, whose approval or rejection
+//! arrives later on a callback and is fed back through
+//! [`TravelRuleGate::record_response`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::account::export::native_asset;
+use crate::crypto::keys::KeyType;
+use crate::error::{Error, Result};
+use super::types::TransactionRequest;
+
+/// IVMS101 name identifier type for legal names
+pub const LEGAL_NAME: &str = "LEGL";
+
+/// IVMS101 natural person name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NaturalPersonNameId {
+    /// Family name
+    pub primary_identifier: String,
+    /// Given names
+    pub secondary_identifier: Option<String>,
+    /// Name type (`LEGL` for legal names)
+    pub name_identifier_type: String,
+}
+
+/// IVMS101 legal person name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegalPersonNameId {
+    /// Registered name
+    pub legal_person_name: String,
+    /// Name type (`LEGL` for legal names)
+    pub legal_person_name_identifier_type: String,
+}
+
+/// IVMS101 geographic address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeographicAddress {
+    /// Address lines
+    pub address_line: Vec<String>,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+}
+
+/// IVMS101 natural person
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NaturalPerson {
+    /// Names
+    pub name: Vec<NaturalPersonNameId>,
+    /// Addresses
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub geographic_address: Vec<GeographicAddress>,
+    /// Date of birth (YYYY-MM-DD)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_of_birth: Option<String>,
+    /// ISO 3166-1 alpha-2 country of residence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country_of_residence: Option<String>,
+}
+
+/// IVMS101 legal person
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegalPerson {
+    /// Names
+    pub name: Vec<LegalPersonNameId>,
+    /// Addresses
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub geographic_address: Vec<GeographicAddress>,
+    /// Legal entity identifier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lei: Option<String>,
+    /// ISO 3166-1 alpha-2 country of registration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country_of_registration: Option<String>,
+}
+
+/// IVMS101 person
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Person {
+    /// A natural person
+    NaturalPerson(NaturalPerson),
+    /// A legal person
+    LegalPerson(LegalPerson),
+}
+
+impl Person {
+    /// Create a natural person from a legal name
+    pub fn natural(family_name: &str, given_names: &str) -> Self {
+        Self::NaturalPerson(NaturalPerson {
+            name: vec![NaturalPersonNameId {
+                primary_identifier: family_name.to_string(),
+                secondary_identifier: Some(given_names.to_string()).filter(|names| !names.is_empty()),
+                name_identifier_type: LEGAL_NAME.to_string(),
+            }],
+            geographic_address: Vec::new(),
+            date_of_birth: None,
+            country_of_residence: None,
+        })
+    }
+
+    /// Create a legal person from a registered name
+    pub fn legal(name: &str, lei: Option<&str>) -> Self {
+        Self::LegalPerson(LegalPerson {
+            name: vec![LegalPersonNameId {
+                legal_person_name: name.to_string(),
+                legal_person_name_identifier_type: LEGAL_NAME.to_string(),
+            }],
+            geographic_address: Vec::new(),
+            lei: lei.map(str::to_string),
+            country_of_registration: None,
+        })
+    }
+
+    fn validate(&self) -> Result<()> {
+        let named = match self {
+            Self::NaturalPerson(person) => person.name.iter().any(|name| !name.primary_identifier.trim().is_empty()),
+            Self::LegalPerson(person) => person.name.iter().any(|name| !name.legal_person_name.trim().is_empty()),
+        };
+
+        if !named {
+            return Err(Error::InvalidInput("IVMS101 person must have a name".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// IVMS101 originator
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Originator {
+    /// Originating persons
+    pub originator_persons: Vec<Person>,
+    /// Originating addresses or account numbers
+    pub account_number: Vec<String>,
+}
+
+/// IVMS101 beneficiary
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Beneficiary {
+    /// Beneficiary persons
+    pub beneficiary_persons: Vec<Person>,
+    /// Beneficiary addresses or account numbers
+    pub account_number: Vec<String>,
+}
+
+/// IVMS101 VASP
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Vasp {
+    /// The VASP as a legal person
+    pub legal_person: Person,
+}
+
+/// IVMS101 identity payload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityPayload {
+    /// Originator
+    pub originator: Originator,
+    /// Beneficiary
+    pub beneficiary: Beneficiary,
+    /// Originating VASP
+    #[serde(rename = "originatingVASP")]
+    pub originating_vasp: Vasp,
+    /// Beneficiary VASP, if the beneficiary is hosted
+    #[serde(rename = "beneficiaryVASP", default, skip_serializing_if = "Option::is_none")]
+    pub beneficiary_vasp: Option<Vasp>,
+}
+
+impl IdentityPayload {
+    /// Check that the payload identifies both parties
+    pub fn validate(&self) -> Result<()> {
+        if self.originator.originator_persons.is_empty() || self.beneficiary.beneficiary_persons.is_empty() {
+            return Err(Error::InvalidInput("IVMS101 payload must identify the originator and the beneficiary".to_string()));
+        }
+        if self.originator.account_number.is_empty() || self.beneficiary.account_number.is_empty() {
+            return Err(Error::InvalidInput("IVMS101 payload must include account numbers".to_string()));
+        }
+
+        self.originator.originator_persons.iter()
+            .chain(&self.beneficiary.beneficiary_persons)
+            .chain(std::iter::once(&self.originating_vasp.legal_person))
+            .try_for_each(Person::validate)
+    }
+}
+
+/// A withdrawal subject to travel rule checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Withdrawal {
+    /// Transaction to release once cleared
+    pub request: TransactionRequest,
+    /// Beneficiary data
+    pub beneficiary: Beneficiary,
+    /// Beneficiary VASP, or `None` for unhosted wallets
+    pub beneficiary_vasp: Option<Vasp>,
+    /// Travel rule endpoint of the beneficiary VASP
+    pub counterparty_endpoint: Option<String>,
+}
+
+/// Response of the beneficiary VASP
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CounterpartyResponse {
+    /// No answer yet
+    Pending,
+    /// The beneficiary VASP accepted the transfer
+    Accepted,
+    /// The beneficiary VASP rejected the transfer
+    Rejected { reason: String },
+}
+
+/// Transport for travel rule messages
+pub trait TravelRuleMessenger: Send + Sync {
+    /// Send the identity payload for a transfer, returning the message ID
+    fn send(&self, transfer: &TravelRuleTransfer) -> Result<String>;
+
+    /// Poll the counterparty response to a message
+    ///
+    /// Callback-based protocols may always report `Pending` and deliver the
+    /// response through [`TravelRuleGate::record_response`] instead.
+    fn poll(&self, message_id: &str) -> Result<CounterpartyResponse>;
+}
+
+/// Travel rule settings of a deployment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TravelRulePolicy {
+    /// Value in US cents at or above which the rule applies
+    pub threshold: i64,
+    /// The originating VASP
+    pub originating_vasp: Vasp,
+    /// Seconds to wait for the counterparty before the transfer expires
+    pub response_timeout: u64,
+    /// Whether withdrawals to unhosted wallets above the threshold are released without a counterparty
+    pub allow_unhosted: bool,
+}
+
+/// State of a travel rule transfer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferState {
+    /// Below the threshold; may be released
+    NotRequired,
+    /// Unhosted beneficiary wallet; may be released with the payload kept on record
+    Unhosted,
+    /// Waiting for the beneficiary VASP
+    AwaitingCounterparty,
+    /// Accepted by the beneficiary VASP; may be released
+    Accepted,
+    /// Rejected by the beneficiary VASP
+    Rejected { reason: String },
+    /// No response within the timeout
+    Expired,
+    /// The transaction was released for signing
+    Released,
+}
+
+/// A withdrawal tracked by the gate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TravelRuleTransfer {
+    /// Transfer ID
+    pub id: String,
+    /// The withdrawal
+    pub withdrawal: Withdrawal,
+    /// Value in US cents as priced by the gate, or `None` if it could not be valued
+    pub fiat_value: Option<i64>,
+    /// Identity payload sent to the counterparty (absent below the threshold)
+    pub payload: Option<IdentityPayload>,
+    /// Message ID assigned by the messenger
+    pub message_id: Option<String>,
+    /// Current state
+    pub state: TransferState,
+    /// Unix timestamp of submission
+    pub created_at: u64,
+}
+
+/// Audit event of the travel rule gate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TravelRuleEvent {
+    /// Transfer ID
+    pub transfer_id: String,
+    /// State after the event
+    pub state: TransferState,
+    /// Human-readable detail
+    pub detail: String,
+    /// Unix timestamp of the event
+    pub timestamp: u64,
+}
+
+/// Audit trail for travel rule decisions
+pub trait TravelRuleAudit: Send + Sync {
+    /// Record an event
+    fn record(&self, event: TravelRuleEvent);
+}
+
+impl<T: TravelRuleAudit + ?Sized> TravelRuleAudit for Arc<T> {
+    fn record(&self, event: TravelRuleEvent) {
+        (**self).record(event);
+    }
+}
+
+/// In-memory travel rule audit trail
+#[derive(Debug, Default)]
+pub struct InMemoryTravelRuleAudit {
+    events: Mutex<Vec<TravelRuleEvent>>,
+}
+
+impl InMemoryTravelRuleAudit {
+    /// Create an empty trail
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the events of a transfer
+    pub fn events(&self, transfer_id: &str) -> Vec<TravelRuleEvent> {
+        self.events.lock().unwrap().iter()
+            .filter(|event| event.transfer_id == transfer_id)
+            .cloned()
+            .collect()
+    }
+}
+
+impl TravelRuleAudit for InMemoryTravelRuleAudit {
+    fn record(&self, event: TravelRuleEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+/// Holds withdrawals until the travel rule exchange completes
+pub struct TravelRuleGate {
+    policy: TravelRulePolicy,
+    originator: Box<dyn Fn(&TransactionRequest) -> Result<Originator> + Send + Sync>,
+    price: Box<dyn Fn(&str) -> Result<Option<f64>> + Send + Sync>,
+    messenger: Box<dyn TravelRuleMessenger>,
+    audit: Box<dyn TravelRuleAudit>,
+    transfers: Mutex<HashMap<String, TravelRuleTransfer>>,
+}
+
+impl TravelRuleGate {
+    /// Create a gate
+    ///
+    /// `originator` looks up the KYC data of the account sending a withdrawal,
+    /// and `price` the USD price of an asset symbol, if known.
+    pub fn new(
+        policy: TravelRulePolicy,
+        originator: impl Fn(&TransactionRequest) -> Result<Originator> + Send + Sync + 'static,
+        price: impl Fn(&str) -> Result<Option<f64>> + Send + Sync + 'static,
+        messenger: Box<dyn TravelRuleMessenger>,
+        audit: Box<dyn TravelRuleAudit>,
+    ) -> Self {
+        Self {
+            policy,
+            originator: Box::new(originator),
+            price: Box::new(price),
+            messenger,
+            audit,
+            transfers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Submit a withdrawal
+    pub fn submit(&self, withdrawal: Withdrawal, now: u64) -> Result<TravelRuleTransfer> {
+        let mut transfer = TravelRuleTransfer {
+            // The ID is also the callback path given to the counterparty, so it must not be guessable
+            id: format!("trp_{}", hex::encode(rand::random::<[u8; 16]>())),
+            fiat_value: self.fiat_value(&withdrawal.request)?,
+            withdrawal,
+            payload: None,
+            message_id: None,
+            state: TransferState::NotRequired,
+            created_at: now,
+        };
+
+        let detail = if transfer.fiat_value.is_some_and(|value| value < self.policy.threshold) {
+            "Below travel rule threshold".to_string()
+        } else {
+            let payload = IdentityPayload {
+                originator: (self.originator)(&transfer.withdrawal.request)?,
+                beneficiary: transfer.withdrawal.beneficiary.clone(),
+                originating_vasp: self.policy.originating_vasp.clone(),
+                beneficiary_vasp: transfer.withdrawal.beneficiary_vasp.clone(),
+            };
+            payload.validate()?;
+            transfer.payload = Some(payload);
+
+            if transfer.withdrawal.beneficiary_vasp.is_none() {
+                if !self.policy.allow_unhosted {
+                    return Err(Error::Compliance("Withdrawals above the travel rule threshold to unhosted wallets are not allowed".to_string()));
+                }
+                transfer.state = TransferState::Unhosted;
+                "Unhosted beneficiary wallet; payload kept on record".to_string()
+            } else {
+                let message_id = self.messenger.send(&transfer)?;
+                transfer.state = TransferState::AwaitingCounterparty;
+                let detail = format!("Identity payload sent as message {}", message_id);
+                transfer.message_id = Some(message_id);
+                detail
+            }
+        };
+
+        self.audit(&transfer, detail, now);
+        self.transfers.lock().unwrap().insert(transfer.id.clone(), transfer.clone());
+        Ok(transfer)
+    }
+
+    /// Get a transfer
+    pub fn transfer(&self, id: &str) -> Result<TravelRuleTransfer> {
+        self.transfers.lock().unwrap().get(id)
+            .cloned()
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown travel rule transfer: {}", id)))
+    }
+
+    /// Record a counterparty response delivered by callback
+    ///
+    /// A response arriving after the timeout expires the transfer instead.
+    pub fn record_response(&self, id: &str, response: CounterpartyResponse, now: u64) -> Result<TravelRuleTransfer> {
+        let mut transfers = self.transfers.lock().unwrap();
+        let transfer = transfers.get_mut(id)
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown travel rule transfer: {}", id)))?;
+
+        if transfer.state != TransferState::AwaitingCounterparty {
+            return Err(Error::InvalidInput(format!("Travel rule transfer {} is not awaiting a response", id)));
+        }
+
+        let detail = match response {
+            _ if now >= transfer.created_at.saturating_add(self.policy.response_timeout) => {
+                transfer.state = TransferState::Expired;
+                "No counterparty response within the timeout".to_string()
+            }
+            CounterpartyResponse::Pending => return Ok(transfer.clone()),
+            CounterpartyResponse::Accepted => {
+                transfer.state = TransferState::Accepted;
+                "Accepted by the beneficiary VASP".to_string()
+            }
+            CounterpartyResponse::Rejected { reason } => {
+                let detail = format!("Rejected by the beneficiary VASP: {}", reason);
+                transfer.state = TransferState::Rejected { reason };
+                detail
+            }
+        };
+
+        let transfer = transfer.clone();
+        drop(transfers);
+        self.audit(&transfer, detail, now);
+        Ok(transfer)
+    }
+
+    /// Poll the messenger for the counterparty response, expiring transfers past the timeout
+    pub fn poll(&self, id: &str, now: u64) -> Result<TravelRuleTransfer> {
+        let transfer = self.transfer(id)?;
+        if transfer.state != TransferState::AwaitingCounterparty {
+            return Ok(transfer);
+        }
+
+        let message_id = transfer.message_id.as_deref().unwrap_or_default();
+        let response = self.messenger.poll(message_id)?;
+        self.record_response(id, response, now)
+    }
+
+    /// Release the transaction of a cleared transfer for signing
+    ///
+    /// Fails with [`Error::Compliance`] unless the transfer is below the
+    /// threshold, unhosted and allowed, or accepted by the counterparty.
+    pub fn release(&self, id: &str, now: u64) -> Result<TransactionRequest> {
+        let mut transfers = self.transfers.lock().unwrap();
+        let transfer = transfers.get_mut(id)
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown travel rule transfer: {}", id)))?;
+
+        match &transfer.state {
+            TransferState::NotRequired | TransferState::Unhosted | TransferState::Accepted => {}
+            state => {
+                return Err(Error::Compliance(format!("Travel rule transfer {} cannot be released while {:?}", id, state)));
+            }
+        }
+
+        transfer.state = TransferState::Released;
+        let transfer = transfer.clone();
+        drop(transfers);
+
+        self.audit(&transfer, "Transaction released for signing".to_string(), now);
+        Ok(transfer.withdrawal.request)
+    }
+
+    /// Value a withdrawal in US cents, or `None` if it cannot be valued
+    fn fiat_value(&self, request: &TransactionRequest) -> Result<Option<i64>> {
+        if request.data.as_ref().is_some_and(|data| !data.is_empty()) {
+            return Ok(None);
+        }
+
+        let amount = request.value.parse::<u128>()
+            .map_err(|_| Error::InvalidInput(format!("Invalid amount: {}", request.value)))?;
+        let (asset, decimals) = native_asset(request.key_type);
+        let Some(price) = (self.price)(asset)? else { return Ok(None) };
+
+        // Float to integer casts saturate, so huge values stay above any threshold
+        Ok(Some((amount as f64 / 10f64.powi(decimals as i32) * price * 100.0).round() as i64))
+    }
+
+    fn audit(&self, transfer: &TravelRuleTransfer, detail: String, now: u64) {
+        self.audit.record(TravelRuleEvent {
+            transfer_id: transfer.id.clone(),
+            state: transfer.state.clone(),
+            detail,
+            timestamp: now,
+        });
+    }
+}
+
+/// OpenVASP Travel Rule Protocol API version
+pub const TRP_API_VERSION: &str = "3.1.0";
+
+/// Build a TRP transfer inquiry body
+///
+/// `callback` is where the beneficiary VASP posts its approval or rejection.
+pub fn trp_inquiry(transfer: &TravelRuleTransfer, callback: &str) -> Result<Value> {
+    let payload = transfer.payload.as_ref()
+        .ok_or_else(|| Error::InvalidInput("Transfer has no identity payload".to_string()))?;
+
+    let slip0044 = match transfer.withdrawal.request.key_type {
+        KeyType::Bitcoin => 0,
+        KeyType::Ethereum => 60,
+        KeyType::Solana => 501,
+        KeyType::Ton => 607,
+        KeyType::Cosmos => 118,
+        KeyType::Xrp => 144,
+    };
+
+    Ok(json!({
+        "asset": { "slip0044": slip0044 },
+        "amount": transfer.withdrawal.request.value,
+        "callback": callback,
+        "IVMS101": serde_json::to_value(payload).map_err(|e| Error::Serialization(e.to_string()))?,
+    }))
+}
+
+/// Parse a TRP callback body into a counterparty response
+pub fn parse_trp_callback(body: &Value) -> Result<CounterpartyResponse> {
+    if body.get("approved").is_some() {
+        return Ok(CounterpartyResponse::Accepted);
+    }
+    if let Some(reason) = body.get("rejected") {
+        let reason = reason.as_str().map(str::to_string).unwrap_or_else(|| reason.to_string());
+        return Ok(CounterpartyResponse::Rejected { reason });
+    }
+    Err(Error::InvalidInput(format!("Invalid TRP callback: {}", body)))
+}
+
+/// Travel rule messenger speaking the OpenVASP Travel Rule Protocol over HTTPS
+#[cfg(feature = "rpc")]
+pub struct TrpMessenger {
+    /// Base URL of our callback endpoint; the transfer ID is appended
+    callback_url: String,
+}
+
+#[cfg(feature = "rpc")]
+impl TrpMessenger {
+    /// Create a messenger
+    pub fn new(callback_url: &str) -> Self {
+        Self { callback_url: callback_url.trim_end_matches('/').to_string() }
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl TravelRuleMessenger for TrpMessenger {
+    fn send(&self, transfer: &TravelRuleTransfer) -> Result<String> {
+        let endpoint = transfer.withdrawal.counterparty_endpoint.as_deref()
+            .ok_or_else(|| Error::InvalidInput("Beneficiary VASP has no travel rule endpoint".to_string()))?;
+        let body = trp_inquiry(transfer, &format!("{}/{}", self.callback_url, transfer.id))?;
+
+        // Inquiries are sent from request handlers, so run on a thread of their own
+        super::ethereum::block_on_detached(async {
+            reqwest::Client::new().post(endpoint)
+                .header("api-version", TRP_API_VERSION)
+                .header("request-identifier", &transfer.id)
+                .json(&body)
+                .send()
+                .await?
+                .error_for_status()
+        })?
+        .map_err(|e| Error::Network(format!("TRP inquiry failed: {}", e)))?;
+
+        Ok(transfer.id.clone())
+    }
+
+    fn poll(&self, _message_id: &str) -> Result<CounterpartyResponse> {
+        // TRP delivers the response on the callback
+        Ok(CounterpartyResponse::Pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::NetworkBinding;
+
+    struct Messenger(Mutex<CounterpartyResponse>);
+
+    impl TravelRuleMessenger for Messenger {
+        fn send(&self, transfer: &TravelRuleTransfer) -> Result<String> {
+            Ok(format!("msg-{}", transfer.id))
+        }
+
+        fn poll(&self, _message_id: &str) -> Result<CounterpartyResponse> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+    }
+
+    fn policy() -> TravelRulePolicy {
+        TravelRulePolicy {
+            threshold: 100_000,
+            originating_vasp: Vasp { legal_person: Person::legal("FO3 Custody Ltd", Some("5493001KJTIIGC8Y1R12")) },
+            response_timeout: 3600,
+            allow_unhosted: false,
+        }
+    }
+
+    fn gate(audit: Arc<InMemoryTravelRuleAudit>, response: CounterpartyResponse) -> TravelRuleGate {
+        TravelRuleGate::new(
+            policy(),
+            |request| Ok(Originator {
+                originator_persons: vec![Person::natural("Doe", "Jane")],
+                account_number: vec![request.from.clone()],
+            }),
+            |asset| Ok((asset == "ETH").then_some(2_000.0)),
+            Box::new(Messenger(Mutex::new(response))),
+            Box::new(audit),
+        )
+    }
+
+    fn withdrawal(value: &str, hosted: bool) -> Withdrawal {
+        Withdrawal {
+            request: TransactionRequest {
+                key_type: KeyType::Ethereum,
+                network: NetworkBinding::Evm { chain_id: 1 },
+                from: "0xfrom".to_string(),
+                to: "0xto".to_string(),
+                value: value.to_string(),
+                gas_price: None,
+                gas_limit: None,
+                nonce: None,
+                data: None,
+                destination_tag: None,
+                memo: None,
+            },
+            beneficiary: Beneficiary {
+                beneficiary_persons: vec![Person::natural("Roe", "Richard")],
+                account_number: vec!["0xto".to_string()],
+            },
+            beneficiary_vasp: hosted.then(|| Vasp { legal_person: Person::legal("Other Exchange", None) }),
+            counterparty_endpoint: hosted.then(|| "https://trp.example.com/transfers".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_below_threshold_released() {
+        let gate = gate(Arc::new(InMemoryTravelRuleAudit::new()), CounterpartyResponse::Pending);
+        let transfer = gate.submit(withdrawal("250000000000000000", true), 0).unwrap();
+
+        assert_eq!(transfer.fiat_value, Some(50_000));
+        assert_eq!(transfer.state, TransferState::NotRequired);
+        assert!(transfer.payload.is_none());
+        assert!(gate.release(&transfer.id, 1).is_ok());
+    }
+
+    #[test]
+    fn test_held_until_accepted() {
+        let audit = Arc::new(InMemoryTravelRuleAudit::new());
+        let gate = gate(audit.clone(), CounterpartyResponse::Pending);
+        let transfer = gate.submit(withdrawal("1000000000000000000", true), 0).unwrap();
+
+        assert_eq!(transfer.state, TransferState::AwaitingCounterparty);
+        assert!(matches!(gate.release(&transfer.id, 1), Err(Error::Compliance(_))));
+
+        assert_eq!(gate.poll(&transfer.id, 10).unwrap().state, TransferState::AwaitingCounterparty);
+        gate.record_response(&transfer.id, CounterpartyResponse::Accepted, 20).unwrap();
+        assert_eq!(gate.release(&transfer.id, 30).unwrap().to, "0xto");
+
+        // A released transfer cannot be released twice
+        assert!(gate.release(&transfer.id, 40).is_err());
+
+        let states: Vec<TransferState> = audit.events(&transfer.id).into_iter().map(|event| event.state).collect();
+        assert_eq!(states, vec![TransferState::AwaitingCounterparty, TransferState::Accepted, TransferState::Released]);
+    }
+
+    #[test]
+    fn test_rejected_and_expired() {
+        let gate_rejecting = gate(Arc::new(InMemoryTravelRuleAudit::new()), CounterpartyResponse::Rejected { reason: "unknown beneficiary".to_string() });
+        let transfer = gate_rejecting.submit(withdrawal("1000000000000000000", true), 0).unwrap();
+        assert!(matches!(gate_rejecting.poll(&transfer.id, 10).unwrap().state, TransferState::Rejected { .. }));
+        assert!(gate_rejecting.release(&transfer.id, 20).is_err());
+
+        let gate_silent = gate(Arc::new(InMemoryTravelRuleAudit::new()), CounterpartyResponse::Pending);
+        let transfer = gate_silent.submit(withdrawal("1000000000000000000", true), 0).unwrap();
+        assert_eq!(gate_silent.poll(&transfer.id, 3600).unwrap().state, TransferState::Expired);
+
+        // A late acceptance does not clear the transfer
+        let transfer = gate_silent.submit(withdrawal("1000000000000000000", true), 0).unwrap();
+        assert_eq!(gate_silent.record_response(&transfer.id, CounterpartyResponse::Accepted, 3600).unwrap().state, TransferState::Expired);
+        assert!(gate_silent.release(&transfer.id, 3601).is_err());
+    }
+
+    #[test]
+    fn test_unvalued_withdrawals_held() {
+        let gate = gate(Arc::new(InMemoryTravelRuleAudit::new()), CounterpartyResponse::Pending);
+
+        // A token transfer carries no native value but may be worth anything
+        let mut token_transfer = withdrawal("0", true);
+        token_transfer.request.data = Some(vec![0xa9, 0x05, 0x9c, 0xbb]);
+        let transfer = gate.submit(token_transfer, 0).unwrap();
+        assert_eq!(transfer.fiat_value, None);
+        assert_eq!(transfer.state, TransferState::AwaitingCounterparty);
+
+        let mut unpriced = withdrawal("1", true);
+        unpriced.request.key_type = KeyType::Solana;
+        assert_eq!(gate.submit(unpriced, 0).unwrap().state, TransferState::AwaitingCounterparty);
+    }
+
+    #[test]
+    fn test_unhosted_policy() {
+        let gate = gate(Arc::new(InMemoryTravelRuleAudit::new()), CounterpartyResponse::Pending);
+        assert!(matches!(gate.submit(withdrawal("1000000000000000000", false), 0), Err(Error::Compliance(_))));
+    }
+
+    #[test]
+    fn test_trp_messages() {
+        let gate = gate(Arc::new(InMemoryTravelRuleAudit::new()), CounterpartyResponse::Pending);
+        let transfer = gate.submit(withdrawal("1000000000000000000", true), 0).unwrap();
+
+        let inquiry = trp_inquiry(&transfer, "https://vasp.example.com/trp/callback").unwrap();
+        assert_eq!(inquiry["asset"]["slip0044"], 60);
+        assert_eq!(inquiry["IVMS101"]["originator"]["originatorPersons"][0]["naturalPerson"]["name"][0]["primaryIdentifier"], "Doe");
+        assert!(inquiry["IVMS101"]["originatingVASP"]["legalPerson"]["legalPerson"]["lei"].is_string());
+
+        assert_eq!(parse_trp_callback(&json!({ "approved": { "address": "0xto" } })).unwrap(), CounterpartyResponse::Accepted);
+        assert!(matches!(parse_trp_callback(&json!({ "rejected": "no such customer" })).unwrap(), CounterpartyResponse::Rejected { .. }));
+        assert!(parse_trp_callback(&json!({})).is_err());
+    }
+}
@@ -2,7 +2,8 @@
 
 use serde::{Serialize, Deserialize};
 use crate::crypto::keys::KeyType;
-use crate::error::Result;
+use crate::error::{Error, Result};
+use super::batch::{CallRequest, CallResult};
 
 /// Transaction status
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -30,10 +31,73 @@ pub enum TransactionType {
     LiquidityProvision,
     /// Staking
     Staking,
+    /// Lightning Network payment, keyed by payment hash
+    Lightning,
     /// Other
     Other,
 }
 
+/// A memo attached to a transaction, such as the reference an exchange matches deposits by
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Memo {
+    /// UTF-8 text
+    Text(String),
+    /// Raw bytes, on chains whose memos aren't text
+    Bytes(Vec<u8>),
+}
+
+impl Memo {
+    /// Get the memo's bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Text(text) => text.as_bytes(),
+            Self::Bytes(bytes) => bytes,
+        }
+    }
+
+    /// Get the memo as text, if it is valid UTF-8
+    pub fn as_text(&self) -> Option<&str> {
+        std::str::from_utf8(self.as_bytes()).ok()
+    }
+
+    /// Read a memo from chain data: text if valid UTF-8, bytes otherwise
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => Self::Text(text),
+            Err(e) => Self::Bytes(e.into_bytes()),
+        }
+    }
+
+    /// Check that a chain can carry the memo
+    ///
+    /// Solana's memo program, Cosmos and TON comments only take text. XRP
+    /// Ledger memos are capped at 1 KB with their type; Cosmos SDK chains
+    /// default to 256 characters.
+    pub fn check(&self, key_type: KeyType) -> Result<()> {
+        let (text_only, max_len) = match key_type {
+            KeyType::Solana => (true, 512),
+            KeyType::Xrp => (false, 1000),
+            KeyType::Cosmos => (true, 256),
+            KeyType::Ton => (true, 1000),
+            KeyType::Ethereum | KeyType::Bitcoin => {
+                return Err(Error::NotSupported(format!("{:?} transactions cannot carry a memo", key_type)));
+            }
+        };
+        if text_only && self.as_text().is_none() {
+            return Err(Error::InvalidInput(format!("{:?} memos must be UTF-8 text", key_type)));
+        }
+        let len = match (key_type, self.as_text()) {
+            (KeyType::Cosmos, Some(text)) => text.chars().count(),
+            _ => self.as_bytes().len(),
+        };
+        if len > max_len {
+            return Err(Error::InvalidInput(format!("{:?} memos are limited to {}, got {}", key_type, max_len, len)));
+        }
+        Ok(())
+    }
+}
+
 /// Transaction data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
@@ -65,6 +129,61 @@ pub struct Transaction {
     pub timestamp: Option<u64>,
     /// Fee paid
     pub fee: Option<String>,
+    /// Memo attached to the transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<Memo>,
+}
+
+/// The network a transaction is bound to
+///
+/// Every signer checks the binding against the network it is connected to, so
+/// a transaction prepared for a testnet can never be signed for mainnet (or
+/// for another EVM chain) and vice versa.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NetworkBinding {
+    /// EVM chain, identified by its EIP-155 chain ID
+    Evm { chain_id: u64 },
+    /// Solana cluster, identified by its genesis hash
+    Solana { genesis_hash: String },
+    /// Bitcoin network (`bitcoin`, `testnet`, `signet` or `regtest`)
+    Bitcoin { network: String },
+    /// TON network, identified by its global ID (-239 mainnet, -3 testnet)
+    Ton { global_id: i32 },
+    /// Cosmos SDK chain, identified by its chain ID (`cosmoshub-4`)
+    Cosmos { chain_id: String },
+    /// XRP Ledger, identified by its network ID (0 mainnet, 1 testnet, 2 devnet)
+    Xrp { network_id: u32 },
+}
+
+impl NetworkBinding {
+    /// Get the blockchain type this binding applies to
+    pub fn key_type(&self) -> KeyType {
+        match self {
+            Self::Evm { .. } => KeyType::Ethereum,
+            Self::Solana { .. } => KeyType::Solana,
+            Self::Bitcoin { .. } => KeyType::Bitcoin,
+            Self::Ton { .. } => KeyType::Ton,
+            Self::Cosmos { .. } => KeyType::Cosmos,
+            Self::Xrp { .. } => KeyType::Xrp,
+        }
+    }
+
+    /// Check that this binding matches the signer's network
+    pub fn ensure_matches(&self, signer_network: &NetworkBinding) -> Result<()> {
+        if let Self::Evm { chain_id: 0 } = self {
+            // Chain ID 0 would produce a pre-EIP-155 signature, replayable on any EVM chain
+            return Err(Error::Transaction("EVM transactions must be bound to a non-zero chain ID".to_string()));
+        }
+
+        if self != signer_network {
+            return Err(Error::Transaction(format!(
+                "Network mismatch: transaction is bound to {:?} but the signer is connected to {:?}",
+                self, signer_network
+            )));
+        }
+
+        Ok(())
+    }
 }
 
 /// Transaction request
@@ -72,6 +191,8 @@ pub struct Transaction {
 pub struct TransactionRequest {
     /// Blockchain type
     pub key_type: KeyType,
+    /// Network the transaction is bound to
+    pub network: NetworkBinding,
     /// From address
     pub from: String,
     /// To address
@@ -86,6 +207,25 @@ pub struct TransactionRequest {
     pub nonce: Option<u64>,
     /// Data (for contract calls)
     pub data: Option<Vec<u8>>,
+    /// Destination tag (for the XRP Ledger), identifying the recipient at a shared account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination_tag: Option<u32>,
+    /// Memo to attach (Solana, XRP Ledger, Cosmos and TON)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<Memo>,
+}
+
+impl TransactionRequest {
+    /// Get the memo as text, checking that the chain can carry it
+    pub fn memo_text(&self) -> Result<Option<&str>> {
+        let Some(memo) = &self.memo else {
+            return Ok(None);
+        };
+        memo.check(self.key_type)?;
+        memo.as_text()
+            .map(Some)
+            .ok_or_else(|| Error::InvalidInput(format!("{:?} memos must be UTF-8 text", self.key_type)))
+    }
 }
 
 /// Transaction receipt
@@ -141,4 +281,12 @@ pub trait TransactionManager: TransactionSigner + TransactionBroadcaster {
     
     /// Get transactions for an address
     fn get_transactions(&self, address: &str, limit: usize, offset: usize) -> Result<Vec<Transaction>>;
+
+    /// Execute read-only contract calls in as few round-trips as possible
+    ///
+    /// Results are returned in call order; individual calls may fail without
+    /// failing the batch.
+    fn batch_call(&self, _calls: &[CallRequest]) -> Result<Vec<CallResult>> {
+        Err(Error::NotSupported("Batch calls are not supported by this provider".to_string()))
+    }
 }
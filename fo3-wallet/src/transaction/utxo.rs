@@ -0,0 +1,505 @@
+//! Bitcoin UTXO backends
+//!
+//! Bitcoin nodes don't index addresses, so the Bitcoin provider reads UTXOs,
+//! fee estimates and address history from an indexer. [`UtxoBackend`]
+//! abstracts over two: Esplora's REST API (as served by mempool.space and
+//! Blockstream) and the Electrum protocol. The backend is chosen by the
+//! provider type of the [`ProviderConfig`].
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+use super::bitcoin::BitcoinInput;
+use super::provider::{ProviderConfig, ProviderType};
+use super::types::TransactionStatus;
+
+/// Confirmation targets of the fee estimates, in blocks
+const FASTEST_TARGET: u32 = 1;
+const HALF_HOUR_TARGET: u32 = 3;
+const HOUR_TARGET: u32 = 6;
+const ECONOMY_TARGET: u32 = 144;
+
+/// An unspent output of an address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Utxo {
+    /// Transaction ID
+    pub txid: String,
+    /// Output index
+    pub vout: u32,
+    /// Value in satoshis
+    pub value: u64,
+    /// Height of the block confirming the output, `None` while in the mempool
+    pub block_height: Option<u64>,
+}
+
+impl Utxo {
+    /// Whether the output is confirmed
+    pub fn is_confirmed(&self) -> bool {
+        self.block_height.is_some()
+    }
+
+    /// Convert to a transaction input spending `script_pubkey` (hex)
+    pub fn to_input(&self, script_pubkey: &str) -> BitcoinInput {
+        BitcoinInput {
+            txid: self.txid.clone(),
+            vout: self.vout,
+            amount: self.value,
+            script_pubkey: script_pubkey.to_string(),
+        }
+    }
+}
+
+/// Fee rates for common confirmation targets, in sat/vB
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FeeEstimates {
+    /// Next block
+    pub fastest: f64,
+    /// Within three blocks
+    pub half_hour: f64,
+    /// Within six blocks
+    pub hour: f64,
+    /// Within a day
+    pub economy: f64,
+}
+
+impl FeeEstimates {
+    /// Pick estimates from fee rates keyed by confirmation target
+    ///
+    /// Each target uses the rate of the nearest target at or above it, or
+    /// the slowest known target when none is that slow.
+    pub fn from_targets(targets: &BTreeMap<u32, f64>) -> Result<Self> {
+        let rate = |target: u32| {
+            targets.range(target..).next()
+                .or_else(|| targets.iter().next_back())
+                .map(|(_, rate)| *rate)
+                .ok_or_else(|| Error::Provider("No fee estimates available".to_string()))
+        };
+        Ok(Self {
+            fastest: rate(FASTEST_TARGET)?,
+            half_hour: rate(HALF_HOUR_TARGET)?,
+            hour: rate(HOUR_TARGET)?,
+            economy: rate(ECONOMY_TARGET)?,
+        })
+    }
+}
+
+/// A transaction touching an address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressTransaction {
+    /// Transaction ID
+    pub txid: String,
+    /// Height of the confirming block, `None` while in the mempool
+    pub block_height: Option<u64>,
+    /// Time of the confirming block (Unix seconds)
+    pub block_time: Option<u64>,
+    /// Fee in satoshis, if the backend reports it
+    pub fee: Option<u64>,
+    /// Change of the address's balance in satoshis, if the backend reports it
+    pub net_value: Option<i64>,
+}
+
+/// Source of UTXOs, fee estimates and history for Bitcoin addresses
+pub trait UtxoBackend: Send + Sync {
+    /// Backend name, for logs
+    fn name(&self) -> &str;
+
+    /// Get the unspent outputs of an address
+    fn get_utxos(&self, address: &str) -> Result<Vec<Utxo>>;
+
+    /// Get current fee estimates
+    fn get_fee_estimates(&self) -> Result<FeeEstimates>;
+
+    /// Get the transactions touching an address, newest first
+    fn get_address_history(&self, address: &str) -> Result<Vec<AddressTransaction>>;
+
+    /// Get the status of a transaction
+    fn get_transaction_status(&self, txid: &str) -> Result<TransactionStatus>;
+
+    /// Broadcast a signed transaction, returning its ID
+    fn broadcast(&self, raw_transaction: &[u8]) -> Result<String>;
+}
+
+/// Create the backend selected by the provider type: Esplora or Electrum
+#[cfg(feature = "rpc")]
+pub fn utxo_backend(config: &ProviderConfig) -> Result<Box<dyn UtxoBackend>> {
+    match config.provider_type {
+        ProviderType::Esplora => Ok(Box::new(EsploraBackend::new(config)?)),
+        ProviderType::Electrum => Ok(Box::new(ElectrumBackend::new(config)?)),
+        other => Err(Error::NotSupported(format!("{:?} providers cannot index Bitcoin addresses", other))),
+    }
+}
+
+/// Whether the provider type selects a UTXO backend
+pub fn is_utxo_backend(config: &ProviderConfig) -> bool {
+    matches!(config.provider_type, ProviderType::Esplora | ProviderType::Electrum)
+}
+
+/// Script pubkey (hex) of a Bitcoin address
+pub fn address_script_pubkey(address: &str) -> Result<String> {
+    let address = bitcoin::Address::from_str(address)
+        .map_err(|e| Error::InvalidInput(format!("Invalid Bitcoin address {}: {}", address, e)))?
+        .assume_checked();
+    Ok(hex::encode(address.script_pubkey().as_bytes()))
+}
+
+/// Electrum script hash of a script pubkey (hex): its SHA-256, byte-reversed
+pub fn electrum_script_hash(script_pubkey: &str) -> Result<String> {
+    let script = hex::decode(script_pubkey)
+        .map_err(|e| Error::InvalidInput(format!("Invalid script pubkey: {}", e)))?;
+    let mut hash = Sha256::digest(script).to_vec();
+    hash.reverse();
+    Ok(hex::encode(hash))
+}
+
+fn confirmed_height(status: &Value) -> Option<u64> {
+    status["confirmed"].as_bool().filter(|confirmed| *confirmed).and_then(|_| status["block_height"].as_u64())
+}
+
+/// Parse Esplora's `/address/:address/utxo` response
+pub fn parse_esplora_utxos(response: &Value) -> Result<Vec<Utxo>> {
+    let entries = response.as_array()
+        .ok_or_else(|| Error::Provider(format!("Invalid Esplora UTXO response: {}", response)))?;
+    entries.iter()
+        .map(|entry| {
+            let invalid = || Error::Provider(format!("Invalid Esplora UTXO: {}", entry));
+            Ok(Utxo {
+                txid: entry["txid"].as_str().ok_or_else(invalid)?.to_string(),
+                vout: entry["vout"].as_u64().ok_or_else(invalid)? as u32,
+                value: entry["value"].as_u64().ok_or_else(invalid)?,
+                block_height: confirmed_height(&entry["status"]),
+            })
+        })
+        .collect()
+}
+
+/// Parse Esplora's `/fee-estimates` response
+pub fn parse_esplora_fees(response: &Value) -> Result<FeeEstimates> {
+    let estimates = response.as_object()
+        .ok_or_else(|| Error::Provider(format!("Invalid Esplora fee estimates: {}", response)))?;
+    let targets: BTreeMap<u32, f64> = estimates.iter()
+        .filter_map(|(target, rate)| Some((target.parse().ok()?, rate.as_f64()?)))
+        .collect();
+    FeeEstimates::from_targets(&targets)
+}
+
+/// Parse Esplora's `/address/:address/txs` response, computing each transaction's effect on `address`
+pub fn parse_esplora_history(response: &Value, address: &str) -> Result<Vec<AddressTransaction>> {
+    let transactions = response.as_array()
+        .ok_or_else(|| Error::Provider(format!("Invalid Esplora history response: {}", response)))?;
+    transactions.iter()
+        .map(|transaction| {
+            let txid = transaction["txid"].as_str()
+                .ok_or_else(|| Error::Provider(format!("Invalid Esplora transaction: {}", transaction)))?;
+            let paid_to_address = |output: &&Value| output["scriptpubkey_address"].as_str() == Some(address);
+            let received: i64 = transaction["vout"].as_array().into_iter().flatten()
+                .filter(paid_to_address)
+                .filter_map(|output| output["value"].as_i64())
+                .sum();
+            let spent: i64 = transaction["vin"].as_array().into_iter().flatten()
+                .map(|input| &input["prevout"])
+                .filter(paid_to_address)
+                .filter_map(|output| output["value"].as_i64())
+                .sum();
+
+            Ok(AddressTransaction {
+                txid: txid.to_string(),
+                block_height: confirmed_height(&transaction["status"]),
+                block_time: transaction["status"]["block_time"].as_u64(),
+                fee: transaction["fee"].as_u64(),
+                net_value: Some(received - spent),
+            })
+        })
+        .collect()
+}
+
+/// Parse the result of Electrum's `blockchain.scripthash.listunspent`
+pub fn parse_electrum_utxos(result: &Value) -> Result<Vec<Utxo>> {
+    let entries = result.as_array()
+        .ok_or_else(|| Error::Provider(format!("Invalid Electrum UTXO response: {}", result)))?;
+    entries.iter()
+        .map(|entry| {
+            let invalid = || Error::Provider(format!("Invalid Electrum UTXO: {}", entry));
+            Ok(Utxo {
+                txid: entry["tx_hash"].as_str().ok_or_else(invalid)?.to_string(),
+                vout: entry["tx_pos"].as_u64().ok_or_else(invalid)? as u32,
+                value: entry["value"].as_u64().ok_or_else(invalid)?,
+                // Height 0 (or -1 with unconfirmed parents) means in the mempool
+                block_height: entry["height"].as_u64().filter(|height| *height > 0),
+            })
+        })
+        .collect()
+}
+
+/// Parse the result of Electrum's `blockchain.scripthash.get_history`, newest first
+pub fn parse_electrum_history(result: &Value) -> Result<Vec<AddressTransaction>> {
+    let entries = result.as_array()
+        .ok_or_else(|| Error::Provider(format!("Invalid Electrum history response: {}", result)))?;
+    let mut history = entries.iter()
+        .map(|entry| {
+            Ok(AddressTransaction {
+                txid: entry["tx_hash"].as_str()
+                    .ok_or_else(|| Error::Provider(format!("Invalid Electrum history entry: {}", entry)))?
+                    .to_string(),
+                block_height: entry["height"].as_u64().filter(|height| *height > 0),
+                block_time: None,
+                fee: entry["fee"].as_u64(),
+                net_value: None,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Electrum lists oldest first, with mempool transactions last
+    history.reverse();
+    Ok(history)
+}
+
+/// Esplora REST backend (mempool.space, Blockstream)
+#[cfg(feature = "rpc")]
+pub struct EsploraBackend {
+    base_url: String,
+    http: reqwest::Client,
+}
+
+#[cfg(feature = "rpc")]
+impl EsploraBackend {
+    /// Create a backend for the Esplora API at `config.url` (e.g. `https://mempool.space/api`)
+    pub fn new(config: &ProviderConfig) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(config.timeout.unwrap_or(30)))
+            .build()
+            .map_err(|e| Error::Provider(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(Self { base_url: config.url.trim_end_matches('/').to_string(), http })
+    }
+
+    fn get(&self, path: &str) -> Result<String> {
+        let url = format!("{}{}", self.base_url, path);
+        super::ethereum::block_on(super::ethereum::traced(super::ethereum::rpc_span("bitcoin", &url, path), async {
+            let response = self.http.get(&url).send().await
+                .map_err(|e| Error::Network(format!("Esplora request failed: {}", e)))?;
+            let status = response.status();
+            let body = response.text().await
+                .map_err(|e| Error::Network(format!("Esplora request failed: {}", e)))?;
+            if !status.is_success() {
+                return Err(Error::Provider(format!("Esplora returned {}: {}", status, body)));
+            }
+            Ok(body)
+        }))?
+    }
+
+    fn get_json(&self, path: &str) -> Result<Value> {
+        serde_json::from_str(&self.get(path)?)
+            .map_err(|e| Error::Provider(format!("Invalid Esplora response: {}", e)))
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl UtxoBackend for EsploraBackend {
+    fn name(&self) -> &str {
+        "esplora"
+    }
+
+    fn get_utxos(&self, address: &str) -> Result<Vec<Utxo>> {
+        parse_esplora_utxos(&self.get_json(&format!("/address/{}/utxo", address))?)
+    }
+
+    fn get_fee_estimates(&self) -> Result<FeeEstimates> {
+        parse_esplora_fees(&self.get_json("/fee-estimates")?)
+    }
+
+    fn get_address_history(&self, address: &str) -> Result<Vec<AddressTransaction>> {
+        parse_esplora_history(&self.get_json(&format!("/address/{}/txs", address))?, address)
+    }
+
+    fn get_transaction_status(&self, txid: &str) -> Result<TransactionStatus> {
+        let status = self.get_json(&format!("/tx/{}/status", txid))?;
+        Ok(match confirmed_height(&status) {
+            Some(_) => TransactionStatus::Confirmed,
+            None => TransactionStatus::Pending,
+        })
+    }
+
+    fn broadcast(&self, raw_transaction: &[u8]) -> Result<String> {
+        let url = format!("{}/tx", self.base_url);
+        super::ethereum::block_on(super::ethereum::traced(super::ethereum::rpc_span("bitcoin", &url, "/tx"), async {
+            let response = self.http.post(&url).body(hex::encode(raw_transaction)).send().await
+                .map_err(|e| Error::Network(format!("Esplora broadcast failed: {}", e)))?;
+            let status = response.status();
+            let body = response.text().await
+                .map_err(|e| Error::Network(format!("Esplora broadcast failed: {}", e)))?;
+            if !status.is_success() {
+                return Err(Error::Transaction(format!("Broadcast rejected: {}", body)));
+            }
+            Ok(body.trim().to_string())
+        }))?
+    }
+}
+
+/// Electrum protocol backend over plain TCP (`tcp://host:50001`)
+#[cfg(feature = "rpc")]
+pub struct ElectrumBackend {
+    address: String,
+    timeout: std::time::Duration,
+    next_id: std::sync::atomic::AtomicU64,
+}
+
+#[cfg(feature = "rpc")]
+impl ElectrumBackend {
+    /// Create a backend for the Electrum server at `config.url`
+    ///
+    /// TLS (`ssl://`) servers are not supported; run them behind a local
+    /// TLS terminator or use Esplora.
+    pub fn new(config: &ProviderConfig) -> Result<Self> {
+        let address = match config.url.split_once("://") {
+            Some(("tcp", address)) => address,
+            Some((scheme, _)) => return Err(Error::NotSupported(format!("Electrum over {} is not supported", scheme))),
+            None => config.url.as_str(),
+        };
+        Ok(Self {
+            address: address.trim_end_matches('/').to_string(),
+            timeout: std::time::Duration::from_secs(config.timeout.unwrap_or(30)),
+            next_id: std::sync::atomic::AtomicU64::new(1),
+        })
+    }
+
+    /// Send one JSON-RPC request and read its newline-terminated response
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::{TcpStream, ToSocketAddrs};
+
+        let id = self.next_id.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let request = serde_json::json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+
+        let network_error = |e: std::io::Error| Error::Network(format!("Electrum request to {} failed: {}", self.address, e));
+        let socket = self.address.to_socket_addrs().map_err(network_error)?.next()
+            .ok_or_else(|| Error::Network(format!("Cannot resolve {}", self.address)))?;
+        let mut stream = TcpStream::connect_timeout(&socket, self.timeout).map_err(network_error)?;
+        stream.set_read_timeout(Some(self.timeout)).map_err(network_error)?;
+        stream.write_all(format!("{}\n", request).as_bytes()).map_err(network_error)?;
+
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).map_err(network_error)?;
+        let response: Value = serde_json::from_str(&line)
+            .map_err(|e| Error::Provider(format!("Invalid Electrum response: {}", e)))?;
+        if !response["error"].is_null() {
+            return Err(Error::Provider(format!("Electrum {} failed: {}", method, response["error"])));
+        }
+        Ok(response["result"].clone())
+    }
+
+    fn script_hash(address: &str) -> Result<String> {
+        electrum_script_hash(&address_script_pubkey(address)?)
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl UtxoBackend for ElectrumBackend {
+    fn name(&self) -> &str {
+        "electrum"
+    }
+
+    fn get_utxos(&self, address: &str) -> Result<Vec<Utxo>> {
+        parse_electrum_utxos(&self.call("blockchain.scripthash.listunspent", serde_json::json!([Self::script_hash(address)?]))?)
+    }
+
+    fn get_fee_estimates(&self) -> Result<FeeEstimates> {
+        let mut targets = BTreeMap::new();
+        for target in [FASTEST_TARGET, HALF_HOUR_TARGET, HOUR_TARGET, ECONOMY_TARGET] {
+            // BTC per kvB, or -1 when the server has no estimate
+            let rate = self.call("blockchain.estimatefee", serde_json::json!([target]))?.as_f64().unwrap_or(-1.0);
+            if rate > 0.0 {
+                targets.insert(target, rate * 100_000_000.0 / 1_000.0);
+            }
+        }
+        FeeEstimates::from_targets(&targets)
+    }
+
+    fn get_address_history(&self, address: &str) -> Result<Vec<AddressTransaction>> {
+        parse_electrum_history(&self.call("blockchain.scripthash.get_history", serde_json::json!([Self::script_hash(address)?]))?)
+    }
+
+    fn get_transaction_status(&self, txid: &str) -> Result<TransactionStatus> {
+        let transaction = self.call("blockchain.transaction.get", serde_json::json!([txid, true]))?;
+        Ok(match transaction["confirmations"].as_u64() {
+            Some(confirmations) if confirmations > 0 => TransactionStatus::Confirmed,
+            _ => TransactionStatus::Pending,
+        })
+    }
+
+    fn broadcast(&self, raw_transaction: &[u8]) -> Result<String> {
+        let txid = self.call("blockchain.transaction.broadcast", serde_json::json!([hex::encode(raw_transaction)]))?;
+        txid.as_str()
+            .map(str::to_string)
+            .ok_or_else(|| Error::Transaction(format!("Broadcast rejected: {}", txid)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    #[test]
+    fn test_script_hash() {
+        let script = address_script_pubkey("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa").unwrap();
+        assert_eq!(script, "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac");
+        assert_eq!(electrum_script_hash(&script).unwrap(), "8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c39161");
+    }
+
+    #[test]
+    fn test_parse_esplora() {
+        let utxos = parse_esplora_utxos(&json!([
+            { "txid": "aa", "vout": 1, "value": 20_000, "status": { "confirmed": true, "block_height": 800_000 } },
+            { "txid": "bb", "vout": 0, "value": 5_000, "status": { "confirmed": false } },
+        ])).unwrap();
+        assert!(utxos[0].is_confirmed() && !utxos[1].is_confirmed());
+        assert_eq!(utxos[0].to_input("0014").amount, 20_000);
+
+        let fees = parse_esplora_fees(&json!({ "1": 30.5, "2": 25.0, "3": 20.1, "6": 12.0, "144": 1.5 })).unwrap();
+        assert_eq!(fees, FeeEstimates { fastest: 30.5, half_hour: 20.1, hour: 12.0, economy: 1.5 });
+
+        let history = parse_esplora_history(&json!([{
+            "txid": "cc",
+            "fee": 500,
+            "status": { "confirmed": true, "block_height": 800_001, "block_time": 1_700_000_000 },
+            "vin": [{ "prevout": { "scriptpubkey_address": ADDRESS, "value": 20_000 } }],
+            "vout": [
+                { "scriptpubkey_address": "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", "value": 15_000 },
+                { "scriptpubkey_address": ADDRESS, "value": 4_500 },
+            ],
+        }]), ADDRESS).unwrap();
+        assert_eq!(history[0].net_value, Some(-15_500));
+        assert_eq!(history[0].block_height, Some(800_001));
+    }
+
+    #[test]
+    fn test_parse_electrum() {
+        let utxos = parse_electrum_utxos(&json!([
+            { "tx_hash": "aa", "tx_pos": 0, "height": 800_000, "value": 10_000 },
+            { "tx_hash": "bb", "tx_pos": 1, "height": 0, "value": 2_000 },
+        ])).unwrap();
+        assert_eq!(utxos[0].block_height, Some(800_000));
+        assert_eq!(utxos[1].block_height, None);
+
+        let history = parse_electrum_history(&json!([
+            { "tx_hash": "old", "height": 700_000 },
+            { "tx_hash": "new", "height": 0, "fee": 300 },
+        ])).unwrap();
+        let txids: Vec<&str> = history.iter().map(|transaction| transaction.txid.as_str()).collect();
+        assert_eq!(txids, vec!["new", "old"]);
+    }
+
+    #[test]
+    fn test_fee_targets_fall_back() {
+        let targets = BTreeMap::from([(2, 10.0), (25, 3.0)]);
+        let fees = FeeEstimates::from_targets(&targets).unwrap();
+        assert_eq!(fees, FeeEstimates { fastest: 10.0, half_hour: 3.0, hour: 3.0, economy: 3.0 });
+        assert!(FeeEstimates::from_targets(&BTreeMap::new()).is_err());
+    }
+}
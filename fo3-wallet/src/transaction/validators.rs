@@ -0,0 +1,330 @@
+//! Validator health monitoring
+//!
+//! This module watches the validators a delegator has staked with on a
+//! Cosmos SDK chain and raises an alert when one degrades: it is jailed,
+//! leaves the active set, raises its commission or is slashed. Slashing is
+//! detected from a fall in the tokens each delegator share is worth, which
+//! is otherwise constant. Each alert carries a ready-to-sign
+//! `MsgBeginRedelegate` moving the delegation to a healthy validator, so the
+//! user can act on it in one step.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use serde::{Serialize, Deserialize};
+
+use crate::error::Result;
+use super::cosmos::{BondStatus, Coin, CosmosDelegation, CosmosMsg, CosmosProvider, CosmosValidator};
+
+/// Relative fall in tokens per share treated as a slash rather than rounding
+const SLASH_TOLERANCE: f64 = 1e-9;
+
+/// Reads delegations and validators from a chain
+pub trait ValidatorSource {
+    /// Get the delegations of an address
+    fn delegations(&self, delegator: &str) -> Result<Vec<CosmosDelegation>>;
+
+    /// Get a validator by operator address
+    fn validator(&self, operator_address: &str) -> Result<CosmosValidator>;
+
+    /// Get the validators in the active set
+    fn bonded_validators(&self) -> Result<Vec<CosmosValidator>>;
+}
+
+impl ValidatorSource for CosmosProvider {
+    fn delegations(&self, delegator: &str) -> Result<Vec<CosmosDelegation>> {
+        self.get_delegations(delegator)
+    }
+
+    fn validator(&self, operator_address: &str) -> Result<CosmosValidator> {
+        self.get_validator(operator_address)
+    }
+
+    fn bonded_validators(&self) -> Result<Vec<CosmosValidator>> {
+        self.get_bonded_validators()
+    }
+}
+
+/// Thresholds for alerting on a validator
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidatorAlertPolicy {
+    /// Largest commission increase, as a fraction, tolerated between checks
+    pub max_commission_increase: f64,
+    /// Highest commission, as a fraction, tolerated at all
+    pub max_commission: f64,
+}
+
+impl Default for ValidatorAlertPolicy {
+    fn default() -> Self {
+        Self {
+            max_commission_increase: 0.02,
+            max_commission: 0.2,
+        }
+    }
+}
+
+/// Why a validator was flagged
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValidatorIssue {
+    /// Jailed for downtime or double signing; it earns no rewards
+    Jailed,
+    /// Left the active set; it earns no rewards
+    Inactive,
+    /// Commission rose by more than the policy allows, or above its ceiling
+    CommissionIncrease { from: f64, to: f64 },
+    /// Delegations lost `fraction` of their value to a slash
+    Slashed { fraction: f64 },
+}
+
+/// An alert about a degraded validator
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidatorAlert {
+    /// Delegator address
+    pub delegator: String,
+    /// Validator operator address
+    pub validator: String,
+    /// Validator display name
+    pub moniker: String,
+    /// What changed since the last check
+    pub issues: Vec<ValidatorIssue>,
+    /// Tokens delegated to the validator
+    pub delegated: Coin,
+    /// Message moving the whole delegation to a healthy validator, if one was found
+    pub redelegation: Option<CosmosMsg>,
+}
+
+/// Receiver of validator alerts
+pub trait ValidatorNotifier {
+    /// Deliver an alert
+    fn notify(&self, alert: &ValidatorAlert);
+}
+
+/// Tracks the validators delegators are staked with
+///
+/// The monitor remembers each validator as it was at the delegator's last
+/// check and alerts only when its state gets worse, so a validator that
+/// stays jailed is reported once.
+pub struct ValidatorMonitor {
+    /// Alert thresholds
+    policy: ValidatorAlertPolicy,
+    /// Last seen state, by delegator and validator
+    snapshots: Mutex<HashMap<(String, String), CosmosValidator>>,
+}
+
+impl ValidatorMonitor {
+    /// Create a monitor
+    pub fn new(policy: ValidatorAlertPolicy) -> Self {
+        Self { policy, snapshots: Mutex::new(HashMap::new()) }
+    }
+
+    /// Check a delegator's validators, notifying and returning new alerts
+    pub fn check(&self, delegator: &str, source: &dyn ValidatorSource, notifier: &dyn ValidatorNotifier) -> Result<Vec<ValidatorAlert>> {
+        let delegations = source.delegations(delegator)?;
+        let delegated: HashSet<&str> = delegations.iter().map(|delegation| delegation.validator_address.as_str()).collect();
+        let mut candidates: Option<Vec<CosmosValidator>> = None;
+        let mut alerts = Vec::new();
+
+        for delegation in &delegations {
+            let validator = source.validator(&delegation.validator_address)?;
+            let key = (delegator.to_string(), validator.operator_address.clone());
+            let previous = self.snapshots.lock().unwrap().insert(key, validator.clone());
+            let issues = self.issues(previous.as_ref(), &validator);
+            if issues.is_empty() {
+                continue;
+            }
+
+            if candidates.is_none() {
+                candidates = Some(source.bonded_validators()?);
+            }
+            let target = self.redelegation_target(candidates.as_deref().unwrap_or_default(), &delegated);
+            let alert = ValidatorAlert {
+                delegator: delegator.to_string(),
+                validator: validator.operator_address.clone(),
+                moniker: validator.moniker.clone(),
+                issues,
+                delegated: delegation.balance.clone(),
+                redelegation: target.map(|target| CosmosMsg::BeginRedelegate {
+                    delegator_address: delegator.to_string(),
+                    validator_src_address: validator.operator_address.clone(),
+                    validator_dst_address: target.operator_address.clone(),
+                    amount: delegation.balance.clone(),
+                }),
+            };
+            notifier.notify(&alert);
+            alerts.push(alert);
+        }
+
+        // Forget validators the delegator has left
+        self.snapshots.lock().unwrap()
+            .retain(|(owner, validator), _| owner != delegator || delegated.contains(validator.as_str()));
+        Ok(alerts)
+    }
+
+    /// Work out what got worse since the previous snapshot
+    fn issues(&self, previous: Option<&CosmosValidator>, current: &CosmosValidator) -> Vec<ValidatorIssue> {
+        let mut issues = Vec::new();
+        if current.jailed && !previous.is_some_and(|previous| previous.jailed) {
+            issues.push(ValidatorIssue::Jailed);
+        } else if !current.jailed
+            && current.status != BondStatus::Bonded
+            && previous.map_or(true, |previous| previous.status == BondStatus::Bonded)
+        {
+            issues.push(ValidatorIssue::Inactive);
+        }
+
+        let from = previous.map(|previous| previous.commission_rate);
+        let raised = from.is_some_and(|from| current.commission_rate - from > self.policy.max_commission_increase);
+        let over_ceiling = current.commission_rate > self.policy.max_commission
+            && from.map_or(true, |from| from <= self.policy.max_commission);
+        if raised || over_ceiling {
+            issues.push(ValidatorIssue::CommissionIncrease {
+                from: from.unwrap_or(current.commission_rate),
+                to: current.commission_rate,
+            });
+        }
+
+        if let Some(previous) = previous {
+            let (before, after) = (previous.tokens_per_share(), current.tokens_per_share());
+            if before > 0.0 && after < before * (1.0 - SLASH_TOLERANCE) {
+                issues.push(ValidatorIssue::Slashed { fraction: 1.0 - after / before });
+            }
+        }
+        issues
+    }
+
+    /// Pick the active validator with the lowest commission, then the most stake
+    ///
+    /// Validators the delegator already uses are skipped so that a single
+    /// alert does not concentrate the stake.
+    fn redelegation_target<'a>(&self, candidates: &'a [CosmosValidator], delegated: &HashSet<&str>) -> Option<&'a CosmosValidator> {
+        candidates.iter()
+            .filter(|validator| {
+                validator.status == BondStatus::Bonded
+                    && !validator.jailed
+                    && validator.commission_rate <= self.policy.max_commission
+                    && !delegated.contains(validator.operator_address.as_str())
+            })
+            .min_by(|a, b| {
+                a.commission_rate.total_cmp(&b.commission_rate)
+                    .then_with(|| b.tokens.cmp(&a.tokens))
+            })
+    }
+}
+
+impl Default for ValidatorMonitor {
+    fn default() -> Self {
+        Self::new(ValidatorAlertPolicy::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    struct Chain {
+        validators: RefCell<Vec<CosmosValidator>>,
+    }
+
+    impl ValidatorSource for Chain {
+        fn delegations(&self, delegator: &str) -> Result<Vec<CosmosDelegation>> {
+            Ok(vec![CosmosDelegation {
+                delegator_address: delegator.to_string(),
+                validator_address: "valoper1a".to_string(),
+                balance: Coin::new("uatom", 5000),
+            }])
+        }
+
+        fn validator(&self, operator_address: &str) -> Result<CosmosValidator> {
+            Ok(self.validators.borrow().iter().find(|v| v.operator_address == operator_address).unwrap().clone())
+        }
+
+        fn bonded_validators(&self) -> Result<Vec<CosmosValidator>> {
+            Ok(self.validators.borrow().iter().filter(|v| v.status == BondStatus::Bonded).cloned().collect())
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder(RefCell<Vec<ValidatorAlert>>);
+
+    impl ValidatorNotifier for Recorder {
+        fn notify(&self, alert: &ValidatorAlert) {
+            self.0.borrow_mut().push(alert.clone());
+        }
+    }
+
+    fn validator(address: &str, commission_rate: f64, tokens: u128) -> CosmosValidator {
+        CosmosValidator {
+            operator_address: address.to_string(),
+            moniker: address.to_string(),
+            jailed: false,
+            status: BondStatus::Bonded,
+            commission_rate,
+            tokens,
+            delegator_shares: 1_000_000.0,
+        }
+    }
+
+    #[test]
+    fn test_alerts_on_degradation() {
+        let chain = Chain {
+            validators: RefCell::new(vec![
+                validator("valoper1a", 0.05, 1_000_000),
+                validator("valoper1b", 0.05, 2_000_000),
+                validator("valoper1c", 0.03, 500_000),
+                validator("valoper1d", 0.25, 9_000_000),
+            ]),
+        };
+        let monitor = ValidatorMonitor::default();
+        let recorder = Recorder::default();
+
+        // A healthy validator raises nothing on first sight
+        assert!(monitor.check("cosmos1x", &chain, &recorder).unwrap().is_empty());
+
+        // Jailed and slashed 5%
+        {
+            let mut validators = chain.validators.borrow_mut();
+            validators[0].jailed = true;
+            validators[0].status = BondStatus::Unbonding;
+            validators[0].tokens = 950_000;
+        }
+        let alerts = monitor.check("cosmos1x", &chain, &recorder).unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].issues[0], ValidatorIssue::Jailed);
+        assert!(matches!(alerts[0].issues[1], ValidatorIssue::Slashed { fraction } if (fraction - 0.05).abs() < 1e-12));
+        match alerts[0].redelegation.as_ref().unwrap() {
+            CosmosMsg::BeginRedelegate { validator_dst_address, amount, .. } => {
+                assert_eq!(validator_dst_address, "valoper1c");
+                assert_eq!(amount, &Coin::new("uatom", 5000));
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+        assert_eq!(recorder.0.borrow().len(), 1);
+
+        // Staying jailed is not reported again
+        assert!(monitor.check("cosmos1x", &chain, &recorder).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_commission_increase() {
+        let chain = Chain { validators: RefCell::new(vec![validator("valoper1a", 0.05, 1_000_000)]) };
+        let monitor = ValidatorMonitor::default();
+        let recorder = Recorder::default();
+        monitor.check("cosmos1x", &chain, &recorder).unwrap();
+
+        chain.validators.borrow_mut()[0].commission_rate = 0.06;
+        assert!(monitor.check("cosmos1x", &chain, &recorder).unwrap().is_empty());
+
+        chain.validators.borrow_mut()[0].commission_rate = 0.10;
+        let alerts = monitor.check("cosmos1x", &chain, &recorder).unwrap();
+        assert_eq!(alerts[0].issues, vec![ValidatorIssue::CommissionIncrease { from: 0.06, to: 0.10 }]);
+        // No other active validator to move to
+        assert!(alerts[0].redelegation.is_none());
+
+        // A new delegator sees a validator already above the ceiling
+        chain.validators.borrow_mut()[0].commission_rate = 0.5;
+        let alerts = monitor.check("cosmos1y", &chain, &recorder).unwrap();
+        assert_eq!(alerts[0].issues, vec![ValidatorIssue::CommissionIncrease { from: 0.5, to: 0.5 }]);
+    }
+}
@@ -0,0 +1,820 @@
+//! XRP Ledger transaction functionality
+//!
+//! Payments are serialized with the XRP Ledger's canonical binary format,
+//! signed with secp256k1 and submitted to a rippled JSON-RPC server.
+//! Exchanges share one account between their users and tell deposits apart
+//! by destination tag, so tags travel with every payment, X-addresses
+//! (which embed one) are understood, and payments to accounts that require
+//! a tag are refused without one.
+
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::crypto::keys::{KeyPair, KeyType};
+use crate::error::{Error, Result};
+use super::types::{Memo, Transaction, TransactionRequest, TransactionReceipt, TransactionStatus, TransactionSigner, TransactionBroadcaster, TransactionManager, TransactionType, NetworkBinding};
+use super::provider::ProviderConfig;
+
+/// Network ID of the XRP Ledger mainnet
+pub const XRPL_MAINNET_NETWORK_ID: u32 = 0;
+
+/// Network ID of the XRP Ledger testnet
+pub const XRPL_TESTNET_NETWORK_ID: u32 = 1;
+
+/// Network ID of the XRP Ledger devnet
+pub const XRPL_DEVNET_NETWORK_ID: u32 = 2;
+
+/// Drops in one XRP
+pub const DROPS_PER_XRP: u64 = 1_000_000;
+
+/// Total XRP supply in drops, which no amount can exceed
+const MAX_DROPS: u64 = 100_000_000_000 * DROPS_PER_XRP;
+
+/// Seconds between the Unix epoch and the XRP Ledger epoch (2000-01-01)
+const RIPPLE_EPOCH_OFFSET: u64 = 946_684_800;
+
+/// `lsfRequireDestTag`: the account refuses payments without a destination tag
+const LSF_REQUIRE_DEST_TAG: u32 = 0x0002_0000;
+
+/// Ledgers a payment stays valid for before it expires
+const LEDGER_VALIDITY: u32 = 20;
+
+/// Fee when the server doesn't report one, in drops
+const DEFAULT_FEE_DROPS: u64 = 12;
+
+/// Prefix of the hash that single signatures sign (`STX\0`)
+const SIGNING_PREFIX: [u8; 4] = *b"STX\0";
+
+/// Prefix of the hash identifying a signed transaction (`TXN\0`)
+const TRANSACTION_ID_PREFIX: [u8; 4] = *b"TXN\0";
+
+/// Version byte of classic addresses
+const CLASSIC_ADDRESS_VERSION: u8 = 0x00;
+
+/// Prefix of mainnet X-addresses
+const X_ADDRESS_MAINNET_PREFIX: [u8; 2] = [0x05, 0x44];
+
+/// Prefix of testnet X-addresses
+const X_ADDRESS_TESTNET_PREFIX: [u8; 2] = [0x04, 0x93];
+
+/// Encode a payload in base58check with the XRP Ledger's alphabet
+fn encode_base58check(payload: &[u8]) -> String {
+    let checksum = Sha256::digest(Sha256::digest(payload));
+    let bytes = [payload, &checksum[..4]].concat();
+    bs58::encode(bytes).with_alphabet(bs58::Alphabet::RIPPLE).into_string()
+}
+
+/// Decode base58check with the XRP Ledger's alphabet, returning the payload
+fn decode_base58check(address: &str) -> Result<Vec<u8>> {
+    let invalid = |reason: &str| Error::InvalidInput(format!("Invalid XRP address {}: {}", address, reason));
+    let bytes = bs58::decode(address).with_alphabet(bs58::Alphabet::RIPPLE).into_vec()
+        .map_err(|_| invalid("not base58"))?;
+    if bytes.len() < 5 {
+        return Err(invalid("too short"));
+    }
+
+    let (payload, checksum) = bytes.split_at(bytes.len() - 4);
+    if Sha256::digest(Sha256::digest(payload))[..4] != *checksum {
+        return Err(invalid("invalid checksum"));
+    }
+    Ok(payload.to_vec())
+}
+
+/// What an X-address carries besides the account
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XrpXAddressInfo {
+    /// Destination tag, if the X-address has one
+    pub tag: Option<u32>,
+    /// Whether the X-address is for a test network
+    pub testnet: bool,
+}
+
+/// An XRP Ledger account
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XrpAddress {
+    /// Account ID, the HASH160 of the account's public key
+    pub account_id: [u8; 20],
+}
+
+impl XrpAddress {
+    /// Create an address from an account ID
+    pub fn new(account_id: [u8; 20]) -> Self {
+        Self { account_id }
+    }
+
+    /// Parse a classic address (`r...`) or an X-address (`X...`), with the tag and network of the latter
+    pub fn parse(address: &str) -> Result<(Self, Option<XrpXAddressInfo>)> {
+        let invalid = |reason: &str| Error::InvalidInput(format!("Invalid XRP address {}: {}", address, reason));
+        let payload = decode_base58check(address)?;
+
+        match payload.len() {
+            21 if payload[0] == CLASSIC_ADDRESS_VERSION => {
+                let account_id = payload[1..].try_into().expect("21-byte payload");
+                Ok((Self { account_id }, None))
+            }
+            31 => {
+                let testnet = match [payload[0], payload[1]] {
+                    X_ADDRESS_MAINNET_PREFIX => false,
+                    X_ADDRESS_TESTNET_PREFIX => true,
+                    _ => return Err(invalid("unknown X-address prefix")),
+                };
+                let account_id = payload[2..22].try_into().expect("31-byte payload");
+                let tag = u32::from_le_bytes(payload[23..27].try_into().expect("31-byte payload"));
+                // Bytes 27..31 are reserved for 64-bit tags, which the ledger doesn't support
+                let tag = match (payload[22], payload[27..].iter().all(|byte| *byte == 0)) {
+                    (0, true) if tag == 0 => None,
+                    (1, true) => Some(tag),
+                    _ => return Err(invalid("unsupported X-address tag")),
+                };
+                Ok((Self { account_id }, Some(XrpXAddressInfo { tag, testnet })))
+            }
+            _ => Err(invalid("unknown address version")),
+        }
+    }
+
+    /// Classic address (`r...`)
+    pub fn to_classic(&self) -> String {
+        encode_base58check(&[&[CLASSIC_ADDRESS_VERSION][..], &self.account_id].concat())
+    }
+
+    /// X-address (`X...` on mainnet, `T...` on test networks) embedding a destination tag
+    pub fn to_x_address(&self, tag: Option<u32>, testnet: bool) -> String {
+        let mut payload = Vec::with_capacity(31);
+        payload.extend_from_slice(if testnet { &X_ADDRESS_TESTNET_PREFIX } else { &X_ADDRESS_MAINNET_PREFIX });
+        payload.extend_from_slice(&self.account_id);
+        payload.push(u8::from(tag.is_some()));
+        payload.extend_from_slice(&tag.unwrap_or(0).to_le_bytes());
+        payload.extend_from_slice(&[0; 4]);
+        encode_base58check(&payload)
+    }
+}
+
+impl std::fmt::Display for XrpAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_classic())
+    }
+}
+
+/// Resolve a destination to its account and tag
+///
+/// An X-address's tag and an explicit tag must agree; either may be absent.
+pub fn resolve_destination(to: &str, destination_tag: Option<u32>) -> Result<(XrpAddress, Option<u32>, Option<bool>)> {
+    let (address, info) = XrpAddress::parse(to)?;
+    let Some(info) = info else {
+        return Ok((address, destination_tag, None));
+    };
+
+    let tag = match (info.tag, destination_tag) {
+        (Some(embedded), Some(explicit)) if embedded != explicit => {
+            return Err(Error::InvalidInput(format!("Destination tag {} differs from the tag {} in the X-address", explicit, embedded)));
+        }
+        (embedded, explicit) => embedded.or(explicit),
+    };
+    Ok((address, tag, Some(info.testnet)))
+}
+
+/// Writer of the XRP Ledger's canonical binary format
+///
+/// Fields must be written in canonical order: by type code, then field code.
+#[derive(Default)]
+struct XrpWriter {
+    buffer: Vec<u8>,
+}
+
+impl XrpWriter {
+    fn field(&mut self, type_code: u8, field_code: u8) {
+        match (type_code < 16, field_code < 16) {
+            (true, true) => self.buffer.push(type_code << 4 | field_code),
+            (true, false) => self.buffer.extend_from_slice(&[type_code << 4, field_code]),
+            (false, true) => self.buffer.extend_from_slice(&[field_code, type_code]),
+            (false, false) => self.buffer.extend_from_slice(&[0, type_code, field_code]),
+        }
+    }
+
+    fn uint16(&mut self, field_code: u8, value: u16) {
+        self.field(1, field_code);
+        self.buffer.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn uint32(&mut self, field_code: u8, value: u32) {
+        self.field(2, field_code);
+        self.buffer.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Amount of XRP: bit 62 marks a positive native amount
+    fn drops(&mut self, field_code: u8, drops: u64) {
+        self.field(6, field_code);
+        self.buffer.extend_from_slice(&(0x4000_0000_0000_0000 | drops).to_be_bytes());
+    }
+
+    /// Variable-length field, its length prefixed in one to three bytes
+    fn blob(&mut self, field_code: u8, bytes: &[u8]) {
+        self.field(7, field_code);
+        match bytes.len() {
+            length @ 0..=192 => self.buffer.push(length as u8),
+            length @ 193..=12_480 => {
+                let length = length - 193;
+                self.buffer.extend_from_slice(&[193 + (length >> 8) as u8, length as u8]);
+            }
+            length => {
+                let length = length - 12_481;
+                self.buffer.extend_from_slice(&[241 + (length >> 16) as u8, (length >> 8) as u8, length as u8]);
+            }
+        }
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// `Memos` array holding a single memo, typed `text/plain` when it is text
+    fn memo(&mut self, memo: &Memo) {
+        self.field(15, 9); // Memos
+        self.field(14, 10); // Memo
+        if let Memo::Text(_) = memo {
+            self.blob(12, b"text/plain"); // MemoType
+        }
+        self.blob(13, memo.as_bytes()); // MemoData
+        self.field(14, 1); // end of object
+        self.field(15, 1); // end of array
+    }
+
+    fn account(&mut self, field_code: u8, address: &XrpAddress) {
+        self.field(8, field_code);
+        self.buffer.push(20);
+        self.buffer.extend_from_slice(&address.account_id);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+/// An XRP payment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XrpPayment {
+    /// Sending account (classic address)
+    pub account: String,
+    /// Receiving account (classic address)
+    pub destination: String,
+    /// Amount in drops
+    pub amount: u64,
+    /// Fee in drops
+    pub fee: u64,
+    /// Sequence number of the sending account
+    pub sequence: u32,
+    /// Destination tag identifying the recipient at a shared account
+    pub destination_tag: Option<u32>,
+    /// Last ledger the payment may be included in
+    pub last_ledger_sequence: Option<u32>,
+    /// Memo attached to the payment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<Memo>,
+}
+
+impl XrpPayment {
+    /// Build a payment from a request whose nonce is the account sequence
+    ///
+    /// An X-address destination is split into its account and tag; the gas
+    /// price, if set, is the fee in drops.
+    pub fn from_request(request: &TransactionRequest) -> Result<Self> {
+        if request.key_type != KeyType::Xrp {
+            return Err(Error::Transaction("Not an XRP transaction".to_string()));
+        }
+        if request.data.is_some() {
+            return Err(Error::Transaction("XRP payments carry no contract data".to_string()));
+        }
+        let (destination, destination_tag, _) = resolve_destination(&request.to, request.destination_tag)?;
+        let drops = |field: &str, value: &str| value.parse::<u64>().ok().filter(|drops| *drops <= MAX_DROPS)
+            .ok_or_else(|| Error::InvalidInput(format!("Invalid {} in drops: {}", field, value)));
+        let sequence = request.nonce
+            .ok_or_else(|| Error::Transaction("XRP payments need the account sequence as nonce".to_string()))?;
+        if let Some(memo) = &request.memo {
+            memo.check(KeyType::Xrp)?;
+        }
+
+        Ok(Self {
+            account: XrpAddress::parse(&request.from)?.0.to_classic(),
+            destination: destination.to_classic(),
+            amount: drops("amount", &request.value)?,
+            fee: request.gas_price.as_deref().map(|fee| drops("fee", fee)).transpose()?.unwrap_or(DEFAULT_FEE_DROPS),
+            sequence: u32::try_from(sequence).map_err(|_| Error::InvalidInput(format!("Invalid sequence: {}", sequence)))?,
+            destination_tag,
+            last_ledger_sequence: None,
+            memo: request.memo.clone(),
+        })
+    }
+
+    /// Canonical binary encoding, with the signature once signed
+    pub fn encode(&self, public_key: &[u8], signature: Option<&[u8]>) -> Result<Vec<u8>> {
+        let (account, _) = XrpAddress::parse(&self.account)?;
+        let (destination, _) = XrpAddress::parse(&self.destination)?;
+
+        let mut writer = XrpWriter::default();
+        writer.uint16(2, 0); // TransactionType: Payment
+        writer.uint32(2, 0); // Flags
+        writer.uint32(4, self.sequence);
+        if let Some(tag) = self.destination_tag {
+            writer.uint32(14, tag);
+        }
+        if let Some(last_ledger_sequence) = self.last_ledger_sequence {
+            writer.uint32(27, last_ledger_sequence);
+        }
+        writer.drops(1, self.amount);
+        writer.drops(8, self.fee);
+        writer.blob(3, public_key);
+        if let Some(signature) = signature {
+            writer.blob(4, signature);
+        }
+        writer.account(1, &account);
+        writer.account(3, &destination);
+        if let Some(memo) = &self.memo {
+            writer.memo(memo);
+        }
+        Ok(writer.finish())
+    }
+}
+
+/// First half of SHA-512, the XRP Ledger's hash function
+fn sha512_half(prefix: &[u8], data: &[u8]) -> [u8; 32] {
+    let digest = Sha512::new().chain_update(prefix).chain_update(data).finalize();
+    digest[..32].try_into().expect("SHA-512 digests are 64 bytes")
+}
+
+/// Sign a payment, returning the transaction blob ready to submit
+pub fn sign_payment(key_pair: &KeyPair, payment: &XrpPayment) -> Result<Vec<u8>> {
+    if key_pair.key_type() != KeyType::Xrp {
+        return Err(Error::Signing("Not an XRP key pair".to_string()));
+    }
+    let account = crate::crypto::keys::xrp::public_key_to_address(key_pair.public_key())?;
+    if account != payment.account {
+        return Err(Error::Signing(format!("Key pair of {} cannot sign for {}", account, payment.account)));
+    }
+
+    let public_key = key_pair.public_key().as_bytes();
+    let secret_key = secp256k1::SecretKey::from_slice(key_pair.private_key().as_bytes())
+        .map_err(|e| Error::Signing(format!("Invalid private key: {}", e)))?;
+    let message = secp256k1::Message::from_digest(sha512_half(&SIGNING_PREFIX, &payment.encode(public_key, None)?));
+    // libsecp256k1 produces low-S signatures, which the ledger requires
+    let signature = secp256k1::Secp256k1::signing_only().sign_ecdsa(&message, &secret_key).serialize_der();
+
+    payment.encode(public_key, Some(&signature))
+}
+
+/// Hash identifying a signed transaction, as explorers show it
+pub fn xrp_tx_hash(blob: &[u8]) -> String {
+    hex::encode_upper(sha512_half(&TRANSACTION_ID_PREFIX, blob))
+}
+
+/// State of an XRP Ledger account
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XrpAccountInfo {
+    /// Balance in drops, including the reserve
+    pub balance: u64,
+    /// Sequence number of the next transaction
+    pub sequence: u32,
+    /// Objects the account owns (trust lines, offers, ...), each adding to its reserve
+    pub owner_count: u32,
+    /// Account flags
+    pub flags: u32,
+}
+
+impl XrpAccountInfo {
+    /// Whether the account refuses payments without a destination tag
+    pub fn requires_destination_tag(&self) -> bool {
+        self.flags & LSF_REQUIRE_DEST_TAG != 0
+    }
+}
+
+/// Reserves of the XRP Ledger, in drops
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XrpReserves {
+    /// Reserve every account holds
+    pub base: u64,
+    /// Reserve for each object an account owns
+    pub owner: u64,
+}
+
+impl XrpReserves {
+    /// Reserve of an account
+    pub fn for_account(&self, account: &XrpAccountInfo) -> u64 {
+        self.base + self.owner * u64::from(account.owner_count)
+    }
+}
+
+/// Balance of an account, split by the reserve it can't spend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XrpBalance {
+    /// Total balance in drops
+    pub total: u64,
+    /// Reserve in drops, locked while the account exists
+    pub reserved: u64,
+    /// Balance above the reserve, in drops
+    pub spendable: u64,
+}
+
+/// XRP Ledger provider, backed by a rippled JSON-RPC server
+pub struct XrpProvider {
+    /// Provider configuration
+    config: ProviderConfig,
+    /// Network ID of the ledger the server follows
+    network_id: u32,
+    /// HTTP client
+    #[cfg(feature = "rpc")]
+    http: reqwest::Client,
+}
+
+impl XrpProvider {
+    /// Create a provider, inferring the network from the URL (`altnet`/`testnet`, `devnet`, otherwise mainnet)
+    pub fn new(config: ProviderConfig) -> Result<Self> {
+        let network_id = if config.url.contains("altnet") || config.url.contains("testnet") {
+            XRPL_TESTNET_NETWORK_ID
+        } else if config.url.contains("devnet") {
+            XRPL_DEVNET_NETWORK_ID
+        } else {
+            XRPL_MAINNET_NETWORK_ID
+        };
+
+        #[cfg(feature = "rpc")]
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(config.timeout.unwrap_or(30)))
+            .build()
+            .map_err(|e| Error::Provider(format!("Failed to create HTTP client: {}", e)))?;
+
+        Ok(Self {
+            config,
+            network_id,
+            #[cfg(feature = "rpc")]
+            http,
+        })
+    }
+
+    /// Set the network ID, for servers whose URL doesn't name their network
+    ///
+    /// Networks above 1024 would need a `NetworkID` field in every
+    /// transaction, which payments here don't carry.
+    pub fn with_network_id(mut self, network_id: u32) -> Result<Self> {
+        if network_id > 1024 {
+            return Err(Error::NotSupported(format!("XRP Ledger networks above 1024 are not supported: {}", network_id)));
+        }
+        self.network_id = network_id;
+        Ok(self)
+    }
+
+    /// Get the network this provider is connected to
+    pub fn network_binding(&self) -> NetworkBinding {
+        NetworkBinding::Xrp { network_id: self.network_id }
+    }
+
+    /// Call a rippled method, returning its result; errors come back as `Err` with the server's error code
+    #[cfg(feature = "rpc")]
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = self.http.post(&self.config.url).json(&json!({ "method": method, "params": [params] }));
+        let response: Value = super::ethereum::block_on(super::ethereum::traced(super::ethereum::rpc_span("xrp", &self.config.url, method), async {
+            request.send().await
+                .map_err(|e| Error::Network(format!("rippled request failed: {}", e)))?
+                .json().await
+                .map_err(|e| Error::Provider(format!("Invalid rippled response: {}", e)))
+        }))??;
+
+        let result = &response["result"];
+        if result["status"].as_str() == Some("error") {
+            return Err(Error::Provider(format!("rippled {} failed: {}", method, result["error"].as_str().unwrap_or("unknown error"))));
+        }
+        Ok(result.clone())
+    }
+
+    #[cfg(not(feature = "rpc"))]
+    fn call(&self, _method: &str, _params: Value) -> Result<Value> {
+        Err(Error::NotSupported("The XRP Ledger requires the rpc feature".to_string()))
+    }
+
+    /// Get the state of an account, `None` if it hasn't been funded
+    pub fn get_account_info(&self, address: &str) -> Result<Option<XrpAccountInfo>> {
+        match self.call("account_info", json!({ "account": address, "ledger_index": "current" })) {
+            Ok(result) => parse_account_info(&result["account_data"]).map(Some),
+            Err(Error::Provider(message)) if message.ends_with("actNotFound") => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get the current reserves
+    pub fn get_reserves(&self) -> Result<XrpReserves> {
+        parse_reserves(&self.call("server_info", json!({}))?)
+    }
+
+    /// Get the balance of an account, net of its reserve
+    pub fn get_balance(&self, address: &str) -> Result<XrpBalance> {
+        let Some(account) = self.get_account_info(address)? else {
+            return Ok(XrpBalance { total: 0, reserved: 0, spendable: 0 });
+        };
+        let reserved = self.get_reserves()?.for_account(&account);
+        Ok(XrpBalance { total: account.balance, reserved, spendable: account.balance.saturating_sub(reserved) })
+    }
+
+    /// Get the open ledger fee in drops and the current ledger index
+    fn get_fee(&self) -> Result<(u64, u32)> {
+        let result = self.call("fee", json!({}))?;
+        let fee = result["drops"]["open_ledger_fee"].as_str().and_then(|fee| fee.parse().ok()).unwrap_or(DEFAULT_FEE_DROPS);
+        let ledger = result["ledger_current_index"].as_u64()
+            .ok_or_else(|| Error::Provider(format!("Invalid fee response: {}", result)))?;
+        Ok((fee.max(DEFAULT_FEE_DROPS), ledger as u32))
+    }
+
+    /// Sign and submit a payment request, returning the transaction hash
+    ///
+    /// The sequence and fee are filled in when the request leaves them
+    /// unset. The payment is refused if the destination requires a tag and
+    /// has none, if it would not fund a new account's reserve, or if it would
+    /// dip into the sender's reserve.
+    pub fn transfer(&self, key_pair: &KeyPair, request: &TransactionRequest) -> Result<String> {
+        request.network.ensure_matches(&self.network_binding())?;
+        let (_, _, testnet) = resolve_destination(&request.to, request.destination_tag)?;
+        if testnet.is_some_and(|testnet| testnet != (self.network_id != XRPL_MAINNET_NETWORK_ID)) {
+            return Err(Error::InvalidInput("The X-address is for a different network".to_string()));
+        }
+
+        let account = self.get_account_info(&request.from)?
+            .ok_or_else(|| Error::Transaction(format!("Account {} has not been funded", request.from)))?;
+        let (fee, ledger) = self.get_fee()?;
+        let mut request = request.clone();
+        request.nonce = request.nonce.or(Some(u64::from(account.sequence)));
+        request.gas_price = request.gas_price.or(Some(fee.to_string()));
+        let mut payment = XrpPayment::from_request(&request)?;
+        payment.last_ledger_sequence = Some(ledger + LEDGER_VALIDITY);
+
+        let reserves = self.get_reserves()?;
+        match self.get_account_info(&payment.destination)? {
+            Some(destination) if destination.requires_destination_tag() && payment.destination_tag.is_none() => {
+                return Err(Error::InvalidInput(format!("{} requires a destination tag", payment.destination)));
+            }
+            None if payment.amount < reserves.base => {
+                return Err(Error::InvalidInput(format!(
+                    "{} does not exist yet; creating it takes at least {} drops", payment.destination, reserves.base,
+                )));
+            }
+            _ => {}
+        }
+        let spendable = account.balance.saturating_sub(reserves.for_account(&account));
+        if payment.amount + payment.fee > spendable {
+            return Err(Error::Transaction(format!(
+                "Insufficient balance: {} drops spendable above the reserve, {} needed", spendable, payment.amount + payment.fee,
+            )));
+        }
+
+        self.broadcast_transaction(&sign_payment(key_pair, &payment)?)
+    }
+}
+
+/// Parse `account_data` from `account_info`
+pub fn parse_account_info(account: &Value) -> Result<XrpAccountInfo> {
+    let balance = account["Balance"].as_str().and_then(|balance| balance.parse().ok())
+        .ok_or_else(|| Error::Provider(format!("Invalid account data: {}", account)))?;
+    let number = |field: &str| account[field].as_u64().unwrap_or(0) as u32;
+
+    Ok(XrpAccountInfo { balance, sequence: number("Sequence"), owner_count: number("OwnerCount"), flags: number("Flags") })
+}
+
+/// Parse the reserves from `server_info`, which reports them in XRP
+pub fn parse_reserves(result: &Value) -> Result<XrpReserves> {
+    let ledger = &result["info"]["validated_ledger"];
+    let drops = |field: &str| ledger[field].as_f64().map(|xrp| (xrp * DROPS_PER_XRP as f64).round() as u64)
+        .ok_or_else(|| Error::Provider(format!("Server reports no {}", field)));
+
+    Ok(XrpReserves { base: drops("reserve_base_xrp")?, owner: drops("reserve_inc_xrp")? })
+}
+
+/// Parse a transaction with its metadata
+///
+/// Payments report the amount delivered, which for partial payments is
+/// less than the amount sent.
+pub fn parse_transaction(tx: &Value, meta: &Value, validated: bool) -> Result<Transaction> {
+    let hash = tx["hash"].as_str()
+        .ok_or_else(|| Error::Provider(format!("Invalid transaction: {}", tx)))?;
+    let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+
+    let (transaction_type, value) = match tx["TransactionType"].as_str() {
+        Some("Payment") => {
+            let delivered = if meta["delivered_amount"].is_null() { &tx["Amount"] } else { &meta["delivered_amount"] };
+            match delivered.as_str() {
+                Some(drops) => (TransactionType::Transfer, drops.to_string()),
+                // Issued currencies are objects with a decimal value
+                None => (TransactionType::TokenTransfer, text(&delivered["value"])),
+            }
+        }
+        _ => (TransactionType::Other, "0".to_string()),
+    };
+    // Memos are hex encoded; only the first is kept
+    let memo = tx["Memos"][0]["Memo"]["MemoData"].as_str()
+        .and_then(|data| hex::decode(data).ok())
+        .map(Memo::from_bytes);
+    let status = match (validated, meta["TransactionResult"].as_str()) {
+        (false, _) => TransactionStatus::Pending,
+        (true, Some("tesSUCCESS")) => TransactionStatus::Confirmed,
+        (true, _) => TransactionStatus::Failed,
+    };
+
+    Ok(Transaction {
+        hash: hash.to_string(),
+        transaction_type,
+        key_type: KeyType::Xrp,
+        from: text(&tx["Account"]),
+        to: text(&tx["Destination"]),
+        value,
+        gas_price: None,
+        gas_limit: None,
+        nonce: tx["Sequence"].as_u64(),
+        data: None,
+        status,
+        block_number: tx["ledger_index"].as_u64(),
+        timestamp: tx["date"].as_u64().map(|date| date + RIPPLE_EPOCH_OFFSET),
+        fee: tx["Fee"].as_str().map(str::to_string),
+        memo,
+    })
+}
+
+impl TransactionSigner for XrpProvider {
+    fn sign_transaction(&self, request: &TransactionRequest) -> Result<Vec<u8>> {
+        request.network.ensure_matches(&self.network_binding())?;
+        XrpPayment::from_request(request)?;
+
+        Err(Error::NotSupported("XRP payments are signed by XrpProvider::transfer with the account's key pair".to_string()))
+    }
+}
+
+impl TransactionBroadcaster for XrpProvider {
+    fn broadcast_transaction(&self, signed_transaction: &[u8]) -> Result<String> {
+        let result = self.call("submit", json!({ "tx_blob": hex::encode_upper(signed_transaction) }))?;
+        let engine_result = result["engine_result"].as_str().unwrap_or_default();
+        // `ter` results are queued or retried by the server and may still succeed
+        if engine_result != "tesSUCCESS" && !engine_result.starts_with("ter") {
+            return Err(Error::Transaction(format!("Transaction rejected ({}): {}", engine_result, result["engine_result_message"])));
+        }
+        Ok(result["tx_json"]["hash"].as_str().map(str::to_string).unwrap_or_else(|| xrp_tx_hash(signed_transaction)))
+    }
+
+    fn get_transaction_status(&self, hash: &str) -> Result<TransactionStatus> {
+        match self.get_transaction(hash) {
+            Ok(transaction) => Ok(transaction.status),
+            Err(Error::Provider(message)) if message.ends_with("txnNotFound") => Ok(TransactionStatus::Pending),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn get_transaction_receipt(&self, hash: &str) -> Result<TransactionReceipt> {
+        let transaction = self.get_transaction(hash)?;
+        Ok(TransactionReceipt {
+            hash: transaction.hash,
+            status: transaction.status,
+            block_number: transaction.block_number,
+            timestamp: transaction.timestamp,
+            fee: transaction.fee,
+            logs: vec![],
+        })
+    }
+}
+
+impl TransactionManager for XrpProvider {
+    fn get_transaction(&self, hash: &str) -> Result<Transaction> {
+        let result = self.call("tx", json!({ "transaction": hash }))?;
+        parse_transaction(&result, &result["meta"], result["validated"].as_bool().unwrap_or(false))
+    }
+
+    fn get_transactions(&self, address: &str, limit: usize, offset: usize) -> Result<Vec<Transaction>> {
+        // account_tx pages by marker, so fetch through the offset and skip it
+        let result = self.call("account_tx", json!({
+            "account": address,
+            "ledger_index_min": -1,
+            "ledger_index_max": -1,
+            "limit": limit + offset,
+            "forward": false,
+        }))?;
+        result["transactions"].as_array().cloned().unwrap_or_default()
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|entry| parse_transaction(&entry["tx"], &entry["meta"], entry["validated"].as_bool().unwrap_or(false)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keys::xrp::{derive_xrp_key_pair, public_key_to_address};
+    use crate::crypto::mnemonic::mnemonic_to_seed;
+
+    const DESTINATION: &str = "rHb9CJAWyB4rj91VRWn96DkukG4bwdtyTh";
+
+    fn request(to: &str, destination_tag: Option<u32>) -> TransactionRequest {
+        TransactionRequest {
+            key_type: KeyType::Xrp,
+            network: NetworkBinding::Xrp { network_id: XRPL_MAINNET_NETWORK_ID },
+            from: "rHsMGQEkVNJmpGWs8XUBoTBiAAbwxZN5v3".to_string(),
+            to: to.to_string(),
+            value: "1000000".to_string(),
+            gas_price: Some("12".to_string()),
+            gas_limit: None,
+            nonce: Some(42),
+            data: None,
+            destination_tag,
+            memo: None,
+        }
+    }
+
+    #[test]
+    fn test_x_address() {
+        let (address, info) = XrpAddress::parse(DESTINATION).unwrap();
+        assert_eq!(info, None);
+        assert_eq!(address.to_x_address(Some(12345), false), "XVPcpSm47b1CZkf5AkKM9a84dQHe3mTAxgxfLw2qYoe7Boa");
+        assert_eq!(address.to_x_address(None, false), "XVPcpSm47b1CZkf5AkKM9a84dQHe3m4sBhsrA4XtnBECTAc");
+
+        let (parsed, info) = XrpAddress::parse("XVPcpSm47b1CZkf5AkKM9a84dQHe3mTAxgxfLw2qYoe7Boa").unwrap();
+        assert_eq!(parsed, address);
+        assert_eq!(info, Some(XrpXAddressInfo { tag: Some(12345), testnet: false }));
+
+        assert!(XrpAddress::parse("rHb9CJAWyB4rj91VRWn96DkukG4bwdtyTi").is_err());
+    }
+
+    #[test]
+    fn test_resolve_destination() {
+        let x_address = "XVPcpSm47b1CZkf5AkKM9a84dQHe3mTAxgxfLw2qYoe7Boa";
+        assert_eq!(resolve_destination(x_address, None).unwrap().1, Some(12345));
+        assert_eq!(resolve_destination(x_address, Some(12345)).unwrap().1, Some(12345));
+        assert!(resolve_destination(x_address, Some(1)).is_err());
+        assert_eq!(resolve_destination(DESTINATION, Some(7)).unwrap().1, Some(7));
+    }
+
+    #[test]
+    fn test_sign_payment() {
+        let seed = mnemonic_to_seed("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap();
+        let key_pair = derive_xrp_key_pair(&seed, "m/44'/144'/0'/0/0").unwrap();
+        assert_eq!(public_key_to_address(key_pair.public_key()).unwrap(), "rHsMGQEkVNJmpGWs8XUBoTBiAAbwxZN5v3");
+
+        // The tag of an X-address ends up in the DestinationTag field
+        let mut payment = XrpPayment::from_request(&request("XVPcpSm47b1CZkf5AkKM9a84dQHe3mTAxgxfLw2qYoe7Boa", None)).unwrap();
+        assert_eq!(payment.destination, DESTINATION);
+        assert_eq!(payment.destination_tag, Some(12345));
+        payment.last_ledger_sequence = Some(90_000_000);
+
+        let blob = sign_payment(&key_pair, &payment).unwrap();
+        assert_eq!(hex::encode_upper(&blob), "1200002200000000240000002A2E00003039201B055D4A806140000000000F424068400000000000000C7321031D68BC1A142E6766B2BDFB006CCFE135EF2E0E2E94ABB5CF5C9AB6104776FBAE7446304402201F3643C54FB0A0B5B8D78AAFF5C31697E5C6CA287076A25B3E5DF97F078120BF02200B392D9723BF4ED7D190D48DE814C0693F48B5F281490FDF260514777F59D5BB8114AFF3C2E33458B30714CA16FFEE19952DD35C17C88314B5F762798A53D543A014CAF8B297CFF8F2F937E8");
+        assert_eq!(xrp_tx_hash(&blob), "E52E5D54ED72A74740DFE2ABCE0F09936C09A19D3749C207B22A3619CDC5F863");
+    }
+
+    #[test]
+    fn test_sign_payment_with_memo() {
+        let seed = mnemonic_to_seed("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap();
+        let key_pair = derive_xrp_key_pair(&seed, "m/44'/144'/0'/0/0").unwrap();
+
+        let request = TransactionRequest { memo: Some(Memo::Text("invoice 42".to_string())), ..request(DESTINATION, Some(12345)) };
+        let mut payment = XrpPayment::from_request(&request).unwrap();
+        payment.last_ledger_sequence = Some(90_000_000);
+
+        let blob = sign_payment(&key_pair, &payment).unwrap();
+        assert!(hex::encode_upper(&blob).ends_with("F9EA7C0A746578742F706C61696E7D0A696E766F696365203432E1F1"));
+        assert_eq!(xrp_tx_hash(&blob), "DCA83D9CDD06217A082311B26BD0D394684CE615B865D55B50BD1B40312A602F");
+
+        let mut writer = XrpWriter::default();
+        writer.blob(13, &[0; 1000]);
+        assert_eq!(writer.finish()[..3], [0x7D, 0xC4, 0x27]);
+    }
+
+    #[test]
+    fn test_payment_from_request() {
+        assert!(XrpPayment::from_request(&request(DESTINATION, None)).unwrap().destination_tag.is_none());
+        assert!(XrpPayment::from_request(&TransactionRequest { nonce: None, ..request(DESTINATION, None) }).is_err());
+        assert!(XrpPayment::from_request(&TransactionRequest { value: "1.5".to_string(), ..request(DESTINATION, None) }).is_err());
+    }
+
+    #[test]
+    fn test_parse_responses() {
+        let account = parse_account_info(&json!({ "Balance": "25000000", "Sequence": 7, "OwnerCount": 2, "Flags": 131072 })).unwrap();
+        assert!(account.requires_destination_tag());
+
+        let reserves = parse_reserves(&json!({ "info": { "validated_ledger": { "reserve_base_xrp": 1, "reserve_inc_xrp": 0.2 } } })).unwrap();
+        assert_eq!(reserves, XrpReserves { base: 1_000_000, owner: 200_000 });
+        assert_eq!(reserves.for_account(&account), 1_400_000);
+
+        // A partial payment delivers less than its Amount
+        let tx = json!({
+            "hash": "ABC",
+            "TransactionType": "Payment",
+            "Account": "rHsMGQEkVNJmpGWs8XUBoTBiAAbwxZN5v3",
+            "Destination": DESTINATION,
+            "Amount": "1000000",
+            "Fee": "12",
+            "Sequence": 42,
+            "ledger_index": 90000000,
+            "date": 753_315_200,
+        });
+        let transaction = parse_transaction(&tx, &json!({ "TransactionResult": "tesSUCCESS", "delivered_amount": "400000" }), true).unwrap();
+        assert_eq!(transaction.value, "400000");
+        assert_eq!(transaction.status, TransactionStatus::Confirmed);
+        assert_eq!(transaction.timestamp, Some(1_700_000_000));
+        assert_eq!(transaction.memo, None);
+
+        let mut with_memo = tx.clone();
+        with_memo["Memos"] = json!([{ "Memo": { "MemoType": "746578742F706C61696E", "MemoData": "696E766F696365203432" } }]);
+        assert_eq!(parse_transaction(&with_memo, &json!({}), true).unwrap().memo, Some(Memo::Text("invoice 42".to_string())));
+        assert_eq!(parse_transaction(&tx, &json!({}), false).unwrap().status, TransactionStatus::Pending);
+    }
+}
@@ -0,0 +1,319 @@
+//! Request validation
+//!
+//! Types implementing [`Validate`] report every invalid field at once as an
+//! [`Error::Validation`] with field paths, instead of failing on the first
+//! parse error with a free-text message.
+
+use std::fmt::Display;
+
+use crate::account::metadata::{is_hex_color, is_icon_name, WalletMetadata, MAX_NOTES, MAX_NOTE_LENGTH};
+use crate::address::{validate_address, AddressInfo};
+use crate::invoice::{CreatePaymentRequest, MAX_EXPIRY, MIN_EXPIRY};
+use crate::crypto::keys::KeyType;
+use crate::defi::{SwapRequest, Token, TokenAmount};
+use crate::error::{Error, FieldViolation, Result};
+use crate::transaction::{NetworkBinding, TransactionRequest, TON_MAINNET_GLOBAL_ID, TON_TESTNET_GLOBAL_ID};
+
+/// Collects field violations
+#[derive(Debug, Default)]
+pub struct Validator {
+    path: Vec<String>,
+    violations: Vec<FieldViolation>,
+}
+
+impl Validator {
+    /// Create a validator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn field_path(&self, field: &str) -> String {
+        self.path.iter().map(String::as_str).chain(std::iter::once(field)).collect::<Vec<_>>().join(".")
+    }
+
+    /// Record a violation unless `valid` holds
+    pub fn check(&mut self, field: &str, valid: bool, description: impl Into<String>) -> &mut Self {
+        if !valid {
+            let field = self.field_path(field);
+            self.violations.push(FieldViolation { field, description: description.into() });
+        }
+        self
+    }
+
+    /// Require a non-blank string
+    pub fn required(&mut self, field: &str, value: &str) -> &mut Self {
+        self.check(field, !value.trim().is_empty(), "must not be empty")
+    }
+
+    /// Require an amount in the smallest unit (a decimal integer), returning it if valid
+    pub fn amount(&mut self, field: &str, value: &str) -> Option<u128> {
+        let amount = value.parse::<u128>().ok().filter(|_| value.bytes().all(|b| b.is_ascii_digit()));
+        self.check(field, amount.is_some(), "must be a non-negative decimal integer");
+        amount
+    }
+
+    /// Require a valid address of the chain, returning it if valid
+    pub fn address(&mut self, field: &str, key_type: KeyType, value: &str) -> Option<AddressInfo> {
+        match validate_address(key_type, value) {
+            Ok(info) => Some(info),
+            Err(error) => {
+                self.check(field, false, error.to_string());
+                None
+            }
+        }
+    }
+
+    /// Require a value within an inclusive range
+    pub fn range<T: PartialOrd + Display>(&mut self, field: &str, value: T, min: T, max: T) -> &mut Self {
+        let valid = value >= min && value <= max;
+        self.check(field, valid, format!("must be between {} and {}", min, max))
+    }
+
+    /// Validate a nested value, prefixing its field paths with `field`
+    pub fn nested(&mut self, field: &str, value: &impl Validate) -> &mut Self {
+        self.path.push(field.to_string());
+        value.check(self);
+        self.path.pop();
+        self
+    }
+
+    /// Get the violations collected so far
+    pub fn violations(&self) -> &[FieldViolation] {
+        &self.violations
+    }
+
+    /// Finish validation, failing with all collected violations
+    pub fn finish(self) -> Result<()> {
+        if self.violations.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Validation(self.violations))
+        }
+    }
+}
+
+/// A request type with field-level validation
+pub trait Validate {
+    /// Record the violations of this value
+    fn check(&self, validator: &mut Validator);
+
+    /// Validate, failing with every violation found
+    fn validate(&self) -> Result<()> {
+        let mut validator = Validator::new();
+        self.check(&mut validator);
+        validator.finish()
+    }
+}
+
+impl Validate for TransactionRequest {
+    fn check(&self, v: &mut Validator) {
+        if let Some(from) = v.address("from", self.key_type, &self.from) {
+            v.check("from", from.can_sign(), "must be a signing key, not a program derived address");
+        }
+        v.address("to", self.key_type, &self.to);
+        v.amount("value", &self.value);
+        if let Some(gas_price) = &self.gas_price {
+            v.amount("gas_price", gas_price);
+        }
+        if let Some(gas_limit) = &self.gas_limit {
+            v.amount("gas_limit", gas_limit);
+        }
+
+        v.check("network", self.network.key_type() == self.key_type, format!("must be a {:?} network", self.key_type));
+        match &self.network {
+            NetworkBinding::Evm { chain_id } => {
+                v.check("network.chain_id", *chain_id != 0, "must not be zero");
+            }
+            NetworkBinding::Solana { genesis_hash } => {
+                v.required("network.genesis_hash", genesis_hash);
+            }
+            NetworkBinding::Bitcoin { network } => {
+                let known = ["bitcoin", "testnet", "signet", "regtest"].contains(&network.as_str());
+                v.check("network.network", known, "must be bitcoin, testnet, signet or regtest");
+            }
+            NetworkBinding::Ton { global_id } => {
+                let known = [TON_MAINNET_GLOBAL_ID, TON_TESTNET_GLOBAL_ID].contains(global_id);
+                v.check("network.global_id", known, "must be -239 (mainnet) or -3 (testnet)");
+            }
+            NetworkBinding::Cosmos { chain_id } => {
+                v.required("network.chain_id", chain_id);
+            }
+            NetworkBinding::Xrp { network_id } => {
+                v.check("network.network_id", *network_id <= 1024, "must be at most 1024");
+            }
+        }
+
+        if self.key_type != KeyType::Ethereum {
+            v.check("data", self.data.is_none(), "is only supported on EVM chains");
+        }
+        if self.key_type != KeyType::Xrp {
+            v.check("destination_tag", self.destination_tag.is_none(), "is only supported on the XRP Ledger");
+        }
+        if let Some(Err(error)) = self.memo.as_ref().map(|memo| memo.check(self.key_type)) {
+            v.check("memo", false, error.to_string());
+        }
+    }
+}
+
+impl Validate for Token {
+    fn check(&self, v: &mut Validator) {
+        // Bitcoin has no token contracts and native TON and XRP have none, so their token address is a placeholder;
+        // Cosmos tokens are denoms rather than addresses
+        if matches!(self.key_type, KeyType::Bitcoin | KeyType::Cosmos) || (self.key_type == KeyType::Ton && self.address == "TON") || (self.key_type == KeyType::Xrp && self.address == "XRP") {
+            v.required("address", &self.address);
+        } else {
+            v.address("address", self.key_type, &self.address);
+        }
+        v.required("symbol", &self.symbol);
+        v.range("decimals", self.decimals, 0, 36);
+    }
+}
+
+impl Validate for TokenAmount {
+    fn check(&self, v: &mut Validator) {
+        v.nested("token", &self.token);
+        if let Some(amount) = v.amount("amount", &self.amount) {
+            v.check("amount", amount > 0, "must be greater than zero");
+        }
+    }
+}
+
+impl Validate for SwapRequest {
+    fn check(&self, v: &mut Validator) {
+        v.nested("from", &self.from).nested("to", &self.to);
+        v.range("slippage", self.slippage, 0.0, 50.0);
+        v.check("to", self.from.token.key_type == self.to.key_type, "must be on the same chain as the input token");
+        v.check("to", !self.from.token.address.eq_ignore_ascii_case(&self.to.address), "must differ from the input token");
+    }
+}
+
+impl Validate for WalletMetadata {
+    fn check(&self, v: &mut Validator) {
+        if let Some(color) = &self.color {
+            v.check("color", is_hex_color(color), "must be a #RRGGBB color");
+        }
+        if let Some(icon) = &self.icon {
+            v.check("icon", is_icon_name(icon), "must be lowercase letters, digits and dashes");
+        }
+        v.check("notes", self.notes.len() <= MAX_NOTES, format!("must have at most {} entries", MAX_NOTES));
+        for (address, note) in &self.notes {
+            v.check(&format!("notes.{}", address), note.chars().count() <= MAX_NOTE_LENGTH, format!("must be at most {} characters", MAX_NOTE_LENGTH));
+        }
+    }
+}
+
+impl Validate for CreatePaymentRequest {
+    fn check(&self, v: &mut Validator) {
+        if let Some(amount) = v.amount("amount", &self.amount) {
+            v.check("amount", amount > 0, "must be greater than zero");
+        }
+        v.range("expires_in", self.expires_in, MIN_EXPIRY, MAX_EXPIRY);
+        if let Some(asset) = &self.asset {
+            match self.key_type {
+                KeyType::Ethereum | KeyType::Solana => {
+                    v.address("asset", self.key_type, asset);
+                }
+                _ => {
+                    v.check("asset", false, format!("tokens are not supported on {:?}", self.key_type));
+                }
+            }
+            v.check("decimals", self.decimals.is_some(), "is required with asset");
+        }
+        if let Some(decimals) = self.decimals {
+            v.range("decimals", decimals, 0, 36);
+        }
+        if self.key_type != KeyType::Ethereum {
+            v.check("chain_id", self.chain_id.is_none(), "is only supported on EVM chains");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::Memo;
+
+    fn request() -> TransactionRequest {
+        TransactionRequest {
+            key_type: KeyType::Ethereum,
+            network: NetworkBinding::Evm { chain_id: 1 },
+            from: "0x9858EfFD232B4033E47d90003D41EC34EcaEda94".to_string(),
+            to: "0x742d35Cc6634C0532925a3b844Bc454e4438f44e".to_string(),
+            value: "1000".to_string(),
+            gas_price: None,
+            gas_limit: None,
+            nonce: None,
+            data: None,
+            destination_tag: None,
+            memo: None,
+        }
+    }
+
+    #[test]
+    fn test_valid_request() {
+        assert!(request().validate().is_ok());
+    }
+
+    #[test]
+    fn test_all_violations_reported() {
+        let invalid = TransactionRequest {
+            network: NetworkBinding::Evm { chain_id: 0 },
+            to: " ".to_string(),
+            value: "-5".to_string(),
+            gas_price: Some("1.5".to_string()),
+            ..request()
+        };
+
+        let error = invalid.validate().unwrap_err();
+        let fields: Vec<&str> = error.violations().iter().map(|violation| violation.field.as_str()).collect();
+        assert_eq!(fields, vec!["to", "value", "gas_price", "network.chain_id"]);
+    }
+
+    #[test]
+    fn test_destination_tag_only_on_xrp() {
+        let tagged = TransactionRequest { destination_tag: Some(12345), ..request() };
+        let error = tagged.validate().unwrap_err();
+        assert_eq!(error.violations()[0].field, "destination_tag");
+    }
+
+    #[test]
+    fn test_memo_checked_per_chain() {
+        let memo = TransactionRequest { memo: Some(Memo::Text("invoice 42".to_string())), ..request() };
+        assert_eq!(memo.validate().unwrap_err().violations()[0].field, "memo");
+
+        let solana = TransactionRequest {
+            key_type: KeyType::Solana,
+            network: NetworkBinding::Solana { genesis_hash: crate::transaction::SOLANA_MAINNET_GENESIS_HASH.to_string() },
+            from: "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM".to_string(),
+            to: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(),
+            ..memo
+        };
+        assert!(solana.validate().is_ok());
+        assert!(TransactionRequest { memo: Some(Memo::Bytes(vec![0xff])), ..solana.clone() }.validate().is_err());
+        assert!(TransactionRequest { memo: Some(Memo::Text("a".repeat(513))), ..solana }.validate().is_err());
+    }
+
+    #[test]
+    fn test_nested_paths() {
+        let token = Token {
+            name: "Ether".to_string(),
+            symbol: "ETH".to_string(),
+            decimals: 18,
+            address: "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE".to_string(),
+            key_type: KeyType::Ethereum,
+            logo_url: None,
+        };
+        let swap = SwapRequest {
+            from: TokenAmount { token: token.clone(), amount: "0".to_string() },
+            to: token,
+            slippage: 75.0,
+            protocol: crate::defi::Protocol::Uniswap,
+            deadline: None,
+            dry_run: false,
+        };
+
+        let error = swap.validate().unwrap_err();
+        let fields: Vec<&str> = error.violations().iter().map(|violation| violation.field.as_str()).collect();
+        assert_eq!(fields, vec!["from.amount", "slippage", "to"]);
+    }
+}
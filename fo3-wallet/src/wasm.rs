@@ -0,0 +1,108 @@
+//! WebAssembly bindings
+//!
+//! This module is only available with the `wasm` feature. It exposes the
+//! mnemonic, key derivation and signing APIs to JavaScript through
+//! wasm-bindgen so browser extensions and web wallets reuse the crypto layer.
+//! Build for the browser without network clients:
+//!
+//! ```text
+//! cargo build -p fo3-wallet --target wasm32-unknown-unknown --no-default-features --features ethereum,bitcoin,wasm
+//! ```
+
+use ed25519_dalek::{Signer as _, SigningKey};
+use secp256k1::{Message, Secp256k1, SecretKey};
+use wasm_bindgen::prelude::*;
+
+use crate::crypto::keys::{self, KeyPair, KeyType};
+use crate::crypto::mnemonic::{self, MnemonicStrength};
+
+fn parse_key_type(key_type: &str) -> Result<KeyType, JsError> {
+    match key_type.to_ascii_lowercase().as_str() {
+        "ethereum" => Ok(KeyType::Ethereum),
+        "solana" => Ok(KeyType::Solana),
+        "bitcoin" => Ok(KeyType::Bitcoin),
+        "ton" => Ok(KeyType::Ton),
+        "cosmos" => Ok(KeyType::Cosmos),
+        "xrp" => Ok(KeyType::Xrp),
+        other => Err(JsError::new(&format!("Unsupported key type: {}", other))),
+    }
+}
+
+fn derive(phrase: &str, passphrase: Option<String>, key_type: &str, path: &str) -> Result<KeyPair, JsError> {
+    let seed = mnemonic::mnemonic_to_seed(phrase, passphrase.as_deref())?;
+    Ok(keys::derive_key_pair(&seed, parse_key_type(key_type)?, path)?)
+}
+
+/// Generate a new mnemonic phrase with 12 or 24 words
+#[wasm_bindgen(js_name = generateMnemonic)]
+pub fn generate_mnemonic(word_count: u8) -> Result<String, JsError> {
+    let strength = match word_count {
+        12 => MnemonicStrength::Words12,
+        24 => MnemonicStrength::Words24,
+        _ => return Err(JsError::new("Word count must be 12 or 24")),
+    };
+
+    Ok(mnemonic::generate_mnemonic(strength)?)
+}
+
+/// Check whether a mnemonic phrase is valid
+#[wasm_bindgen(js_name = validateMnemonic)]
+pub fn validate_mnemonic(phrase: &str) -> bool {
+    mnemonic::validate_mnemonic(phrase).unwrap_or(false)
+}
+
+/// Derive the hex-encoded public key at a path
+#[wasm_bindgen(js_name = derivePublicKey)]
+pub fn derive_public_key(phrase: &str, passphrase: Option<String>, key_type: &str, path: &str) -> Result<String, JsError> {
+    let key_pair = derive(phrase, passphrase, key_type, path)?;
+    Ok(hex::encode(key_pair.public_key().as_bytes()))
+}
+
+/// Derive the address at a path (Bitcoin and TON addresses are for mainnet, Cosmos for the Cosmos Hub)
+#[wasm_bindgen(js_name = deriveAddress)]
+pub fn derive_address(phrase: &str, passphrase: Option<String>, key_type: &str, path: &str) -> Result<String, JsError> {
+    let key_pair = derive(phrase, passphrase, key_type, path)?;
+
+    let address = match key_pair.key_type() {
+        KeyType::Ethereum => keys::ethereum::public_key_to_address(key_pair.public_key())?,
+        KeyType::Solana => keys::solana::public_key_to_address(key_pair.public_key())?,
+        KeyType::Bitcoin => keys::bitcoin::public_key_to_address(key_pair.public_key(), keys::bitcoin::Network::Bitcoin)?,
+        KeyType::Ton => keys::ton::public_key_to_address(key_pair.public_key(), false)?,
+        KeyType::Cosmos => keys::cosmos::public_key_to_address(key_pair.public_key(), "cosmos")?,
+        KeyType::Xrp => keys::xrp::public_key_to_address(key_pair.public_key())?,
+    };
+
+    Ok(address)
+}
+
+/// Sign with the key at a path
+///
+/// For secp256k1 chains `payload` must be a 32-byte hash and the result is
+/// 65 bytes `r || s || recovery_id`; for Solana and TON `payload` is the message and
+/// the result is a 64-byte ed25519 signature.
+#[wasm_bindgen]
+pub fn sign(phrase: &str, passphrase: Option<String>, key_type: &str, path: &str, payload: &[u8]) -> Result<Vec<u8>, JsError> {
+    let key_pair = derive(phrase, passphrase, key_type, path)?;
+
+    match key_pair.key_type() {
+        KeyType::Ethereum | KeyType::Bitcoin | KeyType::Cosmos | KeyType::Xrp => {
+            let secret_key = SecretKey::from_slice(key_pair.private_key().as_bytes())
+                .map_err(|e| JsError::new(&format!("Invalid private key: {}", e)))?;
+            let message = Message::from_digest_slice(payload)
+                .map_err(|_| JsError::new("Payload must be a 32-byte hash"))?;
+
+            let (recovery_id, compact) = Secp256k1::new()
+                .sign_ecdsa_recoverable(&message, &secret_key)
+                .serialize_compact();
+
+            let mut signature = compact.to_vec();
+            signature.push(recovery_id.to_i32() as u8);
+            Ok(signature)
+        }
+        KeyType::Solana | KeyType::Ton => {
+            let secret: [u8; 32] = key_pair.private_key().as_bytes().try_into()
+                .map_err(|_| JsError::new("Invalid ed25519 private key length"))?;
+            Ok(SigningKey::from_bytes(&secret).sign(payload).to_bytes().to_vec())
+        }
+    }
+}
@@ -3,12 +3,15 @@
 use fo3_wallet::crypto::keys::KeyType;
 use fo3_wallet::transaction::provider::{ProviderConfig, ProviderType};
 use fo3_wallet::defi::{
-    Protocol, TokenAmount,
+    Protocol, TokenAmount, SANDWICH_PRICE_IMPACT,
     SwapRequest, LendingRequest, StakingRequest,
     LendingAction, StakingAction,
-    swap_tokens, get_swap_quote, get_supported_tokens,
+    PlatformFeeConfig, InMemoryFeeLedger,
+    swap_tokens, get_swap_quote, quote_swap, execute_quoted_swap, SwapQuoteBook,
+    quote_swap_with_platform_fee, swap_tokens_with_platform_fee, get_supported_tokens, get_token_balances,
     execute_lending, get_supported_lending_protocols,
     execute_staking, get_supported_staking_protocols,
+    dry_run_swap, dry_run_lending, dry_run_staking,
 };
 
 #[test]
@@ -38,6 +41,7 @@ fn test_ethereum_swap() {
         slippage: 0.5,
         protocol: Protocol::Uniswap,
         deadline: Some(1800), // 30 minutes
+        dry_run: false,
     };
     
     // Get a swap quote
@@ -80,6 +84,7 @@ fn test_ethereum_lending() {
             amount: "1000000000000000000".to_string(), // 1 ETH
         }),
         protocol: Protocol::Aave,
+        dry_run: false,
     };
     
     // Execute the lending action
@@ -119,6 +124,7 @@ fn test_ethereum_staking() {
             amount: "1000000000000000000".to_string(), // 1 ETH
         }),
         protocol: Protocol::Lido,
+        dry_run: false,
     };
     
     // Execute the staking action
@@ -161,6 +167,7 @@ fn test_solana_swap() {
         slippage: 0.5,
         protocol: Protocol::Raydium,
         deadline: Some(1800), // 30 minutes
+        dry_run: false,
     };
     
     // Get a swap quote
@@ -203,6 +210,7 @@ fn test_solana_staking() {
             amount: "1000000000".to_string(), // 1 SOL
         }),
         protocol: Protocol::Marinade,
+        dry_run: false,
     };
     
     // Execute the staking action
@@ -217,3 +225,301 @@ fn test_solana_staking() {
     // Check that Marinade is supported
     assert!(protocols.contains(&Protocol::Marinade));
 }
+
+#[test]
+fn test_ethereum_token_balances() {
+    let config = ProviderConfig {
+        provider_type: ProviderType::Http,
+        url: "https://mainnet.infura.io/v3/your-api-key".to_string(),
+        api_key: None,
+        timeout: Some(30),
+    };
+
+    // One balance per supported token
+    let tokens = get_supported_tokens(KeyType::Ethereum, &config).unwrap();
+    let balances = get_token_balances(KeyType::Ethereum, "0x742d35Cc6634C0532925a3b844Bc454e4438f44e", &config).unwrap();
+    assert_eq!(balances.len(), tokens.len());
+
+    // Bitcoin has no token balances through the DeFi providers
+    assert!(get_token_balances(KeyType::Bitcoin, "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", &config).is_err());
+}
+
+#[test]
+fn test_swap_quote_breakdown() {
+    let config = ProviderConfig {
+        provider_type: ProviderType::Http,
+        url: "https://api.mainnet-beta.solana.com".to_string(),
+        api_key: None,
+        timeout: Some(30),
+    };
+
+    let tokens = get_supported_tokens(KeyType::Solana, &config).unwrap();
+    let sol_token = tokens.iter().find(|t| t.symbol == "SOL").unwrap().clone();
+    let usdc_token = tokens.iter().find(|t| t.symbol == "USDC").unwrap().clone();
+
+    let mut request = SwapRequest {
+        from: TokenAmount {
+            token: sol_token,
+            amount: "10000000000".to_string(), // 10 SOL
+        },
+        to: usdc_token,
+        slippage: 1.0,
+        protocol: Protocol::Raydium,
+        deadline: None,
+        dry_run: false,
+    };
+
+    let quote = quote_swap(&request, &config).unwrap();
+
+    // Raydium charges 0.22% to liquidity providers and 0.03% to the protocol
+    assert_eq!(quote.fees.lp_fee.amount, "22000000");
+    assert_eq!(quote.fees.protocol_fee.amount, "3000000");
+    assert_eq!(quote.fees.platform_fee.amount, "0");
+    assert_eq!(quote.route.len(), 1);
+    assert_eq!(quote.expires_at - quote.quoted_at, 30);
+
+    let amount_out: u128 = quote.to.amount.parse().unwrap();
+    assert!(amount_out < 1_000_000_000);
+    assert!(quote.min_amount_out().unwrap() <= amount_out * 99 / 100);
+
+    // Larger trades move the price further
+    request.from.amount = "10000000000000".to_string(); // 10,000 SOL
+    let large = quote_swap(&request, &config).unwrap();
+    assert!(large.price_impact > quote.price_impact);
+    // Solana has no public mempool to sandwich from
+    assert!(!large.suggest_private_submission);
+
+    // Slippage outside 0-50% is rejected
+    request.slippage = 75.0;
+    assert!(quote_swap(&request, &config).is_err());
+}
+
+#[test]
+fn test_execute_quoted_swap() {
+    let config = ProviderConfig {
+        provider_type: ProviderType::Http,
+        url: "https://mainnet.infura.io/v3/your-api-key".to_string(),
+        api_key: None,
+        timeout: Some(30),
+    };
+
+    let tokens = get_supported_tokens(KeyType::Ethereum, &config).unwrap();
+    let eth_token = tokens.iter().find(|t| t.symbol == "ETH").unwrap().clone();
+    let usdc_token = tokens.iter().find(|t| t.symbol == "USDC").unwrap().clone();
+
+    let request = SwapRequest {
+        from: TokenAmount {
+            token: eth_token,
+            amount: "1000000000000000000".to_string(), // 1 ETH
+        },
+        to: usdc_token,
+        slippage: 0.5,
+        protocol: Protocol::Uniswap,
+        deadline: Some(1800),
+        dry_run: false,
+    };
+
+    let ledger = InMemoryFeeLedger::new();
+    let quote = quote_swap(&request, &config).unwrap();
+    assert!(execute_quoted_swap(&request, &quote, &config, None, &ledger).is_ok());
+
+    // An expired quote is refreshed; unchanged prices still satisfy it
+    let mut stale = quote.clone();
+    stale.quoted_at = 0;
+    stale.expires_at = 1;
+    assert!(execute_quoted_swap(&request, &stale, &config, None, &ledger).is_ok());
+
+    // An expired quote promising more than the market now gives is refused
+    stale.min_amount_out = (quote.min_amount_out().unwrap() * 2).to_string();
+    assert!(execute_quoted_swap(&request, &stale, &config, None, &ledger).is_err());
+
+    // A quote for a different amount is refused
+    let mut other = request.clone();
+    other.from.amount = "2000000000000000000".to_string();
+    assert!(execute_quoted_swap(&other, &quote, &config, None, &ledger).is_err());
+
+    // So is one for a different protocol
+    let mut other = request.clone();
+    other.protocol = Protocol::SushiSwap;
+    assert!(execute_quoted_swap(&other, &quote, &config, None, &ledger).is_err());
+
+    // Amounts whose fees overflow are rejected rather than panicking
+    let mut huge = request.clone();
+    huge.from.amount = u128::MAX.to_string();
+    assert!(quote_swap(&huge, &config).is_err());
+}
+
+#[test]
+fn test_swap_quote_book() {
+    let config = ProviderConfig {
+        provider_type: ProviderType::Http,
+        url: "https://mainnet.infura.io/v3/your-api-key".to_string(),
+        api_key: None,
+        timeout: Some(30),
+    };
+
+    let tokens = get_supported_tokens(KeyType::Ethereum, &config).unwrap();
+    let request = SwapRequest {
+        from: TokenAmount {
+            token: tokens.iter().find(|t| t.symbol == "ETH").unwrap().clone(),
+            amount: "1000000000000000000".to_string(),
+        },
+        to: tokens.iter().find(|t| t.symbol == "USDC").unwrap().clone(),
+        slippage: 0.5,
+        protocol: Protocol::Uniswap,
+        deadline: None,
+        dry_run: false,
+    };
+
+    let book = SwapQuoteBook::new();
+    let quote = quote_swap(&request, &config).unwrap();
+    let issued = book.issue(quote.clone(), quote.quoted_at).unwrap();
+    let id = issued.id.clone().unwrap();
+
+    // Quotes execute once
+    assert!(book.take(&id).is_some());
+    assert!(book.take(&id).is_none());
+
+    // Stale quotes are dropped when new ones are issued
+    let stale = book.issue(quote.clone(), quote.quoted_at).unwrap();
+    book.issue(quote.clone(), quote.expires_at + 60).unwrap();
+    assert!(book.take(stale.id.as_deref().unwrap()).is_none());
+}
+
+#[test]
+fn test_sandwich_prone_swaps() {
+    let config = ProviderConfig {
+        provider_type: ProviderType::Http,
+        url: "https://mainnet.infura.io/v3/your-api-key".to_string(),
+        api_key: None,
+        timeout: Some(30),
+    };
+
+    let tokens = get_supported_tokens(KeyType::Ethereum, &config).unwrap();
+    let eth_token = tokens.iter().find(|t| t.symbol == "ETH").unwrap().clone();
+    let usdc_token = tokens.iter().find(|t| t.symbol == "USDC").unwrap().clone();
+
+    let mut request = SwapRequest {
+        from: TokenAmount {
+            token: eth_token,
+            amount: "1000000000000000000".to_string(), // 1 ETH
+        },
+        to: usdc_token,
+        slippage: 0.5,
+        protocol: Protocol::Uniswap,
+        deadline: None,
+        dry_run: true,
+    };
+    assert!(!quote_swap(&request, &config).unwrap().suggest_private_submission);
+
+    // 1,000 ETH moves the pool by several percent
+    request.from.amount = "1000000000000000000000".to_string();
+    let quote = quote_swap(&request, &config).unwrap();
+    assert!(quote.price_impact >= SANDWICH_PRICE_IMPACT);
+    assert!(quote.suggest_private_submission);
+
+    let preview = dry_run_swap(&request, &config, None).unwrap();
+    assert!(preview.preview.iter().any(|line| line.starts_with("Submit through a private relay")));
+}
+
+#[test]
+fn test_swap_platform_fee() {
+    let config = ProviderConfig {
+        provider_type: ProviderType::Http,
+        url: "https://mainnet.infura.io/v3/your-api-key".to_string(),
+        api_key: None,
+        timeout: Some(30),
+    };
+    let platform_fee = PlatformFeeConfig {
+        fee_bps: 25,
+        evm_recipient: Some("0x742d35Cc6634C0532925a3b844Bc454e4438f44e".to_string()),
+        solana_recipient: None,
+    };
+
+    let tokens = get_supported_tokens(KeyType::Ethereum, &config).unwrap();
+    let eth_token = tokens.iter().find(|t| t.symbol == "ETH").unwrap().clone();
+    let usdc_token = tokens.iter().find(|t| t.symbol == "USDC").unwrap().clone();
+
+    let request = SwapRequest {
+        from: TokenAmount {
+            token: eth_token.clone(),
+            amount: "1000000000000000000".to_string(), // 1 ETH
+        },
+        to: usdc_token,
+        slippage: 0.5,
+        protocol: Protocol::Uniswap,
+        deadline: None,
+        dry_run: false,
+    };
+
+    // The fee is taken from the input, so less reaches the pool
+    let quote = quote_swap_with_platform_fee(&request, &config, &platform_fee).unwrap();
+    let plain = quote_swap(&request, &config).unwrap();
+    assert_eq!(quote.fees.platform_fee.amount, "2500000000000000");
+    assert!(quote.to.amount.parse::<u128>().unwrap() < plain.to.amount.parse::<u128>().unwrap());
+
+    let ledger = InMemoryFeeLedger::new();
+    let result = swap_tokens_with_platform_fee(&request, &config, &platform_fee, &ledger).unwrap();
+    let transfer = result.platform_fee.unwrap();
+    assert_eq!(transfer.recipient, "0x742d35Cc6634C0532925a3b844Bc454e4438f44e");
+
+    assert_eq!(ledger.entries().len(), 1);
+    assert_eq!(ledger.revenue(KeyType::Ethereum, &eth_token.address), 2_500_000_000_000_000);
+
+    // Deployments cannot charge more than 1%
+    let excessive = PlatformFeeConfig { fee_bps: 1_000, ..platform_fee };
+    assert!(swap_tokens_with_platform_fee(&request, &config, &excessive, &ledger).is_err());
+}
+
+#[test]
+fn test_dry_run() {
+    let config = ProviderConfig {
+        provider_type: ProviderType::Http,
+        url: "https://mainnet.infura.io/v3/your-api-key".to_string(),
+        api_key: None,
+        timeout: Some(30),
+    };
+    let platform_fee = PlatformFeeConfig {
+        fee_bps: 25,
+        evm_recipient: Some("0x742d35Cc6634C0532925a3b844Bc454e4438f44e".to_string()),
+        solana_recipient: None,
+    };
+
+    let tokens = get_supported_tokens(KeyType::Ethereum, &config).unwrap();
+    let eth_token = tokens.iter().find(|t| t.symbol == "ETH").unwrap().clone();
+    let usdc_token = tokens.iter().find(|t| t.symbol == "USDC").unwrap().clone();
+    let one_eth = TokenAmount { token: eth_token, amount: "1000000000000000000".to_string() };
+
+    let request = SwapRequest {
+        from: one_eth.clone(),
+        to: usdc_token,
+        slippage: 0.5,
+        protocol: Protocol::Uniswap,
+        deadline: None,
+        dry_run: true,
+    };
+
+    // The preview matches the quote, including the platform fee
+    let preview = dry_run_swap(&request, &config, Some(&platform_fee)).unwrap();
+    let quote = quote_swap_with_platform_fee(&request, &config, &platform_fee).unwrap();
+    assert_eq!(preview.credits[0].amount, quote.to.amount);
+    assert_eq!(preview.network_fee, "0.001");
+    assert!(preview.preview[0].starts_with("Swap 1 ETH for "));
+    assert_eq!(preview.preview[1], "Pay a platform fee of 0.0025 ETH");
+
+    // Dry-run requests are never executed
+    assert!(swap_tokens(&request, &config).is_err());
+
+    let lending = LendingRequest { action: LendingAction::Borrow(one_eth.clone()), protocol: Protocol::Aave, dry_run: true };
+    let preview = dry_run_lending(&lending, &config).unwrap();
+    assert!(preview.debits.is_empty());
+    assert_eq!(preview.preview, vec!["Borrow 1 ETH on Aave"]);
+    assert!(execute_lending(&lending, &config).is_err());
+
+    let staking = StakingRequest { action: StakingAction::Stake(one_eth), protocol: Protocol::Lido, dry_run: true };
+    assert_eq!(dry_run_staking(&staking, &config).unwrap().debits[0].amount, "1000000000000000000");
+
+    // Protocols of another chain are refused
+    let wrong = StakingRequest { protocol: Protocol::Marinade, ..staking };
+    assert!(dry_run_staking(&wrong, &config).is_err());
+}
@@ -0,0 +1,97 @@
+//! Deterministic key derivation vectors and golden-file signing tests
+
+#![cfg(feature = "test-vectors")]
+
+use fo3_wallet::crypto::keys::{self, derive_key_pair, KeyType};
+use fo3_wallet::crypto::mnemonic::mnemonic_to_seed;
+use fo3_wallet::transaction::{
+    sign_solana_message, system_transfer_instruction, EthereumProvider, Message, NetworkBinding, ProviderConfig,
+    ProviderType, TransactionRequest, TransactionSigner,
+};
+use fo3_wallet::testing::assert_golden;
+use fo3_wallet::testing::vectors::{
+    check_vector, BIP32_VECTORS, SLIP10_ED25519_VECTORS, WALLET_VECTORS, TEST_MNEMONIC, TEST_MNEMONIC_SEED,
+};
+
+#[test]
+fn test_mnemonic_seed_vector() {
+    let seed = mnemonic_to_seed(TEST_MNEMONIC, None).unwrap();
+    assert_eq!(hex::encode(seed), TEST_MNEMONIC_SEED);
+}
+
+#[test]
+fn test_bip32_vectors() {
+    for vector in BIP32_VECTORS {
+        check_vector(vector).unwrap();
+    }
+}
+
+#[test]
+fn test_slip10_ed25519_vectors() {
+    for vector in SLIP10_ED25519_VECTORS {
+        check_vector(vector).unwrap();
+    }
+}
+
+#[test]
+fn test_wallet_vectors() {
+    for vector in WALLET_VECTORS {
+        check_vector(vector).unwrap();
+    }
+}
+
+#[test]
+fn test_golden_ethereum_signed_transfer() {
+    let seed = mnemonic_to_seed(TEST_MNEMONIC, None).unwrap();
+    let key_pair = derive_key_pair(&seed, KeyType::Ethereum, "m/44'/60'/0'/0/0").unwrap();
+
+    let config = ProviderConfig {
+        provider_type: ProviderType::Http,
+        url: "https://mainnet.infura.io/v3/your-api-key".to_string(),
+        api_key: None,
+        timeout: Some(30),
+    };
+    let provider = EthereumProvider::new(config).unwrap().with_signer(key_pair).unwrap();
+
+    // Legacy EIP-155 transfer of 1 ETH at 20 Gwei
+    let request = TransactionRequest {
+        key_type: KeyType::Ethereum,
+        network: NetworkBinding::Evm { chain_id: 1 },
+        from: "0x9858EfFD232B4033E47d90003D41EC34EcaEda94".to_string(),
+        to: "0x742d35Cc6634C0532925a3b844Bc454e4438f44e".to_string(),
+        value: "1000000000000000000".to_string(),
+        gas_price: Some("20000000000".to_string()),
+        gas_limit: Some("21000".to_string()),
+        nonce: Some(0),
+        data: None,
+        destination_tag: None,
+        memo: None,
+    };
+    let raw = provider.sign_transaction(&request).unwrap();
+
+    assert_golden("ethereum_signed_transfer.hex", &format!("0x{}", hex::encode(raw)));
+}
+
+#[test]
+fn test_golden_bitcoin_signed_message() {
+    let seed = mnemonic_to_seed(TEST_MNEMONIC, None).unwrap();
+    let key_pair = derive_key_pair(&seed, KeyType::Bitcoin, "m/44'/0'/0'/0/0").unwrap();
+
+    let signature = keys::bitcoin::sign_message(&key_pair, b"fo3-wallet golden bitcoin").unwrap();
+
+    assert_golden("bitcoin_signed_message.hex", &hex::encode(signature));
+}
+
+#[test]
+fn test_golden_solana_signed_transfer() {
+    let seed = mnemonic_to_seed(TEST_MNEMONIC, None).unwrap();
+    let key_pair = derive_key_pair(&seed, KeyType::Solana, "m/44'/501'/0'/0'").unwrap();
+    let from = keys::solana::public_key_to_address(key_pair.public_key()).unwrap();
+
+    // Transfer of 0.001 SOL paid for by the sender
+    let instructions = [system_transfer_instruction(&from, "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", 1_000_000)];
+    let message = Message::compile(&from, &instructions, "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG").unwrap();
+    let raw = sign_solana_message(&key_pair, &message).unwrap();
+
+    assert_golden("solana_signed_transfer.hex", &hex::encode(raw));
+}
@@ -1,27 +1,26 @@
 //! Tests for transaction functionality
+//!
+//! These run against the in-process mock providers so they need no RPC endpoint.
+
+#![cfg(feature = "test-vectors")]
 
 use fo3_wallet::crypto::keys::KeyType;
+use fo3_wallet::error::Error;
 use fo3_wallet::transaction::{
-    TransactionRequest, TransactionStatus,
-    provider::{ProviderConfig, ProviderType, ProviderFactory},
+    TransactionRequest, TransactionStatus, TransactionBroadcaster, TransactionManager, NetworkBinding, SOLANA_MAINNET_GENESIS_HASH,
+    mock::{MockEvmProvider, MockSolanaProvider, MockBitcoinProvider, MockFault},
 };
 
 #[test]
 fn test_ethereum_transaction() {
-    // Create a provider configuration
-    let config = ProviderConfig {
-        provider_type: ProviderType::Http,
-        url: "https://mainnet.infura.io/v3/your-api-key".to_string(),
-        api_key: None,
-        timeout: Some(30),
-    };
-    
-    // Create a provider
-    let provider = ProviderFactory::create_provider(KeyType::Ethereum, config).unwrap();
+    // Create a funded mock provider
+    let provider = MockEvmProvider::new(1);
+    provider.set_balance("0x742d35Cc6634C0532925a3b844Bc454e4438f44e", 10000000000000000000);
     
     // Create a transaction request
     let request = TransactionRequest {
         key_type: KeyType::Ethereum,
+        network: NetworkBinding::Evm { chain_id: 1 },
         from: "0x742d35Cc6634C0532925a3b844Bc454e4438f44e".to_string(),
         to: "0x742d35Cc6634C0532925a3b844Bc454e4438f44e".to_string(),
         value: "1000000000000000000".to_string(), // 1 ETH
@@ -29,6 +28,8 @@ fn test_ethereum_transaction() {
         gas_limit: Some("21000".to_string()),
         nonce: Some(0),
         data: None,
+        destination_tag: None,
+        memo: None,
     };
     
     // Send the transaction
@@ -60,20 +61,14 @@ fn test_ethereum_transaction() {
 
 #[test]
 fn test_solana_transaction() {
-    // Create a provider configuration
-    let config = ProviderConfig {
-        provider_type: ProviderType::Http,
-        url: "https://api.mainnet-beta.solana.com".to_string(),
-        api_key: None,
-        timeout: Some(30),
-    };
-    
-    // Create a provider
-    let provider = ProviderFactory::create_provider(KeyType::Solana, config).unwrap();
+    // Create a funded mock provider
+    let provider = MockSolanaProvider::new();
+    provider.set_balance("vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg", 10000000000);
     
     // Create a transaction request
     let request = TransactionRequest {
         key_type: KeyType::Solana,
+        network: NetworkBinding::Solana { genesis_hash: SOLANA_MAINNET_GENESIS_HASH.to_string() },
         from: "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg".to_string(),
         to: "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg".to_string(),
         value: "1000000000".to_string(), // 1 SOL
@@ -81,6 +76,8 @@ fn test_solana_transaction() {
         gas_limit: None,
         nonce: None,
         data: None,
+        destination_tag: None,
+        memo: None,
     };
     
     // Send the transaction
@@ -109,20 +106,14 @@ fn test_solana_transaction() {
 
 #[test]
 fn test_bitcoin_transaction() {
-    // Create a provider configuration
-    let config = ProviderConfig {
-        provider_type: ProviderType::Http,
-        url: "https://btc.getblock.io/mainnet".to_string(),
-        api_key: None,
-        timeout: Some(30),
-    };
-    
-    // Create a provider
-    let provider = ProviderFactory::create_provider(KeyType::Bitcoin, config).unwrap();
+    // Create a funded mock provider
+    let provider = MockBitcoinProvider::new(bitcoin::Network::Bitcoin);
+    provider.set_balance("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", 1000000000);
     
     // Create a transaction request
     let request = TransactionRequest {
         key_type: KeyType::Bitcoin,
+        network: NetworkBinding::Bitcoin { network: "bitcoin".to_string() },
         from: "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".to_string(),
         to: "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa".to_string(),
         value: "100000000".to_string(), // 1 BTC
@@ -130,6 +121,8 @@ fn test_bitcoin_transaction() {
         gas_limit: None,
         nonce: None,
         data: None,
+        destination_tag: None,
+        memo: None,
     };
     
     // Send the transaction
@@ -155,3 +148,60 @@ fn test_bitcoin_transaction() {
     assert_eq!(transaction.value, "100000000");
     assert_eq!(transaction.status, TransactionStatus::Confirmed);
 }
+
+#[test]
+fn test_transaction_history() {
+    let provider = MockEvmProvider::new(1);
+    provider.set_balance("0xsender", 1_000_000_000_000_000_000);
+
+    let request = TransactionRequest {
+        key_type: KeyType::Ethereum,
+        network: NetworkBinding::Evm { chain_id: 1 },
+        from: "0xsender".to_string(),
+        to: "0xrecipient".to_string(),
+        value: "1000".to_string(),
+        gas_price: None,
+        gas_limit: None,
+        nonce: None,
+        data: None,
+        destination_tag: None,
+        memo: None,
+    };
+
+    let first = provider.send_transaction(&request).unwrap();
+    let second = provider.send_transaction(&request).unwrap();
+    assert_ne!(first, second);
+
+    let history = provider.get_transactions("0xrecipient", 10, 0).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].hash, second);
+    assert_eq!(provider.balance("0xrecipient"), 2000);
+}
+
+#[test]
+fn test_injected_faults() {
+    let provider = MockSolanaProvider::new();
+    provider.set_balance("vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg", 10_000_000_000);
+
+    let request = TransactionRequest {
+        key_type: KeyType::Solana,
+        network: NetworkBinding::Solana { genesis_hash: SOLANA_MAINNET_GENESIS_HASH.to_string() },
+        from: "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg".to_string(),
+        to: "11111111111111111111111111111111".to_string(),
+        value: "1000".to_string(),
+        gas_price: None,
+        gas_limit: None,
+        nonce: None,
+        data: None,
+        destination_tag: None,
+        memo: None,
+    };
+
+    provider.inject_fault(MockFault::RpcError("node is behind".to_string()));
+    assert!(matches!(provider.send_transaction(&request), Err(Error::Provider(_))));
+
+    // Dropped transactions are accepted but never become visible
+    provider.set_drop_transactions(true);
+    let hash = provider.send_transaction(&request).unwrap();
+    assert!(provider.get_transaction(&hash).is_err());
+}